@@ -50,6 +50,9 @@ function post_to_vega_editor(window, data) {
     setTimeout(send, step);
 }
 
+// vector graphics stay sharp on paper, hence plots of the print layout are rendered as SVG
+const vegaRenderer = document.body.classList.contains('print-report') ? 'svg' : 'canvas';
+
 document.getElementById('btn-download-config').onclick = function() {
     let blob = new Blob([objects.config.first], {type: 'text/plain'});
     var a = document.createElement('a');
@@ -128,6 +131,8 @@ for (let key in objects.datasets) {
             }
             let opt = {
                 "actions": false,
+                "config": vegaTheme,
+                "renderer": vegaRenderer,
             };
             vegaEmbed(`#${CSS.escape(thisId)}`, copied_spec, opt).then(({ view, spec, vgSpec }) => {
                 if (add_listeners) {
@@ -221,7 +226,7 @@ for (let key in objects.datasets) {
                         "color": {
                             "field": "name",
                             "type": "nominal",
-                            "scale": {
+                            "scale": (vegaTheme.range && vegaTheme.range.category) ? {} : {
                                 "range": ['#f77189', '#bb9832', '#50b131', '#36ada4', '#3ba3ec', '#e866f4']
                             }
                         },
@@ -233,6 +238,11 @@ for (let key in objects.datasets) {
                 },
             ]
         };
+        if (m.hover) {
+            // keeps the additional value of each bar through the aggregation, such that the
+            // tooltip shows it
+            yourVlSpec.layer[0].encoding.detail = {field: m.hover, type: 'quantitative'};
+        }
 
         function render(scaleType, thisId, vlSpec, add_listeners) {
             const copied_spec = JSON.parse(JSON.stringify(vlSpec)); // deep copy
@@ -247,8 +257,10 @@ for (let key in objects.datasets) {
             }
             let opt = {
                 "actions": false,
+                "config": vegaTheme,
+                "renderer": vegaRenderer,
             };
-            vegaEmbed(`#${CSS.escape(thisId)}`, copied_spec, opt).then(({ view, spec, vgSpec }) => {
+            vegaEmbed(`#${CSS.escape(thisId)}`, withPhenotypeStrip(copied_spec, m.phenotypes, 'label'), opt).then(({ view, spec, vgSpec }) => {
                 if (add_listeners) {
                     // Export PNG
                     let png_button = document.getElementById('btn-download-plot-png-' + m.id);
@@ -333,6 +345,8 @@ for (let key in objects.datasets) {
         }
         let opt = {
             "actions": false,
+            "config": vegaTheme,
+            "renderer": vegaRenderer,
         };
         vegaEmbed(`#${CSS.escape(thisId)}`, mySpec, opt).then(({ view, spec, vgSpec }) => {
             // Export PNG
@@ -422,6 +436,8 @@ for (let key in objects.datasets) {
 
         let opt = {
             "actions": false,
+            "config": vegaTheme,
+            "renderer": vegaRenderer,
         };
         vegaEmbed(`#${CSS.escape(thisId)}`, mySpec, opt).then(({ view, spec, vgSpec }) => {
             let list_button = document.getElementById('btn-download-node-list-' + h.id);
@@ -520,8 +536,10 @@ for (let key in objects.datasets) {
 
         let opt = {
             "actions": false,
+            "config": vegaTheme,
+            "renderer": vegaRenderer,
         };
-        vegaEmbed(`#${CSS.escape(thisId)}`, mySpec, opt).then(({ view, spec, vgSpec }) => {
+        vegaEmbed(`#${CSS.escape(thisId)}`, withPhenotypeStrip(mySpec, h.phenotypes, 'x'), opt).then(({ view, spec, vgSpec }) => {
             // Export PNG
             let png_button = document.getElementById('btn-download-plot-png-' + h.id);
             png_button.addEventListener('click', () => {
@@ -561,6 +579,8 @@ for (let key in objects.datasets) {
         let thisId = 'chart-line-' + v.id;
         let opt = {
             "actions": false,
+            "config": vegaTheme,
+            "renderer": vegaRenderer,
         };
         vegaEmbed(`#${CSS.escape(thisId)}`, v.jsonContent, opt).then(({ view, spec, vgSpec }) => {
             // Export PNG
@@ -597,6 +617,315 @@ for (let key in objects.datasets) {
                 });
             });
         });
+    } else if (element instanceof BoxPlot) {
+        let b = element;
+        let thisId = 'chart-box-' + b.id;
+        let mySpec;
+        if (b.violin) {
+            mySpec = {
+                "$schema": "https://vega.github.io/schema/vega-lite/v6.json",
+                "description": "Violin",
+                "data": b.data,
+                "width": 120,
+                "height": 400,
+                "transform": [
+                    {"density": "value", "groupby": ["label"], "as": ["value", "density"]}
+                ],
+                "mark": {"type": "area", "orient": "horizontal", "tooltip": true},
+                "encoding": {
+                    "y": {"field": "value", "type": "quantitative", "title": b.y_label},
+                    "x": {
+                        "field": "density",
+                        "type": "quantitative",
+                        "stack": "center",
+                        "impute": null,
+                        "title": null,
+                        "axis": {"labels": false, "values": [0], "grid": false, "ticks": true}
+                    },
+                    "color": {"field": "label", "type": "nominal", "legend": null},
+                    "column": {
+                        "field": "label",
+                        "type": "nominal",
+                        "title": b.x_label,
+                        "sort": null,
+                        "spacing": 0,
+                        "header": {"titleOrient": "bottom", "labelOrient": "bottom"}
+                    }
+                }
+            };
+        } else {
+            mySpec = {
+                "$schema": "https://vega.github.io/schema/vega-lite/v6.json",
+                "description": "BoxPlot",
+                "data": b.data,
+                "width": 1000,
+                "height": 400,
+                "mark": {"type": "boxplot", "extent": 1.5, "tooltip": true},
+                "encoding": {
+                    "x": {"field": "label", "type": "nominal", "title": b.x_label, "sort": null},
+                    "y": {"field": "value", "type": "quantitative", "title": b.y_label},
+                    "color": {"field": "label", "type": "nominal", "legend": null}
+                }
+            };
+        }
+        let opt = {
+            "actions": false,
+            "config": vegaTheme,
+            "renderer": vegaRenderer,
+        };
+        vegaEmbed(`#${CSS.escape(thisId)}`, mySpec, opt).then(({ view, spec, vgSpec }) => {
+            // Export PNG
+            let png_button = document.getElementById('btn-download-plot-png-' + b.id);
+            png_button.addEventListener('click', () => {
+                view.toImageURL('png').then(url => {
+                    const a = document.createElement('a');
+                    a.href = url;
+                    a.download = 'visualization.png';
+                    a.click();
+                });
+            });
+
+            // Export SVG
+            let svg_button = document.getElementById('btn-download-plot-svg-' + b.id);
+            svg_button.removeEventListener('click', svg_button);
+            svg_button.addEventListener('click', function svg_button() {
+                view.toImageURL('svg').then(url => {
+                    const a = document.createElement('a');
+                    a.href = url;
+                    a.download = 'visualization.svg';
+                    a.click();
+                });
+            });
+
+            // Open in Vega Editor
+            let vega_editor_button = document.getElementById('btn-download-plot-vega-editor-' + b.id);
+            vega_editor_button.addEventListener('click', () => {
+                post_to_vega_editor(window, {
+                    mode: 'vega-lite',
+                    spec: JSON.stringify(spec, null, 2),
+                    renderer: undefined,
+                    config: undefined,
+                });
+            });
+        });
+    } else if (element instanceof Scatter) {
+        let sc = element;
+        let thisId = 'chart-scatter-' + sc.id;
+        let x_scale = sc.log_x ? {"type": "log", "nice": false} : {"zero": false};
+        let y_scale = sc.log_y ? {"type": "log", "nice": false} : {"zero": false};
+        let filter = (sc.log_x ? "datum.x > 0" : "true") + " && " + (sc.log_y ? "datum.y > 0" : "true");
+        let mySpec = {
+            "$schema": "https://vega.github.io/schema/vega-lite/v6.json",
+            "description": "Scatter",
+            "width": 1000,
+            "height": 400,
+            "layer": [
+                {
+                    "data": sc.data,
+                    "transform": [{"filter": filter}],
+                    "mark": {"type": "point", "tooltip": true},
+                    "encoding": {
+                        "x": {"field": "x", "type": "quantitative", "title": sc.x_label, "scale": x_scale},
+                        "y": {"field": "y", "type": "quantitative", "title": sc.y_label, "scale": y_scale},
+                    }
+                }
+            ]
+        };
+        if (sc.fit != null) {
+            mySpec.layer.push({
+                "data": sc.fit,
+                "transform": [{"filter": filter}],
+                "mark": {"type": "line", "color": PCOLORS[0]},
+                "encoding": {
+                    "x": {"field": "x", "type": "quantitative"},
+                    "y": {"field": "y", "type": "quantitative"},
+                }
+            });
+        }
+        if (sc.prediction != null) {
+            mySpec.layer.push({
+                "data": sc.prediction,
+                "transform": [{"filter": filter}],
+                "mark": {"type": "area", "color": PCOLORS[0], "opacity": 0.2},
+                "encoding": {
+                    "x": {"field": "x", "type": "quantitative"},
+                    "y": {"field": "lower", "type": "quantitative"},
+                    "y2": {"field": "upper"},
+                }
+            });
+            mySpec.layer.push({
+                "data": sc.prediction,
+                "transform": [{"filter": filter}],
+                "mark": {"type": "line", "color": PCOLORS[0], "strokeDash": [6, 4], "tooltip": true},
+                "encoding": {
+                    "x": {"field": "x", "type": "quantitative"},
+                    "y": {"field": "y", "type": "quantitative"},
+                }
+            });
+        }
+        if (sc.annotation != null) {
+            mySpec.title = {"text": sc.annotation, "orient": "bottom", "anchor": "end", "fontSize": 12, "fontWeight": "normal"};
+        }
+        let opt = {
+            "actions": false,
+            "config": vegaTheme,
+            "renderer": vegaRenderer,
+        };
+        vegaEmbed(`#${CSS.escape(thisId)}`, mySpec, opt).then(({ view, spec, vgSpec }) => {
+            // Export PNG
+            let png_button = document.getElementById('btn-download-plot-png-' + sc.id);
+            png_button.addEventListener('click', () => {
+                view.toImageURL('png').then(url => {
+                    const a = document.createElement('a');
+                    a.href = url;
+                    a.download = 'visualization.png';
+                    a.click();
+                });
+            });
+
+            // Export SVG
+            let svg_button = document.getElementById('btn-download-plot-svg-' + sc.id);
+            svg_button.removeEventListener('click', svg_button);
+            svg_button.addEventListener('click', function svg_button() {
+                view.toImageURL('svg').then(url => {
+                    const a = document.createElement('a');
+                    a.href = url;
+                    a.download = 'visualization.svg';
+                    a.click();
+                });
+            });
+
+            // Open in Vega Editor
+            let vega_editor_button = document.getElementById('btn-download-plot-vega-editor-' + sc.id);
+            vega_editor_button.addEventListener('click', () => {
+                post_to_vega_editor(window, {
+                    mode: 'vega-lite',
+                    spec: JSON.stringify(spec, null, 2),
+                    renderer: undefined,
+                    config: undefined,
+                });
+            });
+        });
+    } else if (element instanceof Chromosomal) {
+        let ch = element;
+        let thisId = 'chart-chromosomal-' + ch.id;
+        let panels = [{
+            "width": 1000,
+            "height": 300,
+            "data": ch.data,
+            "mark": {"type": "rect", "tooltip": true},
+            "params": [{
+                "name": "zoom",
+                "select": {"type": "interval", "encodings": ["x"]},
+                "bind": "scales"
+            }],
+            "encoding": {
+                "x": {"field": "start", "type": "quantitative", "title": ch.sequence, "scale": {"zero": false}},
+                "x2": {"field": "end"},
+                "y": {"field": "value", "type": "quantitative", "title": ch.y_label},
+                "y2": {"datum": 0},
+                "color": {"value": PCOLORS[0]},
+            }
+        }];
+        // further tracks (e.g., one per group) stacked under the values
+        (ch.tracks || []).forEach((track, i) => {
+            panels.push({
+                "width": 1000,
+                "height": 40,
+                "data": track.data,
+                "mark": {"type": "rect", "tooltip": true},
+                "encoding": {
+                    "x": {"field": "start", "type": "quantitative", "title": ch.sequence, "scale": {"zero": false}},
+                    "x2": {"field": "end"},
+                    "y": {"field": "value", "type": "quantitative", "title": track.name, "axis": {"titleAngle": 0, "titleAlign": "right", "tickCount": 1}},
+                    "y2": {"datum": 0},
+                    "color": {"value": PCOLORS[(i + 2) % PCOLORS.length]},
+                }
+            });
+        });
+        if (ch.annotations && ch.annotations.values.length > 0) {
+            // annotation track under the values, e.g., genes or centromeres
+            panels.push({
+                "width": 1000,
+                "height": 40,
+                "data": ch.annotations,
+                "layer": [{
+                    "mark": {"type": "rect", "tooltip": true},
+                    "encoding": {
+                        "x": {"field": "start", "type": "quantitative", "title": ch.sequence, "scale": {"zero": false}},
+                        "x2": {"field": "end"},
+                        "y": {"value": 4},
+                        "y2": {"value": 20},
+                        "color": {"value": PCOLORS[1]},
+                    }
+                }, {
+                    "mark": {"type": "text", "baseline": "top", "dy": 4},
+                    "encoding": {
+                        "x": {"field": "start", "type": "quantitative"},
+                        "y": {"value": 20},
+                        "text": {"field": "name"},
+                    }
+                }]
+            });
+        }
+        let mySpec;
+        if (panels.length == 1) {
+            mySpec = panels[0];
+        } else {
+            // all panels share the x scale of the values, such that they zoom together; only the
+            // bottom one shows the x axis
+            panels.slice(0, -1).forEach((panel) => {
+                let encoding = panel.encoding || panel.layer[0].encoding;
+                encoding.x.axis = null;
+            });
+            mySpec = {
+                "spacing": 4,
+                "vconcat": panels,
+                "resolve": {"scale": {"x": "shared", "y": "independent"}},
+            };
+        }
+        mySpec["$schema"] = "https://vega.github.io/schema/vega-lite/v6.json";
+        mySpec["description"] = "Chromosomal";
+        let opt = {
+            "actions": false,
+            "config": vegaTheme,
+            "renderer": vegaRenderer,
+        };
+        vegaEmbed(`#${CSS.escape(thisId)}`, mySpec, opt).then(({ view, spec, vgSpec }) => {
+            // Export PNG
+            let png_button = document.getElementById('btn-download-plot-png-' + ch.id);
+            png_button.addEventListener('click', () => {
+                view.toImageURL('png').then(url => {
+                    const a = document.createElement('a');
+                    a.href = url;
+                    a.download = 'visualization.png';
+                    a.click();
+                });
+            });
+
+            // Export SVG
+            let svg_button = document.getElementById('btn-download-plot-svg-' + ch.id);
+            svg_button.removeEventListener('click', svg_button);
+            svg_button.addEventListener('click', function svg_button() {
+                view.toImageURL('svg').then(url => {
+                    const a = document.createElement('a');
+                    a.href = url;
+                    a.download = 'visualization.svg';
+                    a.click();
+                });
+            });
+
+            // Open in Vega Editor
+            let vega_editor_button = document.getElementById('btn-download-plot-vega-editor-' + ch.id);
+            vega_editor_button.addEventListener('click', () => {
+                post_to_vega_editor(window, {
+                    mode: 'vega-lite',
+                    spec: JSON.stringify(spec, null, 2),
+                    renderer: undefined,
+                    config: undefined,
+                });
+            });
+        });
     } else if (element instanceof DownloadHelper) {
         let d = element;
         document.getElementById('btn-download-plot-' + d.id).addEventListener('click', () => {
@@ -623,3 +952,25 @@ for (let key in objects.tables) {
     let table = objects.tables[key];
     buildTableDownload(table, key, key + '_' + fname);
 }
+
+function setTreeCollapse(show) {
+    document.querySelectorAll('#nav-tree .collapse').forEach((el) => {
+        bootstrap.Collapse.getOrCreateInstance(el, {toggle: false})[show ? 'show' : 'hide']();
+    });
+}
+
+function filterTree(query) {
+    let terms = query.toLowerCase().split(/\s+/).filter((t) => t.length > 0);
+    let matches = (el) => terms.every((t) => el.dataset.search.includes(t));
+    document.querySelectorAll('#nav-tree .tree-analysis, #nav-tree .tree-run, #nav-tree .tree-countable').forEach((el) => {
+        el.classList.toggle('d-none', !matches(el));
+    });
+    // reveal matching entries, otherwise restore the collapsed default
+    setTreeCollapse(terms.length > 0);
+}
+
+document.getElementById('tree-search').addEventListener('input', (event) => {
+    filterTree(event.currentTarget.value);
+});
+document.getElementById('btn-tree-expand-all').addEventListener('click', () => setTreeCollapse(true));
+document.getElementById('btn-tree-collapse-all').addEventListener('click', () => setTreeCollapse(false));
@@ -17,12 +17,14 @@ class Bar {
 }
 
 class MultiBar {
-    constructor(id, x_label, y_label, log_toggle, data) {
+    constructor(id, x_label, y_label, log_toggle, data, phenotypes, hover) {
         this.id = id;
         this.x_label = x_label;
         this.y_label = y_label;
         this.log_toggle = log_toggle;
         this.data = data;
+        this.phenotypes = phenotypes;
+        this.hover = hover;
     }
 }
 
@@ -35,10 +37,11 @@ class Hexbin {
 }
 
 class Heatmap {
-    constructor(id, name, data_set) {
+    constructor(id, name, data_set, phenotypes) {
         this.id = id;
         this.name = name;
         this.data_set = data_set;
+        this.phenotypes = phenotypes;
     }
 }
 
@@ -61,6 +64,44 @@ class Line {
     }
 }
 
+class BoxPlot {
+    constructor(id, name, x_label, y_label, violin, data) {
+        this.id = id;
+        this.name = name;
+        this.x_label = x_label;
+        this.y_label = y_label;
+        this.violin = violin;
+        this.data = data;
+    }
+}
+
+class Scatter {
+    constructor(id, name, x_label, y_label, log_x, log_y, data, fit, prediction, annotation) {
+        this.id = id;
+        this.name = name;
+        this.x_label = x_label;
+        this.y_label = y_label;
+        this.log_x = log_x;
+        this.log_y = log_y;
+        this.data = data;
+        this.fit = fit;
+        this.prediction = prediction;
+        this.annotation = annotation;
+    }
+}
+
+class Chromosomal {
+    constructor(id, name, sequence, y_label, data, annotations, tracks) {
+        this.id = id;
+        this.name = name;
+        this.sequence = sequence;
+        this.y_label = y_label;
+        this.data = data;
+        this.annotations = annotations;
+        this.tracks = tracks;
+    }
+}
+
 class DownloadHelper {
     constructor(id, type) {
         this.id = id;
@@ -68,6 +109,34 @@ class DownloadHelper {
     }
 }
 
+// Stacks a strip of cells above a plot whose ordinal x axis shows the labels in the given field,
+// coloring each label by its phenotype; plots without phenotypes are returned unchanged
+function withPhenotypeStrip(spec, phenotypes, field) {
+    if (!phenotypes) {
+        return spec;
+    }
+    let plot = Object.assign({}, spec);
+    let schema = plot['$schema'];
+    delete plot['$schema'];
+    delete plot['autosize'];
+    let strip = {
+        "data": {"values": phenotypes.values.map(p => ({[field]: p.label, "phenotype": p.value}))},
+        "width": plot.width,
+        "height": 12,
+        "mark": {"type": "rect", "tooltip": true},
+        "encoding": {
+            "x": {"field": field, "type": "ordinal", "sort": null, "axis": null},
+            "color": {"field": "phenotype", "type": "nominal", "title": phenotypes.column},
+        },
+    };
+    return {
+        "$schema": schema,
+        "spacing": 4,
+        "vconcat": [strip, plot],
+        "resolve": {"scale": {"color": "independent"}},
+    };
+}
+
 function buildPlotDownload(chart, obj, prefix) {
     document.getElementById('btn-download-plot-' + obj).onclick = function() {
         var a = document.createElement('a');
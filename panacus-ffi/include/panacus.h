@@ -0,0 +1,56 @@
+/*
+ * C ABI of the core analyses of panacus.
+ *
+ * A graph is loaded once with panacus_graph_load, after which analyses are run on it without
+ * parsing it again. Tables are returned as NUL-terminated strings that the caller frees with
+ * panacus_string_free. On failure, NULL is returned and panacus_last_error describes the cause.
+ * A graph must not be used by several threads at the same time.
+ */
+#ifndef PANACUS_H
+#define PANACUS_H
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+typedef struct PanacusGraph PanacusGraph;
+
+/* Loads the graph (GFA, possibly compressed, or a panacus index) */
+PanacusGraph *panacus_graph_load(const char *gfa_file);
+
+/* Frees a graph returned by panacus_graph_load; NULL is ignored */
+void panacus_graph_free(PanacusGraph *graph);
+
+/*
+ * Runs the analyses of a query, i.e., a run of the YAML config without graph in JSON format as
+ * answered by `panacus daemon`, and returns a JSON array of their tables
+ */
+char *panacus_query(PanacusGraph *graph, const char *query);
+
+/* Coverage histogram of count type "node", "bp", "edge", "bubble", or "all" (NULL: "node") */
+char *panacus_hist(PanacusGraph *graph, const char *count_type);
+
+/*
+ * Pangenome growth for comma-separated lists of coverage and quorum thresholds, e.g., "1,1,2"
+ * and "0,0.9,0" (NULL: 1 and 0)
+ */
+char *panacus_growth(PanacusGraph *graph, const char *count_type, const char *coverage,
+                     const char *quorum);
+
+/*
+ * Pairwise similarity of paths, or of their groups given by "sample", "haplotype", or a file
+ * assigning paths to groups (NULL: paths)
+ */
+char *panacus_similarity(PanacusGraph *graph, const char *count_type, const char *grouping);
+
+/* Message of the last failure in the calling thread, or NULL; valid until the next failure */
+const char *panacus_last_error(void);
+
+/* Frees a string returned by panacus; NULL is ignored */
+void panacus_string_free(char *s);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* PANACUS_H */
@@ -0,0 +1,265 @@
+//! C ABI of the core analyses of panacus, declared in `include/panacus.h`. A graph is loaded
+//! once and analyses are run on it without parsing it again; tables are returned as strings
+//! owned by the caller, and failures as NULL together with a message from `panacus_last_error`.
+
+/* standard use */
+use std::cell::RefCell;
+use std::ffi::{CStr, CString};
+use std::os::raw::c_char;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::ptr;
+
+/* external use */
+use panacus::graph_broker::GraphBroker;
+use serde_json::json;
+
+/// Graph loaded by `panacus_graph_load`
+pub struct PanacusGraph {
+    gfa_file: String,
+    gb: GraphBroker,
+}
+
+thread_local! {
+    // message of the last failure in the calling thread
+    static LAST_ERROR: RefCell<Option<CString>> = RefCell::new(None);
+}
+
+fn set_last_error(msg: &str) {
+    let msg = CString::new(msg.replace('\0', " ")).expect("message contains no NUL byte");
+    LAST_ERROR.with(|e| *e.borrow_mut() = Some(msg));
+}
+
+// runs f, turning errors and panics into the default value and the last error, such that no
+// panic unwinds into C
+fn guard<T>(default: T, f: impl FnOnce() -> anyhow::Result<T>) -> T {
+    match catch_unwind(AssertUnwindSafe(f)) {
+        Ok(Ok(res)) => res,
+        Ok(Err(e)) => {
+            set_last_error(&format!("{:#}", e));
+            default
+        }
+        Err(panic) => {
+            let msg = panic
+                .downcast_ref::<&str>()
+                .map(|s| s.to_string())
+                .or_else(|| panic.downcast_ref::<String>().cloned())
+                .unwrap_or_else(|| "unknown panic".to_string());
+            set_last_error(&format!("panacus panicked: {}", msg));
+            default
+        }
+    }
+}
+
+// string argument, which may be NULL if it is optional
+unsafe fn get_str<'a>(s: *const c_char, name: &str) -> anyhow::Result<Option<&'a str>> {
+    if s.is_null() {
+        return Ok(None);
+    }
+    CStr::from_ptr(s)
+        .to_str()
+        .map(Some)
+        .map_err(|_| anyhow::anyhow!("{} is not valid UTF-8", name))
+}
+
+unsafe fn get_graph<'a>(graph: *mut PanacusGraph) -> anyhow::Result<&'a mut PanacusGraph> {
+    graph
+        .as_mut()
+        .ok_or_else(|| anyhow::anyhow!("graph must not be NULL"))
+}
+
+fn into_c_string(s: String) -> anyhow::Result<*mut c_char> {
+    Ok(CString::new(s)
+        .map_err(|_| anyhow::anyhow!("table contains a NUL byte"))?
+        .into_raw())
+}
+
+// count type as given on the command line (e.g., "bp"), node if NULL, in the query format
+unsafe fn get_count_type(count_type: *const c_char) -> anyhow::Result<serde_json::Value> {
+    panacus::query_count_type(get_str(count_type, "count_type")?.unwrap_or("node"))
+}
+
+fn get_grouping(grouping: Option<&str>) -> serde_json::Value {
+    match grouping {
+        None => serde_json::Value::Null,
+        Some("sample") => json!("Sample"),
+        Some("haplotype") => json!("Haplotype"),
+        Some(file) => json!({ "Custom": file }),
+    }
+}
+
+// table of the last analysis of the query, i.e., the requested one if preceded by a histogram
+fn run_last_table(graph: &mut PanacusGraph, query: serde_json::Value) -> anyhow::Result<String> {
+    panacus::run_query(&query.to_string(), &mut graph.gb, &graph.gfa_file)?
+        .pop()
+        .ok_or_else(|| anyhow::anyhow!("query produced no table"))
+}
+
+/// Loads the graph (GFA, possibly compressed, or a panacus index); returns NULL on failure
+///
+/// # Safety
+/// `gfa_file` must be a valid NUL-terminated string
+#[no_mangle]
+pub unsafe extern "C" fn panacus_graph_load(gfa_file: *const c_char) -> *mut PanacusGraph {
+    guard(ptr::null_mut(), || {
+        let gfa_file = get_str(gfa_file, "gfa_file")?
+            .ok_or_else(|| anyhow::anyhow!("gfa_file must not be NULL"))?;
+        let gb = panacus::load_graph(gfa_file)?;
+        Ok(Box::into_raw(Box::new(PanacusGraph {
+            gfa_file: gfa_file.to_string(),
+            gb,
+        })))
+    })
+}
+
+/// Frees a graph returned by `panacus_graph_load`
+///
+/// # Safety
+/// `graph` must be NULL or returned by `panacus_graph_load` and not yet freed
+#[no_mangle]
+pub unsafe extern "C" fn panacus_graph_free(graph: *mut PanacusGraph) {
+    if !graph.is_null() {
+        drop(Box::from_raw(graph));
+    }
+}
+
+/// Runs the analyses of a query (a run of the YAML config without graph in JSON format, as
+/// answered by `panacus daemon`) and returns a JSON array of their tables; returns NULL on
+/// failure
+///
+/// # Safety
+/// `graph` must be returned by `panacus_graph_load`, `query` a valid NUL-terminated string
+#[no_mangle]
+pub unsafe extern "C" fn panacus_query(
+    graph: *mut PanacusGraph,
+    query: *const c_char,
+) -> *mut c_char {
+    guard(ptr::null_mut(), || {
+        let graph = get_graph(graph)?;
+        let query =
+            get_str(query, "query")?.ok_or_else(|| anyhow::anyhow!("query must not be NULL"))?;
+        let tables = panacus::run_query(query, &mut graph.gb, &graph.gfa_file)?;
+        into_c_string(serde_json::to_string(&tables)?)
+    })
+}
+
+/// Coverage histogram of the given count type (node, bp, edge, bubble, or all; node if NULL) as
+/// tab-separated table; returns NULL on failure
+///
+/// # Safety
+/// `graph` must be returned by `panacus_graph_load`, `count_type` NULL or a valid
+/// NUL-terminated string
+#[no_mangle]
+pub unsafe extern "C" fn panacus_hist(
+    graph: *mut PanacusGraph,
+    count_type: *const c_char,
+) -> *mut c_char {
+    guard(ptr::null_mut(), || {
+        let graph = get_graph(graph)?;
+        let count_type = get_count_type(count_type)?;
+        let query = json!({ "analyses": [{ "Hist": { "count_type": count_type } }] });
+        into_c_string(run_last_table(graph, query)?)
+    })
+}
+
+/// Pangenome growth of the given count type for comma-separated lists of coverage and quorum
+/// thresholds (e.g., "1,1,2" and "0,0.9,0"; 1 and 0 if NULL) as tab-separated table; returns
+/// NULL on failure
+///
+/// # Safety
+/// `graph` must be returned by `panacus_graph_load`, the strings NULL or valid NUL-terminated
+/// strings
+#[no_mangle]
+pub unsafe extern "C" fn panacus_growth(
+    graph: *mut PanacusGraph,
+    count_type: *const c_char,
+    coverage: *const c_char,
+    quorum: *const c_char,
+) -> *mut c_char {
+    guard(ptr::null_mut(), || {
+        let graph = get_graph(graph)?;
+        let count_type = get_count_type(count_type)?;
+        let query = json!({ "analyses": [
+            { "Hist": { "count_type": count_type } },
+            { "Growth": {
+                "coverage": get_str(coverage, "coverage")?,
+                "quorum": get_str(quorum, "quorum")?,
+            } },
+        ] });
+        into_c_string(run_last_table(graph, query)?)
+    })
+}
+
+/// Pairwise similarity of the paths, or of their groups (sample, haplotype, or a file assigning
+/// paths to groups; paths if NULL), for the given count type as tab-separated table; returns
+/// NULL on failure
+///
+/// # Safety
+/// `graph` must be returned by `panacus_graph_load`, the strings NULL or valid NUL-terminated
+/// strings
+#[no_mangle]
+pub unsafe extern "C" fn panacus_similarity(
+    graph: *mut PanacusGraph,
+    count_type: *const c_char,
+    grouping: *const c_char,
+) -> *mut c_char {
+    guard(ptr::null_mut(), || {
+        let graph = get_graph(graph)?;
+        let count_type = get_count_type(count_type)?;
+        let query = json!({
+            "grouping": get_grouping(get_str(grouping, "grouping")?),
+            "analyses": [{ "Similarity": { "count_type": count_type } }],
+        });
+        into_c_string(run_last_table(graph, query)?)
+    })
+}
+
+/// Message of the last failure in the calling thread, or NULL; valid until the next failure
+#[no_mangle]
+pub extern "C" fn panacus_last_error() -> *const c_char {
+    LAST_ERROR.with(|e| e.borrow().as_ref().map_or(ptr::null(), |msg| msg.as_ptr()))
+}
+
+/// Frees a string returned by panacus
+///
+/// # Safety
+/// `s` must be NULL or returned by panacus and not yet freed
+#[no_mangle]
+pub unsafe extern "C" fn panacus_string_free(s: *mut c_char) {
+    if !s.is_null() {
+        drop(CString::from_raw(s));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hist_and_errors() {
+        let gfa_file = CString::new("../tests/test_files/t_groups.gfa").unwrap();
+        let count_type = CString::new("bp").unwrap();
+        unsafe {
+            let graph = panacus_graph_load(gfa_file.as_ptr());
+            assert!(!graph.is_null());
+            let table = panacus_hist(graph, count_type.as_ptr());
+            assert!(!table.is_null());
+            assert!(CStr::from_ptr(table)
+                .to_str()
+                .unwrap()
+                .contains("panacus\thist"));
+            panacus_string_free(table);
+
+            // count types are those of the command line, in any case
+            let bubble = CString::new("Bubble").unwrap();
+            let table = panacus_hist(graph, bubble.as_ptr());
+            assert!(!table.is_null());
+            panacus_string_free(table);
+
+            let unknown = CString::new("kmer").unwrap();
+            assert!(panacus_hist(graph, unknown.as_ptr()).is_null());
+            let msg = CStr::from_ptr(panacus_last_error()).to_str().unwrap();
+            assert!(msg.starts_with("unknown count type kmer"));
+            panacus_graph_free(graph);
+        }
+    }
+}
@@ -0,0 +1,37 @@
+/* external use */
+use wasm_bindgen::prelude::*;
+
+/* private use */
+use panacus::graph_broker::GraphBroker;
+use panacus::{add_in_memory_file, clear_in_memory_files, run_query};
+
+// names under which the graph passed from JavaScript is read, depending on its compression
+const GRAPH_FILE: &str = "graph.gfa";
+const COMPRESSED_GRAPH_FILE: &str = "graph.gfa.gz";
+
+/// Makes a file (e.g., a BED file of a subset or a table of groups) available to the next call of
+/// `analyze` under the given name, as there is no file system in the browser
+#[wasm_bindgen(js_name = addFile)]
+pub fn add_file(name: &str, content: &[u8]) {
+    add_in_memory_file(name, content.to_vec());
+}
+
+/// Runs the analyses of the query on the graph, given as (possibly gzip-compressed) GFA content,
+/// and returns a JSON array with the table of each analysis. The query is a run of the YAML config
+/// without graph in JSON format, as answered by `panacus daemon`, e.g.,
+/// `{"analyses": [{"Hist": {"count_type": "Bp"}}]}`. Files added beforehand are dropped once the
+/// analyses are done, such that they are not kept in memory across calls
+#[wasm_bindgen]
+pub fn analyze(gfa: &[u8], query: &str) -> Result<String, JsValue> {
+    let graph_file = if gfa.starts_with(&[0x1f, 0x8b]) {
+        COMPRESSED_GRAPH_FILE
+    } else {
+        GRAPH_FILE
+    };
+    add_in_memory_file(graph_file, gfa.to_vec());
+    let mut gb = GraphBroker::new();
+    let tables = run_query(query, &mut gb, graph_file);
+    clear_in_memory_files();
+    let tables = tables.map_err(|e| JsValue::from_str(&format!("{:#}", e)))?;
+    serde_json::to_string(&tables).map_err(|e| JsValue::from_str(&e.to_string()))
+}
@@ -0,0 +1,25 @@
+//! Smoke tests of the JavaScript API, run with `wasm-pack test --node`
+#![cfg(target_arch = "wasm32")]
+
+use wasm_bindgen_test::*;
+
+use panacus_wasm::{add_file, analyze};
+
+const GFA: &[u8] = include_bytes!("../../tests/test_files/t_groups.gfa");
+const QUERY: &str = r#"{"analyses": [{"Hist": {"count_type": "Node"}}]}"#;
+const SUBSET_QUERY: &str =
+    r#"{"subset": "subset.txt", "analyses": [{"Hist": {"count_type": "Node"}}]}"#;
+
+#[wasm_bindgen_test]
+fn analyze_returns_tables() {
+    let tables: Vec<String> = serde_json::from_str(&analyze(GFA, QUERY).unwrap()).unwrap();
+    assert_eq!(tables.len(), 1);
+    assert!(tables[0].contains("panacus\thist"));
+}
+
+#[wasm_bindgen_test]
+fn added_files_are_dropped_after_analysis() {
+    add_file("subset.txt", b"x\n");
+    assert!(analyze(GFA, SUBSET_QUERY).is_ok());
+    assert!(analyze(GFA, SUBSET_QUERY).is_err());
+}
@@ -0,0 +1,157 @@
+//! R bindings of panacus; the tables of the analyses are handed to R as lists of their header
+//! rows, index, and value columns, from which `R/panacus.R` builds data frames and matrices.
+
+/* external use */
+use extendr_api::prelude::*;
+use panacus::graph_broker::GraphBroker;
+use serde_json::json;
+
+/// Graph loaded once, on which analyses are run without parsing it again
+struct PanacusGraph {
+    gfa_file: String,
+    gb: GraphBroker,
+}
+
+#[extendr]
+impl PanacusGraph {
+    fn new(gfa_file: &str) -> Result<Self> {
+        let gb = panacus::load_graph(gfa_file).map_err(to_r_error)?;
+        Ok(PanacusGraph {
+            gfa_file: gfa_file.to_string(),
+            gb,
+        })
+    }
+
+    fn hist(&mut self, count_type: &str) -> Result<List> {
+        let query =
+            json!({ "analyses": [{ "Hist": { "count_type": get_count_type(count_type)? } }] });
+        self.run_table(query)
+    }
+
+    fn growth(&mut self, count_type: &str, coverage: &str, quorum: &str) -> Result<List> {
+        let query = json!({ "analyses": [
+            { "Hist": { "count_type": get_count_type(count_type)? } },
+            { "Growth": { "coverage": coverage, "quorum": quorum } },
+        ] });
+        self.run_table(query)
+    }
+
+    fn similarity(&mut self, count_type: &str, grouping: Nullable<String>) -> Result<List> {
+        let grouping = match grouping {
+            Nullable::Null => serde_json::Value::Null,
+            Nullable::NotNull(g) if g == "sample" => json!("Sample"),
+            Nullable::NotNull(g) if g == "haplotype" => json!("Haplotype"),
+            Nullable::NotNull(file) => json!({ "Custom": file }),
+        };
+        let query = json!({
+            "grouping": grouping,
+            "analyses": [{ "Similarity": { "count_type": get_count_type(count_type)? } }],
+        });
+        self.run_table(query)
+    }
+
+    fn query(&mut self, query: &str) -> Result<Vec<String>> {
+        panacus::run_query(query, &mut self.gb, &self.gfa_file).map_err(to_r_error)
+    }
+}
+
+impl PanacusGraph {
+    // table of the last analysis of the query, i.e., the requested one if preceded by a histogram
+    fn run_table(&mut self, query: serde_json::Value) -> Result<List> {
+        let table = panacus::run_query(&query.to_string(), &mut self.gb, &self.gfa_file)
+            .map_err(to_r_error)?
+            .pop()
+            .ok_or_else(|| Error::Other("query produced no table".to_string()))?;
+        let (header, index, columns) = parse_table(&table).map_err(Error::Other)?;
+        Ok(list!(
+            header = List::from_values(header),
+            index = index,
+            columns = List::from_values(columns)
+        ))
+    }
+}
+
+fn to_r_error(e: anyhow::Error) -> Error {
+    Error::Other(format!("{:#}", e))
+}
+
+// count type as given on the command line (e.g., "bp") in the query format
+fn get_count_type(count_type: &str) -> Result<serde_json::Value> {
+    panacus::query_count_type(count_type).map_err(to_r_error)
+}
+
+// header rows (without their label), index, and value columns of a table
+type Table = (Vec<Vec<String>>, Vec<String>, Vec<Vec<f64>>);
+
+// tab-separated table of panacus, preceded by comment lines starting with #; tables starting with
+// the row "panacus <analysis>" (e.g., hist and growth) have header rows (count, coverage, ...) up
+// to the first row whose index is a number, all other tables (e.g., similarity) are matrices with
+// a single header row of column names
+fn parse_table(text: &str) -> std::result::Result<Table, String> {
+    let lines: Vec<&str> = text
+        .lines()
+        .filter(|l| !l.starts_with('#') && !l.is_empty())
+        .collect();
+    let header_rows = if lines.first().map_or(false, |l| l.starts_with("panacus\t")) {
+        lines
+            .iter()
+            .position(|l| l.split('\t').next().unwrap().parse::<f64>().is_ok())
+            .unwrap_or(lines.len())
+    } else {
+        usize::min(1, lines.len())
+    };
+    let header: Vec<Vec<String>> = lines[..header_rows]
+        .iter()
+        .map(|l| l.split('\t').skip(1).map(|f| f.to_string()).collect())
+        .collect();
+    let mut index = Vec::new();
+    let mut columns = vec![Vec::new(); header.first().map_or(0, |h| h.len())];
+    for line in &lines[header_rows..] {
+        let mut fields = line.split('\t');
+        index.push(fields.next().unwrap_or_default().to_string());
+        for (column, field) in columns.iter_mut().zip(fields) {
+            column.push(
+                field
+                    .parse::<f64>()
+                    .map_err(|_| format!("{} in table is not a number", field))?,
+            );
+        }
+    }
+    Ok((header, index, columns))
+}
+
+extendr_module! {
+    mod panacusr;
+    impl PanacusGraph;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_table() {
+        let hist = "# panacus hist graph.gfa\npanacus\thist\ncount\tnode\n\t\n\t\n0\t5\n1\t3\n";
+        let (header, index, columns) = parse_table(hist).unwrap();
+        assert_eq!(header.len(), 4);
+        assert_eq!(header[1], vec!["node"]);
+        assert_eq!(index, vec!["0", "1"]);
+        assert_eq!(columns, vec![vec![5.0, 3.0]]);
+
+        let growth = "panacus\tgrowth\tgrowth\ncount\tnode\tnode\ncoverage\t1\t2\nquorum\t0\t0\n0\tNaN\tNaN\n1\t3\t1\n";
+        let (header, index, columns) = parse_table(growth).unwrap();
+        assert_eq!(header[2], vec!["1", "2"]);
+        assert_eq!(index, vec!["0", "1"]);
+        assert!(columns[0][0].is_nan());
+        assert_eq!(columns[1][1], 1.0);
+
+        // the index of similarity tables consists of group names rather than numbers
+        let similarity = "group\ta\tb\na\t1\t0.5\nb\t0.5\t1\n";
+        let (header, index, columns) = parse_table(similarity).unwrap();
+        assert_eq!(header, vec![vec!["a", "b"]]);
+        assert_eq!(index, vec!["a", "b"]);
+        assert_eq!(columns, vec![vec![1.0, 0.5], vec![0.5, 1.0]]);
+
+        assert!(parse_table("group\ta\na\tx\n").is_err());
+    }
+}
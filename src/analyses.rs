@@ -1,20 +1,38 @@
+pub mod base_composition;
+pub mod chromosomal;
+pub mod coverage_colors;
 pub mod coverage_line;
+pub mod depth;
+pub mod differential;
+pub mod duplicates;
 pub mod growth;
 pub mod hist;
 pub mod info;
+pub mod kmer_comparison;
+pub mod node_coverage;
 pub mod node_distribution;
+pub mod node_lengths;
+pub mod node_multiplicity;
 pub mod ordered_histgrowth;
+pub mod paths;
+pub mod phylo_growth;
 pub mod similarity;
+pub mod size_classes;
 pub mod table;
+pub mod window_similarity;
 
 use std::collections::HashSet;
+use std::fs::File;
+use std::io::{BufWriter, Write};
 
 use crate::{
-    analysis_parameter::AnalysisParameter, graph_broker::GraphBroker, html_report::AnalysisSection,
+    analysis_parameter::{AnalysisParameter, Annotations},
+    graph_broker::GraphBroker,
+    html_report::AnalysisSection,
     util::CountType,
 };
 
-pub trait Analysis {
+pub trait Analysis: Send {
     fn generate_table(&mut self, gb: Option<&GraphBroker>) -> anyhow::Result<String>;
     fn generate_report_section(
         &mut self,
@@ -22,17 +40,97 @@ pub trait Analysis {
     ) -> anyhow::Result<Vec<AnalysisSection>>;
     fn get_graph_requirements(&self) -> HashSet<InputRequirement>;
     fn get_type(&self) -> String;
+
+    /// Whether the analysis only uses the histograms of the graph broker (besides the name and
+    /// state of its run), such that cached histograms spare parsing the graph
+    fn uses_hists_only(&self) -> bool {
+        false
+    }
 }
 
 pub trait ConstructibleAnalysis: Analysis {
     fn from_parameter(parameter: AnalysisParameter) -> Self;
 }
 
+// analysis whose report sections are shown under a custom title and with a description, and
+// whose table is written to its own output file, as given in the YAML config
+pub struct Annotated {
+    analysis: Box<dyn Analysis>,
+    annotations: Annotations,
+}
+
+impl Annotated {
+    pub fn new(analysis: Box<dyn Analysis>, annotations: Annotations) -> Self {
+        Self {
+            analysis,
+            annotations,
+        }
+    }
+
+    fn write_output(
+        &mut self,
+        gb: Option<&GraphBroker>,
+        sections: &[AnalysisSection],
+        file: &str,
+    ) -> anyhow::Result<()> {
+        log::info!("writing {} to {}", self.analysis.get_type(), file);
+        let content = if file.ends_with(".json") {
+            serde_json::to_string_pretty(sections)?
+        } else {
+            self.analysis.generate_table(gb)?
+        };
+        let mut out = BufWriter::new(
+            File::create(file)
+                .map_err(|e| anyhow::anyhow!("unable to create output file {}: {}", file, e))?,
+        );
+        crate::run_summary::add_output_file(file);
+        writeln!(out, "{}", content)?;
+        out.flush()?;
+        Ok(())
+    }
+}
+
+impl Analysis for Annotated {
+    fn generate_table(&mut self, gb: Option<&GraphBroker>) -> anyhow::Result<String> {
+        self.analysis.generate_table(gb)
+    }
+
+    fn generate_report_section(
+        &mut self,
+        gb: Option<&GraphBroker>,
+    ) -> anyhow::Result<Vec<AnalysisSection>> {
+        let mut sections = self.analysis.generate_report_section(gb)?;
+        for section in sections.iter_mut() {
+            if let Some(title) = &self.annotations.title {
+                section.analysis = title.clone();
+            }
+            section.description = self.annotations.description.clone();
+        }
+        if let Some(file) = self.annotations.output.clone() {
+            self.write_output(gb, &sections, &file)?;
+        }
+        Ok(sections)
+    }
+
+    fn get_graph_requirements(&self) -> HashSet<InputRequirement> {
+        self.analysis.get_graph_requirements()
+    }
+
+    fn get_type(&self) -> String {
+        self.analysis.get_type()
+    }
+
+    fn uses_hists_only(&self) -> bool {
+        self.analysis.uses_hists_only()
+    }
+}
+
 #[derive(PartialEq, Eq, Debug, Clone, Hash)]
 pub enum InputRequirement {
     Node,
     Edge,
     Bp,
+    Bubble,
     PathLens,
     Hist,
     AbacusByGroup(CountType),
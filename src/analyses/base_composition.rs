@@ -0,0 +1,305 @@
+use std::collections::HashSet;
+
+use crate::{
+    analysis_parameter::AnalysisParameter,
+    graph_broker::GraphBroker,
+    html_report::{AnalysisSection, ReportItem},
+    util::{get_default_plot_downloads, to_id, CountType},
+};
+
+use super::{Analysis, ConstructibleAnalysis, InputRequirement};
+
+const BASES: [&str; 5] = ["A", "C", "G", "T", "N"];
+
+pub struct BaseComposition {
+    // base counts of the nodes of each coverage (index), i.e., covered by that many groups
+    composition: Vec<[u64; 5]>,
+    group_count: usize,
+}
+
+impl Analysis for BaseComposition {
+    fn get_type(&self) -> String {
+        "BaseComposition".to_string()
+    }
+
+    fn generate_table(&mut self, gb: Option<&GraphBroker>) -> anyhow::Result<String> {
+        log::info!("reporting base composition per coverage class");
+        let gb = gb.expect("BaseComposition analysis needs a graph");
+        self.set_composition(gb)?;
+
+        let mut res = String::new();
+        res.push_str(&crate::io::write_metadata_comments()?);
+        for row in self.get_summary() {
+            res.push_str(&format!("# {}\n", row.join("\t")));
+        }
+        res.push_str(&format!("{}\n", Self::get_header().join("\t")));
+        for row in self.get_rows() {
+            res.push_str(&format!("{}\n", row.join("\t")));
+        }
+        Ok(res)
+    }
+
+    fn generate_report_section(
+        &mut self,
+        gb: Option<&GraphBroker>,
+    ) -> anyhow::Result<Vec<AnalysisSection>> {
+        let gb = gb.expect("BaseComposition analysis needs a graph");
+        let table = self.generate_table(Some(gb))?;
+        let table = format!("`{}`", &table);
+        let id_prefix = format!("base-composition-{}", to_id(&self.get_run_id(gb)));
+        let summary = self.get_summary();
+
+        // one group of bars per coverage class, holding the GC content and the fraction of each
+        // base
+        let mut names = vec!["GC".to_string()];
+        names.extend(BASES.iter().map(|b| b.to_string()));
+        let coverages: Vec<usize> = (1..self.composition.len())
+            .filter(|i| total(&self.composition[*i]) > 0)
+            .collect();
+        let mut values = vec![coverages
+            .iter()
+            .map(|i| {
+                let gc = gc_content(&self.composition[*i]);
+                if gc.is_nan() {
+                    0.0
+                } else {
+                    100.0 * gc
+                }
+            })
+            .collect::<Vec<f64>>()];
+        values.extend((0..BASES.len()).map(|b| {
+            coverages
+                .iter()
+                .map(|i| {
+                    let counts = &self.composition[*i];
+                    100.0 * counts[b] as f64 / total(counts) as f64
+                })
+                .collect()
+        }));
+
+        Ok(vec![AnalysisSection {
+            id: id_prefix.clone(),
+            analysis: "Base Composition".to_string(),
+            table: Some(table),
+            run_name: self.get_run_name(gb),
+            run_id: self.get_run_id(gb),
+            countable: CountType::Bp.to_string(),
+            items: vec![
+                ReportItem::MultiBar {
+                    id: format!("{id_prefix}-coverage"),
+                    names,
+                    x_label: "coverage".to_string(),
+                    y_label: "% bp".to_string(),
+                    labels: coverages.iter().map(|i| i.to_string()).collect(),
+                    values,
+                    log_toggle: false,
+                    phenotypes: None,
+                    hover: None,
+                },
+                ReportItem::Table {
+                    id: format!("{id_prefix}-summary"),
+                    header: summary[0].clone(),
+                    values: summary[1..].to_vec(),
+                },
+                ReportItem::Table {
+                    id: format!("{id_prefix}-table"),
+                    header: Self::get_header(),
+                    values: self.get_rows(),
+                },
+            ],
+            plot_downloads: get_default_plot_downloads(),
+            description: None,
+        }])
+    }
+
+    fn get_graph_requirements(&self) -> HashSet<InputRequirement> {
+        HashSet::from([InputRequirement::Node])
+    }
+}
+
+impl ConstructibleAnalysis for BaseComposition {
+    fn from_parameter(_parameter: AnalysisParameter) -> Self {
+        Self {
+            composition: Vec::new(),
+            group_count: 0,
+        }
+    }
+}
+
+impl BaseComposition {
+    fn set_composition(&mut self, gb: &GraphBroker) -> anyhow::Result<()> {
+        if self.composition.is_empty() {
+            let bases = gb.get_node_bases()?;
+            let coverage = &gb.get_abacus_by_total(CountType::Node).countable;
+            self.composition = compose_by_coverage(coverage, &bases);
+            self.group_count = gb.get_group_count();
+        }
+        Ok(())
+    }
+
+    fn get_class(&self, coverage: usize) -> &'static str {
+        if coverage == self.group_count {
+            "core"
+        } else if coverage == 1 {
+            "private"
+        } else {
+            "shell"
+        }
+    }
+
+    fn get_header() -> Vec<String> {
+        let mut header = vec![
+            "coverage".to_string(),
+            "class".to_string(),
+            "bp".to_string(),
+            "GC".to_string(),
+        ];
+        header.extend(BASES.iter().map(|b| b.to_string()));
+        header
+    }
+
+    // one row per coverage of covered sequence, giving GC content (of A, C, G, and T bases) and
+    // fraction of each base
+    fn get_rows(&self) -> Vec<Vec<String>> {
+        (1..self.composition.len())
+            .filter(|i| total(&self.composition[*i]) > 0)
+            .map(|i| {
+                let counts = &self.composition[i];
+                let bp = total(counts);
+                let mut row = vec![
+                    i.to_string(),
+                    self.get_class(i).to_string(),
+                    bp.to_string(),
+                    format!("{:.4}", gc_content(counts)),
+                ];
+                row.extend(
+                    counts
+                        .iter()
+                        .map(|c| format!("{:.4}", *c as f64 / bp as f64)),
+                );
+                row
+            })
+            .collect()
+    }
+
+    // GC content of core and accessory sequence, as well as of all covered sequence
+    fn get_summary(&self) -> Vec<Vec<String>> {
+        let (mut core, mut accessory) = ([0; 5], [0; 5]);
+        for (i, counts) in self.composition.iter().enumerate().skip(1) {
+            let target = if i == self.group_count {
+                &mut core
+            } else {
+                &mut accessory
+            };
+            target.iter_mut().zip(counts).for_each(|(t, c)| *t += c);
+        }
+        let mut all = core;
+        all.iter_mut().zip(&accessory).for_each(|(t, c)| *t += c);
+        let mut res = vec![vec![
+            "sequence".to_string(),
+            "bp".to_string(),
+            "GC".to_string(),
+        ]];
+        for (name, counts) in [("core", core), ("accessory", accessory), ("total", all)] {
+            res.push(vec![
+                name.to_string(),
+                total(&counts).to_string(),
+                format!("{:.4}", gc_content(&counts)),
+            ]);
+        }
+        res
+    }
+
+    fn get_run_name(&self, gb: &GraphBroker) -> String {
+        gb.get_run_name()
+    }
+
+    fn get_run_id(&self, gb: &GraphBroker) -> String {
+        format!("{}-basecomposition", gb.get_run_id())
+    }
+}
+
+// sums up the base counts of the nodes (skipping the unused id 0) by their coverage
+fn compose_by_coverage(coverage: &[u32], bases: &[[u32; 5]]) -> Vec<[u64; 5]> {
+    let max = coverage.iter().skip(1).max().copied().unwrap_or(0) as usize;
+    let mut res = vec![[0; 5]; max + 1];
+    for (cov, counts) in coverage.iter().zip(bases).skip(1) {
+        res[*cov as usize]
+            .iter_mut()
+            .zip(counts)
+            .for_each(|(r, c)| *r += *c as u64);
+    }
+    res
+}
+
+fn total(counts: &[u64; 5]) -> u64 {
+    counts.iter().sum()
+}
+
+// fraction of G and C among all unambiguous bases
+fn gc_content(counts: &[u64; 5]) -> f64 {
+    let acgt: u64 = counts[..4].iter().sum();
+    if acgt == 0 {
+        f64::NAN
+    } else {
+        (counts[1] + counts[2]) as f64 / acgt as f64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graph_broker::GraphState;
+
+    #[test]
+    fn test_set_composition() {
+        let graph = "tests/test_files/t_groups.gfa".to_string();
+        let reqs = HashSet::from([
+            InputRequirement::Graph(graph.clone()),
+            InputRequirement::Node,
+        ]);
+        let state = GraphState {
+            graph,
+            ..GraphState::default()
+        };
+        let mut gb = GraphBroker::new();
+        gb.change_graph_state(state, &reqs, false).unwrap();
+
+        let mut analysis = BaseComposition::from_parameter(AnalysisParameter::BaseComposition);
+        analysis.set_composition(&gb).unwrap();
+        // all bp of covered nodes are counted once in the class of their coverage
+        let coverage = &gb.get_abacus_by_total(CountType::Node).countable;
+        let covered_bp: u64 = gb
+            .get_node_lens()
+            .iter()
+            .zip(coverage.iter())
+            .skip(1)
+            .filter(|(_, c)| **c > 0)
+            .map(|(l, _)| *l as u64)
+            .sum();
+        let rows = analysis.get_rows();
+        let bp: u64 = rows.iter().map(|r| r[2].parse::<u64>().unwrap()).sum();
+        assert_eq!(bp, covered_bp);
+        assert_eq!(analysis.get_summary()[3][1], covered_bp.to_string());
+    }
+
+    #[test]
+    fn test_compose_by_coverage() {
+        let coverage = vec![0, 2, 1, 2, 0];
+        let bases = vec![
+            [0, 0, 0, 0, 0],
+            [1, 2, 3, 4, 0],
+            [0, 5, 0, 0, 1],
+            [2, 0, 1, 0, 0],
+            [7, 0, 0, 0, 0],
+        ];
+        let composition = compose_by_coverage(&coverage, &bases);
+        assert_eq!(
+            composition,
+            vec![[7, 0, 0, 0, 0], [0, 5, 0, 0, 1], [3, 2, 4, 4, 0]]
+        );
+        assert_eq!(gc_content(&composition[1]), 1.0);
+        assert_eq!(gc_content(&composition[2]), 6.0 / 13.0);
+        assert!(gc_content(&[0, 0, 0, 0, 3]).is_nan());
+    }
+}
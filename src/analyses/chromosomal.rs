@@ -0,0 +1,500 @@
+use std::collections::HashSet;
+use std::io::{BufReader, BufWriter, Write};
+
+use crate::{
+    analysis_parameter::AnalysisParameter,
+    graph_broker::{GraphBroker, PathSegment},
+    html_report::{AnalysisSection, ReportItem},
+    io::{parse_bed_features, write_bigwig},
+    util::get_default_plot_downloads,
+    util::to_id,
+    util::CountType,
+};
+
+use super::{Analysis, ConstructibleAnalysis, InputRequirement};
+
+// maximum number of intervals that are shown in the report; larger tracks are averaged over
+// windows of equal size
+const MAX_REPORT_INTERVALS: usize = 5000;
+
+pub struct Chromosomal {
+    parameter: AnalysisParameter,
+    // intervals (start, end, coverage) along the reference sequence
+    intervals: Vec<(usize, usize, f64)>,
+    // fraction of the bp of the same intervals that is covered by each group, if tracks by group
+    // are requested
+    group_tracks: Vec<(String, Vec<(usize, usize, f64)>)>,
+    sequence: String,
+}
+
+impl Analysis for Chromosomal {
+    fn get_type(&self) -> String {
+        "Chromosomal".to_string()
+    }
+
+    fn generate_table(&mut self, gb: Option<&GraphBroker>) -> anyhow::Result<String> {
+        log::info!("reporting chromosomal coverage track");
+        if gb.is_none() {
+            panic!("Chromosomal analysis needs a graph")
+        }
+        let gb = gb.unwrap();
+        self.set_intervals(gb)?;
+        if let Some(bigwig) = self.get_bigwig() {
+            self.write_bigwig(bigwig)?;
+        }
+
+        let mut res = String::new();
+        res.push_str(&crate::io::write_metadata_comments()?);
+        res.push_str(&format!(
+            "track type=bedGraph name=\"{}\" description=\"{} node coverage along {}\"\n",
+            self.get_run_id(gb),
+            gb.get_fname(),
+            self.get_reference()
+        ));
+        for (start, end, value) in &self.intervals {
+            res.push_str(&format!(
+                "{}\t{}\t{}\t{}\n",
+                &self.sequence, start, end, value
+            ));
+        }
+        for (group, intervals) in &self.group_tracks {
+            res.push_str(&format!(
+                "track type=bedGraph name=\"{} {}\" description=\"fraction of bp along {} covered by {}\"\n",
+                self.get_run_id(gb),
+                group,
+                self.get_reference(),
+                group
+            ));
+            for (start, end, value) in intervals {
+                res.push_str(&format!(
+                    "{}\t{}\t{}\t{}\n",
+                    &self.sequence, start, end, value
+                ));
+            }
+        }
+        Ok(res)
+    }
+
+    fn generate_report_section(
+        &mut self,
+        gb: Option<&GraphBroker>,
+    ) -> anyhow::Result<Vec<AnalysisSection>> {
+        if gb.is_none() {
+            panic!("Chromosomal analysis needs a graph")
+        }
+        let gb = gb.unwrap();
+        let table = self.generate_table(Some(gb))?;
+        let table = format!("`{}`", &table);
+        let id_prefix = format!("chromosomal-{}", to_id(&self.get_run_id(gb)));
+
+        // all tracks share the intervals, and hence also the windows they are averaged over
+        let window = if self.intervals.len() > MAX_REPORT_INTERVALS {
+            let length = self.intervals.last().map(|x| x.1).unwrap_or(0)
+                - self.intervals.first().map(|x| x.0).unwrap_or(0);
+            let window = length / MAX_REPORT_INTERVALS + 1;
+            log::info!(
+                "averaging {} intervals over windows of {}bp for the report",
+                self.intervals.len(),
+                window
+            );
+            Some(window)
+        } else {
+            None
+        };
+        let for_report = |intervals: &Vec<(usize, usize, f64)>| match window {
+            Some(window) => average_over_windows(intervals, window),
+            None => intervals.clone(),
+        };
+        let intervals = for_report(&self.intervals);
+        let tracks = self
+            .group_tracks
+            .iter()
+            .map(|(group, intervals)| {
+                (
+                    group.clone(),
+                    for_report(intervals).iter().map(|x| x.2).collect(),
+                )
+            })
+            .collect();
+
+        Ok(vec![AnalysisSection {
+            id: id_prefix.clone(),
+            analysis: "Chromosomal".to_string(),
+            table: Some(table),
+            run_name: self.get_run_name(gb),
+            run_id: self.get_run_id(gb),
+            countable: CountType::Node.to_string(),
+            items: vec![ReportItem::Chromosomal {
+                id: id_prefix,
+                name: gb.get_fname(),
+                sequence: self.sequence.clone(),
+                y_label: "Coverage".to_string(),
+                starts: intervals.iter().map(|x| x.0).collect(),
+                ends: intervals.iter().map(|x| x.1).collect(),
+                values: intervals.iter().map(|x| x.2).collect(),
+                tracks,
+                annotations: self.get_annotations()?,
+            }],
+            plot_downloads: get_default_plot_downloads(),
+            description: None,
+        }])
+    }
+
+    fn get_graph_requirements(&self) -> HashSet<InputRequirement> {
+        let mut req = HashSet::from([InputRequirement::Node]);
+        if self.get_by_group() {
+            req.insert(InputRequirement::AbacusByGroup(CountType::Node));
+        }
+        req
+    }
+}
+
+impl ConstructibleAnalysis for Chromosomal {
+    fn from_parameter(parameter: AnalysisParameter) -> Self {
+        Self {
+            parameter,
+            intervals: Vec::new(),
+            group_tracks: Vec::new(),
+            sequence: String::new(),
+        }
+    }
+}
+
+impl Chromosomal {
+    fn get_reference(&self) -> &str {
+        if let AnalysisParameter::Chromosomal { reference, .. } = &self.parameter {
+            reference
+        } else {
+            panic!("Chromosomal analysis needs chromosomal parameter");
+        }
+    }
+
+    fn get_window(&self) -> Option<usize> {
+        if let AnalysisParameter::Chromosomal { window, .. } = &self.parameter {
+            *window
+        } else {
+            None
+        }
+    }
+
+    fn get_bigwig(&self) -> Option<&str> {
+        if let AnalysisParameter::Chromosomal { bigwig, .. } = &self.parameter {
+            bigwig.as_deref()
+        } else {
+            None
+        }
+    }
+
+    fn get_by_group(&self) -> bool {
+        if let AnalysisParameter::Chromosomal { by_group, .. } = &self.parameter {
+            *by_group
+        } else {
+            false
+        }
+    }
+
+    fn get_annotation(&self) -> Option<&str> {
+        if let AnalysisParameter::Chromosomal { annotation, .. } = &self.parameter {
+            annotation.as_deref()
+        } else {
+            None
+        }
+    }
+
+    // features of the annotation file on the reference sequence, which is named either as in the
+    // reference genome or by the whole path name
+    fn get_annotations(&self) -> anyhow::Result<Vec<(usize, usize, String)>> {
+        let file = match self.get_annotation() {
+            Some(file) => file,
+            None => return Ok(Vec::new()),
+        };
+        log::info!("loading annotation features from {}", file);
+        let mut data = BufReader::new(
+            std::fs::File::open(file)
+                .map_err(|e| anyhow::anyhow!("unable to open annotation file {}: {}", file, e))?,
+        );
+        let reference = PathSegment::from_str(self.get_reference())
+            .clear_coords()
+            .id();
+        let features: Vec<(usize, usize, String)> = parse_bed_features(&mut data, file)?
+            .into_iter()
+            .filter(|(seq, ..)| seq == &self.sequence || seq == &reference)
+            .map(|(_, start, end, name)| (start, end, name))
+            .collect();
+        if features.is_empty() {
+            log::warn!(
+                "annotation file {} has no features on sequence {}",
+                file,
+                self.sequence
+            );
+        }
+        Ok(features)
+    }
+
+    fn write_bigwig(&self, file: &str) -> anyhow::Result<()> {
+        log::info!("writing bigWig track to {}", file);
+        let intervals: Vec<(u32, u32, f32)> = self
+            .intervals
+            .iter()
+            .map(|(start, end, value)| (*start as u32, *end as u32, *value as f32))
+            .collect();
+        let length = intervals.last().map(|x| x.1).unwrap_or(0);
+        let mut out = BufWriter::new(
+            std::fs::File::create(file)
+                .map_err(|e| anyhow::anyhow!("unable to create bigWig file {}: {}", file, e))?,
+        );
+        crate::run_summary::add_output_file(file);
+        write_bigwig(&mut out, &[(&self.sequence, length, &intervals)])
+            .and_then(|_| out.flush())
+            .map_err(|e| anyhow::anyhow!("unable to write bigWig file {}: {}", file, e))
+    }
+
+    fn set_intervals(&mut self, gb: &GraphBroker) -> anyhow::Result<()> {
+        if !self.intervals.is_empty() {
+            return Ok(());
+        }
+        let reference = PathSegment::from_str(self.get_reference());
+        let mut fragments = gb.get_path_steps(&reference)?;
+        if fragments.is_empty() {
+            anyhow::bail!(
+                "reference path {} does not exist in graph {}",
+                self.get_reference(),
+                gb.get_fname()
+            );
+        }
+        fragments.sort_by_key(|(path_seg, _)| path_seg.start.unwrap_or(0));
+
+        // name the sequence as it is called in the reference genome, such that the track can be
+        // shown alongside other annotations
+        self.sequence = reference
+            .seqid
+            .clone()
+            .unwrap_or_else(|| reference.clear_coords().id());
+
+        let node_lens = gb.get_node_lens();
+        let coverage = &gb.get_abacus_by_total(CountType::Node).countable;
+        let groups = if self.get_by_group() {
+            gb.get_abacus_by_group().groups.clone()
+        } else {
+            Vec::new()
+        };
+        // whether each group covers a node
+        let get_presence = |node: usize| {
+            let mut res = vec![0.0; groups.len()];
+            if !groups.is_empty() {
+                let abacus = gb.get_abacus_by_group();
+                for g in &abacus.c[abacus.r[node]..abacus.r[node + 1]] {
+                    res[*g as usize] = 1.0;
+                }
+            }
+            res
+        };
+        let mut intervals: Vec<(usize, usize, f64)> = Vec::new();
+        let mut group_values: Vec<Vec<f64>> = Vec::new();
+        for (path_seg, steps) in fragments {
+            let mut pos = path_seg.start.unwrap_or(0);
+            for (node, _) in steps {
+                let end = pos + node_lens[node.0 as usize] as usize;
+                let value = coverage[node.0 as usize] as f64;
+                let values = get_presence(node.0 as usize);
+                match (intervals.last_mut(), group_values.last()) {
+                    // merge adjacent intervals with equal coverage (also of each group)
+                    (Some(last), Some(last_values))
+                        if last.1 == pos && last.2 == value && last_values == &values =>
+                    {
+                        last.1 = end
+                    }
+                    _ => {
+                        intervals.push((pos, end, value));
+                        group_values.push(values);
+                    }
+                }
+                pos = end;
+            }
+        }
+        let mut group_tracks: Vec<(String, Vec<(usize, usize, f64)>)> = groups
+            .into_iter()
+            .enumerate()
+            .map(|(g, group)| {
+                let track = intervals
+                    .iter()
+                    .zip(&group_values)
+                    .map(|((start, end, _), values)| (*start, *end, values[g]))
+                    .collect();
+                (group, track)
+            })
+            .collect();
+        if let Some(window) = self.get_window() {
+            if window == 0 {
+                anyhow::bail!("window size of chromosomal analysis must be positive");
+            }
+            intervals = average_over_windows(&intervals, window);
+            for (_, track) in group_tracks.iter_mut() {
+                *track = average_over_windows(track, window);
+            }
+        }
+        self.intervals = intervals;
+        self.group_tracks = group_tracks;
+        Ok(())
+    }
+
+    fn get_run_name(&self, gb: &GraphBroker) -> String {
+        gb.get_run_name()
+    }
+
+    fn get_run_id(&self, gb: &GraphBroker) -> String {
+        format!("{}-chromosomal", gb.get_run_id())
+    }
+}
+
+// computes the bp-weighted mean of the interval values within windows of the given size; windows
+// are aligned to multiples of the window size and span only positions covered by intervals
+fn average_over_windows(
+    intervals: &[(usize, usize, f64)],
+    window: usize,
+) -> Vec<(usize, usize, f64)> {
+    let mut res: Vec<(usize, usize, f64)> = Vec::new();
+    // start, end, weighted sum, and covered bp of the window that is currently filled
+    let mut current: Option<(usize, usize, f64, usize)> = None;
+    for &(start, end, value) in intervals {
+        let mut pos = start;
+        while pos < end {
+            let window_start = pos / window * window;
+            let until = end.min(window_start + window);
+            match current.as_mut() {
+                Some(c) if c.0 / window * window == window_start => {
+                    c.1 = until;
+                    c.2 += value * (until - pos) as f64;
+                    c.3 += until - pos;
+                }
+                _ => {
+                    if let Some(c) = current.take() {
+                        res.push((c.0, c.1, c.2 / c.3 as f64));
+                    }
+                    current = Some((pos, until, value * (until - pos) as f64, until - pos));
+                }
+            }
+            pos = until;
+        }
+    }
+    if let Some(c) = current {
+        res.push((c.0, c.1, c.2 / c.3 as f64));
+    }
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::analysis_parameter::Grouping;
+    use crate::graph_broker::GraphState;
+
+    // two contigs of the reference, of which sample a lacks node 2 of chr1 and sample b chr2
+    const GFA: &[u8] = b"H\tVN:Z:1.0
+S\t1\tAAAA
+S\t2\tCC
+S\t3\tGGG
+S\t4\tTT
+S\t5\tA
+P\tref#0#chr1\t1+,2+,3+\t*
+P\tref#0#chr2\t4+,5+\t*
+P\ta#1#chr1\t1+,3+\t*
+P\ta#1#chr2\t4+,5+\t*
+P\tb#1#chr1\t1+,2+\t*
+";
+
+    fn get_chromosomal(reference: &str, window: Option<usize>, by_group: bool) -> Chromosomal {
+        Chromosomal::from_parameter(AnalysisParameter::Chromosomal {
+            reference: reference.to_string(),
+            window,
+            bigwig: None,
+            annotation: None,
+            by_group,
+        })
+    }
+
+    fn get_graph_broker(gfa: &tempfile::NamedTempFile, analysis: &Chromosomal) -> GraphBroker {
+        let graph = gfa.path().to_str().unwrap().to_string();
+        let mut reqs = analysis.get_graph_requirements();
+        reqs.insert(InputRequirement::Graph(graph.clone()));
+        let state = GraphState {
+            graph,
+            grouping: Some(Grouping::Sample),
+            ..GraphState::default()
+        };
+        let mut gb = GraphBroker::new();
+        gb.change_graph_state(state, &reqs, false).unwrap();
+        gb
+    }
+
+    #[test]
+    fn test_set_intervals_of_contigs() {
+        let mut gfa = tempfile::NamedTempFile::new().unwrap();
+        std::io::Write::write_all(&mut gfa, GFA).unwrap();
+
+        // adjacent nodes covered by the same number of samples are merged
+        let mut chr1 = get_chromosomal("ref#0#chr1", None, false);
+        let gb = get_graph_broker(&gfa, &chr1);
+        chr1.set_intervals(&gb).unwrap();
+        assert_eq!(chr1.sequence, "chr1");
+        assert_eq!(chr1.intervals, vec![(0, 4, 3.0), (4, 9, 2.0)]);
+
+        // coverage counts samples, which also cover nodes with other contigs than the reference
+        let mut chr2 = get_chromosomal("ref#0#chr2", None, false);
+        chr2.set_intervals(&gb).unwrap();
+        assert_eq!(chr2.sequence, "chr2");
+        assert_eq!(chr2.intervals, vec![(0, 3, 2.0)]);
+        assert!(chr2.group_tracks.is_empty());
+
+        assert!(get_chromosomal("ref#0#chr3", None, false)
+            .set_intervals(&gb)
+            .is_err());
+    }
+
+    #[test]
+    fn test_set_intervals_by_group() {
+        let mut gfa = tempfile::NamedTempFile::new().unwrap();
+        std::io::Write::write_all(&mut gfa, GFA).unwrap();
+
+        // nodes 2 and 3 are covered by as many samples, but not by the same ones
+        let mut chr1 = get_chromosomal("ref#0#chr1", None, true);
+        let gb = get_graph_broker(&gfa, &chr1);
+        chr1.set_intervals(&gb).unwrap();
+        assert_eq!(chr1.intervals, vec![(0, 4, 3.0), (4, 6, 2.0), (6, 9, 2.0)]);
+        let tracks: HashMap<_, _> = chr1.group_tracks.iter().cloned().collect();
+        assert_eq!(tracks["a"], vec![(0, 4, 1.0), (4, 6, 0.0), (6, 9, 1.0)]);
+        assert_eq!(tracks["b"], vec![(0, 4, 1.0), (4, 6, 1.0), (6, 9, 0.0)]);
+
+        // tracks by group are averaged over the same windows as the coverage
+        let mut chr1 = get_chromosomal("ref#0#chr1", Some(5), true);
+        chr1.set_intervals(&gb).unwrap();
+        assert_eq!(chr1.intervals, vec![(0, 5, 2.8), (5, 9, 2.0)]);
+        let tracks: HashMap<_, _> = chr1.group_tracks.iter().cloned().collect();
+        assert_eq!(tracks["a"], vec![(0, 5, 0.8), (5, 9, 0.75)]);
+    }
+
+    #[test]
+    fn test_average_over_windows() {
+        let intervals = vec![(0, 4, 3.0), (4, 9, 2.0)];
+        assert_eq!(
+            average_over_windows(&intervals, 5),
+            vec![(0, 5, 2.8), (5, 9, 2.0)]
+        );
+        assert_eq!(
+            average_over_windows(&intervals, 100),
+            vec![(0, 9, 22.0 / 9.0)]
+        );
+        assert_eq!(average_over_windows(&intervals, 1).len(), 9);
+
+        // windows only span the positions covered by intervals, e.g., of a reference path that is
+        // fragmented into several path segments
+        let intervals = vec![(2, 4, 1.0), (12, 15, 4.0), (15, 16, 0.0)];
+        assert_eq!(
+            average_over_windows(&intervals, 10),
+            vec![(2, 4, 1.0), (12, 16, 3.0)]
+        );
+        assert!(average_over_windows(&[], 10).is_empty());
+    }
+}
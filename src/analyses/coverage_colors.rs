@@ -0,0 +1,324 @@
+use std::collections::HashSet;
+use std::str::FromStr;
+
+use crate::{
+    analysis_parameter::AnalysisParameter,
+    graph_broker::GraphBroker,
+    html_report::{AnalysisSection, ReportItem},
+    util::{get_default_plot_downloads, to_id, CountType},
+};
+
+use super::{differential::get_phenotype_sets, Analysis, ConstructibleAnalysis, InputRequirement};
+
+mod palette;
+use palette::Palette;
+
+// color of nodes that are not covered by any path/group
+const UNCOVERED_COLOR: &str = "#d3d3d3";
+// number of classes into which the difference in coverage between two phenotypes is divided; the
+// number is odd such that balanced nodes form a class of their own
+const DIVERGING_CLASSES: usize = 9;
+
+pub struct CoverageColors {
+    parameter: AnalysisParameter,
+    // description of the colored value, e.g., of the phenotypes whose coverage is compared
+    description: Option<String>,
+    // label and color of each class, the first one being that of uncovered nodes
+    classes: Vec<(String, String)>,
+    // name, length, value (coverage or difference in coverage), and class of each node
+    nodes: Vec<(String, u32, String, usize)>,
+}
+
+impl Analysis for CoverageColors {
+    fn get_type(&self) -> String {
+        "CoverageColors".to_string()
+    }
+
+    fn generate_table(&mut self, gb: Option<&GraphBroker>) -> anyhow::Result<String> {
+        log::info!("reporting node colors by coverage");
+        let gb = gb.expect("CoverageColors analysis needs a graph");
+        self.set_colors(gb)?;
+
+        let mut res = String::new();
+        res.push_str(&crate::io::write_metadata_comments()?);
+        if let Some(description) = &self.description {
+            res.push_str(&format!("# {}\n", description));
+        }
+        res.push_str(&format!("# {}\n", self.get_legend_header().join("\t")));
+        for row in self.get_legend() {
+            res.push_str(&format!("# {}\n", row.join("\t")));
+        }
+        // Bandage reads node colors from CSV files with columns Name and Color
+        res.push_str(&format!("Name,Color,{}\n", self.get_value_name()));
+        for (name, _, value, class) in &self.nodes {
+            res.push_str(&format!("{},{},{}\n", name, self.classes[*class].1, value));
+        }
+        Ok(res)
+    }
+
+    fn generate_report_section(
+        &mut self,
+        gb: Option<&GraphBroker>,
+    ) -> anyhow::Result<Vec<AnalysisSection>> {
+        let gb = gb.expect("CoverageColors analysis needs a graph");
+        let table = self.generate_table(Some(gb))?;
+        let table = format!("`{}`", &table);
+        let id_prefix = format!("coverage-colors-{}", to_id(&self.get_run_id(gb)));
+        Ok(vec![AnalysisSection {
+            id: id_prefix.clone(),
+            analysis: "Coverage Colors".to_string(),
+            table: Some(table),
+            run_name: self.get_run_name(gb),
+            run_id: self.get_run_id(gb),
+            countable: CountType::Node.to_string(),
+            items: vec![ReportItem::Table {
+                id: format!("{id_prefix}-legend"),
+                header: self.get_legend_header(),
+                values: self.get_legend(),
+            }],
+            plot_downloads: get_default_plot_downloads(),
+            description: None,
+        }])
+    }
+
+    fn get_graph_requirements(&self) -> HashSet<InputRequirement> {
+        let mut req = HashSet::from([InputRequirement::Node]);
+        if self.get_phenotype().is_some() {
+            req.insert(InputRequirement::AbacusByGroup(CountType::Node));
+        }
+        req
+    }
+}
+
+impl ConstructibleAnalysis for CoverageColors {
+    fn from_parameter(parameter: AnalysisParameter) -> Self {
+        Self {
+            parameter,
+            description: None,
+            classes: Vec::new(),
+            nodes: Vec::new(),
+        }
+    }
+}
+
+impl CoverageColors {
+    fn get_phenotype(&self) -> Option<&str> {
+        if let AnalysisParameter::CoverageColors { phenotype, .. } = &self.parameter {
+            phenotype.as_deref()
+        } else {
+            None
+        }
+    }
+
+    fn get_value_name(&self) -> &str {
+        if self.get_phenotype().is_some() {
+            "Difference"
+        } else {
+            "Coverage"
+        }
+    }
+
+    fn set_colors(&mut self, gb: &GraphBroker) -> anyhow::Result<()> {
+        if !self.nodes.is_empty() {
+            return Ok(());
+        }
+        let (palette, breakpoints, phenotype) = match &self.parameter {
+            AnalysisParameter::CoverageColors {
+                palette,
+                breakpoints,
+                phenotype,
+            } => (palette.clone(), breakpoints.clone(), phenotype.clone()),
+            _ => panic!("CoverageColors analysis needs a coverage colors parameter"),
+        };
+        let palette = match palette {
+            Some(palette) => Palette::from_str(&palette).map_err(|e| anyhow::anyhow!(e))?,
+            None if phenotype.is_some() => Palette::diverging(),
+            None => Palette::default(),
+        };
+        // nodes that are not contained in any group of either phenotype are uncovered, too
+        let uncovered = if phenotype.is_some() { "absent" } else { "0" };
+        self.classes = vec![(uncovered.to_string(), UNCOVERED_COLOR.to_string())];
+        match phenotype {
+            Some(phenotype) => {
+                if !breakpoints.is_empty() {
+                    anyhow::bail!("coverage breakpoints cannot be combined with phenotypes");
+                }
+                self.set_difference_colors(gb, &phenotype, &palette)
+            }
+            None => self.set_coverage_colors(gb, &breakpoints, &palette),
+        }
+    }
+
+    fn set_coverage_colors(
+        &mut self,
+        gb: &GraphBroker,
+        breakpoints: &[usize],
+        palette: &Palette,
+    ) -> anyhow::Result<()> {
+        if breakpoints.windows(2).any(|w| w[0] >= w[1]) {
+            anyhow::bail!("coverage breakpoints must be strictly increasing");
+        }
+        let n = gb.get_group_count();
+        // without breakpoints, nodes are classified into private, shell, and core
+        let breakpoints = if breakpoints.is_empty() {
+            vec![2, n]
+        } else {
+            breakpoints.to_vec()
+        };
+        let classes = get_classes(&breakpoints, n);
+        for ((from, to), color) in classes.iter().zip(palette.sample(classes.len())) {
+            let label = if from == to {
+                from.to_string()
+            } else {
+                format!("{}-{}", from, to)
+            };
+            self.classes.push((label, color));
+        }
+
+        let names = gb.get_node_names();
+        let node_lens = gb.get_node_lens();
+        let coverage = &gb.get_abacus_by_total(CountType::Node).countable;
+        self.nodes = (1..names.len())
+            .map(|i| {
+                let c = coverage[i] as usize;
+                let class = classes
+                    .iter()
+                    .position(|(from, to)| (*from..=*to).contains(&c))
+                    .map(|k| k + 1)
+                    .unwrap_or(0);
+                (names[i].clone(), node_lens[i], c.to_string(), class)
+            })
+            .collect();
+        Ok(())
+    }
+
+    // colors nodes by the fraction of groups of the first phenotype that contain them minus that
+    // of the second phenotype, such that sets of different size are comparable
+    fn set_difference_colors(
+        &mut self,
+        gb: &GraphBroker,
+        phenotype: &str,
+        palette: &Palette,
+    ) -> anyhow::Result<()> {
+        let abacus = gb.get_abacus_by_group();
+        let (labels, [n0, n1], sets) = get_phenotype_sets(phenotype, &abacus.groups)?;
+        log::info!("comparing coverage of nodes in {} vs. {} groups", n0, n1);
+        self.description = Some(format!(
+            "difference: fraction of {} ({} groups) minus fraction of {} ({} groups) that contain the node",
+            labels[0], n0, labels[1], n1
+        ));
+        let width = 2.0 / DIVERGING_CLASSES as f64;
+        for (k, color) in palette.sample(DIVERGING_CLASSES).into_iter().enumerate() {
+            let from = -1.0 + k as f64 * width;
+            self.classes
+                .push((format!("{:.2} to {:.2}", from, from + width), color));
+        }
+
+        let names = gb.get_node_names();
+        let node_lens = gb.get_node_lens();
+        self.nodes = (1..names.len())
+            .map(|node| {
+                let mut present = [0, 0];
+                for group in &abacus.c[abacus.r[node]..abacus.r[node + 1]] {
+                    if let Some(l) = sets[*group as usize] {
+                        present[l] += 1;
+                    }
+                }
+                let difference = present[0] as f64 / n0 as f64 - present[1] as f64 / n1 as f64;
+                let class = if present == [0, 0] {
+                    0
+                } else {
+                    get_diverging_class(difference, DIVERGING_CLASSES) + 1
+                };
+                (
+                    names[node].clone(),
+                    node_lens[node],
+                    format!("{:.3}", difference),
+                    class,
+                )
+            })
+            .collect();
+        Ok(())
+    }
+
+    fn get_legend_header(&self) -> Vec<String> {
+        vec![
+            self.get_value_name().to_lowercase(),
+            "color".to_string(),
+            "nodes".to_string(),
+            "bp".to_string(),
+        ]
+    }
+
+    // label, color, number of nodes, and bp of each class, including uncovered nodes
+    fn get_legend(&self) -> Vec<Vec<String>> {
+        let mut counts = vec![(0, 0); self.classes.len()];
+        for (_, len, _, class) in &self.nodes {
+            counts[*class].0 += 1;
+            counts[*class].1 += *len as usize;
+        }
+        self.classes
+            .iter()
+            .zip(counts)
+            .map(|((label, color), (nodes, bp))| {
+                vec![
+                    label.clone(),
+                    color.clone(),
+                    nodes.to_string(),
+                    bp.to_string(),
+                ]
+            })
+            .collect()
+    }
+
+    fn get_run_name(&self, gb: &GraphBroker) -> String {
+        gb.get_run_name()
+    }
+
+    fn get_run_id(&self, gb: &GraphBroker) -> String {
+        format!("{}-coveragecolors", gb.get_run_id())
+    }
+}
+
+// coverage classes, given by their smallest and largest coverage, that partition the coverages 1
+// to n; each breakpoint is the smallest coverage of a class
+fn get_classes(breakpoints: &[usize], n: usize) -> Vec<(usize, usize)> {
+    let mut starts = vec![1];
+    starts.extend(breakpoints.iter().copied().filter(|b| *b > 1 && *b <= n));
+    starts.dedup();
+    starts
+        .iter()
+        .enumerate()
+        .map(|(i, from)| (*from, starts.get(i + 1).map(|to| to - 1).unwrap_or(n)))
+        .collect()
+}
+
+// class of a difference between -1 and 1, given k classes of equal width
+fn get_diverging_class(difference: f64, k: usize) -> usize {
+    usize::min(
+        ((difference + 1.0) / 2.0 * k as f64).floor() as usize,
+        k - 1,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_get_classes() {
+        assert_eq!(get_classes(&[2, 10], 10), vec![(1, 1), (2, 9), (10, 10)]);
+        assert_eq!(get_classes(&[2, 2], 2), vec![(1, 1), (2, 2)]);
+        assert_eq!(get_classes(&[2, 1], 1), vec![(1, 1)]);
+        assert_eq!(get_classes(&[5, 50], 20), vec![(1, 4), (5, 20)]);
+    }
+
+    #[test]
+    fn test_get_diverging_class() {
+        assert_eq!(get_diverging_class(-1.0, 9), 0);
+        assert_eq!(get_diverging_class(0.0, 9), 4);
+        assert_eq!(get_diverging_class(0.1, 9), 4);
+        assert_eq!(get_diverging_class(-0.5, 9), 2);
+        assert_eq!(get_diverging_class(1.0, 9), 8);
+    }
+}
@@ -0,0 +1,178 @@
+use std::fmt;
+use std::str::FromStr;
+
+/// Named colormaps, given by evenly spaced color stops
+const COLORMAPS: &[(&str, &[&str])] = &[
+    (
+        "viridis",
+        &[
+            "#440154", "#482878", "#3e4989", "#31688e", "#26828e", "#1f9e89", "#35b779", "#6ece58",
+            "#b5de2b", "#fde725",
+        ],
+    ),
+    (
+        "magma",
+        &[
+            "#000004", "#180f3d", "#440f76", "#721f81", "#9e2f7f", "#cd4071", "#f1605d", "#fd9668",
+            "#feca8d", "#fcfdbf",
+        ],
+    ),
+    (
+        "plasma",
+        &[
+            "#0d0887", "#46039f", "#7201a8", "#9c179e", "#bd3786", "#d8576b", "#ed7953", "#fb9f3a",
+            "#fdca26", "#f0f921",
+        ],
+    ),
+    (
+        "blues",
+        &[
+            "#f7fbff", "#deebf7", "#c6dbef", "#9ecae1", "#6baed6", "#4292c6", "#2171b5", "#08519c",
+            "#08306b",
+        ],
+    ),
+    (
+        "reds",
+        &[
+            "#fff5f0", "#fee0d2", "#fcbba1", "#fc9272", "#fb6a4a", "#ef3b2c", "#cb181d", "#a50f15",
+            "#67000d",
+        ],
+    ),
+    // diverging colormaps, with a light color at their center
+    (
+        "rdbu",
+        &[
+            "#b2182b", "#d6604d", "#f4a582", "#fddbc7", "#f7f7f7", "#d1e5f0", "#92c5de", "#4393c3",
+            "#2166ac",
+        ],
+    ),
+    (
+        "piyg",
+        &[
+            "#c51b7d", "#de77ae", "#f1b6da", "#fde0ef", "#f7f7f7", "#e6f5d0", "#b8e186", "#7fbc41",
+            "#4d9221",
+        ],
+    ),
+    (
+        "brbg",
+        &[
+            "#8c510a", "#bf812d", "#dfc27d", "#f6e8c3", "#f5f5f5", "#c7eae5", "#80cdc1", "#35978f",
+            "#01665e",
+        ],
+    ),
+];
+
+/// Color gradient through the given color stops, either given as list of hex colors or by the
+/// name of a colormap
+#[derive(Debug, Clone, PartialEq)]
+pub struct Palette {
+    stops: Vec<[u8; 3]>,
+}
+
+impl Palette {
+    /// Default palette for signed values, running from red through white to blue
+    pub fn diverging() -> Self {
+        Self::from_str("rdbu").unwrap()
+    }
+
+    /// Color at position t (between 0 and 1) of the gradient
+    pub fn at(&self, t: f64) -> String {
+        let pos = t.clamp(0.0, 1.0) * (self.stops.len() - 1) as f64;
+        let i = usize::min(pos.floor() as usize, self.stops.len() - 1);
+        let j = usize::min(i + 1, self.stops.len() - 1);
+        let f = pos - i as f64;
+        let mut rgb = [0; 3];
+        for (k, x) in rgb.iter_mut().enumerate() {
+            *x = (self.stops[i][k] as f64 * (1.0 - f) + self.stops[j][k] as f64 * f).round() as u8;
+        }
+        to_hex(&rgb)
+    }
+
+    /// n colors evenly spaced along the gradient, i.e., exactly the color stops if there are n
+    pub fn sample(&self, n: usize) -> Vec<String> {
+        match n {
+            0 => Vec::new(),
+            1 => vec![self.at(0.0)],
+            _ => (0..n).map(|i| self.at(i as f64 / (n - 1) as f64)).collect(),
+        }
+    }
+}
+
+impl Default for Palette {
+    fn default() -> Self {
+        Self::from_str("viridis").unwrap()
+    }
+}
+
+impl FromStr for Palette {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let colormap = COLORMAPS
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case(s.trim()));
+        let stops: Vec<[u8; 3]> = match colormap {
+            Some((_, colors)) => colors.iter().map(|c| parse_hex(c)).collect(),
+            None if s.contains('#') || s.contains(',') => {
+                s.split(',').map(|c| parse_hex(c.trim())).collect()
+            }
+            None => Err(format!(
+                "unknown colormap `{}`, use a comma-separated list of hex colors or one of {}",
+                s,
+                COLORMAPS
+                    .iter()
+                    .map(|(name, _)| *name)
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )),
+        }?;
+        if stops.is_empty() {
+            return Err("palette must contain at least one color".to_string());
+        }
+        Ok(Self { stops })
+    }
+}
+
+impl fmt::Display for Palette {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        let colors: Vec<String> = self.stops.iter().map(to_hex).collect();
+        write!(formatter, "{}", colors.join(","))
+    }
+}
+
+// color given as #RRGGBB (the leading # is optional)
+fn parse_hex(s: &str) -> Result<[u8; 3], String> {
+    let hex = s.strip_prefix('#').unwrap_or(s);
+    if hex.len() != 6 || !hex.is_ascii() {
+        return Err(format!("`{}` is not a color of the form #RRGGBB", s));
+    }
+    let mut rgb = [0; 3];
+    for (i, x) in rgb.iter_mut().enumerate() {
+        *x = u8::from_str_radix(&hex[2 * i..2 * i + 2], 16)
+            .map_err(|_| format!("`{}` is not a color of the form #RRGGBB", s))?;
+    }
+    Ok(rgb)
+}
+
+fn to_hex(rgb: &[u8; 3]) -> String {
+    format!("#{:02x}{:02x}{:02x}", rgb[0], rgb[1], rgb[2])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_palette_from_str() {
+        let palette = Palette::from_str("#000000, ffffff").unwrap();
+        assert_eq!(palette.to_string(), "#000000,#ffffff");
+        assert_eq!(palette.sample(3), vec!["#000000", "#808080", "#ffffff"]);
+        assert_eq!(
+            Palette::from_str("Viridis").unwrap().sample(2),
+            vec!["#440154", "#fde725"]
+        );
+        assert!(Palette::from_str("rainbow").is_err());
+        assert!(Palette::from_str("#12345").is_err());
+        assert!(Palette::from_str("#12345g,#000000").is_err());
+    }
+}
@@ -6,6 +6,7 @@ use crate::{
     html_report::{AnalysisSection, ReportItem},
     io::write_table_with_start_index,
     util::get_default_plot_downloads,
+    util::to_id,
     util::CountType,
 };
 
@@ -66,12 +67,7 @@ impl Analysis for CoverageLine {
         let gb = gb.unwrap();
         let table = self.generate_table(Some(gb))?;
         let table = format!("`{}`", &table);
-        let id_prefix = format!(
-            "coverage-line-{}",
-            self.get_run_id(gb)
-                .to_lowercase()
-                .replace(&[' ', '|', '\\'], "-")
-        );
+        let id_prefix = format!("coverage-line-{}", to_id(&self.get_run_id(gb)));
         let coverage_line_tabs = gb
             .get_hists()
             .iter()
@@ -102,6 +98,7 @@ impl Analysis for CoverageLine {
                         log_y: true,
                     }],
                     plot_downloads: get_default_plot_downloads(),
+                    description: None,
                 }
             })
             .collect::<Vec<_>>();
@@ -131,6 +128,7 @@ impl CoverageLine {
             CountType::Bp => HashSet::from([InputRequirement::Bp]),
             CountType::Node => HashSet::from([InputRequirement::Node]),
             CountType::Edge => HashSet::from([InputRequirement::Edge]),
+            CountType::Bubble => HashSet::from([InputRequirement::Bubble]),
             CountType::All => HashSet::from([
                 InputRequirement::Bp,
                 InputRequirement::Node,
@@ -0,0 +1,211 @@
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+
+use crate::{
+    analysis_parameter::AnalysisParameter,
+    graph_broker::GraphBroker,
+    html_report::{AnalysisSection, ReportItem},
+    util::{get_default_plot_downloads, to_id, CountType},
+};
+
+use super::node_coverage::PathCoverage;
+use super::{Analysis, ConstructibleAnalysis, InputRequirement};
+
+pub struct Depth {
+    // total number of traversals of each node by all paths, including repeated traversals by the
+    // same path (index 0 is unused)
+    depths: Vec<u64>,
+    path_count: usize,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct DepthClass {
+    depth: u64,
+    nodes: usize,
+    bp: u64,
+}
+
+impl Analysis for Depth {
+    fn get_type(&self) -> String {
+        "Depth".to_string()
+    }
+
+    fn generate_table(&mut self, gb: Option<&GraphBroker>) -> anyhow::Result<String> {
+        log::info!("reporting traversal depth of nodes");
+        let gb = gb.expect("Depth analysis needs a graph");
+        self.set_depths(gb)?;
+        let node_lens = gb.get_node_lens();
+
+        let mut res = String::new();
+        res.push_str(&crate::io::write_metadata_comments()?);
+        // nodes traversed more often than there are paths must be traversed repeatedly by at
+        // least one path, which hints at collapsed repeats
+        let (nodes, bp) = self
+            .depths
+            .iter()
+            .zip(node_lens)
+            .skip(1)
+            .filter(|(d, _)| **d > self.path_count as u64)
+            .fold((0, 0), |(n, b), (_, l)| (n + 1, b + *l as u64));
+        res.push_str(&format!(
+            "# {} nodes ({} bp) with depth > {} paths\n",
+            nodes, bp, self.path_count
+        ));
+        res.push_str("depth\tnodes\tbp\n");
+        for class in get_depth_distribution(&self.depths, node_lens) {
+            res.push_str(&format!("{}\t{}\t{}\n", class.depth, class.nodes, class.bp));
+        }
+        Ok(res)
+    }
+
+    fn generate_report_section(
+        &mut self,
+        gb: Option<&GraphBroker>,
+    ) -> anyhow::Result<Vec<AnalysisSection>> {
+        let gb = gb.expect("Depth analysis needs a graph");
+        let table = self.generate_table(Some(gb))?;
+        let table = format!("`{}`", &table);
+        let id_prefix = format!("depth-{}", to_id(&self.get_run_id(gb)));
+        let distribution = get_depth_distribution(&self.depths, gb.get_node_lens());
+        let points = get_length_depth_pairs(&self.depths, gb.get_node_lens());
+
+        Ok(vec![AnalysisSection {
+            id: id_prefix.clone(),
+            analysis: "Depth".to_string(),
+            table: Some(table),
+            run_name: self.get_run_name(gb),
+            run_id: self.get_run_id(gb),
+            countable: CountType::Node.to_string(),
+            items: vec![
+                ReportItem::Bar {
+                    id: format!("{id_prefix}-nodes"),
+                    name: gb.get_fname(),
+                    x_label: "depth".to_string(),
+                    y_label: "#nodes".to_string(),
+                    labels: distribution.iter().map(|c| c.depth.to_string()).collect(),
+                    values: distribution.iter().map(|c| c.nodes as f64).collect(),
+                    log_toggle: true,
+                },
+                ReportItem::Bar {
+                    id: format!("{id_prefix}-bp"),
+                    name: gb.get_fname(),
+                    x_label: "depth".to_string(),
+                    y_label: "#bps".to_string(),
+                    labels: distribution.iter().map(|c| c.depth.to_string()).collect(),
+                    values: distribution.iter().map(|c| c.bp as f64).collect(),
+                    log_toggle: true,
+                },
+                ReportItem::Scatter {
+                    id: format!("{id_prefix}-length"),
+                    name: gb.get_fname(),
+                    x_label: "node length (bp)".to_string(),
+                    y_label: "depth".to_string(),
+                    x_values: points.iter().map(|p| p.0 as f64).collect(),
+                    y_values: points.iter().map(|p| p.1 as f64).collect(),
+                    fit: None,
+                    prediction: None,
+                    annotation: None,
+                    log_x: true,
+                    log_y: true,
+                },
+            ],
+            plot_downloads: get_default_plot_downloads(),
+            description: None,
+        }])
+    }
+
+    fn get_graph_requirements(&self) -> HashSet<InputRequirement> {
+        HashSet::from([InputRequirement::Node])
+    }
+}
+
+impl ConstructibleAnalysis for Depth {
+    fn from_parameter(_parameter: AnalysisParameter) -> Self {
+        Self {
+            depths: Vec::new(),
+            path_count: 0,
+        }
+    }
+}
+
+impl Depth {
+    fn set_depths(&mut self, gb: &GraphBroker) -> anyhow::Result<()> {
+        if !self.depths.is_empty() {
+            return Ok(());
+        }
+        let coverage = PathCoverage::from_graph(gb)?;
+        self.depths = coverage.traversals;
+        self.path_count = coverage.path_count;
+        Ok(())
+    }
+
+    fn get_run_name(&self, gb: &GraphBroker) -> String {
+        gb.get_run_name()
+    }
+
+    fn get_run_id(&self, gb: &GraphBroker) -> String {
+        format!("{}-depth", gb.get_run_id())
+    }
+}
+
+// number of nodes and their bp of each occurring depth (skipping the unused id 0), sorted by depth
+fn get_depth_distribution(depths: &[u64], node_lens: &[u32]) -> Vec<DepthClass> {
+    let mut classes: BTreeMap<u64, (usize, u64)> = BTreeMap::new();
+    for (depth, len) in depths.iter().zip(node_lens).skip(1) {
+        let class = classes.entry(*depth).or_insert((0, 0));
+        class.0 += 1;
+        class.1 += *len as u64;
+    }
+    classes
+        .into_iter()
+        .map(|(depth, (nodes, bp))| DepthClass { depth, nodes, bp })
+        .collect()
+}
+
+// distinct pairs of length and depth of the traversed nodes, which keeps the scatter plot small
+// also for large graphs
+fn get_length_depth_pairs(depths: &[u64], node_lens: &[u32]) -> Vec<(u32, u64)> {
+    depths
+        .iter()
+        .zip(node_lens)
+        .skip(1)
+        .filter(|(depth, _)| **depth > 0)
+        .map(|(depth, len)| (*len, *depth))
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_get_depth_distribution() {
+        let depths = vec![0, 2, 0, 5, 2, 2];
+        let node_lens = vec![0, 10, 3, 1, 10, 4];
+        assert_eq!(
+            get_depth_distribution(&depths, &node_lens),
+            vec![
+                DepthClass {
+                    depth: 0,
+                    nodes: 1,
+                    bp: 3
+                },
+                DepthClass {
+                    depth: 2,
+                    nodes: 3,
+                    bp: 24
+                },
+                DepthClass {
+                    depth: 5,
+                    nodes: 1,
+                    bp: 1
+                },
+            ]
+        );
+        assert_eq!(
+            get_length_depth_pairs(&depths, &node_lens),
+            vec![(1, 5), (4, 2), (10, 2)]
+        );
+    }
+}
@@ -0,0 +1,496 @@
+use std::collections::HashSet;
+
+use crate::{
+    analysis_parameter::{AnalysisParameter, DifferentialTest, PValueCorrection},
+    graph_broker::{GraphBroker, PathSegment},
+    html_report::{AnalysisSection, ReportItem},
+    io::get_group_labels,
+    util::get_default_plot_downloads,
+    util::to_id,
+    util::CountType,
+};
+
+use super::{Analysis, ConstructibleAnalysis, InputRequirement};
+
+// maximum number of nodes that are listed in the report
+const MAX_REPORT_NODES: usize = 1000;
+// maximum number of intervals of the reference track that are shown in the report; larger tracks
+// are reduced to the maximum value within windows of equal size
+const MAX_REPORT_INTERVALS: usize = 5000;
+
+pub struct Differential {
+    parameter: AnalysisParameter,
+    // phenotype labels of the two group sets and their number of groups
+    labels: Vec<String>,
+    group_counts: [usize; 2],
+    // tested nodes ranked by p-value
+    results: Option<Vec<TestResult>>,
+}
+
+struct TestResult {
+    node: usize,
+    // number of groups of either set that contain the node
+    present: [usize; 2],
+    p: f64,
+    p_adjusted: f64,
+}
+
+impl Analysis for Differential {
+    fn get_type(&self) -> String {
+        "Differential".to_string()
+    }
+
+    fn generate_table(&mut self, gb: Option<&GraphBroker>) -> anyhow::Result<String> {
+        log::info!("reporting differential presence of nodes");
+        let gb = gb.expect("Differential analysis needs a graph");
+        self.set_results(gb)?;
+        let names = gb.get_node_names();
+
+        let mut res = String::new();
+        res.push_str(&crate::io::write_metadata_comments()?);
+        res.push_str(&format!(
+            "# {} ({} groups) vs. {} ({} groups), {} test, {} correction\n",
+            self.labels[0],
+            self.group_counts[0],
+            self.labels[1],
+            self.group_counts[1],
+            self.get_test(),
+            self.get_correction()
+        ));
+        res.push_str(&format!(
+            "rank\tnode\t{}\t{}\tp-value\tadjusted p-value\n",
+            self.labels[0], self.labels[1]
+        ));
+        for (i, r) in self.results.as_ref().unwrap().iter().enumerate() {
+            res.push_str(&format!(
+                "{}\t{}\t{}\t{}\t{:e}\t{:e}\n",
+                i + 1,
+                names[r.node],
+                r.present[0],
+                r.present[1],
+                r.p,
+                r.p_adjusted
+            ));
+        }
+        Ok(res)
+    }
+
+    fn generate_report_section(
+        &mut self,
+        gb: Option<&GraphBroker>,
+    ) -> anyhow::Result<Vec<AnalysisSection>> {
+        let gb = gb.expect("Differential analysis needs a graph");
+        let table = self.generate_table(Some(gb))?;
+        let table = format!("`{}`", &table);
+        let id_prefix = format!("differential-{}", to_id(&self.get_run_id(gb)));
+        let names = gb.get_node_names();
+
+        let mut items = vec![ReportItem::Table {
+            id: format!("{id_prefix}-nodes"),
+            header: vec![
+                "rank".to_string(),
+                "node".to_string(),
+                format!("{} ({})", self.labels[0], self.group_counts[0]),
+                format!("{} ({})", self.labels[1], self.group_counts[1]),
+                "p-value".to_string(),
+                "adjusted p-value".to_string(),
+            ],
+            values: self
+                .results
+                .as_ref()
+                .unwrap()
+                .iter()
+                .take(MAX_REPORT_NODES)
+                .enumerate()
+                .map(|(i, r)| {
+                    vec![
+                        (i + 1).to_string(),
+                        names[r.node].clone(),
+                        r.present[0].to_string(),
+                        r.present[1].to_string(),
+                        format!("{:.3e}", r.p),
+                        format!("{:.3e}", r.p_adjusted),
+                    ]
+                })
+                .collect(),
+        }];
+        if let Some(reference) = self.get_reference() {
+            let (sequence, intervals) = self.get_reference_track(gb, reference)?;
+            items.push(ReportItem::Chromosomal {
+                id: format!("{id_prefix}-manhattan"),
+                name: gb.get_fname(),
+                sequence,
+                y_label: "-log10(p)".to_string(),
+                starts: intervals.iter().map(|x| x.0).collect(),
+                ends: intervals.iter().map(|x| x.1).collect(),
+                values: intervals.iter().map(|x| x.2).collect(),
+                tracks: Vec::new(),
+                annotations: Vec::new(),
+            });
+        }
+
+        Ok(vec![AnalysisSection {
+            id: id_prefix,
+            analysis: "Differential Presence".to_string(),
+            table: Some(table),
+            run_name: self.get_run_name(gb),
+            run_id: self.get_run_id(gb),
+            countable: CountType::Node.to_string(),
+            items,
+            plot_downloads: get_default_plot_downloads(),
+            description: None,
+        }])
+    }
+
+    fn get_graph_requirements(&self) -> HashSet<InputRequirement> {
+        HashSet::from([
+            InputRequirement::AbacusByGroup(CountType::Node),
+            InputRequirement::Node,
+        ])
+    }
+}
+
+impl ConstructibleAnalysis for Differential {
+    fn from_parameter(parameter: AnalysisParameter) -> Self {
+        Self {
+            parameter,
+            labels: Vec::new(),
+            group_counts: [0, 0],
+            results: None,
+        }
+    }
+}
+
+impl Differential {
+    fn get_phenotype(&self) -> &str {
+        if let AnalysisParameter::Differential { phenotype, .. } = &self.parameter {
+            phenotype
+        } else {
+            panic!("Differential analysis needs differential parameter");
+        }
+    }
+
+    fn get_reference(&self) -> Option<&str> {
+        if let AnalysisParameter::Differential { reference, .. } = &self.parameter {
+            reference.as_deref()
+        } else {
+            None
+        }
+    }
+
+    fn get_test(&self) -> DifferentialTest {
+        if let AnalysisParameter::Differential { test, .. } = &self.parameter {
+            *test
+        } else {
+            DifferentialTest::default()
+        }
+    }
+
+    fn get_correction(&self) -> PValueCorrection {
+        if let AnalysisParameter::Differential { correction, .. } = &self.parameter {
+            *correction
+        } else {
+            PValueCorrection::default()
+        }
+    }
+
+    fn get_group_sets(&mut self, groups: &[String]) -> anyhow::Result<Vec<Option<usize>>> {
+        let (labels, group_counts, sets) = get_phenotype_sets(self.get_phenotype(), groups)?;
+        self.labels = labels;
+        self.group_counts = group_counts;
+        Ok(sets)
+    }
+
+    fn set_results(&mut self, gb: &GraphBroker) -> anyhow::Result<()> {
+        if self.results.is_some() {
+            return Ok(());
+        }
+        let abacus = gb.get_abacus_by_group();
+        let sets = self.get_group_sets(&abacus.groups)?;
+        let test = self.get_test();
+        let [n0, n1] = self.group_counts;
+        log::info!(
+            "testing differential presence of nodes in {} vs. {} groups",
+            n0,
+            n1
+        );
+
+        let mut results: Vec<TestResult> = Vec::new();
+        for node in 1..abacus.r.len() - 1 {
+            let mut present = [0, 0];
+            for group in &abacus.c[abacus.r[node]..abacus.r[node + 1]] {
+                if let Some(l) = sets[*group as usize] {
+                    present[l] += 1;
+                }
+            }
+            // nodes that are not contained in any group of either set are not tested
+            if present == [0, 0] {
+                continue;
+            }
+            let (a, b, c, d) = (present[0], n0 - present[0], present[1], n1 - present[1]);
+            let p = match test {
+                DifferentialTest::Fisher => fisher_exact(a, b, c, d),
+                DifferentialTest::ChiSquare => chi_square(a, b, c, d),
+            };
+            results.push(TestResult {
+                node,
+                present,
+                p,
+                p_adjusted: p,
+            });
+        }
+
+        let adjusted = adjust_p_values(
+            &results.iter().map(|r| r.p).collect::<Vec<_>>(),
+            self.get_correction(),
+        );
+        for (r, p) in results.iter_mut().zip(adjusted) {
+            r.p_adjusted = p;
+        }
+        results.sort_by(|x, y| x.p.partial_cmp(&y.p).unwrap().then(x.node.cmp(&y.node)));
+        self.results = Some(results);
+        Ok(())
+    }
+
+    // -log10 of the p-value of the nodes along the reference path
+    fn get_reference_track(
+        &self,
+        gb: &GraphBroker,
+        reference: &str,
+    ) -> anyhow::Result<(String, Vec<(usize, usize, f64)>)> {
+        let reference = PathSegment::from_str(reference);
+        let mut fragments = gb.get_path_steps(&reference)?;
+        if fragments.is_empty() {
+            anyhow::bail!(
+                "reference path {} does not exist in graph {}",
+                reference,
+                gb.get_fname()
+            );
+        }
+        fragments.sort_by_key(|(path_seg, _)| path_seg.start.unwrap_or(0));
+        let sequence = reference
+            .seqid
+            .clone()
+            .unwrap_or_else(|| reference.clear_coords().id());
+
+        let mut scores = vec![0.0; gb.get_node_count() + 1];
+        for r in self.results.as_ref().unwrap() {
+            scores[r.node] = p_value_score(r.p);
+        }
+        let node_lens = gb.get_node_lens();
+        let mut intervals: Vec<(usize, usize, f64)> = Vec::new();
+        for (path_seg, steps) in fragments {
+            let mut pos = path_seg.start.unwrap_or(0);
+            for (node, _) in steps {
+                let end = pos + node_lens[node.0 as usize] as usize;
+                let value = scores[node.0 as usize];
+                match intervals.last_mut() {
+                    Some(last) if last.1 == pos && last.2 == value => last.1 = end,
+                    _ => intervals.push((pos, end, value)),
+                }
+                pos = end;
+            }
+        }
+        if intervals.len() > MAX_REPORT_INTERVALS {
+            let length = intervals.last().map(|x| x.1).unwrap_or(0)
+                - intervals.first().map(|x| x.0).unwrap_or(0);
+            intervals = max_over_windows(&intervals, length / MAX_REPORT_INTERVALS + 1);
+        }
+        Ok((sequence, intervals))
+    }
+
+    fn get_run_name(&self, gb: &GraphBroker) -> String {
+        gb.get_run_name()
+    }
+
+    fn get_run_id(&self, gb: &GraphBroker) -> String {
+        format!("{}-differential", gb.get_run_id())
+    }
+}
+
+/// Assigns each of the given groups to one of the two phenotypes of the phenotype file, or to none
+/// if it is not listed; returns the phenotype labels, their number of groups, and the assignment
+pub fn get_phenotype_sets(
+    file: &str,
+    groups: &[String],
+) -> anyhow::Result<(Vec<String>, [usize; 2], Vec<Option<usize>>)> {
+    let (labels, sets) = get_group_labels(file, "phenotype", groups)?;
+    if labels.len() != 2 {
+        anyhow::bail!(
+            "phenotype file {} must assign groups to exactly two phenotypes, but has {}",
+            file,
+            labels.len()
+        );
+    }
+    let group_counts = [0, 1].map(|l| sets.iter().filter(|s| **s == Some(l)).count());
+    if group_counts.contains(&0) {
+        anyhow::bail!(
+            "phenotype {} of file {} has no group in the graph",
+            labels[group_counts.iter().position(|c| *c == 0).unwrap()],
+            file
+        );
+    }
+    Ok((labels, group_counts, sets))
+}
+
+// reduces intervals to the maximum value within windows of the given size, such that peaks remain
+// visible; windows are aligned to multiples of the window size
+fn max_over_windows(intervals: &[(usize, usize, f64)], window: usize) -> Vec<(usize, usize, f64)> {
+    let mut res: Vec<(usize, usize, f64)> = Vec::new();
+    for &(start, end, value) in intervals {
+        let mut pos = start;
+        while pos < end {
+            let window_start = pos / window * window;
+            let until = end.min(window_start + window);
+            match res.last_mut() {
+                Some(last) if last.0 / window * window == window_start => {
+                    last.1 = until;
+                    last.2 = last.2.max(value);
+                }
+                _ => res.push((pos, until, value)),
+            }
+            pos = until;
+        }
+    }
+    res
+}
+
+fn ln_factorials(n: usize) -> Vec<f64> {
+    let mut res = vec![0.0; n + 1];
+    for i in 1..=n {
+        res[i] = res[i - 1] + (i as f64).ln();
+    }
+    res
+}
+
+// two-sided p-value of Fisher's exact test of the 2x2 contingency table [[a, b], [c, d]], i.e.,
+// the probability of all tables with the same margins that are at most as likely as the observed
+fn fisher_exact(a: usize, b: usize, c: usize, d: usize) -> f64 {
+    let n = a + b + c + d;
+    let (row, col) = (a + b, a + c);
+    let lf = ln_factorials(n);
+    let ln_p = |x: usize| {
+        lf[row] + lf[n - row] + lf[col] + lf[n - col]
+            - lf[x]
+            - lf[row - x]
+            - lf[col - x]
+            - lf[n + x - row - col]
+            - lf[n]
+    };
+    let observed = ln_p(a);
+    // tolerance for tables that are as likely as the observed one up to rounding errors
+    let threshold = observed + 1e-7;
+    let p: f64 = ((row + col).saturating_sub(n)..=row.min(col))
+        .map(ln_p)
+        .filter(|l| *l <= threshold)
+        .map(f64::exp)
+        .sum();
+    p.min(1.0)
+}
+
+// p-value of Pearson's chi-square test of independence of the 2x2 contingency table
+// [[a, b], [c, d]]; tables with an empty row or column do not deviate from independence
+fn chi_square(a: usize, b: usize, c: usize, d: usize) -> f64 {
+    let (a, b, c, d) = (a as f64, b as f64, c as f64, d as f64);
+    let n = a + b + c + d;
+    let denominator = (a + b) * (c + d) * (a + c) * (b + d);
+    if denominator == 0.0 {
+        return 1.0;
+    }
+    let statistic = n * (a * d - b * c).powi(2) / denominator;
+    // survival function of the chi-square distribution with one degree of freedom
+    erfc((statistic / 2.0).sqrt())
+}
+
+// complementary error function with fractional error below 1.2e-7 (Numerical Recipes, erfcc)
+fn erfc(x: f64) -> f64 {
+    let z = x.abs();
+    let t = 1.0 / (1.0 + 0.5 * z);
+    let r = t
+        * (-z * z - 1.265_512_23
+            + t * (1.000_023_68
+                + t * (0.374_091_96
+                    + t * (0.096_784_18
+                        + t * (-0.186_288_06
+                            + t * (0.278_868_07
+                                + t * (-1.135_203_98
+                                    + t * (1.488_515_87
+                                        + t * (-0.822_152_23 + t * 0.170_872_77)))))))))
+            .exp();
+    if x >= 0.0 {
+        r
+    } else {
+        2.0 - r
+    }
+}
+
+fn adjust_p_values(p: &[f64], correction: PValueCorrection) -> Vec<f64> {
+    let m = p.len() as f64;
+    match correction {
+        PValueCorrection::None => p.to_vec(),
+        PValueCorrection::Bonferroni => p.iter().map(|x| (x * m).min(1.0)).collect(),
+        PValueCorrection::BenjaminiHochberg => {
+            let mut order: Vec<usize> = (0..p.len()).collect();
+            order.sort_by(|i, j| p[*i].partial_cmp(&p[*j]).unwrap());
+            let mut res = vec![1.0; p.len()];
+            let mut min = 1.0_f64;
+            for (rank, i) in order.into_iter().enumerate().rev() {
+                min = min.min(p[i] * m / (rank + 1) as f64);
+                res[i] = min;
+            }
+            res
+        }
+    }
+}
+
+// -log10(p), with p-values that underflow to 0 clamped to the smallest positive value so that the
+// score stays finite
+fn p_value_score(p: f64) -> f64 {
+    -p.max(f64::MIN_POSITIVE).log10()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(x: f64, y: f64) {
+        assert!((x - y).abs() < 1e-6, "{} != {}", x, y);
+    }
+
+    #[test]
+    fn test_fisher_exact() {
+        // lady tasting tea
+        assert_close(fisher_exact(3, 1, 1, 3), 0.485_714_3);
+        assert_close(fisher_exact(4, 0, 0, 4), 0.028_571_4);
+        assert_close(fisher_exact(10, 0, 10, 0), 1.0);
+    }
+
+    #[test]
+    fn test_chi_square() {
+        // statistic of 4
+        assert_close(chi_square(30, 20, 20, 30), 0.045_500_3);
+        assert_close(chi_square(5, 0, 5, 0), 1.0);
+        assert_close(erfc(0.0), 1.0);
+    }
+
+    #[test]
+    fn test_p_value_score() {
+        assert_close(p_value_score(0.01), 2.0);
+        assert_close(p_value_score(1.0), 0.0);
+        assert!(p_value_score(0.0).is_finite());
+        assert_close(p_value_score(0.0), -f64::MIN_POSITIVE.log10());
+    }
+
+    #[test]
+    fn test_adjust_p_values() {
+        let p = [0.01, 0.04, 0.03, 0.5];
+        let bh = adjust_p_values(&p, PValueCorrection::BenjaminiHochberg);
+        for (x, y) in bh.iter().zip([0.04, 0.053_333_3, 0.053_333_3, 0.5]) {
+            assert_close(*x, y);
+        }
+        let bonferroni = adjust_p_values(&p, PValueCorrection::Bonferroni);
+        for (x, y) in bonferroni.iter().zip([0.04, 0.16, 0.12, 1.0]) {
+            assert_close(*x, y);
+        }
+    }
+}
@@ -0,0 +1,283 @@
+use std::collections::HashSet;
+
+use crate::{
+    analysis_parameter::AnalysisParameter,
+    graph_broker::{GraphBroker, ItemId, Orientation},
+    html_report::{AnalysisSection, ReportItem},
+    util::get_default_plot_downloads,
+    util::to_id,
+};
+
+use super::{Analysis, ConstructibleAnalysis, InputRequirement};
+
+// maximum number of groups of duplicated nodes that are listed in the report
+const MAX_REPORT_GROUPS: usize = 1000;
+
+pub struct Duplicates {
+    groups: Option<Vec<Vec<(ItemId, Orientation)>>>,
+}
+
+impl Analysis for Duplicates {
+    fn get_type(&self) -> String {
+        "Duplicates".to_string()
+    }
+
+    fn generate_table(&mut self, gb: Option<&GraphBroker>) -> anyhow::Result<String> {
+        log::info!("reporting duplicated nodes");
+        let gb = gb.expect("Duplicates analysis needs a graph");
+        self.set_groups(gb)?;
+        let names = gb.get_node_names();
+        let node_lens = gb.get_node_lens();
+
+        let mut res = String::new();
+        res.push_str(&crate::io::write_metadata_comments()?);
+        for row in self.get_summary(gb) {
+            res.push_str(&format!("# {}\n", row.join("\t")));
+        }
+        res.push_str("group\tnode\torientation\tlength\n");
+        for (i, group) in self.groups.as_ref().unwrap().iter().enumerate() {
+            for (id, o) in group {
+                res.push_str(&format!(
+                    "{}\t{}\t{}\t{}\n",
+                    i + 1,
+                    names[id.0 as usize],
+                    if o == &Orientation::Forward { '+' } else { '-' },
+                    node_lens[id.0 as usize]
+                ));
+            }
+        }
+        Ok(res)
+    }
+
+    fn generate_report_section(
+        &mut self,
+        gb: Option<&GraphBroker>,
+    ) -> anyhow::Result<Vec<AnalysisSection>> {
+        let gb = gb.expect("Duplicates analysis needs a graph");
+        let table = self.generate_table(Some(gb))?;
+        let table = format!("`{}`", &table);
+        let id_prefix = format!("duplicates-{}", to_id(&self.get_run_id(gb)));
+        let summary = self.get_summary(gb);
+        let names = gb.get_node_names();
+        let node_lens = gb.get_node_lens();
+
+        // groups with the most redundant bps first
+        let mut groups: Vec<&Vec<(ItemId, Orientation)>> =
+            self.groups.as_ref().unwrap().iter().collect();
+        groups.sort_by_key(|g| {
+            std::cmp::Reverse((g.len() - 1) * node_lens[g[0].0 .0 as usize] as usize)
+        });
+        let group_values = groups
+            .into_iter()
+            .take(MAX_REPORT_GROUPS)
+            .map(|g| {
+                vec![
+                    g.iter()
+                        .map(|(id, o)| format!("{}{}", o, names[id.0 as usize]))
+                        .collect::<Vec<_>>()
+                        .join(" "),
+                    g.len().to_string(),
+                    node_lens[g[0].0 .0 as usize].to_string(),
+                ]
+            })
+            .collect();
+
+        Ok(vec![AnalysisSection {
+            id: id_prefix.clone(),
+            analysis: "Duplicated Nodes".to_string(),
+            table: Some(table),
+            run_name: self.get_run_name(gb),
+            run_id: self.get_run_id(gb),
+            countable: "node".to_string(),
+            items: vec![
+                ReportItem::Table {
+                    id: format!("{id_prefix}-summary"),
+                    header: summary[0].clone(),
+                    values: summary[1..].to_vec(),
+                },
+                ReportItem::Table {
+                    id: format!("{id_prefix}-groups"),
+                    header: vec![
+                        "nodes".to_string(),
+                        "count".to_string(),
+                        "length".to_string(),
+                    ],
+                    values: group_values,
+                },
+            ],
+            plot_downloads: get_default_plot_downloads(),
+            description: None,
+        }])
+    }
+
+    fn get_graph_requirements(&self) -> HashSet<InputRequirement> {
+        HashSet::from([InputRequirement::Node])
+    }
+}
+
+impl ConstructibleAnalysis for Duplicates {
+    fn from_parameter(_parameter: AnalysisParameter) -> Self {
+        Self { groups: None }
+    }
+}
+
+impl Duplicates {
+    fn set_groups(&mut self, gb: &GraphBroker) -> anyhow::Result<()> {
+        if self.groups.is_none() {
+            self.groups = Some(gb.get_duplicate_nodes()?);
+        }
+        Ok(())
+    }
+
+    // number of nodes and bps of the graph, of duplicated nodes, of the redundant copies (all
+    // but one node of each group), and of the graph after merging duplicates
+    fn get_summary(&self, gb: &GraphBroker) -> Vec<Vec<String>> {
+        let node_lens = gb.get_node_lens();
+        let total = (
+            gb.get_node_count(),
+            node_lens.iter().map(|l| *l as usize).sum::<usize>(),
+        );
+        let (mut duplicated, mut redundant) = ((0, 0), (0, 0));
+        for group in self.groups.as_ref().unwrap() {
+            let len = node_lens[group[0].0 .0 as usize] as usize;
+            duplicated.0 += group.len();
+            duplicated.1 += group.len() * len;
+            redundant.0 += group.len() - 1;
+            redundant.1 += (group.len() - 1) * len;
+        }
+        let dedup = (total.0 - redundant.0, total.1 - redundant.1);
+        let mut res = vec![vec![
+            "nodes".to_string(),
+            "count".to_string(),
+            "bp".to_string(),
+            "% bp".to_string(),
+        ]];
+        for (name, (count, bp)) in [
+            ("total", total),
+            ("duplicated", duplicated),
+            ("redundant", redundant),
+            ("deduplicated", dedup),
+        ] {
+            res.push(vec![
+                name.to_string(),
+                count.to_string(),
+                bp.to_string(),
+                format!("{:.2}", 100.0 * bp as f64 / usize::max(total.1, 1) as f64),
+            ]);
+        }
+        res
+    }
+
+    fn get_run_name(&self, gb: &GraphBroker) -> String {
+        gb.get_run_name()
+    }
+
+    fn get_run_id(&self, gb: &GraphBroker) -> String {
+        format!("{}-duplicates", gb.get_run_id())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graph_broker::GraphState;
+
+    // nodes 2 and 3 are identical and reverse-complementary identical to node 1, respectively,
+    // node 5 is reverse-complementary identical to node 4, and node 6 is unique
+    const GFA: &[u8] = b"H\tVN:Z:1.0
+S\t1\tACGTT
+S\t2\tacgtt
+S\t3\tAACGT
+S\t4\tGGGA
+S\t5\tTCCC
+S\t6\tCAT
+P\tx\t1+,4+,6+\t*
+P\ty\t2+,3-,5-,6+\t*
+";
+
+    fn get_graph_broker(gfa: &tempfile::NamedTempFile) -> GraphBroker {
+        let graph = gfa.path().to_str().unwrap().to_string();
+        let reqs = HashSet::from([
+            InputRequirement::Graph(graph.clone()),
+            InputRequirement::Node,
+        ]);
+        let state = GraphState {
+            graph,
+            ..GraphState::default()
+        };
+        let mut gb = GraphBroker::new();
+        gb.change_graph_state(state, &reqs, false).unwrap();
+        gb
+    }
+
+    #[test]
+    fn test_duplicate_groups() {
+        let mut gfa = tempfile::NamedTempFile::new().unwrap();
+        std::io::Write::write_all(&mut gfa, GFA).unwrap();
+        let gb = get_graph_broker(&gfa);
+        let mut duplicates = Duplicates::from_parameter(AnalysisParameter::Duplicates);
+        duplicates.set_groups(&gb).unwrap();
+
+        // orientations are given relative to the first node of each group
+        let names = gb.get_node_names();
+        let groups: Vec<Vec<(&str, Orientation)>> = duplicates
+            .groups
+            .as_ref()
+            .unwrap()
+            .iter()
+            .map(|g| {
+                g.iter()
+                    .map(|(id, o)| (&names[id.0 as usize][..], *o))
+                    .collect()
+            })
+            .collect();
+        assert_eq!(
+            groups,
+            vec![
+                vec![
+                    ("1", Orientation::Forward),
+                    ("2", Orientation::Forward),
+                    ("3", Orientation::Backward)
+                ],
+                vec![("4", Orientation::Forward), ("5", Orientation::Backward)],
+            ]
+        );
+    }
+
+    #[test]
+    fn test_redundant_bps() {
+        let mut gfa = tempfile::NamedTempFile::new().unwrap();
+        std::io::Write::write_all(&mut gfa, GFA).unwrap();
+        let gb = get_graph_broker(&gfa);
+        let mut duplicates = Duplicates::from_parameter(AnalysisParameter::Duplicates);
+        duplicates.set_groups(&gb).unwrap();
+
+        // all but one node of each group are redundant, i.e., 2 * 5bp and 4bp
+        assert_eq!(
+            duplicates.get_summary(&gb)[1..],
+            vec![
+                vec!["total", "6", "26", "100.00"],
+                vec!["duplicated", "5", "23", "88.46"],
+                vec!["redundant", "3", "14", "53.85"],
+                vec!["deduplicated", "3", "12", "46.15"],
+            ]
+        );
+
+        let table = duplicates.generate_table(Some(&gb)).unwrap();
+        let rows: Vec<&str> = table
+            .lines()
+            .skip_while(|l| !l.starts_with("group\t"))
+            .skip(1)
+            .collect();
+        assert_eq!(
+            rows,
+            vec![
+                "1\t1\t+\t5",
+                "1\t2\t+\t5",
+                "1\t3\t-\t5",
+                "2\t4\t+\t4",
+                "2\t5\t-\t4"
+            ]
+        );
+    }
+}
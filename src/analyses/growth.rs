@@ -9,16 +9,31 @@ use crate::analysis_parameter::AnalysisParameter;
 use crate::graph_broker::{GraphBroker, Hist, ThresholdContainer};
 use crate::html_report::ReportItem;
 use crate::{
+    cache,
     io::parse_hists,
-    io::write_table,
-    util::{get_default_plot_downloads, CountType},
+    io::{write_long_table, write_table},
+    util::{
+        get_default_plot_downloads, get_seed, percentile_already_sorted, to_id, CountType,
+        Threshold,
+    },
 };
 
+use super::similarity::get_population_sets;
 use super::{Analysis, AnalysisSection, ConstructibleAnalysis, InputRequirement};
 
+mod openness;
+use openness::HeapsFit;
+
 type Hists = Vec<Hist>;
 type Growths = Vec<(CountType, Vec<Vec<f64>>)>;
 type Comments = Vec<Vec<u8>>;
+// growth curves of each percentile (inner) for each coverage and quorum (outer)
+type PercentileGrowths = (CountType, Vec<Vec<Vec<f64>>>);
+// pangenome sizes of the subsamples (innermost) at each rarefaction depth for each coverage and
+// quorum (outer)
+type Rarefaction = (CountType, Vec<Vec<Vec<f64>>>);
+// growth curve of stratified orders of the genomes for each coverage and quorum
+type StratifiedGrowths = (CountType, Vec<Vec<f64>>);
 
 pub struct Growth {
     parameter: AnalysisParameter,
@@ -97,7 +112,45 @@ impl Analysis for Growth {
                     }),
             );
         }
-        res.push_str(&write_table(&header_cols, &output_columns)?);
+        let fits = self.fit_growths(growths);
+        res.push_str(&describe_fits(growths, hist_aux, &fits));
+        if let Some(target) = self.get_extrapolate() {
+            add_extrapolation(
+                target,
+                growths,
+                hist_aux,
+                &fits,
+                &mut header_cols,
+                &mut output_columns,
+            );
+        }
+        if let Some(percentile_growths) = &self.inner.as_ref().unwrap().percentile_growths {
+            add_percentiles(
+                percentile_growths,
+                &self.get_percentiles(),
+                hist_aux,
+                &mut header_cols,
+                &mut output_columns,
+            );
+        }
+        if let Some(stratified_growths) = &self.inner.as_ref().unwrap().stratified_growths {
+            add_stratified_growths(
+                stratified_growths,
+                hist_aux,
+                &mut header_cols,
+                &mut output_columns,
+            );
+        }
+        if let Some(rarefaction) = &self.inner.as_ref().unwrap().rarefaction {
+            add_rarefaction(
+                rarefaction,
+                &self.get_rarefaction_depths(),
+                hist_aux,
+                &mut header_cols,
+                &mut output_columns,
+            );
+        }
+        res.push_str(&self.write_output_table(&header_cols, &output_columns)?);
         Ok(res)
     }
 
@@ -109,14 +162,22 @@ impl Analysis for Growth {
         let hist_aux = &self.inner.as_ref().unwrap().hist_aux;
         let growth_labels = (0..hist_aux.coverage.len())
             .map(|i| {
-                format!(
-                    "coverage ≥ {}, quorum ≥ {}%",
-                    hist_aux.coverage[i].get_string(),
-                    match hist_aux.quorum[i] {
-                        crate::util::Threshold::Relative(x) => (x * 100.0).to_string(),
-                        crate::util::Threshold::Absolute(x) => (x * 100).to_string(),
+                let coverage = match hist_aux.coverage[i] {
+                    Threshold::AbsoluteRange(a, b) => format!("coverage ∈ [{}, {}]", a, b),
+                    Threshold::RelativeRange(a, b) => {
+                        format!("coverage ∈ [{}%, {}%]", a * 100.0, b * 100.0)
                     }
-                )
+                    t => format!("coverage ≥ {}", t.get_string()),
+                };
+                let quorum = match hist_aux.quorum[i] {
+                    Threshold::Relative(x) => format!("quorum ≥ {}%", x * 100.0),
+                    Threshold::Absolute(x) => format!("quorum ≥ {}%", x * 100),
+                    Threshold::RelativeRange(a, b) => {
+                        format!("quorum ∈ [{}%, {}%]", a * 100.0, b * 100.0)
+                    }
+                    Threshold::AbsoluteRange(a, b) => format!("quorum ∈ [{}, {}]", a, b),
+                };
+                format!("{}, {}", coverage, quorum)
             })
             .collect::<Vec<_>>();
         let table = self.generate_table(dm)?;
@@ -124,24 +185,30 @@ impl Analysis for Growth {
         let growths = &self.inner.as_ref().unwrap().growths;
         let id_prefix = format!(
             "pan-growth-{}",
-            self.get_run_id(dm.expect("Growth should be called with a graph"))
-                .to_lowercase()
-                .replace(&[' ', '|', '\\'], "-")
+            to_id(&self.get_run_id(dm.expect("Growth should be called with a graph")))
         );
+        let extrapolate = self.get_extrapolate();
+        let percentiles = self.get_percentiles();
+        let percentile_growths = &self.inner.as_ref().unwrap().percentile_growths;
+        let depths = self.get_rarefaction_depths();
+        let rarefaction = &self.inner.as_ref().unwrap().rarefaction;
+        let stratified_growths = &self.inner.as_ref().unwrap().stratified_growths;
+        let fits = self.fit_growths(growths);
         let growth_tabs = growths
             .iter()
-            .map(|(k, v)| AnalysisSection {
+            .zip(&fits)
+            .map(|((k, v), fits)| AnalysisSection {
                 id: format!("{id_prefix}-{k}"),
                 analysis: "Pangenome Growth".to_string(),
                 run_name: self.get_run_name(dm.expect("Growth should be called with a graph")),
                 run_id: self.get_run_id(dm.expect("Growth should be called with a graph")),
                 countable: k.to_string(),
                 table: Some(table.clone()),
-                items: vec![ReportItem::MultiBar {
+                items: std::iter::once(ReportItem::MultiBar {
                     id: format!("{id_prefix}-{k}"),
                     names: growth_labels.clone(),
                     x_label: "taxa".to_string(),
-                    y_label: format!("#{}s", k),
+                    y_label: self.get_y_label(k),
                     labels: (0..v[0].len()).map(|i| i.to_string()).collect(),
                     values: v
                         .iter()
@@ -152,8 +219,53 @@ impl Analysis for Growth {
                         })
                         .collect(),
                     log_toggle: false,
-                }],
+                    phenotypes: None,
+                    hover: None,
+                })
+                .chain(extrapolate.into_iter().flat_map(|target| {
+                    get_extrapolation_items(
+                        &format!("{id_prefix}-{k}"),
+                        &self.get_y_label(k),
+                        v,
+                        fits,
+                        &growth_labels,
+                        target,
+                    )
+                }))
+                .chain(
+                    percentile_growths
+                        .iter()
+                        .filter(|(count, _)| count == k)
+                        .flat_map(|(_, p)| {
+                            get_percentile_items(
+                                &format!("{id_prefix}-{k}"),
+                                &self.get_y_label(k),
+                                v,
+                                p,
+                                &percentiles,
+                                &growth_labels,
+                            )
+                        }),
+                )
+                .chain(get_stratified_items(
+                    &format!("{id_prefix}-{k}"),
+                    k,
+                    &self.get_y_label(k),
+                    v,
+                    stratified_growths.as_ref(),
+                    &growth_labels,
+                ))
+                .chain(get_rarefaction_items(
+                    &format!("{id_prefix}-{k}"),
+                    k,
+                    &self.get_y_label(k),
+                    rarefaction.as_ref(),
+                    &depths,
+                    &growth_labels,
+                ))
+                .collect(),
                 plot_downloads: get_default_plot_downloads(),
+                description: None,
             })
             .collect();
         Ok(growth_tabs)
@@ -173,7 +285,26 @@ impl Analysis for Growth {
     // }
 
     fn get_graph_requirements(&self) -> HashSet<super::InputRequirement> {
-        HashSet::from([InputRequirement::Hist])
+        if self.get_percentiles().is_empty()
+            && self.get_rarefaction_depths().is_empty()
+            && self.get_stratify_file().is_none()
+        {
+            HashSet::from([InputRequirement::Hist])
+        } else {
+            // percentiles, rarefaction, and stratified growth are estimated from the coverage of
+            // each countable of the requested type by each group
+            let count = self.get_count_type();
+            let mut req = HashSet::from([
+                InputRequirement::Hist,
+                InputRequirement::AbacusByGroup(count),
+            ]);
+            req.extend(Self::count_to_input_req(count));
+            req
+        }
+    }
+
+    fn uses_hists_only(&self) -> bool {
+        self.get_graph_requirements() == HashSet::from([InputRequirement::Hist])
     }
 }
 
@@ -192,6 +323,8 @@ impl Growth {
             quorum,
             coverage,
             add_hist,
+            extrapolate,
+            ..
         } = &self.parameter
         {
             log::info!("reporting hist table");
@@ -199,17 +332,23 @@ impl Growth {
             let quorum = quorum.to_owned().unwrap_or("0".to_string());
             let coverage = coverage.to_owned().unwrap_or("1".to_string());
             let hist_aux = ThresholdContainer::parse_params(&quorum, &coverage)?;
-            let file = File::open(file)?;
-            let mut data = BufReader::new(file);
-            let (coverages, comments) = parse_hists(&mut data)?;
+            let mut data = BufReader::new(File::open(file)?);
+            let (coverages, comments) = parse_hists(&mut data, file)?;
             let hists: Hists = coverages
                 .into_iter()
                 .map(|(count, coverage)| Hist { count, coverage })
                 .collect();
-            let growths: Growths = hists
+            let mut growths: Growths = hists
                 .par_iter()
                 .map(|h| (h.count, h.calc_all_growths(&hist_aux)))
                 .collect();
+            let mut comments = comments;
+            if self.is_normalized() {
+                let genome_sizes = get_genome_sizes(hists.iter());
+                for (count, g) in growths.iter_mut() {
+                    comments.extend(normalize_growths(g, *count, &genome_sizes));
+                }
+            }
             let mut res = String::new();
             for c in comments {
                 res.push_str(str::from_utf8(&c[..])?);
@@ -240,7 +379,7 @@ impl Growth {
                 }
             }
 
-            for (count, g) in growths {
+            for (count, g) in &growths {
                 output_columns.extend(g.clone());
                 let m = hist_aux.coverage.len();
                 header_cols.extend(
@@ -254,13 +393,294 @@ impl Growth {
                         }),
                 );
             }
-            res.push_str(&write_table(&header_cols, &output_columns)?);
+            let fits = self.fit_growths(&growths);
+            res.push_str(&describe_fits(&growths, &hist_aux, &fits));
+            if let Some(target) = extrapolate {
+                add_extrapolation(
+                    *target,
+                    &growths,
+                    &hist_aux,
+                    &fits,
+                    &mut header_cols,
+                    &mut output_columns,
+                );
+            }
+            res.push_str(&self.write_output_table(&header_cols, &output_columns)?);
             Ok(res)
         } else {
             panic!("Growth needs growth parameter");
         }
     }
 
+    fn fit_growths(&self, growths: &Growths) -> Fits {
+        if let AnalysisParameter::Growth {
+            alpha_method,
+            alpha_from,
+            alpha_to,
+            ..
+        } = &self.parameter
+        {
+            growths
+                .iter()
+                .map(|(_, g)| {
+                    g.iter()
+                        .map(|growth| {
+                            HeapsFit::from_growth(growth, *alpha_method, *alpha_from, *alpha_to)
+                        })
+                        .collect()
+                })
+                .collect()
+        } else {
+            panic!("Growth needs growth parameter");
+        }
+    }
+
+    fn write_output_table(
+        &self,
+        headers: &Vec<Vec<String>>,
+        columns: &Vec<Vec<f64>>,
+    ) -> Result<String, std::io::Error> {
+        if let AnalysisParameter::Growth { long: true, .. } = &self.parameter {
+            write_long_table(headers, columns, 0, "m")
+        } else {
+            write_table(headers, columns)
+        }
+    }
+
+    fn get_extrapolate(&self) -> Option<usize> {
+        if let AnalysisParameter::Growth { extrapolate, .. } = &self.parameter {
+            *extrapolate
+        } else {
+            None
+        }
+    }
+
+    fn get_percentiles(&self) -> Vec<f64> {
+        if let AnalysisParameter::Growth { percentiles, .. } = &self.parameter {
+            percentiles.iter().map(|p| p.0).collect()
+        } else {
+            Vec::new()
+        }
+    }
+
+    fn is_normalized(&self) -> bool {
+        matches!(
+            self.parameter,
+            AnalysisParameter::Growth {
+                normalize: true,
+                ..
+            }
+        )
+    }
+
+    fn get_y_label(&self, count: &CountType) -> String {
+        if self.is_normalized() {
+            format!("#{}s / average genome size", count)
+        } else {
+            format!("#{}s", count)
+        }
+    }
+
+    fn get_rarefaction_depths(&self) -> Vec<usize> {
+        if let AnalysisParameter::Growth { rarefaction, .. } = &self.parameter {
+            rarefaction.clone()
+        } else {
+            Vec::new()
+        }
+    }
+
+    fn get_stratify_file(&self) -> Option<&str> {
+        if let AnalysisParameter::Growth { stratify, .. } = &self.parameter {
+            stratify.as_deref()
+        } else {
+            None
+        }
+    }
+
+    fn get_count_type(&self) -> CountType {
+        if let AnalysisParameter::Growth { count_type, .. } = &self.parameter {
+            *count_type
+        } else {
+            CountType::default()
+        }
+    }
+
+    fn count_to_input_req(count: CountType) -> HashSet<InputRequirement> {
+        match count {
+            CountType::Bp => HashSet::from([InputRequirement::Bp]),
+            CountType::Node => HashSet::from([InputRequirement::Node]),
+            CountType::Edge => HashSet::from([InputRequirement::Edge]),
+            CountType::Bubble => HashSet::from([InputRequirement::Bubble]),
+            CountType::All => HashSet::from([
+                InputRequirement::Bp,
+                InputRequirement::Node,
+                InputRequirement::Edge,
+            ]),
+        }
+    }
+
+    // percentiles of the growth over random orders of the groups, for the countable of the abacus
+    // by group
+    fn calc_percentile_growths(
+        &self,
+        gb: &GraphBroker,
+        hist_aux: &ThresholdContainer,
+    ) -> anyhow::Result<Option<PercentileGrowths>> {
+        let (percentiles, permutations) = match &self.parameter {
+            AnalysisParameter::Growth {
+                percentiles,
+                permutations,
+                ..
+            } if !percentiles.is_empty() => (self.get_percentiles(), *permutations),
+            _ => return Ok(None),
+        };
+        if let Some(p) = percentiles.iter().find(|p| !(0.0..=100.0).contains(*p)) {
+            anyhow::bail!("percentile {} is not within 0 and 100", p);
+        }
+        if permutations == 0 {
+            anyhow::bail!("percentiles of the growth need at least one permutation");
+        }
+        let abacus = gb.get_abacus_by_group();
+        log::info!(
+            "estimating percentiles of {} growth from {} random orders of {} groups",
+            abacus.count,
+            permutations,
+            abacus.groups.len()
+        );
+        let growths = hist_aux
+            .coverage
+            .iter()
+            .zip(&hist_aux.quorum)
+            .map(|(c, q)| {
+                let key = gb.get_cache_key(&[
+                    "growth-percentiles",
+                    &abacus.count.to_string(),
+                    &format!("{:?} {:?} {:?}", c, q, percentiles),
+                    &permutations.to_string(),
+                    &get_seed().to_string(),
+                ]);
+                let growths: Vec<Vec<f64>> =
+                    cache::get_or_compute("growth-percentiles", key, || {
+                        abacus.calc_growth_percentiles(
+                            c,
+                            q,
+                            gb.get_node_lens(),
+                            &percentiles,
+                            permutations,
+                        )
+                    });
+                growths
+                    .into_iter()
+                    .map(|mut g| {
+                        g.insert(0, f64::NAN);
+                        g
+                    })
+                    .collect()
+            })
+            .collect();
+        Ok(Some((abacus.count, growths)))
+    }
+
+    // mean growth over random orders of the groups that draw equally from each population, for the
+    // countable of the abacus by group
+    fn calc_stratified_growths(
+        &self,
+        gb: &GraphBroker,
+        hist_aux: &ThresholdContainer,
+    ) -> anyhow::Result<Option<StratifiedGrowths>> {
+        let (file, permutations) = match &self.parameter {
+            AnalysisParameter::Growth {
+                stratify: Some(file),
+                permutations,
+                ..
+            } => (file, *permutations),
+            _ => return Ok(None),
+        };
+        if permutations == 0 {
+            anyhow::bail!("stratified growth needs at least one permutation");
+        }
+        let abacus = gb.get_abacus_by_group();
+        let (populations, sets) = get_population_sets(file, &abacus.groups)?;
+        if let Some(g) = abacus
+            .groups
+            .iter()
+            .zip(&sets)
+            .find(|(_, p)| p.is_none())
+            .map(|(g, _)| g)
+        {
+            log::warn!(
+                "group {} is not assigned to a population in {} and is left out of the stratified growth",
+                g,
+                file
+            );
+        }
+        log::info!(
+            "estimating {} growth from {} random orders drawing equally from {} populations",
+            abacus.count,
+            permutations,
+            populations.len()
+        );
+        let growths = hist_aux
+            .coverage
+            .iter()
+            .zip(&hist_aux.quorum)
+            .map(|(c, q)| {
+                let mut g =
+                    abacus.calc_stratified_growth(c, q, gb.get_node_lens(), &sets, permutations);
+                g.insert(0, f64::NAN);
+                g
+            })
+            .collect();
+        Ok(Some((abacus.count, growths)))
+    }
+
+    // pangenome sizes of random subsets of exactly m groups for each rarefaction depth m, for the
+    // countable of the abacus by group
+    fn calc_rarefaction(
+        &self,
+        gb: &GraphBroker,
+        hist_aux: &ThresholdContainer,
+    ) -> anyhow::Result<Option<Rarefaction>> {
+        let (depths, samples) = match &self.parameter {
+            AnalysisParameter::Growth {
+                rarefaction,
+                permutations,
+                ..
+            } if !rarefaction.is_empty() => (rarefaction, *permutations),
+            _ => return Ok(None),
+        };
+        let abacus = gb.get_abacus_by_group();
+        let n = abacus.groups.len();
+        if let Some(m) = depths.iter().find(|m| **m == 0 || **m > n) {
+            anyhow::bail!(
+                "rarefaction depth {} is not within 1 and the number of genomes ({})",
+                m,
+                n
+            );
+        }
+        if samples == 0 {
+            anyhow::bail!("rarefaction needs at least one subsample per depth");
+        }
+        log::info!(
+            "subsampling {} {} times each to {} of {} groups",
+            abacus.count,
+            samples,
+            depths
+                .iter()
+                .map(|m| m.to_string())
+                .collect::<Vec<_>>()
+                .join(", "),
+            n
+        );
+        let sizes = hist_aux
+            .coverage
+            .iter()
+            .zip(&hist_aux.quorum)
+            .map(|(c, q)| abacus.calc_rarefaction(c, q, gb.get_node_lens(), depths, samples))
+            .collect();
+        Ok(Some((abacus.count, sizes)))
+    }
+
     fn get_run_name(&self, gb: &GraphBroker) -> String {
         format!("{}", gb.get_run_name())
     }
@@ -288,14 +708,39 @@ impl Growth {
                     .get_hists()
                     .values()
                     .par_bridge()
-                    .map(|h| (h.count, h.calc_all_growths(&hist_aux)))
+                    .map(|h| {
+                        let key =
+                            gb.get_cache_key(&["growth", &h.count.to_string(), &coverage, &quorum]);
+                        let growth =
+                            cache::get_or_compute("growth", key, || h.calc_all_growths(&hist_aux));
+                        (h.count, growth)
+                    })
                     .collect();
-                self.inner = Some(InnerGrowth {
+                if !self.uses_hists_only()
+                    && matches!(self.get_count_type(), CountType::All | CountType::Bubble)
+                {
+                    anyhow::bail!("percentiles, rarefaction, and stratified growth can only be estimated for a single count type of node, bp, or edge");
+                }
+                let percentile_growths = self.calc_percentile_growths(gb, &hist_aux)?;
+                let rarefaction = self.calc_rarefaction(gb, &hist_aux)?;
+                let stratified_growths = self.calc_stratified_growths(gb, &hist_aux)?;
+                let mut inner = InnerGrowth {
                     growths,
-                    comments: Vec::new(),
+                    comments: gb
+                        .get_dedup_comment()
+                        .map(|c| c.trim_end().as_bytes().to_vec())
+                        .into_iter()
+                        .collect(),
                     hist_aux,
                     hists: None,
-                });
+                    percentile_growths,
+                    rarefaction,
+                    stratified_growths,
+                };
+                if self.is_normalized() {
+                    inner.normalize(&get_genome_sizes(gb.get_hists().values()));
+                }
+                self.inner = Some(inner);
             }
             Ok(())
         } else {
@@ -304,9 +749,522 @@ impl Growth {
     }
 }
 
+type Fits = Vec<Vec<Option<HeapsFit>>>;
+
+// comment lines that describe the openness models fitted to the growth curves
+fn describe_fits(growths: &Growths, hist_aux: &ThresholdContainer, fits: &Fits) -> String {
+    let mut res = String::new();
+    for ((count, _), fits) in growths.iter().zip(fits) {
+        for (i, fit) in fits.iter().enumerate() {
+            let (c, q) = (
+                hist_aux.coverage[i].get_string(),
+                hist_aux.quorum[i].get_string(),
+            );
+            match fit {
+                Some(fit) => res.push_str(&format!(
+                    "# heaps' law fit of {} growth with coverage {} and quorum {}: {}\n",
+                    count, c, q, fit
+                )),
+                None => log::warn!(
+                    "too few growth points to fit heaps' law to {} growth with coverage {} and quorum {}",
+                    count,
+                    c,
+                    q
+                ),
+            }
+        }
+    }
+    res
+}
+
+// appends the growth predicted by the fitted openness models for up to `target` genomes as extra
+// rows, together with the bounds of its prediction interval as extra columns
+fn add_extrapolation(
+    target: usize,
+    growths: &Growths,
+    hist_aux: &ThresholdContainer,
+    fits: &Fits,
+    header_cols: &mut Vec<Vec<String>>,
+    output_columns: &mut Vec<Vec<f64>>,
+) {
+    let observed = output_columns.first().map(|c| c.len()).unwrap_or(0);
+    if target < observed {
+        log::warn!(
+            "not extrapolating growth to {} genomes, which is not beyond the observed {}",
+            target,
+            observed.saturating_sub(1)
+        );
+        return;
+    }
+    let mut j = output_columns.len() - growths.iter().map(|(_, g)| g.len()).sum::<usize>();
+    for column in output_columns.iter_mut() {
+        column.resize(target + 1, f64::NAN);
+    }
+    for ((count, _), fits) in growths.iter().zip(fits) {
+        for (i, fit) in fits.iter().enumerate() {
+            if let Some(fit) = fit {
+                let (c, q) = (
+                    hist_aux.coverage[i].get_string(),
+                    hist_aux.quorum[i].get_string(),
+                );
+                let mut lower = vec![f64::NAN; target + 1];
+                let mut upper = vec![f64::NAN; target + 1];
+                for n in observed..=target {
+                    let (y, lo, hi) = fit.predict(n);
+                    output_columns[j][n] = y;
+                    lower[n] = lo;
+                    upper[n] = hi;
+                }
+                output_columns.push(lower);
+                header_cols.push(vec![
+                    "growth-lower".to_string(),
+                    count.to_string(),
+                    c.clone(),
+                    q.clone(),
+                ]);
+                output_columns.push(upper);
+                header_cols.push(vec!["growth-upper".to_string(), count.to_string(), c, q]);
+            }
+            j += 1;
+        }
+    }
+}
+
+// appends the percentiles of the growth over random orders of the genomes as extra columns
+fn add_percentiles(
+    percentile_growths: &PercentileGrowths,
+    percentiles: &[f64],
+    hist_aux: &ThresholdContainer,
+    header_cols: &mut Vec<Vec<String>>,
+    output_columns: &mut Vec<Vec<f64>>,
+) {
+    let (count, growths) = percentile_growths;
+    let rows = output_columns.first().map(|c| c.len()).unwrap_or(0);
+    for (i, growths) in growths.iter().enumerate() {
+        for (p, growth) in percentiles.iter().zip(growths) {
+            let mut column = growth.clone();
+            column.resize(usize::max(rows, column.len()), f64::NAN);
+            output_columns.push(column);
+            header_cols.push(vec![
+                format!("growth-p{}", p),
+                count.to_string(),
+                hist_aux.coverage[i].get_string(),
+                hist_aux.quorum[i].get_string(),
+            ]);
+        }
+    }
+}
+
+// appends the growth of stratified orders of the genomes as extra columns
+fn add_stratified_growths(
+    stratified_growths: &StratifiedGrowths,
+    hist_aux: &ThresholdContainer,
+    header_cols: &mut Vec<Vec<String>>,
+    output_columns: &mut Vec<Vec<f64>>,
+) {
+    let (count, growths) = stratified_growths;
+    let rows = output_columns.first().map(|c| c.len()).unwrap_or(0);
+    for (i, growth) in growths.iter().enumerate() {
+        let mut column = growth.clone();
+        column.resize(usize::max(rows, column.len()), f64::NAN);
+        output_columns.push(column);
+        header_cols.push(vec![
+            "growth-stratified".to_string(),
+            count.to_string(),
+            hist_aux.coverage[i].get_string(),
+            hist_aux.quorum[i].get_string(),
+        ]);
+    }
+}
+
+// bar charts of the expected growth next to the growth of stratified orders of the genomes, one
+// for each coverage and quorum, if the stratified growth is of the given countable
+fn get_stratified_items(
+    id: &str,
+    count: &CountType,
+    y_label: &str,
+    growths: &[Vec<f64>],
+    stratified_growths: Option<&StratifiedGrowths>,
+    labels: &[String],
+) -> Vec<ReportItem> {
+    let stratified = match stratified_growths {
+        Some((c, stratified)) if c == count => stratified,
+        _ => return Vec::new(),
+    };
+    growths
+        .iter()
+        .zip(stratified)
+        .zip(labels)
+        .enumerate()
+        .map(|(i, ((growth, stratified), label))| ReportItem::MultiBar {
+            id: format!("{id}-stratified-{i}"),
+            names: vec![format!("all ({})", label), "stratified".to_string()],
+            x_label: "taxa".to_string(),
+            y_label: y_label.to_string(),
+            labels: (0..growth.len()).map(|m| m.to_string()).collect(),
+            values: [growth, stratified]
+                .iter()
+                .map(|row| {
+                    (0..growth.len())
+                        .map(|m| row.get(m).copied().filter(|x| !x.is_nan()).unwrap_or(0.0))
+                        .collect()
+                })
+                .collect(),
+            log_toggle: false,
+            phenotypes: None,
+            hover: None,
+        })
+        .collect()
+}
+
+// summary statistics of the distribution of the pangenome size at each rarefaction depth, as
+// name of the column and the statistic of the sorted sizes
+const RAREFACTION_STATISTICS: [(&str, fn(&[f64]) -> f64); 7] = [
+    ("mean", |v| v.iter().sum::<f64>() / v.len() as f64),
+    ("sd", |v| {
+        let mean = v.iter().sum::<f64>() / v.len() as f64;
+        (v.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / (v.len() as f64 - 1.0)).sqrt()
+    }),
+    ("min", |v| percentile_already_sorted(v, 0.0)),
+    ("q1", |v| percentile_already_sorted(v, 25.0)),
+    ("median", |v| percentile_already_sorted(v, 50.0)),
+    ("q3", |v| percentile_already_sorted(v, 75.0)),
+    ("max", |v| percentile_already_sorted(v, 100.0)),
+];
+
+// appends summary statistics of the pangenome size over the subsamples as extra columns, with
+// values in the rows of the rarefaction depths only
+fn add_rarefaction(
+    rarefaction: &Rarefaction,
+    depths: &[usize],
+    hist_aux: &ThresholdContainer,
+    header_cols: &mut Vec<Vec<String>>,
+    output_columns: &mut Vec<Vec<f64>>,
+) {
+    let (count, sizes) = rarefaction;
+    let rows = output_columns.first().map(|c| c.len()).unwrap_or(0);
+    let rows = usize::max(rows, depths.iter().max().map_or(0, |m| m + 1));
+    for column in output_columns.iter_mut() {
+        column.resize(rows, f64::NAN);
+    }
+    for (i, sizes) in sizes.iter().enumerate() {
+        let sorted: Vec<Vec<f64>> = sizes
+            .iter()
+            .map(|s| {
+                let mut s = s.clone();
+                s.sort_by(|a, b| a.partial_cmp(b).unwrap());
+                s
+            })
+            .collect();
+        for (name, statistic) in RAREFACTION_STATISTICS.iter() {
+            let mut column = vec![f64::NAN; rows];
+            for (m, s) in depths.iter().zip(&sorted) {
+                column[*m] = statistic(s);
+            }
+            output_columns.push(column);
+            header_cols.push(vec![
+                format!("rarefaction-{}", name),
+                count.to_string(),
+                hist_aux.coverage[i].get_string(),
+                hist_aux.quorum[i].get_string(),
+            ]);
+        }
+    }
+}
+
+// box plots of the distribution of the pangenome size at each rarefaction depth, one for each
+// coverage and quorum, if the rarefaction is of the given countable
+fn get_rarefaction_items(
+    id: &str,
+    count: &CountType,
+    y_label: &str,
+    rarefaction: Option<&Rarefaction>,
+    depths: &[usize],
+    labels: &[String],
+) -> Vec<ReportItem> {
+    let sizes = match rarefaction {
+        Some((c, sizes)) if c == count => sizes,
+        _ => return Vec::new(),
+    };
+    sizes
+        .iter()
+        .zip(labels)
+        .enumerate()
+        .map(|(i, (sizes, label))| ReportItem::BoxPlot {
+            id: format!("{id}-rarefaction-{i}"),
+            name: format!("rarefaction ({})", label),
+            x_label: "taxa".to_string(),
+            y_label: y_label.to_string(),
+            labels: depths.iter().map(|m| m.to_string()).collect(),
+            values: sizes.clone(),
+            violin: false,
+        })
+        .collect()
+}
+
+// bar charts of the expected growth (i.e., the mean over all orders of the genomes) next to its
+// percentiles, one for each coverage and quorum
+fn get_percentile_items(
+    id: &str,
+    y_label: &str,
+    growths: &[Vec<f64>],
+    percentile_growths: &[Vec<Vec<f64>>],
+    percentiles: &[f64],
+    labels: &[String],
+) -> Vec<ReportItem> {
+    growths
+        .iter()
+        .zip(percentile_growths)
+        .zip(labels)
+        .enumerate()
+        .map(|(i, ((growth, percentile_growth), label))| {
+            let mut names = vec![format!("mean ({})", label)];
+            names.extend(percentiles.iter().map(|p| format!("{}th percentile", p)));
+            ReportItem::MultiBar {
+                id: format!("{id}-percentiles-{i}"),
+                names,
+                x_label: "taxa".to_string(),
+                y_label: y_label.to_string(),
+                labels: (0..growth.len()).map(|m| m.to_string()).collect(),
+                values: std::iter::once(growth)
+                    .chain(percentile_growth)
+                    .map(|row| {
+                        row.iter()
+                            .map(|el| if el.is_nan() { 0.0 } else { *el })
+                            .collect()
+                    })
+                    .collect(),
+                log_toggle: false,
+                phenotypes: None,
+                hover: None,
+            }
+        })
+        .collect()
+}
+
+// scatter plots of the observed growth curves, each with its fitted openness model and its
+// extrapolation up to `target` genomes
+fn get_extrapolation_items(
+    id: &str,
+    y_label: &str,
+    growths: &[Vec<f64>],
+    fits: &[Option<HeapsFit>],
+    labels: &[String],
+    target: usize,
+) -> Vec<ReportItem> {
+    growths
+        .iter()
+        .zip(fits)
+        .zip(labels)
+        .enumerate()
+        .filter_map(|(i, ((growth, fit), label))| {
+            let fit = fit.as_ref()?;
+            let observed = growth.len();
+            let points: Vec<(f64, f64)> = growth
+                .iter()
+                .enumerate()
+                .skip(1)
+                .filter(|(_, y)| y.is_finite())
+                .map(|(n, y)| (n as f64, *y))
+                .collect();
+            Some(ReportItem::Scatter {
+                id: format!("{id}-extrapolation-{i}"),
+                name: label.clone(),
+                x_label: "taxa".to_string(),
+                y_label: y_label.to_string(),
+                x_values: points.iter().map(|p| p.0).collect(),
+                y_values: points.iter().map(|p| p.1).collect(),
+                fit: Some(
+                    (fit.from..=fit.to)
+                        .map(|n| (n as f64, fit.predict(n).0))
+                        .collect(),
+                ),
+                prediction: Some(
+                    (observed - 1..=target.max(observed - 1))
+                        .map(|n| {
+                            let (y, lower, upper) = fit.predict(n);
+                            (n as f64, y, lower, upper)
+                        })
+                        .collect(),
+                ),
+                annotation: Some(format!("{}: {}", label, fit)),
+                log_x: false,
+                log_y: false,
+            })
+        })
+        .collect()
+}
+
+// average size of a genome (i.e., path or group) in each countable, by which the growth is
+// divided if it is normalized
+fn get_genome_sizes<'a>(hists: impl Iterator<Item = &'a Hist>) -> Vec<(CountType, f64)> {
+    hists
+        .map(|h| (h.count, h.get_average_group_size()))
+        .collect()
+}
+
+// divides the growth curves of the countable by its average genome size, and returns the comment
+// line that documents the normalization
+fn normalize_growths(
+    growths: &mut [Vec<f64>],
+    count: CountType,
+    genome_sizes: &[(CountType, f64)],
+) -> Option<Vec<u8>> {
+    let size = match genome_sizes.iter().find(|(c, _)| *c == count) {
+        Some((_, size)) if *size > 0.0 => *size,
+        _ => {
+            log::warn!(
+                "unable to normalize {} growth, as the average genome size is unknown",
+                count
+            );
+            return None;
+        }
+    };
+    for g in growths.iter_mut() {
+        for x in g.iter_mut() {
+            *x /= size;
+        }
+    }
+    Some(
+        format!(
+            "# {} growth normalized by the average genome size of {:.2} {}s",
+            count, size, count
+        )
+        .into_bytes(),
+    )
+}
+
 struct InnerGrowth {
     growths: Growths,
     comments: Comments,
     hist_aux: ThresholdContainer,
     hists: Option<Hists>,
+    percentile_growths: Option<PercentileGrowths>,
+    rarefaction: Option<Rarefaction>,
+    stratified_growths: Option<StratifiedGrowths>,
+}
+
+impl InnerGrowth {
+    // divides all growth curves, including their percentiles, stratified growths, and
+    // rarefaction, by the average genome size of their countable
+    fn normalize(&mut self, genome_sizes: &[(CountType, f64)]) {
+        for (count, g) in self.growths.iter_mut() {
+            self.comments
+                .extend(normalize_growths(g, *count, genome_sizes));
+        }
+        if let Some((count, growths)) = &mut self.percentile_growths {
+            for g in growths.iter_mut() {
+                normalize_growths(g, *count, genome_sizes);
+            }
+        }
+        if let Some((count, growths)) = &mut self.stratified_growths {
+            normalize_growths(growths, *count, genome_sizes);
+        }
+        if let Some((count, sizes)) = &mut self.rarefaction {
+            for s in sizes.iter_mut() {
+                normalize_growths(s, *count, genome_sizes);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analysis_parameter::{AlphaMethod, Percentile};
+
+    #[test]
+    fn test_get_graph_requirements() {
+        let growth = |percentiles: Vec<Percentile>, count_type| {
+            Growth::from_parameter(AnalysisParameter::Growth {
+                coverage: None,
+                quorum: None,
+                add_hist: false,
+                extrapolate: None,
+                alpha_method: AlphaMethod::default(),
+                alpha_from: None,
+                alpha_to: None,
+                long: false,
+                percentiles,
+                permutations: 10,
+                rarefaction: Vec::new(),
+                stratify: None,
+                normalize: false,
+                count_type,
+            })
+        };
+        assert_eq!(
+            growth(Vec::new(), CountType::Bp).get_graph_requirements(),
+            HashSet::from([InputRequirement::Hist])
+        );
+        assert_eq!(
+            growth(vec![Percentile(50.0)], CountType::Bp).get_graph_requirements(),
+            HashSet::from([
+                InputRequirement::Hist,
+                InputRequirement::Bp,
+                InputRequirement::AbacusByGroup(CountType::Bp)
+            ])
+        );
+    }
+
+    #[test]
+    fn test_add_extrapolation() {
+        // heaps' law growth with gamma 0.5, perturbed such that the prediction interval is not empty
+        let growth: Vec<f64> = (0..=10)
+            .map(|n| 1000.0 * (n as f64).sqrt() * if n % 2 == 0 { 1.01 } else { 0.99 })
+            .collect();
+        let growths: Growths = vec![(CountType::Node, vec![growth.clone()])];
+        let hist_aux = ThresholdContainer {
+            coverage: vec![Threshold::Absolute(1)],
+            quorum: vec![Threshold::Relative(0.0)],
+        };
+        let fits: Fits = vec![vec![HeapsFit::from_growth(
+            &growth,
+            AlphaMethod::Ols,
+            None,
+            None,
+        )]];
+        let mut header_cols = vec![vec!["growth".to_string(); 4]];
+        let mut output_columns = vec![growth.clone()];
+
+        // targets within the observed genomes are ignored
+        add_extrapolation(
+            5,
+            &growths,
+            &hist_aux,
+            &fits,
+            &mut header_cols,
+            &mut output_columns,
+        );
+        assert_eq!(output_columns, vec![growth.clone()]);
+
+        add_extrapolation(
+            20,
+            &growths,
+            &hist_aux,
+            &fits,
+            &mut header_cols,
+            &mut output_columns,
+        );
+        assert_eq!(header_cols[1], vec!["growth-lower", "node", "1", "0"]);
+        assert_eq!(header_cols[2], vec!["growth-upper", "node", "1", "0"]);
+        assert_eq!(output_columns.len(), 3);
+        assert!(output_columns.iter().all(|c| c.len() == 21));
+        assert_eq!(&output_columns[0][1..=10], &growth[1..]);
+        assert!(output_columns[1][..11].iter().all(|x| x.is_nan()));
+        for n in 11..=20 {
+            let (y, lower, upper) = (
+                output_columns[0][n],
+                output_columns[1][n],
+                output_columns[2][n],
+            );
+            assert!((y / (1000.0 * (n as f64).sqrt()) - 1.0).abs() < 0.02);
+            assert!(lower < y && y < upper);
+        }
+        // the prediction interval widens with the distance to the observed genomes
+        assert!(
+            output_columns[2][20] - output_columns[1][20]
+                > output_columns[2][11] - output_columns[1][11]
+        );
+    }
 }
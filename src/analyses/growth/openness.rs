@@ -0,0 +1,277 @@
+use std::fmt;
+
+use crate::analysis_parameter::AlphaMethod;
+
+// two-sided 95% quantile of the standard normal distribution
+const Z_95: f64 = 1.959_963_984_540_054;
+// tuning constant of the Huber loss, relative to the residual scale, for 95% efficiency under
+// normally distributed residuals
+const HUBER_K: f64 = 1.345;
+const HUBER_MAX_ITERATIONS: usize = 100;
+
+/// Heaps' law f(n) = k * n^gamma fitted to a growth curve by regression in log-log space; the
+/// openness of the pangenome is alpha = 1 - gamma, i.e., it is open for alpha < 1
+pub struct HeapsFit {
+    pub k: f64,
+    pub gamma: f64,
+    pub method: AlphaMethod,
+    // range of genome counts whose growth points are fitted
+    pub from: usize,
+    pub to: usize,
+    // coefficient of determination and median absolute deviation of the residuals
+    pub r2: f64,
+    pub mad: f64,
+    // number of growth points, mean and sum of squared deviations of log(n), and standard
+    // deviation of the residuals; needed for prediction intervals
+    points: usize,
+    x_mean: f64,
+    x_ss: f64,
+    residual_sd: f64,
+}
+
+impl HeapsFit {
+    /// Fits the model to a growth curve whose i-th element is the growth for i genomes, using the
+    /// points from `from` to `to` genomes; by default, the point of a single genome is left out
+    /// unless too few points remain. Undefined and non-positive values (such as the one for 0
+    /// genomes) are ignored.
+    pub fn from_growth(
+        growth: &[f64],
+        method: AlphaMethod,
+        from: Option<usize>,
+        to: Option<usize>,
+    ) -> Option<Self> {
+        let to = to
+            .unwrap_or(growth.len())
+            .min(growth.len().saturating_sub(1));
+        let from = from.unwrap_or(if to >= 5 { 2 } else { 1 }).max(1);
+        if from > to {
+            return None;
+        }
+        let (x, y): (Vec<f64>, Vec<f64>) = (from..=to)
+            .filter(|n| growth[*n].is_finite() && growth[*n] > 0.0)
+            .map(|n| ((n as f64).ln(), growth[n].ln()))
+            .unzip();
+        // a residual variance can only be estimated from at least three points
+        if x.len() < 3 {
+            return None;
+        }
+        let points = x.len();
+        let x_mean = mean(&x);
+        let x_ss: f64 = x.iter().map(|xi| (xi - x_mean).powi(2)).sum();
+        if x_ss == 0.0 {
+            return None;
+        }
+        let (intercept, gamma) = match method {
+            AlphaMethod::Ols => weighted_least_squares(&x, &y, None),
+            AlphaMethod::Huber => huber(&x, &y),
+            AlphaMethod::TheilSen => theil_sen(&x, &y),
+        };
+        let residuals: Vec<f64> = x
+            .iter()
+            .zip(&y)
+            .map(|(xi, yi)| yi - intercept - gamma * xi)
+            .collect();
+        let residual_ss: f64 = residuals.iter().map(|r| r * r).sum();
+        let y_mean = mean(&y);
+        let y_ss: f64 = y.iter().map(|yi| (yi - y_mean).powi(2)).sum();
+        Some(Self {
+            k: intercept.exp(),
+            gamma,
+            method,
+            from,
+            to,
+            r2: if y_ss > 0.0 {
+                1.0 - residual_ss / y_ss
+            } else {
+                1.0
+            },
+            mad: mad(&residuals),
+            points,
+            x_mean,
+            x_ss,
+            residual_sd: (residual_ss / (points - 2) as f64).sqrt(),
+        })
+    }
+
+    pub fn alpha(&self) -> f64 {
+        1.0 - self.gamma
+    }
+
+    /// Predicted growth for n genomes together with the lower and upper bound of its 95%
+    /// prediction interval
+    pub fn predict(&self, n: usize) -> (f64, f64, f64) {
+        let x = (n as f64).ln();
+        let y = self.k.ln() + self.gamma * x;
+        let se = self.residual_sd
+            * (1.0 + 1.0 / self.points as f64 + (x - self.x_mean).powi(2) / self.x_ss).sqrt();
+        let t = student_t_95(self.points - 2);
+        (y.exp(), (y - t * se).exp(), (y + t * se).exp())
+    }
+}
+
+impl fmt::Display for HeapsFit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "alpha={:.4} ({}), gamma={:.4}, k={:.2}, R^2={:.4}, MAD={:.4} ({} fit of {} to {} genomes)",
+            self.alpha(),
+            if self.alpha() < 1.0 { "open" } else { "closed" },
+            self.gamma,
+            self.k,
+            self.r2,
+            self.mad,
+            self.method,
+            self.from,
+            self.to
+        )
+    }
+}
+
+fn mean(v: &[f64]) -> f64 {
+    v.iter().sum::<f64>() / v.len() as f64
+}
+
+fn median(v: &mut [f64]) -> f64 {
+    v.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    let m = v.len() / 2;
+    if v.len() % 2 == 0 {
+        (v[m - 1] + v[m]) / 2.0
+    } else {
+        v[m]
+    }
+}
+
+// median absolute deviation from the median
+fn mad(v: &[f64]) -> f64 {
+    let mut v = v.to_vec();
+    let m = median(&mut v);
+    let mut deviations: Vec<f64> = v.iter().map(|x| (x - m).abs()).collect();
+    median(&mut deviations)
+}
+
+// returns intercept and slope
+fn weighted_least_squares(x: &[f64], y: &[f64], weights: Option<&[f64]>) -> (f64, f64) {
+    let w = |i: usize| weights.map(|w| w[i]).unwrap_or(1.0);
+    let total: f64 = (0..x.len()).map(w).sum();
+    let x_mean = (0..x.len()).map(|i| w(i) * x[i]).sum::<f64>() / total;
+    let y_mean = (0..x.len()).map(|i| w(i) * y[i]).sum::<f64>() / total;
+    let xy: f64 = (0..x.len())
+        .map(|i| w(i) * (x[i] - x_mean) * (y[i] - y_mean))
+        .sum();
+    let xx: f64 = (0..x.len()).map(|i| w(i) * (x[i] - x_mean).powi(2)).sum();
+    let slope = xy / xx;
+    (y_mean - slope * x_mean, slope)
+}
+
+// Huber regression by iteratively reweighted least squares, starting from the least squares fit;
+// the residual scale is estimated by the normalized MAD of each iteration's residuals
+fn huber(x: &[f64], y: &[f64]) -> (f64, f64) {
+    let (mut intercept, mut slope) = weighted_least_squares(x, y, None);
+    for _ in 0..HUBER_MAX_ITERATIONS {
+        let residuals: Vec<f64> = x
+            .iter()
+            .zip(y)
+            .map(|(xi, yi)| yi - intercept - slope * xi)
+            .collect();
+        let scale = mad(&residuals) / 0.6745;
+        if scale == 0.0 {
+            break;
+        }
+        let weights: Vec<f64> = residuals
+            .iter()
+            .map(|r| {
+                let u = (r / scale).abs();
+                if u <= HUBER_K {
+                    1.0
+                } else {
+                    HUBER_K / u
+                }
+            })
+            .collect();
+        let (i, s) = weighted_least_squares(x, y, Some(&weights));
+        let converged = (i - intercept).abs() < 1e-10 && (s - slope).abs() < 1e-10;
+        intercept = i;
+        slope = s;
+        if converged {
+            break;
+        }
+    }
+    (intercept, slope)
+}
+
+// Theil-Sen estimator: the slope is the median of the slopes between all pairs of points, the
+// intercept the median of the points' intercepts under that slope
+fn theil_sen(x: &[f64], y: &[f64]) -> (f64, f64) {
+    let mut slopes = Vec::with_capacity(x.len() * (x.len() - 1) / 2);
+    for i in 0..x.len() {
+        for j in i + 1..x.len() {
+            if x[j] != x[i] {
+                slopes.push((y[j] - y[i]) / (x[j] - x[i]));
+            }
+        }
+    }
+    let slope = median(&mut slopes);
+    let mut intercepts: Vec<f64> = x.iter().zip(y).map(|(xi, yi)| yi - slope * xi).collect();
+    (median(&mut intercepts), slope)
+}
+
+// two-sided 95% quantiles of Student's t-distribution for 1 to 10 degrees of freedom
+const T_95: [f64; 10] = [
+    12.706, 4.303, 3.182, 2.776, 2.571, 2.447, 2.365, 2.306, 2.262, 2.228,
+];
+
+// two-sided 95% quantile of Student's t-distribution with the given degrees of freedom; beyond the
+// tabulated values, it is approximated by the Cornish-Fisher expansion around the normal quantile
+fn student_t_95(df: usize) -> f64 {
+    if (1..=T_95.len()).contains(&df) {
+        return T_95[df - 1];
+    }
+    let (z, v) = (Z_95, df as f64);
+    z + (z.powi(3) + z) / (4.0 * v)
+        + (5.0 * z.powi(5) + 16.0 * z.powi(3) + 3.0 * z) / (96.0 * v.powi(2))
+        + (3.0 * z.powi(7) + 19.0 * z.powi(5) + 17.0 * z.powi(3) - 15.0 * z) / (384.0 * v.powi(3))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn heaps_growth(n: usize, gamma: f64) -> Vec<f64> {
+        (0..=n)
+            .map(|i| {
+                if i == 0 {
+                    f64::NAN
+                } else {
+                    1000.0 * (i as f64).powf(gamma)
+                }
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_fit_recovers_exponent() {
+        let growth = heaps_growth(20, 0.4);
+        for method in [AlphaMethod::Ols, AlphaMethod::Huber, AlphaMethod::TheilSen] {
+            let fit = HeapsFit::from_growth(&growth, method, None, None).unwrap();
+            assert!((fit.gamma - 0.4).abs() < 1e-6, "{}", fit);
+            assert!((fit.k - 1000.0).abs() < 1e-3, "{}", fit);
+            assert!(fit.r2 > 0.999999);
+            assert_eq!((fit.from, fit.to), (2, 20));
+        }
+        let fit = HeapsFit::from_growth(&growth, AlphaMethod::Ols, Some(1), Some(10)).unwrap();
+        assert_eq!((fit.from, fit.to), (1, 10));
+        assert!(HeapsFit::from_growth(&growth, AlphaMethod::Ols, Some(5), Some(6)).is_none());
+    }
+
+    #[test]
+    fn test_robust_fits_ignore_outlier() {
+        let mut growth = heaps_growth(20, 0.4);
+        growth[20] *= 3.0;
+        let ols = HeapsFit::from_growth(&growth, AlphaMethod::Ols, None, None).unwrap();
+        let huber = HeapsFit::from_growth(&growth, AlphaMethod::Huber, None, None).unwrap();
+        let theil_sen = HeapsFit::from_growth(&growth, AlphaMethod::TheilSen, None, None).unwrap();
+        assert!((ols.gamma - 0.4).abs() > 0.01);
+        assert!((huber.gamma - 0.4).abs() < (ols.gamma - 0.4).abs());
+        assert!((theil_sen.gamma - 0.4).abs() < 1e-6);
+    }
+}
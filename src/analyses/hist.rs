@@ -1,19 +1,23 @@
 use core::panic;
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::io::{BufWriter, Write};
 
-use crate::analysis_parameter::AnalysisParameter;
-use crate::graph_broker::GraphBroker;
+use crate::analysis_parameter::{AnalysisParameter, UncoveredMode};
+use crate::graph_broker::{GraphBroker, Hist as CoverageHist};
 use crate::html_report::ReportItem;
 use crate::{
     analyses::InputRequirement,
-    io::write_table,
-    util::{get_default_plot_downloads, CountType},
+    io::{write_long_table, write_table},
+    util::{get_default_plot_downloads, to_id, CountType},
 };
 
 use super::{Analysis, AnalysisSection, ConstructibleAnalysis};
 
 pub struct Hist {
     parameter: AnalysisParameter,
+    // number of countables of each type that are not covered by any counted path
+    uncovered: Option<HashMap<CountType, usize>>,
 }
 
 impl Analysis for Hist {
@@ -30,8 +34,21 @@ impl Analysis for Hist {
             panic!("Hist analysis needs a graph")
         }
         let gb = gb.unwrap();
+        self.set_uncovered(gb)?;
         let mut res = String::new();
         res.push_str(&crate::io::write_metadata_comments()?);
+        if let Some(comment) = gb.get_dedup_comment() {
+            res.push_str(&comment);
+        }
+        if self.get_uncovered_mode() == UncoveredMode::Report {
+            for h in gb.get_hists().values() {
+                res.push_str(&format!(
+                    "# not covered by any path (left out of coverage 0): {} {}\n",
+                    self.get_uncovered(h),
+                    h.count
+                ));
+            }
+        }
 
         let mut header_cols = vec![vec![
             "panacus".to_string(),
@@ -41,7 +58,7 @@ impl Analysis for Hist {
         ]];
         let mut output_columns = Vec::new();
         for h in gb.get_hists().values() {
-            output_columns.push(h.coverage.iter().map(|x| *x as f64).collect());
+            output_columns.push(self.get_coverage(h).iter().map(|x| *x as f64).collect());
             header_cols.push(vec![
                 "hist".to_string(),
                 h.count.to_string(),
@@ -49,7 +66,32 @@ impl Analysis for Hist {
                 String::new(),
             ])
         }
-        res.push_str(&write_table(&header_cols, &output_columns)?);
+        if let Some(name) = self.get_transform_name() {
+            let (cumulative, normalized) = self.get_transform();
+            for h in gb.get_hists().values() {
+                output_columns.push(transform_hist(
+                    &self.get_coverage(h),
+                    cumulative,
+                    normalized,
+                ));
+                header_cols.push(vec![
+                    name.clone(),
+                    h.count.to_string(),
+                    String::new(),
+                    String::new(),
+                ])
+            }
+        }
+        if let AnalysisParameter::Hist { long: true, .. } = &self.parameter {
+            res.push_str(&write_long_table(
+                &header_cols,
+                &output_columns,
+                0,
+                "coverage",
+            )?);
+        } else {
+            res.push_str(&write_table(&header_cols, &output_columns)?);
+        }
         Ok(res)
     }
 
@@ -63,32 +105,52 @@ impl Analysis for Hist {
         let gb = gb.unwrap();
         let table = self.generate_table(Some(gb))?;
         let table = format!("`{}`", &table);
-        let id_prefix = format!(
-            "cov-hist-{}",
-            self.get_run_id(gb)
-                .to_lowercase()
-                .replace(&[' ', '|', '\\'], "-")
-        );
+        let id_prefix = format!("cov-hist-{}", to_id(&self.get_run_id(gb)));
         let histogram_tabs = gb
             .get_hists()
             .iter()
-            .map(|(k, v)| AnalysisSection {
+            .map(|(k, v)| (k, self.get_coverage(v), self.get_uncovered(v)))
+            .map(|(k, coverage, uncovered)| AnalysisSection {
                 id: format!("{id_prefix}-{k}"),
                 analysis: "Coverage Histogram".to_string(),
                 table: Some(table.clone()),
                 run_name: self.get_run_name(gb),
                 run_id: self.get_run_id(gb),
                 countable: k.to_string(),
-                items: vec![ReportItem::Bar {
+                items: std::iter::once(ReportItem::Bar {
                     id: format!("{id_prefix}-{k}"),
                     name: gb.get_fname(),
                     x_label: "taxa".to_string(),
                     y_label: format!("#{}s", k),
-                    labels: (0..v.coverage.len()).map(|s| s.to_string()).collect(),
-                    values: v.coverage.iter().map(|c| *c as f64).collect(),
+                    labels: (0..coverage.len()).map(|s| s.to_string()).collect(),
+                    values: coverage.iter().map(|c| *c as f64).collect(),
                     log_toggle: true,
-                }],
+                })
+                .chain(self.get_transform_name().map(|name| {
+                    let (cumulative, normalized) = self.get_transform();
+                    ReportItem::Bar {
+                        id: format!("{id_prefix}-{k}-{name}"),
+                        name: gb.get_fname(),
+                        x_label: "taxa".to_string(),
+                        y_label: match (cumulative, normalized) {
+                            (true, true) => format!("cumulative fraction of {}s", k),
+                            (true, false) => format!("cumulative #{}s", k),
+                            _ => format!("fraction of {}s", k),
+                        },
+                        labels: (0..coverage.len()).map(|s| s.to_string()).collect(),
+                        values: transform_hist(&coverage, cumulative, normalized),
+                        log_toggle: !normalized,
+                    }
+                }))
+                .collect(),
                 plot_downloads: get_default_plot_downloads(),
+                description: match self.get_uncovered_mode() {
+                    UncoveredMode::Report => Some(format!(
+                        "{} {}s are not covered by any path of the graph and are left out of coverage 0",
+                        uncovered, k
+                    )),
+                    _ => None,
+                },
             })
             .collect::<Vec<_>>();
         Ok(histogram_tabs)
@@ -103,11 +165,26 @@ impl Analysis for Hist {
             HashSet::new()
         }
     }
+
+    fn uses_hists_only(&self) -> bool {
+        // countables not covered by any path are counted on the graph
+        match &self.parameter {
+            AnalysisParameter::Hist {
+                uncovered,
+                list_uncovered,
+                ..
+            } => *uncovered == UncoveredMode::Include && list_uncovered.is_none(),
+            _ => false,
+        }
+    }
 }
 
 impl ConstructibleAnalysis for Hist {
     fn from_parameter(parameter: AnalysisParameter) -> Self {
-        Self { parameter }
+        Self {
+            parameter,
+            uncovered: None,
+        }
     }
 }
 
@@ -117,6 +194,7 @@ impl Hist {
             CountType::Bp => HashSet::from([InputRequirement::Bp]),
             CountType::Node => HashSet::from([InputRequirement::Node]),
             CountType::Edge => HashSet::from([InputRequirement::Edge]),
+            CountType::Bubble => HashSet::from([InputRequirement::Bubble]),
             CountType::All => HashSet::from([
                 InputRequirement::Bp,
                 InputRequirement::Node,
@@ -125,6 +203,111 @@ impl Hist {
         }
     }
 
+    fn get_uncovered_mode(&self) -> UncoveredMode {
+        match &self.parameter {
+            AnalysisParameter::Hist { uncovered, .. } => *uncovered,
+            _ => UncoveredMode::Include,
+        }
+    }
+
+    // counts the countables that are not covered by any counted path, if they are left out of
+    // coverage 0 or listed; excluded countables are not among them
+    fn set_uncovered(&mut self, gb: &GraphBroker) -> anyhow::Result<()> {
+        let list_uncovered = match &self.parameter {
+            AnalysisParameter::Hist { list_uncovered, .. } => list_uncovered.clone(),
+            _ => None,
+        };
+        if self.uncovered.is_some()
+            || (self.get_uncovered_mode() == UncoveredMode::Include && list_uncovered.is_none())
+        {
+            return Ok(());
+        }
+        let node_lens = gb.get_node_lens();
+        let mut uncovered = HashMap::new();
+        let mut nodes = None;
+        for count in gb.get_hists().keys() {
+            let ids = &gb.get_abacus_by_total(*count).uncovered;
+            let n = match count {
+                CountType::Bp => ids.iter().map(|id| node_lens[*id as usize] as usize).sum(),
+                _ => ids.len(),
+            };
+            log::info!("{} {}s are not covered by any path", n, count);
+            uncovered.insert(*count, n);
+            if matches!(count, CountType::Node | CountType::Bp) {
+                nodes = Some(ids);
+            }
+        }
+        if let Some(file) = list_uncovered {
+            let nodes = nodes.ok_or_else(|| {
+                anyhow::anyhow!(
+                    "nodes not covered by any path can only be listed when counting nodes or bp"
+                )
+            })?;
+            log::info!(
+                "writing {} nodes not covered by any path to {}",
+                nodes.len(),
+                file
+            );
+            let names = gb.get_node_names();
+            let mut out = BufWriter::new(
+                File::create(&file)
+                    .map_err(|e| anyhow::anyhow!("unable to create file {}: {}", file, e))?,
+            );
+            crate::run_summary::add_output_file(&file);
+            writeln!(out, "name\tlength")?;
+            for id in nodes {
+                let id = *id as usize;
+                writeln!(out, "{}\t{}", names[id], node_lens[id])?;
+            }
+            out.flush()?;
+        }
+        self.uncovered = Some(uncovered);
+        Ok(())
+    }
+
+    fn get_uncovered(&self, h: &CoverageHist) -> usize {
+        self.uncovered
+            .as_ref()
+            .and_then(|x| x.get(&h.count))
+            .copied()
+            .unwrap_or(0)
+    }
+
+    // histogram in which coverage 0 holds the countables not covered by any path only if they are
+    // included
+    fn get_coverage(&self, h: &CoverageHist) -> Vec<usize> {
+        let mut coverage = h.coverage.clone();
+        if self.get_uncovered_mode() != UncoveredMode::Include {
+            if let Some(c) = coverage.first_mut() {
+                *c = c.saturating_sub(self.get_uncovered(h));
+            }
+        }
+        coverage
+    }
+
+    fn get_transform(&self) -> (bool, bool) {
+        if let AnalysisParameter::Hist {
+            cumulative,
+            normalized,
+            ..
+        } = &self.parameter
+        {
+            (*cumulative, *normalized)
+        } else {
+            (false, false)
+        }
+    }
+
+    // name of the column holding the cumulative and/or normalized histogram, if requested
+    fn get_transform_name(&self) -> Option<String> {
+        match self.get_transform() {
+            (true, true) => Some("hist-cumulative-normalized".to_string()),
+            (true, false) => Some("hist-cumulative".to_string()),
+            (false, true) => Some("hist-normalized".to_string()),
+            (false, false) => None,
+        }
+    }
+
     fn get_run_name(&self, gb: &GraphBroker) -> String {
         format!("{}", gb.get_run_name())
     }
@@ -133,3 +316,67 @@ impl Hist {
         format!("{}-hist", gb.get_run_id())
     }
 }
+
+// cumulative histogram, i.e., the count of coverage at most i (index), and/or histogram given as
+// fractions of the total count
+fn transform_hist(coverage: &[usize], cumulative: bool, normalized: bool) -> Vec<f64> {
+    let total: usize = coverage.iter().sum();
+    let mut sum = 0;
+    coverage
+        .iter()
+        .map(|c| {
+            sum += c;
+            let value = if cumulative { sum } else { *c } as f64;
+            if normalized {
+                value / total as f64
+            } else {
+                value
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_transform_hist() {
+        let coverage = vec![2, 0, 3, 5];
+        assert_eq!(
+            transform_hist(&coverage, true, false),
+            vec![2.0, 2.0, 5.0, 10.0]
+        );
+        assert_eq!(
+            transform_hist(&coverage, false, true),
+            vec![0.2, 0.0, 0.3, 0.5]
+        );
+        assert_eq!(
+            transform_hist(&coverage, true, true),
+            vec![0.2, 0.2, 0.5, 1.0]
+        );
+    }
+
+    #[test]
+    fn test_get_coverage() {
+        let parameter = |uncovered| AnalysisParameter::Hist {
+            count_type: CountType::Node,
+            long: false,
+            cumulative: false,
+            normalized: false,
+            uncovered,
+            list_uncovered: None,
+        };
+        let h = CoverageHist {
+            count: CountType::Node,
+            coverage: vec![5, 3, 2],
+        };
+        let mut hist = Hist::from_parameter(parameter(UncoveredMode::Include));
+        hist.uncovered = Some(HashMap::from([(CountType::Node, 4)]));
+        assert_eq!(hist.get_coverage(&h), vec![5, 3, 2]);
+        let mut hist = Hist::from_parameter(parameter(UncoveredMode::Report));
+        hist.uncovered = Some(HashMap::from([(CountType::Node, 4)]));
+        assert_eq!(hist.get_coverage(&h), vec![1, 3, 2]);
+        assert_eq!(hist.get_uncovered(&h), 4);
+    }
+}
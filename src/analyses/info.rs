@@ -1,18 +1,27 @@
 use core::{fmt, panic};
 use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::io::{BufRead, BufWriter, Write};
 
 use crate::{
     analyses::{Analysis, AnalysisSection, InputRequirement},
     analysis_parameter::AnalysisParameter,
-    graph_broker::{Edge, GraphBroker, ItemId},
+    graph_broker::{BubbleStats, Edge, GraphBroker, ItemId, Orientation, PathSegment},
     html_report::ReportItem,
-    util::{averageu32, get_default_plot_downloads, median_already_sorted, n50_already_sorted},
+    io::{bufreader_from_compressed_gfa, is_graph_index},
+    util::{
+        averageu32, get_default_plot_downloads, median_already_sorted, n50_already_sorted, to_id,
+        CountType, ItemIdSize,
+    },
 };
 
 use super::ConstructibleAnalysis;
 
 pub struct Info {
+    parameter: AnalysisParameter,
     graph_info: Option<GraphInfo>,
+    complexity_info: Option<ComplexityInfo>,
+    edge_info: Option<EdgeInfo>,
     path_info: Option<PathInfo>,
     group_info: Option<GroupInfo>,
 }
@@ -20,7 +29,7 @@ pub struct Info {
 impl Analysis for Info {
     fn generate_table(&mut self, gb: Option<&GraphBroker>) -> anyhow::Result<String> {
         if self.group_info.is_none() || self.path_info.is_none() {
-            self.set_info(gb.expect("Cannot set info without a GraphBroker"));
+            self.set_info(gb.expect("Cannot set info without a GraphBroker"))?;
         }
         let mut res = format!(
             "# {}\n",
@@ -39,12 +48,14 @@ impl Analysis for Info {
         gb: Option<&GraphBroker>,
     ) -> anyhow::Result<Vec<AnalysisSection>> {
         if self.group_info.is_none() || self.path_info.is_none() {
-            self.set_info(gb.expect("Cannot set info without a GraphBroker"));
+            self.set_info(gb.expect("Cannot set info without a GraphBroker"))?;
         }
         let (graph_header, graph_values) = self.get_graph_table();
         let graph_values = Self::remove_duplication(graph_values);
         let (node_header, node_values) = self.get_node_table();
         let node_values = Self::remove_duplication(node_values);
+        let (complexity_header, complexity_values) = self.get_complexity_table();
+        let complexity_values = Self::remove_duplication(complexity_values);
         let (path_header, path_values) = self.get_path_table();
         let path_values = Self::remove_duplication(path_values);
 
@@ -52,10 +63,7 @@ impl Analysis for Info {
         let table = format!("`{}`", &table);
         let run_name = self.get_run_name(gb.expect("Info should be called with a graph"));
         let run_id = self.get_run_id(gb.expect("Info should be called with a graph"));
-        let safe_run_name = run_id
-            .clone()
-            .to_lowercase()
-            .replace(&[' ', '|', '\\'], "-");
+        let safe_run_name = to_id(&run_id);
         Ok(vec![
             AnalysisSection {
                 id: format!("{safe_run_name}-graph"),
@@ -70,6 +78,7 @@ impl Analysis for Info {
                     values: graph_values,
                 }],
                 plot_downloads: get_default_plot_downloads(),
+                description: None,
             },
             AnalysisSection {
                 id: format!("{safe_run_name}-node"),
@@ -84,6 +93,22 @@ impl Analysis for Info {
                     values: node_values,
                 }],
                 plot_downloads: get_default_plot_downloads(),
+                description: None,
+            },
+            AnalysisSection {
+                id: format!("{safe_run_name}-complexity"),
+                analysis: "Pangenome Info".to_string(),
+                run_name: run_name.clone(),
+                run_id: run_id.clone(),
+                countable: "Complexity Info".to_string(),
+                table: Some(table.clone()),
+                items: vec![ReportItem::Table {
+                    id: "info-4-table".to_string(),
+                    header: complexity_header,
+                    values: complexity_values,
+                }],
+                plot_downloads: get_default_plot_downloads(),
+                description: None,
             },
             AnalysisSection {
                 id: format!("{safe_run_name}-path"),
@@ -98,6 +123,7 @@ impl Analysis for Info {
                     values: path_values,
                 }],
                 plot_downloads: get_default_plot_downloads(),
+                description: None,
             },
             AnalysisSection {
                 id: format!("{safe_run_name}-group"),
@@ -109,8 +135,15 @@ impl Analysis for Info {
                 items: vec![
                     self.get_group_bar(&run_id, "node"),
                     self.get_group_bar(&run_id, "bp"),
+                    self.get_group_bar(&run_id, "path"),
+                    ReportItem::Table {
+                        id: format!("info-{}-group-table", run_id),
+                        header: GroupInfo::get_header(),
+                        values: self.group_info.as_ref().unwrap().get_rows(),
+                    },
                 ],
                 plot_downloads: get_default_plot_downloads(),
+                description: None,
             },
         ])
     }
@@ -127,9 +160,12 @@ impl Analysis for Info {
 }
 
 impl ConstructibleAnalysis for Info {
-    fn from_parameter(_parameter: AnalysisParameter) -> Self {
+    fn from_parameter(parameter: AnalysisParameter) -> Self {
         Self {
+            parameter,
             graph_info: None,
+            complexity_info: None,
+            edge_info: None,
             path_info: None,
             group_info: None,
         }
@@ -137,10 +173,61 @@ impl ConstructibleAnalysis for Info {
 }
 
 impl Info {
-    fn set_info(&mut self, gb: &GraphBroker) {
+    fn set_info(&mut self, gb: &GraphBroker) -> anyhow::Result<()> {
+        let (complexity, reference, node_ids, clean_gfa) = match &self.parameter {
+            AnalysisParameter::Info {
+                complexity,
+                reference,
+                node_ids,
+                clean_gfa,
+            } => (
+                *complexity,
+                reference.as_deref(),
+                node_ids.as_deref(),
+                clean_gfa.as_deref(),
+            ),
+            _ => (false, None, None, None),
+        };
+        if clean_gfa.is_some() && is_graph_index(&gb.get_fname()) {
+            anyhow::bail!(
+                "graph index {} does not contain sequences, use the graph file to write the cleaned graph",
+                gb.get_fname()
+            );
+        }
+        if let Some(file) = node_ids {
+            Self::write_node_ids(gb, file)?;
+        }
         self.graph_info = Some(GraphInfo::from(gb));
+        if complexity {
+            self.complexity_info = Some(ComplexityInfo::from(gb, reference)?);
+        }
+        let edge_info = EdgeInfo::from(gb);
+        if let Some(file) = clean_gfa {
+            edge_info.write_clean_gfa(gb, file)?;
+        }
+        self.edge_info = Some(edge_info);
         self.path_info = Some(PathInfo::from(gb));
-        self.group_info = Some(GroupInfo::from(gb));
+        self.group_info = Some(GroupInfo::from(gb)?);
+        Ok(())
+    }
+
+    // mapping of the internal node IDs, e.g., of the nodes listed for hexbin selections of the
+    // report, to the segment names of the graph and their lengths
+    fn write_node_ids(gb: &GraphBroker, file: &str) -> anyhow::Result<()> {
+        log::info!("writing node IDs to {}", file);
+        let names = gb.get_node_names();
+        let node_lens = gb.get_node_lens();
+        let mut out = BufWriter::new(
+            File::create(file)
+                .map_err(|e| anyhow::anyhow!("unable to create node ID file {}: {}", file, e))?,
+        );
+        crate::run_summary::add_output_file(file);
+        writeln!(out, "id\tname\tlength")?;
+        for (id, (name, len)) in names.iter().zip(node_lens).enumerate().skip(1) {
+            writeln!(out, "{}\t{}\t{}", id, name, len)?;
+        }
+        out.flush()?;
+        Ok(())
     }
 
     fn get_run_name(&self, gb: &GraphBroker) -> String {
@@ -241,18 +328,36 @@ impl Info {
         (header, values)
     }
 
+    fn get_complexity_table(&self) -> (Vec<String>, Vec<Vec<String>>) {
+        let header = Self::get_header();
+        let edge_rows = self.edge_info.iter().flat_map(|x| x.get_rows());
+        let values = self
+            .complexity_info
+            .iter()
+            .flat_map(|x| x.get_rows())
+            .chain(edge_rows)
+            .map(|(first, second, third, value)| Self::get_row(first, &second, third, value))
+            .collect();
+        (header, values)
+    }
+
     fn get_group_bar(&self, graph: &str, countable: &str) -> ReportItem {
-        let groups = &self.group_info.as_ref().unwrap().groups;
+        let group_info = self.group_info.as_ref().unwrap();
+        let groups = &group_info.groups;
         log::info!(
             "Gotten {} groups for graph {} and countable {}",
             groups.len(),
             graph,
             countable
         );
-        let (labels, values): (Vec<_>, Vec<_>) = if countable == "node" {
-            groups.iter().map(|(k, v)| (k.to_string(), v.0)).unzip()
-        } else {
-            groups.iter().map(|(k, v)| (k.to_string(), v.1)).unzip()
+        let (labels, values): (Vec<_>, Vec<_>) = match countable {
+            "node" => groups.iter().map(|(k, v)| (k.to_string(), v.0)).unzip(),
+            "path" => group_info
+                .path_counts
+                .iter()
+                .map(|(k, v)| (k.to_string(), *v as u32))
+                .unzip(),
+            _ => groups.iter().map(|(k, v)| (k.to_string(), v.1)).unzip(),
         };
         if labels.len() <= 100 {
             ReportItem::Bar {
@@ -438,6 +543,16 @@ impl fmt::Display for Info {
         writeln!(f, "node\tN50 node\tbp\t{}", graph_info.n50_node)?;
         writeln!(f, "node\tmax\tdegree\t{}", graph_info.max_degree)?;
         writeln!(f, "node\tmin\tdegree\t{}", graph_info.min_degree)?;
+        if let Some(complexity_info) = &self.complexity_info {
+            for (first, second, third, value) in complexity_info.get_rows() {
+                writeln!(f, "{}\t{}\t{}\t{}", first, second, third, value)?;
+            }
+        }
+        if let Some(edge_info) = &self.edge_info {
+            for (first, second, third, value) in edge_info.get_rows() {
+                writeln!(f, "{}\t{}\t{}\t{}", first, second, third, value)?;
+            }
+        }
         writeln!(f, "path\taverage\tbp\t{}", path_info.bp_len.average)?;
         writeln!(f, "path\taverage\tnode\t{}", path_info.node_len.average)?;
         writeln!(f, "path\tlongest\tbp\t{}", path_info.bp_len.longest)?;
@@ -445,11 +560,13 @@ impl fmt::Display for Info {
         writeln!(f, "path\tshortest\tbp\t{}", path_info.bp_len.shortest)?;
         write!(f, "path\tshortest\tnode\t{}", path_info.node_len.shortest)?;
         if let Some(group_info) = &self.group_info {
-            let mut sorted: Vec<_> = group_info.groups.clone().into_iter().collect();
-            sorted.sort_by(|(k0, _v0), (k1, _v1)| k0.cmp(k1));
-            for (k, v) in sorted {
-                write!(f, "\ngroup\t{}\tbp\t{}\n", k, v.1)?;
-                write!(f, "group\t{}\tnode\t{}", k, v.0)?;
+            for row in group_info.get_rows() {
+                let k = &row[0];
+                write!(f, "\ngroup\t{}\tbp\t{}\n", k, row[3])?;
+                writeln!(f, "group\t{}\tnode\t{}", k, row[2])?;
+                writeln!(f, "group\t{}\tpath\t{}", k, row[1])?;
+                writeln!(f, "group\t{}\texclusive node\t{}", k, row[4])?;
+                write!(f, "group\t{}\texclusive bp\t{}", k, row[5])?;
             }
         }
         Ok(())
@@ -506,6 +623,248 @@ impl GraphInfo {
     }
 }
 
+pub struct ComplexityInfo {
+    pub bubble_count: usize,
+    pub top_level_bubble_count: usize,
+    pub max_nesting_depth: usize,
+    pub average_bubble_nodes: f64,
+    pub average_bubble_bp: f64,
+    pub branching_node_count: usize,
+    // branching nodes per Mbp of the reference, or of the graph if no reference is given
+    pub branching_density: f64,
+    pub reference: Option<String>,
+}
+
+impl ComplexityInfo {
+    fn from(gb: &GraphBroker, reference: Option<&str>) -> anyhow::Result<Self> {
+        let stats = BubbleStats::from_edges(gb.get_node_count(), gb.get_edges().keys());
+        let node_lens = gb.get_node_lens();
+        let bubble_count = stats.bubbles.len();
+        let average = |total: usize| {
+            if bubble_count == 0 {
+                0.0
+            } else {
+                total as f64 / bubble_count as f64
+            }
+        };
+
+        // branching nodes are related to the bp of the reference they lie on, or of the graph
+        let (branching, bps) = match reference {
+            Some(r) => {
+                let fragments = gb.get_path_steps(&PathSegment::from_str(r))?;
+                if fragments.is_empty() {
+                    anyhow::bail!(
+                        "reference path {} does not exist in graph {}",
+                        r,
+                        gb.get_fname()
+                    );
+                }
+                let branching: HashSet<&ItemIdSize> = stats.branching_nodes.iter().collect();
+                let mut nodes: HashSet<ItemIdSize> = HashSet::new();
+                let mut bps = 0;
+                for (_, steps) in fragments {
+                    for (node, _) in steps {
+                        bps += node_lens[node.0 as usize] as usize;
+                        nodes.insert(node.0);
+                    }
+                }
+                (nodes.iter().filter(|n| branching.contains(n)).count(), bps)
+            }
+            None => (
+                stats.branching_nodes.len(),
+                node_lens.iter().map(|l| *l as usize).sum(),
+            ),
+        };
+
+        Ok(Self {
+            bubble_count,
+            top_level_bubble_count: stats.bubbles.iter().filter(|(_, d)| *d == 1).count(),
+            max_nesting_depth: stats.bubbles.iter().map(|(_, d)| *d).max().unwrap_or(0),
+            average_bubble_nodes: average(stats.bubbles.iter().map(|(n, _)| n.len()).sum()),
+            average_bubble_bp: average(
+                stats
+                    .bubbles
+                    .iter()
+                    .flat_map(|(n, _)| n.iter())
+                    .map(|n| node_lens[*n as usize] as usize)
+                    .sum(),
+            ),
+            branching_node_count: stats.branching_nodes.len(),
+            branching_density: if bps == 0 {
+                0.0
+            } else {
+                branching as f64 * 1_000_000.0 / bps as f64
+            },
+            reference: reference.map(|r| r.to_string()),
+        })
+    }
+
+    fn get_rows(&self) -> Vec<(&str, String, &str, String)> {
+        vec![
+            (
+                "bubble",
+                "total".to_string(),
+                "bubble",
+                self.bubble_count.to_string(),
+            ),
+            (
+                "bubble",
+                "total".to_string(),
+                "top-level bubble",
+                self.top_level_bubble_count.to_string(),
+            ),
+            (
+                "bubble",
+                "max".to_string(),
+                "nesting depth",
+                self.max_nesting_depth.to_string(),
+            ),
+            (
+                "bubble",
+                "average".to_string(),
+                "node",
+                format!("{:.2}", self.average_bubble_nodes),
+            ),
+            (
+                "bubble",
+                "average".to_string(),
+                "bp",
+                format!("{:.2}", self.average_bubble_bp),
+            ),
+            (
+                "node",
+                "total".to_string(),
+                "branching node",
+                self.branching_node_count.to_string(),
+            ),
+            (
+                "node",
+                match &self.reference {
+                    Some(r) => format!("per Mbp of {}", r),
+                    None => "per Mbp".to_string(),
+                },
+                "branching node",
+                format!("{:.2}", self.branching_density),
+            ),
+        ]
+    }
+}
+
+pub struct EdgeInfo {
+    // edges that are not traversed by any counted path, and thus do not contribute to edge-based
+    // counts
+    pub unused: HashSet<Edge>,
+    // edges u->w for which there is a node v with edges u->v and v->w
+    pub transitive: HashSet<Edge>,
+}
+
+impl EdgeInfo {
+    fn from(gb: &GraphBroker) -> Self {
+        let uncovered: HashSet<ItemIdSize> = gb
+            .get_abacus_by_total(CountType::Edge)
+            .uncovered
+            .iter()
+            .copied()
+            .collect();
+        let unused = gb
+            .get_edges()
+            .iter()
+            .filter(|(_, id)| uncovered.contains(&id.0))
+            .map(|(edge, _)| *edge)
+            .collect::<HashSet<Edge>>();
+        let transitive = get_transitive_edges(gb.get_edges().keys());
+        log::info!(
+            "found {} edges not used by any path and {} transitive edges",
+            unused.len(),
+            transitive.len()
+        );
+        Self { unused, transitive }
+    }
+
+    // copy of the graph without the unused edges; transitive edges that are used by paths are
+    // kept, as the paths would be broken otherwise; the graph is written in GFA1 as it is read,
+    // i.e., GFA2 graphs are translated and the graphs of comma-separated lists are concatenated
+    fn write_clean_gfa(&self, gb: &GraphBroker, file: &str) -> anyhow::Result<()> {
+        log::info!(
+            "writing graph without {} unused edges to {}",
+            self.unused.len(),
+            file
+        );
+        let node2id: HashMap<Vec<u8>, ItemId> = gb
+            .get_gfa_node_names()
+            .into_iter()
+            .enumerate()
+            .skip(1)
+            .map(|(id, name)| (name.into_bytes(), ItemId(id as ItemIdSize)))
+            .collect();
+        let mut data = bufreader_from_compressed_gfa(&gb.get_fname())?;
+        let mut out = BufWriter::new(
+            File::create(file)
+                .map_err(|e| anyhow::anyhow!("unable to create GFA file {}: {}", file, e))?,
+        );
+        crate::run_summary::add_output_file(file);
+        let mut buf = Vec::new();
+        while data.read_until(b'\n', &mut buf)? > 0 {
+            let is_unused = buf[0] == b'L'
+                && Edge::from_link(&buf, &node2id, true)
+                    .map_or(false, |edge| self.unused.contains(&edge));
+            if !is_unused {
+                out.write_all(&buf)?;
+            }
+            buf.clear();
+        }
+        out.flush()?;
+        Ok(())
+    }
+
+    fn get_rows(&self) -> Vec<(&str, String, &str, String)> {
+        vec![
+            (
+                "edge",
+                "total".to_string(),
+                "unused edge",
+                self.unused.len().to_string(),
+            ),
+            (
+                "edge",
+                "total".to_string(),
+                "transitive edge",
+                self.transitive.len().to_string(),
+            ),
+            (
+                "edge",
+                "total".to_string(),
+                "unused transitive edge",
+                self.unused
+                    .intersection(&self.transitive)
+                    .count()
+                    .to_string(),
+            ),
+        ]
+    }
+}
+
+// canonical edges u->w that are implied by a detour u->v->w over a third node v, following the
+// orientations of the nodes
+fn get_transitive_edges<'a, I: Iterator<Item = &'a Edge>>(edges: I) -> HashSet<Edge> {
+    let edges: HashSet<Edge> = edges.copied().collect();
+    let mut successors: HashMap<(ItemId, Orientation), Vec<(ItemId, Orientation)>> = HashMap::new();
+    for edge in &edges {
+        for Edge(u, o1, v, o2) in [*edge, edge.flip()] {
+            successors.entry((u, o1)).or_default().push((v, o2));
+        }
+    }
+    edges
+        .iter()
+        .filter(|Edge(u, o1, w, o2)| {
+            successors[&(*u, *o1)].iter().any(|(v, ov)| {
+                v != u && v != w && edges.contains(&Edge::canonical(*v, *ov, *w, *o2))
+            })
+        })
+        .copied()
+        .collect()
+}
+
 pub struct PathInfo {
     pub no_paths: usize,
     pub node_len: LenInfo,
@@ -541,23 +900,103 @@ pub struct LenInfo {
 
 pub struct GroupInfo {
     pub groups: HashMap<String, (u32, u32)>,
+    pub path_counts: HashMap<String, usize>,
+    // number of nodes and bp traversed only by the paths of the group
+    pub exclusive: HashMap<String, (usize, usize)>,
 }
 
 impl GroupInfo {
-    fn from(gb: &GraphBroker) -> Self {
-        let groups = gb.get_groups();
+    fn from(gb: &GraphBroker) -> anyhow::Result<Self> {
+        // walks are grouped by their sample if no grouping is given, instead of each walk forming
+        // its own group
+        let groups: HashMap<PathSegment, String> = if !gb.has_grouping() && gb.has_walks_only() {
+            log::info!("no grouping given, reporting group info of walks by their sample");
+            gb.get_groups()
+                .keys()
+                .map(|p| (p.clone(), p.sample.clone()))
+                .collect()
+        } else {
+            gb.get_groups().clone()
+        };
         let mut group_map: HashMap<String, (u32, u32)> = HashMap::new();
+        let mut path_counts: HashMap<String, usize> = HashMap::new();
         for (k, v) in gb.get_path_lens() {
             if !groups.contains_key(&k.clear_coords()) {
                 continue;
             }
             let group = groups[&k.clear_coords()].clone();
+            *path_counts.entry(group.clone()).or_insert(0) += 1;
             let tmp = group_map.entry(group).or_insert((0, 0));
             tmp.0 += v.0;
             tmp.1 += v.1;
         }
 
-        GroupInfo { groups: group_map }
+        // the group traversing each node, or None if it is traversed by more than one group
+        let mut node_groups: HashMap<ItemIdSize, Option<&str>> = HashMap::new();
+        gb.visit_path_steps(
+            |path_seg| groups.contains_key(&path_seg.clear_coords()),
+            |path_seg, steps| {
+                let group = groups[&path_seg.clear_coords()].as_str();
+                for (node, _) in steps {
+                    node_groups
+                        .entry(node.0)
+                        .and_modify(|g| {
+                            if *g != Some(group) {
+                                *g = None;
+                            }
+                        })
+                        .or_insert(Some(group));
+                }
+            },
+        )?;
+        let node_lens = gb.get_node_lens();
+        let mut exclusive: HashMap<String, (usize, usize)> = HashMap::new();
+        for (node, group) in node_groups {
+            if let Some(group) = group {
+                let tmp = exclusive.entry(group.to_string()).or_insert((0, 0));
+                tmp.0 += 1;
+                tmp.1 += node_lens[node as usize] as usize;
+            }
+        }
+
+        Ok(GroupInfo {
+            groups: group_map,
+            path_counts,
+            exclusive,
+        })
+    }
+
+    // one row per group, sorted by group name
+    fn get_rows(&self) -> Vec<Vec<String>> {
+        let mut names: Vec<&String> = self.groups.keys().collect();
+        names.sort();
+        names
+            .into_iter()
+            .map(|k| {
+                let (nodes, bp) = self.groups[k];
+                let (exclusive_nodes, exclusive_bp) =
+                    self.exclusive.get(k).copied().unwrap_or((0, 0));
+                vec![
+                    k.to_string(),
+                    self.path_counts.get(k).copied().unwrap_or(0).to_string(),
+                    nodes.to_string(),
+                    bp.to_string(),
+                    exclusive_nodes.to_string(),
+                    exclusive_bp.to_string(),
+                ]
+            })
+            .collect()
+    }
+
+    fn get_header() -> Vec<String> {
+        vec![
+            "group".to_string(),
+            "path".to_string(),
+            "node".to_string(),
+            "bp".to_string(),
+            "exclusive node".to_string(),
+            "exclusive bp".to_string(),
+        ]
     }
 }
 
@@ -601,3 +1040,37 @@ fn dfs(edges: &HashMap<ItemId, Vec<ItemId>>, node: ItemId, visited: &mut HashSet
     }
     length
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn edge(u: ItemIdSize, o1: u8, v: ItemIdSize, o2: u8) -> Edge {
+        Edge::canonical(
+            ItemId(u),
+            Orientation::from_pm(o1),
+            ItemId(v),
+            Orientation::from_pm(o2),
+        )
+    }
+
+    #[test]
+    fn test_get_transitive_edges() {
+        let edges = vec![
+            edge(1, b'+', 2, b'+'),
+            edge(2, b'+', 3, b'+'),
+            edge(1, b'+', 3, b'+'),
+            // 3+ -> 4- -> 5+ is written from the other strand as 5- -> 4+ -> 3-
+            edge(5, b'-', 4, b'+'),
+            edge(4, b'+', 3, b'-'),
+            edge(3, b'+', 5, b'+'),
+            // no detour over 2-, as 1+ is followed by 2+ only
+            edge(2, b'-', 6, b'+'),
+            edge(1, b'+', 6, b'+'),
+        ];
+        assert_eq!(
+            get_transitive_edges(edges.iter()),
+            HashSet::from([edge(1, b'+', 3, b'+'), edge(3, b'+', 5, b'+')])
+        );
+    }
+}
@@ -0,0 +1,504 @@
+use std::collections::HashSet;
+use std::io::BufRead;
+
+use rayon::prelude::*;
+
+use crate::{
+    analysis_parameter::AnalysisParameter,
+    graph_broker::{GraphBroker, ItemId, Orientation},
+    html_report::{AnalysisSection, ReportItem},
+    io::bufreader_from_compressed_file,
+    util::{get_default_plot_downloads, to_id, CountType},
+};
+
+use super::{Analysis, ConstructibleAnalysis, InputRequirement};
+
+// k-mers are encoded with 2 bits per base in a u64
+const MAX_K: usize = 32;
+
+pub struct KmerComparison {
+    parameter: AnalysisParameter,
+    // number of distinct (sampled) k-mers of the graph, and of those found in any assembly
+    graph_kmers: usize,
+    graph_kmers_in_assemblies: usize,
+    assemblies: Vec<AssemblyKmers>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct AssemblyKmers {
+    file: String,
+    sequences: usize,
+    bp: u64,
+    // number of distinct (sampled) k-mers of the assembly, and of those found in the graph
+    kmers: usize,
+    kmers_in_graph: usize,
+}
+
+impl AssemblyKmers {
+    fn get_containment(&self) -> f64 {
+        if self.kmers == 0 {
+            0.0
+        } else {
+            self.kmers_in_graph as f64 / self.kmers as f64
+        }
+    }
+}
+
+impl Analysis for KmerComparison {
+    fn get_type(&self) -> String {
+        "KmerComparison".to_string()
+    }
+
+    fn generate_table(&mut self, gb: Option<&GraphBroker>) -> anyhow::Result<String> {
+        log::info!("reporting k-mer comparison of graph and assemblies");
+        let gb = gb.expect("KmerComparison analysis needs a graph");
+        self.set_comparison(gb)?;
+        let (k, scale) = self.get_k_and_scale();
+
+        let mut res = String::new();
+        res.push_str(&crate::io::write_metadata_comments()?);
+        res.push_str(&format!(
+            "# k={}, scale={}; k-mers of the graph are those spelled by its paths/walks and nodes\n",
+            k, scale
+        ));
+        res.push_str(&format!(
+            "# graph\t{}\t{}\n",
+            self.graph_kmers, self.graph_kmers_in_assemblies
+        ));
+        res.push_str(&format!("{}\n", Self::get_header().join("\t")));
+        for row in self.get_rows() {
+            res.push_str(&format!("{}\n", row.join("\t")));
+        }
+        Ok(res)
+    }
+
+    fn generate_report_section(
+        &mut self,
+        gb: Option<&GraphBroker>,
+    ) -> anyhow::Result<Vec<AnalysisSection>> {
+        let gb = gb.expect("KmerComparison analysis needs a graph");
+        let table = self.generate_table(Some(gb))?;
+        let table = format!("`{}`", &table);
+        let id_prefix = format!("kmer-comparison-{}", to_id(&self.get_run_id(gb)));
+        let (k, _) = self.get_k_and_scale();
+
+        Ok(vec![AnalysisSection {
+            id: id_prefix.clone(),
+            analysis: "K-mer Comparison".to_string(),
+            table: Some(table),
+            run_name: self.get_run_name(gb),
+            run_id: self.get_run_id(gb),
+            countable: CountType::Bp.to_string(),
+            items: vec![
+                ReportItem::Bar {
+                    id: format!("{id_prefix}-containment"),
+                    name: gb.get_fname(),
+                    x_label: "assembly".to_string(),
+                    y_label: format!("% {}-mers in graph", k),
+                    labels: self.assemblies.iter().map(|a| a.file.clone()).collect(),
+                    values: self
+                        .assemblies
+                        .iter()
+                        .map(|a| 100.0 * a.get_containment())
+                        .collect(),
+                    log_toggle: false,
+                },
+                ReportItem::Table {
+                    id: format!("{id_prefix}-table"),
+                    header: Self::get_header(),
+                    values: self.get_rows(),
+                },
+            ],
+            plot_downloads: get_default_plot_downloads(),
+            description: None,
+        }])
+    }
+
+    fn get_graph_requirements(&self) -> HashSet<InputRequirement> {
+        HashSet::from([InputRequirement::Node])
+    }
+}
+
+impl ConstructibleAnalysis for KmerComparison {
+    fn from_parameter(parameter: AnalysisParameter) -> Self {
+        Self {
+            parameter,
+            graph_kmers: 0,
+            graph_kmers_in_assemblies: 0,
+            assemblies: Vec::new(),
+        }
+    }
+}
+
+impl KmerComparison {
+    fn get_k_and_scale(&self) -> (usize, u64) {
+        match &self.parameter {
+            AnalysisParameter::KmerComparison { k, scale, .. } => (*k, *scale),
+            _ => panic!("KmerComparison analysis needs a k-mer comparison parameter"),
+        }
+    }
+
+    fn get_assembly_files(&self) -> &[String] {
+        match &self.parameter {
+            AnalysisParameter::KmerComparison { assemblies, .. } => assemblies,
+            _ => panic!("KmerComparison analysis needs a k-mer comparison parameter"),
+        }
+    }
+
+    fn set_comparison(&mut self, gb: &GraphBroker) -> anyhow::Result<()> {
+        if !self.assemblies.is_empty() {
+            return Ok(());
+        }
+        let (k, scale) = self.get_k_and_scale();
+        if k == 0 || k > MAX_K {
+            anyhow::bail!("k-mer size must be between 1 and {}, but is {}", MAX_K, k);
+        }
+        if scale == 0 {
+            anyhow::bail!("scale of the k-mer sample must be at least 1");
+        }
+        let graph_kmers = collect_graph_kmers(gb, k, scale)?;
+        log::info!(
+            "found {} distinct {}-mers in the graph",
+            graph_kmers.len(),
+            k
+        );
+
+        let results = self
+            .get_assembly_files()
+            .par_iter()
+            .map(|file| compare_assembly(file, k, scale, &graph_kmers))
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        let mut found = HashSet::new();
+        for (assembly, assembly_found) in results {
+            found.extend(assembly_found);
+            self.assemblies.push(assembly);
+        }
+        self.graph_kmers = graph_kmers.len();
+        self.graph_kmers_in_assemblies = found.len();
+        Ok(())
+    }
+
+    fn get_header() -> Vec<String> {
+        vec![
+            "assembly".to_string(),
+            "sequences".to_string(),
+            "bp".to_string(),
+            "k-mers".to_string(),
+            "k-mers in graph".to_string(),
+            "containment".to_string(),
+        ]
+    }
+
+    fn get_rows(&self) -> Vec<Vec<String>> {
+        self.assemblies
+            .iter()
+            .map(|a| {
+                vec![
+                    a.file.clone(),
+                    a.sequences.to_string(),
+                    a.bp.to_string(),
+                    a.kmers.to_string(),
+                    a.kmers_in_graph.to_string(),
+                    format!("{:.4}", a.get_containment()),
+                ]
+            })
+            .collect()
+    }
+
+    fn get_run_name(&self, gb: &GraphBroker) -> String {
+        gb.get_run_name()
+    }
+
+    fn get_run_id(&self, gb: &GraphBroker) -> String {
+        format!("{}-kmercomparison", gb.get_run_id())
+    }
+}
+
+// k-merizes the node sequences and the junctions of nodes along the paths/walks, such that k-mers
+// spanning node boundaries are found as well
+fn collect_graph_kmers(gb: &GraphBroker, k: usize, scale: u64) -> anyhow::Result<HashSet<u64>> {
+    log::info!("collecting {}-mers of node sequences", k);
+    let mut scanner = KmerScanner::new(k, scale);
+    let mut kmers = HashSet::new();
+    let mut ends = NodeEnds::new(k, gb.get_node_lens().len());
+    gb.visit_node_sequences(|id, seq| {
+        scanner.reset();
+        kmers.extend(seq.iter().filter_map(|c| scanner.push(*c)));
+        ends.add(id, seq);
+    })?;
+    log::info!(
+        "collecting {}-mers spanning node boundaries of paths/walks",
+        k
+    );
+    gb.visit_path_steps(
+        |_| true,
+        |_, steps| {
+            scanner.reset();
+            ends.spell(&steps, |c| kmers.extend(scanner.push(c)));
+        },
+    )?;
+    Ok(kmers)
+}
+
+// ends of the node sequences that make up the k-mers spanning node boundaries: the whole sequence
+// of nodes of at most 2 (k - 1) bp, and the first and last k - 1 bases of longer ones
+struct NodeEnds {
+    k: usize,
+    ends: Vec<Vec<u8>>,
+    // whether the middle of the node sequence is left out
+    cut: Vec<bool>,
+}
+
+impl NodeEnds {
+    fn new(k: usize, nodes: usize) -> Self {
+        Self {
+            k,
+            ends: vec![Vec::new(); nodes],
+            cut: vec![false; nodes],
+        }
+    }
+
+    fn add(&mut self, id: ItemId, seq: &[u8]) {
+        let (i, n) = (id.0 as usize, self.k - 1);
+        if i >= self.ends.len() {
+            return;
+        }
+        if seq.len() > 2 * n {
+            self.ends[i] = [&seq[..n], &seq[seq.len() - n..]].concat();
+            self.cut[i] = true;
+        } else {
+            self.ends[i] = seq.to_vec();
+        }
+    }
+
+    // spells the oriented node ends along the steps base by base, with an N where the middle of a
+    // node is left out, which only holds k-mers within the node
+    fn spell<V: FnMut(u8)>(&self, steps: &[(ItemId, Orientation)], mut visit: V) {
+        for (id, o) in steps {
+            let i = id.0 as usize;
+            let seq: Vec<u8> = match o {
+                Orientation::Forward => self.ends[i].clone(),
+                Orientation::Backward => {
+                    self.ends[i].iter().rev().map(|c| complement(*c)).collect()
+                }
+            };
+            for (j, c) in seq.iter().enumerate() {
+                if self.cut[i] && j == self.k - 1 {
+                    visit(b'N');
+                }
+                visit(*c);
+            }
+        }
+    }
+}
+
+fn complement(c: u8) -> u8 {
+    match c {
+        b'A' | b'a' => b'T',
+        b'C' | b'c' => b'G',
+        b'G' | b'g' => b'C',
+        b'T' | b't' => b'A',
+        _ => b'N',
+    }
+}
+
+// k-merizes the sequences of the FASTA file, returning the counts of the assembly and its k-mers
+// that are also k-mers of the graph
+fn compare_assembly(
+    file: &str,
+    k: usize,
+    scale: u64,
+    graph_kmers: &HashSet<u64>,
+) -> anyhow::Result<(AssemblyKmers, HashSet<u64>)> {
+    log::info!("collecting {}-mers of assembly {}", k, file);
+    let mut data = bufreader_from_compressed_file(file)?;
+    let mut scanner = KmerScanner::new(k, scale);
+    let mut kmers = HashSet::new();
+    let (mut sequences, mut bp) = (0, 0);
+    let mut buf = Vec::new();
+    while data.read_until(b'\n', &mut buf)? > 0 {
+        if buf[0] == b'>' {
+            sequences += 1;
+            scanner.reset();
+        } else {
+            let line = buf.strip_suffix(b"\n").unwrap_or(&buf);
+            let line = line.strip_suffix(b"\r").unwrap_or(line);
+            bp += line.len() as u64;
+            kmers.extend(line.iter().filter_map(|c| scanner.push(*c)));
+        }
+        buf.clear();
+    }
+    if kmers.is_empty() {
+        log::warn!("no {}-mer of assembly {} is sampled", k, file);
+    }
+    let found: HashSet<u64> = kmers.intersection(graph_kmers).copied().collect();
+    Ok((
+        AssemblyKmers {
+            file: file.to_string(),
+            sequences,
+            bp,
+            kmers: kmers.len(),
+            kmers_in_graph: found.len(),
+        },
+        found,
+    ))
+}
+
+// rolls over a sequence, yielding the canonical k-mer (the smaller of a k-mer and its reverse
+// complement) ending at each base, if it consists of A, C, G, and T only and is part of the
+// sample; a k-mer is sampled if its hash is among the smallest 1/scale of all hashes
+// (FracMinHash), such that the containment of assemblies is estimated from a fraction of k-mers
+struct KmerScanner {
+    k: usize,
+    mask: u64,
+    max_hash: u64,
+    forward: u64,
+    reverse: u64,
+    len: usize,
+}
+
+impl KmerScanner {
+    fn new(k: usize, scale: u64) -> Self {
+        Self {
+            k,
+            mask: if k == MAX_K {
+                u64::MAX
+            } else {
+                (1 << (2 * k)) - 1
+            },
+            max_hash: u64::MAX / scale,
+            forward: 0,
+            reverse: 0,
+            len: 0,
+        }
+    }
+
+    fn reset(&mut self) {
+        self.forward = 0;
+        self.reverse = 0;
+        self.len = 0;
+    }
+
+    fn push(&mut self, c: u8) -> Option<u64> {
+        let base = match c {
+            b'A' | b'a' => 0,
+            b'C' | b'c' => 1,
+            b'G' | b'g' => 2,
+            b'T' | b't' => 3,
+            _ => {
+                self.reset();
+                return None;
+            }
+        };
+        self.forward = ((self.forward << 2) | base) & self.mask;
+        self.reverse = (self.reverse >> 2) | ((3 - base) << (2 * (self.k - 1)));
+        self.len += 1;
+        if self.len < self.k {
+            return None;
+        }
+        let kmer = self.forward.min(self.reverse);
+        if hash(kmer) <= self.max_hash {
+            Some(kmer)
+        } else {
+            None
+        }
+    }
+}
+
+// finalizer of MurmurHash3, mixing the bits of the k-mer
+fn hash(mut x: u64) -> u64 {
+    x ^= x >> 33;
+    x = x.wrapping_mul(0xff51afd7ed558ccd);
+    x ^= x >> 33;
+    x = x.wrapping_mul(0xc4ceb9fe1a85ec53);
+    x ^= x >> 33;
+    x
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    fn kmers(seq: &[u8], k: usize) -> Vec<u64> {
+        let mut scanner = KmerScanner::new(k, 1);
+        seq.iter().filter_map(|c| scanner.push(*c)).collect()
+    }
+
+    #[test]
+    fn test_kmer_scanner() {
+        // ACG (0b000110) is the reverse complement of CGT (0b011011)
+        assert_eq!(kmers(b"ACGT", 3), vec![0b000110, 0b000110]);
+        assert_eq!(kmers(b"acgNacg", 3), vec![0b000110, 0b000110]);
+        assert_eq!(kmers(b"AC", 3), Vec::<u64>::new());
+        let seq = b"GATTACAGATTACAGATTACAGATTACAGATTACA";
+        let revcomp: Vec<u8> = seq
+            .iter()
+            .rev()
+            .map(|c| match c {
+                b'A' => b'T',
+                b'C' => b'G',
+                b'G' => b'C',
+                _ => b'A',
+            })
+            .collect();
+        let mut forward = kmers(seq, MAX_K);
+        let mut reverse = kmers(&revcomp, MAX_K);
+        forward.sort_unstable();
+        reverse.sort_unstable();
+        assert_eq!(forward.len(), seq.len() - MAX_K + 1);
+        assert_eq!(forward, reverse);
+    }
+
+    #[test]
+    fn test_node_ends() {
+        // node 1 (ACGTAC) is cut to ACAC for k = 3, node 2 (GG) is kept as it is
+        let mut ends = NodeEnds::new(3, 3);
+        ends.add(ItemId(1), b"ACGTAC");
+        ends.add(ItemId(2), b"GG");
+        let mut spelled = Vec::new();
+        let steps = [
+            (ItemId(1), Orientation::Forward),
+            (ItemId(2), Orientation::Forward),
+            (ItemId(1), Orientation::Backward),
+        ];
+        ends.spell(&steps, |c| spelled.push(c));
+        assert_eq!(spelled, b"ACNACGGGTNGT".to_vec());
+
+        // k-mers across node boundaries of the spelled sequence ACGTACGGGTACGT are those of ACGGGT
+        let mut scanner = KmerScanner::new(3, 1);
+        let mut found = HashSet::new();
+        ends.spell(&steps, |c| found.extend(scanner.push(c)));
+        let expected: HashSet<u64> = kmers(b"ACGGGT", 3).into_iter().collect();
+        assert_eq!(found, expected);
+    }
+
+    #[test]
+    fn test_compare_assembly() {
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        writeln!(file, ">a\nACGTT\nNGG\n>b\r\nacg\r").unwrap();
+        let graph_kmers: HashSet<u64> = kmers(b"ACGTG", 3).into_iter().collect();
+        let (assembly, found) =
+            compare_assembly(file.path().to_str().unwrap(), 3, 1, &graph_kmers).unwrap();
+        // ACG and CGT (the same canonical k-mer) and GTT of sequence a, and ACG of sequence b; only
+        // ACG is also a k-mer of the graph
+        assert_eq!(
+            (assembly.sequences, assembly.bp, assembly.kmers),
+            (2, 11, 2)
+        );
+        assert_eq!(assembly.kmers_in_graph, 1);
+        assert_eq!(found.len(), 1);
+    }
+
+    #[test]
+    fn test_kmer_sample() {
+        let seq: Vec<u8> = (0..10000u64)
+            .map(|i| b"ACGT"[(hash(i) % 4) as usize])
+            .collect();
+        let all: HashSet<u64> = kmers(&seq, 21).into_iter().collect();
+        let mut scanner = KmerScanner::new(21, 10);
+        let sample: HashSet<u64> = seq.iter().filter_map(|c| scanner.push(*c)).collect();
+        assert!(sample.is_subset(&all));
+        assert!(sample.len() < all.len() / 5);
+    }
+}
@@ -0,0 +1,227 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::{
+    analysis_parameter::AnalysisParameter,
+    graph_broker::{GraphBroker, PathSegment},
+    html_report::{AnalysisSection, ReportItem},
+    util::{get_default_plot_downloads, to_id, CountType, ItemIdSize},
+};
+
+use super::{Analysis, ConstructibleAnalysis, InputRequirement};
+
+// maximum number of nodes that are listed in the report
+const MAX_REPORT_NODES: usize = 1000;
+
+pub struct NodeCoverage {
+    // number of groups covering each node (index 0 is unused)
+    groups: Vec<u32>,
+    paths: PathCoverage,
+}
+
+// number of paths traversing each node and the total number of their traversals, where
+// segments of a path (e.g., of a subset) count as one path
+#[derive(Debug, Clone, PartialEq)]
+pub(super) struct PathCoverage {
+    pub(super) paths: Vec<u32>,
+    pub(super) traversals: Vec<u64>,
+    // last path that was counted for each node, offset by 1
+    last_path: Vec<u32>,
+    // number of distinct paths
+    pub(super) path_count: usize,
+}
+
+impl PathCoverage {
+    fn new(node_count: usize) -> Self {
+        Self {
+            paths: vec![0; node_count + 1],
+            traversals: vec![0; node_count + 1],
+            last_path: vec![0; node_count + 1],
+            path_count: 0,
+        }
+    }
+
+    /// Counts the traversals of the nodes by the paths/walks as the abaci count them, i.e.,
+    /// restricted to the subset coordinates and leaving out excluded nodes
+    pub(super) fn from_graph(gb: &GraphBroker) -> anyhow::Result<Self> {
+        let mut path_ids: HashMap<PathSegment, usize> = HashMap::new();
+        let mut res = Self::new(gb.get_node_count());
+        gb.visit_counted_path_nodes(|path_seg, nodes| {
+            let n = path_ids.len();
+            let path = *path_ids.entry(path_seg.clear_coords()).or_insert(n);
+            res.add_path(path, nodes.iter().copied());
+        })?;
+        res.path_count = path_ids.len();
+        Ok(res)
+    }
+
+    // counts the steps of a path, given by its index; the segments of a path are expected to be
+    // visited one after another
+    fn add_path(&mut self, path: usize, nodes: impl Iterator<Item = ItemIdSize>) {
+        let path = path as u32 + 1;
+        for node in nodes {
+            let node = node as usize;
+            self.traversals[node] += 1;
+            if self.last_path[node] != path {
+                self.last_path[node] = path;
+                self.paths[node] += 1;
+            }
+        }
+    }
+}
+
+impl Analysis for NodeCoverage {
+    fn get_type(&self) -> String {
+        "NodeCoverage".to_string()
+    }
+
+    fn generate_table(&mut self, gb: Option<&GraphBroker>) -> anyhow::Result<String> {
+        log::info!("reporting coverage of each node");
+        let gb = gb.expect("NodeCoverage analysis needs a graph");
+        self.set_coverage(gb)?;
+
+        let mut res = String::new();
+        res.push_str(&crate::io::write_metadata_comments()?);
+        res.push_str(&format!("{}\n", Self::get_header().join("\t")));
+        for row in self.get_rows(gb, (1..self.groups.len()).collect()) {
+            res.push_str(&format!("{}\n", row.join("\t")));
+        }
+        Ok(res)
+    }
+
+    fn generate_report_section(
+        &mut self,
+        gb: Option<&GraphBroker>,
+    ) -> anyhow::Result<Vec<AnalysisSection>> {
+        let gb = gb.expect("NodeCoverage analysis needs a graph");
+        let table = self.generate_table(Some(gb))?;
+        let table = format!("`{}`", &table);
+        let id_prefix = format!("node-coverage-{}", to_id(&self.get_run_id(gb)));
+
+        // the most traversed nodes, the full list is part of the table
+        let mut nodes: Vec<usize> = (1..self.groups.len()).collect();
+        nodes.sort_by_key(|node| (std::cmp::Reverse(self.paths.traversals[*node]), *node));
+        nodes.truncate(MAX_REPORT_NODES);
+
+        Ok(vec![AnalysisSection {
+            id: id_prefix.clone(),
+            analysis: "Node Coverage".to_string(),
+            table: Some(table),
+            run_name: self.get_run_name(gb),
+            run_id: self.get_run_id(gb),
+            countable: CountType::Node.to_string(),
+            items: vec![ReportItem::Table {
+                id: format!("{id_prefix}-table"),
+                header: Self::get_header(),
+                values: self.get_rows(gb, nodes),
+            }],
+            plot_downloads: get_default_plot_downloads(),
+            description: None,
+        }])
+    }
+
+    fn get_graph_requirements(&self) -> HashSet<InputRequirement> {
+        HashSet::from([InputRequirement::Node])
+    }
+}
+
+impl ConstructibleAnalysis for NodeCoverage {
+    fn from_parameter(_parameter: AnalysisParameter) -> Self {
+        Self {
+            groups: Vec::new(),
+            paths: PathCoverage::new(0),
+        }
+    }
+}
+
+impl NodeCoverage {
+    fn set_coverage(&mut self, gb: &GraphBroker) -> anyhow::Result<()> {
+        if !self.groups.is_empty() {
+            return Ok(());
+        }
+        self.paths = PathCoverage::from_graph(gb)?;
+        self.groups = gb.get_abacus_by_total(CountType::Node).countable.clone();
+        Ok(())
+    }
+
+    fn get_header() -> Vec<String> {
+        vec![
+            "node".to_string(),
+            "length".to_string(),
+            "groups".to_string(),
+            "paths".to_string(),
+            "traversals".to_string(),
+        ]
+    }
+
+    fn get_rows(&self, gb: &GraphBroker, nodes: Vec<usize>) -> Vec<Vec<String>> {
+        let names = gb.get_node_names();
+        let node_lens = gb.get_node_lens();
+        nodes
+            .into_iter()
+            .map(|node| {
+                vec![
+                    names[node].clone(),
+                    node_lens[node].to_string(),
+                    self.groups[node].to_string(),
+                    self.paths.paths[node].to_string(),
+                    self.paths.traversals[node].to_string(),
+                ]
+            })
+            .collect()
+    }
+
+    fn get_run_name(&self, gb: &GraphBroker) -> String {
+        gb.get_run_name()
+    }
+
+    fn get_run_id(&self, gb: &GraphBroker) -> String {
+        format!("{}-nodecoverage", gb.get_run_id())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graph_broker::GraphState;
+
+    #[test]
+    fn test_path_coverage() {
+        let mut coverage = PathCoverage::new(4);
+        coverage.add_path(0, vec![1, 2, 1].into_iter());
+        // second segment of the first path
+        coverage.add_path(0, vec![1, 3].into_iter());
+        coverage.add_path(1, vec![3, 3, 3].into_iter());
+        assert_eq!(coverage.paths, vec![0, 1, 1, 2, 0]);
+        assert_eq!(coverage.traversals, vec![0, 3, 1, 4, 0]);
+    }
+
+    #[test]
+    fn test_path_coverage_from_graph() {
+        let mut exclude = tempfile::NamedTempFile::new().unwrap();
+        std::io::Write::write_all(&mut exclude, b"y#4\n").unwrap();
+        let graph = "tests/test_files/t_groups.gfa".to_string();
+        let reqs = HashSet::from([
+            InputRequirement::Graph(graph.clone()),
+            InputRequirement::Node,
+        ]);
+        let state = GraphState {
+            graph,
+            exclude: exclude.path().to_str().unwrap().to_string(),
+            ..GraphState::default()
+        };
+        let mut gb = GraphBroker::new();
+        gb.change_graph_state(state, &reqs, false).unwrap();
+
+        // path y#4 and its node 14 are excluded, all other nodes are traversed by x and some y#i
+        let coverage = PathCoverage::from_graph(&gb).unwrap();
+        assert_eq!(coverage.path_count, 5);
+        assert_eq!(
+            coverage.paths,
+            vec![0, 2, 0, 2, 0, 2, 2, 0, 2, 2, 0, 2, 2, 0, 0, 2]
+        );
+        assert_eq!(
+            coverage.traversals,
+            coverage.paths.iter().map(|c| *c as u64).collect::<Vec<_>>()
+        );
+    }
+}
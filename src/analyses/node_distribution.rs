@@ -1,18 +1,25 @@
 use std::collections::HashSet;
+use std::io::{BufWriter, Write};
 
 use itertools::multizip;
 use itertools::Itertools;
 
 use crate::{
+    analysis_parameter::AnalysisParameter,
     graph_broker::{GraphBroker, ItemId},
     html_report::{AnalysisSection, Bin, ReportItem},
     util::get_default_plot_downloads,
+    util::to_id,
     util::CountType,
 };
 
 use super::{Analysis, ConstructibleAnalysis, InputRequirement};
 
+// number of bases per line of written FASTA files
+const FASTA_LINE_WIDTH: usize = 80;
+
 pub struct NodeDistribution {
+    parameter: AnalysisParameter,
     bins: Vec<Bin>,
     min: (u32, f64),
     max: (u32, f64),
@@ -30,6 +37,14 @@ impl Analysis for NodeDistribution {
         if self.bins.is_empty() {
             self.set_table(gb);
         }
+        if let Some(gb) = gb {
+            if let Some(file) = self.get_members() {
+                self.write_members(gb, file)?;
+            }
+            if let Some(file) = self.get_fasta() {
+                self.write_fasta(gb, file)?;
+            }
+        }
         let mut result = "Bin\tCoverage\tLog-Length\tLog-Size\n".to_string();
         for (i, bin) in self.bins.iter().enumerate() {
             result.push_str(&format!("{}\t{}\t{}\t{}\n", i, bin.x, bin.y, bin.size));
@@ -50,9 +65,7 @@ impl Analysis for NodeDistribution {
         let table = format!("`{}`", &table);
         let id_prefix = format!(
             "node-dist-{}",
-            self.get_run_id(gb.expect("Node Distribution should be called with a graph"))
-                .to_lowercase()
-                .replace(&[' ', '|', '\\'], "-")
+            to_id(&self.get_run_id(gb.expect("Node Distribution should be called with a graph")))
         );
         let tab = vec![AnalysisSection {
             id: format!("{}-{}", id_prefix, CountType::Node.to_string()),
@@ -67,14 +80,16 @@ impl Analysis for NodeDistribution {
                 bins: self.bins.clone(),
             }],
             plot_downloads: get_default_plot_downloads(),
+            description: None,
         }];
         Ok(tab)
     }
 }
 
 impl ConstructibleAnalysis for NodeDistribution {
-    fn from_parameter(_parameter: crate::analysis_parameter::AnalysisParameter) -> Self {
+    fn from_parameter(parameter: AnalysisParameter) -> Self {
         Self {
+            parameter,
             bins: Vec::new(),
             min: (0, 0.0),
             max: (0, 0.0),
@@ -112,6 +127,103 @@ impl NodeDistribution {
         }
     }
 
+    fn get_members(&self) -> Option<&str> {
+        if let AnalysisParameter::NodeDistribution { members, .. } = &self.parameter {
+            members.as_deref()
+        } else {
+            None
+        }
+    }
+
+    fn get_fasta(&self) -> Option<&str> {
+        if let AnalysisParameter::NodeDistribution { fasta, .. } = &self.parameter {
+            fasta.as_deref()
+        } else {
+            None
+        }
+    }
+
+    // bins whose nodes are written to the FASTA file
+    fn get_fasta_bins(&self) -> anyhow::Result<Vec<usize>> {
+        let bins = match &self.parameter {
+            AnalysisParameter::NodeDistribution { fasta_bins, .. } if !fasta_bins.is_empty() => {
+                fasta_bins.clone()
+            }
+            _ => (0..self.bins.len()).collect(),
+        };
+        if let Some(bin) = bins.iter().find(|b| **b >= self.bins.len()) {
+            anyhow::bail!(
+                "bin {} does not exist, there are only {} bins",
+                bin,
+                self.bins.len()
+            );
+        }
+        Ok(bins)
+    }
+
+    fn write_members(&self, gb: &GraphBroker, file: &str) -> anyhow::Result<()> {
+        log::info!("writing nodes of each bin to {}", file);
+        let names = gb.get_node_names();
+        let node_lens = gb.get_node_lens();
+        let coverage = &gb.get_abacus_by_total(CountType::Node).countable;
+        let mut out =
+            BufWriter::new(std::fs::File::create(file).map_err(|e| {
+                anyhow::anyhow!("unable to create bin member file {}: {}", file, e)
+            })?);
+        crate::run_summary::add_output_file(file);
+        writeln!(out, "bin\tnode\tcoverage\tlength")?;
+        for (i, bin) in self.bins.iter().enumerate() {
+            for node in &bin.content {
+                let id = node.0 as usize;
+                writeln!(
+                    out,
+                    "{}\t{}\t{}\t{}",
+                    i, names[id], coverage[id], node_lens[id]
+                )?;
+            }
+        }
+        out.flush()?;
+        Ok(())
+    }
+
+    fn write_fasta(&self, gb: &GraphBroker, file: &str) -> anyhow::Result<()> {
+        let bins = self.get_fasta_bins()?;
+        log::info!("writing node sequences of {} bins to {}", bins.len(), file);
+        let nodes: HashSet<ItemId> = bins
+            .iter()
+            .flat_map(|i| self.bins[*i].content.iter().copied())
+            .collect();
+        let sequences = gb.get_node_sequences(&nodes)?;
+        let names = gb.get_node_names();
+        let coverage = &gb.get_abacus_by_total(CountType::Node).countable;
+        let mut out = BufWriter::new(
+            std::fs::File::create(file)
+                .map_err(|e| anyhow::anyhow!("unable to create FASTA file {}: {}", file, e))?,
+        );
+        crate::run_summary::add_output_file(file);
+        for i in bins {
+            for node in &self.bins[i].content {
+                if let Some(seq) = sequences.get(node) {
+                    let id = node.0 as usize;
+                    writeln!(
+                        out,
+                        ">{} bin={} coverage={} length={}",
+                        names[id],
+                        i,
+                        coverage[id],
+                        seq.len()
+                    )?;
+                    for line in seq.chunks(FASTA_LINE_WIDTH) {
+                        out.write_all(line)?;
+                        writeln!(out)?;
+                    }
+                }
+            }
+        }
+        out.flush()?;
+        Ok(())
+    }
+
     fn get_run_name(&self, gb: &GraphBroker) -> String {
         format!("{}", gb.get_run_name())
     }
@@ -119,3 +231,55 @@ impl NodeDistribution {
         format!("{}-nodedistribution", gb.get_run_id())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graph_broker::GraphState;
+
+    #[test]
+    fn test_write_members_and_fasta() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = |name: &str| dir.path().join(name).to_str().unwrap().to_string();
+        let (members, fasta) = (path("members.tsv"), path("nodes.fa"));
+        let graph = "tests/test_files/t_groups.gfa".to_string();
+        let reqs = HashSet::from([
+            InputRequirement::Graph(graph.clone()),
+            InputRequirement::Node,
+        ]);
+        let state = GraphState {
+            graph,
+            ..GraphState::default()
+        };
+        let mut gb = GraphBroker::new();
+        gb.change_graph_state(state, &reqs, false).unwrap();
+
+        let mut analysis = NodeDistribution::from_parameter(AnalysisParameter::NodeDistribution {
+            radius: 20,
+            members: Some(members.clone()),
+            fasta: Some(fasta.clone()),
+            fasta_bins: Vec::new(),
+        });
+        analysis.generate_table(Some(&gb)).unwrap();
+
+        // each node is listed once, in the bin that holds it
+        let members = std::fs::read_to_string(members).unwrap();
+        let mut lines = members.lines();
+        assert_eq!(lines.next(), Some("bin\tnode\tcoverage\tlength"));
+        let mut nodes: Vec<String> = lines
+            .map(|l| {
+                let fields: Vec<&str> = l.split('\t').collect();
+                assert_eq!(fields.len(), 4);
+                fields[1].to_string()
+            })
+            .collect();
+        nodes.sort();
+        let mut exp = gb.get_node_names()[1..].to_vec();
+        exp.sort();
+        assert_eq!(nodes, exp);
+
+        let fasta = std::fs::read_to_string(fasta).unwrap();
+        assert_eq!(fasta.matches('>').count(), gb.get_node_count());
+        assert!(fasta.contains("\nCAAATAAG\n"));
+    }
+}
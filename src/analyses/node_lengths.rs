@@ -0,0 +1,322 @@
+use std::collections::HashSet;
+
+use crate::{
+    analysis_parameter::AnalysisParameter,
+    graph_broker::GraphBroker,
+    html_report::{AnalysisSection, ReportItem},
+    util::{get_default_plot_downloads, to_id, CountType},
+};
+
+use super::{Analysis, ConstructibleAnalysis, InputRequirement};
+
+const CLASSES: [&str; 5] = ["private", "shell", "core", "accessory", "total"];
+
+pub struct NodeLengths {
+    // lengths of the covered nodes of each class (in the order of CLASSES), sorted decreasingly
+    lengths: Vec<Vec<u32>>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct LengthStats {
+    nodes: usize,
+    bp: u64,
+    mean: f64,
+    median: u32,
+    n50: u32,
+    max: u32,
+}
+
+impl LengthStats {
+    // statistics of the given lengths, which must be sorted decreasingly
+    fn from_sorted_lengths(lengths: &[u32]) -> Self {
+        let bp: u64 = lengths.iter().map(|l| *l as u64).sum();
+        let mut n50 = 0;
+        let mut cumulative = 0;
+        for l in lengths {
+            cumulative += *l as u64;
+            if 2 * cumulative >= bp {
+                n50 = *l;
+                break;
+            }
+        }
+        Self {
+            nodes: lengths.len(),
+            bp,
+            mean: if lengths.is_empty() {
+                0.0
+            } else {
+                bp as f64 / lengths.len() as f64
+            },
+            median: lengths.get(lengths.len() / 2).copied().unwrap_or(0),
+            n50,
+            max: lengths.first().copied().unwrap_or(0),
+        }
+    }
+}
+
+impl Analysis for NodeLengths {
+    fn get_type(&self) -> String {
+        "NodeLengths".to_string()
+    }
+
+    fn generate_table(&mut self, gb: Option<&GraphBroker>) -> anyhow::Result<String> {
+        log::info!("reporting node lengths per coverage class");
+        let gb = gb.expect("NodeLengths analysis needs a graph");
+        self.set_lengths(gb);
+
+        let mut res = String::new();
+        res.push_str(&crate::io::write_metadata_comments()?);
+        res.push_str(&format!("{}\n", Self::get_header().join("\t")));
+        for row in self.get_rows() {
+            res.push_str(&format!("{}\n", row.join("\t")));
+        }
+        Ok(res)
+    }
+
+    fn generate_report_section(
+        &mut self,
+        gb: Option<&GraphBroker>,
+    ) -> anyhow::Result<Vec<AnalysisSection>> {
+        let gb = gb.expect("NodeLengths analysis needs a graph");
+        let table = self.generate_table(Some(gb))?;
+        let table = format!("`{}`", &table);
+        let id_prefix = format!("node-lengths-{}", to_id(&self.get_run_id(gb)));
+
+        // fraction of the nodes and of the bp of each class (apart from total) in each bin of
+        // lengths, i.e., whether its content consists of many short nodes or few long ones
+        let bins = get_length_bins(&self.lengths);
+        let bin_count = bins.len();
+        let names: Vec<String> = CLASSES[..4].iter().map(|c| c.to_string()).collect();
+        let distribution = |bp: bool| -> Vec<Vec<f64>> {
+            self.lengths[..4]
+                .iter()
+                .map(|lengths| {
+                    let counts = count_by_length_bin(lengths, bin_count, bp);
+                    let total = counts.iter().sum::<u64>().max(1) as f64;
+                    counts.iter().map(|c| 100.0 * *c as f64 / total).collect()
+                })
+                .collect()
+        };
+
+        Ok(vec![AnalysisSection {
+            id: id_prefix.clone(),
+            analysis: "Node Lengths".to_string(),
+            table: Some(table),
+            run_name: self.get_run_name(gb),
+            run_id: self.get_run_id(gb),
+            countable: CountType::Node.to_string(),
+            items: vec![
+                ReportItem::MultiBar {
+                    id: format!("{id_prefix}-nodes"),
+                    names: names.clone(),
+                    x_label: "node length (bp)".to_string(),
+                    y_label: "% nodes".to_string(),
+                    labels: bins.clone(),
+                    values: distribution(false),
+                    log_toggle: false,
+                    phenotypes: None,
+                    hover: None,
+                },
+                ReportItem::MultiBar {
+                    id: format!("{id_prefix}-bp"),
+                    names,
+                    x_label: "node length (bp)".to_string(),
+                    y_label: "% bp".to_string(),
+                    labels: bins,
+                    values: distribution(true),
+                    log_toggle: false,
+                    phenotypes: None,
+                    hover: None,
+                },
+                ReportItem::Table {
+                    id: format!("{id_prefix}-table"),
+                    header: Self::get_header(),
+                    values: self.get_rows(),
+                },
+            ],
+            plot_downloads: get_default_plot_downloads(),
+            description: None,
+        }])
+    }
+
+    fn get_graph_requirements(&self) -> HashSet<InputRequirement> {
+        HashSet::from([InputRequirement::Node])
+    }
+}
+
+impl ConstructibleAnalysis for NodeLengths {
+    fn from_parameter(_parameter: AnalysisParameter) -> Self {
+        Self {
+            lengths: Vec::new(),
+        }
+    }
+}
+
+impl NodeLengths {
+    fn set_lengths(&mut self, gb: &GraphBroker) {
+        if self.lengths.is_empty() {
+            let coverage = &gb.get_abacus_by_total(CountType::Node).countable;
+            self.lengths =
+                lengths_by_class(coverage, gb.get_node_lens(), gb.get_group_count() as u32);
+        }
+    }
+
+    fn get_header() -> Vec<String> {
+        vec![
+            "class".to_string(),
+            "nodes".to_string(),
+            "bp".to_string(),
+            "mean".to_string(),
+            "median".to_string(),
+            "N50".to_string(),
+            "max".to_string(),
+        ]
+    }
+
+    fn get_rows(&self) -> Vec<Vec<String>> {
+        CLASSES
+            .iter()
+            .zip(&self.lengths)
+            .map(|(class, lengths)| {
+                let stats = LengthStats::from_sorted_lengths(lengths);
+                vec![
+                    class.to_string(),
+                    stats.nodes.to_string(),
+                    stats.bp.to_string(),
+                    format!("{:.2}", stats.mean),
+                    stats.median.to_string(),
+                    stats.n50.to_string(),
+                    stats.max.to_string(),
+                ]
+            })
+            .collect()
+    }
+
+    fn get_run_name(&self, gb: &GraphBroker) -> String {
+        gb.get_run_name()
+    }
+
+    fn get_run_id(&self, gb: &GraphBroker) -> String {
+        format!("{}-nodelengths", gb.get_run_id())
+    }
+}
+
+// lengths of the nodes (skipping the unused id 0 and uncovered nodes) of each class, where
+// accessory comprises private and shell, and total all classes
+fn lengths_by_class(coverage: &[u32], node_lens: &[u32], group_count: u32) -> Vec<Vec<u32>> {
+    let mut res = vec![Vec::new(); CLASSES.len()];
+    for (cov, len) in coverage.iter().zip(node_lens).skip(1) {
+        let class = match *cov {
+            0 => continue,
+            c if c >= group_count => 2,
+            1 => 0,
+            _ => 1,
+        };
+        res[class].push(*len);
+        if class != 2 {
+            res[3].push(*len);
+        }
+        res[4].push(*len);
+    }
+    for lengths in res.iter_mut() {
+        lengths.sort_unstable_by(|a, b| b.cmp(a));
+    }
+    res
+}
+
+// bin of a length, where bin i holds the lengths in (10^(i-1), 10^i], i.e., 1, 2-10, 11-100, ...
+fn get_length_bin(length: u32) -> usize {
+    let mut bin = 0;
+    let mut upper: u64 = 1;
+    while length as u64 > upper {
+        bin += 1;
+        upper *= 10;
+    }
+    bin
+}
+
+// labels of the bins of lengths up to the one of the longest node
+fn get_length_bins(lengths: &[Vec<u32>]) -> Vec<String> {
+    let max = lengths.iter().filter_map(|l| l.first()).max().copied();
+    let bins = get_length_bin(max.unwrap_or(1)) + 1;
+    let mut res = vec!["1".to_string()];
+    let mut upper: u64 = 1;
+    for _ in 1..bins {
+        res.push(format!("{}-{}", upper + 1, upper * 10));
+        upper *= 10;
+    }
+    res
+}
+
+// number of nodes (or their bp) in each bin of lengths
+fn count_by_length_bin(lengths: &[u32], bins: usize, bp: bool) -> Vec<u64> {
+    let mut res = vec![0; bins];
+    for l in lengths {
+        res[get_length_bin(*l)] += if bp { *l as u64 } else { 1 };
+    }
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lengths_by_class() {
+        let coverage = vec![0, 3, 1, 2, 0, 3, 1];
+        let node_lens = vec![0, 10, 1, 5, 100, 20, 7];
+        assert_eq!(
+            lengths_by_class(&coverage, &node_lens, 3),
+            vec![
+                vec![7, 1],
+                vec![5],
+                vec![20, 10],
+                vec![7, 5, 1],
+                vec![20, 10, 7, 5, 1]
+            ]
+        );
+    }
+
+    #[test]
+    fn test_length_stats() {
+        assert_eq!(
+            LengthStats::from_sorted_lengths(&[20, 10, 7, 5, 1]),
+            LengthStats {
+                nodes: 5,
+                bp: 43,
+                mean: 8.6,
+                median: 7,
+                n50: 10,
+                max: 20,
+            }
+        );
+        assert_eq!(
+            LengthStats::from_sorted_lengths(&[]),
+            LengthStats {
+                nodes: 0,
+                bp: 0,
+                mean: 0.0,
+                median: 0,
+                n50: 0,
+                max: 0,
+            }
+        );
+    }
+
+    #[test]
+    fn test_count_by_length_bin() {
+        let lengths = vec![vec![150, 20, 1]];
+        let bins = get_length_bins(&lengths);
+        assert_eq!(bins, vec!["1", "2-10", "11-100", "101-1000"]);
+        assert_eq!(
+            count_by_length_bin(&lengths[0], bins.len(), false),
+            vec![1, 0, 1, 1]
+        );
+        assert_eq!(
+            count_by_length_bin(&lengths[0], bins.len(), true),
+            vec![1, 0, 20, 150]
+        );
+        assert_eq!(get_length_bin(10), 1);
+        assert_eq!(get_length_bin(11), 2);
+    }
+}
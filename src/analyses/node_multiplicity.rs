@@ -0,0 +1,275 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::{
+    analysis_parameter::AnalysisParameter,
+    graph_broker::GraphBroker,
+    html_report::{AnalysisSection, ReportItem},
+    util::{get_default_plot_downloads, to_id, CountType, ItemIdSize},
+};
+
+use super::{Analysis, ConstructibleAnalysis, InputRequirement};
+
+// maximum number of high-copy nodes that are listed in the report
+const MAX_REPORT_NODES: usize = 1000;
+
+pub struct NodeMultiplicity {
+    parameter: AnalysisParameter,
+    groups: Vec<String>,
+    // multiplicity of each node within each group, i.e., the maximum number of times the node is
+    // traversed by a single path of the group
+    multiplicities: Vec<HashMap<ItemIdSize, u32>>,
+}
+
+impl Analysis for NodeMultiplicity {
+    fn get_type(&self) -> String {
+        "NodeMultiplicity".to_string()
+    }
+
+    fn generate_table(&mut self, gb: Option<&GraphBroker>) -> anyhow::Result<String> {
+        log::info!("reporting node multiplicities");
+        let gb = gb.expect("NodeMultiplicity analysis needs a graph");
+        self.set_multiplicities(gb)?;
+        let names = gb.get_node_names();
+        let node_lens = gb.get_node_lens();
+
+        let mut res = String::new();
+        res.push_str(&crate::io::write_metadata_comments()?);
+        let high_copy = self.get_high_copy_nodes();
+        res.push_str(&format!(
+            "# {} high-copy nodes (multiplicity >= {})\n",
+            high_copy.len(),
+            self.get_min_copies()
+        ));
+        for (node, multiplicity, groups) in &high_copy {
+            res.push_str(&format!(
+                "# {}\t{}\t{}\t{}\n",
+                names[*node as usize], node_lens[*node as usize], multiplicity, groups
+            ));
+        }
+        res.push_str(&format!("multiplicity\t{}\n", self.groups.join("\t")));
+        let hists = self.get_histograms();
+        for m in 1..hists.iter().map(|h| h.len()).max().unwrap_or(0) {
+            res.push_str(&m.to_string());
+            for hist in &hists {
+                res.push_str(&format!("\t{}", hist.get(m).copied().unwrap_or(0)));
+            }
+            res.push('\n');
+        }
+        Ok(res)
+    }
+
+    fn generate_report_section(
+        &mut self,
+        gb: Option<&GraphBroker>,
+    ) -> anyhow::Result<Vec<AnalysisSection>> {
+        let gb = gb.expect("NodeMultiplicity analysis needs a graph");
+        let table = self.generate_table(Some(gb))?;
+        let table = format!("`{}`", &table);
+        let id_prefix = format!("node-multiplicity-{}", to_id(&self.get_run_id(gb)));
+        let names = gb.get_node_names();
+        let node_lens = gb.get_node_lens();
+
+        let hists = self.get_histograms();
+        let max = hists.iter().map(|h| h.len()).max().unwrap_or(0);
+        let values = hists
+            .iter()
+            .map(|h| (1..max).map(|m| *h.get(m).unwrap_or(&0) as f64).collect())
+            .collect();
+        let node_values = self
+            .get_high_copy_nodes()
+            .into_iter()
+            .take(MAX_REPORT_NODES)
+            .map(|(node, multiplicity, groups)| {
+                vec![
+                    names[node as usize].clone(),
+                    node_lens[node as usize].to_string(),
+                    multiplicity.to_string(),
+                    groups.to_string(),
+                ]
+            })
+            .collect();
+
+        Ok(vec![AnalysisSection {
+            id: id_prefix.clone(),
+            analysis: "Node Multiplicity".to_string(),
+            table: Some(table),
+            run_name: self.get_run_name(gb),
+            run_id: self.get_run_id(gb),
+            countable: CountType::Node.to_string(),
+            items: vec![
+                ReportItem::MultiBar {
+                    id: format!("{id_prefix}-hist"),
+                    names: self.groups.clone(),
+                    x_label: "multiplicity".to_string(),
+                    y_label: "#nodes".to_string(),
+                    labels: (1..max).map(|m| m.to_string()).collect(),
+                    values,
+                    log_toggle: true,
+                    phenotypes: gb.get_phenotypes(&self.groups)?,
+                    hover: None,
+                },
+                ReportItem::Table {
+                    id: format!("{id_prefix}-high-copy"),
+                    header: vec![
+                        "node".to_string(),
+                        "length".to_string(),
+                        "max multiplicity".to_string(),
+                        "groups".to_string(),
+                    ],
+                    values: node_values,
+                },
+            ],
+            plot_downloads: get_default_plot_downloads(),
+            description: None,
+        }])
+    }
+
+    fn get_graph_requirements(&self) -> HashSet<InputRequirement> {
+        HashSet::from([InputRequirement::Node])
+    }
+}
+
+impl ConstructibleAnalysis for NodeMultiplicity {
+    fn from_parameter(parameter: AnalysisParameter) -> Self {
+        Self {
+            parameter,
+            groups: Vec::new(),
+            multiplicities: Vec::new(),
+        }
+    }
+}
+
+impl NodeMultiplicity {
+    fn get_min_copies(&self) -> u32 {
+        match &self.parameter {
+            AnalysisParameter::NodeMultiplicity { min_copies } => *min_copies,
+            _ => panic!("NodeMultiplicity analysis needs a node multiplicity parameter"),
+        }
+    }
+
+    fn set_multiplicities(&mut self, gb: &GraphBroker) -> anyhow::Result<()> {
+        if !self.groups.is_empty() {
+            return Ok(());
+        }
+        let path_groups = gb.get_groups();
+        let mut group_ids: HashMap<String, usize> = HashMap::new();
+        let (groups, multiplicities) = (&mut self.groups, &mut self.multiplicities);
+        // nodes are counted as in the coverage analyses, i.e., restricted to the subset and
+        // leaving out excluded nodes
+        gb.visit_counted_path_nodes(|path_seg, nodes| {
+            let group = match path_groups.get(&path_seg.clear_coords()) {
+                Some(group) => group,
+                None => return,
+            };
+            let g = *group_ids.entry(group.clone()).or_insert_with(|| {
+                groups.push(group.clone());
+                multiplicities.push(HashMap::new());
+                groups.len() - 1
+            });
+            let mut counts: HashMap<ItemIdSize, u32> = HashMap::new();
+            for node in nodes {
+                *counts.entry(*node).or_insert(0) += 1;
+            }
+            for (node, count) in counts {
+                let m = multiplicities[g].entry(node).or_insert(0);
+                *m = u32::max(*m, count);
+            }
+        })?;
+        Ok(())
+    }
+
+    // number of nodes of each multiplicity (index) per group
+    fn get_histograms(&self) -> Vec<Vec<usize>> {
+        self.multiplicities.iter().map(get_histogram).collect()
+    }
+
+    // nodes whose multiplicity reaches the threshold in at least one group, together with their
+    // maximum multiplicity and the number of groups in which they reach the threshold; sorted by
+    // decreasing multiplicity
+    fn get_high_copy_nodes(&self) -> Vec<(ItemIdSize, u32, usize)> {
+        let min_copies = self.get_min_copies();
+        let mut nodes: HashMap<ItemIdSize, (u32, usize)> = HashMap::new();
+        for (node, m) in self.multiplicities.iter().flatten() {
+            if *m >= min_copies {
+                let entry = nodes.entry(*node).or_insert((0, 0));
+                entry.0 = u32::max(entry.0, *m);
+                entry.1 += 1;
+            }
+        }
+        let mut res: Vec<(ItemIdSize, u32, usize)> = nodes
+            .into_iter()
+            .map(|(node, (m, groups))| (node, m, groups))
+            .collect();
+        res.sort_by_key(|(node, m, groups)| (std::cmp::Reverse((*m, *groups)), *node));
+        res
+    }
+
+    fn get_run_name(&self, gb: &GraphBroker) -> String {
+        gb.get_run_name()
+    }
+
+    fn get_run_id(&self, gb: &GraphBroker) -> String {
+        format!("{}-nodemultiplicity", gb.get_run_id())
+    }
+}
+
+// number of nodes of each multiplicity (index)
+fn get_histogram(multiplicities: &HashMap<ItemIdSize, u32>) -> Vec<usize> {
+    let max = multiplicities.values().max().copied().unwrap_or(0) as usize;
+    let mut hist = vec![0; max + 1];
+    for m in multiplicities.values() {
+        hist[*m as usize] += 1;
+    }
+    hist
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graph_broker::GraphState;
+
+    #[test]
+    fn test_get_histogram() {
+        let multiplicities = HashMap::from([(1, 1), (2, 3), (3, 1), (5, 3), (8, 4)]);
+        assert_eq!(get_histogram(&multiplicities), vec![0, 2, 0, 2, 1]);
+        assert_eq!(get_histogram(&HashMap::new()), vec![0]);
+    }
+
+    #[test]
+    fn test_set_multiplicities_leaves_out_excluded_nodes() {
+        let mut gfa = tempfile::NamedTempFile::new().unwrap();
+        std::io::Write::write_all(
+            &mut gfa,
+            b"S\t1\tA\nS\t2\tC\nS\t3\tG\nP\ta\t1+,2+,1+,3+\t*\nP\tb\t1+,3+,3+\t*\nP\tc\t1+\t*\n",
+        )
+        .unwrap();
+        let mut exclude = tempfile::NamedTempFile::new().unwrap();
+        std::io::Write::write_all(&mut exclude, b"c\n").unwrap();
+        let graph = gfa.path().to_str().unwrap().to_string();
+        let reqs = HashSet::from([
+            InputRequirement::Graph(graph.clone()),
+            InputRequirement::Node,
+        ]);
+        let state = GraphState {
+            graph,
+            exclude: exclude.path().to_str().unwrap().to_string(),
+            ..GraphState::default()
+        };
+        let mut gb = GraphBroker::new();
+        gb.change_graph_state(state, &reqs, false).unwrap();
+
+        // node 1, which is traversed twice by path a, is excluded along with path c
+        let mut analysis =
+            NodeMultiplicity::from_parameter(AnalysisParameter::NodeMultiplicity { min_copies: 2 });
+        analysis.set_multiplicities(&gb).unwrap();
+        let multiplicities: HashMap<&str, &HashMap<ItemIdSize, u32>> = analysis
+            .groups
+            .iter()
+            .map(|g| g.as_str())
+            .zip(&analysis.multiplicities)
+            .collect();
+        assert_eq!(multiplicities["a"], &HashMap::from([(2, 1), (3, 1)]));
+        assert_eq!(multiplicities["b"], &HashMap::from([(3, 2)]));
+        assert!(multiplicities.get("c").map_or(true, |m| m.is_empty()));
+    }
+}
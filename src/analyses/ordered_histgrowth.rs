@@ -5,7 +5,7 @@ use rayon::iter::{IndexedParallelIterator, IntoParallelRefIterator, ParallelIter
 use crate::analysis_parameter::AnalysisParameter;
 use crate::graph_broker::{GraphBroker, ThresholdContainer};
 use crate::html_report::ReportItem;
-use crate::util::{get_default_plot_downloads, CountType};
+use crate::util::{get_default_plot_downloads, get_new_content, to_id, CountType};
 use crate::{analyses::InputRequirement, io::write_ordered_histgrowth_table};
 
 use super::{Analysis, AnalysisSection, ConstructibleAnalysis};
@@ -71,11 +71,10 @@ impl Analysis for OrderedHistgrowth {
         let growths = &self.inner.as_ref().unwrap().growths;
         let id_prefix = format!(
             "pan-ordered-growth-{}",
-            self.get_run_id(dm.expect("Ordered Growth should be called with a graph"))
-                .to_lowercase()
-                .replace(&[' ', '|', '\\'], "-")
+            to_id(&self.get_run_id(dm.expect("Ordered Growth should be called with a graph")))
         );
         let labels = dm.unwrap().get_abacus_by_group().groups.clone();
+        let phenotypes = dm.unwrap().get_phenotypes(&labels)?;
         let growth_tabs = vec![AnalysisSection {
             id: format!("{id_prefix}"),
             analysis: "Ordered Growth".to_string(),
@@ -92,8 +91,14 @@ impl Analysis for OrderedHistgrowth {
                 labels,
                 values: growths.clone(),
                 log_toggle: false,
+                phenotypes,
+                hover: Some((
+                    format!("new {}s", count),
+                    growths.iter().map(|g| get_new_content(g)).collect(),
+                )),
             }],
             plot_downloads: get_default_plot_downloads(),
+            description: None,
         }];
         Ok(growth_tabs)
         //let mut growths: Vec<Vec<f64>> = self
@@ -138,6 +143,7 @@ impl OrderedHistgrowth {
             CountType::Bp => HashSet::from([InputRequirement::Bp]),
             CountType::Node => HashSet::from([InputRequirement::Node]),
             CountType::Edge => HashSet::from([InputRequirement::Edge]),
+            CountType::Bubble => HashSet::from([InputRequirement::Bubble]),
             CountType::All => HashSet::from([
                 InputRequirement::Bp,
                 InputRequirement::Node,
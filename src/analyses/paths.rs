@@ -0,0 +1,228 @@
+use std::collections::{BTreeMap, HashSet};
+
+use crate::{
+    analysis_parameter::AnalysisParameter,
+    graph_broker::GraphBroker,
+    html_report::{AnalysisSection, ReportItem},
+    util::{get_default_plot_downloads, to_id, CountType},
+};
+
+use super::{Analysis, ConstructibleAnalysis, InputRequirement};
+
+pub struct Paths {
+    // one row per path/walk of the graph, in the order of the GFA file
+    rows: Vec<PathRow>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct PathRow {
+    name: String,
+    sample: String,
+    haplotype: Option<String>,
+    contig: Option<String>,
+    start: Option<usize>,
+    end: Option<usize>,
+    nodes: usize,
+    bp: u64,
+    // group the path/walk is assigned to, if any
+    group: Option<String>,
+}
+
+impl Analysis for Paths {
+    fn get_type(&self) -> String {
+        "Paths".to_string()
+    }
+
+    fn generate_table(&mut self, gb: Option<&GraphBroker>) -> anyhow::Result<String> {
+        log::info!("reporting paths/walks and their groups");
+        let gb = gb.expect("Paths analysis needs a graph");
+        self.set_rows(gb)?;
+
+        let mut res = String::new();
+        res.push_str(&crate::io::write_metadata_comments()?);
+        res.push_str(&format!("{}\n", Self::get_header().join("\t")));
+        for row in self.get_rows() {
+            res.push_str(&format!("{}\n", row.join("\t")));
+        }
+        Ok(res)
+    }
+
+    fn generate_report_section(
+        &mut self,
+        gb: Option<&GraphBroker>,
+    ) -> anyhow::Result<Vec<AnalysisSection>> {
+        let gb = gb.expect("Paths analysis needs a graph");
+        let table = self.generate_table(Some(gb))?;
+        let table = format!("`{}`", &table);
+        let id_prefix = format!("paths-{}", to_id(&self.get_run_id(gb)));
+        let group_sizes = get_group_sizes(&self.rows);
+
+        Ok(vec![AnalysisSection {
+            id: id_prefix.clone(),
+            analysis: "Paths".to_string(),
+            table: Some(table),
+            run_name: self.get_run_name(gb),
+            run_id: self.get_run_id(gb),
+            countable: CountType::Node.to_string(),
+            items: vec![
+                ReportItem::Bar {
+                    id: format!("{id_prefix}-groups"),
+                    name: gb.get_fname(),
+                    x_label: "group".to_string(),
+                    y_label: "#paths".to_string(),
+                    labels: group_sizes.keys().cloned().collect(),
+                    values: group_sizes.values().map(|n| *n as f64).collect(),
+                    log_toggle: false,
+                },
+                ReportItem::Table {
+                    id: format!("{id_prefix}-table"),
+                    header: Self::get_header(),
+                    values: self.get_rows(),
+                },
+            ],
+            plot_downloads: get_default_plot_downloads(),
+            description: None,
+        }])
+    }
+
+    // path/walk names, steps, and groups are available from the graph alone, without counting
+    fn get_graph_requirements(&self) -> HashSet<InputRequirement> {
+        HashSet::new()
+    }
+}
+
+impl ConstructibleAnalysis for Paths {
+    fn from_parameter(_parameter: AnalysisParameter) -> Self {
+        Self { rows: Vec::new() }
+    }
+}
+
+impl Paths {
+    fn set_rows(&mut self, gb: &GraphBroker) -> anyhow::Result<()> {
+        if !self.rows.is_empty() {
+            return Ok(());
+        }
+        let groups = gb.get_groups();
+        let node_lens = gb.get_node_lens();
+        let pansn = gb.get_pansn();
+        let rows = &mut self.rows;
+        gb.visit_path_steps(
+            |_| true,
+            |path_seg, steps| {
+                let path = path_seg.clear_coords();
+                let name = path.id();
+                let (sample, haplotype, contig) = pansn.split_name(&name);
+                rows.push(PathRow {
+                    sample: sample.to_string(),
+                    haplotype: haplotype.map(|h| h.to_string()),
+                    contig: contig.map(|c| c.to_string()),
+                    start: path_seg.start,
+                    end: path_seg.end,
+                    nodes: steps.len(),
+                    bp: steps
+                        .iter()
+                        .map(|(node, _)| node_lens[node.0 as usize] as u64)
+                        .sum(),
+                    group: groups.get(&path).cloned(),
+                    name,
+                });
+            },
+        )?;
+        let unassigned = self.rows.iter().filter(|r| r.group.is_none()).count();
+        if unassigned > 0 {
+            log::warn!("{} paths/walks are not assigned to any group", unassigned);
+        }
+        Ok(())
+    }
+
+    fn get_header() -> Vec<String> {
+        vec![
+            "path".to_string(),
+            "sample".to_string(),
+            "haplotype".to_string(),
+            "contig".to_string(),
+            "start".to_string(),
+            "end".to_string(),
+            "nodes".to_string(),
+            "bp".to_string(),
+            "group".to_string(),
+        ]
+    }
+
+    // missing fields are given as *, as in GFA files
+    fn get_rows(&self) -> Vec<Vec<String>> {
+        let or_star = |x: Option<String>| x.unwrap_or_else(|| "*".to_string());
+        self.rows
+            .iter()
+            .map(|r| {
+                vec![
+                    r.name.clone(),
+                    r.sample.clone(),
+                    or_star(r.haplotype.clone()),
+                    or_star(r.contig.clone()),
+                    or_star(r.start.map(|s| s.to_string())),
+                    or_star(r.end.map(|e| e.to_string())),
+                    r.nodes.to_string(),
+                    r.bp.to_string(),
+                    or_star(r.group.clone()),
+                ]
+            })
+            .collect()
+    }
+
+    fn get_run_name(&self, gb: &GraphBroker) -> String {
+        gb.get_run_name()
+    }
+
+    fn get_run_id(&self, gb: &GraphBroker) -> String {
+        format!("{}-paths", gb.get_run_id())
+    }
+}
+
+// number of distinct paths (counting segments of a path once) assigned to each group
+fn get_group_sizes(rows: &[PathRow]) -> BTreeMap<String, usize> {
+    let mut paths: HashSet<(&str, &str)> = HashSet::new();
+    let mut res = BTreeMap::new();
+    for row in rows {
+        if let Some(group) = &row.group {
+            if paths.insert((row.name.as_str(), group.as_str())) {
+                *res.entry(group.clone()).or_insert(0) += 1;
+            }
+        }
+    }
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn row(name: &str, group: Option<&str>) -> PathRow {
+        PathRow {
+            name: name.to_string(),
+            sample: name.to_string(),
+            haplotype: None,
+            contig: None,
+            start: None,
+            end: None,
+            nodes: 0,
+            bp: 0,
+            group: group.map(|g| g.to_string()),
+        }
+    }
+
+    #[test]
+    fn test_get_group_sizes() {
+        let rows = vec![
+            row("a", Some("x")),
+            row("a", Some("x")),
+            row("b", Some("x")),
+            row("c", Some("y")),
+            row("d", None),
+        ];
+        assert_eq!(
+            get_group_sizes(&rows),
+            BTreeMap::from([("x".to_string(), 2), ("y".to_string(), 1)])
+        );
+    }
+}
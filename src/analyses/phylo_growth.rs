@@ -0,0 +1,385 @@
+use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::io::BufReader;
+
+use itertools::Itertools;
+
+use crate::{
+    analysis_parameter::AnalysisParameter,
+    graph_broker::GraphBroker,
+    html_report::{AnalysisSection, ReportItem},
+    io::parse_newick_tree,
+    util::{get_default_plot_downloads, to_id, CountType, GroupSize},
+};
+
+use super::{Analysis, ConstructibleAnalysis, InputRequirement};
+
+pub struct PhyloGrowth {
+    parameter: AnalysisParameter,
+    count: CountType,
+    inner: Option<InnerPhyloGrowth>,
+}
+
+struct InnerPhyloGrowth {
+    // names and parents of the nodes of the tree in preorder
+    names: Vec<String>,
+    parents: Vec<Option<usize>>,
+    counts: Vec<CladeCount>,
+    // taxa of the tree that hold groups, in tree order, and the content accumulated up to each
+    taxa: Vec<String>,
+    growth: Vec<f64>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+struct CladeCount {
+    // number of leaves of the clade that hold groups
+    leaves: usize,
+    // content of the union and intersection of the clade's groups
+    pangenome: usize,
+    core: usize,
+    // content that is not found in any group outside of the clade
+    private: usize,
+    // content that is new when clades are added in tree order, i.e., that is not found in any
+    // group preceding the clade
+    novel: usize,
+}
+
+impl Analysis for PhyloGrowth {
+    fn get_type(&self) -> String {
+        "PhyloGrowth".to_string()
+    }
+
+    fn generate_table(&mut self, gb: Option<&GraphBroker>) -> anyhow::Result<String> {
+        log::info!("reporting pangenome content along phylogeny");
+        let gb = gb.expect("PhyloGrowth analysis needs a graph");
+        self.set_inner(gb)?;
+        let inner = self.inner.as_ref().unwrap();
+
+        let mut res = String::new();
+        res.push_str(&crate::io::write_metadata_comments()?);
+        res.push_str(&format!(
+            "# tree {}, {} taxa holding groups\n",
+            self.get_tree(),
+            inner.taxa.len()
+        ));
+        res.push_str("clade\tparent\tleaves\tpangenome\tcore\tprivate\tnovel\n");
+        for row in inner.get_rows() {
+            res.push_str(&row.join("\t"));
+            res.push('\n');
+        }
+        Ok(res)
+    }
+
+    fn generate_report_section(
+        &mut self,
+        gb: Option<&GraphBroker>,
+    ) -> anyhow::Result<Vec<AnalysisSection>> {
+        let gb = gb.expect("PhyloGrowth analysis needs a graph");
+        let table = self.generate_table(Some(gb))?;
+        let table = format!("`{}`", &table);
+        let id_prefix = format!("phylo-growth-{}", to_id(&self.get_run_id(gb)));
+        let inner = self.inner.as_ref().unwrap();
+
+        Ok(vec![AnalysisSection {
+            id: id_prefix.clone(),
+            analysis: "Phylogeny-aware Growth".to_string(),
+            table: Some(table),
+            run_name: self.get_run_name(gb),
+            run_id: self.get_run_id(gb),
+            countable: self.count.to_string(),
+            items: vec![
+                ReportItem::MultiBar {
+                    id: format!("{id_prefix}-growth"),
+                    names: vec!["tree order".to_string()],
+                    x_label: "taxa".to_string(),
+                    y_label: format!("{}s", self.count),
+                    labels: inner.taxa.clone(),
+                    values: vec![inner.growth.clone()],
+                    log_toggle: false,
+                    phenotypes: gb.get_phenotypes(&inner.taxa)?,
+                    hover: None,
+                },
+                ReportItem::Table {
+                    id: format!("{id_prefix}-clades"),
+                    header: [
+                        "clade",
+                        "parent",
+                        "leaves",
+                        "pangenome",
+                        "core",
+                        "private",
+                        "novel",
+                    ]
+                    .iter()
+                    .map(|h| h.to_string())
+                    .collect(),
+                    values: inner.get_rows(),
+                },
+            ],
+            plot_downloads: get_default_plot_downloads(),
+            description: None,
+        }])
+    }
+
+    fn get_graph_requirements(&self) -> HashSet<InputRequirement> {
+        let mut req = HashSet::from([InputRequirement::AbacusByGroup(self.count)]);
+        req.extend(Self::count_to_input_req(self.count));
+        req
+    }
+}
+
+impl ConstructibleAnalysis for PhyloGrowth {
+    fn from_parameter(parameter: AnalysisParameter) -> Self {
+        Self {
+            count: match &parameter {
+                AnalysisParameter::PhyloGrowth { count_type, .. } => *count_type,
+                _ => panic!("PhyloGrowth analysis needs phylo-growth parameter"),
+            },
+            parameter,
+            inner: None,
+        }
+    }
+}
+
+impl PhyloGrowth {
+    fn count_to_input_req(count: CountType) -> HashSet<InputRequirement> {
+        match count {
+            CountType::Bp => HashSet::from([InputRequirement::Bp]),
+            CountType::Node => HashSet::from([InputRequirement::Node]),
+            CountType::Edge => HashSet::from([InputRequirement::Edge]),
+            CountType::Bubble => HashSet::from([InputRequirement::Bubble]),
+            CountType::All => HashSet::from([
+                InputRequirement::Bp,
+                InputRequirement::Node,
+                InputRequirement::Edge,
+            ]),
+        }
+    }
+
+    fn get_tree(&self) -> &str {
+        if let AnalysisParameter::PhyloGrowth { tree, .. } = &self.parameter {
+            tree
+        } else {
+            panic!("PhyloGrowth analysis needs phylo-growth parameter");
+        }
+    }
+
+    fn set_inner(&mut self, gb: &GraphBroker) -> anyhow::Result<()> {
+        if self.inner.is_some() {
+            return Ok(());
+        }
+        let file = self.get_tree().to_string();
+        log::info!("loading phylogeny from {}", file);
+        let mut data = BufReader::new(
+            File::open(&file)
+                .map_err(|e| anyhow::anyhow!("unable to open tree file {}: {}", file, e))?,
+        );
+        let tree = parse_newick_tree(&mut data, &file)?;
+
+        let names: Vec<String> = tree
+            .nodes
+            .iter()
+            .enumerate()
+            .map(|(i, n)| match &n.label {
+                Some(l) => l.clone(),
+                None => format!("clade-{}", i),
+            })
+            .collect();
+        let mut parents: Vec<Option<usize>> = vec![None; tree.nodes.len()];
+        for (i, n) in tree.nodes.iter().enumerate() {
+            for child in &n.children {
+                parents[*child] = Some(i);
+            }
+        }
+
+        // groups are placed on the leaf of the same name or, failing that, of their sample
+        let mut taxon2leaf: HashMap<&str, usize> = HashMap::new();
+        for (i, n) in tree
+            .nodes
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| tree.is_leaf(*i))
+        {
+            if let Some(l) = &n.label {
+                if taxon2leaf.insert(l, i).is_some() {
+                    anyhow::bail!("taxon {} occurs more than once in tree {}", l, file);
+                }
+            }
+        }
+        let groups = &gb.get_abacus_by_group().groups;
+        let group_leaves: Vec<Option<usize>> = groups
+            .iter()
+            .map(|g| {
+                let leaf = taxon2leaf
+                    .get(g.as_str())
+                    .or_else(|| taxon2leaf.get(g.split('#').next().unwrap_or_default()))
+                    .copied();
+                if leaf.is_none() {
+                    log::warn!("group {} is not a taxon of tree {}, ignoring it", g, file);
+                }
+                leaf
+            })
+            .collect();
+        let placed: HashSet<usize> = group_leaves.iter().flatten().copied().collect();
+        if placed.is_empty() {
+            anyhow::bail!("none of the groups is a taxon of tree {}", file);
+        }
+        for (taxon, leaf) in taxon2leaf.iter() {
+            if !placed.contains(leaf) {
+                log::warn!("taxon {} of tree {} is not part of the graph", taxon, file);
+            }
+        }
+
+        let abacus = gb.get_abacus_by_group();
+        let weights = abacus.get_item_weights(gb.get_node_lens());
+        let (counts, gains) = count_clades(&abacus.r, &abacus.c, &weights, &parents, &group_leaves);
+        let taxa_leaves: Vec<usize> = (0..tree.nodes.len())
+            .filter(|i| placed.contains(i))
+            .collect();
+        let mut total = 0;
+        let growth = taxa_leaves
+            .iter()
+            .map(|i| {
+                total += gains[*i];
+                total as f64
+            })
+            .collect();
+        self.inner = Some(InnerPhyloGrowth {
+            taxa: taxa_leaves.iter().map(|i| names[*i].clone()).collect(),
+            names,
+            parents,
+            counts,
+            growth,
+        });
+        Ok(())
+    }
+
+    fn get_run_name(&self, gb: &GraphBroker) -> String {
+        gb.get_run_name()
+    }
+
+    fn get_run_id(&self, gb: &GraphBroker) -> String {
+        format!("{}-phylogrowth", gb.get_run_id())
+    }
+}
+
+impl InnerPhyloGrowth {
+    fn get_rows(&self) -> Vec<Vec<String>> {
+        self.counts
+            .iter()
+            .enumerate()
+            .map(|(i, c)| {
+                vec![
+                    self.names[i].clone(),
+                    self.parents[i]
+                        .map(|p| self.names[p].clone())
+                        .unwrap_or_else(|| "-".to_string()),
+                    c.leaves.to_string(),
+                    c.pangenome.to_string(),
+                    c.core.to_string(),
+                    c.private.to_string(),
+                    c.novel.to_string(),
+                ]
+            })
+            .collect()
+    }
+}
+
+// counts the content of each clade of a tree, whose nodes are given in preorder by their parents,
+// and the content that each leaf adds when leaves are added in tree order; group_leaves assigns
+// each group of the abacus to a leaf of the tree
+fn count_clades(
+    r: &[usize],
+    c: &[GroupSize],
+    weights: &[usize],
+    parents: &[Option<usize>],
+    group_leaves: &[Option<usize>],
+) -> (Vec<CladeCount>, Vec<usize>) {
+    let n = parents.len();
+    let mut counts = vec![CladeCount::default(); n];
+    let mut is_placed = vec![false; n];
+    for leaf in group_leaves.iter().flatten() {
+        is_placed[*leaf] = true;
+    }
+    // in reverse preorder, each node comes after all of its descendants
+    for v in (0..n).rev() {
+        if is_placed[v] {
+            counts[v].leaves += 1;
+        }
+        if let Some(p) = parents[v] {
+            counts[p].leaves += counts[v].leaves;
+        }
+    }
+
+    let mut gains = vec![0; n];
+    // number of the item's leaves in the clade of each node visited for the current item
+    let mut hits = vec![0; n];
+    let mut visited: Vec<usize> = Vec::new();
+    let mut leaves: Vec<usize> = Vec::new();
+    for (item, (start, end)) in r.iter().copied().tuple_windows().enumerate() {
+        let weight = weights.get(item).copied().unwrap_or(0);
+        leaves.clear();
+        leaves.extend(
+            c[start..end]
+                .iter()
+                .filter_map(|g| group_leaves[*g as usize]),
+        );
+        if weight == 0 || leaves.is_empty() {
+            continue;
+        }
+        leaves.sort_unstable();
+        leaves.dedup();
+        for leaf in leaves.iter() {
+            let mut v = Some(*leaf);
+            while let Some(u) = v {
+                if hits[u] == 0 {
+                    visited.push(u);
+                }
+                hits[u] += 1;
+                v = parents[u];
+            }
+        }
+        // leaves are in tree order, so the first leaf is where the item is first encountered
+        gains[leaves[0]] += weight;
+        for v in visited.drain(..) {
+            let count = &mut counts[v];
+            count.pangenome += weight;
+            if hits[v] == count.leaves {
+                count.core += weight;
+            }
+            if hits[v] == leaves.len() {
+                count.private += weight;
+            }
+            // a clade comprises the nodes that follow it in preorder up to its last descendant
+            if leaves[0] >= v {
+                count.novel += weight;
+            }
+            hits[v] = 0;
+        }
+    }
+    (counts, gains)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_count_clades() {
+        // ((g0,g1)x,g2)root in preorder: root, x, g0, g1, g2
+        let parents = vec![None, Some(0), Some(1), Some(1), Some(0)];
+        let group_leaves = vec![Some(2), Some(3), Some(4)];
+        // items: empty, in g0 only, in g0 and g1, in g1 and g2, in all groups
+        let r = vec![0, 0, 1, 3, 5, 8];
+        let c: Vec<GroupSize> = vec![0, 0, 1, 1, 2, 0, 1, 2];
+        let weights = vec![1, 1, 1, 1, 1];
+        let (counts, gains) = count_clades(&r, &c, &weights, &parents, &group_leaves);
+        let as_tuple = |c: &CladeCount| (c.leaves, c.pangenome, c.core, c.private, c.novel);
+        assert_eq!(as_tuple(&counts[0]), (3, 4, 1, 4, 4));
+        assert_eq!(as_tuple(&counts[1]), (2, 4, 2, 2, 4));
+        assert_eq!(as_tuple(&counts[2]), (1, 3, 3, 1, 3));
+        assert_eq!(as_tuple(&counts[3]), (1, 3, 3, 0, 1));
+        assert_eq!(as_tuple(&counts[4]), (1, 2, 2, 0, 0));
+        assert_eq!(gains, vec![0, 0, 3, 1, 0]);
+    }
+}
@@ -1,14 +1,21 @@
 use itertools::Itertools;
-use kodama::{linkage, Dendrogram};
+use kodama::{linkage, Dendrogram, Method};
+use rand::Rng;
+use rayon::prelude::*;
 
-use crate::graph_broker::GraphBroker;
-use crate::util::get_default_plot_downloads;
+use crate::graph_broker::{AbacusByGroup, Draw, GraphBroker};
+use crate::util::{get_default_plot_downloads, get_rng, to_id, GroupSize};
 use crate::{
-    analyses::InputRequirement, analysis_parameter::AnalysisParameter, html_report::ReportItem,
-    io::write_metadata_comments, util::CountType,
+    analyses::InputRequirement,
+    analysis_parameter::AnalysisParameter,
+    html_report::ReportItem,
+    io::{get_group_labels, write_metadata_comments, NewickNode, NewickTree},
+    util::CountType,
 };
 use core::panic;
-use std::collections::{HashMap, HashSet};
+use std::collections::HashSet;
+use std::fs::File;
+use std::io::{BufWriter, Write};
 use std::usize;
 
 use super::{Analysis, AnalysisSection, ConstructibleAnalysis};
@@ -17,6 +24,11 @@ pub struct Similarity {
     parameter: AnalysisParameter,
     table: Option<Vec<Vec<f32>>>,
     labels: Option<Vec<String>>,
+    // populations whose pan-sets the paths/groups are compared against, if any
+    populations: Option<Vec<String>>,
+    // clades of the dendrogram, given by their groups, and their bootstrap support in percent
+    clades: Option<Vec<(Vec<String>, Option<f64>)>>,
+    newick: Option<String>,
     count: CountType,
 }
 
@@ -26,12 +38,20 @@ impl Analysis for Similarity {
         gb: Option<&crate::graph_broker::GraphBroker>,
     ) -> anyhow::Result<String> {
         if self.table.is_none() {
-            self.set_table(gb);
+            self.set_table(gb)?;
         }
         let mut text = write_metadata_comments()?;
+        if let Some(newick) = &self.newick {
+            text.push_str(&format!("# dendrogram: {}\n", newick));
+        }
         let table = self.table.as_ref().unwrap();
         let labels = self.labels.as_ref().unwrap();
-        text.push_str(&get_table_string(table, labels));
+        match &self.populations {
+            Some(populations) => {
+                text.push_str(&get_rectangular_table_string(table, labels, populations))
+            }
+            None => text.push_str(&get_table_string(table, labels)),
+        }
         Ok(text)
     }
 
@@ -50,7 +70,7 @@ impl Analysis for Similarity {
         gb: Option<&crate::graph_broker::GraphBroker>,
     ) -> anyhow::Result<Vec<AnalysisSection>> {
         if self.table.is_none() {
-            self.set_table(gb);
+            self.set_table(gb)?;
         }
         if gb.is_none() {
             panic!("Similarity analysis needs a graph")
@@ -62,13 +82,8 @@ impl Analysis for Similarity {
         };
         let table = self.generate_table(Some(gb))?;
         let table = format!("`{}`", &table);
-        let id_prefix = format!(
-            "sim-heat-{}",
-            self.get_run_id(gb)
-                .to_lowercase()
-                .replace(&[' ', '|', '\\'], "-")
-        );
-        let tabs = vec![AnalysisSection {
+        let id_prefix = format!("sim-heat-{}", to_id(&self.get_run_id(gb)));
+        let mut tabs = vec![AnalysisSection {
             id: format!("{id_prefix}-{k}"),
             analysis: "Similarity Heatmap".to_string(),
             table: Some(table.clone()),
@@ -79,11 +94,40 @@ impl Analysis for Similarity {
                 id: format!("{id_prefix}-{k}"),
                 name: gb.get_fname(),
                 x_labels: self.labels.as_ref().unwrap().clone(),
-                y_labels: self.labels.as_ref().unwrap().clone(),
+                y_labels: self
+                    .populations
+                    .as_ref()
+                    .unwrap_or(self.labels.as_ref().unwrap())
+                    .clone(),
                 values: self.table.as_ref().unwrap().clone(),
+                phenotypes: gb.get_phenotypes(self.labels.as_ref().unwrap())?,
             }],
             plot_downloads: get_default_plot_downloads(),
+            description: None,
         }];
+        if let Some(clades) = &self.clades {
+            tabs[0].items.push(ReportItem::Table {
+                id: format!("{id_prefix}-{k}-clades"),
+                header: vec![
+                    "clade".to_string(),
+                    "groups".to_string(),
+                    "bootstrap support (%)".to_string(),
+                ],
+                values: clades
+                    .iter()
+                    .enumerate()
+                    .map(|(i, (groups, support))| {
+                        vec![
+                            (i + 1).to_string(),
+                            groups.join(", "),
+                            support
+                                .map(|s| format!("{:.0}", s))
+                                .unwrap_or_else(|| "NA".to_string()),
+                        ]
+                    })
+                    .collect(),
+            });
+        }
         Ok(tabs)
     }
 }
@@ -98,6 +142,9 @@ impl ConstructibleAnalysis for Similarity {
             parameter,
             table: None,
             labels: None,
+            populations: None,
+            clades: None,
+            newick: None,
         }
     }
 }
@@ -108,6 +155,7 @@ impl Similarity {
             CountType::Bp => HashSet::from([InputRequirement::Bp]),
             CountType::Node => HashSet::from([InputRequirement::Node]),
             CountType::Edge => HashSet::from([InputRequirement::Edge]),
+            CountType::Bubble => HashSet::from([InputRequirement::Bubble]),
             CountType::All => HashSet::from([
                 InputRequirement::Bp,
                 InputRequirement::Node,
@@ -116,60 +164,81 @@ impl Similarity {
         }
     }
 
-    fn set_table(&mut self, gb: Option<&crate::graph_broker::GraphBroker>) {
+    fn get_populations_file(&self) -> Option<&str> {
+        match &self.parameter {
+            AnalysisParameter::Similarity { populations, .. } => populations.as_deref(),
+            _ => None,
+        }
+    }
+
+    fn set_table(&mut self, gb: Option<&crate::graph_broker::GraphBroker>) -> anyhow::Result<()> {
         let gb = gb.as_ref().unwrap();
+        if let Some(file) = self.get_populations_file().map(|f| f.to_string()) {
+            return self.set_population_table(gb, &file);
+        }
         let r = &gb.get_abacus_by_group().r;
         let c = &gb.get_abacus_by_group().c;
         let mut labels = gb.get_abacus_by_group().groups.clone();
 
-        let tuples: Vec<(_, _)> = r.iter().map(|x| *x as usize).tuple_windows().collect();
-
-        let mut path_similarities: HashMap<u128, usize> = HashMap::new();
-        let mut path_lens: HashMap<u64, usize> = HashMap::new();
-        let node_lens = gb.get_node_lens();
-        for (index, tuple) in tuples.iter().enumerate() {
-            let node_length = node_lens[index] as usize;
-            for x in &c[tuple.0..tuple.1] {
-                if self.count == CountType::Bp {
-                    *path_lens.entry(*x).or_insert(0) += node_length;
-                } else {
-                    *path_lens.entry(*x).or_insert(0) += 1;
-                }
-                for y in &c[tuple.0..tuple.1] {
-                    if self.count == CountType::Bp {
-                        *path_similarities
-                            .entry((*x as u128) << 64 | *y as u128)
-                            .or_insert(0) += node_length;
-                    } else {
-                        *path_similarities
-                            .entry((*x as u128) << 64 | *y as u128)
-                            .or_insert(0) += 1;
-                    }
-                }
-            }
-        }
-
-        let group_count = gb.get_group_count();
-        let mut table: Vec<Vec<f32>> = vec![vec![0.0; group_count]; group_count];
-        for i in 0..group_count {
-            for j in 0..group_count {
-                let intersection = path_similarities
-                    .get(&((i as u128) << 64 | j as u128))
-                    .copied()
-                    .unwrap_or_default();
-                table[i][j] = intersection as f32
-                    / (path_lens[&(i as u64)] + path_lens[&(j as u64)] - intersection) as f32;
-            }
-        }
+        let weights = gb
+            .get_abacus_by_group()
+            .get_item_weights(gb.get_node_lens());
+        let mut table = jaccard_table(r, c, &weights, gb.get_group_count());
 
         let mut distances = calculate_distances(&table);
 
-        let method = match self.parameter {
-            AnalysisParameter::Similarity { cluster_method, .. } => cluster_method,
+        let (method, bootstrap, newick) = match &self.parameter {
+            AnalysisParameter::Similarity {
+                cluster_method,
+                bootstrap,
+                newick,
+                ..
+            } => (cluster_method.to_kodama(), *bootstrap, newick.clone()),
             _ => panic!("Similarity analysis needs to contain similarity parameter"),
-        }
-        .to_kodama();
+        };
         let dend = linkage(&mut distances, table.len(), method);
+
+        let clades = get_clades(&dend);
+        let support = if bootstrap > 0 {
+            log::info!(
+                "computing bootstrap support of clades from {} replicates",
+                bootstrap
+            );
+            Some(bootstrap_support(
+                r,
+                c,
+                &weights,
+                gb.get_group_count(),
+                method,
+                &clades,
+                bootstrap,
+            ))
+        } else {
+            None
+        };
+        let tree = get_newick_tree(&dend, &labels, support.as_deref());
+        if let Some(file) = newick {
+            log::info!("writing dendrogram to {}", file);
+            let mut out =
+                BufWriter::new(File::create(&file).map_err(|e| {
+                    anyhow::anyhow!("unable to create Newick file {}: {}", file, e)
+                })?);
+            crate::run_summary::add_output_file(&file);
+            writeln!(out, "{}", tree)?;
+        }
+        self.newick = Some(tree.to_string());
+        self.clades = Some(
+            clades
+                .iter()
+                .enumerate()
+                .map(|(i, clade)| {
+                    (
+                        clade.iter().map(|g| labels[*g].clone()).collect(),
+                        support.as_ref().map(|s| s[i]),
+                    )
+                })
+                .collect(),
+        );
         let order = get_order_from_dendrogram(&dend);
         let mut order = order.into_iter().enumerate().collect::<Vec<_>>();
         order.sort_by_key(|el| el.1);
@@ -182,6 +251,41 @@ impl Similarity {
 
         self.table = Some(table);
         self.labels = Some(labels);
+        Ok(())
+    }
+
+    // similarity of each path/group to the pan-set of each population; rows are ordered by the
+    // population that the paths/groups are assigned to, with unassigned ones last
+    fn set_population_table(&mut self, gb: &GraphBroker, file: &str) -> anyhow::Result<()> {
+        if let AnalysisParameter::Similarity {
+            bootstrap, newick, ..
+        } = &self.parameter
+        {
+            if *bootstrap > 0 || newick.is_some() {
+                anyhow::bail!("similarity to populations is not clustered into a dendrogram");
+            }
+        }
+        let abacus = gb.get_abacus_by_group();
+        let (populations, sets) = get_population_sets(file, &abacus.groups)?;
+        log::info!(
+            "comparing {} paths/groups against the pan-sets of {} populations",
+            abacus.groups.len(),
+            populations.len()
+        );
+        let weights = abacus.get_item_weights(gb.get_node_lens());
+        let mut table =
+            jaccard_to_populations(&abacus.r, &abacus.c, &weights, &sets, populations.len());
+        let mut labels = abacus.groups.clone();
+
+        let mut order: Vec<usize> = (0..labels.len()).collect();
+        order.sort_by_key(|i| sets[*i].unwrap_or(usize::MAX));
+        table = order.iter().map(|i| table[*i].clone()).collect();
+        labels = order.iter().map(|i| labels[*i].clone()).collect();
+
+        self.table = Some(table);
+        self.labels = Some(labels);
+        self.populations = Some(populations);
+        Ok(())
     }
 
     fn get_run_name(&self, gb: &GraphBroker) -> String {
@@ -193,6 +297,214 @@ impl Similarity {
     }
 }
 
+/// Assigns each of the given groups to a population of the tab-separated two-column population
+/// file, or to none if it is not listed; returns the populations and the assignment
+pub fn get_population_sets(
+    file: &str,
+    groups: &[String],
+) -> anyhow::Result<(Vec<String>, Vec<Option<usize>>)> {
+    let (populations, sets) = get_group_labels(file, "population", groups)?;
+    if populations.is_empty() {
+        anyhow::bail!("population file {} does not assign any group", file);
+    }
+    Ok((populations, sets))
+}
+
+// weighted Jaccard similarity of each group to the pan-set (union) of each population, given the
+// group-based abacus in CSC format (rows r, columns c), the weight of each countable, and the
+// population of each group; a group is left out of the pan-set of its own population, such that
+// it is not trivially most similar to it
+fn jaccard_to_populations(
+    r: &[usize],
+    c: &[GroupSize],
+    weights: &[usize],
+    sets: &[Option<usize>],
+    population_count: usize,
+) -> Vec<Vec<f32>> {
+    let group_count = sets.len();
+    let mut intersections: Vec<Vec<usize>> = vec![vec![0; population_count]; group_count];
+    let mut totals = vec![0; group_count];
+    let mut pan_totals = vec![0; population_count];
+    // weight of the countables that are private to a group within its population
+    let mut private = vec![0; group_count];
+    let mut members = vec![0; population_count];
+    for (index, (start, end)) in r.iter().copied().tuple_windows().enumerate() {
+        let weight = weights.get(index).copied().unwrap_or(0);
+        if weight == 0 {
+            continue;
+        }
+        members.iter_mut().for_each(|m| *m = 0);
+        for x in &c[start..end] {
+            if let Some(p) = sets[*x as usize] {
+                members[p] += 1;
+            }
+        }
+        for (p, m) in members.iter().enumerate() {
+            if *m > 0 {
+                pan_totals[p] += weight;
+            }
+        }
+        for x in &c[start..end] {
+            let x = *x as usize;
+            totals[x] += weight;
+            for (p, m) in members.iter().enumerate() {
+                let others = if sets[x] == Some(p) { m - 1 } else { *m };
+                if others > 0 {
+                    intersections[x][p] += weight;
+                }
+            }
+            if let Some(p) = sets[x] {
+                if members[p] == 1 {
+                    private[x] += weight;
+                }
+            }
+        }
+    }
+    let mut table: Vec<Vec<f32>> = vec![vec![0.0; population_count]; group_count];
+    for g in 0..group_count {
+        for p in 0..population_count {
+            let pan_total = if sets[g] == Some(p) {
+                pan_totals[p] - private[g]
+            } else {
+                pan_totals[p]
+            };
+            let union = totals[g] + pan_total - intersections[g][p];
+            if union > 0 {
+                table[g][p] = intersections[g][p] as f32 / union as f32;
+            }
+        }
+    }
+    table
+}
+
+// weighted Jaccard similarity of all pairs of groups, given the group-based abacus in CSC format
+// (rows r, columns c) and the weight of each countable; pairs without any countable have similarity
+// 0
+fn jaccard_table(
+    r: &[usize],
+    c: &[GroupSize],
+    weights: &[usize],
+    group_count: usize,
+) -> Vec<Vec<f32>> {
+    let mut intersections: Vec<Vec<usize>> = vec![vec![0; group_count]; group_count];
+    for (index, (start, end)) in r.iter().copied().tuple_windows().enumerate() {
+        let weight = weights.get(index).copied().unwrap_or(0);
+        if weight == 0 {
+            continue;
+        }
+        for x in &c[start..end] {
+            for y in &c[start..end] {
+                intersections[*x as usize][*y as usize] += weight;
+            }
+        }
+    }
+    // the diagonal holds the total weight of each group
+    let mut table: Vec<Vec<f32>> = vec![vec![0.0; group_count]; group_count];
+    for i in 0..group_count {
+        for j in 0..group_count {
+            let union = intersections[i][i] + intersections[j][j] - intersections[i][j];
+            if union > 0 {
+                table[i][j] = intersections[i][j] as f32 / union as f32;
+            }
+        }
+    }
+    table
+}
+
+// clades of the dendrogram in the order of its steps, each given by its sorted observations
+fn get_clades(dend: &Dendrogram<f32>) -> Vec<Vec<usize>> {
+    let observations = dend.observations();
+    let mut clades: Vec<Vec<usize>> = Vec::with_capacity(dend.len());
+    for step in dend.steps() {
+        let mut clade = Vec::new();
+        for cluster in [step.cluster1, step.cluster2] {
+            if cluster < observations {
+                clade.push(cluster);
+            } else {
+                clade.extend(clades[cluster - observations].iter().copied());
+            }
+        }
+        clade.sort_unstable();
+        clades.push(clade);
+    }
+    clades
+}
+
+// percentage of bootstrap replicates in which each clade is recovered; replicates resample the
+// countables with replacement, i.e., each countable is weighted by the number of times it is drawn
+fn bootstrap_support(
+    r: &[usize],
+    c: &[GroupSize],
+    weights: &[usize],
+    group_count: usize,
+    method: Method,
+    clades: &[Vec<usize>],
+    replicates: usize,
+) -> Vec<f64> {
+    let items: Vec<usize> = (0..weights.len()).filter(|i| weights[*i] > 0).collect();
+    let recovered: Vec<HashSet<Vec<usize>>> = (0..replicates)
+        .into_par_iter()
+        .map(|i| {
+            let mut rng = get_rng(AbacusByGroup::get_stream(Draw::Bootstrap, i));
+            let mut resampled = vec![0; weights.len()];
+            for _ in 0..items.len() {
+                let item = items[rng.gen_range(0..items.len())];
+                resampled[item] += weights[item];
+            }
+            let mut distances = calculate_distances(&jaccard_table(r, c, &resampled, group_count));
+            let dend = linkage(&mut distances, group_count, method);
+            get_clades(&dend).into_iter().collect()
+        })
+        .collect();
+    clades
+        .iter()
+        .map(|clade| {
+            let n = recovered.iter().filter(|x| x.contains(clade)).count();
+            100.0 * n as f64 / replicates as f64
+        })
+        .collect()
+}
+
+// dendrogram as phylogeny with the groups as leaves, whose internal nodes are labeled by their
+// bootstrap support, if given
+fn get_newick_tree(
+    dend: &Dendrogram<f32>,
+    labels: &[String],
+    support: Option<&[f64]>,
+) -> NewickTree {
+    let observations = dend.observations();
+    let mut tree = NewickTree { nodes: Vec::new() };
+    // clusters yet to be added, along with their parent in the tree
+    let mut stack: Vec<(usize, Option<usize>)> = Vec::new();
+    if observations > 1 {
+        stack.push((observations + dend.len() - 1, None));
+    } else if observations == 1 {
+        stack.push((0, None));
+    }
+    while let Some((cluster, parent)) = stack.pop() {
+        let node = tree.nodes.len();
+        if let Some(parent) = parent {
+            tree.nodes[parent].children.push(node);
+        }
+        if cluster < observations {
+            tree.nodes.push(NewickNode {
+                label: Some(labels[cluster].clone()),
+                children: Vec::new(),
+            });
+        } else {
+            let step = &dend.steps()[cluster - observations];
+            tree.nodes.push(NewickNode {
+                label: support.map(|s| format!("{:.0}", s[cluster - observations])),
+                children: Vec::new(),
+            });
+            // the first cluster is added first
+            stack.push((step.cluster2, Some(node)));
+            stack.push((step.cluster1, Some(node)));
+        }
+    }
+    tree
+}
+
 fn sort_by_indices<T>(list: &mut Vec<T>, indices: &Vec<usize>) {
     let mut indices = indices.clone();
     for i in 0..indices.len() {
@@ -235,6 +547,38 @@ fn get_table_string(table: &Vec<Vec<f32>>, groups: &Vec<String>) -> String {
     res
 }
 
+// rectangular table of the similarity of each group to each population, along with the population
+// of the group with the highest similarity
+fn get_rectangular_table_string(
+    table: &[Vec<f32>],
+    groups: &[String],
+    populations: &[String],
+) -> String {
+    let mut res = String::new();
+    res.push_str("group\tbest match");
+    for population in populations {
+        res.push_str(&format!("\t{}", population));
+    }
+    res.push('\n');
+    for (row, group) in table.iter().zip(groups) {
+        let best = row
+            .iter()
+            .enumerate()
+            .fold(None, |best: Option<(usize, f32)>, (p, x)| match best {
+                Some((_, y)) if y >= *x => best,
+                _ => Some((p, *x)),
+            })
+            .map(|(p, _)| populations[p].as_str())
+            .unwrap_or("NA");
+        res.push_str(&format!("{}\t{}", group, best));
+        for cell in row {
+            res.push_str(&format!("\t{}", cell));
+        }
+        res.push('\n');
+    }
+    res
+}
+
 fn euclidean(row1: &Vec<f32>, row2: &Vec<f32>) -> f32 {
     row1.iter()
         .zip(row2.iter())
@@ -252,3 +596,70 @@ fn calculate_distances(table: &Vec<Vec<f32>>) -> Vec<f32> {
     }
     condensed
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_jaccard_table_weights() {
+        // countables 1..=3 covered by groups {0, 1}, {0}, and {1}
+        let r = vec![0, 0, 2, 3, 4];
+        let c: Vec<GroupSize> = vec![0, 1, 0, 1];
+        let table = jaccard_table(&r, &c, &[1, 1, 1, 1], 2);
+        assert_eq!(table, vec![vec![1.0, 1.0 / 3.0], vec![1.0 / 3.0, 1.0]]);
+        // a long shared countable dominates the bp-weighted similarity
+        let table = jaccard_table(&r, &c, &[0, 98, 1, 1], 2);
+        assert_eq!(table[0][1], 0.98);
+        // groups without countables
+        let table = jaccard_table(&r, &c, &[0, 0, 0, 0], 2);
+        assert_eq!(table, vec![vec![0.0, 0.0], vec![0.0, 0.0]]);
+    }
+
+    #[test]
+    fn test_jaccard_to_populations() {
+        // countables 1..=3 covered by groups {0, 1}, {0}, and {2}; groups 0 and 1 form population
+        // 0, group 2 population 1
+        let r = vec![0, 0, 2, 3, 4];
+        let c: Vec<GroupSize> = vec![0, 1, 0, 2];
+        let sets = vec![Some(0), Some(0), Some(1)];
+        let table = jaccard_to_populations(&r, &c, &[1, 1, 1, 1], &sets, 2);
+        // group 0 is compared against the pan-set of group 1 only
+        assert_eq!(table[0], vec![0.5, 0.0]);
+        assert_eq!(table[1], vec![0.5, 0.0]);
+        assert_eq!(table[2], vec![0.0, 0.0]);
+        // without the population of group 2, it is compared against no pan-set of its own
+        let sets = vec![Some(0), Some(0), None];
+        let table = jaccard_to_populations(&r, &c, &[1, 1, 1, 1], &sets, 1);
+        assert_eq!(table[2], vec![0.0]);
+        assert_eq!(table[0], vec![0.5]);
+    }
+
+    #[test]
+    fn test_clades_and_newick_tree() {
+        // groups 0 and 2 are identical, group 1 differs
+        let table = vec![
+            vec![1.0, 0.2, 1.0],
+            vec![0.2, 1.0, 0.2],
+            vec![1.0, 0.2, 1.0],
+        ];
+        let dend = linkage(&mut calculate_distances(&table), 3, Method::Average);
+        assert_eq!(get_clades(&dend), vec![vec![0, 2], vec![0, 1, 2]]);
+        let labels = vec!["a".to_string(), "b".to_string(), "c".to_string()];
+        let tree = get_newick_tree(&dend, &labels, Some(&[90.0, 100.0]));
+        assert_eq!(tree.get_leaves().len(), 3);
+        let text = tree.to_string();
+        assert!(text == "(b,(a,c)90)100;" || text == "((a,c)90,b)100;");
+    }
+
+    #[test]
+    fn test_bootstrap_support() {
+        // countables 1..=4 covered by groups {0, 2}, {0, 2}, {1}, and {0, 1, 2}
+        let r = vec![0, 0, 2, 4, 5, 8];
+        let c: Vec<GroupSize> = vec![0, 2, 0, 2, 1, 0, 1, 2];
+        let weights = vec![0, 1, 1, 1, 1];
+        let clades = vec![vec![0, 2], vec![0, 1, 2]];
+        let support = bootstrap_support(&r, &c, &weights, 3, Method::Average, &clades, 20);
+        assert_eq!(support, vec![100.0, 100.0]);
+    }
+}
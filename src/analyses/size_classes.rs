@@ -0,0 +1,313 @@
+use std::collections::HashSet;
+use std::fmt;
+
+use crate::{
+    analysis_parameter::AnalysisParameter,
+    graph_broker::{GraphBroker, Hist, ThresholdContainer},
+    html_report::{AnalysisSection, ReportItem},
+    io::write_table,
+    util::{get_default_plot_downloads, to_id, CountType},
+};
+
+use super::{Analysis, ConstructibleAnalysis, InputRequirement};
+
+pub struct SizeClasses {
+    parameter: AnalysisParameter,
+    classes: Vec<SizeClass>,
+    // histogram of each class and its growth curves for each coverage and quorum
+    hists: Vec<Vec<usize>>,
+    growths: Vec<Vec<Vec<f64>>>,
+    hist_aux: Option<ThresholdContainer>,
+}
+
+// range [min, max] of node lengths, where the maximum is open if not given
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct SizeClass {
+    min: u32,
+    max: Option<u32>,
+}
+
+impl fmt::Display for SizeClass {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.max {
+            Some(max) if max == self.min => write!(f, "{}bp", max),
+            Some(max) => write!(f, "{}-{}bp", self.min, max),
+            None => write!(f, "≥{}bp", self.min),
+        }
+    }
+}
+
+impl Analysis for SizeClasses {
+    fn get_type(&self) -> String {
+        "SizeClasses".to_string()
+    }
+
+    fn generate_table(&mut self, gb: Option<&GraphBroker>) -> anyhow::Result<String> {
+        log::info!("reporting hist and growth per size class of nodes");
+        let gb = gb.expect("SizeClasses analysis needs a graph");
+        self.set_hists(gb)?;
+        let count = self.get_count_type();
+        let hist_aux = self.hist_aux.as_ref().unwrap();
+
+        let mut res = String::new();
+        res.push_str(&crate::io::write_metadata_comments()?);
+        let mut header_cols = vec![vec![
+            "panacus".to_string(),
+            "count".to_string(),
+            "node length".to_string(),
+            "coverage".to_string(),
+            "quorum".to_string(),
+        ]];
+        let mut output_columns: Vec<Vec<f64>> = Vec::new();
+        for (class, hist) in self.classes.iter().zip(&self.hists) {
+            output_columns.push(hist.iter().map(|x| *x as f64).collect());
+            header_cols.push(vec![
+                "hist".to_string(),
+                count.to_string(),
+                class.to_string(),
+                String::new(),
+                String::new(),
+            ]);
+        }
+        for (class, growths) in self.classes.iter().zip(&self.growths) {
+            for ((growth, c), q) in growths.iter().zip(&hist_aux.coverage).zip(&hist_aux.quorum) {
+                output_columns.push(growth.clone());
+                header_cols.push(vec![
+                    "growth".to_string(),
+                    count.to_string(),
+                    class.to_string(),
+                    c.get_string(),
+                    q.get_string(),
+                ]);
+            }
+        }
+        res.push_str(&write_table(&header_cols, &output_columns)?);
+        Ok(res)
+    }
+
+    fn generate_report_section(
+        &mut self,
+        gb: Option<&GraphBroker>,
+    ) -> anyhow::Result<Vec<AnalysisSection>> {
+        let gb = gb.expect("SizeClasses analysis needs a graph");
+        let table = self.generate_table(Some(gb))?;
+        let table = format!("`{}`", &table);
+        let id_prefix = format!("size-classes-{}", to_id(&self.get_run_id(gb)));
+        let count = self.get_count_type();
+        let hist_aux = self.hist_aux.as_ref().unwrap();
+        let names: Vec<String> = self.classes.iter().map(|c| c.to_string()).collect();
+        let growth_labels: Vec<String> = hist_aux
+            .coverage
+            .iter()
+            .zip(&hist_aux.quorum)
+            .map(|(c, q)| format!("coverage ≥ {}, quorum ≥ {}", c.get_string(), q.get_string()))
+            .collect();
+
+        // the histograms of all classes side by side, followed by one growth plot per class
+        let mut items = vec![ReportItem::MultiBar {
+            id: format!("{id_prefix}-hist"),
+            names,
+            x_label: "taxa".to_string(),
+            y_label: format!("#{}s", count),
+            labels: (0..self.hists[0].len()).map(|i| i.to_string()).collect(),
+            values: self
+                .hists
+                .iter()
+                .map(|h| h.iter().map(|x| *x as f64).collect())
+                .collect(),
+            log_toggle: true,
+            phenotypes: None,
+            hover: None,
+        }];
+        items.extend(self.classes.iter().zip(&self.growths).enumerate().map(
+            |(i, (class, growths))| {
+                ReportItem::MultiBar {
+                    id: format!("{id_prefix}-growth-{i}"),
+                    names: growth_labels.clone(),
+                    x_label: format!("taxa ({} nodes)", class),
+                    y_label: format!("#{}s", count),
+                    labels: (0..growths[0].len()).map(|i| i.to_string()).collect(),
+                    values: growths
+                        .iter()
+                        .map(|g| {
+                            g.iter()
+                                .map(|x| if x.is_nan() { 0.0 } else { *x })
+                                .collect()
+                        })
+                        .collect(),
+                    log_toggle: false,
+                    phenotypes: None,
+                    hover: None,
+                }
+            },
+        ));
+
+        Ok(vec![AnalysisSection {
+            id: id_prefix,
+            analysis: "Size Classes".to_string(),
+            table: Some(table),
+            run_name: self.get_run_name(gb),
+            run_id: self.get_run_id(gb),
+            countable: count.to_string(),
+            items,
+            plot_downloads: get_default_plot_downloads(),
+            description: None,
+        }])
+    }
+
+    fn get_graph_requirements(&self) -> HashSet<InputRequirement> {
+        let mut req = HashSet::from([InputRequirement::Node]);
+        if self.get_count_type() == CountType::Bp {
+            req.insert(InputRequirement::Bp);
+        }
+        req
+    }
+}
+
+impl ConstructibleAnalysis for SizeClasses {
+    fn from_parameter(parameter: AnalysisParameter) -> Self {
+        Self {
+            parameter,
+            classes: Vec::new(),
+            hists: Vec::new(),
+            growths: Vec::new(),
+            hist_aux: None,
+        }
+    }
+}
+
+impl SizeClasses {
+    fn get_count_type(&self) -> CountType {
+        match &self.parameter {
+            AnalysisParameter::SizeClasses { count_type, .. } => *count_type,
+            _ => panic!("SizeClasses analysis needs a size classes parameter"),
+        }
+    }
+
+    fn set_hists(&mut self, gb: &GraphBroker) -> anyhow::Result<()> {
+        if self.hist_aux.is_some() {
+            return Ok(());
+        }
+        let (classes, coverage, quorum) = match &self.parameter {
+            AnalysisParameter::SizeClasses {
+                classes,
+                coverage,
+                quorum,
+                ..
+            } => (
+                parse_size_classes(classes)?,
+                coverage.to_owned().unwrap_or_else(|| "1".to_string()),
+                quorum.to_owned().unwrap_or_else(|| "0".to_string()),
+            ),
+            _ => panic!("SizeClasses analysis needs a size classes parameter"),
+        };
+        let count = self.get_count_type();
+        if !matches!(count, CountType::Node | CountType::Bp) {
+            anyhow::bail!("size classes can only be counted in nodes or bp");
+        }
+        let hist_aux = ThresholdContainer::parse_params(&quorum, &coverage)?;
+
+        let ranges: Vec<(u32, u32)> = classes
+            .iter()
+            .map(|c| (c.min, c.max.unwrap_or(u32::MAX)))
+            .collect();
+        self.hists = gb
+            .get_abacus_by_total(count)
+            .construct_hists_by_node_len(gb.get_node_lens(), &ranges);
+        self.growths = self
+            .hists
+            .iter()
+            .map(|coverage| {
+                Hist {
+                    count,
+                    coverage: coverage.clone(),
+                }
+                .calc_all_growths(&hist_aux)
+            })
+            .collect();
+        self.classes = classes;
+        self.hist_aux = Some(hist_aux);
+        Ok(())
+    }
+
+    fn get_run_name(&self, gb: &GraphBroker) -> String {
+        gb.get_run_name()
+    }
+
+    fn get_run_id(&self, gb: &GraphBroker) -> String {
+        format!("{}-sizeclasses", gb.get_run_id())
+    }
+}
+
+// parses comma-separated ranges of node lengths, e.g., 1-49,50-999,1000-, where a single length
+// is a class of its own and a range without end is open
+fn parse_size_classes(text: &str) -> anyhow::Result<Vec<SizeClass>> {
+    let parse = |x: &str| {
+        x.trim()
+            .parse::<u32>()
+            .map_err(|_| anyhow::anyhow!("size class {} is not a range of node lengths", text))
+    };
+    let mut res = Vec::new();
+    for class in text.split(',').filter(|c| !c.trim().is_empty()) {
+        let class = match class.split_once('-') {
+            Some((min, max)) if max.trim().is_empty() => SizeClass {
+                min: parse(min)?,
+                max: None,
+            },
+            Some((min, max)) => SizeClass {
+                min: parse(min)?,
+                max: Some(parse(max)?),
+            },
+            None => {
+                let len = parse(class)?;
+                SizeClass {
+                    min: len,
+                    max: Some(len),
+                }
+            }
+        };
+        if class.max.map_or(false, |max| max < class.min) {
+            anyhow::bail!("size class {} is empty", class);
+        }
+        res.push(class);
+    }
+    if res.is_empty() {
+        anyhow::bail!("no size class is given in {}", text);
+    }
+    Ok(res)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_size_classes() {
+        let classes = parse_size_classes("1-49, 50-999,1000-").unwrap();
+        assert_eq!(
+            classes,
+            vec![
+                SizeClass {
+                    min: 1,
+                    max: Some(49)
+                },
+                SizeClass {
+                    min: 50,
+                    max: Some(999)
+                },
+                SizeClass {
+                    min: 1000,
+                    max: None
+                },
+            ]
+        );
+        assert_eq!(
+            classes.iter().map(|c| c.to_string()).collect::<Vec<_>>(),
+            vec!["1-49bp", "50-999bp", "≥1000bp"]
+        );
+        assert_eq!(parse_size_classes("1").unwrap()[0].to_string(), "1bp");
+        assert!(parse_size_classes("50-10").is_err());
+        assert!(parse_size_classes("a-10").is_err());
+        assert!(parse_size_classes("").is_err());
+    }
+}
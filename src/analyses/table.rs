@@ -1,5 +1,7 @@
 use crate::{
-    analyses::InputRequirement, analysis_parameter::AnalysisParameter, io::write_metadata_comments,
+    analyses::InputRequirement,
+    analysis_parameter::{AnalysisParameter, TableFormat},
+    io::write_metadata_comments,
     util::CountType,
 };
 use core::panic;
@@ -17,16 +19,23 @@ impl Analysis for Table {
         gb: Option<&crate::graph_broker::GraphBroker>,
     ) -> anyhow::Result<String> {
         if let Some(gb) = gb {
-            let total = match self.parameter {
-                AnalysisParameter::Table { total, .. } => total,
+            let (total, format) = match self.parameter {
+                AnalysisParameter::Table { total, format, .. } => (total, format),
                 _ => {
                     panic!("Table analysis needs a table parameter")
                 }
             };
             let mut buf = BufWriter::new(Vec::new());
-            gb.write_abacus_by_group(total, &mut buf)?;
+            gb.write_abacus_by_group(total, format, &mut buf)?;
             let bytes = buf.into_inner()?;
-            let mut string = write_metadata_comments()?;
+            // MatrixMarket files must start with their banner
+            let mut string = if format == TableFormat::Mtx {
+                String::new()
+            } else {
+                let mut comments = write_metadata_comments()?;
+                comments.push_str(&gb.get_dedup_comment().unwrap_or_default());
+                comments
+            };
             string.push_str(&String::from_utf8(bytes)?);
             Ok(string)
         } else {
@@ -68,6 +77,7 @@ impl Table {
             CountType::Bp => HashSet::from([InputRequirement::Bp]),
             CountType::Node => HashSet::from([InputRequirement::Node]),
             CountType::Edge => HashSet::from([InputRequirement::Edge]),
+            CountType::Bubble => HashSet::from([InputRequirement::Bubble]),
             CountType::All => HashSet::from([
                 InputRequirement::Bp,
                 InputRequirement::Node,
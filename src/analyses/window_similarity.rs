@@ -0,0 +1,403 @@
+use std::collections::HashSet;
+
+use rayon::prelude::*;
+
+use crate::{
+    analysis_parameter::AnalysisParameter,
+    graph_broker::{GraphBroker, PathSegment},
+    html_report::{AnalysisSection, ReportItem},
+    util::{get_default_plot_downloads, to_id, CountType, GroupSize},
+};
+
+use super::{Analysis, ConstructibleAnalysis, InputRequirement};
+
+// maximum number of windows and of pairs of groups that are shown in the report; adjacent windows
+// are averaged beyond that, and more pairs are summarized by the mean similarity of each group to
+// all others
+const MAX_REPORT_WINDOWS: usize = 500;
+const MAX_REPORT_PAIRS: usize = 100;
+
+pub struct WindowSimilarity {
+    parameter: AnalysisParameter,
+    sequence: String,
+    groups: Vec<String>,
+    // windows (start, end) along the reference that hold any countable, and the similarity of
+    // each pair of groups (i < j, in row-major order) within them
+    windows: Vec<(usize, usize)>,
+    similarities: Vec<Vec<f32>>,
+}
+
+impl Analysis for WindowSimilarity {
+    fn get_type(&self) -> String {
+        "WindowSimilarity".to_string()
+    }
+
+    fn generate_table(&mut self, gb: Option<&GraphBroker>) -> anyhow::Result<String> {
+        log::info!("reporting similarity of groups in windows along the reference");
+        let gb = gb.expect("WindowSimilarity analysis needs a graph");
+        self.set_similarities(gb)?;
+
+        let mut res = String::new();
+        res.push_str(&crate::io::write_metadata_comments()?);
+        res.push_str(&format!(
+            "# Jaccard similarity of the {} within windows along {}\n",
+            self.get_count_type(),
+            self.get_reference()
+        ));
+        res.push_str("sequence\tstart\tend\tgroup1\tgroup2\tsimilarity\n");
+        let pairs = get_pairs(self.groups.len());
+        for ((start, end), values) in self.windows.iter().zip(&self.similarities) {
+            for ((i, j), value) in pairs.iter().zip(values) {
+                res.push_str(&format!(
+                    "{}\t{}\t{}\t{}\t{}\t{}\n",
+                    self.sequence, start, end, self.groups[*i], self.groups[*j], value
+                ));
+            }
+        }
+        Ok(res)
+    }
+
+    fn generate_report_section(
+        &mut self,
+        gb: Option<&GraphBroker>,
+    ) -> anyhow::Result<Vec<AnalysisSection>> {
+        let gb = gb.expect("WindowSimilarity analysis needs a graph");
+        let table = self.generate_table(Some(gb))?;
+        let table = format!("`{}`", &table);
+        let id_prefix = format!("window-similarity-{}", to_id(&self.get_run_id(gb)));
+
+        let pairs = get_pairs(self.groups.len());
+        let (y_labels, values): (Vec<String>, Vec<Vec<f32>>) = if pairs.len() <= MAX_REPORT_PAIRS {
+            (
+                pairs
+                    .iter()
+                    .map(|(i, j)| format!("{} vs {}", self.groups[*i], self.groups[*j]))
+                    .collect(),
+                self.similarities.clone(),
+            )
+        } else {
+            log::info!(
+                "showing the mean similarity of each of {} groups to all others in the report",
+                self.groups.len()
+            );
+            (
+                self.groups.clone(),
+                self.similarities
+                    .iter()
+                    .map(|values| mean_by_group(values, self.groups.len()))
+                    .collect(),
+            )
+        };
+        let chunk = self.windows.len() / MAX_REPORT_WINDOWS + 1;
+        if chunk > 1 {
+            log::info!(
+                "averaging {} windows over {} adjacent ones for the report",
+                self.windows.len(),
+                chunk
+            );
+        }
+        let x_labels = self
+            .windows
+            .chunks(chunk)
+            .map(|w| format!("{}-{}", w[0].0, w[w.len() - 1].1))
+            .collect();
+        let values = values
+            .chunks(chunk)
+            .map(|rows| {
+                (0..y_labels.len())
+                    .map(|k| rows.iter().map(|row| row[k]).sum::<f32>() / rows.len() as f32)
+                    .collect()
+            })
+            .collect();
+
+        Ok(vec![AnalysisSection {
+            id: id_prefix.clone(),
+            analysis: "Window Similarity".to_string(),
+            table: Some(table),
+            run_name: self.get_run_name(gb),
+            run_id: self.get_run_id(gb),
+            countable: self.get_count_type().to_string(),
+            items: vec![ReportItem::Heatmap {
+                id: id_prefix,
+                name: gb.get_fname(),
+                x_labels,
+                y_labels,
+                values,
+                phenotypes: None,
+            }],
+            plot_downloads: get_default_plot_downloads(),
+            description: None,
+        }])
+    }
+
+    fn get_graph_requirements(&self) -> HashSet<InputRequirement> {
+        let count = self.get_count_type();
+        let mut req = HashSet::from([
+            InputRequirement::Node,
+            InputRequirement::AbacusByGroup(count),
+        ]);
+        if count == CountType::Bp {
+            req.insert(InputRequirement::Bp);
+        }
+        req
+    }
+}
+
+impl ConstructibleAnalysis for WindowSimilarity {
+    fn from_parameter(parameter: AnalysisParameter) -> Self {
+        Self {
+            parameter,
+            sequence: String::new(),
+            groups: Vec::new(),
+            windows: Vec::new(),
+            similarities: Vec::new(),
+        }
+    }
+}
+
+impl WindowSimilarity {
+    fn get_reference(&self) -> &str {
+        match &self.parameter {
+            AnalysisParameter::WindowSimilarity { reference, .. } => reference,
+            _ => panic!("WindowSimilarity analysis needs a window similarity parameter"),
+        }
+    }
+
+    fn get_count_type(&self) -> CountType {
+        match &self.parameter {
+            AnalysisParameter::WindowSimilarity { count_type, .. } => *count_type,
+            _ => panic!("WindowSimilarity analysis needs a window similarity parameter"),
+        }
+    }
+
+    // size of the windows and the distance between their starts
+    fn get_window_and_step(&self) -> (usize, usize) {
+        match &self.parameter {
+            AnalysisParameter::WindowSimilarity { window, step, .. } => {
+                (*window, step.unwrap_or(*window))
+            }
+            _ => panic!("WindowSimilarity analysis needs a window similarity parameter"),
+        }
+    }
+
+    fn set_similarities(&mut self, gb: &GraphBroker) -> anyhow::Result<()> {
+        if !self.windows.is_empty() {
+            return Ok(());
+        }
+        let count = self.get_count_type();
+        if !matches!(count, CountType::Node | CountType::Bp) {
+            anyhow::bail!("similarity in windows can only be computed over nodes or bp");
+        }
+        let (window, step) = self.get_window_and_step();
+        if window == 0 || step == 0 {
+            anyhow::bail!("size and step of the windows must be at least 1");
+        }
+        let reference = PathSegment::from_str(self.get_reference());
+        let mut fragments = gb.get_path_steps(&reference)?;
+        if fragments.is_empty() {
+            anyhow::bail!(
+                "reference path {} does not exist in graph {}",
+                self.get_reference(),
+                gb.get_fname()
+            );
+        }
+        fragments.sort_by_key(|(path_seg, _)| path_seg.start.unwrap_or(0));
+        self.sequence = reference
+            .seqid
+            .clone()
+            .unwrap_or_else(|| reference.clear_coords().id());
+
+        // position of the nodes of the reference, where repeated nodes are placed at their first
+        // occurrence
+        let node_lens = gb.get_node_lens();
+        let mut ref_pos: Vec<Option<usize>> = vec![None; node_lens.len()];
+        for (path_seg, steps) in fragments {
+            let mut pos = path_seg.start.unwrap_or(0);
+            for (node, _) in steps {
+                let p = &mut ref_pos[node.0 as usize];
+                if p.is_none() {
+                    *p = Some(pos);
+                }
+                pos += node_lens[node.0 as usize] as usize;
+            }
+        }
+
+        // nodes off the reference are placed at the end of the reference node that precedes them
+        // in the first path/walk that visits them
+        log::info!("placing nodes off the reference {}", self.get_reference());
+        let mut pos = ref_pos.clone();
+        gb.visit_path_steps(
+            |_| true,
+            |_, steps| {
+                let mut last = None;
+                for (node, _) in steps {
+                    let i = node.0 as usize;
+                    match ref_pos[i] {
+                        Some(p) => last = Some(p + node_lens[i] as usize),
+                        None if pos[i].is_none() => pos[i] = last,
+                        None => (),
+                    }
+                }
+            },
+        )?;
+
+        let abacus = gb.get_abacus_by_group();
+        let weights = abacus.get_item_weights(node_lens);
+        let mut nodes: Vec<(usize, usize)> = pos
+            .iter()
+            .enumerate()
+            .filter_map(|(i, p)| p.map(|p| (p, i)))
+            .filter(|(_, i)| weights.get(*i).copied().unwrap_or(0) > 0)
+            .collect();
+        nodes.sort_unstable();
+        let windows = get_windows(&nodes, window, step);
+        log::info!(
+            "computing similarity of {} groups in {} windows of {}bp",
+            abacus.groups.len(),
+            windows.len(),
+            window
+        );
+        let (r, c, group_count) = (&abacus.r[..], &abacus.c[..], abacus.groups.len());
+        self.similarities = windows
+            .par_iter()
+            .map(|(_, _, from, to)| {
+                let items: Vec<usize> = nodes[*from..*to].iter().map(|(_, i)| *i).collect();
+                jaccard_pairs(&items, r, c, &weights, group_count)
+            })
+            .collect();
+        self.windows = windows.iter().map(|(s, e, _, _)| (*s, *e)).collect();
+        self.groups = abacus.groups.clone();
+        Ok(())
+    }
+
+    fn get_run_name(&self, gb: &GraphBroker) -> String {
+        gb.get_run_name()
+    }
+
+    fn get_run_id(&self, gb: &GraphBroker) -> String {
+        format!("{}-windowsimilarity", gb.get_run_id())
+    }
+}
+
+// windows (start, end) along the reference, each with the range of the (position-sorted) nodes
+// placed within them; windows without nodes are left out
+fn get_windows(
+    nodes: &[(usize, usize)],
+    window: usize,
+    step: usize,
+) -> Vec<(usize, usize, usize, usize)> {
+    let mut res = Vec::new();
+    let length = match nodes.last() {
+        Some((p, _)) => p + 1,
+        None => return res,
+    };
+    let (mut from, mut to) = (0, 0);
+    let mut start = 0;
+    while start < length {
+        let end = start + window;
+        while from < nodes.len() && nodes[from].0 < start {
+            from += 1;
+        }
+        to = to.max(from);
+        while to < nodes.len() && nodes[to].0 < end {
+            to += 1;
+        }
+        if to > from {
+            res.push((start, end, from, to));
+        }
+        start += step;
+    }
+    res
+}
+
+// pairs (i, j) of groups with i < j
+fn get_pairs(group_count: usize) -> Vec<(usize, usize)> {
+    (0..group_count)
+        .flat_map(|i| (i + 1..group_count).map(move |j| (i, j)))
+        .collect()
+}
+
+// weighted Jaccard similarity of each pair of groups (as given by get_pairs) over the given items
+fn jaccard_pairs(
+    items: &[usize],
+    r: &[usize],
+    c: &[GroupSize],
+    weights: &[usize],
+    group_count: usize,
+) -> Vec<f32> {
+    let mut intersections = vec![vec![0; group_count]; group_count];
+    for i in items {
+        let groups = &c[r[*i]..r[*i + 1]];
+        for x in groups {
+            for y in groups {
+                intersections[*x as usize][*y as usize] += weights[*i];
+            }
+        }
+    }
+    get_pairs(group_count)
+        .into_iter()
+        .map(|(i, j)| {
+            let union = intersections[i][i] + intersections[j][j] - intersections[i][j];
+            if union > 0 {
+                intersections[i][j] as f32 / union as f32
+            } else {
+                0.0
+            }
+        })
+        .collect()
+}
+
+// mean similarity of each group to all other groups
+fn mean_by_group(values: &[f32], group_count: usize) -> Vec<f32> {
+    let mut res = vec![0.0; group_count];
+    for ((i, j), v) in get_pairs(group_count).into_iter().zip(values) {
+        res[i] += v;
+        res[j] += v;
+    }
+    if group_count > 1 {
+        res.iter_mut().for_each(|x| *x /= (group_count - 1) as f32);
+    }
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_get_windows() {
+        let nodes = vec![(0, 1), (5, 2), (12, 3), (35, 4)];
+        assert_eq!(
+            get_windows(&nodes, 10, 10),
+            vec![(0, 10, 0, 2), (10, 20, 2, 3), (30, 40, 3, 4)]
+        );
+        assert_eq!(
+            get_windows(&nodes, 10, 5),
+            vec![
+                (0, 10, 0, 2),
+                (5, 15, 1, 3),
+                (10, 20, 2, 3),
+                (30, 40, 3, 4),
+                (35, 45, 3, 4)
+            ]
+        );
+        assert_eq!(get_windows(&[], 10, 10), vec![]);
+    }
+
+    #[test]
+    fn test_jaccard_pairs() {
+        // node 1 is covered by groups 0 and 1, node 2 by group 1, and node 3 by all groups
+        let r = vec![0, 0, 2, 3, 6];
+        let c = vec![0, 1, 1, 0, 1, 2];
+        let weights = vec![0, 1, 1, 2];
+        assert_eq!(
+            jaccard_pairs(&[1, 2, 3], &r, &c, &weights, 3),
+            vec![0.75, 2.0 / 3.0, 0.5]
+        );
+        assert_eq!(
+            jaccard_pairs(&[2], &r, &c, &weights, 3),
+            vec![0.0, 0.0, 0.0]
+        );
+        assert_eq!(mean_by_group(&[0.75, 0.5, 0.5], 3), vec![0.625, 0.625, 0.5]);
+    }
+}
@@ -1,16 +1,28 @@
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
+use std::convert::TryFrom;
 use std::fmt;
 use std::fmt::Debug;
 use std::fmt::Display;
+use std::str::FromStr;
 use strum_macros::{EnumIter, EnumString, EnumVariantNames};
 
-use serde::{Deserialize, Serialize};
+use serde::de::Error as _;
+use serde::{Deserialize, Deserializer, Serialize};
+use serde_yaml::value::{Tag, TaggedValue};
+use serde_yaml::{Mapping, Value};
 
-use crate::analyses::ConstructibleAnalysis;
 use crate::analyses::{
-    coverage_line::CoverageLine, growth::Growth, info::Info, node_distribution::NodeDistribution,
-    ordered_histgrowth::OrderedHistgrowth, similarity::Similarity, table::Table,
+    base_composition::BaseComposition, chromosomal::Chromosomal, coverage_colors::CoverageColors,
+    coverage_line::CoverageLine, depth::Depth, differential::Differential, duplicates::Duplicates,
+    growth::Growth, info::Info, kmer_comparison::KmerComparison, node_coverage::NodeCoverage,
+    node_distribution::NodeDistribution, node_lengths::NodeLengths,
+    node_multiplicity::NodeMultiplicity, ordered_histgrowth::OrderedHistgrowth, paths::Paths,
+    phylo_growth::PhyloGrowth, similarity::Similarity, size_classes::SizeClasses, table::Table,
+    window_similarity::WindowSimilarity,
 };
+use crate::analyses::{Annotated, ConstructibleAnalysis};
+use crate::graph_broker::GraphState;
+use crate::io::{expand_glob, is_glob};
 use crate::Analysis;
 use crate::{
     analyses::{hist::Hist, InputRequirement},
@@ -36,7 +48,19 @@ pub enum Task {
         nice: bool,
         subset: String,
         exclude: String,
+        exclude_reference: Option<String>,
         grouping: Option<Grouping>,
+        pansn: Option<PanSN>,
+        min_group_size: usize,
+        drop_small_groups: bool,
+        dedup: bool,
+        strand_dedup: bool,
+        count_repeats: bool,
+        min_node_len: Option<u32>,
+        max_node_len: Option<u32>,
+        oriented_edges: bool,
+        metadata: Option<String>,
+        color_by: Option<String>,
     },
     OrderChange(Option<String>),
     AbacusByGroupCSCChange,
@@ -46,6 +70,58 @@ pub enum Task {
     },
 }
 
+impl Task {
+    /// Graph state, requirements, and nice flag of a graph state change
+    pub fn get_graph_state(&self) -> Option<(GraphState, &HashSet<InputRequirement>, bool)> {
+        match self {
+            Self::GraphStateChange {
+                graph,
+                name,
+                reqs,
+                nice,
+                subset,
+                exclude,
+                exclude_reference,
+                grouping,
+                pansn,
+                min_group_size,
+                drop_small_groups,
+                dedup,
+                strand_dedup,
+                count_repeats,
+                min_node_len,
+                max_node_len,
+                oriented_edges,
+                metadata,
+                color_by,
+            } => Some((
+                GraphState {
+                    graph: graph.to_string(),
+                    name: name.clone(),
+                    subset: subset.to_string(),
+                    exclude: exclude.to_string(),
+                    exclude_reference: exclude_reference.clone(),
+                    grouping: grouping.clone(),
+                    pansn: pansn.clone(),
+                    min_group_size: *min_group_size,
+                    drop_small_groups: *drop_small_groups,
+                    dedup: *dedup,
+                    strand_dedup: *strand_dedup,
+                    count_repeats: *count_repeats,
+                    min_node_len: *min_node_len,
+                    max_node_len: *max_node_len,
+                    oriented_edges: *oriented_edges,
+                    metadata: metadata.clone(),
+                    color_by: color_by.clone(),
+                },
+                reqs,
+                *nice,
+            )),
+            _ => None,
+        }
+    }
+}
+
 impl Debug for Task {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -57,16 +133,40 @@ impl Debug for Task {
                 nice,
                 subset,
                 exclude,
+                exclude_reference,
                 grouping,
+                pansn,
+                min_group_size,
+                drop_small_groups,
+                dedup,
+                strand_dedup,
+                count_repeats,
+                min_node_len,
+                max_node_len,
+                oriented_edges,
+                metadata,
+                color_by,
             } => f
                 .debug_tuple("GraphStateChange")
                 .field(graph)
                 .field(name)
                 .field(subset)
                 .field(exclude)
+                .field(exclude_reference)
                 .field(grouping)
+                .field(pansn)
+                .field(min_group_size)
+                .field(drop_small_groups)
                 .field(&reqs)
                 .field(nice)
+                .field(dedup)
+                .field(strand_dedup)
+                .field(count_repeats)
+                .field(min_node_len)
+                .field(max_node_len)
+                .field(oriented_edges)
+                .field(metadata)
+                .field(color_by)
                 .finish(),
             Self::OrderChange(order) => f.debug_tuple("OrderChange").field(&order).finish(),
             Self::AbacusByGroupCSCChange => f.debug_tuple("AbacusByGroupCSCChange").finish(),
@@ -87,10 +187,44 @@ pub struct AnalysisRun {
     subset: String,
     #[serde(default)]
     exclude: String,
+    // reference whose paths are excluded, given by the name of its sample or a regex matching it
+    #[serde(default)]
+    exclude_reference: Option<String>,
     grouping: Option<Grouping>,
+    // naming scheme of paths/walks used when grouping by sample or haplotype
+    #[serde(default)]
+    pansn: Option<PanSN>,
+    // groups with fewer paths are merged into a single group (or dropped)
+    #[serde(default)]
+    min_group_size: usize,
+    #[serde(default)]
+    drop_small_groups: bool,
     #[serde(default)]
     nice: bool,
-    analyses: Vec<AnalysisParameter>,
+    // merge nodes with identical or reverse-complementary identical sequences
+    #[serde(default)]
+    dedup: bool,
+    // place covered bp of duplicates on the strand of their representative, such that reverse
+    // traversals and palindromic nodes count the same bp as forward ones (implies dedup)
+    #[serde(default)]
+    strand_dedup: bool,
+    // count every traversal of a countable by a path instead of only the first one
+    #[serde(default)]
+    count_repeats: bool,
+    // only nodes of at least min_node_len and at most max_node_len bp are counted
+    #[serde(default)]
+    min_node_len: Option<u32>,
+    #[serde(default)]
+    max_node_len: Option<u32>,
+    // count edges traversed in opposite orientations as different countables
+    #[serde(default)]
+    oriented_edges: bool,
+    // sample metadata table and the column by which report plots are colored
+    #[serde(default)]
+    metadata: Option<String>,
+    #[serde(default)]
+    color_by: Option<String>,
+    analyses: Vec<AnalysisEntry>,
 }
 
 impl AnalysisRun {
@@ -108,14 +242,168 @@ impl AnalysisRun {
             name,
             subset,
             exclude,
+            exclude_reference: None,
             grouping,
             nice,
-            analyses,
+            pansn: None,
+            min_group_size: 0,
+            drop_small_groups: false,
+            dedup: false,
+            strand_dedup: false,
+            count_repeats: false,
+            min_node_len: None,
+            max_node_len: None,
+            oriented_edges: false,
+            metadata: None,
+            color_by: None,
+            analyses: analyses.into_iter().map(AnalysisEntry::from).collect(),
         }
     }
 
+    pub fn with_exclude_reference(mut self, exclude_reference: Option<String>) -> Self {
+        self.exclude_reference = exclude_reference;
+        self
+    }
+
+    pub fn with_pansn(mut self, pansn: Option<PanSN>) -> Self {
+        self.pansn = pansn;
+        self
+    }
+
+    pub fn with_min_group_size(mut self, min_group_size: usize, drop_small_groups: bool) -> Self {
+        self.min_group_size = min_group_size;
+        self.drop_small_groups = drop_small_groups;
+        self
+    }
+
+    pub fn with_dedup(mut self, dedup: bool, strand_dedup: bool) -> Self {
+        self.dedup = dedup || strand_dedup;
+        self.strand_dedup = strand_dedup;
+        self
+    }
+
+    pub fn with_count_repeats(mut self, count_repeats: bool) -> Self {
+        self.count_repeats = count_repeats;
+        self
+    }
+
+    pub fn with_node_len_range(
+        mut self,
+        min_node_len: Option<u32>,
+        max_node_len: Option<u32>,
+    ) -> Self {
+        self.min_node_len = min_node_len;
+        self.max_node_len = max_node_len;
+        self
+    }
+
+    pub fn with_oriented_edges(mut self, oriented_edges: bool) -> Self {
+        self.oriented_edges = oriented_edges;
+        self
+    }
+
+    pub fn with_metadata(mut self, metadata: Option<String>, color_by: Option<String>) -> Self {
+        self.metadata = metadata;
+        self.color_by = color_by;
+        self
+    }
+
+    // a YAML config is either a list of runs or a map of the runs and of variables that are
+    // substituted in them; runs whose graph is a pattern such as `graphs/chr*.gfa` are repeated
+    // for each matching file, which is available as variable ${graph}, and without directory
+    // and extensions as ${graph_stem}
+    pub fn from_yaml(config: &str) -> anyhow::Result<Vec<Self>> {
+        let (variables, runs) = match serde_yaml::from_str(config)? {
+            Value::Sequence(runs) => (HashMap::new(), runs),
+            config => {
+                let config: Config = serde_yaml::from_value(config)?;
+                (config.variables, config.runs)
+            }
+        };
+        let mut expanded = Vec::new();
+        for mut run in runs {
+            let graph = match run.get_mut("graph") {
+                Some(graph) => {
+                    substitute_variables(graph, &variables)?;
+                    graph.as_str().map(|x| x.to_string())
+                }
+                None => None,
+            };
+            let graphs = match graph {
+                Some(graph) if is_glob(&graph) => expand_glob(&graph)?,
+                Some(graph) => vec![graph],
+                None => {
+                    expanded.push(serde_yaml::from_value(run)?);
+                    continue;
+                }
+            };
+            for graph in graphs {
+                let mut run = run.clone();
+                run["graph"] = Value::String(graph.clone());
+                let mut variables = variables.clone();
+                variables.insert("graph_stem".to_string(), Value::String(get_stem(&graph)));
+                variables.insert("graph".to_string(), Value::String(graph));
+                substitute_variables(&mut run, &variables)?;
+                expanded.push(serde_yaml::from_value(run)?);
+            }
+        }
+        Ok(expanded)
+    }
+
+    // key by which runs are ordered such that those on the same graph are adjacent and, among
+    // them, those with the same subset, grouping, etc., such that the graph is loaded and its abaci
+    // are constructed once per distinct state rather than in the order of the config
+    #[allow(clippy::type_complexity)]
+    fn get_state_key(
+        &self,
+    ) -> (
+        (&String, bool, bool, bool),
+        (
+            &String,
+            &String,
+            &Option<String>,
+            &Option<Grouping>,
+            &Option<PanSN>,
+        ),
+        (usize, bool, bool, bool, Option<u32>, Option<u32>),
+        (&Option<String>, &Option<String>, &Option<String>),
+    ) {
+        (
+            (
+                &self.graph,
+                self.dedup,
+                self.strand_dedup,
+                self.oriented_edges,
+            ),
+            (
+                &self.subset,
+                &self.exclude,
+                &self.exclude_reference,
+                &self.grouping,
+                &self.pansn,
+            ),
+            (
+                self.min_group_size,
+                self.drop_small_groups,
+                self.count_repeats,
+                self.nice,
+                self.min_node_len,
+                self.max_node_len,
+            ),
+            (&self.metadata, &self.color_by, &self.name),
+        )
+    }
+
     pub fn convert_to_tasks(mut runs: Vec<Self>) -> Vec<Task> {
-        runs.sort();
+        runs.sort_by(|a, b| a.get_state_key().cmp(&b.get_state_key()));
+        // runs that only differ in their analyses are executed as one
+        runs.dedup_by(|run, prev| {
+            let is_same_state = run.get_state_key() == prev.get_state_key();
+            if is_same_state {
+                prev.analyses.append(&mut run.analyses);
+            }
+            is_same_state
+        });
         let mut tasks = Vec::new();
         for i in 0..runs.len() {
             let (current_tasks, mut input_req) = runs[i].to_tasks();
@@ -127,7 +415,19 @@ impl AnalysisRun {
                 nice: runs[i].nice,
                 subset: std::mem::take(&mut runs[i].subset),
                 exclude: std::mem::take(&mut runs[i].exclude),
+                exclude_reference: std::mem::take(&mut runs[i].exclude_reference),
                 grouping: std::mem::take(&mut runs[i].grouping),
+                pansn: std::mem::take(&mut runs[i].pansn),
+                min_group_size: runs[i].min_group_size,
+                drop_small_groups: runs[i].drop_small_groups,
+                dedup: runs[i].dedup || runs[i].strand_dedup,
+                strand_dedup: runs[i].strand_dedup,
+                count_repeats: runs[i].count_repeats,
+                min_node_len: runs[i].min_node_len,
+                max_node_len: runs[i].max_node_len,
+                oriented_edges: runs[i].oriented_edges,
+                metadata: std::mem::take(&mut runs[i].metadata),
+                color_by: std::mem::take(&mut runs[i].color_by),
             });
             tasks.extend(current_tasks);
         }
@@ -137,6 +437,7 @@ impl AnalysisRun {
     pub fn to_tasks(&mut self) -> (Vec<Task>, HashSet<InputRequirement>) {
         let mut analyses = std::mem::take(&mut self.analyses);
         analyses.sort();
+        analyses.dedup();
         let (tasks, requirements): (Vec<Vec<Task>>, Vec<HashSet<InputRequirement>>) =
             analyses.into_iter().map(|a| a.into_tasks()).unzip();
         let tasks: Vec<Task> = tasks.into_iter().flatten().collect();
@@ -151,17 +452,103 @@ impl AnalysisRun {
     }
 }
 
+/// Percentile (in %) of a distribution, which unlike a plain float can be part of the hashable and
+/// ordered analysis parameters
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+#[serde(transparent)]
+pub struct Percentile(pub f64);
+
+impl PartialEq for Percentile {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.to_bits() == other.0.to_bits()
+    }
+}
+
+impl Eq for Percentile {}
+
+impl std::hash::Hash for Percentile {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.0.to_bits().hash(state);
+    }
+}
+
+impl PartialOrd for Percentile {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Percentile {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.0
+            .partial_cmp(&other.0)
+            .filter(|o| o.is_ne())
+            .unwrap_or_else(|| self.0.to_bits().cmp(&other.0.to_bits()))
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Hash, Clone, PartialOrd, Ord)]
 pub enum AnalysisParameter {
     Hist {
         #[serde(default)]
         count_type: CountType,
+        // write table in long format, i.e., one row per count and coverage
+        #[serde(default)]
+        long: bool,
+        // also report the cumulative histogram, i.e., the count of coverage at most i
+        #[serde(default)]
+        cumulative: bool,
+        // also report the histogram as fractions of the total count
+        #[serde(default)]
+        normalized: bool,
+        // treatment of countables not covered by any counted path, other than excluded ones;
+        // growth curves never count them
+        #[serde(default)]
+        uncovered: UncoveredMode,
+        // file to which the nodes not covered by any counted path are written
+        #[serde(default)]
+        list_uncovered: Option<String>,
     },
     Growth {
         coverage: Option<String>,
         quorum: Option<String>,
         #[serde(default)]
         add_hist: bool,
+        #[serde(default)]
+        extrapolate: Option<usize>,
+        #[serde(default)]
+        alpha_method: AlphaMethod,
+        // first and last number of genomes whose growth is used to fit alpha
+        #[serde(default)]
+        alpha_from: Option<usize>,
+        #[serde(default)]
+        alpha_to: Option<usize>,
+        // write table in long format, i.e., one row per count, coverage, quorum, and m
+        #[serde(default)]
+        long: bool,
+        // percentiles (in %) of the growth over random orders of the genomes, e.g., 50 for the
+        // median, reported alongside the expected growth
+        #[serde(default)]
+        percentiles: Vec<Percentile>,
+        // number of random orders of the genomes from which the percentiles are estimated, and
+        // number of subsamples drawn at each rarefaction depth
+        #[serde(default = "get_permutations")]
+        permutations: usize,
+        // numbers of genomes that are subsampled exactly, reporting the distribution of the
+        // pangenome size over the subsamples at each of them
+        #[serde(default)]
+        rarefaction: Vec<usize>,
+        // two-column file assigning groups to populations; if given, the growth is also computed
+        // over random orders that draw equally from each population
+        #[serde(default)]
+        stratify: Option<String>,
+        // divide the growth by the average size of a genome in the countable, e.g., in bp
+        #[serde(default)]
+        normalize: bool,
+        // countable (node, bp, or edge) of which percentiles, rarefaction, and stratified growth
+        // are estimated
+        #[serde(default)]
+        count_type: CountType,
     },
     Table {
         #[serde(default)]
@@ -169,12 +556,83 @@ pub enum AnalysisParameter {
 
         total: bool,
         order: Option<String>,
+        // tsv writes the dense table, mtx and triplets only its non-zero entries
+        #[serde(default)]
+        format: TableFormat,
     },
     NodeDistribution {
         #[serde(default = "get_radius")]
         radius: u32,
+        // file to which the nodes of each bin are written
+        #[serde(default)]
+        members: Option<String>,
+        // FASTA file to which the sequences of the nodes of the selected bins are written
+        #[serde(default)]
+        fasta: Option<String>,
+        // bins whose nodes are written to the FASTA file (all if empty)
+        #[serde(default)]
+        fasta_bins: Vec<usize>,
+    },
+    Info {
+        // also report complexity measures, which requires detecting the bubbles of the graph
+        #[serde(default)]
+        complexity: bool,
+        // reference path to which the density of branching nodes is related
+        #[serde(default)]
+        reference: Option<String>,
+        // file to which the internal node IDs are written along with segment names and lengths
+        #[serde(default)]
+        node_ids: Option<String>,
+        // file to which the graph is written without the edges that are not used by any path
+        #[serde(default)]
+        clean_gfa: Option<String>,
+    },
+    Duplicates,
+    BaseComposition,
+    NodeLengths,
+    NodeCoverage,
+    Depth,
+    Paths,
+    SizeClasses {
+        // comma-separated ranges of node lengths (e.g., 1-49,50-999,1000-), each of which gets
+        // its own hist and growth
+        #[serde(default = "get_size_classes")]
+        classes: String,
+        #[serde(default)]
+        count_type: CountType,
+        #[serde(default)]
+        coverage: Option<String>,
+        #[serde(default)]
+        quorum: Option<String>,
+    },
+    NodeMultiplicity {
+        // multiplicity from which on nodes are reported as high-copy nodes
+        #[serde(default = "get_min_copies")]
+        min_copies: u32,
+    },
+    CoverageColors {
+        // comma-separated list of hex colors or name of a colormap
+        #[serde(default)]
+        palette: Option<String>,
+        // smallest coverages of the coverage classes (apart from the first), by default those of
+        // shell and core
+        #[serde(default)]
+        breakpoints: Vec<usize>,
+        // two-column file assigning groups to two phenotypes; if given, nodes are colored by the
+        // difference of their coverage in either phenotype
+        #[serde(default)]
+        phenotype: Option<String>,
+    },
+    KmerComparison {
+        // FASTA files (optionally gzipped) of the assemblies whose k-mers are compared against
+        // those of the node sequences
+        assemblies: Vec<String>,
+        #[serde(default = "get_kmer_size")]
+        k: usize,
+        // only about 1/scale of all k-mers are sampled (FracMinHash), trading accuracy for memory
+        #[serde(default = "get_kmer_scale")]
+        scale: u64,
     },
-    Info,
     OrderedGrowth {
         coverage: Option<String>,
         quorum: Option<String>,
@@ -193,6 +651,58 @@ pub enum AnalysisParameter {
         count_type: CountType,
         #[serde(default)]
         cluster_method: ClusterMethod,
+        // two-column file assigning groups to populations; if given, each group is compared
+        // against the pan-set of each population
+        #[serde(default)]
+        populations: Option<String>,
+        // number of bootstrap replicates resampling the countables, to attach support values to
+        // the clades of the dendrogram
+        #[serde(default)]
+        bootstrap: usize,
+        // file to which the dendrogram is written in Newick format
+        #[serde(default)]
+        newick: Option<String>,
+    },
+    WindowSimilarity {
+        // reference path along which the windows are laid out; nodes off the reference are
+        // placed after the reference node preceding them in a path/walk
+        reference: String,
+        #[serde(default = "get_similarity_window")]
+        window: usize,
+        // distance between the starts of consecutive windows, by default the window size
+        #[serde(default)]
+        step: Option<usize>,
+        #[serde(default)]
+        count_type: CountType,
+    },
+    Chromosomal {
+        reference: String,
+        #[serde(default)]
+        window: Option<usize>,
+        #[serde(default)]
+        bigwig: Option<String>,
+        // BED file whose features on the reference sequence (e.g., genes or centromeres) are drawn
+        // as annotation track under the coverage
+        #[serde(default)]
+        annotation: Option<String>,
+        // stack a track per path/group, giving the fraction of the bp it covers, under the
+        // coverage
+        #[serde(default)]
+        by_group: bool,
+    },
+    Differential {
+        phenotype: String,
+        #[serde(default)]
+        reference: Option<String>,
+        #[serde(default)]
+        test: DifferentialTest,
+        #[serde(default)]
+        correction: PValueCorrection,
+    },
+    PhyloGrowth {
+        tree: String,
+        #[serde(default)]
+        count_type: CountType,
     },
     Custom {
         name: String,
@@ -200,11 +710,228 @@ pub enum AnalysisParameter {
     },
 }
 
+#[derive(Deserialize)]
+struct Config {
+    #[serde(default)]
+    variables: HashMap<String, Value>,
+    runs: Vec<Value>,
+}
+
+// strings that consist of a single variable are replaced by its value, which can be of any type
+// (e.g., a list of percentiles), other variables are replaced by their value as string
+fn substitute_variables(
+    value: &mut Value,
+    variables: &HashMap<String, Value>,
+) -> anyhow::Result<()> {
+    match value {
+        Value::String(text) => *value = substitute_in_string(text, variables)?,
+        Value::Sequence(values) => {
+            for value in values.iter_mut() {
+                substitute_variables(value, variables)?;
+            }
+        }
+        Value::Mapping(mapping) => {
+            for (_, value) in mapping.iter_mut() {
+                substitute_variables(value, variables)?;
+            }
+        }
+        Value::Tagged(tagged) => substitute_variables(&mut tagged.value, variables)?,
+        _ => {}
+    }
+    Ok(())
+}
+
+fn substitute_in_string(text: &str, variables: &HashMap<String, Value>) -> anyhow::Result<Value> {
+    let get_variable = |name: &str| {
+        variables
+            .get(name)
+            .ok_or_else(|| anyhow::anyhow!("variable ${{{}}} of YAML config is not defined", name))
+    };
+    let mut substituted = String::new();
+    let mut rest = text;
+    while let Some(start) = rest.find("${") {
+        let end = rest[start..]
+            .find('}')
+            .map(|end| start + end)
+            .ok_or_else(|| {
+                anyhow::anyhow!("unterminated variable in \"{}\" of YAML config", text)
+            })?;
+        let variable = get_variable(&rest[start + 2..end])?;
+        if start == 0 && end + 1 == text.len() {
+            return Ok(variable.clone());
+        }
+        substituted.push_str(&rest[..start]);
+        match variable {
+            Value::String(x) => substituted.push_str(x),
+            Value::Number(x) => substituted.push_str(&x.to_string()),
+            Value::Bool(x) => substituted.push_str(&x.to_string()),
+            _ => anyhow::bail!(
+                "variable ${{{}}} of YAML config is not a single value and cannot be part of \"{}\"",
+                &rest[start + 2..end],
+                text
+            ),
+        }
+        rest = &rest[end + 1..];
+    }
+    substituted.push_str(rest);
+    Ok(Value::String(substituted))
+}
+
+// file name without directory and extensions, e.g., chr1 for graphs/chr1.gfa.gz
+fn get_stem(file_name: &str) -> String {
+    let name = file_name.rsplit('/').next().unwrap_or(file_name);
+    name.split('.').next().unwrap_or(name).to_string()
+}
+
+// an analysis of a run together with its annotations in the YAML config, which are given as
+// additional keys of the analysis, e.g.,
+//   - !Hist
+//     count_type: Bp
+//     title: Coverage histogram
+//     description: Number of bp covered by a given number of haplotypes
+//     output: hist.tsv
+#[derive(Serialize, Debug, PartialEq, Eq, Hash, Clone, PartialOrd, Ord)]
+pub struct AnalysisEntry {
+    #[serde(flatten)]
+    parameter: AnalysisParameter,
+    #[serde(flatten)]
+    annotations: Annotations,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default, PartialEq, Eq, Hash, Clone, PartialOrd, Ord)]
+pub struct Annotations {
+    // title and description under which the report sections of the analysis are shown
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    // file to which the table (or, for .json files, the report sections) of the analysis is
+    // written
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output: Option<String>,
+}
+
+impl Annotations {
+    const KEYS: [&'static str; 3] = ["title", "description", "output"];
+
+    fn is_empty(&self) -> bool {
+        self == &Self::default()
+    }
+
+    fn take_from(mapping: &mut Mapping) -> Result<Self, serde_yaml::Error> {
+        let mut annotations = Mapping::new();
+        for key in Self::KEYS {
+            if let Some(value) = mapping.remove(key) {
+                annotations.insert(Value::String(key.to_string()), value);
+            }
+        }
+        serde_yaml::from_value(Value::Mapping(annotations))
+    }
+}
+
+impl From<AnalysisParameter> for AnalysisEntry {
+    fn from(parameter: AnalysisParameter) -> Self {
+        Self {
+            parameter,
+            annotations: Annotations::default(),
+        }
+    }
+}
+
+impl AnalysisEntry {
+    pub fn into_tasks(self) -> (Vec<Task>, HashSet<InputRequirement>) {
+        let Self {
+            parameter,
+            annotations,
+        } = self;
+        let (tasks, reqs) = parameter.into_tasks();
+        if annotations.is_empty() {
+            return (tasks, reqs);
+        }
+        let tasks = tasks
+            .into_iter()
+            .map(|task| match task {
+                Task::Analysis(analysis) => {
+                    Task::Analysis(Box::new(Annotated::new(analysis, annotations.clone())))
+                }
+                task => task,
+            })
+            .collect();
+        (tasks, reqs)
+    }
+
+    // analyses are either given as YAML tags (!Hist) or, e.g., in JSON queries, as maps with the
+    // name of the analysis as single key ({"Hist": {...}}); annotations are accepted as keys next
+    // to the parameters of the analysis in the former and next to its name in the latter case
+    fn from_value(value: Value) -> Result<Self, serde_yaml::Error> {
+        let (annotations, value) = match value {
+            Value::Tagged(mut tagged) => {
+                let annotations = match &mut tagged.value {
+                    Value::Mapping(mapping) => Annotations::take_from(mapping)?,
+                    _ => Annotations::default(),
+                };
+                (annotations, Value::Tagged(tagged))
+            }
+            Value::Mapping(mut mapping) => {
+                let annotations = Annotations::take_from(&mut mapping)?;
+                let analysis = match mapping.iter().next() {
+                    Some((Value::String(name), value)) if mapping.len() == 1 => {
+                        Some((name.clone(), value.clone()))
+                    }
+                    _ => None,
+                };
+                let value = match analysis {
+                    Some((name, value)) => Value::Tagged(Box::new(TaggedValue {
+                        tag: Tag::new(name),
+                        value,
+                    })),
+                    None => Value::Mapping(mapping),
+                };
+                (annotations, value)
+            }
+            value => (Annotations::default(), value),
+        };
+        let parameter = match value {
+            // analyses without parameters, e.g., !Duplicates, that are only given annotations
+            Value::Tagged(tagged) if matches!(&tagged.value, Value::Mapping(m) if m.is_empty()) => {
+                AnalysisParameter::deserialize(Value::Tagged(tagged.clone())).or_else(|_| {
+                    AnalysisParameter::deserialize(Value::Tagged(Box::new(TaggedValue {
+                        tag: tagged.tag,
+                        value: Value::Null,
+                    })))
+                })?
+            }
+            value => AnalysisParameter::deserialize(value)?,
+        };
+        Ok(Self {
+            parameter,
+            annotations,
+        })
+    }
+}
+
+impl<'de> Deserialize<'de> for AnalysisEntry {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let value = Value::deserialize(deserializer)?;
+        Self::from_value(value).map_err(D::Error::custom)
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Hash, Clone, PartialOrd, Ord)]
 pub enum Grouping {
     Sample,
     Haplotype,
     Custom(String),
+    // paths/walks are grouped by the captures of a regex on their names, which are put together by
+    // the label template (e.g., `{pop}_{hap}`), or else joined by #
+    Regex {
+        pattern: String,
+        #[serde(default)]
+        label: Option<String>,
+    },
 }
 
 impl Display for Grouping {
@@ -213,14 +940,163 @@ impl Display for Grouping {
             Self::Sample => write!(f, "Group By Sample"),
             Self::Haplotype => write!(f, "Group By Haplotype"),
             Self::Custom(file) => write!(f, "Group By {}", file),
+            Self::Regex { pattern, .. } => write!(f, "Group By {}", pattern),
+        }
+    }
+}
+
+/// Naming scheme of paths/walks from which sample and haplotype are derived when grouping by
+/// sample or haplotype, generalizing PanSN (sample#haplotype#contig) to other delimiters and field
+/// layouts, e.g., `sample.hap.contig`
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Hash, Clone, PartialOrd, Ord)]
+#[serde(try_from = "PanSNFields")]
+pub struct PanSN {
+    pub delimiter: char,
+    // number of fields, the last of which takes up the rest of the name
+    pub fields: usize,
+    // 1-based index of the field holding the haplotype; the sample is the first field
+    pub haplotype_field: usize,
+}
+
+// PanSN as given in the YAML config, which is checked like the specification on the command line
+#[derive(Deserialize)]
+struct PanSNFields {
+    delimiter: char,
+    #[serde(default = "get_pansn_fields")]
+    fields: usize,
+    #[serde(default = "get_pansn_haplotype_field")]
+    haplotype_field: usize,
+}
+
+impl TryFrom<PanSNFields> for PanSN {
+    type Error = String;
+
+    fn try_from(pansn: PanSNFields) -> Result<Self, Self::Error> {
+        Self {
+            delimiter: pansn.delimiter,
+            fields: pansn.fields,
+            haplotype_field: pansn.haplotype_field,
+        }
+        .check()
+        .map_err(|e| format!("PanSN {}", e))
+    }
+}
+
+impl PanSN {
+    // the haplotype field must be one of the fields after the sample
+    fn check(self) -> Result<Self, String> {
+        if self.fields < 2 || self.haplotype_field < 2 || self.haplotype_field > self.fields {
+            return Err(format!(
+                "must have at least 2 fields and a haplotype field between 2 and the number of fields, but has {} fields and haplotype field {}",
+                self.fields, self.haplotype_field
+            ));
+        }
+        Ok(self)
+    }
+
+    /// Sample, haplotype, and contig of a path/walk name, where haplotype and contig are given
+    /// if present; the contig is the last field unless that is the haplotype field
+    pub fn split_name<'a>(&self, name: &'a str) -> (&'a str, Option<&'a str>, Option<&'a str>) {
+        let fields: Vec<&str> = name.splitn(self.fields, self.delimiter).collect();
+        let contig = if fields.len() == self.fields && self.haplotype_field < self.fields {
+            fields.last().copied()
+        } else {
+            None
+        };
+        (
+            fields[0],
+            fields.get(self.haplotype_field - 1).copied(),
+            contig,
+        )
+    }
+
+    /// Name of the haplotype group, i.e., sample and haplotype joined by the delimiter
+    pub fn get_haplotype(&self, name: &str) -> String {
+        match self.split_name(name) {
+            (sample, Some(haplotype), _) => format!("{}{}{}", sample, self.delimiter, haplotype),
+            (sample, None, _) => sample.to_string(),
+        }
+    }
+}
+
+impl Default for PanSN {
+    fn default() -> Self {
+        Self {
+            delimiter: '#',
+            fields: get_pansn_fields(),
+            haplotype_field: get_pansn_haplotype_field(),
         }
     }
 }
 
+/// Parses `DELIMITER[,FIELDS[,HAPLOTYPE_FIELD]]`, e.g., `.,3,2`
+impl FromStr for PanSN {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut res = Self::default();
+        let mut chars = s.chars();
+        res.delimiter = chars
+            .next()
+            .ok_or_else(|| "PanSN specification must start with a delimiter".to_string())?;
+        let rest = chars.as_str();
+        if !rest.is_empty() {
+            let numbers = rest
+                .strip_prefix(',')
+                .ok_or_else(|| format!("PanSN specification `{}` must be of the form DELIMITER[,FIELDS[,HAPLOTYPE_FIELD]]", s))?
+                .split(',')
+                .map(|x| usize::from_str(x.trim()).map_err(|_| format!("`{}` of PanSN specification `{}` is not a number", x, s)))
+                .collect::<Result<Vec<usize>, String>>()?;
+            match numbers[..] {
+                [fields] => res.fields = fields,
+                [fields, haplotype_field] => {
+                    res.fields = fields;
+                    res.haplotype_field = haplotype_field;
+                }
+                _ => return Err(format!("PanSN specification `{}` has too many fields", s)),
+            }
+        }
+        res.check()
+            .map_err(|e| format!("PanSN specification `{}` {}", s, e))
+    }
+}
+
+fn get_pansn_fields() -> usize {
+    3
+}
+
+fn get_pansn_haplotype_field() -> usize {
+    2
+}
+
+fn get_permutations() -> usize {
+    100
+}
+
 fn get_radius() -> u32 {
     20
 }
 
+fn get_min_copies() -> u32 {
+    5
+}
+
+fn get_size_classes() -> String {
+    "1-49,50-999,1000-".to_string()
+}
+
+fn get_similarity_window() -> usize {
+    100000
+}
+
+fn get_kmer_size() -> usize {
+    31
+}
+
+fn get_kmer_scale() -> u64 {
+    1
+}
+
 impl AnalysisParameter {
     pub fn into_tasks(self) -> (Vec<Task>, HashSet<InputRequirement>) {
         match self {
@@ -233,9 +1109,39 @@ impl AnalysisParameter {
             n @ Self::NodeDistribution { .. } => {
                 get_analysis_task!(NodeDistribution, n)
             }
-            i @ Self::Info => {
+            i @ Self::Info { .. } => {
                 get_analysis_task!(Info, i)
             }
+            d @ Self::Duplicates => {
+                get_analysis_task!(Duplicates, d)
+            }
+            b @ Self::BaseComposition => {
+                get_analysis_task!(BaseComposition, b)
+            }
+            l @ Self::NodeLengths => {
+                get_analysis_task!(NodeLengths, l)
+            }
+            c @ Self::NodeCoverage => {
+                get_analysis_task!(NodeCoverage, c)
+            }
+            d @ Self::Depth => {
+                get_analysis_task!(Depth, d)
+            }
+            p @ Self::Paths => {
+                get_analysis_task!(Paths, p)
+            }
+            s @ Self::SizeClasses { .. } => {
+                get_analysis_task!(SizeClasses, s)
+            }
+            m @ Self::NodeMultiplicity { .. } => {
+                get_analysis_task!(NodeMultiplicity, m)
+            }
+            c @ Self::CoverageColors { .. } => {
+                get_analysis_task!(CoverageColors, c)
+            }
+            k @ Self::KmerComparison { .. } => {
+                get_analysis_task!(KmerComparison, k)
+            }
             ref o @ Self::OrderedGrowth { ref order, .. } => {
                 let mut tasks = vec![Task::OrderChange(order.clone())];
                 let (ordered_task, reqs) = get_analysis_task!(OrderedHistgrowth, o.clone());
@@ -248,6 +1154,18 @@ impl AnalysisParameter {
             s @ Self::Similarity { .. } => {
                 get_analysis_task!(Similarity, s)
             }
+            w @ Self::WindowSimilarity { .. } => {
+                get_analysis_task!(WindowSimilarity, w)
+            }
+            c @ Self::Chromosomal { .. } => {
+                get_analysis_task!(Chromosomal, c)
+            }
+            d @ Self::Differential { .. } => {
+                get_analysis_task!(Differential, d)
+            }
+            p @ Self::PhyloGrowth { .. } => {
+                get_analysis_task!(PhyloGrowth, p)
+            }
             t @ Self::Table { .. } => {
                 get_analysis_task!(Table, t)
             }
@@ -321,3 +1239,373 @@ impl fmt::Display for ClusterMethod {
         )
     }
 }
+
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    EnumString,
+    EnumVariantNames,
+    EnumIter,
+    Hash,
+    Eq,
+    PartialOrd,
+    Ord,
+    Serialize,
+    Deserialize,
+)]
+#[strum(serialize_all = "kebab-case")]
+pub enum AlphaMethod {
+    Ols,
+    Huber,
+    TheilSen,
+}
+
+impl Default for AlphaMethod {
+    fn default() -> Self {
+        Self::Huber
+    }
+}
+
+impl fmt::Display for AlphaMethod {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            formatter,
+            "{}",
+            match self {
+                Self::Ols => "ols",
+                Self::Huber => "huber",
+                Self::TheilSen => "theil-sen",
+            }
+        )
+    }
+}
+
+/// Treatment of the countables that are not covered by any path of the graph (in contrast to
+/// those not covered by the selected paths), which often indicate issues of the graph construction
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    EnumString,
+    EnumVariantNames,
+    EnumIter,
+    Hash,
+    Eq,
+    PartialOrd,
+    Ord,
+    Serialize,
+    Deserialize,
+)]
+#[strum(serialize_all = "kebab-case")]
+pub enum UncoveredMode {
+    // counted with coverage 0
+    Include,
+    // left out of coverage 0
+    Exclude,
+    // left out of coverage 0 and reported separately
+    Report,
+}
+
+impl Default for UncoveredMode {
+    fn default() -> Self {
+        Self::Include
+    }
+}
+
+impl fmt::Display for UncoveredMode {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            formatter,
+            "{}",
+            match self {
+                Self::Include => "include",
+                Self::Exclude => "exclude",
+                Self::Report => "report",
+            }
+        )
+    }
+}
+
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    EnumString,
+    EnumVariantNames,
+    EnumIter,
+    Hash,
+    Eq,
+    PartialOrd,
+    Ord,
+    Serialize,
+    Deserialize,
+)]
+#[strum(serialize_all = "kebab-case")]
+pub enum DifferentialTest {
+    Fisher,
+    ChiSquare,
+}
+
+impl Default for DifferentialTest {
+    fn default() -> Self {
+        Self::Fisher
+    }
+}
+
+impl fmt::Display for DifferentialTest {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            formatter,
+            "{}",
+            match self {
+                Self::Fisher => "fisher",
+                Self::ChiSquare => "chi-square",
+            }
+        )
+    }
+}
+
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    EnumString,
+    EnumVariantNames,
+    EnumIter,
+    Hash,
+    Eq,
+    PartialOrd,
+    Ord,
+    Serialize,
+    Deserialize,
+)]
+#[strum(serialize_all = "lowercase")]
+pub enum TableFormat {
+    Tsv,
+    Mtx,
+    Triplets,
+}
+
+impl Default for TableFormat {
+    fn default() -> Self {
+        Self::Tsv
+    }
+}
+
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    EnumString,
+    EnumVariantNames,
+    EnumIter,
+    Hash,
+    Eq,
+    PartialOrd,
+    Ord,
+    Serialize,
+    Deserialize,
+)]
+#[strum(serialize_all = "kebab-case")]
+pub enum PValueCorrection {
+    BenjaminiHochberg,
+    Bonferroni,
+    None,
+}
+
+impl Default for PValueCorrection {
+    fn default() -> Self {
+        Self::BenjaminiHochberg
+    }
+}
+
+impl fmt::Display for PValueCorrection {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            formatter,
+            "{}",
+            match self {
+                Self::BenjaminiHochberg => "benjamini-hochberg",
+                Self::Bonferroni => "bonferroni",
+                Self::None => "none",
+            }
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pansn_split_name() {
+        let pansn = PanSN::default();
+        assert_eq!(
+            pansn.split_name("HG00438#1#chr1#x"),
+            ("HG00438", Some("1"), Some("chr1#x"))
+        );
+        assert_eq!(pansn.split_name("HG00438#1"), ("HG00438", Some("1"), None));
+        assert_eq!(pansn.split_name("CHM13"), ("CHM13", None, None));
+        let pansn = PanSN::from_str(".,4,3").unwrap();
+        assert_eq!(
+            pansn.split_name("pop.HG00438.2.chr1"),
+            ("pop", Some("2"), Some("chr1"))
+        );
+    }
+
+    #[test]
+    fn test_pansn_from_yaml() {
+        let pansn: PanSN = serde_yaml::from_str("delimiter: '.'\nfields: 4").unwrap();
+        assert_eq!(pansn, PanSN::from_str(".,4").unwrap());
+        for yaml in [
+            "delimiter: '#'\nfields: 0",
+            "delimiter: '#'\nfields: 1",
+            "delimiter: '#'\nhaplotype_field: 0",
+            "delimiter: '#'\nhaplotype_field: 1",
+            "delimiter: '#'\nfields: 2\nhaplotype_field: 3",
+        ] {
+            let msg = serde_yaml::from_str::<PanSN>(yaml).unwrap_err().to_string();
+            assert!(msg.contains("PanSN must have at least 2 fields"), "{}", msg);
+        }
+        assert!(PanSN::from_str("#,0").is_err());
+    }
+
+    #[test]
+    fn test_analysis_entry_annotations() {
+        let yaml = "- !Hist
+  count_type: Bp
+  title: Coverage
+  description: Number of bp per coverage
+- !Duplicates
+  title: Duplicated nodes
+- Hist:
+    count_type: Node
+  title: Nodes
+  output: nodes.tsv
+";
+        let entries: Vec<AnalysisEntry> = serde_yaml::from_str(yaml).unwrap();
+        let hist = |count_type| AnalysisParameter::Hist {
+            count_type,
+            long: false,
+            cumulative: false,
+            normalized: false,
+            uncovered: UncoveredMode::Include,
+            list_uncovered: None,
+        };
+        assert_eq!(
+            entries,
+            vec![
+                AnalysisEntry {
+                    parameter: hist(CountType::Bp),
+                    annotations: Annotations {
+                        title: Some("Coverage".to_string()),
+                        description: Some("Number of bp per coverage".to_string()),
+                        output: None,
+                    },
+                },
+                AnalysisEntry {
+                    parameter: AnalysisParameter::Duplicates,
+                    annotations: Annotations {
+                        title: Some("Duplicated nodes".to_string()),
+                        ..Default::default()
+                    },
+                },
+                AnalysisEntry {
+                    parameter: hist(CountType::Node),
+                    annotations: Annotations {
+                        title: Some("Nodes".to_string()),
+                        output: Some("nodes.tsv".to_string()),
+                        ..Default::default()
+                    },
+                },
+            ]
+        );
+        let json = serde_json::to_string(&entries).unwrap();
+        let from_json: Vec<AnalysisEntry> = serde_json::from_str(&json).unwrap();
+        assert_eq!(entries, from_json);
+    }
+
+    #[test]
+    fn test_from_yaml_variables_and_globs() {
+        let yaml = "variables:
+  dir: tests/test_files
+  percentiles: [5, 95]
+runs:
+  - graph: ${dir}/t_*.gfa
+    name: ${graph_stem} (${dir})
+    analyses:
+      - !Growth
+        percentiles: ${percentiles}
+";
+        let runs = AnalysisRun::from_yaml(yaml).unwrap();
+        assert_eq!(
+            runs.iter()
+                .map(|run| (run.graph.as_str(), run.name.as_deref()))
+                .collect::<Vec<_>>(),
+            vec![
+                (
+                    "tests/test_files/t_groups.gfa",
+                    Some("t_groups (tests/test_files)")
+                ),
+                (
+                    "tests/test_files/t_walks.gfa",
+                    Some("t_walks (tests/test_files)")
+                ),
+            ]
+        );
+        assert!(matches!(
+            &runs[0].analyses[0].parameter,
+            AnalysisParameter::Growth { percentiles, .. }
+                if percentiles == &vec![Percentile(5.0), Percentile(95.0)]
+        ));
+        assert!(AnalysisRun::from_yaml("- graph: ${undefined}.gfa\n  analyses: []\n").is_err());
+    }
+
+    #[test]
+    fn test_convert_to_tasks_groups_states() {
+        let run = |graph: &str, subset: &str, analysis| {
+            AnalysisRun::new(
+                graph.to_string(),
+                Some("run".to_string()),
+                subset.to_string(),
+                String::new(),
+                None,
+                false,
+                vec![analysis],
+            )
+        };
+        let runs = vec![
+            run("a.gfa", "x.bed", AnalysisParameter::Duplicates),
+            run("b.gfa", "x.bed", AnalysisParameter::Duplicates),
+            run("a.gfa", "y.bed", AnalysisParameter::Duplicates),
+            run("a.gfa", "x.bed", AnalysisParameter::BaseComposition),
+        ];
+        let states: Vec<(String, String, usize)> =
+            AnalysisRun::convert_to_tasks(runs)
+                .iter()
+                .fold(Vec::new(), |mut states, task| {
+                    match task {
+                        Task::GraphStateChange { graph, subset, .. } => {
+                            states.push((graph.clone(), subset.clone(), 0))
+                        }
+                        Task::Analysis(_) => states.last_mut().unwrap().2 += 1,
+                        _ => {}
+                    }
+                    states
+                });
+        assert_eq!(
+            states,
+            vec![
+                ("a.gfa".to_string(), "x.bed".to_string(), 2),
+                ("a.gfa".to_string(), "y.bed".to_string(), 1),
+                ("b.gfa".to_string(), "x.bed".to_string(), 1),
+            ]
+        );
+    }
+}
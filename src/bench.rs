@@ -0,0 +1,161 @@
+/* standard use */
+use std::io::Write;
+
+/* private use */
+use crate::analysis_parameter::{AnalysisParameter, AnalysisRun, Task};
+use crate::graph_broker::GraphBroker;
+use crate::util::{format_rss, CountType, StageMeter, StageUsage};
+
+/// Times loading the graph, constructing its abaci, and each analysis for each number of threads,
+/// and writes the breakdown, along with the peak memory of each stage, as tab-separated table
+pub fn bench<W: Write>(
+    gfa_file: &str,
+    count_type: CountType,
+    thread_counts: &[usize],
+    out: &mut W,
+) -> anyhow::Result<()> {
+    let mut runtimes = Vec::new();
+    for &threads in thread_counts {
+        log::info!("benchmarking {} on {} threads", gfa_file, threads);
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(threads)
+            .build()?;
+        let stages = pool.install(|| run_stages(gfa_file, count_type))?;
+        runtimes.push((threads, stages));
+    }
+    write!(out, "{}", get_breakdown_table(&runtimes))?;
+    Ok(())
+}
+
+/// Numbers of threads that are compared if none are given: 1, 2, 4, ... up to the given maximum,
+/// which is always included
+pub fn get_default_thread_counts(max_threads: usize) -> Vec<usize> {
+    let mut res: Vec<usize> = std::iter::successors(Some(1), |t| Some(t * 2))
+        .take_while(|t| *t < max_threads)
+        .collect();
+    res.push(max_threads.max(1));
+    res
+}
+
+// analyses that are timed, with default parameters, as they need nothing but the graph
+fn get_analyses(count_type: CountType) -> anyhow::Result<Vec<AnalysisParameter>> {
+    Ok(serde_json::from_value(serde_json::json!([
+        { "Info": {} },
+        { "Hist": { "count_type": count_type } },
+        { "Growth": {} },
+        { "NodeDistribution": {} },
+        { "Similarity": { "count_type": count_type } },
+    ]))?)
+}
+
+fn run_stages(gfa_file: &str, count_type: CountType) -> anyhow::Result<Vec<StageUsage>> {
+    let run = AnalysisRun::new(
+        gfa_file.to_string(),
+        None,
+        String::new(),
+        String::new(),
+        None,
+        false,
+        get_analyses(count_type)?,
+    );
+    let mut gb = GraphBroker::new();
+    let mut stages = Vec::new();
+    for mut task in AnalysisRun::convert_to_tasks(vec![run]) {
+        match &mut task {
+            Task::GraphStateChange { .. } => {
+                let (state, reqs, nice) = task.get_graph_state().unwrap();
+                gb.change_graph_state(state, reqs, nice)?;
+                stages.extend(gb.take_stage_usage());
+            }
+            Task::Analysis(analysis) => {
+                let meter = StageMeter::start(&analysis.get_type());
+                analysis.generate_table(Some(&gb))?;
+                stages.push(meter.finish());
+            }
+            _ => unreachable!("benchmark consists of graph state changes and analyses only"),
+        }
+    }
+    Ok(stages)
+}
+
+// one row per number of threads and stage, including the total, along with the speedup relative
+// to the first number of threads and the peak memory
+fn get_breakdown_table(runtimes: &[(usize, Vec<StageUsage>)]) -> String {
+    let with_total = |stages: &[StageUsage]| {
+        let mut res = stages.to_vec();
+        res.push(StageUsage {
+            stage: "total".to_string(),
+            seconds: stages.iter().map(|s| s.seconds).sum(),
+            peak_rss: stages.iter().filter_map(|s| s.peak_rss).max(),
+        });
+        res
+    };
+    let baseline = runtimes
+        .first()
+        .map(|(_, stages)| with_total(stages))
+        .unwrap_or_default();
+    let mut res = "threads\tstage\tseconds\tspeedup\tpeak RSS (MB)\n".to_string();
+    for (threads, stages) in runtimes {
+        for usage in with_total(stages) {
+            let speedup = baseline
+                .iter()
+                .find(|s| s.stage == usage.stage)
+                .map(|base| base.seconds / usage.seconds)
+                .filter(|x| x.is_finite())
+                .map_or("NaN".to_string(), |x| format!("{:.2}", x));
+            res.push_str(&format!(
+                "{}\t{}\t{:.3}\t{}\t{}\n",
+                threads,
+                usage.stage,
+                usage.seconds,
+                speedup,
+                format_rss(usage.peak_rss)
+            ));
+        }
+    }
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_get_default_thread_counts() {
+        assert_eq!(get_default_thread_counts(1), vec![1]);
+        assert_eq!(get_default_thread_counts(8), vec![1, 2, 4, 8]);
+        assert_eq!(get_default_thread_counts(6), vec![1, 2, 4, 6]);
+    }
+
+    #[test]
+    fn test_get_breakdown_table() {
+        let usage = |stage: &str, seconds: f64, peak_rss: Option<u64>| StageUsage {
+            stage: stage.to_string(),
+            seconds,
+            peak_rss,
+        };
+        let runtimes = vec![
+            (
+                1,
+                vec![
+                    usage("parse graph", 2.0, Some(2 * 1024 * 1024)),
+                    usage("Hist", 1.0, Some(1024 * 1024)),
+                ],
+            ),
+            (
+                2,
+                vec![usage("parse graph", 1.0, None), usage("Hist", 0.0, None)],
+            ),
+        ];
+        assert_eq!(
+            get_breakdown_table(&runtimes),
+            "threads\tstage\tseconds\tspeedup\tpeak RSS (MB)\n\
+             1\tparse graph\t2.000\t1.00\t2.0\n\
+             1\tHist\t1.000\t1.00\t1.0\n\
+             1\ttotal\t3.000\t1.00\t2.0\n\
+             2\tparse graph\t1.000\t2.00\tNA\n\
+             2\tHist\t0.000\tNaN\tNA\n\
+             2\ttotal\t1.000\t3.00\tNA\n"
+        );
+    }
+}
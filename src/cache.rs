@@ -0,0 +1,224 @@
+/* standard use */
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{BufReader, BufWriter, Error};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::SystemTime;
+
+/* external use */
+use once_cell::sync::Lazy;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+/* private use */
+use crate::graph_broker::GraphState;
+use crate::io::file_size_and_checksum;
+
+// results are only cached if enabled by the command line interface, such that library users
+// (and tests) do not write to the user's cache directory
+static ENABLED: AtomicBool = AtomicBool::new(false);
+// checksums of the files seen so far, by name, size, and modification time
+type FileId = (String, u64, SystemTime);
+static CHECKSUMS: Lazy<Mutex<HashMap<FileId, u32>>> = Lazy::new(Default::default);
+// oldest entries are removed once the cache directory grows beyond this size (in bytes)
+const MAX_CACHE_SIZE: u64 = 1 << 30;
+
+/// Caches histograms and growth curves on disk from now on and reuses them in subsequent runs
+/// on the same graph state
+pub fn set_enabled(enabled: bool) {
+    ENABLED.store(enabled, Ordering::Relaxed);
+}
+
+fn get_cache_dir() -> Option<PathBuf> {
+    if !ENABLED.load(Ordering::Relaxed) {
+        return None;
+    }
+    let dir = match std::env::var_os("XDG_CACHE_HOME") {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => PathBuf::from(std::env::var_os("HOME")?).join(".cache"),
+    };
+    Some(dir.join("panacus"))
+}
+
+fn get_checksum(file_name: &str) -> Result<u32, Error> {
+    let metadata = fs::metadata(file_name)?;
+    let id = (file_name.to_string(), metadata.len(), metadata.modified()?);
+    if let Some(checksum) = CHECKSUMS.lock().unwrap().get(&id) {
+        return Ok(*checksum);
+    }
+    let (_, checksum) = file_size_and_checksum(file_name)?;
+    CHECKSUMS.lock().unwrap().insert(id, checksum);
+    Ok(checksum)
+}
+
+/// Key of a result computed on the graph state, made of the version of panacus, the state, and
+/// the checksums of the graph and of the files of the state (subset, exclude, and grouping),
+/// together with the given parts naming the result and its parameters; None if caching is
+/// disabled
+pub fn get_key(state: &GraphState, parts: &[&str]) -> Option<String> {
+    get_cache_dir()?;
+    let files = state.get_input_files();
+    // results of other versions may have been computed differently
+    let mut key = vec![format!("panacus {}", env!("CARGO_PKG_VERSION"))];
+    for file in files {
+        match get_checksum(&file) {
+            Ok(checksum) => key.push(format!("{}:{:08x}", file, checksum)),
+            Err(e) => {
+                log::warn!("unable to compute checksum of {}, not caching: {}", file, e);
+                return None;
+            }
+        }
+    }
+    // the name of the run and the coloring of the report do not change any result
+    key.push(format!(
+        "{:?}",
+        GraphState {
+            name: None,
+            metadata: None,
+            color_by: None,
+            ..state.clone()
+        }
+    ));
+    key.extend(parts.iter().map(|x| x.to_string()));
+    Some(key.join("\t"))
+}
+
+#[derive(Serialize, Deserialize)]
+struct Entry<T> {
+    key: String,
+    value: T,
+}
+
+// entries are named by a checksum of their key, which is also stored in them, such that
+// colliding checksums are detected; values are written as YAML, which (unlike JSON) keeps NaN
+fn get_entry_path(dir: &Path, kind: &str, key: &str) -> PathBuf {
+    let mut crc = flate2::Crc::new();
+    crc.update(key.as_bytes());
+    dir.join(format!("{}-{:08x}-{}.yaml", kind, crc.sum(), key.len()))
+}
+
+fn load<T: DeserializeOwned>(dir: &Path, kind: &str, key: &str) -> Option<T> {
+    let path = get_entry_path(dir, kind, key);
+    let reader = BufReader::new(File::open(&path).ok()?);
+    match serde_yaml::from_reader::<_, Entry<T>>(reader) {
+        Ok(entry) if entry.key == key => {
+            log::info!("reusing cached {} from {}", kind, path.display());
+            Some(entry.value)
+        }
+        Ok(_) => None,
+        Err(e) => {
+            log::warn!("ignoring invalid cache entry {}: {}", path.display(), e);
+            None
+        }
+    }
+}
+
+fn store<T: Serialize>(dir: &Path, kind: &str, key: &str, value: &T) -> anyhow::Result<()> {
+    fs::create_dir_all(dir)?;
+    let path = get_entry_path(dir, kind, key);
+    // written to a temporary file first, such that parallel runs never read partial entries
+    let tmp = path.with_extension(format!("{}.tmp", std::process::id()));
+    let writer = BufWriter::new(File::create(&tmp)?);
+    serde_yaml::to_writer(
+        writer,
+        &Entry {
+            key: key.to_string(),
+            value,
+        },
+    )?;
+    fs::rename(&tmp, &path)?;
+    prune(dir, &path, MAX_CACHE_SIZE)?;
+    Ok(())
+}
+
+// removes the oldest entries (other than the given one) until the entries of the directory take
+// at most max_size bytes
+fn prune(dir: &Path, keep: &Path, max_size: u64) -> Result<(), Error> {
+    let mut entries = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let metadata = entry.metadata()?;
+        if metadata.is_file() && entry.path().extension().map_or(false, |x| x == "yaml") {
+            entries.push((metadata.modified()?, entry.path(), metadata.len()));
+        }
+    }
+    let mut size: u64 = entries.iter().map(|x| x.2).sum();
+    entries.sort();
+    for (_, path, len) in entries {
+        if size <= max_size {
+            break;
+        }
+        if path != keep {
+            log::info!(
+                "removing cache entry {} to bound the cache size",
+                path.display()
+            );
+            fs::remove_file(&path)?;
+            size -= len;
+        }
+    }
+    Ok(())
+}
+
+/// The cached value of the given kind and key, if any; None if caching is disabled
+pub fn get<T: DeserializeOwned>(kind: &str, key: Option<String>) -> Option<T> {
+    load(&get_cache_dir()?, kind, &key?)
+}
+
+/// The cached value of the given kind and key, or, if there is none, the computed one, which is
+/// then cached; values are always computed if the key is None, i.e., caching is disabled
+pub fn get_or_compute<T, F>(kind: &str, key: Option<String>, compute: F) -> T
+where
+    T: Serialize + DeserializeOwned,
+    F: FnOnce() -> T,
+{
+    let (dir, key) = match (get_cache_dir(), key) {
+        (Some(dir), Some(key)) => (dir, key),
+        _ => return compute(),
+    };
+    if let Some(value) = load(&dir, kind, &key) {
+        return value;
+    }
+    let value = compute();
+    if let Err(e) = store(&dir, kind, &key, &value) {
+        log::warn!("unable to cache {} in {}: {}", kind, dir.display(), e);
+    }
+    value
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_store_and_load() {
+        let dir = std::env::temp_dir().join(format!("panacus-cache-test-{}", std::process::id()));
+        let value = vec![vec![f64::NAN, 1.0, 2.5], vec![]];
+        store(&dir, "growth", "a\tb", &value).unwrap();
+        let loaded: Vec<Vec<f64>> = load(&dir, "growth", "a\tb").unwrap();
+        assert!(loaded[0][0].is_nan());
+        assert_eq!(&loaded[0][1..], &value[0][1..]);
+        assert!(loaded[1].is_empty());
+        assert!(load::<Vec<Vec<f64>>>(&dir, "growth", "a\tc").is_none());
+        assert!(load::<Vec<Vec<f64>>>(&dir, "hist", "a\tb").is_none());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_prune() {
+        let dir = tempfile::tempdir().unwrap();
+        for key in ["a", "b", "c"] {
+            store(dir.path(), "hist", key, &vec![1.0; 100]).unwrap();
+        }
+        let keep = get_entry_path(dir.path(), "hist", "b");
+        let size = fs::metadata(&keep).unwrap().len();
+        prune(dir.path(), &keep, size).unwrap();
+        let remaining: Vec<PathBuf> = fs::read_dir(dir.path())
+            .unwrap()
+            .map(|x| x.unwrap().path())
+            .collect();
+        assert_eq!(remaining, vec![keep]);
+    }
+}
@@ -1,10 +1,52 @@
+pub mod base_composition;
+pub mod bench;
+pub mod chromosomal;
+pub mod coverage_colors;
+pub mod daemon;
+pub mod depth;
+pub mod differential;
+pub mod duplicates;
+pub mod groups;
 pub mod growth;
 pub mod hist;
 pub mod histgrowth;
+pub mod index;
 pub mod info;
+pub mod kmer_comparison;
+pub mod node_coverage;
 pub mod node_distribution;
+pub mod node_lengths;
+pub mod node_multiplicity;
 pub mod ordered_histgrowth;
+pub mod paths;
+pub mod phylo_growth;
 pub mod render;
 pub mod report;
+pub mod serve;
 pub mod similarity;
+pub mod size_classes;
 pub mod table;
+pub mod window_similarity;
+
+use clap::ArgMatches;
+
+use crate::analysis_parameter::Grouping;
+
+/// Grouping of the paths given by --groupby-sample, --groupby-haplotype, --groupby-regex (and
+/// --group-label), or --groupby, in this order of precedence, for subcommands that offer all of them
+pub fn get_grouping(args: &ArgMatches) -> Option<Grouping> {
+    if args.get_flag("groupby-sample") {
+        Some(Grouping::Sample)
+    } else if args.get_flag("groupby-haplotype") {
+        Some(Grouping::Haplotype)
+    } else if let Some(pattern) = args.get_one::<String>("groupby-regex") {
+        Some(Grouping::Regex {
+            pattern: pattern.clone(),
+            label: args.get_one::<String>("group-label").cloned(),
+        })
+    } else {
+        args.get_one::<String>("groupby")
+            .cloned()
+            .map(Grouping::Custom)
+    }
+}
@@ -0,0 +1,29 @@
+use clap::{arg, Arg, ArgMatches, Command};
+use strum::VariantNames;
+
+use crate::bench::get_default_thread_counts;
+use crate::clap_enum_variants_no_all;
+use crate::util::CountType;
+
+pub fn get_subcommand() -> Command {
+    Command::new("bench")
+        .about("Time parsing of the graph, construction of its abaci, and each analysis for varying numbers of threads and print the breakdown, to find the bottlenecks on a specific graph")
+        .args(&[
+            arg!(gfa_file: <GFA_FILE> "graph in GFA1 or GFA2 format, accepts also compressed (.gz) file; several files (e.g., one per chromosome) can be given as comma-separated list"),
+            Arg::new("count").help("Graph quantity to be counted").default_value("node").ignore_case(true).short('c').long("count").value_parser(clap_enum_variants_no_all!(CountType)),
+            arg!(-T --"thread-counts" <LIST> "Comma-separated numbers of threads that are compared (default: 1, 2, 4, ... up to the number of threads given by -t)"),
+        ])
+}
+
+pub fn get_thread_counts(args: &ArgMatches) -> anyhow::Result<Vec<usize>> {
+    match args.get_one::<String>("thread-counts") {
+        Some(list) => list
+            .split(',')
+            .map(|t| match t.trim().parse::<usize>() {
+                Ok(t) if t > 0 => Ok(t),
+                _ => Err(anyhow::anyhow!("{} is not a positive number of threads", t)),
+            })
+            .collect(),
+        None => Ok(get_default_thread_counts(rayon::current_num_threads())),
+    }
+}
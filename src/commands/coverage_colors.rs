@@ -0,0 +1,64 @@
+use clap::{arg, ArgMatches, Command};
+
+use crate::analysis_parameter::{AnalysisParameter, AnalysisRun, PanSN};
+use crate::commands::get_grouping;
+
+pub fn get_subcommand() -> Command {
+    Command::new("coverage-colors")
+        .about("Color nodes by their coverage class, writing a CSV file of node colors that can be loaded into Bandage")
+        .args(&[
+            arg!(gfa_file: <GFA_FILE> "graph in GFA1 or GFA2 format, accepts also compressed (.gz) file; several files (e.g., one per chromosome) can be given as comma-separated list"),
+            arg!(-s --subset <FILE> "Produce counts by subsetting the graph to a given list of paths (1-column list) or path coordinates (3- or 12-column BED file), or to a single path interval (e.g., HG00438#1#chr1:1,000,000-2,000,000); several files can be combined by union, intersection, and difference (e.g., a.txt+b.txt, a.bed&b.bed, a.txt-b.txt)"),
+            arg!(-e --exclude <FILE> "Exclude bp/node/edge in growth count that intersect with paths (1-column list) or path coordinates (3- or 12-column BED-file) provided by the given file; all intersecting bp/node/edge will be exluded also in other paths not part of the given list; several files can be combined as for --subset"),
+            arg!(--"exclude-reference" <NAME> "Exclude bp/node/edge in growth count that intersect with the paths of the reference (e.g., GRCh38 or CHM13), given by the name of its sample (as given by --pansn) or of its paths, or by a regex matching them entirely, in addition to those excluded by --exclude"),
+            arg!(-g --groupby <FILE> "Merge counts from paths by path-group mapping from given tab-separated two-column file"),
+            arg!(-H --"groupby-haplotype" "Merge counts from paths belonging to same haplotype"),
+            arg!(-S --"groupby-sample" "Merge counts from paths belonging to same sample"),
+            arg!(--"groupby-regex" <REGEX> "Merge counts from paths by the captures of the given regex on their names, e.g., '^(?P<pop>[A-Z]+)_[^#]+#(?P<hap>\\d+)' for population and haplotype; paths not matched by the regex are not merged"),
+            arg!(--"group-label" <TEMPLATE> "Label of the groups of --groupby-regex, referring to its named captures in braces, e.g., '{pop}_{hap}' (default: captures joined by #)").requires("groupby-regex"),
+            arg!(-p --palette <PALETTE> "Colors of the coverage classes, given as comma-separated list of hex colors (e.g., '#1b9e77,#d95f02,#7570b3') or as name of a colormap (viridis, magma, plasma, blues, reds, and the diverging rdbu, piyg, brbg); colors are interpolated if their number differs from that of the classes (default: viridis, or rdbu with --phenotype)"),
+            arg!(-b --breakpoints <LIST> "Comma-separated, increasing list of coverages at which a new coverage class starts (e.g., 2,10,50 for classes 1, 2-9, 10-49, and 50 and above; default: the coverages of shell and core, i.e., 2 and the number of paths/groups)").value_parser(clap::value_parser!(usize)).value_delimiter(',').conflicts_with("phenotype"),
+            arg!(--phenotype <FILE> "Color nodes by the difference in coverage between two phenotypes (e.g., wild minus cultivated), given by a tab-separated two-column file assigning paths/groups (after grouping) to one of the phenotypes; the difference is that of the fractions of paths/groups of either phenotype that contain the node, shown in a diverging palette"),
+            arg!(--pansn <SPEC> "Naming scheme of paths/walks used by --groupby-haplotype and --groupby-sample, given as delimiter, number of fields, and 1-based index of the haplotype field (e.g., '.,3,2' for sample.haplotype.contig); the sample is the first field (default: '#,3,2', i.e., PanSN)").value_parser(clap::value_parser!(PanSN)),
+        ])
+}
+
+pub fn get_instructions(args: &ArgMatches) -> Option<Result<Vec<AnalysisRun>, anyhow::Error>> {
+    if let Some(args) = args.subcommand_matches("coverage-colors") {
+        let graph = args
+            .get_one::<String>("gfa_file")
+            .expect("coverage-colors subcommand has gfa file")
+            .to_owned();
+        let subset = args
+            .get_one::<String>("subset")
+            .cloned()
+            .unwrap_or_default();
+        let exclude = args
+            .get_one::<String>("exclude")
+            .cloned()
+            .unwrap_or_default();
+        let grouping = get_grouping(args);
+        let parameters = vec![AnalysisRun::new(
+            graph,
+            None,
+            subset,
+            exclude,
+            grouping,
+            false,
+            vec![AnalysisParameter::CoverageColors {
+                palette: args.get_one::<String>("palette").cloned(),
+                breakpoints: args
+                    .get_many::<usize>("breakpoints")
+                    .map(|b| b.copied().collect())
+                    .unwrap_or_default(),
+                phenotype: args.get_one::<String>("phenotype").cloned(),
+            }],
+        )
+        .with_exclude_reference(args.get_one::<String>("exclude-reference").cloned())
+        .with_pansn(args.get_one::<PanSN>("pansn").cloned())];
+        log::info!("{parameters:?}");
+        Some(Ok(parameters))
+    } else {
+        None
+    }
+}
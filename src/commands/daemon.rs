@@ -0,0 +1,12 @@
+use clap::{arg, value_parser, Command};
+
+pub fn get_subcommand() -> Command {
+    Command::new("daemon")
+        .about("Load a graph once and answer repeated queries (hist, growth, similarity, ... of subsets or groupings) sent as JSON lines to a local port, without parsing the graph again")
+        .args(&[
+            arg!(gfa_file: <GFA_FILE> "graph in GFA1 or GFA2 format, accepts also compressed (.gz) file"),
+            arg!(-p --port <PORT> "Local port on which queries are answered")
+                .default_value("8001")
+                .value_parser(value_parser!(u16)),
+        ])
+}
@@ -0,0 +1,33 @@
+use clap::{arg, ArgMatches, Command};
+
+use crate::analysis_parameter::{AnalysisParameter, AnalysisRun};
+
+pub fn get_subcommand() -> Command {
+    Command::new("duplicates")
+        .about("Report nodes with identical or reverse-complementary identical sequences")
+        .args(&[
+            arg!(gfa_file: <GFA_FILE> "graph in GFA1 or GFA2 format, accepts also compressed (.gz) file; several files (e.g., one per chromosome) can be given as comma-separated list"),
+        ])
+}
+
+pub fn get_instructions(args: &ArgMatches) -> Option<Result<Vec<AnalysisRun>, anyhow::Error>> {
+    if let Some(args) = args.subcommand_matches("duplicates") {
+        let graph = args
+            .get_one::<String>("gfa_file")
+            .expect("duplicates subcommand has gfa file")
+            .to_owned();
+        let parameters = vec![AnalysisRun::new(
+            graph,
+            None,
+            String::new(),
+            String::new(),
+            None,
+            false,
+            vec![AnalysisParameter::Duplicates],
+        )];
+        log::info!("{parameters:?}");
+        Some(Ok(parameters))
+    } else {
+        None
+    }
+}
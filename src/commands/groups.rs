@@ -0,0 +1,18 @@
+use clap::{arg, Arg, Command};
+
+use crate::clap_enum_variants;
+use crate::lint::ListKind;
+
+pub fn get_subcommand() -> Command {
+    Command::new("groups")
+        .about("Check a grouping, order, or subset file against the paths/walks of the graph, report the entries that do not match and the paths/walks that are not listed, and write the file with canonical path names")
+        .args(&[
+            arg!(gfa_file: <GFA_FILE> "graph in GFA1 or GFA2 format, accepts also compressed (.gz) file; several files (e.g., one per chromosome) can be given as comma-separated list"),
+            arg!(file: <FILE> "Grouping (tab-separated two-column file), order, or subset file (1-column list or 3- or 12-column BED file) as given to --groupby, --order, or --subset"),
+            Arg::new("kind").help("Kind of the file (default: grouping if it has two columns, subset otherwise); paths/walks missing from an order are appended to the normalized file").ignore_case(true).short('k').long("kind").value_parser(clap_enum_variants!(ListKind)),
+            arg!(-g --groupby <FILE> "Group paths/walks by the given tab-separated two-column file, such that entries of order and subset files may also name groups"),
+            arg!(-H --"groupby-haplotype" "Group paths/walks by haplotype, such that entries of order and subset files may also name haplotypes"),
+            arg!(-S --"groupby-sample" "Group paths/walks by sample, such that entries of order and subset files may also name samples"),
+            arg!(-o --output <FILE> "Write the file with canonical path names, leaving out entries that are unknown, malformed, duplicate, or conflicting"),
+        ])
+}
@@ -1,22 +1,46 @@
+use crate::clap_enum_variants;
 use clap::{arg, Arg, ArgMatches, Command};
 
-use crate::analysis_parameter::{AnalysisParameter, AnalysisRun, Grouping};
+use crate::analysis_parameter::{AlphaMethod, AnalysisParameter, AnalysisRun, PanSN, Percentile};
+use crate::commands::get_grouping;
+use crate::util::CountType;
 
 pub fn get_subcommand() -> Command {
     Command::new("growth")
         .about("Calculate growth curve from coverage histogram")
-        .visible_alias("histgrowth")
         .args(&[
-            arg!(file: <FILE> "EITHER graph in GFA1 format, accepts also compressed (.gz) file OR a histogram as a .tsv"),
-            arg!(-s --subset <FILE> "Produce counts by subsetting the graph to a given list of paths (1-column list) or path coordinates (3- or 12-column BED file) (ONLY IN GFA MODE)"),
-            arg!(-e --exclude <FILE> "Exclude bp/node/edge in growth count that intersect with paths (1-column list) or path coordinates (3- or 12-column BED-file) provided by the given file; all intersecting bp/node/edge will be exluded also in other paths not part of the given list (ONLY IN GFA MODE)"),
+            arg!(file: <FILE> "EITHER graph in GFA1 or GFA2 format, accepts also compressed (.gz) file OR a histogram as a .tsv"),
+            arg!(-s --subset <FILE> "Produce counts by subsetting the graph to a given list of paths (1-column list) or path coordinates (3- or 12-column BED file), or to a single path interval (e.g., HG00438#1#chr1:1,000,000-2,000,000); several files can be combined by union, intersection, and difference (e.g., a.txt+b.txt, a.bed&b.bed, a.txt-b.txt) (ONLY IN GFA MODE)"),
+            arg!(-e --exclude <FILE> "Exclude bp/node/edge in growth count that intersect with paths (1-column list) or path coordinates (3- or 12-column BED-file) provided by the given file; all intersecting bp/node/edge will be exluded also in other paths not part of the given list; several files can be combined as for --subset (ONLY IN GFA MODE)"),
+            arg!(--"exclude-reference" <NAME> "Exclude bp/node/edge in growth count that intersect with the paths of the reference (e.g., GRCh38 or CHM13), given by the name of its sample (as given by --pansn) or of its paths, or by a regex matching them entirely, in addition to those excluded by --exclude (ONLY IN GFA MODE)"),
             arg!(-g --groupby <FILE> "Merge counts from paths by path-group mapping from given tab-separated two-column file (ONLY IN GFA MODE)"),
             arg!(-H --"groupby-haplotype" "Merge counts from paths belonging to same haplotype (ONLY IN GFA MODE)"),
             arg!(-S --"groupby-sample" "Merge counts from paths belonging to same sample (ONLY IN GFA MODE)"),
+            arg!(--"groupby-regex" <REGEX> "Merge counts from paths by the captures of the given regex on their names, e.g., '^(?P<pop>[A-Z]+)_[^#]+#(?P<hap>\\d+)' for population and haplotype; paths not matched by the regex are not merged (ONLY IN GFA MODE)"),
+            arg!(--"group-label" <TEMPLATE> "Label of the groups of --groupby-regex, referring to its named captures in braces, e.g., '{pop}_{hap}' (default: captures joined by #)").requires("groupby-regex"),
+            arg!(--pansn <SPEC> "Naming scheme of paths/walks used by --groupby-haplotype and --groupby-sample, given as delimiter, number of fields, and 1-based index of the haplotype field (e.g., '.,3,2' for sample.haplotype.contig); the sample is the first field (default: '#,3,2', i.e., PanSN) (ONLY IN GFA MODE)").value_parser(clap::value_parser!(PanSN)),
+            arg!(--"min-group-size" <N> "Merge groups with fewer than N paths into a single group named \"other\", e.g., to prevent tiny groups from distorting quorum-based growth (ONLY IN GFA MODE)").value_parser(clap::value_parser!(usize)),
+            arg!(--"drop-small-groups" "Drop groups with fewer than --min-group-size paths instead of merging them (ONLY IN GFA MODE)").requires("min-group-size"),
+            arg!(-D --dedup "Merge nodes with identical or reverse-complementary identical sequences such that their duplicates are counted only once (ONLY IN GFA MODE)"),
+            arg!(--"strand-dedup" "Merge duplicated nodes like --dedup and place the bp of nodes that are partially covered by --subset coordinates on the forward strand of their representative, such that bp covered on the reverse strand of a node or of its duplicates (or on either strand of a palindromic node) count as the same bp; without subset coordinates, nodes are covered as a whole and this is the same as --dedup; the policy is documented in the header of the output (ONLY IN GFA MODE)"),
+            arg!(--"oriented-edges" "Count edges traversed in opposite orientations (e.g., +a+b and -b-a) as different countables instead of as the same edge (ONLY IN GFA MODE)"),
+            arg!(-R --"count-repeats" "Count each traversal of a countable by a path, such that the k-th copy of a countable repeated within a path is counted as separate countable covered by all paths/groups traversing it at least k times (ONLY IN GFA MODE)"),
+            arg!(--"min-node-len" <N> "Only count nodes of at least N bp, e.g., 50 to restrict the count to nodes at the scale of structural variants; other nodes are left out like excluded ones (applies to node and bp counts)").value_parser(clap::value_parser!(u32)),
+            arg!(--"max-node-len" <N> "Only count nodes of at most N bp, e.g., 1 to restrict the count to nodes at the scale of SNPs; other nodes are left out like excluded ones (applies to node and bp counts)").value_parser(clap::value_parser!(u32)),
             arg!(-a --hist "Also include histogram in output (ONLY IN GFA MODE)"),
-            Arg::new("coverage").help("Ignore all countables with a coverage lower than the specified threshold. The coverage of a countable corresponds to the number of path/walk that contain it. Repeated appearances of a countable in the same path/walk are counted as one. You can pass a comma-separated list of coverage thresholds, each one will produce a separated growth curve (e.g., --coverage 2,3). Use --quorum to set a threshold in conjunction with each coverage (e.g., --quorum 0.5,0.9)")
+            arg!(--percentiles <LIST> "Also report the given comma-separated percentiles (in %) of the growth over random orders of the genomes next to the expected growth, which is the mean over all orders, e.g., 5,50,95 for the median and a 90% range (ONLY IN GFA MODE)").value_parser(clap::value_parser!(f64)).value_delimiter(','),
+            arg!(--permutations <N> "Number of random orders of the genomes from which the percentiles of the growth are estimated, and number of subsamples drawn at each rarefaction depth (ONLY IN GFA MODE)").value_parser(clap::value_parser!(usize)).default_value("100"),
+            arg!(--rarefaction <LIST> "Subsample exactly m genomes for each of the given comma-separated depths m (e.g., 5,10,20), --permutations times each, and report the empirical distribution of the pangenome size at each depth next to the expected growth (ONLY IN GFA MODE)").value_parser(clap::value_parser!(usize)).value_delimiter(','),
+            arg!(--stratify <FILE> "Also report the growth over --permutations random orders that draw equally from each population of the given tab-separated two-column file assigning groups to populations, such that the curve is not dominated by the most-sequenced population; the curve ends once the smallest population is exhausted (ONLY IN GFA MODE)"),
+            arg!(--normalize "Divide the growth by the average size of a genome in the respective countable (e.g., its number of bp), such that curves of organisms with very different genome sizes, or of bp and node counts, can be compared on one axis"),
+            arg!(--long "Write the table in long format, i.e., one row per count, coverage, quorum, and m, instead of one column per count, coverage, and quorum"),
+            arg!(-x --extrapolate <N> "Predict the growth up to N genomes from a fitted Heaps' law model (with 95% prediction intervals)").value_parser(clap::value_parser!(usize)),
+            Arg::new("alpha_method").help("Regression estimator used to fit Heaps' law (and its openness alpha) to the growth curve").default_value("huber").ignore_case(true).long("alpha-method").value_parser(clap_enum_variants!(AlphaMethod)),
+            arg!(--"alpha-from" <N> "First number of genomes whose growth is used to fit alpha (default: 2, or 1 for fewer than 5 genomes)").value_parser(clap::value_parser!(usize)),
+            arg!(--"alpha-to" <N> "Last number of genomes whose growth is used to fit alpha (default: all)").value_parser(clap::value_parser!(usize)),
+            Arg::new("coverage").help("Ignore all countables with a coverage lower than the specified threshold. The coverage of a countable corresponds to the number of path/walk that contain it. Repeated appearances of a countable in the same path/walk are counted as one. You can pass a comma-separated list of coverage thresholds, each one will produce a separated growth curve (e.g., --coverage 2,3). Use --quorum to set a threshold in conjunction with each coverage (e.g., --quorum 0.5,0.9). Besides plain values, thresholds can be given as expressions: ranges with both bounds included (e.g., 2..5), open-ended bounds (e.g., >=5, >4, <=5, <6), percentages (e.g., 50%), or the upper part of the coverage range (e.g., top10%)")
             .short('l').long("coverage").default_value("1"),
-            Arg::new("quorum").help("Unlike the --coverage parameter, which specifies a minimum constant number of paths for all growth point m (1 <= m <= num_paths), --quorum adjust the threshold based on m. At each m, a countable is counted in the average growth if the countable is contained in at least floor(m*quorum) paths. Example: A quorum of 0.9 requires a countable to be in 90% of paths for each subset size m. At m=10, it must appear in at least 9 paths. At m=100, it must appear in at least 90 paths. A quorum of 1 (100%) requires presence in all paths of the subset, corresponding to the core. Default: 0, a countable counts if it is present in any path at each growth point. Specify multiple quorum values with a comma-separated list (e.g., --quorum 0.5,0.9). Use --coverage to set static path thresholds in conjunction with variable quorum percentages (e.g., --coverage 5,10).")
+            Arg::new("quorum").help("Unlike the --coverage parameter, which specifies a minimum constant number of paths for all growth point m (1 <= m <= num_paths), --quorum adjust the threshold based on m. At each m, a countable is counted in the average growth if the countable is contained in at least floor(m*quorum) paths. Example: A quorum of 0.9 requires a countable to be in 90% of paths for each subset size m. At m=10, it must appear in at least 9 paths. At m=100, it must appear in at least 90 paths. A quorum of 1 (100%) requires presence in all paths of the subset, corresponding to the core. Default: 0, a countable counts if it is present in any path at each growth point. Specify multiple quorum values with a comma-separated list (e.g., --quorum 0.5,0.9). Use --coverage to set static path thresholds in conjunction with variable quorum percentages (e.g., --coverage 5,10). Like coverage thresholds, quorums can be given as ranges (e.g., 0.2..0.5), bounds (e.g., <=0.5), percentages (e.g., 20%..50%), or the top part (e.g., top10%).")
             .short('q').long("quorum").default_value("0"),
         ])
 }
@@ -27,6 +51,13 @@ pub fn get_instructions(args: &ArgMatches) -> Option<Result<Vec<AnalysisRun>, an
         let coverage = args.get_one::<String>("coverage").cloned();
         let quorum = args.get_one::<String>("quorum").cloned();
         let add_hist = args.get_flag("hist");
+        let extrapolate = args.get_one::<usize>("extrapolate").copied();
+        let alpha_method = args
+            .get_one::<AlphaMethod>("alpha_method")
+            .expect("growth subcommand has alpha method")
+            .to_owned();
+        let alpha_from = args.get_one::<usize>("alpha-from").copied();
+        let alpha_to = args.get_one::<usize>("alpha-to").copied();
         let graph = args
             .get_one::<String>("file")
             .expect("growth subcommand has gfa file")
@@ -39,14 +70,7 @@ pub fn get_instructions(args: &ArgMatches) -> Option<Result<Vec<AnalysisRun>, an
             .get_one::<String>("exclude")
             .cloned()
             .unwrap_or_default();
-        let grouping = args.get_one::<String>("groupby").cloned();
-        let grouping = if args.get_flag("groupby-sample") {
-            Some(Grouping::Sample)
-        } else if args.get_flag("groupby-haplotype") {
-            Some(Grouping::Haplotype)
-        } else {
-            grouping.map(|g| Grouping::Custom(g))
-        };
+        let grouping = get_grouping(args);
         Some(Ok(vec![AnalysisRun::new(
             graph,
             None,
@@ -58,7 +82,41 @@ pub fn get_instructions(args: &ArgMatches) -> Option<Result<Vec<AnalysisRun>, an
                 coverage,
                 quorum,
                 add_hist,
+                extrapolate,
+                alpha_method,
+                alpha_from,
+                alpha_to,
+                long: args.get_flag("long"),
+                percentiles: args
+                    .get_many::<f64>("percentiles")
+                    .map(|p| p.copied().map(Percentile).collect())
+                    .unwrap_or_default(),
+                permutations: *args
+                    .get_one::<usize>("permutations")
+                    .expect("growth subcommand has number of permutations"),
+                rarefaction: args
+                    .get_many::<usize>("rarefaction")
+                    .map(|m| m.copied().collect())
+                    .unwrap_or_default(),
+                stratify: args.get_one::<String>("stratify").cloned(),
+                normalize: args.get_flag("normalize"),
+                count_type: CountType::Node,
             }],
+        )
+        .with_exclude_reference(args.get_one::<String>("exclude-reference").cloned())
+        .with_pansn(args.get_one::<PanSN>("pansn").cloned())
+        .with_min_group_size(
+            args.get_one::<usize>("min-group-size")
+                .copied()
+                .unwrap_or(0),
+            args.get_flag("drop-small-groups"),
+        )
+        .with_dedup(args.get_flag("dedup"), args.get_flag("strand-dedup"))
+        .with_oriented_edges(args.get_flag("oriented-edges"))
+        .with_count_repeats(args.get_flag("count-repeats"))
+        .with_node_len_range(
+            args.get_one::<u32>("min-node-len").copied(),
+            args.get_one::<u32>("max-node-len").copied(),
         )]))
     } else {
         None
@@ -1,19 +1,37 @@
 use crate::clap_enum_variants;
 use clap::{arg, Arg, ArgMatches, Command};
 
-use crate::analysis_parameter::{AnalysisParameter, AnalysisRun, Grouping};
+use crate::analysis_parameter::{AnalysisParameter, AnalysisRun, PanSN, UncoveredMode};
+use crate::commands::get_grouping;
 use crate::util::CountType;
 
 pub fn get_subcommand() -> Command {
     Command::new("hist")
         .about("Calculate coverage histogram")
         .args(&[
-            arg!(gfa_file: <GFA_FILE> "graph in GFA1 format, accepts also compressed (.gz) file"),
-            arg!(-s --subset <FILE> "Produce counts by subsetting the graph to a given list of paths (1-column list) or path coordinates (3- or 12-column BED file)"),
-            arg!(-e --exclude <FILE> "Exclude bp/node/edge in growth count that intersect with paths (1-column list) or path coordinates (3- or 12-column BED-file) provided by the given file; all intersecting bp/node/edge will be exluded also in other paths not part of the given list"),
+            arg!(gfa_file: <GFA_FILE> "graph in GFA1 or GFA2 format, accepts also compressed (.gz) file; several files (e.g., one per chromosome) can be given as comma-separated list"),
+            arg!(-s --subset <FILE> "Produce counts by subsetting the graph to a given list of paths (1-column list) or path coordinates (3- or 12-column BED file), or to a single path interval (e.g., HG00438#1#chr1:1,000,000-2,000,000); several files can be combined by union, intersection, and difference (e.g., a.txt+b.txt, a.bed&b.bed, a.txt-b.txt)"),
+            arg!(-e --exclude <FILE> "Exclude bp/node/edge in growth count that intersect with paths (1-column list) or path coordinates (3- or 12-column BED-file) provided by the given file; all intersecting bp/node/edge will be exluded also in other paths not part of the given list; several files can be combined as for --subset"),
+            arg!(--"exclude-reference" <NAME> "Exclude bp/node/edge in growth count that intersect with the paths of the reference (e.g., GRCh38 or CHM13), given by the name of its sample (as given by --pansn) or of its paths, or by a regex matching them entirely, in addition to those excluded by --exclude"),
             arg!(-g --groupby <FILE> "Merge counts from paths by path-group mapping from given tab-separated two-column file"),
             arg!(-H --"groupby-haplotype" "Merge counts from paths belonging to same haplotype"),
             arg!(-S --"groupby-sample" "Merge counts from paths belonging to same sample"),
+            arg!(--"groupby-regex" <REGEX> "Merge counts from paths by the captures of the given regex on their names, e.g., '^(?P<pop>[A-Z]+)_[^#]+#(?P<hap>\\d+)' for population and haplotype; paths not matched by the regex are not merged"),
+            arg!(--"group-label" <TEMPLATE> "Label of the groups of --groupby-regex, referring to its named captures in braces, e.g., '{pop}_{hap}' (default: captures joined by #)").requires("groupby-regex"),
+            arg!(--pansn <SPEC> "Naming scheme of paths/walks used by --groupby-haplotype and --groupby-sample, given as delimiter, number of fields, and 1-based index of the haplotype field (e.g., '.,3,2' for sample.haplotype.contig); the sample is the first field (default: '#,3,2', i.e., PanSN)").value_parser(clap::value_parser!(PanSN)),
+            arg!(--"min-group-size" <N> "Merge groups with fewer than N paths into a single group named \"other\", e.g., to prevent tiny groups from distorting quorum-based growth").value_parser(clap::value_parser!(usize)),
+            arg!(--"drop-small-groups" "Drop groups with fewer than --min-group-size paths instead of merging them").requires("min-group-size"),
+            arg!(-D --dedup "Merge nodes with identical or reverse-complementary identical sequences such that their duplicates are counted only once"),
+            arg!(--"strand-dedup" "Merge duplicated nodes like --dedup and place the bp of nodes that are partially covered by --subset coordinates on the forward strand of their representative, such that bp covered on the reverse strand of a node or of its duplicates (or on either strand of a palindromic node) count as the same bp; without subset coordinates, nodes are covered as a whole and this is the same as --dedup; the policy is documented in the header of the output"),
+            arg!(--"oriented-edges" "Count edges traversed in opposite orientations (e.g., +a+b and -b-a) as different countables instead of as the same edge"),
+            arg!(-R --"count-repeats" "Count each traversal of a countable by a path, such that the k-th copy of a countable repeated within a path is counted as separate countable covered by all paths/groups traversing it at least k times"),
+            arg!(--"min-node-len" <N> "Only count nodes of at least N bp, e.g., 50 to restrict the count to nodes at the scale of structural variants; other nodes are left out like excluded ones (applies to node and bp counts)").value_parser(clap::value_parser!(u32)),
+            arg!(--"max-node-len" <N> "Only count nodes of at most N bp, e.g., 1 to restrict the count to nodes at the scale of SNPs; other nodes are left out like excluded ones (applies to node and bp counts)").value_parser(clap::value_parser!(u32)),
+            arg!(--cumulative "Also report the cumulative histogram, i.e., the number of countables with a coverage of at most i"),
+            arg!(--normalized "Also report the histogram as fractions of the total number of countables (combined with --cumulative, the cumulative fractions)"),
+            arg!(--long "Write the table in long format, i.e., one row per count and coverage, instead of one column per count"),
+            Arg::new("uncovered").help("Treatment of countables not covered by any path (of the subset, if given) other than excluded ones, which often indicate issues of the graph construction: count them with coverage 0 (include), leave them out (exclude), or leave them out and report their number separately (report); only the histogram is affected, as growth curves never count them").default_value("include").ignore_case(true).long("uncovered").value_parser(clap_enum_variants!(UncoveredMode)),
+            arg!(--"list-uncovered" <FILE> "Write the names and lengths of the nodes not covered by any path (of the subset, if given) to the given tab-separated file for inspection; requires counting nodes or bp"),
             Arg::new("count").help("Graph quantity to be counted").default_value("node").ignore_case(true).short('c').long("count").value_parser(clap_enum_variants!(CountType)),
         ])
 }
@@ -36,14 +54,7 @@ pub fn get_instructions(args: &ArgMatches) -> Option<Result<Vec<AnalysisRun>, an
             .get_one::<String>("exclude")
             .cloned()
             .unwrap_or_default();
-        let grouping = args.get_one::<String>("groupby").cloned();
-        let grouping = if args.get_flag("groupby-sample") {
-            Some(Grouping::Sample)
-        } else if args.get_flag("groupby-haplotype") {
-            Some(Grouping::Haplotype)
-        } else {
-            grouping.map(|g| Grouping::Custom(g))
-        };
+        let grouping = get_grouping(args);
         Some(Ok(vec![AnalysisRun::new(
             graph,
             None,
@@ -51,7 +62,31 @@ pub fn get_instructions(args: &ArgMatches) -> Option<Result<Vec<AnalysisRun>, an
             exclude,
             grouping,
             false,
-            vec![AnalysisParameter::Hist { count_type: count }],
+            vec![AnalysisParameter::Hist {
+                count_type: count,
+                long: args.get_flag("long"),
+                cumulative: args.get_flag("cumulative"),
+                normalized: args.get_flag("normalized"),
+                uncovered: *args
+                    .get_one::<UncoveredMode>("uncovered")
+                    .expect("hist subcommand has uncovered mode"),
+                list_uncovered: args.get_one::<String>("list-uncovered").cloned(),
+            }],
+        )
+        .with_exclude_reference(args.get_one::<String>("exclude-reference").cloned())
+        .with_pansn(args.get_one::<PanSN>("pansn").cloned())
+        .with_min_group_size(
+            args.get_one::<usize>("min-group-size")
+                .copied()
+                .unwrap_or(0),
+            args.get_flag("drop-small-groups"),
+        )
+        .with_dedup(args.get_flag("dedup"), args.get_flag("strand-dedup"))
+        .with_oriented_edges(args.get_flag("oriented-edges"))
+        .with_count_repeats(args.get_flag("count-repeats"))
+        .with_node_len_range(
+            args.get_one::<u32>("min-node-len").copied(),
+            args.get_one::<u32>("max-node-len").copied(),
         )]))
     } else {
         None
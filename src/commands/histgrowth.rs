@@ -1,65 +1,115 @@
 use crate::clap_enum_variants;
 use clap::{arg, Arg, ArgMatches, Command};
 
-use crate::analysis_parameter::AnalysisParameter;
+use crate::analysis_parameter::{
+    AlphaMethod, AnalysisParameter, AnalysisRun, PanSN, UncoveredMode,
+};
+use crate::commands::get_grouping;
 use crate::util::CountType;
 
-#[allow(dead_code)]
 pub fn get_subcommand() -> Command {
     Command::new("histgrowth")
-        .about("Run hist and growth. Return the growth curve")
+        .about("Run hist and growth in a single pass over the graph. Return the growth curve")
         .args(&[
-            arg!(gfa_file: <GFA_FILE> "graph in GFA1 format, accepts also compressed (.gz) file"),
+            arg!(gfa_file: <GFA_FILE> "graph in GFA1 or GFA2 format, accepts also compressed (.gz) file; several files (e.g., one per chromosome) can be given as comma-separated list"),
             arg!(-a --hist "Also include histogram in output"),
-            arg!(-s --subset <FILE> "Produce counts by subsetting the graph to a given list of paths (1-column list) or path coordinates (3- or 12-column BED file)"),
-            arg!(-e --exclude <FILE> "Exclude bp/node/edge in growth count that intersect with paths (1-column list) or path coordinates (3- or 12-column BED-file) provided by the given file; all intersecting bp/node/edge will be exluded also in other paths not part of the given list"),
+            arg!(-s --subset <FILE> "Produce counts by subsetting the graph to a given list of paths (1-column list) or path coordinates (3- or 12-column BED file), or to a single path interval (e.g., HG00438#1#chr1:1,000,000-2,000,000); several files can be combined by union, intersection, and difference (e.g., a.txt+b.txt, a.bed&b.bed, a.txt-b.txt)"),
+            arg!(-e --exclude <FILE> "Exclude bp/node/edge in growth count that intersect with paths (1-column list) or path coordinates (3- or 12-column BED-file) provided by the given file; all intersecting bp/node/edge will be exluded also in other paths not part of the given list; several files can be combined as for --subset"),
+            arg!(--"exclude-reference" <NAME> "Exclude bp/node/edge in growth count that intersect with the paths of the reference (e.g., GRCh38 or CHM13), given by the name of its sample (as given by --pansn) or of its paths, or by a regex matching them entirely, in addition to those excluded by --exclude"),
             arg!(-g --groupby <FILE> "Merge counts from paths by path-group mapping from given tab-separated two-column file"),
             arg!(-H --"groupby-haplotype" "Merge counts from paths belonging to same haplotype"),
             arg!(-S --"groupby-sample" "Merge counts from paths belonging to same sample"),
+            arg!(--"groupby-regex" <REGEX> "Merge counts from paths by the captures of the given regex on their names, e.g., '^(?P<pop>[A-Z]+)_[^#]+#(?P<hap>\\d+)' for population and haplotype; paths not matched by the regex are not merged"),
+            arg!(--"group-label" <TEMPLATE> "Label of the groups of --groupby-regex, referring to its named captures in braces, e.g., '{pop}_{hap}' (default: captures joined by #)").requires("groupby-regex"),
+            arg!(--pansn <SPEC> "Naming scheme of paths/walks used by --groupby-haplotype and --groupby-sample, given as delimiter, number of fields, and 1-based index of the haplotype field (e.g., '.,3,2' for sample.haplotype.contig); the sample is the first field (default: '#,3,2', i.e., PanSN)").value_parser(clap::value_parser!(PanSN)),
+            arg!(--"min-group-size" <N> "Merge groups with fewer than N paths into a single group named \"other\", e.g., to prevent tiny groups from distorting quorum-based growth").value_parser(clap::value_parser!(usize)),
+            arg!(--"drop-small-groups" "Drop groups with fewer than --min-group-size paths instead of merging them").requires("min-group-size"),
+            arg!(-D --dedup "Merge nodes with identical or reverse-complementary identical sequences such that their duplicates are counted only once"),
+            arg!(--"strand-dedup" "Merge duplicated nodes like --dedup and place the bp of nodes that are partially covered by --subset coordinates on the forward strand of their representative, such that bp covered on the reverse strand of a node or of its duplicates (or on either strand of a palindromic node) count as the same bp; without subset coordinates, nodes are covered as a whole and this is the same as --dedup; the policy is documented in the header of the output"),
+            arg!(--"oriented-edges" "Count edges traversed in opposite orientations (e.g., +a+b and -b-a) as different countables instead of as the same edge"),
+            arg!(-R --"count-repeats" "Count each traversal of a countable by a path, such that the k-th copy of a countable repeated within a path is counted as separate countable covered by all paths/groups traversing it at least k times"),
+            arg!(--"min-node-len" <N> "Only count nodes of at least N bp, e.g., 50 to restrict the count to nodes at the scale of structural variants; other nodes are left out like excluded ones (applies to node and bp counts)").value_parser(clap::value_parser!(u32)),
+            arg!(--"max-node-len" <N> "Only count nodes of at most N bp, e.g., 1 to restrict the count to nodes at the scale of SNPs; other nodes are left out like excluded ones (applies to node and bp counts)").value_parser(clap::value_parser!(u32)),
+            Arg::new("uncovered").help("Treatment of countables not covered by any path (of the subset, if given) other than excluded ones, which often indicate issues of the graph construction: count them with coverage 0 (include), leave them out (exclude), or leave them out and report their number separately (report); only the histogram is affected, as growth curves never count them").default_value("include").ignore_case(true).long("uncovered").value_parser(clap_enum_variants!(UncoveredMode)),
+            arg!(--"list-uncovered" <FILE> "Write the names and lengths of the nodes not covered by any path (of the subset, if given) to the given tab-separated file for inspection; requires counting nodes or bp"),
             Arg::new("count").help("Graph quantity to be counted").default_value("node").ignore_case(true).short('c').long("count").value_parser(clap_enum_variants!(CountType)),
-            Arg::new("coverage").help("Ignore all countables with a coverage lower than the specified threshold. The coverage of a countable corresponds to the number of path/walk that contain it. Repeated appearances of a countable in the same path/walk are counted as one. You can pass a comma-separated list of coverage thresholds, each one will produce a separated growth curve (e.g., --coverage 2,3). Use --quorum to set a threshold in conjunction with each coverage (e.g., --quorum 0.5,0.9)")
+            Arg::new("coverage").help("Ignore all countables with a coverage lower than the specified threshold. The coverage of a countable corresponds to the number of path/walk that contain it. Repeated appearances of a countable in the same path/walk are counted as one. You can pass a comma-separated list of coverage thresholds, each one will produce a separated growth curve (e.g., --coverage 2,3). Use --quorum to set a threshold in conjunction with each coverage (e.g., --quorum 0.5,0.9). Besides plain values, thresholds can be given as expressions: ranges with both bounds included (e.g., 2..5), open-ended bounds (e.g., >=5, >4, <=5, <6), percentages (e.g., 50%), or the upper part of the coverage range (e.g., top10%)")
             .short('l').long("coverage").default_value("1"),
-            Arg::new("quorum").help("Unlike the --coverage parameter, which specifies a minimum constant number of paths for all growth point m (1 <= m <= num_paths), --quorum adjust the threshold based on m. At each m, a countable is counted in the average growth if the countable is contained in at least floor(m*quorum) paths. Example: A quorum of 0.9 requires a countable to be in 90% of paths for each subset size m. At m=10, it must appear in at least 9 paths. At m=100, it must appear in at least 90 paths. A quorum of 1 (100%) requires presence in all paths of the subset, corresponding to the core. Default: 0, a countable counts if it is present in any path at each growth point. Specify multiple quorum values with a comma-separated list (e.g., --quorum 0.5,0.9). Use --coverage to set static path thresholds in conjunction with variable quorum percentages (e.g., --coverage 5,10).")
+            Arg::new("quorum").help("Unlike the --coverage parameter, which specifies a minimum constant number of paths for all growth point m (1 <= m <= num_paths), --quorum adjust the threshold based on m. At each m, a countable is counted in the average growth if the countable is contained in at least floor(m*quorum) paths. Example: A quorum of 0.9 requires a countable to be in 90% of paths for each subset size m. At m=10, it must appear in at least 9 paths. At m=100, it must appear in at least 90 paths. A quorum of 1 (100%) requires presence in all paths of the subset, corresponding to the core. Default: 0, a countable counts if it is present in any path at each growth point. Specify multiple quorum values with a comma-separated list (e.g., --quorum 0.5,0.9). Use --coverage to set static path thresholds in conjunction with variable quorum percentages (e.g., --coverage 5,10). Like coverage thresholds, quorums can be given as ranges (e.g., 0.2..0.5), bounds (e.g., <=0.5), percentages (e.g., 20%..50%), or the top part (e.g., top10%).")
             .short('q').long("quorum").default_value("0"),
         ])
 }
 
-#[allow(dead_code)]
-pub fn get_instructions(
-    args: &ArgMatches,
-) -> Option<Result<Vec<AnalysisParameter>, anyhow::Error>> {
+pub fn get_instructions(args: &ArgMatches) -> Option<Result<Vec<AnalysisRun>, anyhow::Error>> {
     if let Some(args) = args.subcommand_matches("histgrowth") {
-        // let graph = args
-        //     .get_one::<String>("gfa_file")
-        //     .expect("hist subcommand has gfa file")
-        //     .to_owned();
+        let graph = args
+            .get_one::<String>("gfa_file")
+            .expect("histgrowth subcommand has gfa file")
+            .to_owned();
         let count = args
             .get_one::<CountType>("count")
-            .expect("hist subcommand has count type")
+            .expect("histgrowth subcommand has count type")
             .to_owned();
         let add_hist = args.get_flag("hist");
-        // let subset = args.get_one::<String>("subset").cloned();
-        // let exclude = args.get_one::<String>("exclude").cloned();
-        // let grouping = args.get_one::<String>("groupby").cloned();
-        // let grouping = if args.get_flag("groupby-sample") {
-        //     Some(Grouping::Sample)
-        // } else if args.get_flag("groupby-haplotype") {
-        //     Some(Grouping::Haplotype)
-        // } else {
-        //     grouping.map(|g| Grouping::Custom(g))
-        // };
+        let subset = args
+            .get_one::<String>("subset")
+            .cloned()
+            .unwrap_or_default();
+        let exclude = args
+            .get_one::<String>("exclude")
+            .cloned()
+            .unwrap_or_default();
+        let grouping = get_grouping(args);
         let coverage = args.get_one::<String>("coverage").cloned();
         let quorum = args.get_one::<String>("quorum").cloned();
+        // both analyses share the same graph state and hence the same pass over the graph; as
+        // last analysis, growth determines the table that is written
         let parameters = vec![
-            AnalysisParameter::Hist { count_type: count },
+            AnalysisParameter::Hist {
+                count_type: count,
+                long: false,
+                cumulative: false,
+                normalized: false,
+                uncovered: *args
+                    .get_one::<UncoveredMode>("uncovered")
+                    .expect("histgrowth subcommand has uncovered mode"),
+                list_uncovered: args.get_one::<String>("list-uncovered").cloned(),
+            },
             AnalysisParameter::Growth {
                 coverage,
                 quorum,
                 add_hist,
+                extrapolate: None,
+                alpha_method: AlphaMethod::default(),
+                alpha_from: None,
+                alpha_to: None,
+                long: false,
+                percentiles: Vec::new(),
+                permutations: 0,
+                rarefaction: Vec::new(),
+                stratify: None,
+                normalize: false,
+                count_type: count,
             },
         ];
-        log::info!("{parameters:?}");
-        Some(Ok(parameters))
+        Some(Ok(vec![AnalysisRun::new(
+            graph, None, subset, exclude, grouping, false, parameters,
+        )
+        .with_exclude_reference(args.get_one::<String>("exclude-reference").cloned())
+        .with_pansn(args.get_one::<PanSN>("pansn").cloned())
+        .with_min_group_size(
+            args.get_one::<usize>("min-group-size")
+                .copied()
+                .unwrap_or(0),
+            args.get_flag("drop-small-groups"),
+        )
+        .with_dedup(args.get_flag("dedup"), args.get_flag("strand-dedup"))
+        .with_oriented_edges(args.get_flag("oriented-edges"))
+        .with_count_repeats(args.get_flag("count-repeats"))
+        .with_node_len_range(
+            args.get_one::<u32>("min-node-len").copied(),
+            args.get_one::<u32>("max-node-len").copied(),
+        )]))
     } else {
         None
     }
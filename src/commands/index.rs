@@ -0,0 +1,20 @@
+use clap::{arg, Command};
+
+pub fn get_subcommand() -> Command {
+    Command::new("index")
+        .about("Parse a graph once and write a compact binary index (.pidx) of its nodes, edges, and paths/walks, which all other subcommands accept in place of the graph file")
+        .args(&[
+            arg!(gfa_file: <GFA_FILE> "graph in GFA1 or GFA2 format, accepts also compressed (.gz) file; several files (e.g., one per chromosome) can be given as comma-separated list"),
+            arg!(-o --output <FILE> "Index file (default: name of the graph file with extension .pidx)"),
+        ])
+}
+
+/// Name of the index of the graph file if no name is given, replacing the extensions of the
+/// graph file by .pidx
+pub fn get_default_index_file(gfa_file: &str) -> String {
+    let mut name = gfa_file;
+    for ext in [".gz", ".gfa", ".gfa1", ".gfa2"] {
+        name = name.strip_suffix(ext).unwrap_or(name);
+    }
+    format!("{}.pidx", name)
+}
@@ -1,22 +1,31 @@
 use clap::{arg, ArgMatches, Command};
 
-use crate::analysis_parameter::{AnalysisParameter, AnalysisRun, Grouping};
+use crate::analysis_parameter::{AnalysisParameter, AnalysisRun, PanSN};
+use crate::commands::get_grouping;
 
 pub fn get_subcommand() -> Command {
     Command::new("info")
-        .about("Return general graph and paths info")
+        .about("Return general graph and paths info, optionally including graph complexity measures")
         .args(&[
-            arg!(gfa_file: <GFA_FILE> "graph in GFA1 format, accepts also compressed (.gz) file"),
-            arg!(-s --subset <FILE> "Produce counts by subsetting the graph to a given list of paths (1-column list) or path coordinates (3- or 12-column BED file)"),
-            arg!(-e --exclude <FILE> "Exclude bp/node/edge in growth count that intersect with paths (1-column list) or path coordinates (3- or 12-column BED-file) provided by the given file; all intersecting bp/node/edge will be exluded also in other paths not part of the given list"),
+            arg!(gfa_file: <GFA_FILE> "graph in GFA1 or GFA2 format, accepts also compressed (.gz) file; several files (e.g., one per chromosome) can be given as comma-separated list"),
+            arg!(--complexity "Also report graph complexity measures, i.e., the number, nesting, and size of bubbles and the density of branching nodes, which requires detecting the bubbles of the graph"),
+            arg!(-r --reference <PATH> "Reference path (e.g., GRCh38#0#chr1) to whose length the density of branching nodes is related (default: total length of the graph)").requires("complexity"),
+            arg!(--"node-ids" <FILE> "Write the mapping of the internal node IDs (e.g., of the node lists exported from hexbin selections in the report) to segment names and lengths as tab-separated file"),
+            arg!(--"clean-gfa" <FILE> "Write the graph without the edges that are not used by any path (i.e., without the unused edges reported as such) to the given GFA1 file; transitive edges used by paths are kept").conflicts_with("subset"),
+            arg!(-s --subset <FILE> "Produce counts by subsetting the graph to a given list of paths (1-column list) or path coordinates (3- or 12-column BED file), or to a single path interval (e.g., HG00438#1#chr1:1,000,000-2,000,000); several files can be combined by union, intersection, and difference (e.g., a.txt+b.txt, a.bed&b.bed, a.txt-b.txt)"),
+            arg!(-e --exclude <FILE> "Exclude bp/node/edge in growth count that intersect with paths (1-column list) or path coordinates (3- or 12-column BED-file) provided by the given file; all intersecting bp/node/edge will be exluded also in other paths not part of the given list; several files can be combined as for --subset"),
+            arg!(--"exclude-reference" <NAME> "Exclude bp/node/edge in growth count that intersect with the paths of the reference (e.g., GRCh38 or CHM13), given by the name of its sample (as given by --pansn) or of its paths, or by a regex matching them entirely, in addition to those excluded by --exclude"),
             arg!(-g --groupby <FILE> "Merge counts from paths by path-group mapping from given tab-separated two-column file"),
             arg!(-H --"groupby-haplotype" "Merge counts from paths belonging to same haplotype"),
             arg!(-S --"groupby-sample" "Merge counts from paths belonging to same sample"),
+            arg!(--"groupby-regex" <REGEX> "Merge counts from paths by the captures of the given regex on their names, e.g., '^(?P<pop>[A-Z]+)_[^#]+#(?P<hap>\\d+)' for population and haplotype; paths not matched by the regex are not merged"),
+            arg!(--"group-label" <TEMPLATE> "Label of the groups of --groupby-regex, referring to its named captures in braces, e.g., '{pop}_{hap}' (default: captures joined by #)").requires("groupby-regex"),
+            arg!(--pansn <SPEC> "Naming scheme of paths/walks used by --groupby-haplotype and --groupby-sample, given as delimiter, number of fields, and 1-based index of the haplotype field (e.g., '.,3,2' for sample.haplotype.contig); the sample is the first field (default: '#,3,2', i.e., PanSN)").value_parser(clap::value_parser!(PanSN)),
         ])
 }
 
 pub fn get_instructions(args: &ArgMatches) -> Option<Result<Vec<AnalysisRun>, anyhow::Error>> {
-    if let Some(_args) = args.subcommand_matches("info") {
+    if let Some(args) = args.subcommand_matches("info") {
         let graph = args
             .get_one::<String>("gfa_file")
             .expect("info subcommand has gfa file")
@@ -29,14 +38,7 @@ pub fn get_instructions(args: &ArgMatches) -> Option<Result<Vec<AnalysisRun>, an
             .get_one::<String>("exclude")
             .cloned()
             .unwrap_or_default();
-        let grouping = args.get_one::<String>("groupby").cloned();
-        let grouping = if args.get_flag("groupby-sample") {
-            Some(Grouping::Sample)
-        } else if args.get_flag("groupby-haplotype") {
-            Some(Grouping::Haplotype)
-        } else {
-            grouping.map(|g| Grouping::Custom(g))
-        };
+        let grouping = get_grouping(args);
         let parameters = vec![AnalysisRun::new(
             graph,
             None,
@@ -44,8 +46,15 @@ pub fn get_instructions(args: &ArgMatches) -> Option<Result<Vec<AnalysisRun>, an
             exclude,
             grouping,
             false,
-            vec![AnalysisParameter::Info],
-        )];
+            vec![AnalysisParameter::Info {
+                complexity: args.get_flag("complexity"),
+                reference: args.get_one::<String>("reference").cloned(),
+                node_ids: args.get_one::<String>("node-ids").cloned(),
+                clean_gfa: args.get_one::<String>("clean-gfa").cloned(),
+            }],
+        )
+        .with_exclude_reference(args.get_one::<String>("exclude-reference").cloned())
+        .with_pansn(args.get_one::<PanSN>("pansn").cloned())];
         log::info!("{parameters:?}");
         Some(Ok(parameters))
     } else {
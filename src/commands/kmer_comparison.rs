@@ -0,0 +1,51 @@
+use clap::{arg, ArgMatches, Command};
+
+use crate::analysis_parameter::{AnalysisParameter, AnalysisRun};
+
+pub fn get_subcommand() -> Command {
+    Command::new("kmer-comparison")
+        .about("Compare the k-mers of the node sequences with those of assemblies, reporting how much of each assembly is represented in the graph")
+        .args(&[
+            arg!(gfa_file: <GFA_FILE> "graph in GFA1 or GFA2 format, accepts also compressed (.gz) file; several files (e.g., one per chromosome) can be given as comma-separated list"),
+            arg!(-a --assemblies <FILES> "Comma-separated list of FASTA files (optionally gzipped) of the assemblies whose k-mers are looked up in the graph").required(true).value_delimiter(','),
+            arg!(-k --"kmer-size" <K> "Size of the k-mers, at most 32; k-mers of the graph are those of its nodes and those spanning nodes along its paths/walks").default_value("31").value_parser(clap::value_parser!(usize)),
+            arg!(--scale <SCALE> "Sample about 1/SCALE of all k-mers by their hash (FracMinHash), estimating the containment with less memory").default_value("1").value_parser(clap::builder::RangedU64ValueParser::<u64>::new().range(1..)),
+        ])
+}
+
+pub fn get_instructions(args: &ArgMatches) -> Option<Result<Vec<AnalysisRun>, anyhow::Error>> {
+    if let Some(args) = args.subcommand_matches("kmer-comparison") {
+        let graph = args
+            .get_one::<String>("gfa_file")
+            .expect("kmer-comparison subcommand has gfa file")
+            .to_owned();
+        let assemblies = args
+            .get_many::<String>("assemblies")
+            .expect("kmer-comparison subcommand has assemblies")
+            .cloned()
+            .collect();
+        let k = *args
+            .get_one::<usize>("kmer-size")
+            .expect("kmer-comparison subcommand has k-mer size");
+        let scale = *args
+            .get_one::<u64>("scale")
+            .expect("kmer-comparison subcommand has scale");
+        let parameters = vec![AnalysisRun::new(
+            graph,
+            None,
+            String::new(),
+            String::new(),
+            None,
+            false,
+            vec![AnalysisParameter::KmerComparison {
+                assemblies,
+                k,
+                scale,
+            }],
+        )];
+        log::info!("{parameters:?}");
+        Some(Ok(parameters))
+    } else {
+        None
+    }
+}
@@ -6,13 +6,16 @@ pub fn get_subcommand() -> Command {
     Command::new("node-distribution")
         .about("Return the list of bins with there coverages, log10-lengths and log10-sizes. Due to this being the values for the centers of the hexagons shown in the html plot and not real values, some values might be negative.")
         .args(&[
-            arg!(gfa_file: <GFA_FILE> "graph in GFA1 format, accepts also compressed (.gz) file"),
+            arg!(gfa_file: <GFA_FILE> "graph in GFA1 or GFA2 format, accepts also compressed (.gz) file; several files (e.g., one per chromosome) can be given as comma-separated list"),
             Arg::new("radius")
                 .help("Radius of the hexagons used to bin")
                 .short('r')
                 .long("radius")
                 .value_parser(clap::value_parser!(u32))
                 .default_value("20"),
+            arg!(--members <FILE> "Write the nodes of each bin, together with their coverage and length, to the given tab-separated file"),
+            arg!(--fasta <FILE> "Write the sequences of the nodes of the bins given by --fasta-bins (default: all bins) to the given FASTA file, e.g., to BLAST outlier bins"),
+            arg!(--"fasta-bins" <LIST> "Comma-separated list of bins (as numbered in the output table) whose node sequences are written to the FASTA file").value_parser(clap::value_parser!(usize)).value_delimiter(','),
         ])
 }
 
@@ -33,7 +36,15 @@ pub fn get_instructions(args: &ArgMatches) -> Option<Result<Vec<AnalysisRun>, an
             "".to_string(),
             None,
             false,
-            vec![AnalysisParameter::NodeDistribution { radius }],
+            vec![AnalysisParameter::NodeDistribution {
+                radius,
+                members: args.get_one::<String>("members").cloned(),
+                fasta: args.get_one::<String>("fasta").cloned(),
+                fasta_bins: args
+                    .get_many::<usize>("fasta-bins")
+                    .map(|b| b.copied().collect())
+                    .unwrap_or_default(),
+            }],
         )];
         log::info!("{parameters:?}");
         Some(Ok(parameters))
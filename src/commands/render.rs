@@ -1,4 +1,4 @@
-use clap::{Arg, Command};
+use clap::{Arg, ArgAction, Command};
 
 pub fn get_subcommand() -> Command {
     Command::new("render")
@@ -8,4 +8,31 @@ pub fn get_subcommand() -> Command {
             .num_args(1..)
             .trailing_var_arg(true)
             .help("Specifies one or more JSON files")])
+        .args(&[Arg::new("theme")
+            .required(false)
+            .long("theme")
+            .value_name("FILE")
+            .help("Vega config (JSON) overriding colors, fonts and sizes of all plots")])
+        .args(&[Arg::new("report_dir")
+            .required(false)
+            .long("report-dir")
+            .value_name("DIR")
+            .help("Write the report as directory holding index.html and separate javascript, stylesheet, and data files instead of a single self-contained HTML file, which browsers open more easily for large reports")])
+        .args(&[Arg::new("plot")
+            .required(false)
+            .long("plot")
+            .value_name("DIR")
+            .conflicts_with("report_dir")
+            .help("Write one small standalone HTML file per analysis, holding just its plots and their data, into the given directory instead of the report, e.g., for embedding in lab notebooks")])
+        .args(&[Arg::new("print")
+            .long("print")
+            .action(ArgAction::SetTrue)
+            .conflicts_with_all(["report_dir", "plot"])
+            .help("Write the report as self-contained HTML file that shows all analyses one after another, each starting on a new page when printed, instead of the interactive report, e.g., for archiving it as a static document")])
+        .args(&[Arg::new("pdf")
+            .required(false)
+            .long("pdf")
+            .value_name("FILE")
+            .conflicts_with_all(["report_dir", "plot", "print"])
+            .help("Print the report (in the layout of --print) to the given PDF file with a headless Chromium or Chrome browser, found in PATH or given by PANACUS_BROWSER, e.g., for attaching it to publications")])
 }
@@ -1,5 +1,5 @@
 use std::fs::File;
-use std::io::BufReader;
+use std::io::{BufReader, Read};
 
 use anyhow::anyhow;
 use clap::{Arg, ArgAction, ArgMatches, Command};
@@ -18,7 +18,7 @@ pub fn get_subcommand() -> Command {
             .short('d')
             .action(ArgAction::SetTrue)
             .help(
-                "If set, no actual computation is done, only the planned computation will be shown",
+                "If set, no actual computation is done, only the planned computation will be shown, along with a rough estimate of the memory and runtime of each task from the numbers of nodes, edges, and path steps of the graphs",
             )])
         .args(&[Arg::new("json")
                 .required(false)
@@ -29,6 +29,33 @@ pub fn get_subcommand() -> Command {
                     "Instead of an HTML report, a json result will be delivered. These can later be combined and rendered as a single HTML.",
                 )
         ])
+        .args(&[Arg::new("theme")
+            .required(false)
+            .long("theme")
+            .value_name("FILE")
+            .help("Vega config (JSON) overriding colors, fonts and sizes of all plots")])
+        .args(&[Arg::new("report_dir")
+            .required(false)
+            .long("report-dir")
+            .value_name("DIR")
+            .help("Write the report as directory holding index.html and separate javascript, stylesheet, and data files instead of a single self-contained HTML file, which browsers open more easily for large reports")])
+        .args(&[Arg::new("plot")
+            .required(false)
+            .long("plot")
+            .value_name("DIR")
+            .conflicts_with("report_dir")
+            .help("Write one small standalone HTML file per analysis, holding just its plots and their data, into the given directory instead of the report, e.g., for embedding in lab notebooks")])
+        .args(&[Arg::new("print")
+            .long("print")
+            .action(ArgAction::SetTrue)
+            .conflicts_with_all(["report_dir", "plot"])
+            .help("Write the report as self-contained HTML file that shows all analyses one after another, each starting on a new page when printed, instead of the interactive report, e.g., for archiving it as a static document")])
+        .args(&[Arg::new("pdf")
+            .required(false)
+            .long("pdf")
+            .value_name("FILE")
+            .conflicts_with_all(["report_dir", "plot", "print"])
+            .help("Print the report (in the layout of --print) to the given PDF file with a headless Chromium or Chrome browser, found in PATH or given by PANACUS_BROWSER, e.g., for attaching it to publications")])
 }
 
 pub fn get_instructions(args: &ArgMatches) -> Option<Result<Vec<AnalysisRun>, anyhow::Error>> {
@@ -42,9 +69,10 @@ pub fn get_instructions(args: &ArgMatches) -> Option<Result<Vec<AnalysisRun>, an
 fn parse_report_args(args: &ArgMatches) -> Result<Vec<AnalysisRun>, anyhow::Error> {
     if let Some(yaml_file) = args.get_one::<String>("yaml_file").cloned() {
         let f = File::open(yaml_file)?;
-        let reader = BufReader::new(f);
-        let contents = serde_yaml::from_reader(reader)?;
-        Ok(contents)
+        let mut reader = BufReader::new(f);
+        let mut config = String::new();
+        reader.read_to_string(&mut config)?;
+        AnalysisRun::from_yaml(&config)
     } else {
         println!(
             "
@@ -0,0 +1,22 @@
+use clap::{Arg, Command};
+
+pub fn get_subcommand() -> Command {
+    Command::new("serve")
+        .about("Run the pipeline of a YAML config and serve the HTML report on a local port, rebuilding and reloading it whenever the config changes")
+        .args(&[Arg::new("yaml_file")
+            .required(true)
+            .help("Specifies yaml config")])
+        .args(&[Arg::new("port")
+            .required(false)
+            .long("port")
+            .short('p')
+            .value_name("PORT")
+            .default_value("8000")
+            .value_parser(clap::value_parser!(u16))
+            .help("Local port on which the report is served")])
+        .args(&[Arg::new("theme")
+            .required(false)
+            .long("theme")
+            .value_name("FILE")
+            .help("Vega config (JSON) overriding colors, fonts and sizes of all plots")])
+}
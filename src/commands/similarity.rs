@@ -2,21 +2,31 @@ use crate::clap_enum_variants_no_all;
 use clap::{arg, Arg, ArgMatches, Command};
 use strum::VariantNames;
 
-use crate::analysis_parameter::{AnalysisParameter, AnalysisRun, ClusterMethod, Grouping};
+use crate::analysis_parameter::{AnalysisParameter, AnalysisRun, ClusterMethod, PanSN};
+use crate::commands::get_grouping;
 use crate::util::CountType;
 
 pub fn get_subcommand() -> Command {
     Command::new("similarity")
-        .about("Compute coverage table for count type")
+        .about("Compute pairwise Jaccard similarity of paths/groups")
         .args(&[
-            arg!(gfa_file: <GFA_FILE> "graph in GFA1 format, accepts also compressed (.gz) file"),
-            arg!(-s --subset <FILE> "Produce counts by subsetting the graph to a given list of paths (1-column list) or path coordinates (3- or 12-column BED file)"),
-            arg!(-e --exclude <FILE> "Exclude bp/node/edge in growth count that intersect with paths (1-column list) or path coordinates (3- or 12-column BED-file) provided by the given file; all intersecting bp/node/edge will be exluded also in other paths not part of the given list"),
+            arg!(gfa_file: <GFA_FILE> "graph in GFA1 or GFA2 format, accepts also compressed (.gz) file; several files (e.g., one per chromosome) can be given as comma-separated list"),
+            arg!(-s --subset <FILE> "Produce counts by subsetting the graph to a given list of paths (1-column list) or path coordinates (3- or 12-column BED file), or to a single path interval (e.g., HG00438#1#chr1:1,000,000-2,000,000); several files can be combined by union, intersection, and difference (e.g., a.txt+b.txt, a.bed&b.bed, a.txt-b.txt)"),
+            arg!(-e --exclude <FILE> "Exclude bp/node/edge in growth count that intersect with paths (1-column list) or path coordinates (3- or 12-column BED-file) provided by the given file; all intersecting bp/node/edge will be exluded also in other paths not part of the given list; several files can be combined as for --subset"),
+            arg!(--"exclude-reference" <NAME> "Exclude bp/node/edge in growth count that intersect with the paths of the reference (e.g., GRCh38 or CHM13), given by the name of its sample (as given by --pansn) or of its paths, or by a regex matching them entirely, in addition to those excluded by --exclude"),
             arg!(-g --groupby <FILE> "Merge counts from paths by path-group mapping from given tab-separated two-column file"),
             arg!(-H --"groupby-haplotype" "Merge counts from paths belonging to same haplotype"),
             arg!(-S --"groupby-sample" "Merge counts from paths belonging to same sample"),
+            arg!(--"groupby-regex" <REGEX> "Merge counts from paths by the captures of the given regex on their names, e.g., '^(?P<pop>[A-Z]+)_[^#]+#(?P<hap>\\d+)' for population and haplotype; paths not matched by the regex are not merged"),
+            arg!(--"group-label" <TEMPLATE> "Label of the groups of --groupby-regex, referring to its named captures in braces, e.g., '{pop}_{hap}' (default: captures joined by #)").requires("groupby-regex"),
+            arg!(--pansn <SPEC> "Naming scheme of paths/walks used by --groupby-haplotype and --groupby-sample, given as delimiter, number of fields, and 1-based index of the haplotype field (e.g., '.,3,2' for sample.haplotype.contig); the sample is the first field (default: '#,3,2', i.e., PanSN)").value_parser(clap::value_parser!(PanSN)),
+            arg!(--metadata <FILE> "Tab-separated sample metadata table with a header line, whose first column holds the paths/groups or their samples; plots are colored by a phenotype column"),
+            arg!(--"color-by" <COLUMN> "Column of the metadata table by which plots are colored (default: the first column after the paths/groups)"),
             arg!(-a --"total" "Summarize by totaling presence/absence over all groups"),
-            Arg::new("count").help("Graph quantity to be counted").default_value("node").ignore_case(true).short('c').long("count").value_parser(clap_enum_variants_no_all!(CountType)),
+            Arg::new("count").help("Graph quantity to be counted; with bp, shared nodes are weighted by their sequence length (bp-based Jaccard), which is less skewed by many tiny nodes; with edge, similarity is computed over shared adjacencies, which captures structural/ordering agreement between paths").default_value("node").ignore_case(true).short('c').long("count").value_parser(clap_enum_variants_no_all!(CountType)),
+            arg!(-p --populations <FILE> "Compare each path/group against the pan-set (union) of each population, given by a tab-separated two-column file assigning paths/groups (after grouping) to populations, e.g., to assign samples or check their labels; a path/group is left out of the pan-set of its own population"),
+            arg!(-b --bootstrap <N> "Number of bootstrap replicates resampling nodes (or bp/edges) with replacement, to attach support values to the clades of the dendrogram (default: 0, i.e., no bootstrap)").value_parser(clap::value_parser!(usize)),
+            arg!(--newick <FILE> "Write the dendrogram of the clustering in Newick format to the given file, with bootstrap support values as labels of its internal nodes"),
             Arg::new("cluster_method").help("Method for clustering results").default_value("centroid").ignore_case(true).short('m').long("method").value_parser(clap_enum_variants_no_all!(ClusterMethod)),
         ])
 }
@@ -43,14 +53,7 @@ pub fn get_instructions(args: &ArgMatches) -> Option<anyhow::Result<Vec<Analysis
             .get_one::<String>("exclude")
             .cloned()
             .unwrap_or_default();
-        let grouping = args.get_one::<String>("groupby").cloned();
-        let grouping = if args.get_flag("groupby-sample") {
-            Some(Grouping::Sample)
-        } else if args.get_flag("groupby-haplotype") {
-            Some(Grouping::Haplotype)
-        } else {
-            grouping.map(|g| Grouping::Custom(g))
-        };
+        let grouping = get_grouping(args);
         let parameters = vec![AnalysisRun::new(
             graph,
             None,
@@ -61,7 +64,16 @@ pub fn get_instructions(args: &ArgMatches) -> Option<anyhow::Result<Vec<Analysis
             vec![AnalysisParameter::Similarity {
                 count_type: count,
                 cluster_method,
+                populations: args.get_one::<String>("populations").cloned(),
+                bootstrap: args.get_one::<usize>("bootstrap").copied().unwrap_or(0),
+                newick: args.get_one::<String>("newick").cloned(),
             }],
+        )
+        .with_exclude_reference(args.get_one::<String>("exclude-reference").cloned())
+        .with_pansn(args.get_one::<PanSN>("pansn").cloned())
+        .with_metadata(
+            args.get_one::<String>("metadata").cloned(),
+            args.get_one::<String>("color-by").cloned(),
         )];
         // log::info!("{parameters:?}");
         Some(Ok(parameters))
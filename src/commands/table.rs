@@ -2,7 +2,8 @@ use crate::clap_enum_variants_no_all;
 use clap::{arg, Arg, ArgMatches, Command};
 use strum::VariantNames;
 
-use crate::analysis_parameter::{AnalysisParameter, AnalysisRun, Grouping};
+use crate::analysis_parameter::{AnalysisParameter, AnalysisRun, PanSN, TableFormat};
+use crate::commands::get_grouping;
 
 use crate::util::CountType;
 
@@ -10,14 +11,22 @@ pub fn get_subcommand() -> Command {
     Command::new("table")
         .about("Compute coverage table for count type")
         .args(&[
-            arg!(gfa_file: <GFA_FILE> "graph in GFA1 format, accepts also compressed (.gz) file"),
-            arg!(-s --subset <FILE> "Produce counts by subsetting the graph to a given list of paths (1-column list) or path coordinates (3- or 12-column BED file)"),
-            arg!(-e --exclude <FILE> "Exclude bp/node/edge in growth count that intersect with paths (1-column list) or path coordinates (3- or 12-column BED-file) provided by the given file; all intersecting bp/node/edge will be exluded also in other paths not part of the given list"),
+            arg!(gfa_file: <GFA_FILE> "graph in GFA1 or GFA2 format, accepts also compressed (.gz) file; several files (e.g., one per chromosome) can be given as comma-separated list"),
+            arg!(-s --subset <FILE> "Produce counts by subsetting the graph to a given list of paths (1-column list) or path coordinates (3- or 12-column BED file), or to a single path interval (e.g., HG00438#1#chr1:1,000,000-2,000,000); several files can be combined by union, intersection, and difference (e.g., a.txt+b.txt, a.bed&b.bed, a.txt-b.txt)"),
+            arg!(-e --exclude <FILE> "Exclude bp/node/edge in growth count that intersect with paths (1-column list) or path coordinates (3- or 12-column BED-file) provided by the given file; all intersecting bp/node/edge will be exluded also in other paths not part of the given list; several files can be combined as for --subset"),
+            arg!(--"exclude-reference" <NAME> "Exclude bp/node/edge in growth count that intersect with the paths of the reference (e.g., GRCh38 or CHM13), given by the name of its sample (as given by --pansn) or of its paths, or by a regex matching them entirely, in addition to those excluded by --exclude"),
             arg!(-g --groupby <FILE> "Merge counts from paths by path-group mapping from given tab-separated two-column file"),
             arg!(-H --"groupby-haplotype" "Merge counts from paths belonging to same haplotype"),
             arg!(-S --"groupby-sample" "Merge counts from paths belonging to same sample"),
+            arg!(--"groupby-regex" <REGEX> "Merge counts from paths by the captures of the given regex on their names, e.g., '^(?P<pop>[A-Z]+)_[^#]+#(?P<hap>\\d+)' for population and haplotype; paths not matched by the regex are not merged"),
+            arg!(--"group-label" <TEMPLATE> "Label of the groups of --groupby-regex, referring to its named captures in braces, e.g., '{pop}_{hap}' (default: captures joined by #)").requires("groupby-regex"),
+            arg!(--pansn <SPEC> "Naming scheme of paths/walks used by --groupby-haplotype and --groupby-sample, given as delimiter, number of fields, and 1-based index of the haplotype field (e.g., '.,3,2' for sample.haplotype.contig); the sample is the first field (default: '#,3,2', i.e., PanSN)").value_parser(clap::value_parser!(PanSN)),
+            arg!(-D --dedup "Merge nodes with identical or reverse-complementary identical sequences such that their duplicates are counted only once"),
+            arg!(--"strand-dedup" "Merge duplicated nodes like --dedup and place the bp of nodes that are partially covered by --subset coordinates on the forward strand of their representative, such that bp covered on the reverse strand of a node or of its duplicates (or on either strand of a palindromic node) count as the same bp; without subset coordinates, nodes are covered as a whole and this is the same as --dedup; the policy is documented in the header of the output"),
+            arg!(--"oriented-edges" "Count edges traversed in opposite orientations (e.g., +a+b and -b-a) as different countables instead of as the same edge"),
             arg!(-a --"total" "Summarize by totaling presence/absence over all groups"),
-            arg!(-O --order <FILE> "The ordered histogram will be produced according to order of paths/groups in the supplied file (1-column list). If this option is not used, the order is determined by the rank of paths/groups in the subset list, and if that option is not used, the order is determined by the rank of paths/groups in the GFA file."),
+            arg!(-O --order <FILE> "The ordered histogram will be produced according to order of paths/groups in the supplied file (1-column list, or the leaf order of a phylogeny in Newick format whose leaves name paths, groups, or samples; taxa not in the graph are ignored and paths/groups missing from the tree are appended). If this option is not used, the order is determined by the rank of paths/groups in the subset list, and if that option is not used, the order is determined by the rank of paths/groups in the GFA file."),
+            Arg::new("format").help("Format of the table: tsv writes the dense table, mtx (MatrixMarket) and triplets (tab-separated countable, group, and value) only its non-zero entries, which is much smaller for large pangenomes; both can be loaded into scipy/R as sparse matrices").default_value("tsv").ignore_case(true).short('f').long("format").value_parser(clap_enum_variants_no_all!(TableFormat)),
             Arg::new("count").help("Graph quantity to be counted").default_value("node").ignore_case(true).short('c').long("count").value_parser(clap_enum_variants_no_all!(CountType)),
         ])
 }
@@ -43,14 +52,7 @@ pub fn get_instructions(args: &ArgMatches) -> Option<anyhow::Result<Vec<Analysis
             .get_one::<String>("exclude")
             .cloned()
             .unwrap_or_default();
-        let grouping = args.get_one::<String>("groupby").cloned();
-        let grouping = if args.get_flag("groupby-sample") {
-            Some(Grouping::Sample)
-        } else if args.get_flag("groupby-haplotype") {
-            Some(Grouping::Haplotype)
-        } else {
-            grouping.map(|g| Grouping::Custom(g))
-        };
+        let grouping = get_grouping(args);
         let parameters = vec![AnalysisRun::new(
             graph,
             None,
@@ -62,8 +64,15 @@ pub fn get_instructions(args: &ArgMatches) -> Option<anyhow::Result<Vec<Analysis
                 count_type: count,
                 order,
                 total,
+                format: *args
+                    .get_one::<TableFormat>("format")
+                    .expect("table subcommand has format"),
             }],
-        )];
+        )
+        .with_exclude_reference(args.get_one::<String>("exclude-reference").cloned())
+        .with_pansn(args.get_one::<PanSN>("pansn").cloned())
+        .with_dedup(args.get_flag("dedup"), args.get_flag("strand-dedup"))
+        .with_oriented_edges(args.get_flag("oriented-edges"))];
         Some(Ok(parameters))
     } else {
         None
@@ -0,0 +1,77 @@
+use crate::clap_enum_variants_no_all;
+use clap::{arg, Arg, ArgMatches, Command};
+use strum::VariantNames;
+
+use crate::analysis_parameter::{AnalysisParameter, AnalysisRun, PanSN};
+use crate::commands::get_grouping;
+use crate::util::CountType;
+
+pub fn get_subcommand() -> Command {
+    Command::new("window-similarity")
+        .about("Compute pairwise Jaccard similarity of paths/groups within sliding windows along a reference path, localizing where haplotypes diverge")
+        .args(&[
+            arg!(gfa_file: <GFA_FILE> "graph in GFA1 or GFA2 format, accepts also compressed (.gz) file; several files (e.g., one per chromosome) can be given as comma-separated list"),
+            arg!(-r --reference <PATH> "Reference path (e.g., GRCh38#0#chr1) along which the windows are laid out; nodes off the reference are placed after the reference node that precedes them in a path/walk").required(true),
+            arg!(-w --window <BP> "Size of the windows").default_value("100000").value_parser(clap::value_parser!(usize)),
+            arg!(--step <BP> "Distance between the starts of consecutive windows (default: the window size, i.e., non-overlapping windows)").value_parser(clap::value_parser!(usize)),
+            arg!(-s --subset <FILE> "Produce counts by subsetting the graph to a given list of paths (1-column list) or path coordinates (3- or 12-column BED file), or to a single path interval (e.g., HG00438#1#chr1:1,000,000-2,000,000); several files can be combined by union, intersection, and difference (e.g., a.txt+b.txt, a.bed&b.bed, a.txt-b.txt)"),
+            arg!(-e --exclude <FILE> "Exclude bp/node/edge in growth count that intersect with paths (1-column list) or path coordinates (3- or 12-column BED-file) provided by the given file; all intersecting bp/node/edge will be exluded also in other paths not part of the given list; several files can be combined as for --subset"),
+            arg!(--"exclude-reference" <NAME> "Exclude bp/node/edge in growth count that intersect with the paths of the reference (e.g., GRCh38 or CHM13), given by the name of its sample (as given by --pansn) or of its paths, or by a regex matching them entirely, in addition to those excluded by --exclude"),
+            arg!(-g --groupby <FILE> "Merge counts from paths by path-group mapping from given tab-separated two-column file"),
+            arg!(-H --"groupby-haplotype" "Merge counts from paths belonging to same haplotype"),
+            arg!(-S --"groupby-sample" "Merge counts from paths belonging to same sample"),
+            arg!(--"groupby-regex" <REGEX> "Merge counts from paths by the captures of the given regex on their names, e.g., '^(?P<pop>[A-Z]+)_[^#]+#(?P<hap>\\d+)' for population and haplotype; paths not matched by the regex are not merged"),
+            arg!(--"group-label" <TEMPLATE> "Label of the groups of --groupby-regex, referring to its named captures in braces, e.g., '{pop}_{hap}' (default: captures joined by #)").requires("groupby-regex"),
+            arg!(--pansn <SPEC> "Naming scheme of paths/walks used by --groupby-haplotype and --groupby-sample, given as delimiter, number of fields, and 1-based index of the haplotype field (e.g., '.,3,2' for sample.haplotype.contig); the sample is the first field (default: '#,3,2', i.e., PanSN)").value_parser(clap::value_parser!(PanSN)),
+            Arg::new("count").help("Graph quantity to be counted; with bp, shared nodes are weighted by their sequence length").default_value("node").ignore_case(true).short('c').long("count").value_parser(clap_enum_variants_no_all!(CountType)),
+        ])
+}
+
+pub fn get_instructions(args: &ArgMatches) -> Option<anyhow::Result<Vec<AnalysisRun>>> {
+    if let Some(args) = args.subcommand_matches("window-similarity") {
+        let graph = args
+            .get_one::<String>("gfa_file")
+            .expect("window-similarity subcommand has gfa file")
+            .to_owned();
+        let count = args
+            .get_one::<CountType>("count")
+            .expect("window-similarity subcommand has count type")
+            .to_owned();
+        let reference = args
+            .get_one::<String>("reference")
+            .expect("window-similarity subcommand has reference")
+            .to_owned();
+        let window = *args
+            .get_one::<usize>("window")
+            .expect("window-similarity subcommand has window size");
+        let subset = args
+            .get_one::<String>("subset")
+            .cloned()
+            .unwrap_or_default();
+        let exclude = args
+            .get_one::<String>("exclude")
+            .cloned()
+            .unwrap_or_default();
+        let grouping = get_grouping(args);
+        let parameters = vec![AnalysisRun::new(
+            graph,
+            None,
+            subset,
+            exclude,
+            grouping,
+            false,
+            vec![AnalysisParameter::WindowSimilarity {
+                reference,
+                window,
+                step: args.get_one::<usize>("step").copied(),
+                count_type: count,
+            }],
+        )
+        .with_exclude_reference(args.get_one::<String>("exclude-reference").cloned())
+        .with_pansn(args.get_one::<PanSN>("pansn").cloned())];
+        log::info!("{parameters:?}");
+        Some(Ok(parameters))
+    } else {
+        None
+    }
+}
@@ -0,0 +1,169 @@
+/* standard use */
+use std::collections::HashSet;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::net::{TcpListener, TcpStream};
+use std::str::FromStr;
+use std::time::Duration;
+
+/* external use */
+use strum::VariantNames;
+
+/* private use */
+use crate::analyses::InputRequirement as Req;
+use crate::analysis_parameter::{AnalysisRun, Task};
+use crate::graph_broker::{GraphBroker, GraphState};
+use crate::util::CountType;
+
+// time after which a connection that has not sent its next query is dropped, such that an idle
+// client does not keep others from being answered
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Loads the graph once and answers queries on the given local port, such that the graph is not
+/// parsed again for each analysis. Queries and responses are JSON objects, one per line; a query
+/// is a run of the YAML config without graph, e.g.,
+/// `{"subset": "subset.bed", "analyses": [{"Hist": {"count_type": "Bp"}}]}`, and is answered by
+/// `{"ok": true, "tables": [...]}` with the table of each analysis, or by
+/// `{"ok": false, "error": "..."}`. Connections are answered one after another and are closed
+/// once they have not sent a query for 5 seconds
+pub fn daemon(gfa_file: &str, port: u16) -> anyhow::Result<()> {
+    let mut gb = load_graph(gfa_file)?;
+    let listener = TcpListener::bind(("127.0.0.1", port))
+        .map_err(|e| anyhow::anyhow!("unable to listen on port {}: {}", port, e))?;
+    log::info!("answering queries on {} at 127.0.0.1:{}", gfa_file, port);
+
+    for stream in listener.incoming() {
+        match stream {
+            Ok(stream) => {
+                if let Err(e) = answer_queries(stream, &mut gb, gfa_file) {
+                    log::warn!("unable to answer queries: {}", e);
+                }
+            }
+            Err(e) => log::warn!("unable to accept connection: {}", e),
+        }
+    }
+    Ok(())
+}
+
+/// Loads the graph such that queries can be run on it without parsing it again
+pub fn load_graph(gfa_file: &str) -> anyhow::Result<GraphBroker> {
+    let mut gb = GraphBroker::new();
+    let reqs = HashSet::from([Req::Graph(gfa_file.to_string()), Req::Node]);
+    gb.change_graph_state(
+        GraphState {
+            graph: gfa_file.to_string(),
+            ..Default::default()
+        },
+        &reqs,
+        false,
+    )?;
+    Ok(gb)
+}
+
+/// Count type given as on the command line (e.g., "bp", ignoring case) in the format of queries,
+/// such that bindings can pass it on
+pub fn query_count_type(count_type: &str) -> anyhow::Result<serde_json::Value> {
+    let count = CountType::from_str(&count_type.to_lowercase()).map_err(|_| {
+        anyhow::anyhow!(
+            "unknown count type {}, use {}",
+            count_type,
+            CountType::VARIANTS.join(", ")
+        )
+    })?;
+    Ok(serde_json::to_value(count)?)
+}
+
+// answers the queries of a connection one after another until the client closes it or does not
+// send a query within REQUEST_TIMEOUT
+fn answer_queries(stream: TcpStream, gb: &mut GraphBroker, gfa_file: &str) -> std::io::Result<()> {
+    stream.set_read_timeout(Some(REQUEST_TIMEOUT))?;
+    let mut out = BufWriter::new(stream.try_clone()?);
+    for line in BufReader::new(stream).lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let response = match run_query(&line, gb, gfa_file) {
+            Ok(tables) => serde_json::json!({ "ok": true, "tables": tables }),
+            Err(e) => {
+                log::warn!("query failed: {:#}", e);
+                serde_json::json!({ "ok": false, "error": format!("{:#}", e) })
+            }
+        };
+        writeln!(out, "{}", response)?;
+        out.flush()?;
+    }
+    Ok(())
+}
+
+/// Runs the analyses of a query on the graph of the broker and returns their tables, ordered as
+/// the analyses are executed; the broker keeps the graph loaded for the next query
+pub fn run_query(query: &str, gb: &mut GraphBroker, gfa_file: &str) -> anyhow::Result<Vec<String>> {
+    let run = parse_query(query, gfa_file)?;
+    let mut tables = Vec::new();
+    for mut task in AnalysisRun::convert_to_tasks(vec![run]) {
+        match &mut task {
+            Task::Analysis(analysis) => {
+                log::info!("Executing Analysis: {}", analysis.get_type());
+                tables.push(analysis.generate_table(Some(&*gb))?);
+            }
+            Task::GraphStateChange { .. } => {
+                // the graph stays the same, hence only subset, grouping, etc. are applied
+                let (state, reqs, nice) = task.get_graph_state().unwrap();
+                gb.change_graph_state(state, reqs, nice)?;
+            }
+            Task::OrderChange(order) => gb.change_order(order.as_deref())?,
+            _ => anyhow::bail!("{:?} is not supported in queries", task),
+        }
+    }
+    Ok(tables)
+}
+
+// queries always refer to the graph loaded by the daemon
+fn parse_query(query: &str, gfa_file: &str) -> anyhow::Result<AnalysisRun> {
+    let mut query: serde_json::Value = serde_json::from_str(query)?;
+    let fields = query
+        .as_object_mut()
+        .ok_or_else(|| anyhow::anyhow!("query must be a JSON object"))?;
+    if fields.contains_key("graph") {
+        anyhow::bail!(
+            "queries must not specify a graph, the daemon answers them on the loaded one"
+        );
+    }
+    fields.insert("graph".to_string(), gfa_file.into());
+    Ok(serde_json::from_value(query)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_query() {
+        let run = parse_query(
+            r#"{"subset": "subset.bed", "analyses": [{"Hist": {"count_type": "Bp"}}]}"#,
+            "graph.gfa",
+        )
+        .unwrap();
+        let tasks = AnalysisRun::convert_to_tasks(vec![run]);
+        let (state, reqs, _) = tasks[0].get_graph_state().unwrap();
+        assert_eq!(state.graph, "graph.gfa");
+        assert_eq!(state.subset, "subset.bed");
+        assert!(reqs.contains(&Req::Graph("graph.gfa".to_string())));
+        assert!(matches!(tasks[1], Task::Analysis(_)));
+
+        assert!(parse_query(r#"{"graph": "other.gfa", "analyses": []}"#, "graph.gfa").is_err());
+        assert!(parse_query(r#"[]"#, "graph.gfa").is_err());
+        assert!(parse_query(r#"{"analyses": "#, "graph.gfa").is_err());
+    }
+
+    #[test]
+    fn test_query_count_type() {
+        assert_eq!(query_count_type("bp").unwrap(), "Bp");
+        assert_eq!(query_count_type("Bubble").unwrap(), "Bubble");
+        let msg = query_count_type("kmer").unwrap_err().to_string();
+        assert_eq!(
+            msg,
+            "unknown count type kmer, use node, bp, edge, bubble, all"
+        );
+    }
+}
@@ -0,0 +1,312 @@
+/* standard use */
+use std::collections::{HashMap, HashSet};
+use std::io::{BufRead, Error};
+
+/* external use */
+use memchr::memchr;
+
+/* private use */
+use crate::analyses::InputRequirement as Req;
+use crate::analysis_parameter::Task;
+use crate::io::{bufreader_from_compressed_gfa, is_graph_index, split_graph_files};
+use crate::util::format_rss;
+
+// rough sizes and single-thread throughputs, as observed on human pangenome graphs; estimates
+// derived from them are meant for sizing cluster jobs, not as predictions
+const BYTES_PER_NODE: u64 = 64; // length, name, and coverages of a node
+const BYTES_PER_EDGE: u64 = 32;
+const BYTES_PER_STEP: u64 = 8; // item tables of the paths
+const BYTES_PER_GROUP_ENTRY: u64 = 4; // non-zero entries of the abacus by group
+const PARSED_BYTES_PER_SECOND: f64 = 100e6;
+// bytes of path/walk lines per step, i.e., node id, orientation, and separator
+const TEXT_BYTES_PER_STEP: u64 = 8;
+const STEPS_PER_SECOND: f64 = 50e6;
+
+/// Numbers of records of a graph, counted by the type of its (uncompressed) lines without
+/// parsing them; the number of steps is estimated from the length of the path/walk lines
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct GraphCounts {
+    pub bytes: u64,
+    pub nodes: u64,
+    pub edges: u64,
+    pub paths: u64,
+    pub steps: u64,
+}
+
+impl GraphCounts {
+    pub fn from_gfa<R: BufRead>(data: &mut R) -> Result<Self, Error> {
+        let mut counts = Self::default();
+        let mut path_bytes = 0;
+        // type of the current line, or none at the start of a line
+        let mut line_type: Option<u8> = None;
+        loop {
+            let buf = data.fill_buf()?;
+            if buf.is_empty() {
+                break;
+            }
+            let mut pos = 0;
+            while pos < buf.len() {
+                let t = *line_type.get_or_insert_with(|| {
+                    match buf[pos] {
+                        b'S' => counts.nodes += 1,
+                        b'L' => counts.edges += 1,
+                        b'P' | b'W' => counts.paths += 1,
+                        _ => (),
+                    }
+                    buf[pos]
+                });
+                // the rest of the line is skipped without looking at it
+                let end = match memchr(b'\n', &buf[pos..]) {
+                    Some(i) => {
+                        line_type = None;
+                        pos + i + 1
+                    }
+                    None => buf.len(),
+                };
+                if t == b'P' || t == b'W' {
+                    path_bytes += (end - pos) as u64;
+                }
+                pos = end;
+            }
+            let n = buf.len();
+            counts.bytes += n as u64;
+            data.consume(n);
+        }
+        counts.steps = path_bytes / TEXT_BYTES_PER_STEP;
+        Ok(counts)
+    }
+
+    fn add(&mut self, other: &Self) {
+        self.bytes += other.bytes;
+        self.nodes += other.nodes;
+        self.edges += other.edges;
+        self.paths += other.paths;
+        self.steps += other.steps;
+    }
+}
+
+/// Expected memory footprint (of the loaded graph state and the task) and runtime of a task
+#[derive(Debug, Clone, PartialEq)]
+pub struct TaskEstimate {
+    pub task: String,
+    pub memory: Option<u64>,
+    pub seconds: Option<f64>,
+}
+
+fn count_graph(gfa_file: &str) -> Option<GraphCounts> {
+    let mut counts = GraphCounts::default();
+    for file in split_graph_files(gfa_file) {
+        if is_graph_index(file) {
+            log::warn!("cannot estimate resources of graph index {}", file);
+            return None;
+        }
+        log::info!("counting records of {}", file);
+        match bufreader_from_compressed_gfa(file).and_then(|mut x| GraphCounts::from_gfa(&mut x)) {
+            Ok(c) => counts.add(&c),
+            Err(e) => {
+                log::warn!("cannot estimate resources of {}: {}", file, e);
+                return None;
+            }
+        }
+    }
+    Some(counts)
+}
+
+// memory of a loaded graph state and the time to load it, given its requirements; the number of
+// groups is at most that of the paths
+fn estimate_graph_state(counts: &GraphCounts, reqs: &HashSet<Req>) -> (u64, f64) {
+    let mut memory = counts.nodes * BYTES_PER_NODE + counts.steps * BYTES_PER_STEP;
+    let mut seconds =
+        counts.bytes as f64 / PARSED_BYTES_PER_SECOND + counts.steps as f64 / STEPS_PER_SECOND;
+    if reqs.contains(&Req::Edge) {
+        memory += counts.edges * BYTES_PER_EDGE;
+    }
+    if reqs.iter().any(|r| matches!(r, Req::AbacusByGroup(_))) {
+        memory += counts.steps.min(counts.nodes * counts.paths) * BYTES_PER_GROUP_ENTRY;
+        seconds += counts.steps as f64 / STEPS_PER_SECOND;
+    }
+    (memory, seconds)
+}
+
+// additional memory and time of an analysis on a loaded graph state; comparing all pairs of
+// groups dominates the similarity, all others are about linear in the number of nodes
+fn estimate_analysis(analysis_type: &str, counts: &GraphCounts) -> (u64, f64) {
+    match analysis_type {
+        "Similarity" => (
+            counts.paths * counts.paths * 8,
+            (counts.steps * counts.paths) as f64 / STEPS_PER_SECOND,
+        ),
+        _ => (0, counts.nodes as f64 / STEPS_PER_SECOND),
+    }
+}
+
+/// Estimates of the tasks of a dry run on the given number of threads, assuming that all stages
+/// scale linearly with them
+pub fn estimate_tasks(tasks: &[Task], threads: usize) -> Vec<TaskEstimate> {
+    let mut graphs: HashMap<String, Option<GraphCounts>> = HashMap::new();
+    let mut state: Option<(GraphCounts, u64)> = None;
+    let threads = threads.max(1) as f64;
+    let mut res = Vec::new();
+    for task in tasks {
+        let (task_name, estimate) = match task {
+            Task::GraphStateChange { graph, reqs, .. } => {
+                let counts = *graphs
+                    .entry(graph.clone())
+                    .or_insert_with(|| count_graph(graph));
+                state = counts.map(|c| (c, estimate_graph_state(&c, reqs).0));
+                (
+                    format!("load graph {}", graph),
+                    counts.map(|c| estimate_graph_state(&c, reqs)),
+                )
+            }
+            Task::Analysis(analysis) => {
+                let analysis_type = analysis.get_type();
+                let estimate = state.map(|(counts, memory)| {
+                    let (m, s) = estimate_analysis(&analysis_type, &counts);
+                    (memory + m, s)
+                });
+                (analysis_type, estimate)
+            }
+            Task::OrderChange(order) => (
+                format!("{:?}", task),
+                state.map(|(counts, memory)| {
+                    let seconds = match order {
+                        Some(_) => counts.steps as f64 / STEPS_PER_SECOND,
+                        None => 0.0,
+                    };
+                    (memory, seconds)
+                }),
+            ),
+            task => (
+                format!("{:?}", task),
+                state.map(|(_, memory)| (memory, 0.0)),
+            ),
+        };
+        res.push(TaskEstimate {
+            task: task_name,
+            memory: estimate.map(|(m, _)| m),
+            seconds: estimate.map(|(_, s)| s / threads),
+        });
+    }
+    res
+}
+
+/// Expected peak memory of executing the tasks, i.e., the largest memory of any of them; None if
+/// the memory of some task cannot be estimated
+pub fn estimate_peak_memory(tasks: &[Task], threads: usize) -> Option<u64> {
+    estimate_tasks(tasks, threads)
+        .iter()
+        .map(|e| e.memory)
+        .try_fold(0, |max, m| m.map(|m| max.max(m)))
+}
+
+/// Number of runs, of at most parallel_runs, that can be executed at a time such that the ones
+/// expected to take the most memory fit into the budget (bytes) together; runs whose memory
+/// cannot be estimated take the whole budget, and a single run is always executed
+pub fn get_runs_within_budget(memory: &[Option<u64>], parallel_runs: usize, budget: u64) -> usize {
+    let mut memory: Vec<u64> = memory.iter().map(|m| m.unwrap_or(budget)).collect();
+    memory.sort_unstable_by(|a, b| b.cmp(a));
+    let mut total = 0;
+    let mut runs = 0;
+    for m in memory.iter().take(parallel_runs) {
+        total += m;
+        if total > budget {
+            break;
+        }
+        runs += 1;
+    }
+    runs.max(1)
+}
+
+/// Tab-separated table of the estimates, including their total
+pub fn get_estimates_table(estimates: &[TaskEstimate]) -> String {
+    let mut res = "task\testimated memory (MB)\testimated runtime (s)\n".to_string();
+    let format_seconds = |s: Option<f64>| s.map_or("NA".to_string(), |s| format!("{:.1}", s));
+    for e in estimates {
+        res.push_str(&format!(
+            "{}\t{}\t{}\n",
+            e.task,
+            format_rss(e.memory),
+            format_seconds(e.seconds)
+        ));
+    }
+    let complete = estimates.iter().all(|e| e.memory.is_some());
+    res.push_str(&format!(
+        "total\t{}\t{}\n",
+        format_rss(
+            estimates
+                .iter()
+                .filter_map(|e| e.memory)
+                .max()
+                .filter(|_| complete)
+        ),
+        format_seconds(Some(estimates.iter().filter_map(|e| e.seconds).sum()).filter(|_| complete))
+    ));
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_graph_counts_from_gfa() {
+        let gfa = "H\tVN:Z:1.0\n\
+                   S\t1\tACGT\n\
+                   S\t2\tA\n\
+                   S\t3\tCC\n\
+                   L\t1\t+\t2\t+\t0M\n\
+                   L\t2\t+\t3\t-\t0M\n\
+                   P\ta#0#chr1\t1+,2+,3-\t*\n\
+                   W\tb\t1\tchr1\t0\t7\t>1<3\n";
+        // lines are also counted if they are split across reads
+        let counts =
+            GraphCounts::from_gfa(&mut std::io::BufReader::with_capacity(5, gfa.as_bytes()))
+                .unwrap();
+        let path_bytes = gfa.lines().skip(6).map(|l| l.len() as u64 + 1).sum::<u64>();
+        assert_eq!(
+            counts,
+            GraphCounts {
+                bytes: gfa.len() as u64,
+                nodes: 3,
+                edges: 2,
+                paths: 2,
+                steps: path_bytes / TEXT_BYTES_PER_STEP,
+            }
+        );
+    }
+
+    #[test]
+    fn test_get_runs_within_budget() {
+        let memory = vec![Some(30), Some(10), Some(20), Some(10)];
+        assert_eq!(get_runs_within_budget(&memory, 4, 100), 4);
+        // the two largest runs take 50 bytes together, the three largest 60
+        assert_eq!(get_runs_within_budget(&memory, 4, 55), 2);
+        assert_eq!(get_runs_within_budget(&memory, 2, 1000), 2);
+        assert_eq!(get_runs_within_budget(&memory, 4, 5), 1);
+        assert_eq!(get_runs_within_budget(&[Some(1), None], 2, 100), 1);
+    }
+
+    #[test]
+    fn test_get_estimates_table() {
+        let estimates = vec![
+            TaskEstimate {
+                task: "load graph g.gfa".to_string(),
+                memory: Some(2 * 1024 * 1024),
+                seconds: Some(1.5),
+            },
+            TaskEstimate {
+                task: "Hist".to_string(),
+                memory: Some(3 * 1024 * 1024),
+                seconds: Some(0.25),
+            },
+        ];
+        assert_eq!(
+            get_estimates_table(&estimates),
+            "task\testimated memory (MB)\testimated runtime (s)\n\
+             load graph g.gfa\t2.0\t1.5\n\
+             Hist\t3.0\t0.2\n\
+             total\t3.0\t1.8\n"
+        );
+    }
+}
@@ -2,39 +2,110 @@ use core::panic;
 use std::iter::zip;
 use std::{
     collections::{HashMap, HashSet},
-    io::{BufWriter, Error, Write},
+    io::{BufReader, BufWriter, Error, ErrorKind, Write},
     str,
 };
 
 use abacus::{AbacusByTotal, GraphMask};
 use graph::GraphStorage;
+use index::bufreader_from_graph;
+use stable::StablePaths;
+use util::PathReader;
 
 use crate::{
-    analyses::InputRequirement as Req, analysis_parameter::Grouping,
-    io::bufreader_from_compressed_gfa, util::CountType,
+    analyses::InputRequirement as Req,
+    analysis_parameter::{Grouping, PanSN, TableFormat},
+    cache,
+    html_report::Phenotypes,
+    io::{
+        is_graph_index, is_multi_file_graph, open_file, parse_metadata, split_graph_files,
+        strip_segment_id_prefixes, InputProblems,
+    },
+    util::{CountType, ItemIdSize, StageMeter, StageUsage},
 };
 
 mod abacus;
+mod bubble;
 mod graph;
 mod hist;
+mod index;
+mod intervals;
+mod path_sets;
+mod spill;
+mod stable;
 mod util;
 
 pub use abacus::AbacusByGroup;
+pub use abacus::Draw;
 pub use abacus::GraphMaskParameters;
+pub use bubble::BubbleStats;
 pub use graph::Edge;
 pub use graph::ItemId;
 pub use graph::Orientation;
 pub use graph::PathSegment;
 pub use hist::Hist;
 pub use hist::ThresholdContainer;
+pub use index::write_graph_index;
+pub use intervals::{NodeInterval, PathNodeIntervals};
+pub use path_sets::{get_coord_files, set_path_sets_file};
+pub use spill::{set_spill_dir, SpillVec};
 
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone, Default, PartialEq)]
 pub struct GraphState {
     pub graph: String,
     pub name: Option<String>,
     pub subset: String,
     pub exclude: String,
+    // reference whose paths are excluded, given by the name of its sample or a regex matching it
+    pub exclude_reference: Option<String>,
     pub grouping: Option<Grouping>,
+    // naming scheme of paths/walks used when grouping by sample or haplotype
+    pub pansn: Option<PanSN>,
+    // groups with fewer paths are merged into a single group (or dropped)
+    pub min_group_size: usize,
+    pub drop_small_groups: bool,
+    // merge nodes with identical sequences
+    pub dedup: bool,
+    // place covered bp of duplicates on the strand of their representative
+    pub strand_dedup: bool,
+    // count every traversal of a countable by a path instead of only the first one
+    pub count_repeats: bool,
+    // only nodes of at least min_node_len and at most max_node_len bp are counted
+    pub min_node_len: Option<u32>,
+    pub max_node_len: Option<u32>,
+    // count edges traversed in opposite orientations as different countables
+    pub oriented_edges: bool,
+    // sample metadata table and the column by which report plots are colored
+    pub metadata: Option<String>,
+    pub color_by: Option<String>,
+}
+
+impl GraphState {
+    // whether the state equals the other one apart from the name of the run
+    fn is_renaming_of(&self, other: &Self) -> bool {
+        let unnamed = |state: &Self| Self {
+            name: None,
+            ..state.clone()
+        };
+        unnamed(self) == unnamed(other)
+    }
+
+    /// Files the state is loaded from, i.e., the graph files and the files of subset, exclude,
+    /// and grouping
+    pub fn get_input_files(&self) -> Vec<String> {
+        let mut files: Vec<String> = split_graph_files(&self.graph)
+            .into_iter()
+            .map(|x| x.to_string())
+            .collect();
+        // subset and exclude lists may also combine several files by set operations
+        for coord_text in [&self.subset, &self.exclude] {
+            files.extend(get_coord_files(coord_text));
+        }
+        if let Some(Grouping::Custom(file)) = &self.grouping {
+            files.push(file.to_string());
+        }
+        files
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -58,6 +129,8 @@ pub struct GraphBroker {
     _nice: bool,
     input_requirements: HashSet<Req>,
     count_type: CountType,
+    // wall-clock time (s) of each stage of loading the graph and constructing its abaci
+    stage_usage: Vec<StageUsage>,
 }
 
 impl GraphBroker {
@@ -77,6 +150,7 @@ impl GraphBroker {
             input_requirements: HashSet::new(),
             count_type: CountType::All,
             csc_abacus: false,
+            stage_usage: Vec::new(),
         }
     }
 
@@ -99,10 +173,30 @@ impl GraphBroker {
         input_requirements: &HashSet<Req>,
         nice: bool,
     ) -> Result<(), Error> {
-        if self.state.is_some() {
+        // a broker that was only loaded with cached histograms holds no graph to build on
+        if self.state.is_some() && self.graph_aux.is_some() {
             let prev_state = std::mem::take(&mut self.state).unwrap();
-            if prev_state.graph != state.graph {
-                *self = Self::from_gfa(input_requirements, nice);
+            // runs that differ only in their names share the loaded graph and its abaci
+            if self.input_requirements == *input_requirements && prev_state.is_renaming_of(&state) {
+                self.name = match &state.name {
+                    Some(name) => name.to_owned(),
+                    None => self.get_default_run_name(&state),
+                };
+                self.state = Some(state);
+                return Ok(());
+            }
+            if prev_state.graph != state.graph
+                || prev_state.dedup != state.dedup
+                || prev_state.strand_dedup != state.strand_dedup
+                || prev_state.oriented_edges != state.oriented_edges
+            {
+                let meter = StageMeter::start("parse graph");
+                *self = Self::from_gfa(input_requirements, nice, &state)?;
+                self.stage_usage.push(meter.finish());
+            } else if self.input_requirements != *input_requirements {
+                let meter = StageMeter::start("index edges and bubbles");
+                self.with_requirements(input_requirements)?;
+                self.stage_usage.push(meter.finish());
             }
             if prev_state.subset != state.subset {
                 self.include_coords(&state.subset);
@@ -113,14 +207,20 @@ impl GraphBroker {
             if prev_state.grouping != state.grouping {
                 self.with_group(&state.grouping);
             }
+            self.with_exclude_reference(&state.exclude_reference);
+            self.with_pansn(&state.pansn);
+            self.with_min_group_size(state.min_group_size, state.drop_small_groups);
+            self.with_count_repeats(state.count_repeats);
+            self.with_node_len_range(state.min_node_len, state.max_node_len);
             if let Some(name) = &state.name {
                 self.name = name.to_owned();
             } else {
                 self.name = self.get_default_run_name(&state);
             }
-            self.finish()?;
         } else {
-            *self = Self::from_gfa(input_requirements, nice);
+            let meter = StageMeter::start("parse graph");
+            *self = Self::from_gfa(input_requirements, nice, &state)?;
+            self.stage_usage.push(meter.finish());
             if !state.subset.is_empty() {
                 self.include_coords(&state.subset);
             }
@@ -130,15 +230,69 @@ impl GraphBroker {
             if state.grouping.is_some() {
                 self.with_group(&state.grouping);
             }
+            self.with_exclude_reference(&state.exclude_reference);
+            self.with_pansn(&state.pansn);
+            self.with_min_group_size(state.min_group_size, state.drop_small_groups);
+            self.with_count_repeats(state.count_repeats);
+            self.with_node_len_range(state.min_node_len, state.max_node_len);
             if let Some(name) = &state.name {
                 self.name = name.to_owned();
             } else {
                 self.name = self.get_default_run_name(&state);
             }
-            self.finish()?;
         }
+        // the state is set before the abaci are constructed, as it keys their cached results
         self.state = Some(state);
-        Ok(())
+        self.finish()
+    }
+
+    /// Sets the state without parsing the graph if the histograms of all countables of the
+    /// requirements are cached, such that only analyses that use nothing but the histograms can
+    /// be executed afterwards; returns whether this was the case
+    pub fn load_cached_hists(
+        &mut self,
+        state: GraphState,
+        input_requirements: &HashSet<Req>,
+    ) -> bool {
+        if input_requirements.iter().any(|r| {
+            !matches!(
+                r,
+                Req::Graph(_) | Req::Hist | Req::Node | Req::Bp | Req::Edge
+            )
+        }) {
+            return false;
+        }
+        let count_type = Self::get_count_type(input_requirements);
+        let count_types = match count_type {
+            CountType::All => vec![CountType::Node, CountType::Bp, CountType::Edge],
+            CountType::Bubble => return false,
+            count_type => vec![count_type],
+        };
+        let mut hists = HashMap::new();
+        for count in count_types {
+            let key = cache::get_key(&state, &["hist", &count.to_string()]);
+            match cache::get("hist", key) {
+                Some(hist) => hists.insert(count, hist),
+                None => return false,
+            };
+        }
+        log::info!(
+            "all histograms of graph {} are cached, skipping its parsing",
+            state.graph
+        );
+        *self = GraphBroker {
+            gfa_file: state.graph.clone(),
+            input_requirements: input_requirements.clone(),
+            count_type,
+            hists: Some(hists),
+            ..Self::new()
+        };
+        self.name = match &state.name {
+            Some(name) => name.to_owned(),
+            None => self.get_default_run_name(&state),
+        };
+        self.state = Some(state);
+        true
     }
 
     pub fn change_order(&mut self, order: Option<&str>) -> Result<(), Error> {
@@ -146,8 +300,8 @@ impl GraphBroker {
         self.finish()
     }
 
-    fn from_gfa(input_requirements: &HashSet<Req>, nice: bool) -> Self {
-        let count_type = if Self::contains_at_least_two(input_requirements) {
+    fn get_count_type(input_requirements: &HashSet<Req>) -> CountType {
+        if Self::contains_at_least_two(input_requirements) {
             CountType::All
         } else if input_requirements.contains(&Req::Node) {
             CountType::Node
@@ -155,9 +309,35 @@ impl GraphBroker {
             CountType::Bp
         } else if input_requirements.contains(&Req::Edge) {
             CountType::Edge
+        } else if input_requirements.contains(&Req::Bubble) {
+            CountType::Bubble
         } else {
             CountType::Node
-        };
+        }
+    }
+
+    // adapts the broker to the requirements of the next analyses on the same graph without parsing
+    // the graph again; only edges and bubbles are indexed if they were not needed before
+    fn with_requirements(&mut self, input_requirements: &HashSet<Req>) -> Result<(), Error> {
+        let count_type = Self::get_count_type(input_requirements);
+        let graph_aux = self.graph_aux.as_mut().unwrap();
+        if matches!(count_type, CountType::Edge | CountType::All) {
+            graph_aux.index_edges(&self.gfa_file)?;
+        }
+        if input_requirements.contains(&Req::Bubble) && graph_aux.bubbles.is_none() {
+            graph_aux.index_bubbles(&self.gfa_file)?;
+        }
+        self.input_requirements = input_requirements.clone();
+        self.count_type = count_type;
+        Ok(())
+    }
+
+    fn from_gfa(
+        input_requirements: &HashSet<Req>,
+        nice: bool,
+        state: &GraphState,
+    ) -> Result<Self, Error> {
+        let count_type = Self::get_count_type(input_requirements);
         let gfa_file = input_requirements
             .iter()
             .find(|v| matches!(v, Req::Graph(_)))
@@ -166,8 +346,23 @@ impl GraphBroker {
             Req::Graph(gfa_file) => gfa_file,
             _ => panic!("Requirements really need to contain gfa file"),
         };
-        let graph_aux = Some(GraphStorage::from_gfa(gfa_file, nice, count_type));
-        GraphBroker {
+        let mut graph_aux = if is_graph_index(gfa_file) {
+            GraphStorage::from_index(gfa_file)?
+        } else {
+            GraphStorage::from_gfa(gfa_file, nice, count_type)?
+        };
+        if input_requirements.contains(&Req::Bubble) {
+            graph_aux.index_bubbles(gfa_file)?;
+        }
+        if state.dedup || state.strand_dedup {
+            graph_aux.dedup_nodes(gfa_file, state.strand_dedup)?;
+        }
+        if state.strand_dedup && state.subset.is_empty() {
+            log::warn!("strand-aware deduplication only affects nodes that are partially covered by subset coordinates, counting duplicates like --dedup");
+        }
+        graph_aux.oriented_edges = state.oriented_edges;
+        let graph_aux = Some(graph_aux);
+        Ok(GraphBroker {
             state: None,
             name: "".to_string(),
             graph_aux,
@@ -182,19 +377,30 @@ impl GraphBroker {
             input_requirements: input_requirements.clone(),
             count_type,
             csc_abacus: false,
-        }
+            stage_usage: Vec::new(),
+        })
     }
 
     fn with_group(&mut self, grouping: &Option<Grouping>) {
+        // a previous grouping of the same graph must not linger
+        self.abacus_aux_params.groupby = String::new();
+        self.abacus_aux_params.groupby_haplotype = false;
+        self.abacus_aux_params.groupby_sample = false;
+        self.abacus_aux_params.groupby_regex = None;
         if let Some(grouping) = grouping {
             match grouping {
                 Grouping::Sample => self.with_sample_group(),
                 Grouping::Haplotype => self.with_haplo_group(),
                 Grouping::Custom(file_name) => self.with_custom_group(file_name),
+                Grouping::Regex { pattern, label } => self.with_regex_group(pattern, label),
             };
         }
     }
 
+    fn with_regex_group(&mut self, pattern: &str, label: &Option<String>) {
+        self.abacus_aux_params.groupby_regex = Some((pattern.to_owned(), label.clone()));
+    }
+
     fn with_custom_group(&mut self, file_name: &str) {
         self.abacus_aux_params.groupby = file_name.to_owned();
     }
@@ -207,6 +413,15 @@ impl GraphBroker {
         self.abacus_aux_params.groupby_sample = true;
     }
 
+    fn with_pansn(&mut self, pansn: &Option<PanSN>) {
+        self.abacus_aux_params.pansn = pansn.clone();
+    }
+
+    fn with_min_group_size(&mut self, min_group_size: usize, drop_small_groups: bool) {
+        self.abacus_aux_params.min_group_size = min_group_size;
+        self.abacus_aux_params.drop_small_groups = drop_small_groups;
+    }
+
     fn include_coords(&mut self, file_name: &str) {
         self.abacus_aux_params.positive_list = file_name.to_owned();
     }
@@ -215,6 +430,19 @@ impl GraphBroker {
         self.abacus_aux_params.negative_list = exclude.to_owned();
     }
 
+    fn with_exclude_reference(&mut self, exclude_reference: &Option<String>) {
+        self.abacus_aux_params.exclude_reference = exclude_reference.clone();
+    }
+
+    fn with_count_repeats(&mut self, count_repeats: bool) {
+        self.abacus_aux_params.count_repeats = count_repeats;
+    }
+
+    fn with_node_len_range(&mut self, min_node_len: Option<u32>, max_node_len: Option<u32>) {
+        self.abacus_aux_params.min_node_len = min_node_len;
+        self.abacus_aux_params.max_node_len = max_node_len;
+    }
+
     fn with_order(&mut self, file_name: Option<&str>) {
         self.abacus_aux_params.order = file_name.map(str::to_owned);
     }
@@ -225,10 +453,16 @@ impl GraphBroker {
     }
 
     fn finish(&mut self) -> Result<(), Error> {
+        let meter = StageMeter::start("graph mask");
         self.set_abacus_aux()?;
-        self.set_abaci_by_total();
+        self.stage_usage.push(meter.finish());
+        let meter = StageMeter::start("abaci by total");
+        self.set_abaci_by_total()?;
+        self.stage_usage.push(meter.finish());
         if self.input_requirements.contains(&Req::Hist) {
+            let meter = StageMeter::start("histograms");
             self.set_hists();
+            self.stage_usage.push(meter.finish());
         }
         let mut has_already_used_abacus = false;
         for req in self.input_requirements.clone() {
@@ -237,7 +471,9 @@ impl GraphBroker {
                     if has_already_used_abacus {
                         panic!("Panacus is currently not able to have multiple Abaci By Group for different countables. Please run panacus either multiple times or wait for the planned pipelining feature");
                     }
+                    let meter = StageMeter::start("abacus by group");
                     self.set_abacus_by_group(count)?;
+                    self.stage_usage.push(meter.finish());
                     has_already_used_abacus = true;
                 }
                 _ => continue,
@@ -246,10 +482,37 @@ impl GraphBroker {
         Ok(())
     }
 
+    /// Wall-clock time and peak memory of each stage of loading the graph and constructing its
+    /// abaci since the last call
+    pub fn take_stage_usage(&mut self) -> Vec<StageUsage> {
+        std::mem::take(&mut self.stage_usage)
+    }
+
     pub fn get_run_name(&self) -> String {
         self.name.to_owned()
     }
 
+    /// Comment line of output tables that documents how duplicated nodes are counted, if they
+    /// are merged
+    pub fn get_dedup_comment(&self) -> Option<String> {
+        match &self.state {
+            Some(state) if state.strand_dedup => Some(
+                "# dedup: strand-aware; nodes with identical or reverse-complementary identical \
+                 sequences are counted once, and bp of nodes partially covered by subset \
+                 coordinates that are covered on the reverse strand of a node or of its \
+                 duplicates count as the same bp of the forward strand, which for palindromic \
+                 nodes are the same in both orientations\n"
+                    .to_string(),
+            ),
+            Some(state) if state.dedup => Some(
+                "# dedup: nodes with identical or reverse-complementary identical sequences are \
+                 counted once\n"
+                    .to_string(),
+            ),
+            _ => None,
+        }
+    }
+
     pub fn get_run_id(&self) -> String {
         self.name
             .to_lowercase()
@@ -301,6 +564,213 @@ impl GraphBroker {
         self.abacus_aux.as_ref().unwrap().count_groups()
     }
 
+    /// Node sequences of all paths/walks with the identifier of the given path (ignoring
+    /// coordinates), e.g., all fragments of a reference chromosome
+    pub fn get_path_steps(
+        &self,
+        path: &PathSegment,
+    ) -> Result<Vec<(PathSegment, Vec<(ItemId, Orientation)>)>, Error> {
+        let path = path.clear_coords();
+        let mut res = Vec::new();
+        self.visit_path_steps(
+            |path_seg| path_seg.clear_coords() == path,
+            |path_seg, steps| res.push((path_seg, steps)),
+        )?;
+        if res.is_empty() {
+            // the path may name a stable sequence of an rGFA graph
+            let stable = &self.graph_aux.as_ref().unwrap().stable_coords;
+            res = stable.get_steps(&path.id(), self.get_node_lens());
+        }
+        Ok(res)
+    }
+
+    // paths/walks that spell out (fragments of) the stable sequences of an rGFA graph
+    fn find_stable_paths(&self) -> Result<StablePaths, Error> {
+        let mut res = StablePaths::new();
+        // stable coordinates are collected while parsing the graph, such that graphs without them
+        // are not read again
+        let stable = &self.graph_aux.as_ref().unwrap().stable_coords;
+        if stable.is_empty() {
+            return Ok(res);
+        }
+        let node_lens = self.get_node_lens();
+        self.visit_path_steps(
+            |_| true,
+            |path_seg, steps| {
+                if let Some((seq, start, end)) = stable.get_stable_range(&steps, node_lens) {
+                    res.entry(seq.to_string())
+                        .or_default()
+                        .push((path_seg, start, end));
+                }
+            },
+        )?;
+        log::info!(
+            "found paths/walks spelling out {} of {} stable sequences",
+            res.len(),
+            stable.get_names().len()
+        );
+        Ok(res)
+    }
+
+    /// Streams the node sequences of all paths/walks accepted by the filter to the visitor, in
+    /// the order in which they appear in the graph file
+    pub fn visit_path_steps<F, V>(&self, filter: F, mut visit: V) -> Result<(), Error>
+    where
+        F: Fn(&PathSegment) -> bool,
+        V: FnMut(PathSegment, Vec<(ItemId, Orientation)>),
+    {
+        let graph_storage = self.graph_aux.as_ref().unwrap();
+        let mut problems = InputProblems::new(&self.gfa_file);
+
+        let mut data = bufreader_from_graph(&self.gfa_file, graph_storage)?;
+        let mut paths = PathReader::new(&mut data, graph_storage);
+        while let Some((line, record)) = paths.next_path()? {
+            match record {
+                Ok((path_seg, steps)) if filter(&path_seg) => {
+                    match steps.to_item_vec(graph_storage) {
+                        Ok(items) => visit(path_seg, items),
+                        Err(msg) => problems.push(line, steps.col(), msg),
+                    }
+                }
+                Ok(_) => (),
+                Err((col, msg)) => problems.push(line, col, msg),
+            }
+        }
+        problems.into_result()
+    }
+
+    /// Visits the nodes traversed by each path/walk as they are counted, i.e., restricted to the
+    /// subset coordinates and leaving out excluded nodes; paths/walks are visited grouped, and
+    /// duplicated nodes are given by their representative if nodes are deduplicated
+    pub fn visit_counted_path_nodes<V>(&self, visit: V) -> Result<(), Error>
+    where
+        V: FnMut(&PathSegment, &[ItemIdSize]),
+    {
+        let graph_storage = self.graph_aux.as_ref().unwrap();
+        let mut data = bufreader_from_graph(&self.gfa_file, graph_storage)?;
+        AbacusByTotal::visit_counted_nodes(
+            &mut data,
+            &self.gfa_file,
+            self.abacus_aux.as_ref().unwrap(),
+            graph_storage,
+            visit,
+        )
+    }
+
+    /// Iterates over the nodes visited by all paths/walks, each given as (path, node, orientation,
+    /// start, end) in the coordinates of the path/walk, such that per-node statistics can be
+    /// computed without parsing the graph file again
+    pub fn get_path_node_intervals(&self) -> Result<PathNodeIntervals<'_>, Error> {
+        PathNodeIntervals::new(
+            &self.gfa_file,
+            self.graph_aux.as_ref().unwrap(),
+            self.get_node_lens(),
+        )
+    }
+
+    /// Groups of nodes with identical or reverse-complementary identical sequences
+    pub fn get_duplicate_nodes(&self) -> Result<Vec<Vec<(ItemId, Orientation)>>, Error> {
+        self.graph_aux
+            .as_ref()
+            .unwrap()
+            .find_duplicate_nodes(&self.gfa_file)
+    }
+
+    /// Sequences of the given nodes, reading them from the graph file
+    pub fn get_node_sequences(
+        &self,
+        nodes: &HashSet<ItemId>,
+    ) -> Result<HashMap<ItemId, Vec<u8>>, Error> {
+        self.graph_aux
+            .as_ref()
+            .unwrap()
+            .get_node_sequences(&self.gfa_file, nodes)
+    }
+
+    /// Visits the sequence of each node that has one, reading them from the graph file
+    pub fn visit_node_sequences<F: FnMut(ItemId, &[u8])>(&self, visitor: F) -> Result<(), Error> {
+        self.graph_aux
+            .as_ref()
+            .unwrap()
+            .visit_node_sequences(&self.gfa_file, visitor)
+    }
+
+    /// Number of A, C, G, T, and other bases of each node's sequence
+    pub fn get_node_bases(&self) -> Result<Vec<[u32; 5]>, Error> {
+        self.graph_aux
+            .as_ref()
+            .unwrap()
+            .count_node_bases(&self.gfa_file)
+    }
+
+    /// Phenotypes of the given groups in the column of the run's sample metadata by which plots
+    /// are colored (by default, the first column after the sample/group identifiers); groups are
+    /// matched by their name or, failing that, by their sample
+    pub fn get_phenotypes(&self, groups: &[String]) -> Result<Option<Phenotypes>, Error> {
+        let state = match &self.state {
+            Some(state) => state,
+            None => return Ok(None),
+        };
+        let file = match &state.metadata {
+            Some(file) => file,
+            None => return Ok(None),
+        };
+        let mut data = BufReader::new(open_file(file)?);
+        let (header, rows) = parse_metadata(&mut data, file)?;
+        let column = match &state.color_by {
+            Some(c) => header.iter().skip(1).position(|h| h == c).map(|i| i + 1),
+            None => Some(1),
+        }
+        .ok_or_else(|| {
+            Error::new(
+                ErrorKind::InvalidData,
+                format!(
+                    "metadata file {} has no column {}",
+                    file,
+                    state.color_by.as_deref().unwrap_or_default()
+                ),
+            )
+        })?;
+        let id2value: HashMap<&str, &str> = rows
+            .iter()
+            .map(|row| (row[0].as_str(), row[column].as_str()))
+            .collect();
+        let values = groups
+            .iter()
+            .map(|g| {
+                id2value
+                    .get(g.as_str())
+                    .or_else(|| id2value.get(g.split('#').next().unwrap_or_default()))
+                    .map(|v| v.to_string())
+                    .unwrap_or_else(|| "NA".to_string())
+            })
+            .collect();
+        Ok(Some(Phenotypes {
+            column: header[column].clone(),
+            values,
+        }))
+    }
+
+    /// Names of the nodes as given in the GFA file, indexed by node ID
+    pub fn get_node_names(&self) -> Vec<String> {
+        let res = self.get_gfa_node_names();
+        if is_multi_file_graph(&self.gfa_file) {
+            strip_segment_id_prefixes(res)
+        } else {
+            res
+        }
+    }
+
+    /// Names of the nodes as read from the graph, indexed by node ID, i.e., prefixed by the index
+    /// of their file if the graph consists of several files
+    pub fn get_gfa_node_names(&self) -> Vec<String> {
+        let mut res = vec![String::new(); self.get_node_count() + 1];
+        for (name, id) in self.graph_aux.as_ref().unwrap().get_node_tuples() {
+            res[id.0 as usize] = String::from_utf8_lossy(&name).into_owned();
+        }
+        res
+    }
+
     pub fn get_fname(&self) -> String {
         self.gfa_file.to_string()
     }
@@ -310,6 +780,26 @@ impl GraphBroker {
         &self.abacus_aux.as_ref().unwrap().groups
     }
 
+    /// Whether paths/walks are grouped by sample, haplotype, or a grouping file, rather than
+    /// each forming its own group
+    pub fn has_grouping(&self) -> bool {
+        self.state.as_ref().map_or(false, |s| s.grouping.is_some())
+    }
+
+    /// Naming scheme of paths/walks used when grouping by sample or haplotype
+    pub fn get_pansn(&self) -> PanSN {
+        self.state
+            .as_ref()
+            .and_then(|s| s.pansn.clone())
+            .unwrap_or_default()
+    }
+
+    /// Whether all paths of the graph are given by W lines
+    pub fn has_walks_only(&self) -> bool {
+        let graph_aux = self.graph_aux.as_ref().unwrap();
+        graph_aux.walk_count > 0 && graph_aux.walk_count == graph_aux.path_segments.len()
+    }
+
     pub fn get_path_lens(&self) -> &HashMap<PathSegment, (u32, u32)> {
         Self::check_and_error(self.path_lens.as_ref(), "path_lens");
         self.path_lens.as_ref().unwrap()
@@ -333,16 +823,27 @@ impl GraphBroker {
     pub fn write_abacus_by_group<W: Write>(
         &self,
         total: bool,
+        format: TableFormat,
         out: &mut BufWriter<W>,
     ) -> Result<(), Error> {
         Self::check_and_error(self.group_abacus.as_ref(), "abacus_by_group");
-        self.group_abacus
-            .as_ref()
-            .unwrap()
-            .to_tsv(total, out, self.graph_aux.as_ref().unwrap())
+        let abacus = self.group_abacus.as_ref().unwrap();
+        let graph_storage = self.graph_aux.as_ref().unwrap();
+        match format {
+            TableFormat::Tsv => abacus.to_tsv(total, out, graph_storage),
+            TableFormat::Mtx => abacus.to_mtx(total, out, graph_storage),
+            TableFormat::Triplets => abacus.to_triplets(total, out, graph_storage),
+        }
     }
 
     fn set_abacus_aux(&mut self) -> Result<(), Error> {
+        // subset and exclude coordinates may refer to stable sequences of rGFA graphs
+        let has_coords = !self.abacus_aux_params.positive_list.is_empty()
+            || !self.abacus_aux_params.negative_list.is_empty();
+        if has_coords && self.graph_aux.as_ref().unwrap().stable_paths.is_none() {
+            let stable_paths = self.find_stable_paths()?;
+            self.graph_aux.as_mut().unwrap().stable_paths = Some(stable_paths);
+        }
         self.abacus_aux = Some(GraphMask::from_datamgr(
             &self.abacus_aux_params,
             self.graph_aux.as_ref().unwrap(),
@@ -353,14 +854,23 @@ impl GraphBroker {
     fn set_hists(&mut self) {
         let mut hists = HashMap::new();
         for (k, v) in self.total_abaci.as_ref().unwrap() {
+            let key = self.get_cache_key(&["hist", &k.to_string()]);
             hists.insert(
                 *k,
-                Hist::from_abacus(v, Some(self.graph_aux.as_ref().unwrap())),
+                cache::get_or_compute("hist", key, || {
+                    Hist::from_abacus(v, Some(self.graph_aux.as_ref().unwrap()))
+                }),
             );
         }
         self.hists = Some(hists);
     }
 
+    /// Key under which results computed on the current graph state are cached, see
+    /// [cache::get_key]; None if caching is disabled
+    pub fn get_cache_key(&self, parts: &[&str]) -> Option<String> {
+        cache::get_key(self.state.as_ref()?, parts)
+    }
+
     fn check_and_error<T>(value: Option<T>, type_of_value: &str) {
         if value.is_none() {
             let msg = format!(
@@ -373,9 +883,10 @@ impl GraphBroker {
 
     fn set_abacus_by_group(&mut self, count: CountType) -> Result<(), Error> {
         // let mut abaci_by_group = HashMap::new();
-        let mut data = bufreader_from_compressed_gfa(&self.gfa_file);
+        let mut data = bufreader_from_graph(&self.gfa_file, self.graph_aux.as_ref().unwrap())?;
         let abacus = AbacusByGroup::from_gfa(
             &mut data,
+            &self.gfa_file,
             self.abacus_aux.as_ref().unwrap(),
             self.graph_aux.as_ref().unwrap(),
             count,
@@ -386,48 +897,175 @@ impl GraphBroker {
         Ok(())
     }
 
-    fn set_abaci_by_total(&mut self) {
-        let count_types_not_edge = if self.count_type == CountType::All {
-            vec![CountType::Node, CountType::Bp]
-        } else if self.count_type != CountType::Edge {
-            vec![self.count_type.clone()]
-        } else {
-            Vec::new()
+    fn set_abaci_by_total(&mut self) -> Result<(), Error> {
+        // node, bp, and edge abaci are computed from a single pass over the paths/walks
+        let count_types = match self.count_type {
+            // nothing is counted for analyses that require only the graph, such as the listing of
+            // paths/walks and their groups
+            _ if self
+                .input_requirements
+                .iter()
+                .all(|r| matches!(r, Req::Graph(_))) =>
+            {
+                Vec::new()
+            }
+            CountType::All => vec![CountType::Node, CountType::Bp, CountType::Edge],
+            CountType::Bubble => Vec::new(),
+            count_type => vec![count_type],
         };
-        let shall_calculate_edge =
-            self.count_type == CountType::All || self.count_type == CountType::Edge;
-        log::info!(
-            "calculating abaci for count_types: {:?}, and edge: {}",
-            count_types_not_edge,
-            shall_calculate_edge
-        );
-        let mut data = bufreader_from_compressed_gfa(&self.gfa_file);
-        let mut abaci = if !count_types_not_edge.is_empty() {
+        log::info!("calculating abaci for count_types: {:?}", count_types);
+        let mut abaci = if !count_types.is_empty() {
+            let mut data = bufreader_from_graph(&self.gfa_file, self.graph_aux.as_ref().unwrap())?;
             let (abaci, path_lens) = AbacusByTotal::from_gfa_multiple(
                 &mut data,
+                &self.gfa_file,
                 self.abacus_aux.as_ref().unwrap(),
                 self.graph_aux.as_ref().unwrap(),
-                &count_types_not_edge,
-            );
-            let abaci: HashMap<CountType, AbacusByTotal> =
-                zip(count_types_not_edge, abaci).collect();
-            if self.input_requirements.contains(&Req::PathLens) {
+                &count_types,
+            )?;
+            // path lengths are only determined by parsing node or bp counts
+            if self.input_requirements.contains(&Req::PathLens)
+                && self.count_type != CountType::Edge
+            {
                 self.path_lens = Some(path_lens);
             }
-            abaci
+            zip(count_types, abaci).collect()
         } else {
             HashMap::new()
         };
-        if shall_calculate_edge {
-            let mut data = bufreader_from_compressed_gfa(&self.gfa_file);
-            let (mut edge_abacus, _) = AbacusByTotal::from_gfa_multiple(
+        if self.input_requirements.contains(&Req::Bubble) {
+            let mut data = bufreader_from_graph(&self.gfa_file, self.graph_aux.as_ref().unwrap())?;
+            let (mut bubble_abacus, _) = AbacusByTotal::from_gfa_multiple(
                 &mut data,
+                &self.gfa_file,
                 self.abacus_aux.as_ref().unwrap(),
                 self.graph_aux.as_ref().unwrap(),
-                &vec![CountType::Edge],
-            );
-            abaci.insert(CountType::Edge, edge_abacus.pop().unwrap());
+                &vec![CountType::Bubble],
+            )?;
+            abaci.insert(CountType::Bubble, bubble_abacus.pop().unwrap());
         }
         self.total_abaci = Some(abaci);
+        Ok(())
+    }
+}
+
+/// Paths/walks of the graph (or of its index) in the order of the file, without their steps
+pub fn read_path_segments(gfa_file: &str) -> Result<Vec<PathSegment>, Error> {
+    let graph_storage = if is_graph_index(gfa_file) {
+        GraphStorage::from_index(gfa_file)?
+    } else {
+        GraphStorage::from_gfa(gfa_file, false, CountType::Node)?
+    };
+    Ok(graph_storage.path_segments)
+}
+
+/// Group of each of the paths/walks, given by a tab-separated two-column file, or by their
+/// sample or haplotype; paths/walks without group form groups of their own
+pub fn get_path_groups(
+    path_segments: &[PathSegment],
+    groupby: &str,
+    groupby_haplotype: bool,
+    groupby_sample: bool,
+) -> Result<HashMap<PathSegment, String>, Error> {
+    GraphMask::load_groups(groupby, groupby_haplotype, groupby_sample, path_segments)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_get_input_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = |name: &str| {
+            let path = dir.path().join(name).to_str().unwrap().to_string();
+            std::fs::write(&path, "a\n").unwrap();
+            path
+        };
+        let (a, b, c, groups) = (file("a.txt"), file("b.txt"), file("c.txt"), file("g.tsv"));
+        let state = GraphState {
+            graph: "x.gfa,y.gfa".to_string(),
+            subset: format!("{}+{}", a, b),
+            exclude: c.clone(),
+            grouping: Some(Grouping::Custom(groups.clone())),
+            ..GraphState::default()
+        };
+        assert_eq!(
+            state.get_input_files(),
+            vec!["x.gfa".to_string(), "y.gfa".to_string(), a, b, c, groups]
+        );
+    }
+
+    #[test]
+    fn test_get_path_node_intervals() {
+        let dir = tempfile::tempdir().unwrap();
+        let graph = dir.path().join("g.gfa").to_str().unwrap().to_string();
+        std::fs::write(
+            &graph,
+            "S\t1\tACGT\nS\t2\tAC\nL\t1\t+\t2\t-\t0M\nP\tx\t1+,2-\t*\nW\ty\t1\tchr1\t10\t12\t<2\n",
+        )
+        .unwrap();
+        let mut gb = GraphBroker::new();
+        gb.change_graph_state(
+            GraphState {
+                graph: graph.clone(),
+                ..GraphState::default()
+            },
+            &HashSet::from([Req::Graph(graph), Req::Node]),
+            false,
+        )
+        .unwrap();
+        let intervals: Vec<NodeInterval> = gb
+            .get_path_node_intervals()
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        let (f, b) = (Orientation::Forward, Orientation::Backward);
+        let (n1, n2) = (ItemId(1), ItemId(2));
+        assert_eq!(
+            intervals
+                .iter()
+                .map(|(p, n, o, start, end)| (p.id(), *n, *o, *start, *end))
+                .collect::<Vec<_>>(),
+            vec![
+                ("x".to_string(), n1, f, 0, 4),
+                ("x".to_string(), n2, b, 4, 6),
+                ("y#1#chr1".to_string(), n2, b, 10, 12),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_abaci_of_one_pass_equal_separate_ones() {
+        let graph = "tests/test_files/t_groups.gfa".to_string();
+        let get_broker = |reqs: &[Req]| {
+            let mut reqs: HashSet<Req> = reqs.iter().cloned().collect();
+            reqs.insert(Req::Graph(graph.clone()));
+            let mut gb = GraphBroker::new();
+            gb.change_graph_state(
+                GraphState {
+                    graph: graph.clone(),
+                    ..GraphState::default()
+                },
+                &reqs,
+                false,
+            )
+            .unwrap();
+            gb
+        };
+        let all = get_broker(&[Req::Node, Req::Bp, Req::Edge]);
+        for (count, req) in [
+            (CountType::Node, Req::Node),
+            (CountType::Bp, Req::Bp),
+            (CountType::Edge, Req::Edge),
+        ] {
+            let single = get_broker(&[req]);
+            assert_eq!(
+                all.get_abacus_by_total(count).countable,
+                single.get_abacus_by_total(count).countable,
+                "{}",
+                count
+            );
+        }
     }
 }
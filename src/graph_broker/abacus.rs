@@ -1,5 +1,4 @@
 /* standard use */
-use std::fs;
 use std::io::{BufReader, BufWriter, Write};
 use std::io::{Error, ErrorKind};
 use std::iter::FromIterator;
@@ -9,25 +8,49 @@ use std::path::Path;
 
 /* external crate*/
 use itertools::Itertools;
+use rand::seq::SliceRandom;
+use rayon::prelude::*;
 use regex::Regex;
-use std::collections::{HashMap, HashSet, VecDeque};
+use std::collections::{HashMap, HashSet};
 
-use crate::graph_broker::graph::{Edge, ItemId, Orientation};
+use crate::graph_broker::graph::ItemId;
 /* private use */
+use crate::analysis_parameter::PanSN;
 use crate::io::*;
 use crate::util::*;
 
 use super::graph::{GraphStorage, PathSegment};
+use super::path_sets::{evaluate_set_expression, parse_set_expression};
+use super::spill::SpillVec;
+use super::stable::resolve_stable_coords;
 use super::util::{parse_gfa_paths_walks, parse_gfa_paths_walks_multiple};
 
+// name of the pseudo-group into which groups with too few paths are merged
+const SMALL_GROUPS_NAME: &str = "other";
+
 #[derive(Debug, PartialEq, Eq, Clone)]
 pub struct GraphMaskParameters {
     pub positive_list: String,
     pub negative_list: String,
+    // reference whose paths are excluded, given by the name of its sample or a regex matching it
+    pub exclude_reference: Option<String>,
     pub groupby: String,
     pub groupby_sample: bool,
     pub groupby_haplotype: bool,
+    // regex whose captures on the names of paths/walks give their groups, and the label template
+    // putting the captures together
+    pub groupby_regex: Option<(String, Option<String>)>,
     pub order: Option<String>,
+    // naming scheme of paths/walks used when grouping by sample or haplotype
+    pub pansn: Option<PanSN>,
+    // groups with fewer paths are merged into a single group, or dropped altogether
+    pub min_group_size: usize,
+    pub drop_small_groups: bool,
+    // count each traversal of a countable by a path instead of only its first one
+    pub count_repeats: bool,
+    // only nodes of at least min_node_len and at most max_node_len bp are counted
+    pub min_node_len: Option<u32>,
+    pub max_node_len: Option<u32>,
 }
 
 impl GraphMaskParameters {
@@ -35,20 +58,60 @@ impl GraphMaskParameters {
         Self {
             positive_list: "".to_owned(),
             negative_list: "".to_owned(),
+            exclude_reference: None,
             groupby: "".to_owned(),
             groupby_sample: false,
             groupby_haplotype: false,
+            groupby_regex: None,
             order: None,
+            pansn: None,
+            min_group_size: 0,
+            drop_small_groups: false,
+            count_repeats: false,
+            min_node_len: None,
+            max_node_len: None,
         }
     }
 }
 
+// splits a label template such as `{pop}_{hap}` into pieces of text, each followed by the name
+// of a capture (if any)
+fn parse_label_template<'a>(
+    label: &'a str,
+    names: &[&'a str],
+) -> Result<Vec<(&'a str, Option<&'a str>)>, String> {
+    let mut parts = Vec::new();
+    let mut rest = label;
+    while let Some(start) = rest.find('{') {
+        let end = rest[start..]
+            .find('}')
+            .map(|end| start + end)
+            .ok_or_else(|| format!("group label {} has an unterminated capture", label))?;
+        let name = &rest[start + 1..end];
+        let name = names.iter().find(|n| **n == name).ok_or_else(|| {
+            format!(
+                "group label {} refers to capture {}, which is not among the named captures of the grouping regex: {}",
+                label,
+                name,
+                names.join(", ")
+            )
+        })?;
+        parts.push((&rest[..start], Some(*name)));
+        rest = &rest[end + 1..];
+    }
+    parts.push((rest, None));
+    Ok(parts)
+}
+
 #[derive(Debug, Clone)]
 pub struct GraphMask {
     pub groups: HashMap<PathSegment, String>,
     pub include_coords: Option<Vec<PathSegment>>,
     pub exclude_coords: Option<Vec<PathSegment>>,
     pub order: Option<Vec<PathSegment>>,
+    pub count_repeats: bool,
+    pub min_node_len: Option<u32>,
+    pub max_node_len: Option<u32>,
 }
 
 impl GraphMask {
@@ -56,25 +119,58 @@ impl GraphMask {
         params: &GraphMaskParameters,
         graph_storage: &GraphStorage,
     ) -> Result<Self, Error> {
-        let groups = GraphMask::load_groups(
-            &params.groupby,
-            params.groupby_haplotype,
-            params.groupby_sample,
-            graph_storage,
-        )?;
+        let groups = match (&params.groupby_regex, &params.pansn) {
+            (Some((pattern, label)), _) => {
+                GraphMask::load_regex_groups(pattern, label.as_deref(), graph_storage)?
+            }
+            (None, Some(pansn)) if params.groupby_haplotype || params.groupby_sample => {
+                GraphMask::load_pansn_groups(pansn, params.groupby_haplotype, graph_storage)
+            }
+            _ => GraphMask::load_groups(
+                &params.groupby,
+                params.groupby_haplotype,
+                params.groupby_sample,
+                &graph_storage.path_segments,
+            )?,
+        };
+        let (groups, small_groups) =
+            GraphMask::merge_small_groups(groups, params.min_group_size, params.drop_small_groups)?;
         let paths = &graph_storage.path_segments;
-        let include_coords = GraphMask::complement_with_group_assignments(
-            GraphMask::load_coord_list(&params.positive_list, paths)?,
+        let mut include_coords = GraphMask::complement_with_group_assignments(
+            GraphMask::load_stable_coord_list(&params.positive_list, graph_storage)?,
             &groups,
         )?;
-        let exclude_coords = GraphMask::complement_with_group_assignments(
-            GraphMask::load_coord_list(&params.negative_list, paths)?,
+        if !small_groups.is_empty() {
+            // dropped groups are removed from the subset, which otherwise comprises all paths
+            let is_kept = |p: &PathSegment| match groups.get(&p.clear_coords()) {
+                Some(g) => !small_groups.contains(g),
+                None => true,
+            };
+            include_coords = Some(match include_coords {
+                Some(coords) => coords.into_iter().filter(is_kept).collect(),
+                None => paths
+                    .iter()
+                    .map(|p| p.clear_coords())
+                    .filter(is_kept)
+                    .collect(),
+            });
+        }
+        let mut exclude_coords = GraphMask::complement_with_group_assignments(
+            GraphMask::load_stable_coord_list(&params.negative_list, graph_storage)?,
             &groups,
         )?;
+        if let Some(reference) = &params.exclude_reference {
+            exclude_coords = Some(GraphMask::add_reference_paths(
+                exclude_coords.unwrap_or_default(),
+                reference,
+                params.pansn.as_ref(),
+                paths,
+            )?);
+        }
 
         let order = if let Some(order) = &params.order {
             let maybe_order = GraphMask::complement_with_group_assignments(
-                GraphMask::load_coord_list_file(order)?, // It does not make sense to
+                GraphMask::load_order_file(order, paths, &groups)?, // It does not make sense to
                 // specify order with a regex
                 &groups,
             )?;
@@ -114,15 +210,25 @@ impl GraphMask {
 
                 // check that groups are not scrambled in include
                 let mut visited: HashSet<&str> = HashSet::new();
-                let mut cur: &str = groups.get(&o[0]).unwrap();
+                let mut cur: Option<&str> = None;
                 for p in o.iter() {
-                    let g: &str = groups.get(p).unwrap();
-                    if cur != g && !visited.insert(g) {
+                    let g: &str = match groups.get(&p.clear_coords()) {
+                        Some(g) => g,
+                        None => {
+                            let msg = format!(
+                                "order list {} contains path {}, which is not part of the graph",
+                                order, p
+                            );
+                            log::error!("{}", &msg);
+                            return Err(Error::new(ErrorKind::InvalidData, msg));
+                        }
+                    };
+                    if cur.is_some() && cur != Some(g) && !visited.insert(g) {
                         let msg = format!("order of paths contains fragmented groups: path {} belongs to group that is interspersed by one or more other groups", p);
                         log::error!("{}", &msg);
                         return Err(Error::new(ErrorKind::InvalidData, msg));
                     }
-                    cur = g;
+                    cur = Some(g);
                 }
             }
             maybe_order
@@ -146,9 +252,52 @@ impl GraphMask {
             include_coords,
             exclude_coords,
             order,
+            count_repeats: params.count_repeats,
+            min_node_len: params.min_node_len,
+            max_node_len: params.max_node_len,
         })
     }
 
+    // whether nodes of the given length are counted
+    pub fn is_node_len_counted(&self, len: u32) -> bool {
+        self.min_node_len.map_or(true, |min| len >= min)
+            && self.max_node_len.map_or(true, |max| len <= max)
+    }
+
+    // marks nodes whose length is outside the range of counted node lengths as excluded, such
+    // that they are left out of node and bp counts like nodes of excluded path coordinates
+    pub fn exclude_by_node_len(
+        &self,
+        exclude_table: &mut Option<ActiveTable>,
+        graph_storage: &GraphStorage,
+        count: CountType,
+    ) {
+        if self.min_node_len.is_none() && self.max_node_len.is_none() {
+            return;
+        }
+        if !matches!(count, CountType::Node | CountType::Bp) {
+            log::warn!(
+                "node length range only applies to node and bp counts, not to {}",
+                count
+            );
+            return;
+        }
+        let exclude_table = exclude_table.get_or_insert_with(|| {
+            ActiveTable::new(graph_storage.number_of_items(&count) + 1, false)
+        });
+        let mut excluded = 0;
+        for (i, len) in graph_storage.node_lens.iter().enumerate().skip(1) {
+            if !self.is_node_len_counted(*len) {
+                exclude_table.activate(&ItemId(i as ItemIdSize));
+                excluded += 1;
+            }
+        }
+        log::info!(
+            "excluded {} nodes outside of the range of counted node lengths",
+            excluded
+        );
+    }
+
     pub fn complement_with_group_assignments(
         coords: Option<Vec<PathSegment>>,
         groups: &HashMap<PathSegment, String>,
@@ -202,13 +351,132 @@ impl GraphMask {
 
     pub fn load_coord_list_file(file_name: &str) -> Result<Option<Vec<PathSegment>>, Error> {
         log::info!("loading coordinates from {}", file_name);
-        let mut data = BufReader::new(fs::File::open(file_name)?);
+        let mut data = BufReader::new(open_file(file_name)?);
         let use_block_info = true;
-        let coords = parse_bed_to_path_segments(&mut data, use_block_info);
+        let coords = parse_bed_to_path_segments(&mut data, file_name, use_block_info)?;
         log::debug!("loaded {} coordinates", coords.len());
         Ok(Some(coords))
     }
 
+    pub fn load_order_file(
+        file_name: &str,
+        paths: &[PathSegment],
+        groups: &HashMap<PathSegment, String>,
+    ) -> Result<Option<Vec<PathSegment>>, Error> {
+        if !is_newick_file(file_name)? {
+            return Self::load_coord_list_file(file_name);
+        }
+        log::info!("loading order from leaves of Newick tree {}", file_name);
+        let mut data = BufReader::new(open_file(file_name)?);
+        let leaves = parse_newick_leaves(&mut data, file_name)?;
+
+        let group_names: HashSet<&str> = groups.values().map(|g| g.as_str()).collect();
+        let path_ids: HashSet<PathSegment> = groups.keys().map(|p| p.clear_coords()).collect();
+        let mut order: Vec<PathSegment> = Vec::new();
+        let mut covered: HashSet<&str> = HashSet::new();
+        for leaf in leaves.iter() {
+            let p = PathSegment::from_str(leaf);
+            if group_names.contains(leaf.as_str()) || path_ids.contains(&p) {
+                // leaf names a group or a path
+                if let Some(g) = groups.get(&p) {
+                    covered.insert(g);
+                } else {
+                    covered.insert(*group_names.get(leaf.as_str()).unwrap());
+                }
+                order.push(p);
+            } else {
+                // leaf names a sample, take all of its paths in graph order
+                let sample_paths: Vec<PathSegment> = paths
+                    .iter()
+                    .filter(|p| &p.sample == leaf)
+                    .map(|p| p.clear_coords())
+                    .unique()
+                    .collect();
+                if sample_paths.is_empty() {
+                    log::warn!(
+                        "taxon {} of tree {} is not part of the graph, ignoring it",
+                        leaf,
+                        file_name
+                    );
+                }
+                for p in sample_paths {
+                    if let Some(g) = groups.get(&p) {
+                        covered.insert(g);
+                    }
+                    order.push(p);
+                }
+            }
+        }
+
+        // groups not represented in the tree are appended in the order of the graph
+        let missing: Vec<&str> = paths
+            .iter()
+            .filter_map(|p| groups.get(&p.clear_coords()).map(|g| g.as_str()))
+            .unique()
+            .filter(|g| !covered.contains(g))
+            .collect();
+        if !missing.is_empty() {
+            log::warn!(
+                "tree {} does not contain {} path(s)/group(s), appending them in graph order: {}",
+                file_name,
+                missing.len(),
+                missing.join(", ")
+            );
+            order.extend(missing.into_iter().map(PathSegment::from_str));
+        }
+        log::debug!("derived order of {} paths/groups from tree", order.len());
+        Ok(Some(order))
+    }
+
+    // loads a coordinate list that may also refer to the stable sequences of an rGFA graph, which
+    // are mapped onto the paths/walks spelling them out
+    fn load_stable_coord_list(
+        coord_text: &str,
+        graph_storage: &GraphStorage,
+    ) -> Result<Option<Vec<PathSegment>>, Error> {
+        let paths = &graph_storage.path_segments;
+        match &graph_storage.stable_paths {
+            Some(stable_paths) if !stable_paths.is_empty() => {
+                let mut names = paths.clone();
+                names.extend(
+                    stable_paths
+                        .keys()
+                        .map(|s| PathSegment::from_str(s))
+                        .filter(|s| !paths.iter().any(|p| p.clear_coords() == *s)),
+                );
+                Ok(Self::load_coord_list(coord_text, &names)?
+                    .map(|coords| resolve_stable_coords(coords, paths, stable_paths)))
+            }
+            _ => Self::load_coord_list(coord_text, paths),
+        }
+    }
+
+    // interval of a path of the graph given as path:start-end, e.g.,
+    // HG00438#1#chr1:1,000,000-2,000,000
+    fn parse_path_interval(
+        text: &str,
+        paths: &[PathSegment],
+    ) -> Result<Option<PathSegment>, Error> {
+        let interval = PathSegment::from_str(text);
+        let (start, end) = match interval.coords() {
+            Some(coords) => coords,
+            None => return Ok(None),
+        };
+        let path = interval.clear_coords();
+        if !paths.iter().any(|p| p.clear_coords() == path) {
+            return Ok(None);
+        }
+        if start >= end {
+            let msg = format!(
+                "interval {} of path {} is empty, its start must be smaller than its end",
+                text, path
+            );
+            log::error!("{}", &msg);
+            return Err(Error::new(ErrorKind::InvalidInput, msg));
+        }
+        Ok(Some(interval))
+    }
+
     pub fn load_coord_list(
         coord_text: &str,
         paths: &Vec<PathSegment>,
@@ -218,6 +486,16 @@ impl GraphMask {
         } else {
             if Path::new(coord_text).is_file() {
                 Self::load_coord_list_file(coord_text)?
+            } else if let Some(expression) =
+                parse_set_expression(coord_text, &|x: &str| Path::new(x).is_file())
+            {
+                log::info!("combining paths/path coordinates of {}", coord_text);
+                Some(evaluate_set_expression(coord_text, expression, |file| {
+                    Ok(Self::load_coord_list_file(file)?.unwrap_or_default())
+                })?)
+            } else if let Some(interval) = Self::parse_path_interval(coord_text, paths)? {
+                log::info!("restricting paths to interval {}", interval);
+                Some(vec![interval])
             } else if let Ok(re) = Regex::new(coord_text) {
                 log::info!("filtering paths based on regex {}", coord_text);
                 let coords = paths
@@ -230,24 +508,195 @@ impl GraphMask {
                 }
                 Some(coords)
             } else {
-                log::error!(
+                let msg = format!(
                     "string {} is not valid! Neither as a file name nor as a regex",
                     coord_text
                 );
-                panic!("Invalid file name");
+                log::error!("{}", &msg);
+                return Err(Error::new(ErrorKind::InvalidInput, msg));
             }
         })
     }
 
-    fn load_groups(
+    // adds the paths of the reference to the coordinates, i.e., those whose sample (as given by
+    // the naming scheme) or whose whole name equals the reference or matches it as a regex
+    fn add_reference_paths(
+        mut coords: Vec<PathSegment>,
+        reference: &str,
+        pansn: Option<&PanSN>,
+        paths: &[PathSegment],
+    ) -> Result<Vec<PathSegment>, Error> {
+        let re = Regex::new(&format!("^(?:{})$", reference)).map_err(|e| {
+            let msg = format!(
+                "reference {} is neither a name nor a valid regex: {}",
+                reference, e
+            );
+            log::error!("{}", &msg);
+            Error::new(ErrorKind::InvalidInput, msg)
+        })?;
+        let is_reference = |p: &PathSegment| {
+            let name = p.clear_coords().id();
+            let sample = match pansn {
+                Some(pansn) => pansn.split_name(&name).0.to_string(),
+                None => p.sample.clone(),
+            };
+            re.is_match(&sample) || re.is_match(&name)
+        };
+        let reference_paths: Vec<PathSegment> =
+            paths.iter().filter(|p| is_reference(p)).cloned().collect();
+        if reference_paths.is_empty() {
+            log::warn!("graph does not contain any path of reference {}", reference);
+        } else {
+            log::info!(
+                "excluding {} paths of reference {}",
+                reference_paths.len(),
+                reference
+            );
+        }
+        for p in reference_paths {
+            if !coords.contains(&p) {
+                coords.push(p);
+            }
+        }
+        Ok(coords)
+    }
+
+    // merges groups with fewer than min_group_size paths into a single group, or, if drop is set,
+    // leaves them unchanged and returns their names such that they can be dropped; the name of the
+    // merged group is reserved, as the merged groups could not be told apart from a group of the
+    // same name otherwise
+    fn merge_small_groups(
+        mut groups: HashMap<PathSegment, String>,
+        min_group_size: usize,
+        drop: bool,
+    ) -> Result<(HashMap<PathSegment, String>, HashSet<String>), Error> {
+        let mut sizes: HashMap<&str, usize> = HashMap::new();
+        for g in groups.values() {
+            *sizes.entry(g).or_insert(0) += 1;
+        }
+        let small_groups: HashSet<String> = sizes
+            .into_iter()
+            .filter(|(_, size)| *size < min_group_size)
+            .map(|(g, _)| g.to_string())
+            .collect();
+        if small_groups.is_empty() {
+            return Ok((groups, small_groups));
+        }
+        if drop {
+            log::info!(
+                "dropping {} groups with fewer than {} paths",
+                small_groups.len(),
+                min_group_size
+            );
+            return Ok((groups, small_groups));
+        }
+        if groups.values().any(|g| g == SMALL_GROUPS_NAME) {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                format!(
+                    "group name \"{}\" is reserved for the groups merged by --min-group-size, rename the group or drop small groups instead",
+                    SMALL_GROUPS_NAME
+                ),
+            ));
+        }
+        log::info!(
+            "merging {} groups with fewer than {} paths into group \"{}\"",
+            small_groups.len(),
+            min_group_size,
+            SMALL_GROUPS_NAME
+        );
+        for g in groups.values_mut() {
+            if small_groups.contains(g) {
+                *g = SMALL_GROUPS_NAME.to_string();
+            }
+        }
+        Ok((groups, HashSet::new()))
+    }
+
+    // groups paths/walks by sample or haplotype as given by a custom naming scheme
+    fn load_pansn_groups(
+        pansn: &PanSN,
+        groupby_haplotype: bool,
+        graph_storage: &GraphStorage,
+    ) -> HashMap<PathSegment, String> {
+        graph_storage
+            .path_segments
+            .iter()
+            .map(|x| {
+                let path = x.clear_coords();
+                let name = path.id();
+                let group = if groupby_haplotype {
+                    pansn.get_haplotype(&name)
+                } else {
+                    pansn.split_name(&name).0.to_string()
+                };
+                (path, group)
+            })
+            .collect()
+    }
+
+    // groups paths/walks by the captures of a regex on their names, put together by the label
+    // template, in which {name} stands for a capture, or else joined by #; paths/walks not matched
+    // by the regex form groups of their own
+    fn load_regex_groups(
+        pattern: &str,
+        label: Option<&str>,
+        graph_storage: &GraphStorage,
+    ) -> Result<HashMap<PathSegment, String>, Error> {
+        let invalid = |msg: String| {
+            log::error!("{}", &msg);
+            Error::new(ErrorKind::InvalidInput, msg)
+        };
+        let re = Regex::new(pattern)
+            .map_err(|e| invalid(format!("grouping regex {} is invalid: {}", pattern, e)))?;
+        let names: Vec<&str> = re.capture_names().flatten().collect();
+        let label = match label {
+            Some(label) => Some(parse_label_template(label, &names).map_err(invalid)?),
+            None => None,
+        };
+        log::info!("grouping paths by regex {}", pattern);
+        let mut unmatched = 0;
+        let mut groups = HashMap::default();
+        for x in graph_storage.path_segments.iter() {
+            let path = x.clear_coords();
+            let name = path.id();
+            let group = match re.captures(&name) {
+                Some(caps) => {
+                    let get = |n: &str| caps.name(n).map_or("", |m| m.as_str());
+                    match &label {
+                        Some(parts) => parts
+                            .iter()
+                            .map(|(text, capture)| format!("{}{}", text, capture.map_or("", get)))
+                            .collect(),
+                        None if names.is_empty() => caps[0].to_string(),
+                        None => names.iter().map(|n| get(n)).join("#"),
+                    }
+                }
+                None => {
+                    unmatched += 1;
+                    name.clone()
+                }
+            };
+            groups.insert(path, group);
+        }
+        if unmatched > 0 {
+            log::warn!(
+                "grouping regex {} does not match {} paths, each of which forms a group of its own",
+                pattern,
+                unmatched
+            );
+        }
+        Ok(groups)
+    }
+
+    pub fn load_groups(
         file_name: &str,
         groupby_haplotype: bool,
         groupby_sample: bool,
-        graph_storage: &GraphStorage,
+        path_segments: &[PathSegment],
     ) -> Result<HashMap<PathSegment, String>, Error> {
         if groupby_haplotype {
-            Ok(graph_storage
-                .path_segments
+            Ok(path_segments
                 .iter()
                 .map(|x| {
                     (
@@ -261,27 +710,29 @@ impl GraphMask {
                 })
                 .collect())
         } else if groupby_sample {
-            Ok(graph_storage
-                .path_segments
+            Ok(path_segments
                 .iter()
                 .map(|x| (x.clear_coords(), x.sample.clone()))
                 .collect())
         } else if !file_name.is_empty() {
             log::info!("loading groups from {}", file_name);
-            let mut data = BufReader::new(fs::File::open(file_name)?);
-            let group_assignments = parse_groups(&mut data)?;
+            let mut data = BufReader::new(open_file(file_name)?);
+            let group_assignments = parse_groups(&mut data, file_name)?;
             let mut path_to_group = HashMap::default();
+            let mut problems = InputProblems::new(file_name);
             for (i, (path, group)) in group_assignments.into_iter().enumerate() {
                 let path_nocoords = path.clear_coords();
                 match path_to_group.get(&path_nocoords) {
                     Some(g) => {
                         if g != &group {
-                            let msg = format!(
-                                "error in line {}: path {} cannot be assigned to more than one group, but is assigned to at least two groups: {}, {}",
-                                i, &path_nocoords, &g, &group
+                            problems.push(
+                                i + 1,
+                                0,
+                                format!(
+                                    "path {} cannot be assigned to more than one group, but is assigned to at least two groups: {}, {}",
+                                    &path_nocoords, &g, &group
+                                ),
                             );
-                            log::error!("{}", &msg);
-                            return Err(Error::new(ErrorKind::InvalidData, msg));
                         }
                     }
                     None => {
@@ -289,18 +740,18 @@ impl GraphMask {
                     }
                 }
             }
+            problems.into_result()?;
             log::debug!("loaded {} group assignments", path_to_group.len());
 
             // augment the group assignments with yet unassigned path segments
-            graph_storage.path_segments.iter().for_each(|x| {
+            path_segments.iter().for_each(|x| {
                 let path = x.clear_coords();
                 path_to_group.entry(path).or_insert_with(|| x.id());
             });
             Ok(path_to_group)
         } else {
             log::info!("no explicit grouping instruction given, group paths by their IDs (sample ID+haplotype ID+seq ID)");
-            Ok(graph_storage
-                .path_segments
+            Ok(path_segments
                 .iter()
                 .map(|x| (x.clear_coords(), x.id()))
                 .collect())
@@ -479,18 +930,25 @@ pub struct AbacusByTotal {
     pub countable: Vec<CountSize>,
     pub uncovered_bps: Option<HashMap<ItemIdSize, usize>>,
     pub groups: Vec<String>,
+    // in repeat-aware mode, the additional copies of countables that are traversed more than once
+    // by a path: the k-th copy of a countable is covered by the groups having a path that
+    // traverses the countable at least k times; given as countable id and coverage
+    pub copies: Vec<(ItemIdSize, CountSize)>,
+    // countables that are not covered by any of the counted paths, leaving out excluded ones
+    pub uncovered: Vec<ItemIdSize>,
 }
 
 impl AbacusByTotal {
     pub fn from_gfa<R: std::io::Read>(
         data: &mut BufReader<R>,
+        gfa_file: &str,
         graph_mask: &GraphMask,
         graph_storage: &GraphStorage,
         count_type: CountType,
-    ) -> (Self, HashMap<PathSegment, (u32, u32)>) {
+    ) -> Result<(Self, HashMap<PathSegment, (u32, u32)>), Error> {
         let (item_table, exclude_table, subset_covered_bps, paths_len) =
-            parse_gfa_paths_walks(data, graph_mask, graph_storage, &count_type);
-        (
+            parse_gfa_paths_walks(data, gfa_file, graph_mask, graph_storage, &count_type)?;
+        Ok((
             Self::item_table_to_abacus(
                 graph_mask,
                 graph_storage,
@@ -500,50 +958,96 @@ impl AbacusByTotal {
                 subset_covered_bps,
             ),
             paths_len,
-        )
+        ))
     }
 
     pub fn from_gfa_multiple<R: std::io::Read>(
         data: &mut BufReader<R>,
+        gfa_file: &str,
         graph_mask: &GraphMask,
         graph_storage: &GraphStorage,
         count_types: &Vec<CountType>,
-    ) -> (Vec<Self>, HashMap<PathSegment, (u32, u32)>) {
+    ) -> Result<(Vec<Self>, HashMap<PathSegment, (u32, u32)>), Error> {
         let (item_tables, exclude_tables, mut subset_covered_bps, path_lens) =
-            parse_gfa_paths_walks_multiple(data, graph_mask, graph_storage, count_types);
-        let mut item_tables = VecDeque::from(item_tables);
-        let mut exclude_tables = VecDeque::from(exclude_tables);
-        let mut subset_covered_bps: VecDeque<_> = count_types
+            parse_gfa_paths_walks_multiple(data, gfa_file, graph_mask, graph_storage, count_types)?;
+        let subset_covered_bps: Vec<_> = count_types
             .iter()
             .map(|count| match count {
                 &CountType::Bp if subset_covered_bps.is_some() => take(&mut subset_covered_bps),
                 _ => None,
             })
             .collect();
+        // the abaci of the different count types are independent of each other once the paths
+        // are parsed, and are therefore computed concurrently
         let abaci = count_types
-            .iter()
-            .map(|count| {
-                Self::item_table_to_abacus(
-                    graph_mask,
-                    graph_storage,
-                    *count,
-                    item_tables.pop_front().unwrap(),
-                    exclude_tables.pop_front().unwrap(),
-                    subset_covered_bps.pop_front().unwrap(),
-                )
-            })
+            .par_iter()
+            .zip(item_tables)
+            .zip(exclude_tables)
+            .zip(subset_covered_bps)
+            .map(
+                |(((count, item_table), exclude_table), subset_covered_bps)| {
+                    Self::item_table_to_abacus(
+                        graph_mask,
+                        graph_storage,
+                        *count,
+                        item_table,
+                        exclude_table,
+                        subset_covered_bps,
+                    )
+                },
+            )
             .collect();
-        (abaci, path_lens)
+        Ok((abaci, path_lens))
+    }
+
+    /// Visits the nodes traversed by each counted path/walk in the order in which they are
+    /// counted, restricted to the subset coordinates and leaving out excluded nodes, as they are
+    /// counted in the node abacus
+    pub fn visit_counted_nodes<R: std::io::Read, V: FnMut(&PathSegment, &[ItemIdSize])>(
+        data: &mut BufReader<R>,
+        gfa_file: &str,
+        graph_mask: &GraphMask,
+        graph_storage: &GraphStorage,
+        mut visit: V,
+    ) -> Result<(), Error> {
+        let count = CountType::Node;
+        let (mut item_tables, mut exclude_tables, _, _) = parse_gfa_paths_walks_multiple(
+            data,
+            gfa_file,
+            graph_mask,
+            graph_storage,
+            &vec![count],
+        )?;
+        let (mut item_table, mut exclude_table) = (item_tables.remove(0), exclude_tables.remove(0));
+        graph_storage.dedup_item_table(&mut item_table);
+        graph_mask.exclude_by_node_len(&mut exclude_table, graph_storage, count);
+        let mut nodes = Vec::new();
+        for (path_id, _) in graph_mask.get_path_order(&graph_storage.path_segments) {
+            let start = item_table.id_prefsum[path_id as usize] as usize;
+            let end = item_table.id_prefsum[path_id as usize + 1] as usize;
+            nodes.clear();
+            nodes.extend(item_table.items[start..end].iter().filter(|sid| {
+                exclude_table
+                    .as_ref()
+                    .map_or(true, |t| !t.items[**sid as usize])
+            }));
+            visit(&graph_storage.path_segments[path_id as usize], &nodes);
+        }
+        Ok(())
     }
 
     pub fn item_table_to_abacus(
         graph_mask: &GraphMask,
         graph_storage: &GraphStorage,
         count: CountType,
-        item_table: ItemTable,
-        exclude_table: Option<ActiveTable>,
+        mut item_table: ItemTable,
+        mut exclude_table: Option<ActiveTable>,
         subset_covered_bps: Option<IntervalContainer>,
     ) -> Self {
+        if count == CountType::Node || count == CountType::Bp {
+            graph_storage.dedup_item_table(&mut item_table);
+        }
+        graph_mask.exclude_by_node_len(&mut exclude_table, graph_storage, count);
         log::info!("counting abacus entries..");
         // first element in countable is "zero" element. It is ignored in counting
         let mut countable: Vec<CountSize> = vec![0; graph_storage.number_of_items(&count) + 1];
@@ -553,6 +1057,7 @@ impl AbacusByTotal {
             vec![ItemIdSize::MAX; graph_storage.number_of_items(&count) + 1];
 
         let mut groups = Vec::new();
+        let mut path_order: Vec<(ItemIdSize, ItemIdSize)> = Vec::new();
         for (path_id, group_id) in graph_mask.get_path_order(&graph_storage.path_segments) {
             if groups.is_empty() || groups.last().unwrap() != group_id {
                 groups.push(group_id.to_string());
@@ -565,8 +1070,21 @@ impl AbacusByTotal {
                 path_id,
                 groups.len() as ItemIdSize - 1,
             );
+            path_order.push((path_id, groups.len() as ItemIdSize - 1));
         }
+        let copies = if graph_mask.count_repeats {
+            log::info!("counting repeated traversals of countables..");
+            AbacusByTotal::repeat_coverage(
+                &item_table,
+                &exclude_table,
+                &path_order,
+                countable.len(),
+            )
+        } else {
+            Vec::new()
+        };
 
+        let uncovered = Self::find_uncovered(&countable, &exclude_table, graph_storage, count);
         log::info!(
             "abacus has {} path groups and {} countables",
             groups.len(),
@@ -582,9 +1100,44 @@ impl AbacusByTotal {
                 graph_storage,
             )),
             groups,
+            copies,
+            uncovered,
         }
     }
 
+    // countables that no counted path covers, other than excluded ones and, if duplicates are
+    // merged, nodes represented by another node; an edge counted per orientation is uncovered
+    // only if it is covered in neither orientation
+    fn find_uncovered(
+        countable: &[CountSize],
+        exclude_table: &Option<ActiveTable>,
+        graph_storage: &GraphStorage,
+        count: CountType,
+    ) -> Vec<ItemIdSize> {
+        let edge_count = graph_storage.edge_count;
+        let is_covered = |id: usize| match count {
+            CountType::Edge if graph_storage.oriented_edges => {
+                let id = if id > edge_count { id - edge_count } else { id };
+                countable[id] > 0 || countable[id + edge_count] > 0
+            }
+            _ => countable[id] > 0,
+        };
+        let is_merged = |id: usize| match (count, &graph_storage.node_representatives) {
+            (CountType::Node | CountType::Bp, Some(representatives)) => {
+                representatives[id] as usize != id
+            }
+            _ => false,
+        };
+        (1..countable.len())
+            .filter(|id| {
+                !is_covered(*id)
+                    && !is_merged(*id)
+                    && exclude_table.as_ref().map_or(true, |t| !t.items[*id])
+            })
+            .map(|id| id as ItemIdSize)
+            .collect()
+    }
+
     // pub fn from_cdbg_gfa<R: std::io::Read>(
     //     data: &mut BufReader<R>,
     //     graph_mask: &GraphMask,
@@ -743,6 +1296,53 @@ impl AbacusByTotal {
         }
     }
 
+    /// Coverage of the additional copies of countables that are traversed repeatedly by a path,
+    /// where the k-th copy is covered by each group that has a path traversing the countable at
+    /// least k times; paths must be given ordered by group
+    pub fn repeat_coverage(
+        item_table: &ItemTable,
+        exclude_table: &Option<ActiveTable>,
+        path_order: &[(ItemIdSize, ItemIdSize)],
+        n_items: usize,
+    ) -> Vec<(ItemIdSize, CountSize)> {
+        // number of traversals of each countable by the current path and the maximum thereof
+        // over the paths of the current group
+        let mut path_count: Vec<(ItemIdSize, CountSize)> = vec![(ItemIdSize::MAX, 0); n_items];
+        let mut group_max: Vec<(ItemIdSize, CountSize)> = vec![(ItemIdSize::MAX, 0); n_items];
+        let mut copies: HashMap<(ItemIdSize, CountSize), CountSize> = HashMap::new();
+        for (path_id, group_id) in path_order {
+            let start = item_table.id_prefsum[*path_id as usize] as usize;
+            let end = item_table.id_prefsum[*path_id as usize + 1] as usize;
+            for &sid in &item_table.items[start..end] {
+                let i = sid as usize;
+                if exclude_table.is_some() && exclude_table.as_ref().unwrap().items[i] {
+                    continue;
+                }
+                if path_count[i].0 != *path_id {
+                    path_count[i] = (*path_id, 0);
+                }
+                path_count[i].1 += 1;
+                if group_max[i].0 != *group_id {
+                    group_max[i] = (*group_id, 0);
+                }
+                let k = path_count[i].1;
+                if k > group_max[i].1 {
+                    group_max[i].1 = k;
+                    if k > 1 {
+                        *copies.entry((sid, k)).or_insert(0) += 1;
+                    }
+                }
+            }
+        }
+        let mut res: Vec<((ItemIdSize, CountSize), CountSize)> = copies.into_iter().collect();
+        res.sort_unstable();
+        log::info!(
+            "found {} additional copies of repeatedly traversed countables",
+            res.len()
+        );
+        res.into_iter().map(|((sid, _), cov)| (sid, cov)).collect()
+    }
+
     pub fn construct_hist(&self) -> Vec<usize> {
         log::info!("constructing histogram..");
         // hist must be of size = num_groups + 1; having an index that starts
@@ -758,6 +1358,9 @@ impl AbacusByTotal {
                 hist[*cov as usize] += 1;
             }
         }
+        for (_, cov) in &self.copies {
+            hist[*cov as usize] += 1;
+        }
         hist
     }
 
@@ -783,16 +1386,82 @@ impl AbacusByTotal {
             // add uncovered bps to 0-coverage count
             hist[0] += uncov;
         }
+        for (id, cov) in &self.copies {
+            let uncov = uncovered_bps.get(id).copied().unwrap_or(0);
+            hist[*cov as usize] +=
+                (graph_storage.node_lens[*id as usize] as usize).saturating_sub(uncov);
+        }
         hist
     }
+
+    /// Histograms of the nodes (or their bp) within each range [min, max] of node lengths, such
+    /// that content of different scale, e.g., of SNPs and structural variants, is told apart
+    pub fn construct_hists_by_node_len(
+        &self,
+        node_lens: &[u32],
+        ranges: &[(u32, u32)],
+    ) -> Vec<Vec<usize>> {
+        log::info!(
+            "constructing histograms of {} node length ranges..",
+            ranges.len()
+        );
+        let bp = self.count == CountType::Bp;
+        let n = self.groups.len() + 1;
+        let mut hists: Vec<Vec<usize>> = vec![vec![0; n]; ranges.len()];
+        // the uncovered bps of a node are counted with coverage 0 once, also if it has copies
+        let mut add = |id: usize, cov: CountSize, first: bool| {
+            if cov as usize >= n {
+                return;
+            }
+            let len = node_lens[id];
+            let (weight, uncov) = if bp {
+                let uncov = self
+                    .uncovered_bps
+                    .as_ref()
+                    .and_then(|u| u.get(&(id as ItemIdSize)))
+                    .copied()
+                    .unwrap_or(0)
+                    .min(len as usize);
+                (len as usize - uncov, uncov)
+            } else {
+                (1, 0)
+            };
+            for (hist, (min, max)) in hists.iter_mut().zip(ranges) {
+                if len >= *min && len <= *max {
+                    hist[cov as usize] += weight;
+                    if first {
+                        hist[0] += uncov;
+                    }
+                }
+            }
+        };
+        for (id, cov) in self.countable.iter().enumerate().skip(1) {
+            add(id, *cov, true);
+        }
+        for (id, cov) in &self.copies {
+            add(*id as usize, *cov, false);
+        }
+        hists
+    }
+}
+
+/// Randomized estimates over the groups of an abacus, each drawing from its own random streams
+#[derive(Debug, Clone, Copy)]
+pub enum Draw {
+    Percentiles,
+    /// Subsamples of the d-th rarefaction depth
+    Rarefaction(usize),
+    Stratified,
+    /// Replicates of the bootstrap support of a phylogeny of the groups
+    Bootstrap,
 }
 
 #[derive(Debug, Clone)]
 pub struct AbacusByGroup {
     pub count: CountType,
     pub r: Vec<usize>,
-    pub v: Option<Vec<CountSize>>,
-    pub c: Vec<GroupSize>,
+    pub v: Option<SpillVec<CountSize>>,
+    pub c: SpillVec<GroupSize>,
     pub uncovered_bps: HashMap<ItemIdSize, usize>,
     pub groups: Vec<String>,
     // pub graph_storage: &'a GraphStorage,
@@ -801,14 +1470,21 @@ pub struct AbacusByGroup {
 impl AbacusByGroup {
     pub fn from_gfa<R: std::io::Read>(
         data: &mut std::io::BufReader<R>,
+        gfa_file: &str,
         graph_mask: &GraphMask,
         graph_storage: &GraphStorage,
         count: CountType,
         report_values: bool,
     ) -> Result<Self, Error> {
         log::info!("parsing path + walk sequences");
-        let (item_table, exclude_table, subset_covered_bps, _paths_len) =
-            parse_gfa_paths_walks(data, graph_mask, graph_storage, &count);
+        let (mut item_table, mut exclude_table, subset_covered_bps, _paths_len) =
+            parse_gfa_paths_walks(data, gfa_file, graph_mask, graph_storage, &count)?;
+        if count == CountType::Node || count == CountType::Bp {
+            graph_storage.dedup_item_table(&mut item_table);
+        }
+        graph_mask.exclude_by_node_len(&mut exclude_table, graph_storage, count);
+        // the item table has been written to disk while parsing, if a spill directory is set
+        let items = take(&mut item_table.items);
 
         let mut path_order: Vec<(ItemIdSize, GroupSize)> = Vec::new();
         let mut groups: Vec<String> = Vec::new();
@@ -829,13 +1505,19 @@ impl AbacusByGroup {
         }
 
         let r = AbacusByGroup::compute_row_storage_space(
-            &item_table,
+            &items,
+            &item_table.id_prefsum,
             &exclude_table,
             &path_order,
             graph_storage.number_of_items(&count),
         );
-        let (v, c) =
-            AbacusByGroup::compute_column_values(&item_table, &path_order, &r, report_values);
+        let (v, c) = AbacusByGroup::compute_column_values(
+            &items,
+            &item_table.id_prefsum,
+            &path_order,
+            &r,
+            report_values,
+        )?;
         log::info!(
             "abacus has {} path groups and {} countables",
             groups.len(),
@@ -857,7 +1539,8 @@ impl AbacusByGroup {
     }
 
     fn compute_row_storage_space(
-        item_table: &ItemTable,
+        items: &[ItemIdSize],
+        id_prefsum: &[ItemIdSize],
         exclude_table: &Option<ActiveTable>,
         path_order: &Vec<(ItemIdSize, GroupSize)>,
         n_items: usize,
@@ -869,10 +1552,10 @@ impl AbacusByGroup {
         let mut r: Vec<usize> = vec![0; n_items + 2];
         let r_ptr = Wrap(&mut r);
         for (path_id, group_id) in path_order {
-            let start = item_table.id_prefsum[*path_id as usize] as usize;
-            let end = item_table.id_prefsum[*path_id as usize + 1] as usize;
-            for j in start..end {
-                let sid = item_table.items[j] as usize;
+            let start = id_prefsum[*path_id as usize] as usize;
+            let end = id_prefsum[*path_id as usize + 1] as usize;
+            for &sid in &items[start..end] {
+                let sid = sid as usize;
                 if &last[sid] != group_id
                     && (exclude_table.is_none() || !exclude_table.as_ref().unwrap().items[sid])
                 {
@@ -899,20 +1582,21 @@ impl AbacusByGroup {
     }
 
     fn compute_column_values(
-        item_table: &ItemTable,
+        items: &[ItemIdSize],
+        id_prefsum: &[ItemIdSize],
         path_order: &Vec<(ItemIdSize, GroupSize)>,
         r: &[usize],
         report_values: bool,
-    ) -> (Option<Vec<CountSize>>, Vec<GroupSize>) {
+    ) -> Result<(Option<SpillVec<CountSize>>, SpillVec<GroupSize>), Error> {
         let n = { *r.last().unwrap() };
         log::info!("allocating storage for group-based coverage table..");
         let mut v = if report_values {
-            vec![0; n]
+            SpillVec::filled(0, n)?
         } else {
             // we produce a dummy
-            vec![0; 1]
+            SpillVec::from(vec![0; 1])
         };
-        let mut c: Vec<GroupSize> = vec![GroupSize::MAX; n];
+        let mut c: SpillVec<GroupSize> = SpillVec::filled(GroupSize::MAX, n)?;
         log::info!("done");
 
         log::info!("computing group-based coverage..");
@@ -922,10 +1606,10 @@ impl AbacusByGroup {
         // group id is monotone increasing from 0 to #groups
         for (path_id, group_id) in path_order {
             let path_id_u = *path_id as usize;
-            let start = item_table.id_prefsum[path_id_u] as usize;
-            let end = item_table.id_prefsum[path_id_u + 1] as usize;
-            for j in start..end {
-                let sid = item_table.items[j] as usize;
+            let start = id_prefsum[path_id_u] as usize;
+            let end = id_prefsum[path_id_u + 1] as usize;
+            for &sid in &items[start..end] {
+                let sid = sid as usize;
                 let cv_start = r[sid];
                 let mut cv_end = r[sid + 1];
                 if cv_end != cv_start {
@@ -982,7 +1666,24 @@ impl AbacusByGroup {
             }
         }
         log::info!("done");
-        (if report_values { Some(v) } else { None }, c)
+        Ok((if report_values { Some(v) } else { None }, c))
+    }
+
+    /// Weight of each countable (node, edge, or bubble allele), which is 1 except for bp, where
+    /// nodes are weighted by their length, reduced by the bps that are not covered by the subset or
+    /// excluded
+    pub fn get_item_weights(&self, node_lens: &[u32]) -> Vec<usize> {
+        if self.count != CountType::Bp {
+            return vec![1; self.r.len()];
+        }
+        node_lens
+            .iter()
+            .enumerate()
+            .map(|(i, l)| {
+                (*l as usize)
+                    .saturating_sub(*self.uncovered_bps.get(&(i as ItemIdSize)).unwrap_or(&0))
+            })
+            .collect()
     }
 
     // why &self and not self? we could destroy abacus at this point.
@@ -995,23 +1696,28 @@ impl AbacusByGroup {
         let mut res = vec![0.0; self.groups.len()];
 
         let c = usize::max(1, t_coverage.to_absolute(self.groups.len()));
+        let c_upper = t_coverage.to_absolute_upper(self.groups.len());
         let q = f64::max(0.0, t_quorum.to_relative(self.groups.len()));
+        let q_upper = t_quorum.to_relative_upper(self.groups.len());
 
         let mut it = self.r.iter().tuple_windows().enumerate();
         // ignore first entry
         it.next();
         for (i, (&start, &end)) in it {
-            if end - start >= c {
+            if end - start >= c && end - start <= c_upper {
                 let mut k = start;
                 for j in self.c[start] as usize..self.groups.len() {
                     if k < end - 1 && self.c[k + 1] as usize <= j {
                         k += 1
                     }
-                    if k - start + 1 >= ((self.c[k] as f64 + 1.0) * q).ceil() as usize {
+                    if k - start + 1 >= ((self.c[k] as f64 + 1.0) * q).ceil() as usize
+                        && (!q_upper.is_finite()
+                            || k - start < ((j as f64 + 1.0) * q_upper).floor() as usize)
+                    {
                         // we never need to look into the actual value in self.v, because we
                         // know it must be non-zero, which is sufficient
                         match self.count {
-                            CountType::Node | CountType::Edge => res[j] += 1.0,
+                            CountType::Node | CountType::Edge | CountType::Bubble => res[j] += 1.0,
                             CountType::Bp => {
                                 let uncovered =
                                     self.uncovered_bps.get(&(i as ItemIdSize)).unwrap_or(&0);
@@ -1031,6 +1737,190 @@ impl AbacusByGroup {
         res
     }
 
+    /// Growth along the order of the groups given by the position (rank) of each group, with
+    /// countables weighted by the given weights
+    pub fn calc_growth_in_order(
+        &self,
+        t_coverage: &Threshold,
+        t_quorum: &Threshold,
+        weights: &[usize],
+        ranks: &[usize],
+    ) -> Vec<f64> {
+        let n = self.groups.len();
+        let c = usize::max(1, t_coverage.to_absolute(n));
+        let c_upper = t_coverage.to_absolute_upper(n);
+        let q = f64::max(0.0, t_quorum.to_relative(n));
+        let q_upper = t_quorum.to_relative_upper(n);
+
+        // differences of the growth between m - 1 and m groups
+        let mut diff: Vec<i64> = vec![0; n + 2];
+        let mut item_ranks: Vec<usize> = Vec::new();
+        for (i, (&start, &end)) in self.r.iter().tuple_windows().enumerate().skip(1) {
+            if end - start < c || end - start > c_upper {
+                continue;
+            }
+            item_ranks.clear();
+            item_ranks.extend(self.c[start..end].iter().map(|g| ranks[*g as usize]));
+            item_ranks.sort_unstable();
+            for (h, &rank) in item_ranks.iter().enumerate() {
+                // the countable occurs in h + 1 of the first m groups for all m up to the rank of
+                // its next group
+                let next = item_ranks.get(h + 1).copied().unwrap_or(n);
+                let (from, to) = quorum_range(h + 1, q, q_upper, n);
+                let (from, to) = (usize::max(from, rank + 1), usize::min(to, next));
+                if from <= to {
+                    diff[from] += weights[i] as i64;
+                    diff[to + 1] -= weights[i] as i64;
+                }
+            }
+        }
+        diff[1..=n]
+            .iter()
+            .scan(0, |growth, d| {
+                *growth += d;
+                Some(*growth as f64)
+            })
+            .collect()
+    }
+
+    /// Random stream of the i-th draw of a randomized estimate, such that different estimates
+    /// never share streams: percentiles use streams 0, 1, ..., rarefaction depth d the streams
+    /// (d + 1) << 32 | i, bootstrap replicates the streams with the second highest bit set, and
+    /// stratified growth the streams with the highest bit set
+    pub fn get_stream(draw: Draw, i: usize) -> u64 {
+        match draw {
+            Draw::Percentiles => i as u64,
+            Draw::Rarefaction(d) => ((d as u64 + 1) << 32) | i as u64,
+            Draw::Bootstrap => (1 << 62) | i as u64,
+            Draw::Stratified => (1 << 63) | i as u64,
+        }
+    }
+
+    /// Percentiles (in %) of the growth over random orders of the groups, given as one growth
+    /// curve per percentile
+    pub fn calc_growth_percentiles(
+        &self,
+        t_coverage: &Threshold,
+        t_quorum: &Threshold,
+        node_lens: &[u32],
+        percentiles: &[f64],
+        permutations: usize,
+    ) -> Vec<Vec<f64>> {
+        let n = self.groups.len();
+        let weights = self.get_item_weights(node_lens);
+        let growths: Vec<Vec<f64>> = (0..permutations)
+            .into_par_iter()
+            .map(|i| {
+                let mut ranks: Vec<usize> = (0..n).collect();
+                ranks.shuffle(&mut get_rng(Self::get_stream(Draw::Percentiles, i)));
+                self.calc_growth_in_order(t_coverage, t_quorum, &weights, &ranks)
+            })
+            .collect();
+
+        let mut res = vec![Vec::with_capacity(n); percentiles.len()];
+        for m in 0..n {
+            let mut values: Vec<f64> = growths.iter().map(|g| g[m]).collect();
+            values.sort_by(|a, b| a.partial_cmp(b).unwrap());
+            for (growth, p) in res.iter_mut().zip(percentiles) {
+                growth.push(percentile_already_sorted(&values, *p));
+            }
+        }
+        res
+    }
+
+    /// Mean growth over random orders of the groups that draw equally from each population, i.e.,
+    /// take one group of each population in turn; the curve ends once the smallest population is
+    /// exhausted. Groups of no population are never drawn
+    pub fn calc_stratified_growth(
+        &self,
+        t_coverage: &Threshold,
+        t_quorum: &Threshold,
+        node_lens: &[u32],
+        populations: &[Option<usize>],
+        permutations: usize,
+    ) -> Vec<f64> {
+        let n = self.groups.len();
+        let weights = self.get_item_weights(node_lens);
+        let mut members: Vec<Vec<usize>> = Vec::new();
+        for (g, p) in populations.iter().enumerate() {
+            if let Some(p) = p {
+                if members.len() <= *p {
+                    members.resize(p + 1, Vec::new());
+                }
+                members[*p].push(g);
+            }
+        }
+        members.retain(|m| !m.is_empty());
+        let rounds = members.iter().map(|m| m.len()).min().unwrap_or(0);
+        let balanced = rounds * members.len();
+
+        let sum = (0..permutations)
+            .into_par_iter()
+            .map(|i| {
+                let mut rng = get_rng(Self::get_stream(Draw::Stratified, i));
+                let mut members = members.clone();
+                for m in members.iter_mut() {
+                    m.shuffle(&mut rng);
+                }
+                let mut order = Vec::with_capacity(n);
+                let mut turn: Vec<usize> = (0..members.len()).collect();
+                // each round takes the next group of every stratum, all of which have enough
+                // groups for all rounds
+                let mut next: Vec<_> = members.iter().map(|m| m.iter()).collect();
+                for _ in 0..rounds {
+                    turn.shuffle(&mut rng);
+                    order.extend(turn.iter().filter_map(|p| next[*p].next().copied()));
+                }
+                // the remaining groups are placed after the balanced part, which is all that is
+                // reported
+                let mut ranks = vec![n; n];
+                for (rank, g) in order.iter().enumerate() {
+                    ranks[*g] = rank;
+                }
+                for (rank, r) in (order.len()..).zip(ranks.iter_mut().filter(|r| **r == n)) {
+                    *r = rank;
+                }
+                self.calc_growth_in_order(t_coverage, t_quorum, &weights, &ranks)
+            })
+            .reduce(
+                || vec![0.0; n],
+                |a, b| a.iter().zip(&b).map(|(x, y)| x + y).collect(),
+            );
+        sum[..balanced]
+            .iter()
+            .map(|x| x / permutations as f64)
+            .collect()
+    }
+
+    /// Pangenome sizes of random subsets of exactly m groups for each of the given depths m,
+    /// given as the sizes of all subsamples at each depth
+    pub fn calc_rarefaction(
+        &self,
+        t_coverage: &Threshold,
+        t_quorum: &Threshold,
+        node_lens: &[u32],
+        depths: &[usize],
+        samples: usize,
+    ) -> Vec<Vec<f64>> {
+        let n = self.groups.len();
+        let weights = self.get_item_weights(node_lens);
+        depths
+            .iter()
+            .enumerate()
+            .map(|(d, &m)| {
+                (0..samples)
+                    .into_par_iter()
+                    .map(|i| {
+                        // the first m groups of a random order are a random subset of size m
+                        let mut ranks: Vec<usize> = (0..n).collect();
+                        ranks.shuffle(&mut get_rng(Self::get_stream(Draw::Rarefaction(d), i)));
+                        self.calc_growth_in_order(t_coverage, t_quorum, &weights, &ranks)[m - 1]
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
     #[allow(dead_code)]
     pub fn write_rcv<W: Write>(&self, out: &mut BufWriter<W>) -> Result<(), Error> {
         write!(out, "{}", self.r[0])?;
@@ -1053,6 +1943,140 @@ impl AbacusByGroup {
         Ok(())
     }
 
+    /// Writes the non-zero entries of the countable x group matrix (or, if `total` is set, of the
+    /// countable x 1 matrix of coverages) in MatrixMarket coordinate format; rows are numbered by
+    /// countable id, and the names of rows and columns are given in comment lines
+    pub fn to_mtx<W: Write>(
+        &self,
+        total: bool,
+        out: &mut BufWriter<W>,
+        graph_storage: &GraphStorage,
+    ) -> Result<(), Error> {
+        log::info!("reporting coverage table in MatrixMarket format");
+        let mut entries = 0;
+        self.visit_entries(total, &graph_storage.node_lens, |_, _, _| {
+            entries += 1;
+            Ok(())
+        })?;
+        writeln!(out, "%%MatrixMarket matrix coordinate integer general")?;
+        for (i, name) in self
+            .get_item_names(graph_storage)
+            .iter()
+            .enumerate()
+            .skip(1)
+        {
+            writeln!(out, "% row {} {}", i, name)?;
+        }
+        for (j, name) in self.get_column_names(total).iter().enumerate() {
+            writeln!(out, "% column {} {}", j + 1, name)?;
+        }
+        writeln!(
+            out,
+            "{} {} {}",
+            self.r.len() - 1,
+            self.get_column_names(total).len(),
+            entries
+        )?;
+        self.visit_entries(total, &graph_storage.node_lens, |i, j, value| {
+            writeln!(out, "{} {} {}", i, j + 1, value)
+        })
+    }
+
+    /// Writes the non-zero entries of the countable x group matrix (or, if `total` is set, of the
+    /// countable x 1 matrix of coverages) as tab-separated countable, group, and value
+    pub fn to_triplets<W: Write>(
+        &self,
+        total: bool,
+        out: &mut BufWriter<W>,
+        graph_storage: &GraphStorage,
+    ) -> Result<(), Error> {
+        log::info!("reporting coverage table as triplets");
+        let names = self.get_item_names(graph_storage);
+        let columns = self.get_column_names(total);
+        let item = match self.count {
+            CountType::Edge => "edge",
+            CountType::Bubble => "allele",
+            _ => "node",
+        };
+        writeln!(out, "{}\tgroup\t{}", item, self.count)?;
+        self.visit_entries(total, &graph_storage.node_lens, |i, j, value| {
+            writeln!(out, "{}\t{}\t{}", names[i], columns[j], value)
+        })
+    }
+
+    fn get_column_names(&self, total: bool) -> Vec<String> {
+        if total {
+            vec!["total".to_string()]
+        } else {
+            self.groups.clone()
+        }
+    }
+
+    // calls `f` with the countable id, the group index, and the value of each non-zero entry of
+    // the countable x group matrix, or of the countable x 1 matrix of coverages if `total` is set
+    fn visit_entries<F>(&self, total: bool, node_lens: &[u32], mut f: F) -> Result<(), Error>
+    where
+        F: FnMut(usize, usize, usize) -> Result<(), Error>,
+    {
+        let weights = self.get_item_weights(node_lens);
+        for (i, (&start, &end)) in self.r.iter().tuple_windows().enumerate().skip(1) {
+            if total {
+                if end > start {
+                    f(i, 0, end - start)?;
+                }
+                continue;
+            }
+            for k in start..end {
+                let copies = self.v.as_ref().map(|v| v[k] as usize).unwrap_or(1);
+                if copies * weights[i] > 0 {
+                    f(i, self.c[k] as usize, copies * weights[i])?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    // names of the countables, indexed by their ids: node names, edges as oriented node pairs, and
+    // bubble alleles as walks from the entrance to the exit of their bubble
+    fn get_item_names(&self, graph_storage: &GraphStorage) -> Vec<String> {
+        let mut id2node = vec![String::new(); graph_storage.node_count + 1];
+        for (node, id) in graph_storage.get_node_tuples() {
+            id2node[id.0 as usize] = String::from_utf8_lossy(&node).to_string();
+        }
+        match self.count {
+            CountType::Node | CountType::Bp => id2node,
+            CountType::Edge => graph_storage
+                .get_id2edge()
+                .iter()
+                .map(|edge| {
+                    format!(
+                        "{}{}{}{}",
+                        edge.1, id2node[edge.0 .0 as usize], edge.3, id2node[edge.2 .0 as usize]
+                    )
+                })
+                .collect(),
+            CountType::Bubble => {
+                let bubbles = graph_storage
+                    .bubbles
+                    .as_ref()
+                    .expect("bubble count requires bubble index in GraphStorage");
+                (0..self.r.len() - 1)
+                    .map(|i| {
+                        if i == 0 {
+                            return String::new();
+                        }
+                        bubbles
+                            .get_allele_steps(ItemId(i as ItemIdSize))
+                            .iter()
+                            .map(|(sid, o)| format!("{}{}", o, id2node[sid.0 as usize]))
+                            .collect()
+                    })
+                    .collect()
+            }
+            CountType::All => unreachable!("inadmissible count type"),
+        }
+    }
+
     pub fn to_tsv<W: Write>(
         &self,
         total: bool,
@@ -1114,17 +2138,8 @@ impl AbacusByGroup {
                 }
             }
             CountType::Edge => {
-                if let Some(edge2id) = &graph_storage.edge2id {
-                    let dummy_edge = Edge(
-                        ItemId(0),
-                        Orientation::default(),
-                        ItemId(0),
-                        Orientation::default(),
-                    );
-                    let mut id2edge: Vec<&Edge> = vec![&dummy_edge; graph_storage.edge_count + 1];
-                    for (edge, id) in edge2id.iter() {
-                        id2edge[id.0 as usize] = edge;
-                    }
+                if graph_storage.edge2id.is_some() {
+                    let id2edge = graph_storage.get_id2edge();
 
                     write!(out, "edge")?;
                     if total {
@@ -1171,6 +2186,55 @@ impl AbacusByGroup {
                     }
                 }
             }
+            CountType::Bubble => {
+                let bubbles = graph_storage
+                    .bubbles
+                    .as_ref()
+                    .expect("bubble count requires bubble index in GraphStorage");
+                write!(out, "allele")?;
+                if total {
+                    write!(out, "\ttotal")?;
+                } else {
+                    for group in self.groups.iter() {
+                        write!(out, "\t{}", group)?;
+                    }
+                }
+                writeln!(out)?;
+
+                let mut it = self.r.iter().tuple_windows().enumerate();
+                // ignore first entry
+                it.next();
+                for (i, (&start, &end)) in it {
+                    // alleles are written as walks from the entrance to the exit of their bubble
+                    for (sid, o) in bubbles.get_allele_steps(ItemId(i as ItemIdSize)) {
+                        write!(
+                            out,
+                            "{}{}",
+                            o,
+                            std::str::from_utf8(id2node[sid.0 as usize]).unwrap()
+                        )?;
+                    }
+                    if total {
+                        // we never need to look into the actual value in self.v, because we
+                        // know it must be non-zero, which is sufficient
+                        writeln!(out, "\t{}", end - start)?;
+                    } else {
+                        let mut k = start;
+                        for j in 0 as GroupSize..self.groups.len() as GroupSize {
+                            if k == end || j < self.c[k] {
+                                write!(out, "\t0")?;
+                            } else if j == self.c[k] {
+                                match &self.v {
+                                    None => write!(out, "\t1"),
+                                    Some(v) => write!(out, "\t{}", v[k]),
+                                }?;
+                                k += 1;
+                            }
+                        }
+                        writeln!(out)?;
+                    }
+                }
+            }
             CountType::All => unreachable!("inadmissible count type"),
         };
 
@@ -1228,9 +2292,44 @@ pub fn quantify_uncovered_bps(
     res
 }
 
+// range of the number of groups m for which a countable occurring in h of them satisfies the
+// relative quorum bounds, where m is at most n
+fn quorum_range(h: usize, q: f64, q_upper: f64, n: usize) -> (usize, usize) {
+    // largest m with h >= ceil(m * q)
+    let mut to = if q > 0.0 {
+        usize::min(n, (h as f64 / q).floor() as usize)
+    } else {
+        n
+    };
+    while to < n && ((to + 1) as f64 * q).ceil() as usize <= h {
+        to += 1;
+    }
+    while to > 0 && (to as f64 * q).ceil() as usize > h {
+        to -= 1;
+    }
+    // smallest m with h <= floor(m * q_upper)
+    let mut from = if q_upper.is_finite() {
+        if q_upper > 0.0 {
+            usize::min(n + 1, (h as f64 / q_upper).ceil() as usize)
+        } else {
+            n + 1
+        }
+    } else {
+        1
+    };
+    while from > 1 && q_upper.is_finite() && ((from - 1) as f64 * q_upper).floor() as usize >= h {
+        from -= 1;
+    }
+    while from <= n && q_upper.is_finite() && ((from as f64 * q_upper).floor() as usize) < h {
+        from += 1;
+    }
+    (from, to)
+}
+
 #[cfg(test)]
 mod tests {
     use std::io::Error;
+    use std::str::FromStr;
     use tempfile::NamedTempFile;
 
     use super::*;
@@ -1240,15 +2339,127 @@ mod tests {
         let expected = GraphMaskParameters {
             positive_list: String::new(),
             negative_list: String::new(),
+            exclude_reference: None,
             groupby: String::new(),
             groupby_haplotype: false,
             groupby_sample: false,
+            groupby_regex: None,
             order: None,
+            pansn: None,
+            min_group_size: 0,
+            drop_small_groups: false,
+            count_repeats: false,
+            min_node_len: None,
+            max_node_len: None,
         };
         let calculated = GraphMaskParameters::default();
         assert_eq!(calculated, expected);
     }
 
+    #[test]
+    fn test_exclude_by_node_len() {
+        let mut graph_storage = get_graph_storage_path_segments();
+        graph_storage.node_lens = vec![0, 1, 50, 120, 49];
+        graph_storage.node_count = 4;
+        let graph_mask = GraphMask {
+            groups: HashMap::new(),
+            include_coords: None,
+            exclude_coords: None,
+            order: None,
+            count_repeats: false,
+            min_node_len: Some(50),
+            max_node_len: Some(100),
+        };
+        let mut exclude_table = None;
+        graph_mask.exclude_by_node_len(&mut exclude_table, &graph_storage, CountType::Bp);
+        assert_eq!(
+            exclude_table.unwrap().items,
+            vec![false, true, false, true, true]
+        );
+
+        let mut exclude_table = None;
+        graph_mask.exclude_by_node_len(&mut exclude_table, &graph_storage, CountType::Edge);
+        assert!(exclude_table.is_none());
+    }
+
+    #[test]
+    fn test_find_uncovered() {
+        let mut graph_storage = GraphStorage::from_path_segments(Vec::new());
+        // node 2 is excluded, node 4 is a duplicate of node 1
+        let countable = vec![CountSize::MAX, 1, 0, 0, 0, 2];
+        let mut exclude_table = ActiveTable::new(countable.len(), false);
+        exclude_table.activate(&ItemId(2));
+        let exclude_table = Some(exclude_table);
+        graph_storage.node_representatives = Some(vec![0, 1, 2, 3, 1, 5]);
+        assert_eq!(
+            AbacusByTotal::find_uncovered(
+                &countable,
+                &exclude_table,
+                &graph_storage,
+                CountType::Node
+            ),
+            vec![3]
+        );
+
+        // edge 1 is traversed in reverse orientation only, edge 2 in neither
+        graph_storage.edge_count = 2;
+        graph_storage.oriented_edges = true;
+        let countable = vec![CountSize::MAX, 0, 0, 1, 0];
+        assert_eq!(
+            AbacusByTotal::find_uncovered(&countable, &None, &graph_storage, CountType::Edge),
+            vec![2, 4]
+        );
+    }
+
+    #[test]
+    fn test_construct_hists_by_node_len() {
+        let node_lens = vec![0, 1, 60, 2000, 1, 50];
+        let mut abacus = AbacusByTotal {
+            count: CountType::Node,
+            countable: vec![CountSize::MAX, 2, 1, 0, 2, 2],
+            uncovered_bps: None,
+            groups: vec!["a".to_string(), "b".to_string()],
+            copies: vec![(2, 1)],
+            uncovered: vec![3],
+        };
+        let ranges = vec![(1, 49), (50, 999), (1000, u32::MAX)];
+        assert_eq!(
+            abacus.construct_hists_by_node_len(&node_lens, &ranges),
+            vec![vec![0, 0, 2], vec![0, 2, 1], vec![1, 0, 0]]
+        );
+
+        // 10 bp of node 2 are not covered by the subset
+        abacus.count = CountType::Bp;
+        abacus.uncovered_bps = Some(HashMap::from([(2, 10)]));
+        assert_eq!(
+            abacus.construct_hists_by_node_len(&node_lens, &ranges),
+            vec![vec![0, 0, 2], vec![10, 100, 50], vec![2000, 0, 0]]
+        );
+    }
+
+    #[test]
+    fn test_repeat_coverage() {
+        let item_table = ItemTable {
+            items: vec![1, 1, 2, 1, 1, 1, 2, 2, 1].into(),
+            id_prefsum: vec![0, 4, 6, 9],
+        };
+        let path_order = vec![(0, 0), (1, 0), (2, 1)];
+        assert_eq!(
+            AbacusByTotal::repeat_coverage(&item_table, &None, &path_order, 3),
+            vec![(1, 1), (1, 1), (2, 1)]
+        );
+        // the second copy of node 1 is covered by both groups if each traverses it twice
+        let path_order = vec![(1, 0), (2, 1)];
+        let item_table = ItemTable {
+            items: vec![1, 1, 2, 2, 1, 1, 2, 1, 1].into(),
+            id_prefsum: vec![0, 4, 6, 9],
+        };
+        assert_eq!(
+            AbacusByTotal::repeat_coverage(&item_table, &None, &path_order, 3),
+            vec![(1, 2)]
+        );
+    }
+
     fn get_graph_storage_path_segments() -> GraphStorage {
         GraphStorage::from_path_segments(vec![
             PathSegment::from_str("s1#2#2"),
@@ -1271,7 +2482,7 @@ mod tests {
     fn test_load_groups_haplotype() -> Result<(), Error> {
         let expected = get_load_groups_expected_hashmap(["s1#1", "s1#1", "s1#2", "s2#1"]);
         let graph_storage = get_graph_storage_path_segments();
-        let calculated = GraphMask::load_groups("", true, false, &graph_storage)?;
+        let calculated = GraphMask::load_groups("", true, false, &graph_storage.path_segments)?;
         assert_eq!(calculated, expected);
         Ok(())
     }
@@ -1280,11 +2491,225 @@ mod tests {
     fn test_load_groups_sample() -> Result<(), Error> {
         let expected = get_load_groups_expected_hashmap(["s1", "s1", "s1", "s2"]);
         let graph_storage = get_graph_storage_path_segments();
-        let calculated = GraphMask::load_groups("", false, true, &graph_storage)?;
+        let calculated = GraphMask::load_groups("", false, true, &graph_storage.path_segments)?;
         assert_eq!(calculated, expected);
         Ok(())
     }
 
+    #[test]
+    fn test_load_pansn_groups() {
+        let graph_storage = GraphStorage::from_path_segments(vec![
+            PathSegment::from_str("s1.2.chr1.alt"),
+            PathSegment::from_str("s1.1.chr2"),
+            PathSegment::from_str("s2.1.chr1"),
+            PathSegment::from_str("s3"),
+        ]);
+        let pansn = PanSN::from_str(".,3,2").unwrap();
+        let groups = |groupby_haplotype| -> Vec<String> {
+            let groups = GraphMask::load_pansn_groups(&pansn, groupby_haplotype, &graph_storage);
+            graph_storage
+                .path_segments
+                .iter()
+                .map(|x| groups[x].clone())
+                .collect()
+        };
+        assert_eq!(groups(true), vec!["s1.2", "s1.1", "s2.1", "s3"]);
+        assert_eq!(groups(false), vec!["s1", "s1", "s2", "s3"]);
+        assert!(PanSN::from_str(".,3,4").is_err());
+        assert!(PanSN::from_str(".3").is_err());
+        assert_eq!(PanSN::from_str("_").unwrap().delimiter, '_');
+    }
+
+    #[test]
+    fn test_load_regex_groups() {
+        let graph_storage = GraphStorage::from_path_segments(vec![
+            PathSegment::from_str("AFR_HG01#1#chr1"),
+            PathSegment::from_str("AFR_HG02#2#chr1"),
+            PathSegment::from_str("EUR_HG03#1#chr1"),
+            PathSegment::from_str("CHM13#0#chr1"),
+        ]);
+        let groups = |pattern: &str, label: Option<&str>| -> Vec<String> {
+            let groups = GraphMask::load_regex_groups(pattern, label, &graph_storage).unwrap();
+            graph_storage
+                .path_segments
+                .iter()
+                .map(|x| groups[x].clone())
+                .collect()
+        };
+        let pattern = r"^(?P<pop>[A-Z]+)_[^#]+#(?P<hap>\d+)";
+        assert_eq!(
+            groups(pattern, None),
+            vec!["AFR#1", "AFR#2", "EUR#1", "CHM13#0#chr1"]
+        );
+        assert_eq!(
+            groups(pattern, Some("{pop}-h{hap}")),
+            vec!["AFR-h1", "AFR-h2", "EUR-h1", "CHM13#0#chr1"]
+        );
+        assert_eq!(groups("^[A-Z]+", None), vec!["AFR", "AFR", "EUR", "CHM"]);
+        assert!(GraphMask::load_regex_groups("(?P<pop>", None, &graph_storage).is_err());
+        assert!(GraphMask::load_regex_groups(pattern, Some("{sample}"), &graph_storage).is_err());
+        assert!(GraphMask::load_regex_groups(pattern, Some("{pop"), &graph_storage).is_err());
+    }
+
+    #[test]
+    fn test_quorum_range() {
+        assert_eq!(quorum_range(1, 0.0, f64::INFINITY, 5), (1, 5));
+        assert_eq!(quorum_range(2, 0.5, f64::INFINITY, 10), (1, 4));
+        assert_eq!(quorum_range(3, 1.0, f64::INFINITY, 10), (1, 3));
+        assert_eq!(quorum_range(1, 0.0, 0.5, 10), (2, 10));
+        assert_eq!(quorum_range(2, 0.2, 0.5, 8), (4, 8));
+    }
+
+    #[test]
+    fn test_calc_growth_in_order() {
+        // item 1 is covered by groups 0 and 1, item 2 by group 2, and item 3 by all groups
+        let abacus = AbacusByGroup {
+            count: CountType::Node,
+            r: vec![0, 0, 2, 3, 6],
+            v: None,
+            c: vec![0, 1, 2, 0, 1, 2].into(),
+            uncovered_bps: HashMap::new(),
+            groups: vec!["a".to_string(), "b".to_string(), "c".to_string()],
+        };
+        let weights = vec![1; 4];
+        let (union, core) = (Threshold::Relative(0.0), Threshold::Relative(1.0));
+        let coverage = Threshold::Absolute(0);
+        assert_eq!(
+            abacus.calc_growth_in_order(&coverage, &union, &weights, &[0, 1, 2]),
+            abacus.calc_growth(&coverage, &union, &vec![1; 4])
+        );
+        assert_eq!(
+            abacus.calc_growth_in_order(&coverage, &union, &weights, &[2, 1, 0]),
+            vec![2.0, 3.0, 3.0]
+        );
+        assert_eq!(
+            abacus.calc_growth_in_order(&coverage, &core, &weights, &[0, 1, 2]),
+            vec![2.0, 2.0, 1.0]
+        );
+        assert_eq!(
+            abacus.calc_growth_in_order(&coverage, &core, &weights, &[2, 1, 0]),
+            vec![2.0, 1.0, 1.0]
+        );
+        let extremes = abacus.calc_growth_percentiles(&coverage, &core, &[1; 4], &[0.0, 100.0], 50);
+        assert_eq!(extremes, vec![vec![2.0, 1.0, 1.0], vec![2.0, 2.0, 1.0]]);
+        let rarefaction = abacus.calc_rarefaction(&coverage, &union, &[1; 4], &[1, 2, 3], 20);
+        assert_eq!(rarefaction[0], vec![2.0; 20]);
+        assert!(rarefaction[1].iter().all(|x| *x == 2.0 || *x == 3.0));
+        assert_eq!(rarefaction[2], vec![3.0; 20]);
+        // groups a and b form one population, c another; a and b are never both drawn first
+        let stratified = abacus.calc_stratified_growth(
+            &coverage,
+            &union,
+            &[1; 4],
+            &[Some(0), Some(0), Some(1)],
+            20,
+        );
+        assert_eq!(stratified, vec![2.0, 3.0]);
+    }
+
+    #[test]
+    fn test_random_streams() {
+        let abacus = AbacusByGroup {
+            count: CountType::Node,
+            r: vec![0, 0, 2, 3, 6],
+            v: None,
+            c: vec![0, 1, 2, 0, 1, 2].into(),
+            uncovered_bps: HashMap::new(),
+            groups: vec!["a".to_string(), "b".to_string(), "c".to_string()],
+        };
+        let (coverage, union) = (Threshold::Absolute(0), Threshold::Relative(0.0));
+        let populations = [Some(0), Some(1), Some(2)];
+        // estimates are reproducible
+        assert_eq!(
+            abacus.calc_stratified_growth(&coverage, &union, &[1; 4], &populations, 5),
+            abacus.calc_stratified_growth(&coverage, &union, &[1; 4], &populations, 5)
+        );
+        // but no two estimates draw the same orders
+        let draws = [
+            Draw::Percentiles,
+            Draw::Rarefaction(0),
+            Draw::Rarefaction(1),
+            Draw::Stratified,
+            Draw::Bootstrap,
+        ];
+        let streams: HashSet<u64> = draws
+            .iter()
+            .flat_map(|d| (0..100).map(move |i| AbacusByGroup::get_stream(*d, i)))
+            .collect();
+        assert_eq!(streams.len(), draws.len() * 100);
+    }
+
+    #[test]
+    fn test_calc_rarefaction_bp() {
+        // item 1 of 10 bp is covered by groups a and b, item 2 of 1 bp by c, and item 3 of 100 bp
+        // by all groups
+        let abacus = AbacusByGroup {
+            count: CountType::Bp,
+            r: vec![0, 0, 2, 3, 6],
+            v: None,
+            c: vec![0, 1, 2, 0, 1, 2].into(),
+            uncovered_bps: HashMap::new(),
+            groups: vec!["a".to_string(), "b".to_string(), "c".to_string()],
+        };
+        let (coverage, union) = (Threshold::Absolute(0), Threshold::Relative(0.0));
+        let rarefaction = abacus.calc_rarefaction(&coverage, &union, &[0, 10, 1, 100], &[1, 3], 20);
+        assert!(rarefaction[0].iter().all(|x| *x == 110.0 || *x == 101.0));
+        assert_eq!(rarefaction[1], vec![111.0; 20]);
+    }
+
+    #[test]
+    fn test_visit_entries() {
+        let abacus = AbacusByGroup {
+            count: CountType::Bp,
+            r: vec![0, 0, 2, 3],
+            v: Some(vec![1, 2, 1].into()),
+            c: vec![0, 1, 1].into(),
+            uncovered_bps: HashMap::from([(2, 1)]),
+            groups: vec!["a".to_string(), "b".to_string()],
+        };
+        let node_lens = vec![0, 5, 3];
+        let mut entries = Vec::new();
+        abacus
+            .visit_entries(false, &node_lens, |i, j, value| {
+                entries.push((i, j, value));
+                Ok(())
+            })
+            .unwrap();
+        assert_eq!(entries, vec![(1, 0, 5), (1, 1, 10), (2, 1, 2)]);
+        entries.clear();
+        abacus
+            .visit_entries(true, &node_lens, |i, j, value| {
+                entries.push((i, j, value));
+                Ok(())
+            })
+            .unwrap();
+        assert_eq!(entries, vec![(1, 0, 2), (2, 0, 1)]);
+    }
+
+    #[test]
+    fn test_merge_small_groups() {
+        let groups = get_load_groups_expected_hashmap(["s1", "s1", "s1", "s2"]);
+        let (merged, small) = GraphMask::merge_small_groups(groups.clone(), 2, false).unwrap();
+        assert_eq!(
+            merged,
+            get_load_groups_expected_hashmap(["s1", "s1", "s1", "other"])
+        );
+        assert!(small.is_empty());
+        let (kept, small) = GraphMask::merge_small_groups(groups.clone(), 2, true).unwrap();
+        assert_eq!(kept, groups);
+        assert_eq!(small, HashSet::from(["s2".to_string()]));
+        let (kept, small) = GraphMask::merge_small_groups(groups.clone(), 0, true).unwrap();
+        assert_eq!(kept, groups);
+        assert!(small.is_empty());
+
+        // small groups cannot be merged into a group named like the merged one
+        let groups = get_load_groups_expected_hashmap(["other", "other", "s1", "s2"]);
+        assert!(GraphMask::merge_small_groups(groups.clone(), 2, false).is_err());
+        let (kept, small) = GraphMask::merge_small_groups(groups.clone(), 2, true).unwrap();
+        assert_eq!(kept, groups);
+        assert_eq!(small, HashSet::from(["s1".to_string(), "s2".to_string()]));
+    }
+
     fn get_temporary_file_name_with_content(text: &str) -> Result<(NamedTempFile, String), Error> {
         let mut f = NamedTempFile::new()?;
         writeln!(f, "{}", text)?;
@@ -1306,7 +2731,8 @@ s1#1#2\tg2
 s1#2#2\tg1
 s2#1#2\tg2";
         let (_file, file_name) = get_temporary_file_name_with_content(text)?;
-        let calculated = GraphMask::load_groups(&file_name, false, false, &graph_storage)?;
+        let calculated =
+            GraphMask::load_groups(&file_name, false, false, &graph_storage.path_segments)?;
         assert_eq!(calculated, expected);
         Ok(())
     }
@@ -1315,7 +2741,7 @@ s2#1#2\tg2";
     fn test_load_groups_none() -> Result<(), Error> {
         let expected = get_load_groups_expected_hashmap(["s1#1#1", "s1#1#2", "s1#2#2", "s2#1#2"]);
         let graph_storage = get_graph_storage_path_segments();
-        let calculated = GraphMask::load_groups("", false, false, &graph_storage)?;
+        let calculated = GraphMask::load_groups("", false, false, &graph_storage.path_segments)?;
         assert_eq!(calculated, expected);
         Ok(())
     }
@@ -1342,6 +2768,38 @@ s1#1#1\t25\t109";
         Ok(())
     }
 
+    #[test]
+    fn test_load_coord_list_interval() -> Result<(), Error> {
+        let paths = get_graph_storage_path_segments().path_segments;
+        let expected = Some(vec![PathSegment::from_str("s1#1#2:1000000-2000000")]);
+        let calculated = GraphMask::load_coord_list("s1#1#2:1,000,000-2,000,000", &paths)?;
+        assert_eq!(calculated, expected);
+        assert!(GraphMask::load_coord_list("s1#1#2:2,000-1,000", &paths).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_add_reference_paths() -> Result<(), Error> {
+        let paths = vec![
+            PathSegment::from_str("GRCh38#0#chr1"),
+            PathSegment::from_str("GRCh38#0#chr2"),
+            PathSegment::from_str("CHM13.0.chr1"),
+            PathSegment::from_str("HG002#1#chr1"),
+        ];
+        let excluded = vec![PathSegment::from_str("GRCh38#0#chr1")];
+        let calculated = GraphMask::add_reference_paths(excluded, "GRCh38", None, &paths)?;
+        assert_eq!(calculated, paths[..2].to_vec());
+        let calculated =
+            GraphMask::add_reference_paths(Vec::new(), "GRCh\\d+|CHM13.*", None, &paths)?;
+        assert_eq!(calculated, paths[..3].to_vec());
+        let pansn = PanSN::from_str(".").unwrap();
+        let calculated = GraphMask::add_reference_paths(Vec::new(), "CHM13", Some(&pansn), &paths)?;
+        assert_eq!(calculated, vec![paths[2].clone()]);
+        assert!(GraphMask::add_reference_paths(Vec::new(), "HG", None, &paths)?.is_empty());
+        assert!(GraphMask::add_reference_paths(Vec::new(), "GRCh(38", None, &paths).is_err());
+        Ok(())
+    }
+
     #[test]
     fn test_complement_with_group_assignments_no_coords() -> Result<(), Error> {
         let expected: Option<Vec<PathSegment>> = None;
@@ -0,0 +1,399 @@
+/* standard use */
+use std::collections::{HashMap, HashSet};
+use std::io::Error;
+
+/* private use */
+use super::graph::{Edge, GraphStorage, ItemId, Orientation};
+use super::index::bufreader_from_graph;
+use super::util::PathReader;
+use crate::io::InputProblems;
+use crate::util::ItemIdSize;
+
+// the search for the exit of a bubble is aborted once it visits more nodes
+const MAX_BUBBLE_SIZE: usize = 10_000;
+
+type Step = (ItemId, Orientation);
+
+/// Top-level bubbles of the graph, i.e., superbubbles of the bidirected graph that are not nested
+/// in any other, together with the alleles by which paths/walks traverse them
+#[derive(Debug, Clone, Default)]
+pub struct BubbleIndex {
+    // entrance and exit of each bubble in its canonical orientation
+    bubbles: Vec<(Step, Step)>,
+    // entrances of the bubbles in either direction of traversal, mapped to the bubble, its exit,
+    // and whether the bubble is traversed in reverse
+    entrances: HashMap<Step, (usize, Step, bool)>,
+    // inner nodes of the bubbles
+    node2bubble: HashMap<ItemIdSize, usize>,
+    // alleles, i.e., bubble and inner steps in its canonical orientation, where the allele with
+    // ID i is stored at position i-1
+    alleles: Vec<(usize, Vec<Step>)>,
+    allele2id: HashMap<(usize, Vec<Step>), ItemId>,
+}
+
+fn vertex(step: Step) -> usize {
+    2 * step.0 .0 as usize + (step.1 == Orientation::Backward) as usize
+}
+
+fn step(v: usize) -> Step {
+    let o = if v % 2 == 0 {
+        Orientation::Forward
+    } else {
+        Orientation::Backward
+    };
+    (ItemId((v / 2) as ItemIdSize), o)
+}
+
+fn flip(step: Step) -> Step {
+    (step.0, step.1.flip())
+}
+
+impl BubbleIndex {
+    /// Detects the bubbles of the graph and collects the alleles of all its paths/walks
+    pub fn from_gfa(gfa_file: &str, graph_storage: &GraphStorage) -> Result<Self, Error> {
+        let edges = graph_storage
+            .edge2id
+            .as_ref()
+            .expect("bubble detection requires edge2id map in GraphStorage");
+        let mut index = Self::from_edges(graph_storage.node_count, edges.keys());
+
+        log::info!("collecting bubble alleles of paths/walks..");
+        let mut problems = InputProblems::new(gfa_file);
+        let mut data = bufreader_from_graph(gfa_file, graph_storage)?;
+        let mut paths = PathReader::new(&mut data, graph_storage);
+        while let Some((line, record)) = paths.next_path()? {
+            let steps = record.and_then(|(_, steps)| {
+                steps
+                    .to_item_vec(graph_storage)
+                    .map_err(|msg| (steps.col(), msg))
+            });
+            match steps {
+                Ok(steps) => {
+                    for (_, _, bubble, inner) in index.traverse(&steps) {
+                        let id = ItemId(index.alleles.len() as ItemIdSize + 1);
+                        if let std::collections::hash_map::Entry::Vacant(e) =
+                            index.allele2id.entry((bubble, inner))
+                        {
+                            index.alleles.push(e.key().clone());
+                            e.insert(id);
+                        }
+                    }
+                }
+                Err((col, msg)) => problems.push(line, col, msg),
+            }
+        }
+        problems.into_result()?;
+        log::info!(
+            "found: {} bubbles with {} alleles",
+            index.bubbles.len(),
+            index.alleles.len()
+        );
+        Ok(index)
+    }
+
+    fn from_edges<'a>(node_count: usize, edges: impl Iterator<Item = &'a Edge>) -> Self {
+        log::info!("detecting bubbles..");
+        let (out_edges, in_edges) = get_adjacencies(node_count, edges);
+        let candidates = find_bubbles(&out_edges, &in_edges);
+
+        // larger bubbles come first, such that nested ones can be discarded
+        let mut index = Self::default();
+        for (s, t, inner) in candidates {
+            let (s, t) = (step(s), step(t));
+            if index.node2bubble.contains_key(&s.0 .0) || index.node2bubble.contains_key(&t.0 .0) {
+                continue;
+            }
+            let bubble = index.bubbles.len();
+            index.bubbles.push((s, t));
+            index.entrances.insert(s, (bubble, t, false));
+            index.entrances.insert(flip(t), (bubble, flip(s), true));
+            for v in inner {
+                index.node2bubble.insert(step(v).0 .0, bubble);
+            }
+        }
+        index
+    }
+
+    // returns, for each bubble traversed by the path, the positions of its entrance and exit in
+    // the path, the bubble, and the inner steps in the bubble's canonical orientation; bubbles that
+    // are entered but not left (because the path ends) are skipped
+    fn traverse(&self, steps: &[Step]) -> Vec<(usize, usize, usize, Vec<Step>)> {
+        let mut res = Vec::new();
+        let mut i = 0;
+        while i < steps.len() {
+            if let Some(&(bubble, exit, reverse)) = self.entrances.get(&steps[i]) {
+                let mut j = i + 1;
+                while j < steps.len()
+                    && steps[j] != exit
+                    && self.node2bubble.get(&steps[j].0 .0) == Some(&bubble)
+                {
+                    j += 1;
+                }
+                if j < steps.len() && steps[j] == exit {
+                    let mut inner = steps[i + 1..j].to_vec();
+                    if reverse {
+                        inner.reverse();
+                        inner.iter_mut().for_each(|s| *s = flip(*s));
+                    }
+                    res.push((i, j, bubble, inner));
+                    // the exit can be the entrance of the next bubble
+                    i = j;
+                    continue;
+                }
+            }
+            i += 1;
+        }
+        res
+    }
+
+    /// Number of alleles, which are identified by IDs 1..=allele_count()
+    pub fn allele_count(&self) -> usize {
+        self.alleles.len()
+    }
+
+    /// Alleles by which the path traverses bubbles, given by the positions of the bubble's
+    /// entrance and exit in the path and the allele's ID
+    pub fn get_path_alleles(&self, steps: &[Step]) -> Result<Vec<(usize, usize, ItemId)>, String> {
+        self.traverse(steps)
+            .into_iter()
+            .map(|(i, j, bubble, inner)| {
+                self.allele2id
+                    .get(&(bubble, inner))
+                    .map(|id| (i, j, *id))
+                    .ok_or_else(|| {
+                        format!(
+                            "path traverses bubble {}{}{}{} with an allele that is unknown",
+                            steps[i].1, steps[i].0 .0, steps[j].1, steps[j].0 .0
+                        )
+                    })
+            })
+            .collect()
+    }
+
+    /// Steps of the allele with the given ID, including the entrance and exit of its bubble
+    pub fn get_allele_steps(&self, id: ItemId) -> Vec<Step> {
+        let (bubble, inner) = &self.alleles[id.0 as usize - 1];
+        let (s, t) = self.bubbles[*bubble];
+        let mut res = Vec::with_capacity(inner.len() + 2);
+        res.push(s);
+        res.extend(inner.iter().copied());
+        res.push(t);
+        res
+    }
+}
+
+/// Nesting of all bubbles of the graph, including those nested in others, and the branching nodes,
+/// i.e., nodes with more than one neighbor on either of their sides
+#[derive(Debug, Clone, Default)]
+pub struct BubbleStats {
+    // inner nodes and nesting depth of each bubble, where top-level bubbles have depth 1
+    pub bubbles: Vec<(Vec<ItemIdSize>, usize)>,
+    pub branching_nodes: Vec<ItemIdSize>,
+}
+
+impl BubbleStats {
+    pub fn from_edges<'a>(node_count: usize, edges: impl Iterator<Item = &'a Edge>) -> Self {
+        log::info!("detecting nested bubbles..");
+        let (out_edges, in_edges) = get_adjacencies(node_count, edges);
+        let branching_nodes = (1..=node_count as ItemIdSize)
+            .filter(|n| {
+                [Orientation::Forward, Orientation::Backward]
+                    .iter()
+                    .any(|o| out_edges[vertex((ItemId(*n), *o))].len() > 1)
+            })
+            .collect();
+
+        // a bubble is nested in the innermost of the larger bubbles that contain its entrance
+        let mut stats = Self {
+            bubbles: Vec::new(),
+            branching_nodes,
+        };
+        let mut innermost: HashMap<ItemIdSize, usize> = HashMap::new();
+        for (s, _, inner) in find_bubbles(&out_edges, &in_edges) {
+            let depth = innermost
+                .get(&step(s).0 .0)
+                .map(|b| stats.bubbles[*b].1 + 1)
+                .unwrap_or(1);
+            let mut nodes: Vec<ItemIdSize> = inner.into_iter().map(|v| step(v).0 .0).collect();
+            nodes.sort_unstable();
+            nodes.dedup();
+            for n in nodes.iter() {
+                innermost.insert(*n, stats.bubbles.len());
+            }
+            stats.bubbles.push((nodes, depth));
+        }
+        log::info!(
+            "found: {} bubbles and {} branching nodes",
+            stats.bubbles.len(),
+            stats.branching_nodes.len()
+        );
+        stats
+    }
+}
+
+// adjacencies of the directed graph of oriented nodes, where each node is represented by two
+// vertices, one per orientation
+fn get_adjacencies<'a>(
+    node_count: usize,
+    edges: impl Iterator<Item = &'a Edge>,
+) -> (Vec<Vec<usize>>, Vec<Vec<usize>>) {
+    let mut out_edges: Vec<Vec<usize>> = vec![Vec::new(); 2 * (node_count + 1)];
+    let mut in_edges: Vec<Vec<usize>> = vec![Vec::new(); 2 * (node_count + 1)];
+    for Edge(a, oa, b, ob) in edges {
+        for (u, w) in [
+            (vertex((*a, *oa)), vertex((*b, *ob))),
+            (vertex((*b, ob.flip())), vertex((*a, oa.flip()))),
+        ] {
+            out_edges[u].push(w);
+            in_edges[w].push(u);
+        }
+    }
+    for adj in out_edges.iter_mut().chain(in_edges.iter_mut()) {
+        adj.sort_unstable();
+        adj.dedup();
+    }
+    (out_edges, in_edges)
+}
+
+// all bubbles, including nested ones, given by their entrance, exit, and inner vertices; bubbles
+// are found from both of their ends, of which the canonical one is kept, and larger bubbles come
+// first
+fn find_bubbles(
+    out_edges: &[Vec<usize>],
+    in_edges: &[Vec<usize>],
+) -> Vec<(usize, usize, HashSet<usize>)> {
+    let mut candidates: Vec<(usize, usize, HashSet<usize>)> = (2..out_edges.len())
+        .filter(|s| out_edges[*s].len() > 1)
+        .filter_map(|s| find_exit(s, out_edges, in_edges).map(|(t, inner)| (s, t, inner)))
+        .filter(|(s, t, _)| *s <= vertex(flip(step(*t))))
+        .collect();
+    candidates.sort_by_key(|(s, _, inner)| (usize::MAX - inner.len(), *s));
+    candidates
+}
+
+// superbubble detection (Onodera et al., 2013) from entrance s in the directed graph of oriented
+// nodes; returns the exit and the inner vertices of the bubble
+fn find_exit(
+    s: usize,
+    out_edges: &[Vec<usize>],
+    in_edges: &[Vec<usize>],
+) -> Option<(usize, HashSet<usize>)> {
+    let mut visited: HashSet<usize> = HashSet::new();
+    let mut seen: HashSet<usize> = HashSet::from([s]);
+    let mut stack = vec![s];
+    while let Some(v) = stack.pop() {
+        visited.insert(v);
+        seen.remove(&v);
+        if out_edges[v].is_empty() || visited.len() > MAX_BUBBLE_SIZE {
+            // tip or too large
+            return None;
+        }
+        for &u in &out_edges[v] {
+            if u == s {
+                // cycle through entrance
+                return None;
+            }
+            seen.insert(u);
+            if in_edges[u].iter().all(|w| visited.contains(w)) {
+                stack.push(u);
+            }
+        }
+        if stack.len() == 1 && seen.len() == 1 && seen.contains(&stack[0]) {
+            let t = stack[0];
+            if out_edges[t].contains(&s) {
+                return None;
+            }
+            visited.remove(&s);
+            return Some((t, visited));
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fwd(id: ItemIdSize) -> Step {
+        (ItemId(id), Orientation::Forward)
+    }
+
+    fn edge(a: Step, b: Step) -> Edge {
+        Edge(a.0, a.1, b.0, b.1)
+    }
+
+    #[test]
+    fn test_bubble_alleles() {
+        // 1 -> {2, 3} -> 4 -> {5 -> 6, 6} -> 7, with a nested bubble 1 -> 2 -> {8, 9} -> 3 -> 4
+        let edges = vec![
+            edge(fwd(1), fwd(2)),
+            edge(fwd(1), fwd(3)),
+            edge(fwd(2), fwd(8)),
+            edge(fwd(2), fwd(9)),
+            edge(fwd(8), fwd(3)),
+            edge(fwd(9), fwd(3)),
+            edge(fwd(2), fwd(4)),
+            edge(fwd(3), fwd(4)),
+            edge(fwd(4), fwd(5)),
+            edge(fwd(4), fwd(6)),
+            edge(fwd(5), fwd(6)),
+            edge(fwd(6), fwd(7)),
+        ];
+        let index = BubbleIndex::from_edges(9, edges.iter());
+        assert_eq!(index.bubbles, vec![(fwd(1), fwd(4)), (fwd(4), fwd(6))]);
+
+        let path = vec![
+            fwd(1),
+            fwd(2),
+            fwd(8),
+            fwd(3),
+            fwd(4),
+            fwd(5),
+            fwd(6),
+            fwd(7),
+        ];
+        let traversals = index.traverse(&path);
+        assert_eq!(
+            traversals,
+            vec![
+                (0, 4, 0, vec![fwd(2), fwd(8), fwd(3)]),
+                (4, 6, 1, vec![fwd(5)])
+            ]
+        );
+
+        // reverse traversal yields the same alleles
+        let reverse: Vec<Step> = path.iter().rev().map(|s| flip(*s)).collect();
+        let traversals = index.traverse(&reverse);
+        assert_eq!(
+            traversals,
+            vec![
+                (1, 3, 1, vec![fwd(5)]),
+                (3, 7, 0, vec![fwd(2), fwd(8), fwd(3)])
+            ]
+        );
+
+        // paths ending within a bubble do not contribute an allele
+        assert_eq!(index.traverse(&path[..3]), Vec::new());
+    }
+
+    #[test]
+    fn test_bubble_stats() {
+        // 1 -> {2 -> {3, 4} -> 5, 6} -> 7, with bubble 2 -> 5 nested in bubble 1 -> 7
+        let edges = vec![
+            edge(fwd(1), fwd(2)),
+            edge(fwd(1), fwd(6)),
+            edge(fwd(2), fwd(3)),
+            edge(fwd(2), fwd(4)),
+            edge(fwd(3), fwd(5)),
+            edge(fwd(4), fwd(5)),
+            edge(fwd(5), fwd(7)),
+            edge(fwd(6), fwd(7)),
+        ];
+        let stats = BubbleStats::from_edges(7, edges.iter());
+        assert_eq!(
+            stats.bubbles,
+            vec![(vec![2, 3, 4, 5, 6], 1), (vec![3, 4], 2)]
+        );
+        assert_eq!(stats.branching_nodes, vec![1, 2, 5, 7]);
+    }
+}
@@ -1,21 +1,35 @@
 /* standard use */
+use memchr::{memchr, memchr3};
 use once_cell::sync::Lazy;
 use regex::Regex;
-use std::collections::HashMap;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, HashSet};
 use std::fmt;
-use std::hash::Hash;
-use std::io::BufRead;
+use std::hash::{Hash, Hasher};
+use std::io::{BufRead, Error};
 use std::str::{self, FromStr};
 
 /* private use */
-use crate::io::bufreader_from_compressed_gfa;
+use super::bubble::BubbleIndex;
+use super::index::{GraphIndex, IndexedPaths};
+use super::stable::{StableCoords, StablePaths};
+use super::util::{
+    parse_length_tag, parse_path_identifier, parse_walk_identifier, read_segment_line,
+};
+use crate::io::{bufreader_from_compressed_gfa, is_multi_file_graph, InputProblems};
 use crate::util::*;
 use crate::util::{CountType, ItemIdSize};
 use serde::{Deserialize, Serialize};
 
 static PATHID_PANSN: Lazy<Regex> =
     Lazy::new(|| Regex::new(r"^([^#]+)(#[^#]+)?(#[^#].*)?$").unwrap());
-static PATHID_COORDS: Lazy<Regex> = Lazy::new(|| Regex::new(r"^(.+):([0-9]+)-([0-9]+)$").unwrap());
+// coordinates may contain thousands separators, e.g., chr1:1,000,000-2,000,000
+static PATHID_COORDS: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^(.+):([0-9][0-9,]*)-([0-9][0-9,]*)$").unwrap());
+
+fn parse_coordinate(s: &str) -> Option<usize> {
+    usize::from_str(&s.replace(',', "")).ok()
+}
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub enum Orientation {
@@ -97,40 +111,52 @@ impl fmt::Display for ItemId {
 pub struct Edge(pub ItemId, pub Orientation, pub ItemId, pub Orientation);
 
 impl Edge {
-    pub fn from_link(data: &[u8], node2id: &HashMap<Vec<u8>, ItemId>, canonical: bool) -> Self {
-        let (start, mut iter) = match data[0] {
-            b'L' => (2, data[2..].iter()),
-            _ => (0, data.iter()),
+    // malformed links are reported as (1-based column, message)
+    pub fn from_link(
+        data: &[u8],
+        node2id: &HashMap<Vec<u8>, ItemId>,
+        canonical: bool,
+    ) -> Result<Self, (usize, String)> {
+        let (skip, line) = match data.first() {
+            Some(b'L') => (1, data.get(2..).unwrap_or(&[])),
+            _ => (0, data),
         };
+        let fields: Vec<&[u8]> = line
+            .split(|&x| x == b'\t' || x == b'\n' || x == b'\r')
+            .take(4)
+            .collect();
+        if fields.len() < 4 {
+            return Err((0, "link must have at least 5 columns".to_string()));
+        }
 
-        let end = start + iter.position(|&x| x == b'\t').unwrap();
-        let u = node2id.get(&data[start..end]).unwrap_or_else(|| {
-            panic!(
-                "unknown node {}",
-                str::from_utf8(&data[start..end]).unwrap()
-            )
-        });
-
-        // we know that 3rd colum is either '+' or '-', so it has always length 1; still, we
-        // need to advance in the buffer (and  therefore call iter.position(..))
-        iter.position(|&x| x == b'\t');
-        let o1 = Orientation::from_pm(data[end + 1]);
-
-        let start = end + 3;
-        let end = start + iter.position(|&x| x == b'\t').unwrap();
+        let node = |i: usize| {
+            node2id.get(fields[i]).copied().ok_or_else(|| {
+                (
+                    skip + i + 1,
+                    format!("unknown node {}", String::from_utf8_lossy(fields[i])),
+                )
+            })
+        };
+        let orientation = |i: usize| match fields[i] {
+            [c @ b'+'] | [c @ b'-'] => Ok(Orientation::from_pm(*c)),
+            x => Err((
+                skip + i + 1,
+                format!(
+                    "expected '+' or '-', but got '{}'",
+                    String::from_utf8_lossy(x)
+                ),
+            )),
+        };
 
-        let v = node2id.get(&data[start..end]).unwrap_or_else(|| {
-            panic!(
-                "unknown node {}",
-                str::from_utf8(&data[start..end]).unwrap()
-            )
-        });
-        let o2 = Orientation::from_pm(data[end + 1]);
+        let u = node(0)?;
+        let o1 = orientation(1)?;
+        let v = node(2)?;
+        let o2 = orientation(3)?;
 
         if canonical {
-            Self::canonical(*u, o1, *v, o2)
+            Ok(Self::canonical(u, o1, v, o2))
         } else {
-            Self(*u, o1, *v, o2)
+            Ok(Self(u, o1, v, o2))
         }
     }
 
@@ -164,6 +190,10 @@ pub fn get_extremities(node_dna: &[u8], k: usize) -> (u64, u64) {
     (left, right)
 }
 
+// groups of duplicated nodes, each given with its orientation relative to the first node of
+// its group
+type DuplicateNodes = Vec<Vec<(ItemId, Orientation)>>;
+
 #[derive(Debug, Clone)]
 pub struct GraphStorage {
     node2id: HashMap<Vec<u8>, ItemId>,
@@ -174,6 +204,26 @@ pub struct GraphStorage {
     pub node_count: usize,
     pub edge_count: usize,
     pub degree: Option<Vec<u32>>,
+    pub bubbles: Option<BubbleIndex>,
+    // representative of each node among the nodes with identical sequence, if duplicates are
+    // merged
+    pub node_representatives: Option<Vec<ItemIdSize>>,
+    // orientation of each node relative to its representative if duplicates are merged
+    // strand-aware, or none for palindromic nodes whose sequence reads the same on both strands
+    pub node_strands: Option<Vec<Option<Orientation>>>,
+    // count edges traversed in opposite orientations as different countables; the reverse
+    // traversal of edge i has id i + edge_count
+    pub oriented_edges: bool,
+    // stable coordinates of the segments of an rGFA graph, given by their tags
+    pub stable_coords: StableCoords,
+    // paths/walks spelling out the stable sequences of an rGFA graph, which are used to map
+    // subset and exclude coordinates given in stable coordinates
+    pub stable_paths: Option<StablePaths>,
+    // number of path segments given by W lines rather than P lines
+    pub walk_count: usize,
+    // steps of all paths/walks if the graph is loaded from an index, in which case the graph file
+    // is not read again
+    pub indexed_paths: Option<IndexedPaths>,
     // pub extremities: Option<Vec<(u64, u64)>>,
 }
 
@@ -188,16 +238,31 @@ impl GraphStorage {
             node_count: 0,
             edge_count: 0,
             degree: None,
+            bubbles: None,
+            node_representatives: None,
+            node_strands: None,
+            oriented_edges: false,
+            stable_coords: StableCoords::default(),
+            stable_paths: None,
+            walk_count: 0,
+            indexed_paths: None,
             is_nice: false,
         }
     }
 
-    pub fn from_gfa(gfa_file: &str, is_nice: bool, count_type: CountType) -> Self {
-        let (node2id, path_segments, node_lens, _extremities) =
-            Self::parse_nodes_gfa(gfa_file, None);
+    pub fn from_gfa(gfa_file: &str, is_nice: bool, count_type: CountType) -> Result<Self, Error> {
+        // segment ids of multi-file graphs are prefixed and therefore no longer numeric
+        let is_nice = if is_nice && is_multi_file_graph(gfa_file) {
+            log::warn!("ignoring nice flag for graph consisting of multiple files");
+            false
+        } else {
+            is_nice
+        };
+        let (node2id, path_segments, walk_count, node_lens, stable_coords, _extremities) =
+            Self::parse_nodes_gfa(gfa_file, None)?;
         let index_edges: bool = (count_type == CountType::Edge) | (count_type == CountType::All);
         let (edge2id, edge_count, degree) = if index_edges {
-            let (edge2id, edge_count, degree) = Self::parse_edge_gfa(gfa_file, &node2id);
+            let (edge2id, edge_count, degree) = Self::parse_edge_gfa(gfa_file, &node2id)?;
             (Some(edge2id), edge_count, Some(degree))
         } else {
             (None, 0, None)
@@ -205,7 +270,7 @@ impl GraphStorage {
         let node_count = node2id.len();
         log::debug!("Done creating GraphStorage");
 
-        Self {
+        Ok(Self {
             node2id,
             is_nice,
             node_lens,
@@ -214,8 +279,284 @@ impl GraphStorage {
             node_count,
             edge_count,
             degree,
+            bubbles: None,
+            node_representatives: None,
+            node_strands: None,
+            oriented_edges: false,
+            stable_coords,
+            stable_paths: None,
+            walk_count,
+            indexed_paths: None,
             // extremities,
+        })
+    }
+
+    /// Loads the graph from an index written by `panacus index`, whose paths/walks are already
+    /// parsed, such that the graph file is not read at all
+    pub fn from_index(index_file: &str) -> Result<Self, Error> {
+        let index = GraphIndex::read(index_file)?;
+        let node2id = index.get_node2id();
+        let mut edge2id = HashMap::default();
+        let mut degree: Vec<u32> = vec![0; node2id.len() + 1];
+        for (i, edge) in index.edges.iter().enumerate() {
+            degree[edge.0 .0 as usize] += 1;
+            degree[edge.2 .0 as usize] += 1;
+            edge2id.insert(*edge, ItemId(i as ItemIdSize + 1));
+        }
+        log::info!(
+            "found: {} nodes, {} edges, and {} paths/walks",
+            node2id.len(),
+            edge2id.len(),
+            index.path_segments.len()
+        );
+
+        Ok(Self {
+            node_count: node2id.len(),
+            node2id,
+            is_nice: false,
+            node_lens: index.node_lens,
+            edge_count: edge2id.len(),
+            edge2id: Some(edge2id),
+            path_segments: index.path_segments,
+            degree: Some(degree),
+            bubbles: None,
+            node_representatives: None,
+            node_strands: None,
+            oriented_edges: false,
+            stable_coords: index.stable_coords,
+            stable_paths: None,
+            walk_count: index.walk_count,
+            indexed_paths: Some(index.paths),
+        })
+    }
+
+    /// Groups of nodes whose sequences are identical or reverse-complementary identical; each
+    /// node is given with its orientation relative to the first node of its group
+    pub fn find_duplicate_nodes(
+        &self,
+        gfa_file: &str,
+    ) -> Result<Vec<Vec<(ItemId, Orientation)>>, Error> {
+        Ok(self
+            .find_duplicate_and_palindromic_nodes(gfa_file, false)?
+            .0)
+    }
+
+    // groups of duplicated nodes as given by find_duplicate_nodes and, if asked for, the nodes
+    // whose sequence equals its reverse complement, which are found in the same pass over the
+    // node sequences; nodes with IUPAC codes other than N are never palindromic
+    fn find_duplicate_and_palindromic_nodes(
+        &self,
+        gfa_file: &str,
+        palindromes: bool,
+    ) -> Result<(DuplicateNodes, Vec<ItemId>), Error> {
+        log::info!("hashing node sequences to find duplicates..");
+        // nodes are grouped by hash and length of the lexicographically smaller one of their
+        // sequence and its reverse complement; only the sequences of nodes sharing a group are
+        // read again to tell duplicates from hash collisions
+        let mut classes: HashMap<(u64, usize), Vec<(ItemId, Orientation)>> = HashMap::new();
+        let mut palindromic = Vec::new();
+        // nodes without sequence cannot be compared and are not visited
+        self.visit_node_sequences(gfa_file, |id, seq| {
+            let (canonical, o) = canonical_sequence(seq);
+            if palindromes
+                && canonical.iter().all(|c| b"ACGTN".contains(c))
+                && reverse_complement(&canonical) == canonical
+            {
+                palindromic.push(id);
+            }
+            let mut hasher = DefaultHasher::new();
+            canonical.hash(&mut hasher);
+            classes
+                .entry((hasher.finish(), canonical.len()))
+                .or_default()
+                .push((id, o));
+        })?;
+
+        let classes: Vec<Vec<(ItemId, Orientation)>> =
+            classes.into_values().filter(|c| c.len() > 1).collect();
+        let candidates: HashSet<ItemId> = classes.iter().flatten().map(|(id, _)| *id).collect();
+        let sequences = if candidates.is_empty() {
+            HashMap::new()
+        } else {
+            self.get_node_sequences(gfa_file, &candidates)?
+        };
+
+        let mut res: Vec<Vec<(ItemId, Orientation)>> = classes
+            .into_iter()
+            .flat_map(|c| split_by_sequence(c, &sequences))
+            .filter(|c| c.len() > 1)
+            .map(|mut c| {
+                c.sort();
+                if c[0].1 == Orientation::Backward {
+                    c.iter_mut().for_each(|x| x.1 = x.1.flip());
+                }
+                c
+            })
+            .collect();
+        res.sort();
+        log::info!(
+            "found: {} groups of duplicated nodes comprising {} nodes",
+            res.len(),
+            res.iter().map(|c| c.len()).sum::<usize>()
+        );
+        if palindromes {
+            log::info!("found: {} palindromic nodes", palindromic.len());
+        }
+        Ok((res, palindromic))
+    }
+
+    /// Sequences of the given nodes; nodes without sequence are omitted
+    pub fn get_node_sequences(
+        &self,
+        gfa_file: &str,
+        nodes: &HashSet<ItemId>,
+    ) -> Result<HashMap<ItemId, Vec<u8>>, Error> {
+        log::info!("reading sequences of {} nodes..", nodes.len());
+        let mut res = HashMap::new();
+        self.visit_node_sequences(gfa_file, |id, seq| {
+            if nodes.contains(&id) {
+                res.insert(id, seq.to_vec());
+            }
+        })?;
+        Ok(res)
+    }
+
+    /// Calls the visitor with the sequence of each node, in the order of the graph file, without
+    /// holding the sequences in memory; nodes without sequence are skipped
+    pub fn visit_node_sequences<F: FnMut(ItemId, &[u8])>(
+        &self,
+        gfa_file: &str,
+        mut visitor: F,
+    ) -> Result<(), Error> {
+        let mut problems = InputProblems::new(gfa_file);
+
+        let mut line = 0;
+        let mut buf = vec![];
+        let mut data = bufreader_from_compressed_gfa(gfa_file)?;
+        while data.read_until(b'\n', &mut buf)? > 0 {
+            line += 1;
+            if buf[0] == b'S' {
+                let mut fields = buf
+                    .get(2..)
+                    .unwrap_or(&[])
+                    .split(|&c| c == b'\t' || c == b'\n' || c == b'\r');
+                let name = fields.next().unwrap_or(&[]);
+                let seq = fields.next().unwrap_or(&[]);
+                match self.get_node_id(name) {
+                    Some(_) if seq.is_empty() || seq == b"*" => (),
+                    Some(id) => visitor(id, seq),
+                    None => problems.push(
+                        line,
+                        2,
+                        format!("unknown segment {}", String::from_utf8_lossy(name)),
+                    ),
+                }
+            }
+            buf.clear();
+        }
+        problems.into_result()
+    }
+
+    /// Number of A, C, G, T, and other (N and IUPAC codes) bases of the sequence of each node;
+    /// nodes without sequence are counted as all other bases
+    pub fn count_node_bases(&self, gfa_file: &str) -> Result<Vec<[u32; 5]>, Error> {
+        log::info!("counting bases of node sequences..");
+        let mut res: Vec<[u32; 5]> = self.node_lens.iter().map(|l| [0, 0, 0, 0, *l]).collect();
+        self.visit_node_sequences(gfa_file, |id, seq| {
+            let mut counts = [0; 5];
+            for c in seq {
+                counts[match c {
+                    b'A' | b'a' => 0,
+                    b'C' | b'c' => 1,
+                    b'G' | b'g' => 2,
+                    b'T' | b't' => 3,
+                    _ => 4,
+                }] += 1;
+            }
+            res[id.0 as usize] = counts;
+        })?;
+        Ok(res)
+    }
+
+    /// Merges nodes with identical or reverse-complementary identical sequences, such that each
+    /// group of duplicates is counted as a single node; if strand-aware, the orientation of each
+    /// node relative to its representative is kept to place covered bp on the representative
+    pub fn dedup_nodes(&mut self, gfa_file: &str, strand_aware: bool) -> Result<(), Error> {
+        let mut representatives: Vec<ItemIdSize> = (0..self.node_count as ItemIdSize + 1).collect();
+        let mut strands = vec![Some(Orientation::Forward); representatives.len()];
+        let (duplicates, palindromic) =
+            self.find_duplicate_and_palindromic_nodes(gfa_file, strand_aware)?;
+        for class in duplicates {
+            for (id, o) in &class[1..] {
+                representatives[id.0 as usize] = class[0].0 .0;
+                strands[id.0 as usize] = Some(*o);
+            }
+        }
+        if strand_aware {
+            for id in palindromic {
+                strands[id.0 as usize] = None;
+            }
+            self.node_strands = Some(strands);
+        }
+        self.node_representatives = Some(representatives);
+        Ok(())
+    }
+
+    /// Node and interval [a, b) on its forward strand whose sequence is covered by the interval
+    /// [a, b) of a traversal of the node in the given orientation; if duplicates are merged
+    /// strand-aware, the interval is placed on the representative of the node, and palindromic
+    /// nodes cover the same interval in both orientations
+    pub fn get_covered_interval(
+        &self,
+        sid: ItemId,
+        o: Orientation,
+        a: usize,
+        b: usize,
+    ) -> (ItemId, usize, usize) {
+        let l = self.node_len(&sid) as usize;
+        let (sid, o) = match (&self.node_representatives, &self.node_strands) {
+            (Some(representatives), Some(strands)) => {
+                let representative = ItemId(representatives[sid.0 as usize]);
+                match strands[sid.0 as usize] {
+                    Some(Orientation::Backward) => (representative, o.flip()),
+                    Some(Orientation::Forward) => (representative, o),
+                    None => (representative, Orientation::Forward),
+                }
+            }
+            _ => (sid, o),
+        };
+        match o {
+            Orientation::Forward => (sid, a, b),
+            Orientation::Backward => (sid, l - b, l - a),
+        }
+    }
+
+    /// Replaces nodes of the item table by their representative if duplicates are merged
+    pub fn dedup_item_table(&self, item_table: &mut ItemTable) {
+        if let Some(representatives) = &self.node_representatives {
+            item_table
+                .items
+                .iter_mut()
+                .for_each(|sid| *sid = representatives[*sid as usize]);
+        }
+    }
+
+    /// Detects bubbles and indexes their alleles, which are the countables of count type bubble
+    /// Indexes the edges of the graph unless they have already been indexed
+    pub fn index_edges(&mut self, gfa_file: &str) -> Result<(), Error> {
+        if self.edge2id.is_none() {
+            let (edge2id, edge_count, degree) = Self::parse_edge_gfa(gfa_file, &self.node2id)?;
+            self.edge2id = Some(edge2id);
+            self.edge_count = edge_count;
+            self.degree = Some(degree);
         }
+        Ok(())
+    }
+
+    pub fn index_bubbles(&mut self, gfa_file: &str) -> Result<(), Error> {
+        self.index_edges(gfa_file)?;
+        self.bubbles = Some(BubbleIndex::from_gfa(gfa_file, self)?);
+        Ok(())
     }
 
     #[inline]
@@ -224,8 +565,7 @@ impl GraphStorage {
         if self.is_nice {
             unsafe {
                 let node_string = str::from_utf8_unchecked(node_name);
-                let node_number: ItemIdSize = node_string.parse().expect("Node id is fine");
-                Some(ItemId(node_number))
+                node_string.parse::<ItemIdSize>().ok().map(ItemId)
             }
         } else {
             self.node2id.get(node_name).cloned()
@@ -236,6 +576,15 @@ impl GraphStorage {
         self.node2id.values().cloned().collect()
     }
 
+    /// Names of the nodes, indexed by node ID
+    pub fn get_node_names(&self) -> Vec<Vec<u8>> {
+        let mut res = vec![Vec::new(); self.node_count + 1];
+        for (name, id) in &self.node2id {
+            res[id.0 as usize] = name.clone();
+        }
+        res
+    }
+
     pub fn get_node_tuples(&self) -> Vec<(Vec<u8>, ItemId)> {
         self.node2id
             .iter()
@@ -268,94 +617,219 @@ impl GraphStorage {
     pub fn number_of_items(&self, c: &CountType) -> usize {
         match c {
             &CountType::Node | &CountType::Bp => self.node_count,
+            &CountType::Edge if self.oriented_edges => 2 * self.edge_count,
             &CountType::Edge => self.edge_count,
+            &CountType::Bubble => self
+                .bubbles
+                .as_ref()
+                .expect("bubble count requires bubble index in GraphStorage")
+                .allele_count(),
             &CountType::All => unreachable!("inadmissible count type"),
         }
     }
 
+    /// Countable id of the edge traversed from u to v, which depends on the direction of
+    /// traversal only if edges are oriented
+    pub fn get_edge_id(
+        &self,
+        u: ItemId,
+        o1: Orientation,
+        v: ItemId,
+        o2: Orientation,
+    ) -> Result<ItemId, String> {
+        let e = Edge::canonical(u, o1, v, o2);
+        let id = self
+            .edge2id
+            .as_ref()
+            .expect("edge ids require edge2id map in GraphStorage")
+            .get(&e)
+            .ok_or_else(|| format!("path traverses edge {}, which is not part of the graph", &e))?;
+        if self.oriented_edges && e != Edge(u, o1, v, o2) {
+            Ok(ItemId(id.0 + self.edge_count as ItemIdSize))
+        } else {
+            Ok(*id)
+        }
+    }
+
+    /// Edge of each countable id, given in the direction in which it is traversed
+    pub fn get_id2edge(&self) -> Vec<Edge> {
+        let mut id2edge = vec![Edge::default(); self.number_of_items(&CountType::Edge) + 1];
+        if let Some(edge2id) = &self.edge2id {
+            for (edge, id) in edge2id.iter() {
+                id2edge[id.0 as usize] = *edge;
+                if self.oriented_edges {
+                    id2edge[id.0 as usize + self.edge_count] = edge.flip();
+                }
+            }
+        }
+        id2edge
+    }
+
     pub fn parse_edge_gfa(
         gfa_file: &str,
         node2id: &HashMap<Vec<u8>, ItemId>,
-    ) -> (HashMap<Edge, ItemId>, usize, Vec<u32>) {
+    ) -> Result<(HashMap<Edge, ItemId>, usize, Vec<u32>), Error> {
         let mut edge2id = HashMap::default();
         let mut degree: Vec<u32> = vec![0; node2id.len() + 1];
         let mut edge_id: ItemIdSize = 1;
+        let mut problems = InputProblems::new(gfa_file);
 
+        let mut line = 0;
         let mut buf = vec![];
-        let mut data = bufreader_from_compressed_gfa(gfa_file);
-        while data.read_until(b'\n', &mut buf).unwrap_or(0) > 0 {
+        let mut data = bufreader_from_compressed_gfa(gfa_file)?;
+        while data.read_until(b'\n', &mut buf)? > 0 {
+            line += 1;
             if buf[0] == b'L' {
-                let edge = Edge::from_link(&buf[..], node2id, true);
-                if let std::collections::hash_map::Entry::Vacant(e) = edge2id.entry(edge) {
-                    degree[edge.0 .0 as usize] += 1;
-                    //if e.0.0 != e.2.0 {
-                    degree[edge.2 .0 as usize] += 1;
-                    //}
-                    e.insert(ItemId(edge_id));
-                    edge_id += 1;
-                } else {
-                    log::warn!("edge {} is duplicated in GFA", &edge);
+                match Edge::from_link(&buf[..], node2id, true) {
+                    Ok(edge) => {
+                        if let std::collections::hash_map::Entry::Vacant(e) = edge2id.entry(edge) {
+                            degree[edge.0 .0 as usize] += 1;
+                            //if e.0.0 != e.2.0 {
+                            degree[edge.2 .0 as usize] += 1;
+                            //}
+                            e.insert(ItemId(edge_id));
+                            edge_id += 1;
+                        } else {
+                            log::warn!("edge {} is duplicated in GFA", &edge);
+                        }
+                    }
+                    Err((col, msg)) => problems.push(line, col, msg),
                 }
             }
             buf.clear();
         }
+        problems.into_result()?;
         let edge_count = edge2id.len();
         log::info!("found: {} edges", edge_count);
 
-        (edge2id, edge_count, degree)
+        Ok((edge2id, edge_count, degree))
     }
 
     pub fn parse_nodes_gfa(
         gfa_file: &str,
         k: Option<usize>,
-    ) -> (
-        HashMap<Vec<u8>, ItemId>,
-        Vec<PathSegment>,
-        Vec<u32>,
-        Option<Vec<(u64, u64)>>,
-    ) {
+    ) -> Result<
+        (
+            HashMap<Vec<u8>, ItemId>,
+            Vec<PathSegment>,
+            usize,
+            Vec<u32>,
+            StableCoords,
+            Option<Vec<(u64, u64)>>,
+        ),
+        Error,
+    > {
         let mut node2id: HashMap<Vec<u8>, ItemId> = HashMap::default();
         let mut path_segments: Vec<PathSegment> = Vec::new();
+        let mut walk_count = 0;
         let mut node_lens: Vec<u32> = Vec::new();
+        // stable coordinates of rGFA graphs are collected in the same pass
+        let mut stable_coords = StableCoords::default();
         let mut extremities: Vec<(u64, u64)> = Vec::new();
+        let mut problems = InputProblems::new(gfa_file);
 
         log::info!("constructing indexes for node/edge IDs, node lengths, and P/W lines..");
         node_lens.push(u32::MIN); // add empty element to node_lens to make it in sync with node_id
         let mut node_id = 1; // important: id must be > 0, otherwise counting procedure will produce errors
 
+        let mut line = 0;
         let mut buf = vec![];
-        let mut data = bufreader_from_compressed_gfa(gfa_file);
-        while data.read_until(b'\n', &mut buf).unwrap_or(0) > 0 {
+        // number of segments with neither sequence nor LN tag
+        let mut unknown_lens = 0;
+        let mut data = bufreader_from_compressed_gfa(gfa_file)?;
+        loop {
+            // sequences are only needed for k-mer extremities, otherwise they are skipped while
+            // reading and only their length is kept
+            let seq_len = match data.fill_buf()?.first().copied() {
+                None => break,
+                Some(b'S') if k.is_none() => read_segment_line(&mut data, &mut buf)?,
+                Some(_) => {
+                    data.read_until(b'\n', &mut buf)?;
+                    None
+                }
+            };
+            line += 1;
             if buf[0] == b'S' {
-                let mut iter = buf[2..].iter();
-                let offset = iter.position(|&x| x == b'\t').unwrap();
+                let offset = match buf.get(2..).and_then(|x| memchr(b'\t', x)) {
+                    Some(offset) => offset,
+                    None => {
+                        problems.push(line, 0, "segment must have at least 3 columns");
+                        buf.clear();
+                        continue;
+                    }
+                };
                 if node2id
                     .insert(buf[2..offset + 2].to_vec(), ItemId(node_id))
                     .is_some()
                 {
-                    panic!(
-                        "Segment with ID {} occurs multiple times in GFA",
-                        str::from_utf8(&buf[2..offset + 2]).unwrap()
-                    )
+                    problems.push(
+                        line,
+                        2,
+                        format!(
+                            "segment with ID {} occurs multiple times in GFA",
+                            String::from_utf8_lossy(&buf[2..offset + 2])
+                        ),
+                    );
                 }
                 let start_sequence = offset + 3;
-                let offset = iter
-                    .position(|&x| x == b'\t' || x == b'\n' || x == b'\r')
-                    .unwrap();
-                if k.is_some() {
-                    let (left, right) =
-                        get_extremities(&buf[start_sequence..start_sequence + offset], k.unwrap());
-                    extremities.push((left, right));
+                let offset = memchr3(b'\t', b'\n', b'\r', &buf[start_sequence..])
+                    .unwrap_or(buf.len() - start_sequence);
+                if let Some(k) = k {
+                    if offset < k {
+                        problems.push(
+                            line,
+                            3,
+                            format!("sequence is shorter than k-mer size {}", k),
+                        );
+                    } else {
+                        let (left, right) =
+                            get_extremities(&buf[start_sequence..start_sequence + offset], k);
+                        extremities.push((left, right));
+                    }
+                }
+                let len = match seq_len {
+                    Some(len) => len,
+                    // segments without sequence may give their length by an LN tag
+                    None if &buf[start_sequence..start_sequence + offset] == b"*" => {
+                        parse_length_tag(&buf[start_sequence + offset..]).unwrap_or_else(|| {
+                            unknown_lens += 1;
+                            0
+                        })
+                    }
+                    None => offset,
+                };
+                let tags: Vec<&[u8]> = buf[start_sequence + offset..]
+                    .split(|&c| c == b'\t' || c == b'\n' || c == b'\r')
+                    .filter(|tag| !tag.is_empty())
+                    .collect();
+                if let Err(msg) = stable_coords.add_segment(ItemId(node_id), &tags) {
+                    problems.push(line, 0, msg);
                 }
-                node_lens.push(offset as u32);
+                node_lens.push(len as u32);
                 node_id += 1;
-            } else if buf[0] == b'P' {
-                path_segments.push(Self::parse_path_segment(&buf));
-            } else if buf[0] == b'W' {
-                path_segments.push(Self::parse_walk_segment(&buf));
+            } else if buf[0] == b'P' || buf[0] == b'W' {
+                let path_segment = match buf[0] {
+                    b'P' => Self::parse_path_segment(&buf),
+                    _ => {
+                        walk_count += 1;
+                        Self::parse_walk_segment(&buf)
+                    }
+                };
+                match path_segment {
+                    Ok(path_segment) => path_segments.push(path_segment),
+                    Err((col, msg)) => problems.push(line, col, msg),
+                }
             }
             buf.clear();
         }
+        problems.into_result()?;
+        stable_coords.finish();
+        if unknown_lens > 0 {
+            log::warn!(
+                "{} segments have neither sequence nor LN tag, their length is taken to be 0",
+                unknown_lens
+            );
+        }
 
         log::info!(
             "found: {} paths/walks, {} nodes",
@@ -366,50 +840,24 @@ impl GraphStorage {
             log::warn!("graph does not contain any annotated paths (P/W lines)");
         }
 
-        (
+        Ok((
             node2id,
             path_segments,
+            walk_count,
             node_lens,
+            stable_coords,
             if k.is_none() { None } else { Some(extremities) },
-        )
+        ))
     }
 
-    pub fn parse_path_segment(data: &[u8]) -> PathSegment {
-        let mut iter = data.iter();
-        let start = iter.position(|&x| x == b'\t').unwrap() + 1;
-        let offset = iter.position(|&x| x == b'\t').unwrap();
-        let path_name = str::from_utf8(&data[start..start + offset]).unwrap();
-        PathSegment::from_str(path_name)
+    // malformed path names are reported as (1-based column, message)
+    pub fn parse_path_segment(data: &[u8]) -> Result<PathSegment, (usize, String)> {
+        parse_path_identifier(data).map(|(path_segment, _)| path_segment)
     }
 
-    pub fn parse_walk_segment(data: &[u8]) -> PathSegment {
-        let mut six_col: Vec<&str> = Vec::with_capacity(6);
-
-        let mut it = data.iter();
-        let mut i = 0;
-        for _ in 0..6 {
-            let j = it.position(|x| x == &b'\t').unwrap();
-            six_col.push(str::from_utf8(&data[i..i + j]).unwrap());
-            i += j + 1;
-        }
-
-        let seq_start = match six_col[4] {
-            "*" => None,
-            a => Some(usize::from_str(a).unwrap()),
-        };
-
-        let seq_end = match six_col[5] {
-            "*" => None,
-            a => Some(usize::from_str(a).unwrap()),
-        };
-
-        PathSegment::new(
-            six_col[1].to_string(),
-            six_col[2].to_string(),
-            six_col[3].to_string(),
-            seq_start,
-            seq_end,
-        )
+    // malformed walk identifiers are reported as (1-based column, message)
+    pub fn parse_walk_segment(data: &[u8]) -> Result<PathSegment, (usize, String)> {
+        parse_walk_identifier(data).map(|(path_segment, _)| path_segment)
     }
 
     // pub fn get_k_plus_one_mer_edge(
@@ -514,8 +962,8 @@ impl PathSegment {
                         }
                         Some(cc) => {
                             res.seqid = Some(cc.get(1).unwrap().as_str().to_string());
-                            res.start = usize::from_str(cc.get(2).unwrap().as_str()).ok();
-                            res.end = usize::from_str(cc.get(3).unwrap().as_str()).ok();
+                            res.start = parse_coordinate(cc.get(2).unwrap().as_str());
+                            res.end = parse_coordinate(cc.get(3).unwrap().as_str());
                             log::debug!("path has coordinates {} ", res);
                         }
                     }
@@ -528,8 +976,8 @@ impl PathSegment {
                         }
                         Some(cc) => {
                             res.haplotype = Some(cc.get(1).unwrap().as_str().to_string());
-                            res.start = usize::from_str(cc.get(2).unwrap().as_str()).ok();
-                            res.end = usize::from_str(cc.get(3).unwrap().as_str()).ok();
+                            res.start = parse_coordinate(cc.get(2).unwrap().as_str());
+                            res.end = parse_coordinate(cc.get(3).unwrap().as_str());
                             log::debug!("path has coordinates {} ", res);
                         }
                     }
@@ -537,8 +985,8 @@ impl PathSegment {
                 2 => {
                     if let Some(cc) = PATHID_COORDS.captures(segments[1]) {
                         res.sample = cc.get(1).unwrap().as_str().to_string();
-                        res.start = usize::from_str(cc.get(2).unwrap().as_str()).ok();
-                        res.end = usize::from_str(cc.get(3).unwrap().as_str()).ok();
+                        res.start = parse_coordinate(cc.get(2).unwrap().as_str());
+                        res.end = parse_coordinate(cc.get(3).unwrap().as_str());
                         log::debug!("path has coordinates {}", res);
                     }
                 }
@@ -596,6 +1044,12 @@ impl PathSegment {
         }
     }
 
+    /// Range [start, end) of the path segment on its sequence, in which a missing start is 0 and
+    /// a missing end is open, e.g., of walks whose end is given as '*'
+    pub fn range(&self) -> (usize, usize) {
+        (self.start.unwrap_or(0), self.end.unwrap_or(usize::MAX))
+    }
+
     //#[allow(dead_code)]
     //pub fn covers(&self, other: &PathSegment) -> bool {
     //    self.sample == other.sample
@@ -625,3 +1079,165 @@ impl fmt::Display for PathSegment {
         Ok(())
     }
 }
+
+// lexicographically smaller one of the (upper case) sequence and its reverse complement, and the
+// orientation of the sequence relative to it; sequences with IUPAC codes other than N are only
+// compared as they are
+fn canonical_sequence(seq: &[u8]) -> (Vec<u8>, Orientation) {
+    let seq = seq.to_ascii_uppercase();
+    let rc = if seq.iter().all(|c| b"ACGTN".contains(c)) {
+        reverse_complement(&seq)
+    } else {
+        seq.clone()
+    };
+    if seq <= rc {
+        (seq, Orientation::Forward)
+    } else {
+        (rc, Orientation::Backward)
+    }
+}
+
+// splits nodes that share hash and length of their canonical sequence into groups of identical
+// canonical sequences
+fn split_by_sequence(
+    class: Vec<(ItemId, Orientation)>,
+    sequences: &HashMap<ItemId, Vec<u8>>,
+) -> Vec<Vec<(ItemId, Orientation)>> {
+    let mut res: Vec<(Vec<u8>, Vec<(ItemId, Orientation)>)> = Vec::new();
+    for (id, o) in class {
+        let canonical = canonical_sequence(&sequences[&id]).0;
+        match res.iter_mut().find(|(seq, _)| seq == &canonical) {
+            Some((_, nodes)) => nodes.push((id, o)),
+            None => res.push((canonical, vec![(id, o)])),
+        }
+    }
+    res.into_iter().map(|(_, nodes)| nodes).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    #[test]
+    fn test_find_duplicate_nodes() {
+        let mut gfa = tempfile::NamedTempFile::new().unwrap();
+        write!(
+            gfa,
+            "S\t1\tACGTT\nS\t2\tAACGT\nS\t3\tacgtt\nS\t4\tACGTA\nS\t5\t*\nS\t6\tRACG\nS\t7\tRACG\n\
+             P\tx\t1+,2+,3+,4+,5+,6+,7+\t*\n"
+        )
+        .unwrap();
+        let gfa_file = gfa.path().to_str().unwrap();
+        let graph_storage = GraphStorage::from_gfa(gfa_file, true, CountType::Node).unwrap();
+        let id = |name: &[u8]| graph_storage.get_node_id(name).unwrap();
+        let (f, b) = (Orientation::Forward, Orientation::Backward);
+        let mut exp = vec![
+            vec![(id(b"1"), f), (id(b"2"), b), (id(b"3"), f)],
+            vec![(id(b"6"), f), (id(b"7"), f)],
+        ];
+        exp.iter_mut().for_each(|c| c.sort());
+        exp.sort();
+        assert_eq!(graph_storage.find_duplicate_nodes(gfa_file).unwrap(), exp);
+    }
+
+    #[test]
+    fn test_count_node_bases() {
+        let mut gfa = tempfile::NamedTempFile::new().unwrap();
+        write!(
+            gfa,
+            "S\t1\tACgtN\nS\t2\t*\tLN:i:3\nS\t3\tRAA\nP\tx\t1+,2+,3+\t*\n"
+        )
+        .unwrap();
+        let gfa_file = gfa.path().to_str().unwrap();
+        let graph_storage = GraphStorage::from_gfa(gfa_file, true, CountType::Node).unwrap();
+        let bases = graph_storage.count_node_bases(gfa_file).unwrap();
+        let id = |name: &[u8]| graph_storage.get_node_id(name).unwrap().0 as usize;
+        assert_eq!(bases[id(b"1")], [1, 1, 1, 1, 1]);
+        // nodes without sequence count as other bases
+        assert_eq!(bases[id(b"2")], [0, 0, 0, 0, 3]);
+        assert_eq!(bases[id(b"3")], [2, 0, 0, 0, 1]);
+    }
+
+    #[test]
+    fn test_get_edge_id() {
+        let mut gfa = tempfile::NamedTempFile::new().unwrap();
+        write!(
+            gfa,
+            "S\t1\tA\nS\t2\tC\nL\t1\t+\t2\t+\t0M\nP\tx\t1+,2+\t*\nP\ty\t2-,1-\t*\n"
+        )
+        .unwrap();
+        let gfa_file = gfa.path().to_str().unwrap();
+        let mut graph_storage = GraphStorage::from_gfa(gfa_file, true, CountType::Edge).unwrap();
+        let (u, v) = (
+            graph_storage.get_node_id(b"1").unwrap(),
+            graph_storage.get_node_id(b"2").unwrap(),
+        );
+        let (f, b) = (Orientation::Forward, Orientation::Backward);
+        let forward = Edge(u, f, v, f);
+
+        // both directions of traversal are the same countable
+        assert_eq!(graph_storage.number_of_items(&CountType::Edge), 1);
+        assert_eq!(graph_storage.get_edge_id(u, f, v, f), Ok(ItemId(1)));
+        assert_eq!(graph_storage.get_edge_id(v, b, u, b), Ok(ItemId(1)));
+        assert_eq!(graph_storage.get_id2edge()[1..], [forward]);
+        assert!(graph_storage.get_edge_id(u, f, v, b).is_err());
+
+        // the reverse traversal is counted after all edges in forward direction
+        graph_storage.oriented_edges = true;
+        assert_eq!(graph_storage.number_of_items(&CountType::Edge), 2);
+        assert_eq!(graph_storage.get_edge_id(u, f, v, f), Ok(ItemId(1)));
+        assert_eq!(graph_storage.get_edge_id(v, b, u, b), Ok(ItemId(2)));
+        assert_eq!(
+            graph_storage.get_id2edge()[1..],
+            [forward, Edge(v, b, u, b)]
+        );
+    }
+
+    #[test]
+    fn test_split_by_sequence() {
+        // nodes 1 and 3 share hash and length with node 2 by collision only
+        let sequences = HashMap::from([
+            (ItemId(1), b"ACGT".to_vec()),
+            (ItemId(2), b"ACGA".to_vec()),
+            (ItemId(3), b"acgt".to_vec()),
+        ]);
+        let f = Orientation::Forward;
+        let class = vec![(ItemId(1), f), (ItemId(2), f), (ItemId(3), f)];
+        assert_eq!(
+            split_by_sequence(class, &sequences),
+            vec![vec![(ItemId(1), f), (ItemId(3), f)], vec![(ItemId(2), f)]]
+        );
+    }
+
+    #[test]
+    fn test_get_covered_interval() {
+        // node 2 is the reverse complement of node 1, node 3 is palindromic
+        let mut graph_storage = GraphStorage::from_path_segments(Vec::new());
+        graph_storage.node_lens = vec![0, 10, 10, 4];
+        let (f, b) = (Orientation::Forward, Orientation::Backward);
+        assert_eq!(
+            graph_storage.get_covered_interval(ItemId(2), b, 0, 3),
+            (ItemId(2), 7, 10)
+        );
+
+        graph_storage.node_representatives = Some(vec![0, 1, 1, 3]);
+        graph_storage.node_strands = Some(vec![Some(f), Some(f), Some(b), None]);
+        assert_eq!(
+            graph_storage.get_covered_interval(ItemId(1), b, 0, 3),
+            (ItemId(1), 7, 10)
+        );
+        assert_eq!(
+            graph_storage.get_covered_interval(ItemId(2), b, 0, 3),
+            (ItemId(1), 0, 3)
+        );
+        assert_eq!(
+            graph_storage.get_covered_interval(ItemId(2), f, 0, 3),
+            (ItemId(1), 7, 10)
+        );
+        assert_eq!(
+            graph_storage.get_covered_interval(ItemId(3), b, 0, 1),
+            (ItemId(3), 0, 1)
+        );
+    }
+}
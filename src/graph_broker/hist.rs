@@ -5,6 +5,7 @@ use std::str::FromStr;
 
 /* external crate */
 use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
 
 /* private use */
 use crate::util::{CountType, Threshold};
@@ -12,7 +13,7 @@ use crate::util::{CountType, Threshold};
 use super::abacus::AbacusByTotal;
 use super::graph::GraphStorage;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Hist {
     pub count: CountType,
     pub coverage: Vec<usize>,
@@ -40,7 +41,7 @@ impl Hist {
         Self {
             count: abacus.count,
             coverage: match abacus.count {
-                CountType::Node | CountType::Edge => abacus.construct_hist(),
+                CountType::Node | CountType::Edge | CountType::Bubble => abacus.construct_hist(),
                 CountType::Bp => abacus
                     .construct_hist_bps(graph_aux.expect("Graph auxiliary is needed for Bps hist")),
                 CountType::All => unreachable!("inadmissable count type"),
@@ -53,7 +54,10 @@ impl Hist {
 
         if n > 0 {
             let quorum = usize::max(1, t_quorum.to_absolute(n));
-            if quorum == 1 {
+            if t_quorum.to_relative_upper(n) < 1.0 {
+                // content exceeding the quorum's upper bound must be excluded
+                self.calc_growth_quorum(t_coverage, t_quorum)
+            } else if quorum == 1 {
                 self.calc_growth_union(t_coverage)
             } else if quorum >= n {
                 self.calc_growth_core(t_coverage)
@@ -65,6 +69,18 @@ impl Hist {
         }
     }
 
+    /// Average number of countables of a path/group, e.g., its genome size for bp
+    pub fn get_average_group_size(&self) -> f64 {
+        let n = self.coverage.len().saturating_sub(1);
+        let total: f64 = self
+            .coverage
+            .iter()
+            .enumerate()
+            .map(|(i, c)| (i * c) as f64)
+            .sum();
+        total / n as f64
+    }
+
     pub fn calc_all_growths(&self, hist_aux: &ThresholdContainer) -> Vec<Vec<f64>> {
         let mut growths: Vec<Vec<f64>> = hist_aux
             .coverage
@@ -89,10 +105,15 @@ impl Hist {
     pub fn calc_growth_union(&self, t_coverage: &Threshold) -> Vec<f64> {
         let n = self.coverage.len() - 1; // hist array has length n+1: from 0..n (both included)
         let c = usize::max(1, t_coverage.to_absolute(n));
+        let c_upper = usize::min(n, t_coverage.to_absolute_upper(n));
 
         let mut pangrowth: Vec<f64> = vec![0.0; n];
         let mut n_fall_m: f64 = 0.0;
-        let tot = self.coverage[c..].iter().sum::<usize>() as f64;
+        let tot = if c <= c_upper {
+            self.coverage[c..c_upper + 1].iter().sum::<usize>() as f64
+        } else {
+            0.0
+        };
 
         // perc_mult[i] contains the percentage of combinations that
         // have an item of multiplicity i
@@ -104,7 +125,9 @@ impl Hist {
             n_fall_m += (n as f64 - m as f64 + 1.0).log2();
             for i in c..n - m + 1 {
                 perc_mult[i] += (n as f64 - m as f64 - i as f64 + 1.0).log2();
-                y += ((self.coverage[i] as f64).log2() + perc_mult[i] - n_fall_m).exp2();
+                if i <= c_upper {
+                    y += ((self.coverage[i] as f64).log2() + perc_mult[i] - n_fall_m).exp2();
+                }
             }
 
             pangrowth[m - 1] = tot - y;
@@ -116,6 +139,7 @@ impl Hist {
     pub fn calc_growth_core(&self, t_coverage: &Threshold) -> Vec<f64> {
         let n = self.coverage.len() - 1; // hist array has length n+1: from 0..n (both included)
         let c = usize::max(1, t_coverage.to_absolute(n + 1));
+        let c_upper = t_coverage.to_absolute_upper(n + 1);
         let mut n_fall_m: f64 = 0.0;
         let mut pangrowth: Vec<f64> = vec![0.0; n];
 
@@ -129,7 +153,9 @@ impl Hist {
             n_fall_m += (n as f64 - m as f64 + 1.0).log2();
             for i in usize::max(m, c)..n + 1 {
                 perc_mult[i] += (i as f64 - m as f64 + 1.0).log2();
-                y += ((self.coverage[i] as f64).log2() + perc_mult[i] - n_fall_m).exp2();
+                if i <= c_upper {
+                    y += ((self.coverage[i] as f64).log2() + perc_mult[i] - n_fall_m).exp2();
+                }
             }
             pangrowth[m - 1] = y;
         }
@@ -148,7 +174,9 @@ impl Hist {
     pub fn calc_growth_quorum(&self, t_coverage: &Threshold, t_quorum: &Threshold) -> Vec<f64> {
         let n = self.coverage.len() - 1; // hist array has length n+1: from [0..n]
         let c = usize::max(1, t_coverage.to_absolute(n));
+        let c_upper = t_coverage.to_absolute_upper(n);
         let quorum = t_quorum.to_relative(n);
+        let quorum_upper = t_quorum.to_relative_upper(n);
         let mut pangrowth: Vec<f64> = vec![0.0; n];
 
         // Used for \binom{N}{m} = \frac{N^{\underline{m}}}{m!}
@@ -165,13 +193,21 @@ impl Hist {
         for m in 1..n + 1 {
             m_fact += (m as f64).log2();
             let m_quorum = (m as f64 * quorum).ceil() as usize;
+            // largest number of the m genomes in which counted content may occur
+            let m_quorum_upper = if quorum_upper.is_finite() {
+                usize::min(m, (m as f64 * quorum_upper).floor() as usize)
+            } else {
+                m
+            };
 
             //100% quorum, left part of above formula
             let mut left_sum: f64 = 0.0;
             n_fall_m += (n as f64 - m as f64 + 1.0).log2();
             for i in usize::max(m, c)..n + 1 {
                 i_fall_m[i] += (i as f64 - m as f64 + 1.0).log2();
-                left_sum += ((self.coverage[i] as f64).log2() + i_fall_m[i] - n_fall_m).exp2();
+                if i <= c_upper && m_quorum_upper == m {
+                    left_sum += ((self.coverage[i] as f64).log2() + i_fall_m[i] - n_fall_m).exp2();
+                }
             }
 
             //[m_quorum, 100) quorum, right part of above formula
@@ -188,12 +224,16 @@ impl Hist {
                         }
                         numerator[i][j] += (n as f64 - i as f64 - m as f64 + 1.0 + j as f64).log2();
                         numerator[i][j] -= (m as f64 - j as f64).log2();
-                        sum_q += (numerator[i][j] + m_fact - n_fall_m).exp2();
-                        add = true;
+                        // the dynamic programming table is updated beyond the quorum's upper
+                        // bound, but only values within are summed up
+                        if j <= m_quorum_upper {
+                            sum_q += (numerator[i][j] + m_fact - n_fall_m).exp2();
+                            add = true;
+                        }
                     }
                 }
                 // Only add to right sum if at least once something was added to sum_q (otherwise problem because e^0 == 1)
-                if add {
+                if add && i <= c_upper {
                     right_sum += ((self.coverage[i] as f64).log2() + sum_q.log2()).exp2();
                 }
             }
@@ -220,57 +260,119 @@ pub enum RequireThreshold {
     Either,
 }
 
+// Parses a comma-separated list of thresholds. Besides plain values, each element can be an
+// expression:
+//   - `N%`: relative threshold of N percent
+//   - `>=X`, `>X`: at least X, more than X (the latter for integers only)
+//   - `<=X`, `<X`: at most X, less than X (the latter for integers only)
+//   - `A..B`: range from A to B, both bounds included
+//   - `topN%`: the upper N percent, i.e., at least 100-N percent
 pub fn parse_threshold_cli(
     threshold_str: &str,
     require: RequireThreshold,
 ) -> Result<Vec<Threshold>, Error> {
-    let mut thresholds = Vec::new();
-
-    for (i, el) in threshold_str.split(',').enumerate() {
-        let rel_val = match f64::from_str(el.trim()) {
-            Ok(t) => {
-                if (0.0..=1.0).contains(&t) {
-                    Ok(t)
-                } else {
-                    Err(Error::new(
-                        ErrorKind::InvalidData,
-                        format!(
-                            "relative threshold \"{}\" ({}. element in list) must be within [0,1].",
-                            &threshold_str,
-                            i + 1
-                        ),
-                    ))
-                }
+    threshold_str
+        .split(',')
+        .enumerate()
+        .map(|(i, el)| {
+            parse_threshold(el.trim(), &require).map_err(|msg| {
+                Error::new(
+                    ErrorKind::InvalidData,
+                    format!(
+                        "threshold \"{}\" ({}. element in list) {}",
+                        &threshold_str,
+                        i + 1,
+                        msg
+                    ),
+                )
+            })
+        })
+        .collect()
+}
+
+fn parse_threshold(el: &str, require: &RequireThreshold) -> Result<Threshold, String> {
+    if let Some(p) = el.strip_prefix("top") {
+        return match parse_value(p.trim(), &RequireThreshold::Relative)? {
+            Threshold::Relative(x) => Ok(Threshold::Relative(1.0 - x)),
+            _ => unreachable!(),
+        };
+    }
+    if let Some(x) = el.strip_prefix(">=") {
+        return parse_value(x.trim(), require);
+    }
+    if let Some(x) = el.strip_prefix('>') {
+        return match parse_value(x.trim(), require)? {
+            Threshold::Absolute(x) => Ok(Threshold::Absolute(x + 1)),
+            _ => Err("uses \">\" which is only admissible for integers.".to_string()),
+        };
+    }
+    if let Some(x) = el.strip_prefix("<=") {
+        return Ok(match parse_value(x.trim(), require)? {
+            Threshold::Absolute(x) => Threshold::AbsoluteRange(0, x),
+            Threshold::Relative(x) => Threshold::RelativeRange(0.0, x),
+            _ => unreachable!(),
+        });
+    }
+    if let Some(x) = el.strip_prefix('<') {
+        return match parse_value(x.trim(), require)? {
+            Threshold::Absolute(x) if x > 0 => Ok(Threshold::AbsoluteRange(0, x - 1)),
+            Threshold::Absolute(_) => Err("is not satisfiable by any value.".to_string()),
+            _ => Err("uses \"<\" which is only admissible for integers.".to_string()),
+        };
+    }
+    if let Some((lo, hi)) = el.split_once("..") {
+        let hi = hi.strip_prefix('=').unwrap_or(hi);
+        return match (
+            parse_value(lo.trim(), require)?,
+            parse_value(hi.trim(), require)?,
+        ) {
+            (Threshold::Absolute(lo), Threshold::Absolute(hi)) if lo <= hi => {
+                Ok(Threshold::AbsoluteRange(lo, hi))
             }
-            Err(_) => Err(Error::new(
-                ErrorKind::InvalidData,
-                format!(
-                    "threshold \"{}\" ({}. element in list) is required to be float, but isn't.",
-                    &threshold_str,
-                    i + 1
-                ),
-            )),
+            (Threshold::Relative(lo), Threshold::Relative(hi)) if lo <= hi => {
+                Ok(Threshold::RelativeRange(lo, hi))
+            }
+            (Threshold::Absolute(_), Threshold::Absolute(_))
+            | (Threshold::Relative(_), Threshold::Relative(_)) => {
+                Err("has a lower bound that is larger than its upper bound.".to_string())
+            }
+            _ => Err("mixes absolute and relative bounds.".to_string()),
         };
+    }
+    parse_value(el, require)
+}
 
-        thresholds.push(
-            match require {
-                RequireThreshold::Absolute => Threshold::Absolute(usize::from_str(el.trim()).map_err(|_|
-                    Error::new(
-                            ErrorKind::InvalidData,
-                            format!("threshold \"{}\" ({}. element in list) is required to be integer, but isn't.",
-                    &threshold_str,
-                    i + 1)))?),
-            RequireThreshold::Relative => Threshold::Relative(rel_val?),
-            RequireThreshold::Either =>
-        if let Ok(t) = usize::from_str(el.trim()) {
-            Threshold::Absolute(t)
-        } else {
-            Threshold::Relative(rel_val?)
+fn parse_value(el: &str, require: &RequireThreshold) -> Result<Threshold, String> {
+    if let Some(p) = el.strip_suffix('%') {
+        return match f64::from_str(p.trim()) {
+            Ok(t) if (0.0..=100.0).contains(&t) => Ok(Threshold::Relative(t / 100.0)),
+            Ok(_) => Err("must be a percentage within [0,100].".to_string()),
+            Err(_) => Err("is required to be a percentage, but isn't.".to_string()),
+        };
+    }
+    let rel_val = match f64::from_str(el) {
+        Ok(t) => {
+            if (0.0..=1.0).contains(&t) {
+                Ok(t)
+            } else {
+                Err("must be within [0,1].".to_string())
             }
+        }
+        Err(_) => Err("is required to be float, but isn't.".to_string()),
+    };
+    match require {
+        RequireThreshold::Absolute => usize::from_str(el)
+            .map(Threshold::Absolute)
+            .map_err(|_| "is required to be integer, but isn't.".to_string()),
+        RequireThreshold::Relative => rel_val.map(Threshold::Relative),
+        RequireThreshold::Either => {
+            if let Ok(t) = usize::from_str(el) {
+                Ok(Threshold::Absolute(t))
+            } else {
+                rel_val.map(Threshold::Relative)
             }
-            );
+        }
     }
-    Ok(thresholds)
 }
 
 pub struct ThresholdContainer {
@@ -353,6 +455,16 @@ mod tests {
         (1..=n).fold(1.0, |acc, x| acc * x as f64)
     }
 
+    #[test]
+    fn test_get_average_group_size() {
+        // 4 bp in one group, 2 bp in both, and 6 bp uncovered
+        let hist = Hist {
+            count: CountType::Bp,
+            coverage: vec![6, 4, 2],
+        };
+        assert_almost_eq(hist.get_average_group_size(), 4.0);
+    }
+
     #[test]
     fn test_choose_function() {
         assert_almost_eq(log2_choose(5, 0), 0.0);
@@ -364,6 +476,67 @@ mod tests {
         assert_eq!(log2_choose(5, 6), 0.0);
     }
 
+    #[test]
+    fn test_parse_threshold_expressions() {
+        let parse = |s: &str, r: RequireThreshold| parse_threshold_cli(s, r).unwrap();
+        assert_eq!(
+            parse("2..5,>=5,>4,<=3,<3,50%", RequireThreshold::Absolute),
+            vec![
+                Threshold::AbsoluteRange(2, 5),
+                Threshold::Absolute(5),
+                Threshold::Absolute(5),
+                Threshold::AbsoluteRange(0, 3),
+                Threshold::AbsoluteRange(0, 2),
+                Threshold::Relative(0.5),
+            ]
+        );
+        assert_eq!(
+            parse(
+                "0.2..=0.5,<=0.5,20%..50%,top10%",
+                RequireThreshold::Relative
+            ),
+            vec![
+                Threshold::RelativeRange(0.2, 0.5),
+                Threshold::RelativeRange(0.0, 0.5),
+                Threshold::RelativeRange(0.2, 0.5),
+                Threshold::Relative(1.0 - 0.1),
+            ]
+        );
+        assert!(parse_threshold_cli("5..2", RequireThreshold::Absolute).is_err());
+        assert!(parse_threshold_cli("2..50%", RequireThreshold::Absolute).is_err());
+        assert!(parse_threshold_cli("<0", RequireThreshold::Absolute).is_err());
+        assert!(parse_threshold_cli(">0.5", RequireThreshold::Relative).is_err());
+        assert!(parse_threshold_cli("top150%", RequireThreshold::Relative).is_err());
+    }
+
+    #[test]
+    fn test_hist_calc_growth_ranges() {
+        let hist = Hist {
+            count: CountType::Node,
+            coverage: vec![0, 5, 3, 2],
+        };
+        let everything = Threshold::Relative(0.0);
+
+        // union of countables with coverage 1 and 2, respectively
+        let growth = hist.calc_growth(&Threshold::AbsoluteRange(1, 1), &everything);
+        let test_growth = [5.0 / 3.0, 10.0 / 3.0, 5.0];
+        for (a, b) in growth.iter().zip(test_growth) {
+            assert_almost_eq(*a, b);
+        }
+        let growth = hist.calc_growth(&Threshold::AbsoluteRange(2, 2), &everything);
+        let test_growth = [2.0, 3.0, 3.0];
+        for (a, b) in growth.iter().zip(test_growth) {
+            assert_almost_eq(*a, b);
+        }
+
+        // countables that are contained in at most half of each subset
+        let growth = hist.calc_growth(&Threshold::Absolute(0), &Threshold::RelativeRange(0.0, 0.5));
+        let test_growth = [0.0, 16.0 / 3.0, 5.0];
+        for (a, b) in growth.iter().zip(test_growth) {
+            assert_almost_eq(*a, b);
+        }
+    }
+
     #[test]
     fn test_hist_calc_growth_union() {
         let hist = Hist {
@@ -0,0 +1,546 @@
+/* standard use */
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Error, ErrorKind, Read, Take, Write};
+
+/* private use */
+use super::graph::{Edge, GraphStorage, ItemId, Orientation, PathSegment};
+use super::intervals::parse_path_line;
+use super::stable::StableCoords;
+use crate::io::{bufreader_from_compressed_gfa, InputProblems};
+use crate::util::{CountType, ItemIdSize};
+
+const INDEX_MAGIC: &[u8; 4] = b"PIDX";
+const INDEX_VERSION: u32 = 2;
+
+/// Steps of all paths/walks of a graph, in the order of its path segments
+#[derive(Debug, Clone, Default)]
+pub struct IndexedPaths {
+    steps: Vec<(ItemId, Orientation)>,
+    prefsum: Vec<usize>,
+}
+
+impl IndexedPaths {
+    pub fn path_count(&self) -> usize {
+        self.prefsum.len().saturating_sub(1)
+    }
+
+    /// Steps of the i-th path/walk
+    pub fn get(&self, i: usize) -> &[(ItemId, Orientation)] {
+        &self.steps[self.prefsum[i]..self.prefsum[i + 1]]
+    }
+}
+
+/// Reader of the paths/walks of the graph file, which is empty if the graph is loaded from an
+/// index, since the paths/walks are then taken from the index
+pub fn bufreader_from_graph(
+    gfa_file: &str,
+    graph_storage: &GraphStorage,
+) -> Result<BufReader<Box<dyn Read>>, Error> {
+    if graph_storage.indexed_paths.is_some() {
+        let empty: Box<dyn Read> = Box::new(std::io::empty());
+        Ok(BufReader::new(empty))
+    } else {
+        bufreader_from_compressed_gfa(gfa_file)
+    }
+}
+
+/// Content of a graph index: node names and lengths, edges, and paths/walks with their steps, all
+/// indexed by their ID, and the stable coordinates of rGFA segments
+pub struct GraphIndex {
+    pub node_names: Vec<Vec<u8>>,
+    pub node_lens: Vec<u32>,
+    pub edges: Vec<Edge>,
+    pub path_segments: Vec<PathSegment>,
+    pub walk_count: usize,
+    pub paths: IndexedPaths,
+    pub stable_coords: StableCoords,
+}
+
+/// Parses the graph once and writes its index, which all subcommands accept in place of the graph
+/// file; sequences are not part of the index
+pub fn write_graph_index(gfa_file: &str, index_file: &str) -> Result<(), Error> {
+    let graph_storage = GraphStorage::from_gfa(gfa_file, false, CountType::All)?;
+
+    log::info!("parsing path + walk sequences");
+    let mut paths = IndexedPaths {
+        steps: Vec::new(),
+        prefsum: vec![0],
+    };
+    let mut problems = InputProblems::new(gfa_file);
+    let mut line = 0;
+    let mut buf = vec![];
+    let mut data = bufreader_from_compressed_gfa(gfa_file)?;
+    while data.read_until(b'\n', &mut buf)? > 0 {
+        line += 1;
+        match parse_path_line(&buf, &graph_storage, |_| true) {
+            Some(Ok((_, steps))) => {
+                paths.steps.extend(steps);
+                paths.prefsum.push(paths.steps.len());
+            }
+            Some(Err((col, msg))) => problems.push(line, col, msg),
+            None => (),
+        }
+        buf.clear();
+    }
+    problems.into_result()?;
+
+    let index = GraphIndex {
+        node_names: graph_storage.get_node_names(),
+        node_lens: graph_storage.node_lens.clone(),
+        // edge IDs start at 1
+        edges: graph_storage.get_id2edge().split_off(1),
+        path_segments: graph_storage.path_segments.clone(),
+        walk_count: graph_storage.walk_count,
+        paths,
+        stable_coords: graph_storage.stable_coords.clone(),
+    };
+    log::info!(
+        "writing index of {} nodes, {} edges, and {} paths/walks with {} steps to {}",
+        index.node_names.len() - 1,
+        index.edges.len(),
+        index.path_segments.len(),
+        index.paths.steps.len(),
+        index_file
+    );
+    let mut out = BufWriter::new(File::create(index_file).map_err(|e| {
+        Error::new(
+            e.kind(),
+            format!("unable to create graph index {}: {}", index_file, e),
+        )
+    })?);
+    index.write(&mut out)?;
+    out.flush()
+}
+
+impl GraphIndex {
+    fn write<W: Write>(&self, out: &mut W) -> Result<(), Error> {
+        out.write_all(INDEX_MAGIC)?;
+        out.write_all(&INDEX_VERSION.to_le_bytes())?;
+
+        // node 0 is a placeholder, since node IDs start at 1
+        write_u64(out, self.node_names.len() as u64 - 1)?;
+        for (name, len) in self.node_names.iter().zip(&self.node_lens).skip(1) {
+            write_bytes(out, name)?;
+            out.write_all(&len.to_le_bytes())?;
+        }
+
+        write_u64(out, self.edges.len() as u64)?;
+        for Edge(u, o1, v, o2) in &self.edges {
+            write_step(out, *u, *o1)?;
+            write_step(out, *v, *o2)?;
+        }
+
+        write_u64(out, self.path_segments.len() as u64)?;
+        write_u64(out, self.walk_count as u64)?;
+        for (i, path_seg) in self.path_segments.iter().enumerate() {
+            write_bytes(out, path_seg.sample.as_bytes())?;
+            write_opt_bytes(out, path_seg.haplotype.as_ref().map(|s| s.as_bytes()))?;
+            write_opt_bytes(out, path_seg.seqid.as_ref().map(|s| s.as_bytes()))?;
+            write_opt_u64(out, path_seg.start.map(|x| x as u64))?;
+            write_opt_u64(out, path_seg.end.map(|x| x as u64))?;
+            let steps = self.paths.get(i);
+            write_u64(out, steps.len() as u64)?;
+            for (id, o) in steps {
+                write_step(out, *id, *o)?;
+            }
+        }
+
+        let segments = self.stable_coords.get_segments();
+        write_u64(out, segments.len() as u64)?;
+        for (id, seq, offset, rank) in segments {
+            write_u64(out, id.0)?;
+            write_bytes(out, seq.as_bytes())?;
+            write_u64(out, offset as u64)?;
+            out.write_all(&rank.to_le_bytes())?;
+        }
+        Ok(())
+    }
+
+    pub fn read(index_file: &str) -> Result<Self, Error> {
+        log::info!("loading graph index from {}", index_file);
+        let file = File::open(index_file).map_err(|e| {
+            Error::new(
+                e.kind(),
+                format!("unable to open graph index {}: {}", index_file, e),
+            )
+        })?;
+        // lengths and counts read from the index are checked against the remaining size of the
+        // file, such that a corrupt index cannot trigger huge allocations
+        let size = file.metadata()?.len();
+        let mut data = BufReader::new(file).take(size);
+        Self::read_from(&mut data).map_err(|e| {
+            Error::new(
+                e.kind(),
+                format!("unable to read graph index {}: {}", index_file, e),
+            )
+        })
+    }
+
+    fn read_from<R: Read>(data: &mut Take<R>) -> Result<Self, Error> {
+        let mut magic = [0; 4];
+        data.read_exact(&mut magic)?;
+        if &magic != INDEX_MAGIC {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                "not a panacus graph index",
+            ));
+        }
+        let mut version = [0; 4];
+        data.read_exact(&mut version)?;
+        let version = u32::from_le_bytes(version);
+        if version != INDEX_VERSION {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                format!(
+                    "index has version {}, but version {} is required; rebuild it with panacus index",
+                    version, INDEX_VERSION
+                ),
+            ));
+        }
+
+        // node names with their length prefix, and node lengths
+        let node_count = read_count(data, 12)?;
+        let mut node_names = vec![Vec::new()];
+        let mut node_lens = vec![0];
+        for _ in 0..node_count {
+            node_names.push(read_bytes(data)?);
+            let mut len = [0; 4];
+            data.read_exact(&mut len)?;
+            node_lens.push(u32::from_le_bytes(len));
+        }
+
+        let edge_count = read_count(data, 16)?;
+        let mut edges = Vec::with_capacity(edge_count);
+        for _ in 0..edge_count {
+            let (u, o1) = read_step(data, node_count)?;
+            let (v, o2) = read_step(data, node_count)?;
+            edges.push(Edge(u, o1, v, o2));
+        }
+
+        // sample name with its length prefix, flags of the optional fields, and step count
+        let path_count = read_count(data, 20)?;
+        let walk_count = read_u64(data)? as usize;
+        let mut path_segments = Vec::with_capacity(path_count);
+        let mut paths = IndexedPaths {
+            steps: Vec::new(),
+            prefsum: vec![0],
+        };
+        for _ in 0..path_count {
+            path_segments.push(PathSegment {
+                sample: read_string(data)?,
+                haplotype: read_opt_bytes(data)?.map(into_string).transpose()?,
+                seqid: read_opt_bytes(data)?.map(into_string).transpose()?,
+                start: read_opt_u64(data)?.map(|x| x as usize),
+                end: read_opt_u64(data)?.map(|x| x as usize),
+            });
+            let step_count = read_count(data, 8)?;
+            for _ in 0..step_count {
+                paths.steps.push(read_step(data, node_count)?);
+            }
+            paths.prefsum.push(paths.steps.len());
+        }
+
+        let mut stable_coords = StableCoords::default();
+        // node ID, sequence name with its length prefix, offset, and rank
+        let segment_count = read_count(data, 28)?;
+        for _ in 0..segment_count {
+            let id = check_node_id(read_u64(data)?, node_count)?;
+            let seq = read_string(data)?;
+            let offset = read_u64(data)? as usize;
+            let mut rank = [0; 4];
+            data.read_exact(&mut rank)?;
+            stable_coords.insert(id, seq, offset, u32::from_le_bytes(rank));
+        }
+        stable_coords.finish();
+        if data.limit() > 0 {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                format!("{} trailing bytes after the end of the index", data.limit()),
+            ));
+        }
+
+        Ok(Self {
+            node_names,
+            node_lens,
+            edges,
+            path_segments,
+            walk_count,
+            paths,
+            stable_coords,
+        })
+    }
+
+    /// Map of node names to their ID
+    pub fn get_node2id(&self) -> HashMap<Vec<u8>, ItemId> {
+        self.node_names
+            .iter()
+            .enumerate()
+            .skip(1)
+            .map(|(i, name)| (name.clone(), ItemId(i as ItemIdSize)))
+            .collect()
+    }
+}
+
+fn write_u64<W: Write>(out: &mut W, x: u64) -> Result<(), Error> {
+    out.write_all(&x.to_le_bytes())
+}
+
+fn write_bytes<W: Write>(out: &mut W, s: &[u8]) -> Result<(), Error> {
+    write_u64(out, s.len() as u64)?;
+    out.write_all(s)
+}
+
+// missing values are written as a 0 byte, present ones as a 1 byte followed by the value
+fn write_opt_bytes<W: Write>(out: &mut W, s: Option<&[u8]>) -> Result<(), Error> {
+    match s {
+        Some(s) => {
+            out.write_all(&[1])?;
+            write_bytes(out, s)
+        }
+        None => out.write_all(&[0]),
+    }
+}
+
+fn write_opt_u64<W: Write>(out: &mut W, x: Option<u64>) -> Result<(), Error> {
+    match x {
+        Some(x) => {
+            out.write_all(&[1])?;
+            write_u64(out, x)
+        }
+        None => out.write_all(&[0]),
+    }
+}
+
+// steps are written as node ID times two, plus one if the node is traversed in reverse
+fn write_step<W: Write>(out: &mut W, id: ItemId, o: Orientation) -> Result<(), Error> {
+    let flag = match o {
+        Orientation::Forward => 0,
+        Orientation::Backward => 1,
+    };
+    write_u64(out, (id.0 << 1) | flag)
+}
+
+fn read_u64<R: Read>(data: &mut Take<R>) -> Result<u64, Error> {
+    let mut x = [0; 8];
+    data.read_exact(&mut x)?;
+    Ok(u64::from_le_bytes(x))
+}
+
+// number of records of at least the given size in bytes, which must fit into the rest of the index
+fn read_count<R: Read>(data: &mut Take<R>, record_size: u64) -> Result<usize, Error> {
+    let count = read_u64(data)?;
+    if count.saturating_mul(record_size) > data.limit() {
+        return Err(Error::new(
+            ErrorKind::UnexpectedEof,
+            format!(
+                "index claims {} records, which exceed its remaining {} bytes",
+                count,
+                data.limit()
+            ),
+        ));
+    }
+    Ok(count as usize)
+}
+
+fn read_bytes<R: Read>(data: &mut Take<R>) -> Result<Vec<u8>, Error> {
+    let len = read_count(data, 1)?;
+    let mut s = vec![0; len];
+    data.read_exact(&mut s)?;
+    Ok(s)
+}
+
+fn read_string<R: Read>(data: &mut Take<R>) -> Result<String, Error> {
+    into_string(read_bytes(data)?)
+}
+
+fn into_string(s: Vec<u8>) -> Result<String, Error> {
+    String::from_utf8(s).map_err(|e| Error::new(ErrorKind::InvalidData, e))
+}
+
+fn read_flag<R: Read>(data: &mut Take<R>) -> Result<bool, Error> {
+    let mut flag = [0; 1];
+    data.read_exact(&mut flag)?;
+    Ok(flag[0] != 0)
+}
+
+fn read_opt_bytes<R: Read>(data: &mut Take<R>) -> Result<Option<Vec<u8>>, Error> {
+    if read_flag(data)? {
+        read_bytes(data).map(Some)
+    } else {
+        Ok(None)
+    }
+}
+
+fn read_opt_u64<R: Read>(data: &mut Take<R>) -> Result<Option<u64>, Error> {
+    if read_flag(data)? {
+        read_u64(data).map(Some)
+    } else {
+        Ok(None)
+    }
+}
+
+fn check_node_id(id: u64, node_count: usize) -> Result<ItemId, Error> {
+    if id == 0 || id > node_count as u64 {
+        return Err(Error::new(
+            ErrorKind::InvalidData,
+            format!(
+                "index refers to node ID {}, but has {} nodes",
+                id, node_count
+            ),
+        ));
+    }
+    Ok(ItemId(id as ItemIdSize))
+}
+
+fn read_step<R: Read>(
+    data: &mut Take<R>,
+    node_count: usize,
+) -> Result<(ItemId, Orientation), Error> {
+    let x = read_u64(data)?;
+    let o = if x & 1 == 0 {
+        Orientation::Forward
+    } else {
+        Orientation::Backward
+    };
+    Ok((check_node_id(x >> 1, node_count)?, o))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    use crate::analyses::InputRequirement;
+    use crate::graph_broker::{GraphBroker, GraphState};
+
+    fn get_index() -> GraphIndex {
+        let mut stable_coords = StableCoords::default();
+        stable_coords.insert(ItemId(1), "chr1".to_string(), 0, 0);
+        stable_coords.insert(ItemId(2), "chr1".to_string(), 5, 0);
+        stable_coords.finish();
+        GraphIndex {
+            node_names: vec![Vec::new(), b"a".to_vec(), b"b".to_vec()],
+            node_lens: vec![0, 5, 3],
+            edges: vec![Edge(
+                ItemId(1),
+                Orientation::Forward,
+                ItemId(2),
+                Orientation::Backward,
+            )],
+            path_segments: vec![
+                PathSegment::from_str("HG00438#1#chr1:10-18"),
+                PathSegment::from_str("ref"),
+            ],
+            walk_count: 0,
+            paths: IndexedPaths {
+                steps: vec![
+                    (ItemId(1), Orientation::Forward),
+                    (ItemId(2), Orientation::Backward),
+                    (ItemId(2), Orientation::Forward),
+                ],
+                prefsum: vec![0, 2, 3],
+            },
+            stable_coords,
+        }
+    }
+
+    fn read_buf(buf: &[u8]) -> Result<GraphIndex, Error> {
+        GraphIndex::read_from(&mut buf.take(buf.len() as u64))
+    }
+
+    #[test]
+    fn test_graph_index_round_trip() {
+        let index = get_index();
+        let mut buf = Vec::new();
+        index.write(&mut buf).unwrap();
+        let res = read_buf(&buf).unwrap();
+        assert_eq!(res.node_names, index.node_names);
+        assert_eq!(res.node_lens, index.node_lens);
+        assert_eq!(res.edges, index.edges);
+        assert_eq!(res.path_segments, index.path_segments);
+        assert_eq!(res.paths.path_count(), 2);
+        assert_eq!(res.paths.get(1), &[(ItemId(2), Orientation::Forward)]);
+        assert_eq!(res.get_node2id()[&b"b".to_vec()], ItemId(2));
+        assert_eq!(
+            res.stable_coords.get_segments(),
+            index.stable_coords.get_segments()
+        );
+
+        assert!(read_buf(b"GFA\t").is_err());
+    }
+
+    #[test]
+    fn test_graph_index_corrupt() {
+        let mut buf = Vec::new();
+        get_index().write(&mut buf).unwrap();
+
+        // every truncation of the index is detected
+        for len in 0..buf.len() {
+            assert!(read_buf(&buf[..len]).is_err(), "truncated to {} bytes", len);
+        }
+        let mut trailing = buf.clone();
+        trailing.push(0);
+        assert!(read_buf(&trailing).is_err());
+
+        // lengths and counts beyond the size of the index are rejected before allocating
+        let mut corrupt = buf.clone();
+        corrupt[8..16].copy_from_slice(&(1u64 << 60).to_le_bytes());
+        assert_eq!(
+            read_buf(&corrupt).err().unwrap().kind(),
+            ErrorKind::UnexpectedEof
+        );
+        let mut corrupt = buf.clone();
+        corrupt[16..24].copy_from_slice(&u64::MAX.to_le_bytes());
+        assert_eq!(
+            read_buf(&corrupt).err().unwrap().kind(),
+            ErrorKind::UnexpectedEof
+        );
+
+        // the edge refers to node 3 of the 2 nodes, placed after the two nodes and the edge count
+        let mut corrupt = buf;
+        let edge = 16 + 2 * (8 + 1 + 4) + 8;
+        corrupt[edge..edge + 8].copy_from_slice(&(3u64 << 1).to_le_bytes());
+        assert_eq!(
+            read_buf(&corrupt).err().unwrap().kind(),
+            ErrorKind::InvalidData
+        );
+    }
+
+    #[test]
+    fn test_graph_index_subset_exclude() {
+        let dir = tempfile::tempdir().unwrap();
+        let gfa_file = "tests/test_files/t_groups.gfa".to_string();
+        let index_file = dir
+            .path()
+            .join("t_groups.pidx")
+            .to_str()
+            .unwrap()
+            .to_string();
+        write_graph_index(&gfa_file, &index_file).unwrap();
+        let subset = dir.path().join("subset.txt").to_str().unwrap().to_string();
+        std::fs::write(&subset, "x:0-20\ny#2\ny#3:0-10\n").unwrap();
+        let exclude = dir.path().join("exclude.txt").to_str().unwrap().to_string();
+        std::fs::write(&exclude, "y#3:2-5\n").unwrap();
+
+        let countables = |graph: &str, count: CountType| {
+            let reqs = HashSet::from([
+                InputRequirement::Graph(graph.to_string()),
+                InputRequirement::Node,
+                InputRequirement::Bp,
+            ]);
+            let state = GraphState {
+                graph: graph.to_string(),
+                subset: subset.clone(),
+                exclude: exclude.clone(),
+                ..GraphState::default()
+            };
+            let mut gb = GraphBroker::new();
+            gb.change_graph_state(state, &reqs, false).unwrap();
+            gb.get_abacus_by_total(count).countable.clone()
+        };
+        for count in [CountType::Node, CountType::Bp] {
+            assert_eq!(countables(&index_file, count), countables(&gfa_file, count));
+        }
+    }
+}
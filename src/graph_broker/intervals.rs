@@ -0,0 +1,140 @@
+/* standard use */
+use std::io::{BufRead, BufReader, Error, Read};
+use std::vec;
+
+/* private use */
+use super::graph::{GraphStorage, ItemId, Orientation, PathSegment};
+use super::index::bufreader_from_graph;
+use super::util;
+use crate::io::InputProblems;
+
+/// Visit of a node by a path/walk, given as path/walk, node, orientation, and start and end of
+/// the node in the coordinates of the path/walk
+pub type NodeInterval = (PathSegment, ItemId, Orientation, usize, usize);
+
+/// Iterator over the node intervals of all paths/walks, in the order in which they appear in the
+/// graph file; the file is read lazily, one path/walk at a time. Malformed paths/walks are skipped
+/// and reported together as error at the end of the iteration
+pub struct PathNodeIntervals<'a> {
+    graph_storage: &'a GraphStorage,
+    node_lens: &'a [u32],
+    data: BufReader<Box<dyn Read>>,
+    problems: Option<InputProblems>,
+    line: usize,
+    buf: Vec<u8>,
+    // path/walk whose steps are currently iterated, its remaining steps, and the current position
+    path: Option<PathSegment>,
+    steps: vec::IntoIter<(ItemId, Orientation)>,
+    pos: usize,
+}
+
+impl<'a> PathNodeIntervals<'a> {
+    pub(super) fn new(
+        gfa_file: &str,
+        graph_storage: &'a GraphStorage,
+        node_lens: &'a [u32],
+    ) -> Result<Self, Error> {
+        Ok(Self {
+            graph_storage,
+            node_lens,
+            data: bufreader_from_graph(gfa_file, graph_storage)?,
+            problems: Some(InputProblems::new(gfa_file)),
+            line: 0,
+            buf: Vec::new(),
+            path: None,
+            steps: Vec::new().into_iter(),
+            pos: 0,
+        })
+    }
+
+    // advances to the next path/walk of the graph file (or of the index, in which case line counts
+    // the paths/walks); returns false at the end of the file
+    fn next_path(&mut self) -> Result<bool, Error> {
+        if let Some(paths) = &self.graph_storage.indexed_paths {
+            if self.line == paths.path_count() {
+                return Ok(false);
+            }
+            let path_seg = self.graph_storage.path_segments[self.line].clone();
+            self.steps = paths.get(self.line).to_vec().into_iter();
+            self.pos = path_seg.start.unwrap_or(0);
+            self.path = Some(path_seg);
+            self.line += 1;
+            return Ok(true);
+        }
+        let problems = self.problems.as_mut().unwrap();
+        loop {
+            self.buf.clear();
+            if self.data.read_until(b'\n', &mut self.buf)? == 0 {
+                return Ok(false);
+            }
+            self.line += 1;
+            match parse_path_line(&self.buf, self.graph_storage, |_| true) {
+                Some(Ok((path_seg, steps))) => {
+                    self.pos = path_seg.start.unwrap_or(0);
+                    self.path = Some(path_seg);
+                    self.steps = steps.into_iter();
+                    return Ok(true);
+                }
+                Some(Err((col, msg))) => problems.push(self.line, col, msg),
+                None => (),
+            }
+        }
+    }
+}
+
+impl<'a> Iterator for PathNodeIntervals<'a> {
+    type Item = Result<NodeInterval, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let (Some(path_seg), Some((node, o))) = (&self.path, self.steps.next()) {
+                let start = self.pos;
+                self.pos += self.node_lens[node.0 as usize] as usize;
+                return Some(Ok((path_seg.clone(), node, o, start, self.pos)));
+            }
+            // problems are reported once, after which the iteration ends
+            self.problems.as_ref()?;
+            match self.next_path() {
+                Ok(true) => (),
+                Ok(false) => return self.problems.take()?.into_result().err().map(Err),
+                Err(e) => {
+                    self.problems = None;
+                    return Some(Err(e));
+                }
+            }
+        }
+    }
+}
+
+/// Path/walk and its node sequence, given a line of the graph file, or the column and message of
+/// the problem if the path/walk is malformed; lines of other types and paths/walks that are not
+/// accepted by the filter yield none, the node sequences of the latter are not parsed
+pub(super) fn parse_path_line<F>(
+    buf: &[u8],
+    graph_storage: &GraphStorage,
+    filter: F,
+) -> Option<Result<(PathSegment, Vec<(ItemId, Orientation)>), (usize, String)>>
+where
+    F: Fn(&PathSegment) -> bool,
+{
+    let (identifier, steps_col) = match buf.first() {
+        Some(b'P') => (util::parse_path_identifier(buf), 3),
+        Some(b'W') => (util::parse_walk_identifier(buf), 7),
+        _ => return None,
+    };
+    match identifier {
+        Ok((path_seg, steps)) if filter(&path_seg) => {
+            let steps = match buf[0] {
+                b'P' => util::parse_path_seq_to_item_vec(steps, graph_storage),
+                _ => util::parse_walk_seq_to_item_vec(steps, graph_storage),
+            };
+            Some(
+                steps
+                    .map(|steps| (path_seg, steps))
+                    .map_err(|msg| (steps_col, msg)),
+            )
+        }
+        Ok(_) => None,
+        Err(e) => Some(Err(e)),
+    }
+}
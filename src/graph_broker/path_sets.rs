@@ -0,0 +1,347 @@
+/* standard use */
+use std::collections::BTreeMap;
+use std::fs::{File, OpenOptions};
+use std::io::{BufWriter, Error, Write};
+use std::path::{Path, PathBuf};
+
+/* external crate */
+use once_cell::sync::OnceCell;
+
+/* private use */
+use super::graph::PathSegment;
+
+// file to which the effective paths/path coordinates of set expressions are written; if unset,
+// they are only logged
+static PATH_SETS_FILE: OnceCell<PathBuf> = OnceCell::new();
+
+// intervals covering a whole path
+const WHOLE_PATH: (usize, usize) = (0, usize::MAX);
+
+/// Writes the paths/path coordinates resulting from subset and exclude lists combined by set
+/// operations to the given file (in BED format) from now on, such that they can be verified; can
+/// only be set once
+pub fn set_path_sets_file(file: &Path) -> Result<(), Error> {
+    File::create(file)?;
+    PATH_SETS_FILE.set(file.to_path_buf()).map_err(|_| {
+        Error::new(
+            std::io::ErrorKind::AlreadyExists,
+            "file of path sets has already been set",
+        )
+    })
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SetOperator {
+    Union,
+    Intersection,
+    Difference,
+}
+
+impl SetOperator {
+    fn from_char(c: char) -> Option<Self> {
+        match c {
+            '+' => Some(Self::Union),
+            '&' => Some(Self::Intersection),
+            '-' => Some(Self::Difference),
+            _ => None,
+        }
+    }
+}
+
+/// Files of a set expression such as `a.txt+b.txt-c.bed`, which combines lists of paths/path
+/// coordinates by union (+), intersection (&), and difference (-), evaluated from left to right;
+/// None if the text is not made of at least two files joined by operators. As file names may
+/// contain operator characters themselves, every split into existing files is tried
+pub fn parse_set_expression<F: Fn(&str) -> bool>(
+    text: &str,
+    is_file: &F,
+) -> Option<(String, Vec<(SetOperator, String)>)> {
+    for (i, c) in text.char_indices() {
+        let op = match SetOperator::from_char(c) {
+            Some(op) => op,
+            None => continue,
+        };
+        let (first, rest) = (&text[..i], &text[i + 1..]);
+        if first.is_empty() || !is_file(first) {
+            continue;
+        }
+        if is_file(rest) {
+            return Some((first.to_string(), vec![(op, rest.to_string())]));
+        }
+        if let Some((second, mut others)) = parse_set_expression(rest, is_file) {
+            others.insert(0, (op, second));
+            return Some((first.to_string(), others));
+        }
+    }
+    None
+}
+
+/// Files from which a subset or exclude list is loaded, i.e., the list itself if it is a file,
+/// or the files of its set expression
+pub fn get_coord_files(coord_text: &str) -> Vec<String> {
+    let is_file = |x: &str| Path::new(x).is_file();
+    if is_file(coord_text) {
+        vec![coord_text.to_string()]
+    } else if let Some((first, others)) = parse_set_expression(coord_text, &is_file) {
+        std::iter::once(first)
+            .chain(others.into_iter().map(|(_, file)| file))
+            .collect()
+    } else {
+        Vec::new()
+    }
+}
+
+// disjoint and sorted intervals of each path, where an interval spanning the whole path stands for
+// a path given without coordinates
+#[derive(Debug, Clone, Default, PartialEq)]
+struct PathSet(BTreeMap<PathSegment, Vec<(usize, usize)>>);
+
+impl PathSet {
+    fn from_coords(coords: Vec<PathSegment>) -> Self {
+        let mut res = Self::default();
+        for p in coords {
+            let interval = p.coords().unwrap_or(WHOLE_PATH);
+            res.0.entry(p.clear_coords()).or_default().push(interval);
+        }
+        for intervals in res.0.values_mut() {
+            *intervals = merge_intervals(std::mem::take(intervals));
+        }
+        res
+    }
+
+    fn into_coords(self) -> Vec<PathSegment> {
+        let mut res = Vec::new();
+        for (path, intervals) in self.0 {
+            for (start, end) in intervals {
+                if (start, end) == WHOLE_PATH {
+                    res.push(path.clone());
+                } else {
+                    let mut segment = path.clone();
+                    segment.start = Some(start);
+                    segment.end = Some(end);
+                    res.push(segment);
+                }
+            }
+        }
+        res
+    }
+
+    fn apply(self, op: SetOperator, other: Self) -> Self {
+        let mut res = Self::default();
+        match op {
+            SetOperator::Union => {
+                res = self;
+                for (path, intervals) in other.0 {
+                    let entry = res.0.entry(path).or_default();
+                    entry.extend(intervals);
+                    *entry = merge_intervals(std::mem::take(entry));
+                }
+            }
+            SetOperator::Intersection => {
+                for (path, intervals) in self.0 {
+                    if let Some(others) = other.0.get(&path) {
+                        res.0.insert(path, intersect_intervals(&intervals, others));
+                    }
+                }
+            }
+            SetOperator::Difference => {
+                for (path, intervals) in self.0 {
+                    let intervals = match other.0.get(&path) {
+                        Some(others) => subtract_intervals(&intervals, others),
+                        None => intervals,
+                    };
+                    res.0.insert(path, intervals);
+                }
+            }
+        }
+        res.0.retain(|_, intervals| !intervals.is_empty());
+        res
+    }
+}
+
+fn merge_intervals(mut intervals: Vec<(usize, usize)>) -> Vec<(usize, usize)> {
+    intervals.sort_unstable();
+    let mut res: Vec<(usize, usize)> = Vec::with_capacity(intervals.len());
+    for (start, end) in intervals {
+        match res.last_mut() {
+            Some(last) if start <= last.1 => last.1 = last.1.max(end),
+            _ => res.push((start, end)),
+        }
+    }
+    res
+}
+
+fn intersect_intervals(a: &[(usize, usize)], b: &[(usize, usize)]) -> Vec<(usize, usize)> {
+    let mut res = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < a.len() && j < b.len() {
+        let start = a[i].0.max(b[j].0);
+        let end = a[i].1.min(b[j].1);
+        if start < end {
+            res.push((start, end));
+        }
+        if a[i].1 < b[j].1 {
+            i += 1;
+        } else {
+            j += 1;
+        }
+    }
+    res
+}
+
+fn subtract_intervals(a: &[(usize, usize)], b: &[(usize, usize)]) -> Vec<(usize, usize)> {
+    let mut res = Vec::new();
+    for &(a_start, end) in a {
+        let mut start = a_start;
+        for &(b_start, b_end) in b.iter().filter(|x| x.1 > a_start && x.0 < end) {
+            if b_start > start {
+                res.push((start, b_start));
+            }
+            start = start.max(b_end);
+        }
+        if start < end {
+            res.push((start, end));
+        }
+    }
+    res
+}
+
+/// Evaluates the set expression, loading each of its files with the given function, and writes
+/// the resulting paths/path coordinates to the file of path sets, if set
+pub fn evaluate_set_expression<F>(
+    text: &str,
+    expression: (String, Vec<(SetOperator, String)>),
+    load: F,
+) -> Result<Vec<PathSegment>, Error>
+where
+    F: Fn(&str) -> Result<Vec<PathSegment>, Error>,
+{
+    let (first, others) = expression;
+    let mut res = PathSet::from_coords(load(&first)?);
+    for (op, file) in others {
+        res = res.apply(op, PathSet::from_coords(load(&file)?));
+    }
+    let coords = res.into_coords();
+    log::info!(
+        "set expression {} results in {} paths/path coordinates",
+        text,
+        coords.len()
+    );
+    if let Some(file) = PATH_SETS_FILE.get() {
+        let mut out = BufWriter::new(OpenOptions::new().append(true).open(file)?);
+        write_path_set(&mut out, text, &coords)?;
+        out.flush()?;
+    }
+    Ok(coords)
+}
+
+fn write_path_set<W: Write>(out: &mut W, text: &str, coords: &[PathSegment]) -> Result<(), Error> {
+    writeln!(out, "# {}", text)?;
+    for p in coords {
+        match p.coords() {
+            Some((start, end)) => writeln!(out, "{}\t{}\t{}", p.clear_coords().id(), start, end)?,
+            None => writeln!(out, "{}", p.id())?,
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn segment(name: &str, coords: Option<(usize, usize)>) -> PathSegment {
+        match coords {
+            Some((start, end)) => PathSegment::from_str_start_end(name, start, end),
+            None => PathSegment::from_str(name),
+        }
+    }
+
+    #[test]
+    fn test_parse_set_expression() {
+        let files = ["a.txt", "b-c.txt", "d.bed"];
+        let is_file = |x: &str| files.contains(&x);
+        assert_eq!(
+            parse_set_expression("a.txt+b-c.txt-d.bed", &is_file),
+            Some((
+                "a.txt".to_string(),
+                vec![
+                    (SetOperator::Union, "b-c.txt".to_string()),
+                    (SetOperator::Difference, "d.bed".to_string())
+                ]
+            ))
+        );
+        assert_eq!(
+            parse_set_expression("d.bed&a.txt", &is_file),
+            Some((
+                "d.bed".to_string(),
+                vec![(SetOperator::Intersection, "a.txt".to_string())]
+            ))
+        );
+        assert_eq!(parse_set_expression("a.txt", &is_file), None);
+        assert_eq!(parse_set_expression("a.txt+e.txt", &is_file), None);
+        assert_eq!(parse_set_expression("HG.*+", &is_file), None);
+    }
+
+    #[test]
+    fn test_interval_operations() {
+        let a = vec![(0, 10), (20, 30)];
+        let b = vec![(5, 25)];
+        assert_eq!(
+            merge_intervals(vec![(20, 30), (5, 25), (0, 10)]),
+            vec![(0, 30)]
+        );
+        assert_eq!(intersect_intervals(&a, &b), vec![(5, 10), (20, 25)]);
+        assert_eq!(subtract_intervals(&a, &b), vec![(0, 5), (25, 30)]);
+        assert_eq!(subtract_intervals(&b, &a), vec![(10, 20)]);
+        assert_eq!(subtract_intervals(&a, &[WHOLE_PATH]), vec![]);
+    }
+
+    #[test]
+    fn test_evaluate_set_expression() {
+        let load = |file: &str| -> Result<Vec<PathSegment>, Error> {
+            Ok(match file {
+                "a" => vec![segment("s1#1#chr1", None), segment("s2#1#chr1", None)],
+                "b" => vec![segment("s3#1#chr1", None)],
+                "c" => vec![
+                    segment("s1#1#chr1", Some((100, 200))),
+                    segment("s2#1#chr1", None),
+                ],
+                _ => unreachable!(),
+            })
+        };
+        let eval = |text: &str| {
+            let expression = parse_set_expression(text, &|x: &str| x.len() == 1).unwrap();
+            evaluate_set_expression(text, expression, load).unwrap()
+        };
+        assert_eq!(
+            eval("a+b"),
+            vec![
+                segment("s1#1#chr1", None),
+                segment("s2#1#chr1", None),
+                segment("s3#1#chr1", None)
+            ]
+        );
+        assert_eq!(
+            eval("a+b&c"),
+            vec![
+                segment("s1#1#chr1", Some((100, 200))),
+                segment("s2#1#chr1", None)
+            ]
+        );
+        let coords = eval("a-c");
+        assert_eq!(
+            coords,
+            vec![
+                segment("s1#1#chr1", Some((0, 100))),
+                segment("s1#1#chr1", Some((200, usize::MAX)))
+            ]
+        );
+        let mut out = Vec::new();
+        write_path_set(&mut out, "a-c", &coords[..1]).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "# a-c\ns1#1#chr1\t0\t100\n"
+        );
+    }
+}
@@ -0,0 +1,295 @@
+/* standard use */
+use std::fmt;
+use std::fs::{self, OpenOptions};
+use std::io::Error;
+use std::mem;
+use std::ops::{Deref, DerefMut};
+use std::path::{Path, PathBuf};
+use std::slice;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/* external crate */
+use memmap2::MmapMut;
+use once_cell::sync::OnceCell;
+
+// directory for temporary files to which large tables are spilled; if unset, tables are kept in
+// memory
+static SPILL_DIR: OnceCell<PathBuf> = OnceCell::new();
+// number of temporary files created so far, used to give each of them a unique name
+static SPILL_COUNT: AtomicUsize = AtomicUsize::new(0);
+// minimum number of elements by which the temporary file of a growing array is extended
+const SPILL_CHUNK_LEN: usize = 1 << 20;
+
+/// Spills item tables and group abaci to memory-mapped temporary files in the given directory
+/// from now on, such that graphs exceeding the available memory can be processed (slower); can
+/// only be set once
+pub fn set_spill_dir(dir: &Path) -> Result<(), Error> {
+    if !dir.is_dir() {
+        return Err(Error::new(
+            std::io::ErrorKind::NotFound,
+            format!("spill directory {} does not exist", dir.display()),
+        ));
+    }
+    SPILL_DIR.set(dir.to_path_buf()).map_err(|_| {
+        Error::new(
+            std::io::ErrorKind::AlreadyExists,
+            "spill directory has already been set",
+        )
+    })?;
+    log::info!("spilling large tables to {}", dir.display());
+    Ok(())
+}
+
+/// Plain integer types that can be stored in memory-mapped files
+pub trait Spillable: Copy + fmt::Debug {}
+
+impl Spillable for u16 {}
+impl Spillable for u32 {}
+impl Spillable for u64 {}
+impl Spillable for usize {}
+
+/// Array that is either held in memory or, if a spill directory is set, in a memory-mapped
+/// temporary file that is removed once the array is dropped
+pub enum SpillVec<T: Spillable> {
+    Memory(Vec<T>),
+    Disk {
+        map: Option<MmapMut>,
+        len: usize,
+        // number of elements the temporary file has room for
+        capacity: usize,
+        path: PathBuf,
+    },
+}
+
+impl<T: Spillable> SpillVec<T> {
+    /// Array of the given length whose elements are all set to value
+    pub fn filled(value: T, len: usize) -> Result<Self, Error> {
+        match SPILL_DIR.get() {
+            Some(dir) => {
+                let mut res = Self::create(dir, len)?;
+                res.fill(value);
+                Ok(res)
+            }
+            None => Ok(Self::Memory(vec![value; len])),
+        }
+    }
+
+    /// Moves the elements of the vector to a temporary file if a spill directory is set
+    pub fn from_vec(v: Vec<T>) -> Result<Self, Error> {
+        match SPILL_DIR.get() {
+            Some(dir) => {
+                let mut res = Self::create(dir, v.len())?;
+                res.copy_from_slice(&v);
+                Ok(res)
+            }
+            None => Ok(Self::Memory(v)),
+        }
+    }
+
+    /// Empty array to which elements are appended; if a spill directory is set, they are
+    /// written to a temporary file that grows as needed, such that the array is never held in
+    /// memory as a whole
+    pub fn growable() -> Self {
+        match SPILL_DIR.get() {
+            Some(dir) => Self::create(dir, 0).unwrap_or_else(|e| {
+                log::warn!("unable to spill table, keeping it in memory: {}", e);
+                Self::Memory(Vec::new())
+            }),
+            None => Self::Memory(Vec::new()),
+        }
+    }
+
+    /// Appends an element, extending the temporary file if it is full; if that fails, the array
+    /// is moved to memory
+    pub fn push(&mut self, value: T) {
+        if let Self::Disk { len, capacity, .. } = self {
+            if *len == *capacity {
+                if let Err(e) = self.grow() {
+                    log::warn!(
+                        "unable to extend spilled table, keeping it in memory: {}",
+                        e
+                    );
+                    *self = Self::Memory(self.to_vec());
+                }
+            }
+        }
+        match self {
+            Self::Memory(v) => v.push(value),
+            Self::Disk { len, .. } => {
+                *len += 1;
+                let last = *len - 1;
+                self[last] = value;
+            }
+        }
+    }
+
+    // doubles the room of the temporary file, by at least SPILL_CHUNK_LEN elements
+    fn grow(&mut self) -> Result<(), Error> {
+        if let Self::Disk {
+            map,
+            capacity,
+            path,
+            ..
+        } = self
+        {
+            let new_capacity = usize::max(2 * *capacity, SPILL_CHUNK_LEN);
+            let file = OpenOptions::new().read(true).write(true).open(&*path)?;
+            file.set_len((new_capacity * mem::size_of::<T>()) as u64)?;
+            log::debug!(
+                "extending {} to {} bytes",
+                path.display(),
+                new_capacity * mem::size_of::<T>()
+            );
+            *map = Some(unsafe { MmapMut::map_mut(&file)? });
+            *capacity = new_capacity;
+        }
+        Ok(())
+    }
+
+    fn create(dir: &Path, len: usize) -> Result<Self, Error> {
+        let path = dir.join(format!(
+            "panacus-{}-{}.tmp",
+            std::process::id(),
+            SPILL_COUNT.fetch_add(1, Ordering::SeqCst)
+        ));
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create_new(true)
+            .open(&path)?;
+        // the file is removed on drop, hence also if mapping it fails
+        let mut res = Self::Disk {
+            map: None,
+            len,
+            capacity: len,
+            path,
+        };
+        let size = len * mem::size_of::<T>();
+        file.set_len(size as u64)?;
+        // empty files cannot be mapped
+        if size > 0 {
+            if let Self::Disk { map, path, .. } = &mut res {
+                log::debug!("spilling {} bytes to {}", size, path.display());
+                *map = Some(unsafe { MmapMut::map_mut(&file)? });
+            }
+        }
+        Ok(res)
+    }
+}
+
+impl<T: Spillable> Default for SpillVec<T> {
+    fn default() -> Self {
+        Self::Memory(Vec::new())
+    }
+}
+
+impl<T: Spillable> From<Vec<T>> for SpillVec<T> {
+    fn from(v: Vec<T>) -> Self {
+        Self::Memory(v)
+    }
+}
+
+impl<T: Spillable> Deref for SpillVec<T> {
+    type Target = [T];
+
+    fn deref(&self) -> &[T] {
+        match self {
+            Self::Memory(v) => v,
+            // mappings are page-aligned and thus suitably aligned for any integer type
+            Self::Disk {
+                map: Some(map),
+                len,
+                ..
+            } => unsafe { slice::from_raw_parts(map.as_ptr() as *const T, *len) },
+            Self::Disk { map: None, .. } => &[],
+        }
+    }
+}
+
+impl<T: Spillable> DerefMut for SpillVec<T> {
+    fn deref_mut(&mut self) -> &mut [T] {
+        match self {
+            Self::Memory(v) => v,
+            Self::Disk {
+                map: Some(map),
+                len,
+                ..
+            } => unsafe { slice::from_raw_parts_mut(map.as_mut_ptr() as *mut T, *len) },
+            Self::Disk { map: None, .. } => &mut [],
+        }
+    }
+}
+
+impl<T: Spillable> Clone for SpillVec<T> {
+    fn clone(&self) -> Self {
+        match self {
+            Self::Memory(v) => Self::Memory(v.clone()),
+            Self::Disk { .. } => Self::from_vec(self.to_vec()).unwrap_or_else(|e| {
+                log::warn!("unable to spill copy of table, keeping it in memory: {}", e);
+                Self::Memory(self.to_vec())
+            }),
+        }
+    }
+}
+
+impl<T: Spillable> fmt::Debug for SpillVec<T> {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.debug_list().entries(self.iter()).finish()
+    }
+}
+
+impl<T: Spillable> Drop for SpillVec<T> {
+    fn drop(&mut self) {
+        if let Self::Disk { map, path, .. } = self {
+            // unmap before removing the file, which is required on some platforms
+            drop(map.take());
+            if let Err(e) = fs::remove_file(&*path) {
+                log::warn!("unable to remove {}: {}", path.display(), e);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_spill_vec() {
+        let dir = tempfile::tempdir().unwrap();
+        // the spill directory is set process-wide, hence the file is created explicitly
+        let mut v = SpillVec::<u64>::create(dir.path(), 4).unwrap();
+        v.fill(7);
+        v[2] = 3;
+        assert_eq!(&v[..], &[7, 7, 3, 7]);
+        let path = match &v {
+            SpillVec::Disk { path, .. } => path.clone(),
+            _ => panic!("table is not spilled"),
+        };
+        assert!(path.exists());
+        assert_eq!(&v.clone()[..], &[7, 7, 3, 7]);
+        drop(v);
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn test_spill_vec_push() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut v = SpillVec::<u32>::create(dir.path(), 0).unwrap();
+        let n = 2 * SPILL_CHUNK_LEN + 1;
+        for i in 0..n {
+            v.push(i as u32);
+        }
+        // the elements are written to the file as they are appended, rather than buffered in
+        // memory, and the file grows by at most twice the elements it holds
+        match &v {
+            SpillVec::Disk { len, capacity, .. } => {
+                assert_eq!(*len, n);
+                assert!(*capacity < 2 * n);
+            }
+            _ => panic!("table is not spilled"),
+        }
+        assert_eq!(v.len(), n);
+        assert!(v.iter().enumerate().all(|(i, x)| *x == i as u32));
+    }
+}
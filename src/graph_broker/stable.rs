@@ -0,0 +1,223 @@
+/* standard use */
+use std::collections::HashMap;
+use std::str::{self, FromStr};
+
+/* private use */
+use super::graph::{ItemId, Orientation, PathSegment};
+use crate::util::ItemIdSize;
+
+/// Paths/walks that spell out (a fragment of) a stable sequence, given per stable sequence as
+/// path, and start and end of the fragment in stable coordinates
+pub type StablePaths = HashMap<String, Vec<(PathSegment, usize, usize)>>;
+
+/// Stable coordinates of the segments of a graph in rGFA format, given by their SN (name of the
+/// stable sequence), SO (offset on the stable sequence), and SR (rank) tags
+#[derive(Debug, Clone, Default)]
+pub struct StableCoords {
+    // segments of each stable sequence, sorted by offset
+    sequences: HashMap<String, Vec<(usize, ItemId)>>,
+    ranks: HashMap<String, u32>,
+    // stable sequence and offset of each tagged segment
+    node2stable: HashMap<ItemIdSize, (String, usize)>,
+}
+
+impl StableCoords {
+    /// Records the stable coordinates given by the tags of a segment, if it has any
+    pub fn add_segment(&mut self, id: ItemId, tags: &[&[u8]]) -> Result<(), String> {
+        if let Some((seq, offset, rank)) = parse_stable_tags(tags)? {
+            self.insert(id, seq, offset, rank);
+        }
+        Ok(())
+    }
+
+    pub fn insert(&mut self, id: ItemId, seq: String, offset: usize, rank: u32) {
+        self.ranks.insert(seq.clone(), rank);
+        self.sequences
+            .entry(seq.clone())
+            .or_default()
+            .push((offset, id));
+        self.node2stable.insert(id.0, (seq, offset));
+    }
+
+    /// Sorts the segments of each stable sequence by their offset, once all are added
+    pub fn finish(&mut self) {
+        self.sequences.values_mut().for_each(|s| s.sort());
+        if !self.sequences.is_empty() {
+            log::info!(
+                "found stable coordinates of {} segments on {} stable sequences ({} of rank 0)",
+                self.node2stable.len(),
+                self.sequences.len(),
+                self.ranks.values().filter(|r| **r == 0).count()
+            );
+        }
+    }
+
+    /// Stable sequence, offset, and rank of each tagged segment, sorted by segment ID
+    pub fn get_segments(&self) -> Vec<(ItemId, &str, usize, u32)> {
+        let mut res: Vec<(ItemId, &str, usize, u32)> = self
+            .node2stable
+            .iter()
+            .map(|(id, (seq, offset))| (ItemId(*id), seq.as_str(), *offset, self.ranks[seq]))
+            .collect();
+        res.sort();
+        res
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.sequences.is_empty()
+    }
+
+    pub fn get_names(&self) -> Vec<&str> {
+        self.sequences.keys().map(|s| s.as_str()).collect()
+    }
+
+    /// Segments of the stable sequence, split into fragments wherever consecutive segments are
+    /// not adjacent; each fragment is given as path segment in stable coordinates
+    pub fn get_steps(
+        &self,
+        name: &str,
+        node_lens: &[u32],
+    ) -> Vec<(PathSegment, Vec<(ItemId, Orientation)>)> {
+        let mut res: Vec<(PathSegment, Vec<(ItemId, Orientation)>)> = Vec::new();
+        for (offset, id) in self.sequences.get(name).into_iter().flatten() {
+            let end = offset + node_lens[id.0 as usize] as usize;
+            match res.last_mut() {
+                Some((fragment, steps)) if fragment.end == Some(*offset) => {
+                    fragment.end = Some(end);
+                    steps.push((*id, Orientation::Forward));
+                }
+                _ => res.push((
+                    PathSegment::from_str_start_end(name, *offset, end),
+                    vec![(*id, Orientation::Forward)],
+                )),
+            }
+        }
+        res
+    }
+
+    /// Start and end in stable coordinates of the given path/walk steps, if they spell out a
+    /// contiguous stretch of a single stable sequence, together with the name of that sequence
+    pub fn get_stable_range(
+        &self,
+        steps: &[(ItemId, Orientation)],
+        node_lens: &[u32],
+    ) -> Option<(&str, usize, usize)> {
+        let (seq, start) = self.node2stable.get(&steps.first()?.0 .0)?;
+        let mut end = *start;
+        for (id, o) in steps {
+            match self.node2stable.get(&id.0) {
+                Some((s, offset)) if s == seq && *offset == end && *o == Orientation::Forward => {
+                    end += node_lens[id.0 as usize] as usize;
+                }
+                _ => return None,
+            }
+        }
+        Some((seq, *start, end))
+    }
+}
+
+// stable sequence name, offset, and rank of a segment, given by its SN, SO, and SR tags
+fn parse_stable_tags(tags: &[&[u8]]) -> Result<Option<(String, usize, u32)>, String> {
+    let (mut name, mut offset, mut rank) = (None, None, None);
+    for tag in tags {
+        let value = || str::from_utf8(&tag[5..]).unwrap_or("");
+        match tag.get(..5) {
+            Some(b"SN:Z:") => name = Some(value().to_string()),
+            Some(b"SO:i:") => {
+                offset = Some(
+                    usize::from_str(value())
+                        .map_err(|_| format!("SO tag `{}` is not a valid offset", value()))?,
+                )
+            }
+            Some(b"SR:i:") => {
+                rank = Some(
+                    u32::from_str(value())
+                        .map_err(|_| format!("SR tag `{}` is not a valid rank", value()))?,
+                )
+            }
+            _ => (),
+        }
+    }
+    match (name, offset) {
+        (Some(name), Some(offset)) => Ok(Some((name, offset, rank.unwrap_or(0)))),
+        (None, None) => Ok(None),
+        _ => Err("segment must have both or neither of SN and SO tags".to_string()),
+    }
+}
+
+/// Replaces path segments that refer to stable sequences rather than paths/walks by the
+/// corresponding intervals of the paths/walks spelling out these sequences
+pub fn resolve_stable_coords(
+    coords: Vec<PathSegment>,
+    paths: &[PathSegment],
+    stable_paths: &StablePaths,
+) -> Vec<PathSegment> {
+    let mut res = Vec::new();
+    for coord in coords {
+        let id = coord.clear_coords();
+        let fragments = match stable_paths.get(&id.id()) {
+            Some(fragments) if !paths.iter().any(|p| p.clear_coords() == id) => fragments,
+            _ => {
+                res.push(coord);
+                continue;
+            }
+        };
+        let (start, end) = coord.coords().unwrap_or((0, usize::MAX));
+        for (path, stable_start, stable_end) in fragments {
+            let (lo, hi) = (
+                usize::max(start, *stable_start),
+                usize::min(end, *stable_end),
+            );
+            if lo < hi {
+                let path_start = path.start.unwrap_or(0);
+                let mut interval = path.clear_coords();
+                interval.start = Some(path_start + lo - stable_start);
+                interval.end = Some(path_start + hi - stable_start);
+                log::debug!("stable coordinates {} map to path {}", coord, interval);
+                res.push(interval);
+            }
+        }
+    }
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_stable_tags() {
+        let tags: Vec<&[u8]> = vec![b"LN:i:10", b"SN:Z:chr1", b"SO:i:1200", b"SR:i:1"];
+        assert_eq!(
+            parse_stable_tags(&tags),
+            Ok(Some(("chr1".to_string(), 1200, 1)))
+        );
+        let tags: Vec<&[u8]> = vec![b"LN:i:10"];
+        assert_eq!(parse_stable_tags(&tags), Ok(None));
+        let tags: Vec<&[u8]> = vec![b"SN:Z:chr1"];
+        assert!(parse_stable_tags(&tags).is_err());
+        let tags: Vec<&[u8]> = vec![b"SN:Z:chr1", b"SO:i:x"];
+        assert!(parse_stable_tags(&tags).is_err());
+    }
+
+    #[test]
+    fn test_resolve_stable_coords() {
+        let paths = vec![
+            PathSegment::from_str("GRCh38#0#chr1:100-600"),
+            PathSegment::from_str("HG00438#1#chr1"),
+        ];
+        let stable_paths =
+            StablePaths::from([("chr1".to_string(), vec![(paths[0].clone(), 1000, 1500)])]);
+        let coords = vec![
+            PathSegment::from_str("chr1:1200-2000"),
+            PathSegment::from_str("HG00438#1#chr1:5-10"),
+        ];
+        assert_eq!(
+            resolve_stable_coords(coords, &paths, &stable_paths),
+            vec![
+                PathSegment::from_str("GRCh38#0#chr1:300-600"),
+                PathSegment::from_str("HG00438#1#chr1:5-10"),
+            ]
+        );
+    }
+}
@@ -1,35 +1,125 @@
 use itertools::Itertools;
-use memchr::{memchr, memchr2};
+use memchr::{memchr, memchr2, memchr3};
 use std::str::{self, FromStr};
-use std::time::Instant;
 use std::{
     collections::HashMap,
-    io::{BufRead, BufReader, Read},
-    sync::{atomic::AtomicU32, Arc, Mutex},
+    io::{BufRead, BufReader, Error, Read},
 };
 
 use rayon::prelude::*;
 
 use crate::{
-    graph_broker::Edge,
-    util::{intersects, is_contained, ActiveTable, CountType, IntervalContainer, ItemTable, Wrap},
+    io::InputProblems,
+    util::{
+        intersects, is_contained, ActiveTable, CountType, IntervalContainer, ItemIdSize, ItemTable,
+        Stopwatch,
+    },
 };
 
 use super::{abacus::GraphMask, graph::GraphStorage, ItemId, Orientation, PathSegment};
 
 const CHUNK_SIZE: usize = 8192;
 
+/// Steps of a path/walk, either given by the steps column of its line in the graph file or, if
+/// the graph is loaded from an index, already parsed
+#[derive(Clone, Copy)]
+pub enum PathSteps<'a> {
+    Path(&'a [u8]),
+    Walk(&'a [u8]),
+    Parsed(&'a [(ItemId, Orientation)]),
+}
+
+impl<'a> PathSteps<'a> {
+    /// Column of the steps in the graph file, used to report problems
+    pub fn col(&self) -> usize {
+        match self {
+            PathSteps::Path(_) => 3,
+            PathSteps::Walk(_) => 7,
+            PathSteps::Parsed(_) => 0,
+        }
+    }
+
+    pub fn to_item_vec(
+        self,
+        graph_storage: &GraphStorage,
+    ) -> Result<Vec<(ItemId, Orientation)>, String> {
+        match self {
+            PathSteps::Path(steps) => parse_path_seq_to_item_vec(steps, graph_storage),
+            PathSteps::Walk(steps) => parse_walk_seq_to_item_vec(steps, graph_storage),
+            PathSteps::Parsed(steps) => Ok(steps.to_vec()),
+        }
+    }
+}
+
+/// Reads the paths/walks of a graph one at a time, either from its graph file or, if the graph is
+/// loaded from an index, from the index; each path/walk is given with its line number (or its
+/// rank in the index) and either its path segment and steps or the column and message of the
+/// problem with its identifier
+pub struct PathReader<'a, R: Read> {
+    data: &'a mut BufReader<R>,
+    graph_storage: &'a GraphStorage,
+    buf: Vec<u8>,
+    line: usize,
+}
+
+impl<'a, R: Read> PathReader<'a, R> {
+    pub fn new(data: &'a mut BufReader<R>, graph_storage: &'a GraphStorage) -> Self {
+        Self {
+            data,
+            graph_storage,
+            buf: Vec::new(),
+            line: 0,
+        }
+    }
+
+    #[allow(clippy::type_complexity)]
+    pub fn next_path(
+        &mut self,
+    ) -> Result<Option<(usize, Result<(PathSegment, PathSteps<'_>), (usize, String)>)>, Error> {
+        if let Some(paths) = &self.graph_storage.indexed_paths {
+            if self.line == paths.path_count() {
+                return Ok(None);
+            }
+            self.line += 1;
+            let path_seg = self.graph_storage.path_segments[self.line - 1].clone();
+            let steps = PathSteps::Parsed(paths.get(self.line - 1));
+            return Ok(Some((self.line, Ok((path_seg, steps)))));
+        }
+        loop {
+            self.buf.clear();
+            if self.data.read_until(b'\n', &mut self.buf)? == 0 {
+                return Ok(None);
+            }
+            self.line += 1;
+            if self.buf[0] == b'P' || self.buf[0] == b'W' {
+                break;
+            }
+        }
+        let record = match self.buf[0] {
+            b'P' => parse_path_identifier(&self.buf)
+                .map(|(path_seg, steps)| (path_seg, PathSteps::Path(steps))),
+            _ => parse_walk_identifier(&self.buf)
+                .map(|(path_seg, steps)| (path_seg, PathSteps::Walk(steps))),
+        };
+        Ok(Some((self.line, record)))
+    }
+}
+
 pub fn parse_gfa_paths_walks_multiple<R: Read>(
     data: &mut BufReader<R>,
+    gfa_file: &str,
     graph_mask: &GraphMask,
     graph_storage: &GraphStorage,
     count_types: &Vec<CountType>,
-) -> (
-    Vec<ItemTable>,
-    Vec<Option<ActiveTable>>,
-    Option<IntervalContainer>,
-    HashMap<PathSegment, (u32, u32)>,
-) {
+) -> Result<
+    (
+        Vec<ItemTable>,
+        Vec<Option<ActiveTable>>,
+        Option<IntervalContainer>,
+        HashMap<PathSegment, (u32, u32)>,
+    ),
+    Error,
+> {
     log::info!("parsing path + walk sequences");
     let mut item_tables =
         vec![ItemTable::new(graph_storage.path_segments.len()); count_types.len()];
@@ -37,143 +127,192 @@ pub fn parse_gfa_paths_walks_multiple<R: Read>(
     let (mut subset_covered_bps, mut exclude_tables, include_map, exclude_map) =
         graph_mask.load_optional_subsetting_multiple(graph_storage, count_types);
 
+    // count types that share a table, i.e., node and bp counts, in order of their first appearance
+    let mut indices: Vec<(CountType, Vec<usize>)> = Vec::new();
+    for (i, count) in count_types.iter().enumerate() {
+        let count = match count {
+            CountType::Node => CountType::Bp,
+            count => *count,
+        };
+        match indices.iter_mut().find(|(c, _)| *c == count) {
+            Some((_, is)) => is.push(i),
+            None => indices.push((count, vec![i])),
+        }
+    }
+    // edge and bubble counts need the steps of each path as items
+    let items_needed = indices
+        .iter()
+        .any(|(c, _)| matches!(c, CountType::Edge | CountType::Bubble));
+
     let mut num_path = 0;
     let complete: Vec<(usize, usize)> = vec![(0, usize::MAX)];
     let mut paths_len: HashMap<PathSegment, (u32, u32)> = HashMap::new();
 
-    let mut buf = vec![];
-    let timer = Instant::now();
-    while data.read_until(b'\n', &mut buf).unwrap_or(0) > 0 {
-        if buf[0] == b'P' || buf[0] == b'W' {
-            let (path_seg, buf_path_seg) = match buf[0] {
-                b'P' => parse_path_identifier(&buf),
-                b'W' => parse_walk_identifier(&buf),
-                _ => unreachable!(),
-            };
-
-            log::debug!("processing path {}", &path_seg);
-
-            let include_coords = if graph_mask.include_coords.is_none() {
-                &complete[..]
-            } else {
-                match include_map.get(&path_seg.id()) {
-                    None => &[],
-                    Some(coords) => {
-                        log::debug!(
-                            "found include coords {:?} for path segment {}",
-                            &coords[..],
-                            &path_seg.id()
-                        );
-                        &coords[..]
-                    }
+    let mut problems = InputProblems::new(gfa_file);
+    let mut paths = PathReader::new(data, graph_storage);
+    let timer = Stopwatch::start();
+    while let Some((line, record)) = paths.next_path()? {
+        let (path_seg, steps) = match record {
+            Ok(x) => x,
+            Err((col, msg)) => {
+                problems.push(line, col, msg);
+                num_path += 1;
+                continue;
+            }
+        };
+        let steps_col = steps.col();
+
+        log::debug!("processing path {}", &path_seg);
+
+        let path_id = path_seg.id();
+        let include_coords = if graph_mask.include_coords.is_none() {
+            &complete[..]
+        } else {
+            match include_map.get(&path_id) {
+                None => &[],
+                Some(coords) => {
+                    log::debug!(
+                        "found include coords {:?} for path segment {}",
+                        &coords[..],
+                        &path_id
+                    );
+                    &coords[..]
                 }
-            };
-            let exclude_coords = if graph_mask.exclude_coords.is_none() {
-                &[]
-            } else {
-                match exclude_map.get(&path_seg.id()) {
-                    None => &[],
-                    Some(coords) => {
-                        log::debug!(
-                            "found exclude coords {:?} for path segment {}",
-                            &coords[..],
-                            &path_seg.id()
-                        );
-                        &coords[..]
-                    }
+            }
+        };
+        let exclude_coords = if graph_mask.exclude_coords.is_none() {
+            &[]
+        } else {
+            match exclude_map.get(&path_id) {
+                None => &[],
+                Some(coords) => {
+                    log::debug!(
+                        "found exclude coords {:?} for path segment {}",
+                        &coords[..],
+                        &path_id
+                    );
+                    &coords[..]
                 }
-            };
+            }
+        };
 
-            let (start, end) = path_seg.coords().unwrap_or((0, usize::MAX));
+        let (start, end) = path_seg.range();
 
-            // do not process the path sequence if path is neither part of subset nor exclude
-            if graph_mask.include_coords.is_some()
-                && !intersects(include_coords, &(start, end))
-                && !intersects(exclude_coords, &(start, end))
-            {
-                log::debug!("path {} does not intersect with subset coordinates {:?} nor with exclude coordinates {:?} and therefore is skipped from processing",
+        // do not process the path sequence if path is neither part of subset nor exclude
+        if graph_mask.include_coords.is_some()
+            && !intersects(include_coords, &(start, end))
+            && !intersects(exclude_coords, &(start, end))
+        {
+            log::debug!("path {} does not intersect with subset coordinates {:?} nor with exclude coordinates {:?} and therefore is skipped from processing",
                     &path_seg, &include_coords, &exclude_coords);
 
-                // update prefix sum
-                for item_table in &mut item_tables {
-                    item_table.id_prefsum[num_path + 1] += item_table.id_prefsum[num_path];
-                }
-
-                num_path += 1;
-                buf.clear();
-                continue;
+            // update prefix sum
+            for item_table in &mut item_tables {
+                item_table.id_prefsum[num_path + 1] += item_table.id_prefsum[num_path];
             }
 
-            let mut indices: HashMap<CountType, Vec<usize>> = HashMap::new();
-            for (i, count_type) in count_types
-                .iter()
-                .map(|c| match c {
-                    CountType::Node => &CountType::Bp,
-                    count => count,
-                })
-                .enumerate()
-            {
-                if let Some(entry) = indices.get_mut(count_type) {
-                    (*entry).push(i);
-                } else {
-                    indices.insert(*count_type, vec![i]);
+            num_path += 1;
+            continue;
+        }
+
+        // the steps of the path are turned into items at most once and shared by all count types;
+        // node and bp counts of paths that are fully contained in the subset coordinates do not
+        // need them, unless they are there anyway
+        let fully_contained = !matches!(steps, PathSteps::Parsed(_))
+            && (graph_mask.include_coords.is_none() || is_contained(include_coords, &(start, end)))
+            && (graph_mask.exclude_coords.is_none() || is_contained(exclude_coords, &(start, end)));
+        let sids = if items_needed || !fully_contained {
+            match steps.to_item_vec(graph_storage) {
+                Ok(sids) => Some(sids),
+                Err(msg) => {
+                    problems.push(line, steps_col, msg);
+                    num_path += 1;
+                    continue;
                 }
             }
-            indices.into_iter().for_each(|(count, is)| {
-                if count != CountType::Edge
-                    && (graph_mask.include_coords.is_none()
-                        || is_contained(include_coords, &(start, end)))
-                        && (graph_mask.exclude_coords.is_none()
-                            || is_contained(exclude_coords, &(start, end)))
-                {
-                    log::debug!("path {} is fully contained within subset coordinates {:?} and is eligible for full parallel processing", path_seg, include_coords);
-                    let mut none = None;
-                    let ex: Vec<&mut Option<ActiveTable>> = if exclude_coords.is_empty() {
-                        vec![&mut none]
-                    } else {
-                        exclude_tables.iter_mut().enumerate().filter(|(i, _)| is.contains(i)).map(|(_, e)| e).collect()
-                    };
-                    let (num_added_nodes, bp_len) = match buf[0] {
-                        b'P' => parse_path_seq_update_tables_multiple(
-                            buf_path_seg,
+        } else {
+            None
+        };
+
+        for (count, is) in &indices {
+            if *count != CountType::Edge && *count != CountType::Bubble && fully_contained {
+                log::debug!("path {} is fully contained within subset coordinates {:?} and is eligible for full parallel processing", path_seg, include_coords);
+                let mut none = None;
+                let ex: Vec<&mut Option<ActiveTable>> = if exclude_coords.is_empty() {
+                    vec![&mut none]
+                } else {
+                    exclude_tables
+                        .iter_mut()
+                        .enumerate()
+                        .filter(|(i, _)| is.contains(i))
+                        .map(|(_, e)| e)
+                        .collect()
+                };
+                let res = if let Some(sids) = &sids {
+                    Ok(update_tables_from_items_multiple(
+                        sids,
+                        graph_storage,
+                        &mut item_tables[is[0]],
+                        ex,
+                        num_path,
+                    ))
+                } else {
+                    match steps {
+                        PathSteps::Path(steps) => parse_path_seq_update_tables_multiple(
+                            steps,
                             graph_storage,
                             &mut item_tables[is[0]],
                             ex,
                             num_path,
                         ),
-                        b'W' => parse_walk_seq_update_tables_multiple(
-                            buf_path_seg,
+                        PathSteps::Walk(steps) => parse_walk_seq_update_tables_multiple(
+                            steps,
                             graph_storage,
                             &mut item_tables[is[0]],
                             ex,
                             num_path,
                         ),
-                        _ => unreachable!(),
-                    };
-                    paths_len.insert(path_seg.clone(), (num_added_nodes, bp_len));
-                } else {
-                    let sids = match buf[0] {
-                        b'P' => parse_path_seq_to_item_vec(buf_path_seg, graph_storage),
-                        b'W' => parse_walk_seq_to_item_vec(buf_path_seg, graph_storage),
-                        _ => unreachable!(),
-                    };
-                    let mut exclude_tables_red = exclude_tables.iter_mut().enumerate().filter(|(i, _)| is.contains(i)).map(|(_, e)| e).collect();
-                    match count {
-                        CountType::Node | CountType::Bp => {
-                            let (node_len, bp_len) = update_tables_multiple(
-                                &mut item_tables[is[0]],
-                                &mut subset_covered_bps.as_mut(),
-                                exclude_tables_red,
-                                num_path,
-                                graph_storage,
-                                sids,
-                                include_coords,
-                                exclude_coords,
-                                start,
-                            );
-                            paths_len.insert(path_seg.clone(), (node_len as u32, bp_len as u32));
+                        PathSteps::Parsed(_) => {
+                            unreachable!("parsed steps are not processed in parallel")
                         }
-                        CountType::Edge => update_tables_edgecount(
+                    }
+                };
+                match res {
+                    Ok((num_added_nodes, bp_len)) => {
+                        paths_len.insert(path_seg.clone(), (num_added_nodes, bp_len));
+                    }
+                    Err(msg) => {
+                        problems.push(line, steps_col, msg);
+                        break;
+                    }
+                }
+            } else {
+                let sids = sids
+                    .as_ref()
+                    .expect("steps are turned into items if they are needed");
+                let mut exclude_tables_red = exclude_tables
+                    .iter_mut()
+                    .enumerate()
+                    .filter(|(i, _)| is.contains(i))
+                    .map(|(_, e)| e)
+                    .collect();
+                match count {
+                    CountType::Node | CountType::Bp => {
+                        let (node_len, bp_len) = update_tables_multiple(
+                            &mut item_tables[is[0]],
+                            &mut subset_covered_bps.as_mut(),
+                            exclude_tables_red,
+                            num_path,
+                            graph_storage,
+                            sids,
+                            include_coords,
+                            exclude_coords,
+                            start,
+                        );
+                        paths_len.insert(path_seg.clone(), (node_len as u32, bp_len as u32));
+                    }
+                    CountType::Edge => {
+                        if let Err(msg) = update_tables_edgecount(
                             &mut item_tables[is[0]],
                             &mut exclude_tables_red[0].as_mut(),
                             num_path,
@@ -182,15 +321,35 @@ pub fn parse_gfa_paths_walks_multiple<R: Read>(
                             include_coords,
                             exclude_coords,
                             start,
-                        ),
-                        CountType::All => unreachable!("inadmissable count type"),
-                    };
-                }
-            });
-            num_path += 1;
+                        ) {
+                            problems.push(line, steps_col, msg);
+                            break;
+                        }
+                    }
+                    CountType::Bubble => {
+                        if let Err(msg) = update_tables_bubblecount(
+                            &mut item_tables[is[0]],
+                            &mut exclude_tables_red[0].as_mut(),
+                            num_path,
+                            graph_storage,
+                            &PathTraversal {
+                                path: sids,
+                                include_coords,
+                                exclude_coords,
+                                offset: start,
+                            },
+                        ) {
+                            problems.push(line, steps_col, msg);
+                            break;
+                        }
+                    }
+                    CountType::All => unreachable!("inadmissable count type"),
+                };
+            }
         }
-        buf.clear();
+        num_path += 1;
     }
+    problems.into_result()?;
     let duration = timer.elapsed();
     log::info!(
         "func done; count: {:?}; time elapsed: {:?}",
@@ -198,24 +357,32 @@ pub fn parse_gfa_paths_walks_multiple<R: Read>(
         duration
     );
 
-    // In case of node/bps, copy first table into the second
-    if item_tables.len() == 2 {
-        item_tables[1] = item_tables[0].clone();
+    // node and bp counts share the table that is filled for the first of them, copy it into the
+    // other
+    let node = count_types.iter().position(|c| c == &CountType::Node);
+    let bp = count_types.iter().position(|c| c == &CountType::Bp);
+    if let (Some(node), Some(bp)) = (node, bp) {
+        let (first, second) = (usize::min(node, bp), usize::max(node, bp));
+        item_tables[second] = item_tables[first].clone();
     }
-    (item_tables, exclude_tables, subset_covered_bps, paths_len)
+    Ok((item_tables, exclude_tables, subset_covered_bps, paths_len))
 }
 
 pub fn parse_gfa_paths_walks<R: Read>(
     data: &mut BufReader<R>,
+    gfa_file: &str,
     graph_mask: &GraphMask,
     graph_storage: &GraphStorage,
     count: &CountType,
-) -> (
-    ItemTable,
-    Option<ActiveTable>,
-    Option<IntervalContainer>,
-    HashMap<PathSegment, (u32, u32)>,
-) {
+) -> Result<
+    (
+        ItemTable,
+        Option<ActiveTable>,
+        Option<IntervalContainer>,
+        HashMap<PathSegment, (u32, u32)>,
+    ),
+    Error,
+> {
     log::info!("parsing path + walk sequences");
     let mut item_table = ItemTable::new(graph_storage.path_segments.len());
 
@@ -226,165 +393,215 @@ pub fn parse_gfa_paths_walks<R: Read>(
     let complete: Vec<(usize, usize)> = vec![(0, usize::MAX)];
     let mut paths_len: HashMap<PathSegment, (u32, u32)> = HashMap::new();
 
-    let mut buf = vec![];
-    let timer = Instant::now();
-    while data.read_until(b'\n', &mut buf).unwrap_or(0) > 0 {
-        if buf[0] == b'P' || buf[0] == b'W' {
-            let (path_seg, buf_path_seg) = match buf[0] {
-                b'P' => parse_path_identifier(&buf),
-                b'W' => parse_walk_identifier(&buf),
-                _ => unreachable!(),
-            };
-
-            log::debug!("processing path {}", &path_seg);
-
-            let include_coords = if graph_mask.include_coords.is_none() {
-                &complete[..]
-            } else {
-                match include_map.get(&path_seg.id()) {
-                    None => &[],
-                    Some(coords) => {
-                        log::debug!(
-                            "found include coords {:?} for path segment {}",
-                            &coords[..],
-                            &path_seg.id()
-                        );
-                        &coords[..]
-                    }
+    let mut problems = InputProblems::new(gfa_file);
+    let mut paths = PathReader::new(data, graph_storage);
+    let timer = Stopwatch::start();
+    while let Some((line, record)) = paths.next_path()? {
+        let (path_seg, steps) = match record {
+            Ok(x) => x,
+            Err((col, msg)) => {
+                problems.push(line, col, msg);
+                num_path += 1;
+                continue;
+            }
+        };
+        let steps_col = steps.col();
+
+        log::debug!("processing path {}", &path_seg);
+
+        let include_coords = if graph_mask.include_coords.is_none() {
+            &complete[..]
+        } else {
+            match include_map.get(&path_seg.id()) {
+                None => &[],
+                Some(coords) => {
+                    log::debug!(
+                        "found include coords {:?} for path segment {}",
+                        &coords[..],
+                        &path_seg.id()
+                    );
+                    &coords[..]
                 }
-            };
-            let exclude_coords = if graph_mask.exclude_coords.is_none() {
-                &[]
-            } else {
-                match exclude_map.get(&path_seg.id()) {
-                    None => &[],
-                    Some(coords) => {
-                        log::debug!(
-                            "found exclude coords {:?} for path segment {}",
-                            &coords[..],
-                            &path_seg.id()
-                        );
-                        &coords[..]
-                    }
+            }
+        };
+        let exclude_coords = if graph_mask.exclude_coords.is_none() {
+            &[]
+        } else {
+            match exclude_map.get(&path_seg.id()) {
+                None => &[],
+                Some(coords) => {
+                    log::debug!(
+                        "found exclude coords {:?} for path segment {}",
+                        &coords[..],
+                        &path_seg.id()
+                    );
+                    &coords[..]
                 }
-            };
+            }
+        };
 
-            let (start, end) = path_seg.coords().unwrap_or((0, usize::MAX));
+        let (start, end) = path_seg.range();
 
-            // do not process the path sequence if path is neither part of subset nor exclude
-            if graph_mask.include_coords.is_some()
-                && !intersects(include_coords, &(start, end))
-                && !intersects(exclude_coords, &(start, end))
-            {
-                log::debug!("path {} does not intersect with subset coordinates {:?} nor with exclude coordinates {:?} and therefore is skipped from processing",
+        // do not process the path sequence if path is neither part of subset nor exclude
+        if graph_mask.include_coords.is_some()
+            && !intersects(include_coords, &(start, end))
+            && !intersects(exclude_coords, &(start, end))
+        {
+            log::debug!("path {} does not intersect with subset coordinates {:?} nor with exclude coordinates {:?} and therefore is skipped from processing",
                     &path_seg, &include_coords, &exclude_coords);
 
-                // update prefix sum
-                item_table.id_prefsum[num_path + 1] += item_table.id_prefsum[num_path];
+            // update prefix sum
+            item_table.id_prefsum[num_path + 1] += item_table.id_prefsum[num_path];
 
-                num_path += 1;
-                buf.clear();
-                continue;
+            num_path += 1;
+            continue;
+        }
+
+        if count != &CountType::Edge
+            && count != &CountType::Bubble
+            && !matches!(steps, PathSteps::Parsed(_))
+            && (graph_mask.include_coords.is_none() || is_contained(include_coords, &(start, end)))
+            && (graph_mask.exclude_coords.is_none() || is_contained(exclude_coords, &(start, end)))
+        {
+            log::debug!("path {} is fully contained within subset coordinates {:?} and is eligible for full parallel processing", path_seg, include_coords);
+            let ex = if exclude_coords.is_empty() {
+                None
+            } else {
+                exclude_table.as_mut()
+            };
+            let res = match steps {
+                PathSteps::Path(steps) => parse_path_seq_update_tables(
+                    steps,
+                    graph_storage,
+                    &mut item_table,
+                    ex,
+                    num_path,
+                ),
+                PathSteps::Walk(steps) => parse_walk_seq_update_tables(
+                    steps,
+                    graph_storage,
+                    &mut item_table,
+                    ex,
+                    num_path,
+                ),
+                PathSteps::Parsed(_) => unreachable!("parsed steps are not processed in parallel"),
+            };
+            match res {
+                Ok((num_added_nodes, bp_len)) => {
+                    paths_len.insert(path_seg, (num_added_nodes, bp_len));
+                }
+                Err(msg) => problems.push(line, steps_col, msg),
             }
+        } else {
+            let sids = match steps.to_item_vec(graph_storage) {
+                Ok(sids) => sids,
+                Err(msg) => {
+                    problems.push(line, steps_col, msg);
+                    num_path += 1;
+                    continue;
+                }
+            };
 
-            if count != &CountType::Edge
-                && (graph_mask.include_coords.is_none()
-                    || is_contained(include_coords, &(start, end)))
-                && (graph_mask.exclude_coords.is_none()
-                    || is_contained(exclude_coords, &(start, end)))
-            {
-                log::debug!("path {} is fully contained within subset coordinates {:?} and is eligible for full parallel processing", path_seg, include_coords);
-                let ex = if exclude_coords.is_empty() {
-                    None
-                } else {
-                    exclude_table.as_mut()
-                };
-                let (num_added_nodes, bp_len) = match buf[0] {
-                    b'P' => parse_path_seq_update_tables(
-                        buf_path_seg,
-                        graph_storage,
+            match count {
+                CountType::Node | CountType::Bp => {
+                    let (node_len, bp_len) = update_tables(
                         &mut item_table,
-                        ex,
+                        &mut subset_covered_bps.as_mut(),
+                        &mut exclude_table.as_mut(),
                         num_path,
-                    ),
-                    b'W' => parse_walk_seq_update_tables(
-                        buf_path_seg,
                         graph_storage,
-                        &mut item_table,
-                        ex,
-                        num_path,
-                    ),
-                    _ => unreachable!(),
-                };
-                paths_len.insert(path_seg, (num_added_nodes, bp_len));
-            } else {
-                let sids = match buf[0] {
-                    b'P' => parse_path_seq_to_item_vec(buf_path_seg, graph_storage),
-                    b'W' => parse_walk_seq_to_item_vec(buf_path_seg, graph_storage),
-                    _ => unreachable!(),
-                };
-
-                match count {
-                    CountType::Node | CountType::Bp => {
-                        let (node_len, bp_len) = update_tables(
-                            &mut item_table,
-                            &mut subset_covered_bps.as_mut(),
-                            &mut exclude_table.as_mut(),
-                            num_path,
-                            graph_storage,
-                            sids,
-                            include_coords,
-                            exclude_coords,
-                            start,
-                        );
-                        paths_len.insert(path_seg, (node_len as u32, bp_len as u32));
-                    }
-                    CountType::Edge => update_tables_edgecount(
+                        sids,
+                        include_coords,
+                        exclude_coords,
+                        start,
+                    );
+                    paths_len.insert(path_seg, (node_len as u32, bp_len as u32));
+                }
+                CountType::Edge => {
+                    if let Err(msg) = update_tables_edgecount(
                         &mut item_table,
                         &mut exclude_table.as_mut(),
                         num_path,
                         graph_storage,
-                        sids,
+                        &sids,
                         include_coords,
                         exclude_coords,
                         start,
-                    ),
-                    CountType::All => unreachable!("inadmissable count type"),
-                };
-            }
-            num_path += 1;
+                    ) {
+                        problems.push(line, steps_col, msg);
+                    }
+                }
+                CountType::Bubble => {
+                    if let Err(msg) = update_tables_bubblecount(
+                        &mut item_table,
+                        &mut exclude_table.as_mut(),
+                        num_path,
+                        graph_storage,
+                        &PathTraversal {
+                            path: &sids,
+                            include_coords,
+                            exclude_coords,
+                            offset: start,
+                        },
+                    ) {
+                        problems.push(line, steps_col, msg);
+                    }
+                }
+                CountType::All => unreachable!("inadmissable count type"),
+            };
         }
-        buf.clear();
+        num_path += 1;
     }
+    problems.into_result()?;
     let duration = timer.elapsed();
     log::info!(
         "func done; count: {:?}; time elapsed: {:?}",
         count,
         duration
     );
-    (item_table, exclude_table, subset_covered_bps, paths_len)
+    Ok((item_table, exclude_table, subset_covered_bps, paths_len))
+}
+
+// returns the position of the first tab or line break, i.e., the end of the current GFA column
+pub fn find_field_end(data: &[u8]) -> Option<usize> {
+    memchr3(b'\t', b'\n', b'\r', data)
 }
 
-pub fn parse_walk_identifier(data: &[u8]) -> (PathSegment, &[u8]) {
+// malformed identifiers are reported as (1-based column, message)
+pub fn parse_walk_identifier(data: &[u8]) -> Result<(PathSegment, &[u8]), (usize, String)> {
     let mut six_col: Vec<&str> = Vec::with_capacity(6);
 
-    let mut it = data.iter();
     let mut i = 0;
-    for _ in 0..6 {
-        let j = it.position(|x| x == &b'\t').unwrap();
-        six_col.push(str::from_utf8(&data[i..i + j]).unwrap());
+    for c in 0..6 {
+        let j = memchr(b'\t', &data[i..]).ok_or_else(|| {
+            (
+                0,
+                format!("walk must have at least 7 columns, but has {}", c + 1),
+            )
+        })?;
+        six_col.push(
+            str::from_utf8(&data[i..i + j])
+                .map_err(|_| (c + 1, "some character is not UTF-8".to_string()))?,
+        );
         i += j + 1;
     }
 
-    let seq_start = match six_col[4] {
-        "*" => None,
-        a => Some(usize::from_str(a).unwrap()),
-    };
-
-    let seq_end = match six_col[5] {
-        "*" => None,
-        a => Some(usize::from_str(a).unwrap()),
+    let parse_coord = |c: usize| match six_col[c] {
+        "*" => Ok(None),
+        a => usize::from_str(a)
+            .map(Some)
+            .map_err(|_| (c + 1, format!("`{}` is neither '*' nor an usize", a))),
     };
+    let seq_start = parse_coord(4)?;
+    let seq_end = parse_coord(5)?;
+    if let (Some(start), Some(end)) = (seq_start, seq_end) {
+        if end < start {
+            return Err((
+                6,
+                format!("end {} of walk is smaller than its start {}", end, start),
+            ));
+        }
+    }
 
     let path_seg = PathSegment::new(
         six_col[1].to_string(),
@@ -394,19 +611,20 @@ pub fn parse_walk_identifier(data: &[u8]) -> (PathSegment, &[u8]) {
         seq_end,
     );
 
-    (path_seg, &data[i..])
+    Ok((path_seg, &data[i..]))
 }
 
-pub fn parse_path_identifier(data: &[u8]) -> (PathSegment, &[u8]) {
-    let mut iter = data.iter();
-
-    let start = iter.position(|&x| x == b'\t').unwrap() + 1;
-    let offset = iter.position(|&x| x == b'\t').unwrap();
-    let path_name = str::from_utf8(&data[start..start + offset]).unwrap();
-    (
+// malformed identifiers are reported as (1-based column, message)
+pub fn parse_path_identifier(data: &[u8]) -> Result<(PathSegment, &[u8]), (usize, String)> {
+    let too_short = || (0, "path must have at least 3 columns".to_string());
+    let start = memchr(b'\t', data).ok_or_else(too_short)? + 1;
+    let offset = memchr(b'\t', &data[start..]).ok_or_else(too_short)?;
+    let path_name = str::from_utf8(&data[start..start + offset])
+        .map_err(|_| (2, "some character is not UTF-8".to_string()))?;
+    Ok((
         PathSegment::from_str(path_name),
         &data[start + offset + 1..],
-    )
+    ))
 }
 
 pub fn update_tables_multiple(
@@ -415,7 +633,7 @@ pub fn update_tables_multiple(
     mut exclude_tables: Vec<&mut Option<ActiveTable>>,
     num_path: usize,
     graph_storage: &GraphStorage,
-    path: Vec<(ItemId, Orientation)>,
+    path: &[(ItemId, Orientation)],
     include_coords: &[(usize, usize)],
     exclude_coords: &[(usize, usize)],
     offset: usize,
@@ -437,7 +655,7 @@ pub fn update_tables_multiple(
     }
 
     let rexclude_tables = &mut exclude_tables;
-    for (sid, o) in &path {
+    for (sid, o) in path {
         let l = graph_storage.node_len(&sid) as usize;
 
         // this implementation of include coords for bps is *not exact* as illustrated by the
@@ -469,12 +687,8 @@ pub fn update_tables_multiple(
         let mut stop_here = false;
         while i < include_coords.len() && include_coords[i].0 < p + l && !stop_here {
             if include_coords[i].1 > p {
-                let mut a = if include_coords[i].0 > p {
-                    include_coords[i].0 - p
-                } else {
-                    0
-                };
-                let mut b = if include_coords[i].1 < p + l {
+                let a = include_coords[i].0.saturating_sub(p);
+                let b = if include_coords[i].1 < p + l {
                     // advance to the next interval
                     i += 1;
                     include_coords[i - 1].1 - p
@@ -483,21 +697,20 @@ pub fn update_tables_multiple(
                     l
                 };
 
-                // reverse coverage interval in case of backward orientation
-                if o == &Orientation::Backward {
-                    (a, b) = (l - b, l - a);
-                }
+                // reverse coverage interval in case of backward orientation, and place it on
+                // the representative of the node if duplicates are merged strand-aware
+                let (cid, a, b) = graph_storage.get_covered_interval(*sid, *o, a, b);
 
                 item_table.items.push(sid.0);
                 item_table.id_prefsum[num_path + 1] += 1;
                 if let Some(int) = subset_covered_bps.as_mut() {
                     // if fully covered, we do not need to store anything in the map
                     if b - a == l {
-                        if int.contains(sid) {
-                            int.remove(sid);
+                        if int.contains(&cid) {
+                            int.remove(&cid);
                         }
                     } else {
-                        int.add(*sid, a, b);
+                        int.add(cid, a, b);
                     }
                 }
                 included += 1;
@@ -511,11 +724,7 @@ pub fn update_tables_multiple(
         let mut stop_here = false;
         while j < exclude_coords.len() && exclude_coords[j].0 < p + l && !stop_here {
             if exclude_coords[j].1 > p {
-                let mut a = if exclude_coords[j].0 > p {
-                    exclude_coords[j].0 - p
-                } else {
-                    0
-                };
+                let mut a = exclude_coords[j].0.saturating_sub(p);
                 let mut b = if exclude_coords[j].1 < p + l {
                     // advance to the next interval for the next iteration
                     j += 1;
@@ -625,12 +834,8 @@ pub fn update_tables(
         let mut stop_here = false;
         while i < include_coords.len() && include_coords[i].0 < p + l && !stop_here {
             if include_coords[i].1 > p {
-                let mut a = if include_coords[i].0 > p {
-                    include_coords[i].0 - p
-                } else {
-                    0
-                };
-                let mut b = if include_coords[i].1 < p + l {
+                let a = include_coords[i].0.saturating_sub(p);
+                let b = if include_coords[i].1 < p + l {
                     // advance to the next interval
                     i += 1;
                     include_coords[i - 1].1 - p
@@ -639,21 +844,20 @@ pub fn update_tables(
                     l
                 };
 
-                // reverse coverage interval in case of backward orientation
-                if o == &Orientation::Backward {
-                    (a, b) = (l - b, l - a);
-                }
+                // reverse coverage interval in case of backward orientation, and place it on
+                // the representative of the node if duplicates are merged strand-aware
+                let (cid, a, b) = graph_storage.get_covered_interval(*sid, *o, a, b);
 
                 item_table.items.push(sid.0);
                 item_table.id_prefsum[num_path + 1] += 1;
                 if let Some(int) = subset_covered_bps.as_mut() {
                     // if fully covered, we do not need to store anything in the map
                     if b - a == l {
-                        if int.contains(sid) {
-                            int.remove(sid);
+                        if int.contains(&cid) {
+                            int.remove(&cid);
                         }
                     } else {
-                        int.add(*sid, a, b);
+                        int.add(cid, a, b);
                     }
                 }
                 included += 1;
@@ -667,11 +871,7 @@ pub fn update_tables(
         let mut stop_here = false;
         while j < exclude_coords.len() && exclude_coords[j].0 < p + l && !stop_here {
             if exclude_coords[j].1 > p {
-                let mut a = if exclude_coords[j].0 > p {
-                    exclude_coords[j].0 - p
-                } else {
-                    0
-                };
+                let mut a = exclude_coords[j].0.saturating_sub(p);
                 let mut b = if exclude_coords[j].1 < p + l {
                     // advance to the next interval for the next iteration
                     j += 1;
@@ -725,11 +925,11 @@ pub fn update_tables_edgecount(
     exclude_table: &mut Option<&mut ActiveTable>,
     num_path: usize,
     graph_storage: &GraphStorage,
-    path: Vec<(ItemId, Orientation)>,
+    path: &[(ItemId, Orientation)],
     include_coords: &[(usize, usize)],
     exclude_coords: &[(usize, usize)],
     offset: usize,
-) {
+) -> Result<(), String> {
     let mut i = 0;
     let mut j = 0;
     let mut p = offset;
@@ -741,7 +941,7 @@ pub fn update_tables_edgecount(
 
     log::debug!("checking inclusion/exclusion criteria on {} nodes, inserting successful candidates to corresponding data structures..", path.len());
 
-    for ((sid1, o1), (sid2, o2)) in path.into_iter().tuple_windows() {
+    for ((sid1, o1), (sid2, o2)) in path.iter().copied().tuple_windows() {
         // update current pointer in include_coords list
         while i < include_coords.len() && include_coords[i].1 <= p {
             i += 1;
@@ -754,35 +954,14 @@ pub fn update_tables_edgecount(
 
         let l = graph_storage.node_len(&sid2) as usize;
 
-        let e = Edge::canonical(sid1, o1, sid2, o2);
-        let eid = graph_storage
-            .edge2id
-            .as_ref()
-            .expect("update_tables_edgecount requires edge2id map in GraphStorage")
-            .get(&e)
-            .unwrap_or_else(|| {
-                panic!(
-                    "unknown edge {}. Is flipped edge known? {}",
-                    &e,
-                    if graph_storage
-                        .edge2id
-                        .as_ref()
-                        .unwrap()
-                        .contains_key(&e.flip())
-                    {
-                        "Yes"
-                    } else {
-                        "No"
-                    }
-                )
-            });
+        let eid = graph_storage.get_edge_id(sid1, o1, sid2, o2)?;
         // check if the current position fits within active segment
         if i < include_coords.len() && include_coords[i].0 < p + l {
             item_table.items.push(eid.0);
             item_table.id_prefsum[num_path + 1] += 1;
         }
         if exclude_table.is_some() && j < exclude_coords.len() && exclude_coords[j].0 < p + l {
-            exclude_table.as_mut().unwrap().activate(eid);
+            exclude_table.as_mut().unwrap().activate(&eid);
         } else if i >= include_coords.len() && j >= exclude_coords.len() {
             // terminate parse if all "include" and "exclude" coords are processed
             break;
@@ -792,27 +971,85 @@ pub fn update_tables_edgecount(
     // Compute prefix sum
     item_table.id_prefsum[num_path + 1] += item_table.id_prefsum[num_path];
     log::debug!("..done");
+    Ok(())
+}
+
+/// Steps of a path along with the coordinates (relative to the path) of its subset and excluded
+/// parts, given by intervals sorted by position, and the position of its first step
+pub struct PathTraversal<'a> {
+    pub path: &'a [(ItemId, Orientation)],
+    pub include_coords: &'a [(usize, usize)],
+    pub exclude_coords: &'a [(usize, usize)],
+    pub offset: usize,
+}
+
+pub fn update_tables_bubblecount(
+    item_table: &mut ItemTable,
+    exclude_table: &mut Option<&mut ActiveTable>,
+    num_path: usize,
+    graph_storage: &GraphStorage,
+    traversal: &PathTraversal,
+) -> Result<(), String> {
+    let PathTraversal {
+        path,
+        include_coords,
+        exclude_coords,
+        offset,
+    } = *traversal;
+    let alleles = graph_storage
+        .bubbles
+        .as_ref()
+        .expect("update_tables_bubblecount requires bubble index in GraphStorage")
+        .get_path_alleles(path)?;
+
+    // start position of each step
+    let mut pos = Vec::with_capacity(path.len() + 1);
+    let mut p = offset;
+    for (sid, _) in path {
+        pos.push(p);
+        p += graph_storage.node_len(sid) as usize;
+    }
+    pos.push(p);
+
+    log::debug!(
+        "checking inclusion/exclusion criteria on {} bubble alleles..",
+        alleles.len()
+    );
+    for (i, j, aid) in alleles {
+        // an allele is positioned between entrance and exit of its bubble; alleles without
+        // inner nodes (deletions) are positioned at the start of the exit
+        let interval = (pos[i + 1], usize::max(pos[j], pos[i + 1] + 1));
+        if intersects(include_coords, &interval) {
+            item_table.items.push(aid.0);
+            item_table.id_prefsum[num_path + 1] += 1;
+        }
+        if exclude_table.is_some() && intersects(exclude_coords, &interval) {
+            exclude_table.as_mut().unwrap().activate(&aid);
+        }
+    }
+    // Compute prefix sum
+    item_table.id_prefsum[num_path + 1] += item_table.id_prefsum[num_path];
+    log::debug!("..done");
+    Ok(())
 }
 
 pub fn parse_walk_seq_to_item_vec(
     data: &[u8],
     graph_storage: &GraphStorage,
-) -> Vec<(ItemId, Orientation)> {
+) -> Result<Vec<(ItemId, Orientation)>, String> {
     // later codes assumes that data is non-empty...
     if data.is_empty() {
-        return Vec::new();
+        return Ok(Vec::new());
     }
 
-    let mut it = data.iter();
-    let end = it
-        .position(|x| x == &b'\t' || x == &b'\n' || x == &b'\r')
-        .unwrap_or_else(|| it.len());
+    let end = find_field_end(data).unwrap_or(data.len());
 
     log::debug!("parsing walk sequences of size {}..", end);
 
     let segment_ids: Vec<_> = (0..end)
+        .into_par_iter()
         .step_by(CHUNK_SIZE)
-        .map(|chunk_start| {
+        .map(|chunk_start| -> Result<_, String> {
             let chunk_end = *[end, chunk_start + CHUNK_SIZE].iter().min().unwrap();
 
             let mut curr_pos = match chunk_start {
@@ -833,20 +1070,20 @@ pub fn parse_walk_seq_to_item_vec(
                 if curr_pos >= segment_end {
                     break;
                 }
-                let segment_id = get_walk_segment_id(&data[curr_pos..segment_end], graph_storage);
+                let segment_id = get_walk_segment_id(&data[curr_pos..segment_end], graph_storage)?;
                 let orientation = Orientation::from_lg(data[curr_pos]);
                 segment_ids.push((segment_id, orientation));
                 // move curr_pos forward (after next comma)
                 curr_pos = segment_end;
             }
-            segment_ids
+            Ok(segment_ids)
         })
-        .collect();
+        .collect::<Result<_, String>>()?;
 
     let sids = segment_ids.into_iter().concat();
 
     log::debug!("..done");
-    sids
+    Ok(sids)
 }
 
 pub fn parse_walk_seq_update_tables_multiple(
@@ -855,20 +1092,17 @@ pub fn parse_walk_seq_update_tables_multiple(
     item_table: &mut ItemTable,
     exclude_tables: Vec<&mut Option<ActiveTable>>,
     num_path: usize,
-) -> (u32, u32) {
+) -> Result<(u32, u32), String> {
     // later codes assumes that data is non-empty...
     if data.is_empty() {
-        return (0, 0);
+        return Ok((0, 0));
     }
 
-    let mut it = data.iter();
-    let end = it
-        .position(|x| x == &b'\t' || x == &b'\n' || x == &b'\r')
-        .unwrap();
+    let end = find_field_end(data).unwrap_or(data.len());
 
     log::debug!("parsing walk sequences of size {}..", end);
 
-    let (segment_ids, bp_len) = get_walk_segment_ids(data, graph_storage, end, CHUNK_SIZE);
+    let (segment_ids, bp_len) = get_walk_segment_ids(data, graph_storage, end, CHUNK_SIZE)?;
 
     segment_ids.into_iter().for_each(|segment_id| {
         item_table.items.push(segment_id.0);
@@ -893,7 +1127,7 @@ pub fn parse_walk_seq_update_tables_multiple(
     }
 
     log::debug!("..done");
-    (num_nodes_path as u32, bp_len)
+    Ok((num_nodes_path as u32, bp_len))
 }
 
 pub fn parse_walk_seq_update_tables(
@@ -902,44 +1136,22 @@ pub fn parse_walk_seq_update_tables(
     item_table: &mut ItemTable,
     exclude_table: Option<&mut ActiveTable>,
     num_path: usize,
-) -> (u32, u32) {
+) -> Result<(u32, u32), String> {
     // later codes assumes that data is non-empty...
     if data.is_empty() {
-        return (0, 0);
+        return Ok((0, 0));
     }
 
-    let items_ptr = Wrap(&mut item_table.items);
-    let id_prefsum_ptr = Wrap(&mut item_table.id_prefsum);
-
-    let mutex_item_table = Arc::new(Mutex::new(&mut item_table.items));
-
-    let mut it = data.iter();
-    let end = it
-        .position(|x| x == &b'\t' || x == &b'\n' || x == &b'\r')
-        .unwrap();
+    let end = find_field_end(data).unwrap_or(data.len());
 
     log::debug!("parsing walk sequences of size {}..", end);
 
-    let bp_len = Arc::new(AtomicU32::new(0));
-    // ignore first > | < so that no empty is created for 1st node
-    data[1..end]
-        .par_split(|&x| x == b'>' || x == b'<')
-        .for_each(|node| {
-            let sid = graph_storage
-                .get_node_id(node)
-                .unwrap_or_else(|| panic!("unknown node {}", str::from_utf8(node).unwrap()));
-            if let Ok(_) = mutex_item_table.lock() {
-                unsafe {
-                    (*items_ptr.0).push(sid.0);
-                    (&mut (*id_prefsum_ptr.0))[num_path + 1] += 1;
-                }
-            }
-            bp_len.fetch_add(
-                graph_storage.node_len(&sid),
-                std::sync::atomic::Ordering::SeqCst,
-            );
-        });
-    let bp_len = bp_len.load(std::sync::atomic::Ordering::SeqCst);
+    let (segment_ids, bp_len) = get_walk_segment_ids(data, graph_storage, end, CHUNK_SIZE)?;
+
+    segment_ids.into_iter().for_each(|segment_id| {
+        item_table.items.push(segment_id.0);
+        item_table.id_prefsum[num_path + 1] += 1;
+    });
 
     // compute prefix sum
     let mut num_nodes_path = 0;
@@ -957,24 +1169,21 @@ pub fn parse_walk_seq_update_tables(
     }
 
     log::debug!("..done");
-    (num_nodes_path as u32, bp_len)
+    Ok((num_nodes_path as u32, bp_len))
 }
 
 pub fn parse_path_seq_to_item_vec(
     data: &[u8],
     graph_storage: &GraphStorage,
-) -> Vec<(ItemId, Orientation)> {
-    let mut it = data.iter();
-    let end = it
-        .position(|x| x == &b'\t' || x == &b'\n' || x == &b'\r')
-        .unwrap_or_else(|| it.len());
+) -> Result<Vec<(ItemId, Orientation)>, String> {
+    let end = find_field_end(data).unwrap_or(data.len());
 
     log::debug!("parsing path sequences of size {}..", end);
 
     let segment_ids: Vec<_> = (0..end)
         .into_par_iter()
         .step_by(CHUNK_SIZE)
-        .map(|chunk_start| {
+        .map(|chunk_start| -> Result<_, String> {
             let chunk_end = *[end, chunk_start + CHUNK_SIZE].iter().min().unwrap();
 
             // sits after first comma in chunk
@@ -996,53 +1205,62 @@ pub fn parse_path_seq_to_item_vec(
                     Some(idx) => curr_pos + idx,
                 };
                 let segment_end = if segment_end < end { segment_end } else { end };
-                if curr_pos >= segment_end {
+                if is_path_end(data, curr_pos, end) {
                     break;
                 }
-                let segment_id = get_segment_id(&data[curr_pos..segment_end], graph_storage);
+                let segment_id = get_segment_id(&data[curr_pos..segment_end], graph_storage)?;
                 let orientation = Orientation::from_pm(data[segment_end - 1]);
                 segment_ids.push((segment_id, orientation));
                 // move curr_pos forward (after next comma)
                 curr_pos = segment_end + 1;
             }
-            segment_ids
+            Ok(segment_ids)
         })
-        .collect();
+        .collect::<Result<_, String>>()?;
 
     log::debug!("..done");
 
     let segment_ids = segment_ids.into_iter().concat();
-    segment_ids
+    Ok(segment_ids)
 }
 
-fn get_segment_id(node: &[u8], graph_storage: &GraphStorage) -> ItemId {
-    let segment_id = graph_storage
-        .get_node_id(&node[0..node.len() - 1])
-        .unwrap_or_else(|| panic!("unknown node {}", str::from_utf8(node).unwrap()));
+// position in the path sequence after the last step; an empty step, as in `1+,,2+` or `1+,2+,`,
+// does not end the path, but is reported by get_segment_id
+fn is_path_end(data: &[u8], pos: usize, end: usize) -> bool {
+    pos > end || (pos == end && (pos == 0 || data[pos - 1] != b','))
+}
+
+fn get_segment_id(node: &[u8], graph_storage: &GraphStorage) -> Result<ItemId, String> {
     // TODO: Is orientation really necessary?
-    let orientation = node[node.len() - 1];
-    assert!(
-        orientation == b'-' || orientation == b'+',
-        "unknown orientation of segment {}",
-        str::from_utf8(node).unwrap()
-    );
+    let orientation = match node.last() {
+        Some(orientation) => *orientation,
+        None => return Err("empty segment in path".to_string()),
+    };
+    if orientation != b'-' && orientation != b'+' {
+        return Err(format!(
+            "unknown orientation of segment {}",
+            String::from_utf8_lossy(node)
+        ));
+    }
     //plus_strands[rayon::current_thread_index().unwrap()] += (orientation == b'+') as u32;
-    segment_id
+    graph_storage
+        .get_node_id(&node[0..node.len() - 1])
+        .ok_or_else(|| format!("unknown node {}", String::from_utf8_lossy(node)))
 }
 
-fn get_walk_segment_id(node: &[u8], graph_storage: &GraphStorage) -> ItemId {
-    let segment_id = graph_storage
-        .get_node_id(&node[1..node.len()])
-        .unwrap_or_else(|| panic!("unknown node {}", str::from_utf8(node).unwrap()));
+fn get_walk_segment_id(node: &[u8], graph_storage: &GraphStorage) -> Result<ItemId, String> {
     // TODO: Is orientation really necessary?
     let orientation = node[0];
-    assert!(
-        orientation == b'<' || orientation == b'>',
-        "unknown orientation of segment {}",
-        str::from_utf8(node).unwrap()
-    );
+    if orientation != b'<' && orientation != b'>' {
+        return Err(format!(
+            "unknown orientation of segment {}",
+            String::from_utf8_lossy(node)
+        ));
+    }
     //plus_strands[rayon::current_thread_index().unwrap()] += (orientation == b'+') as u32;
-    segment_id
+    graph_storage
+        .get_node_id(&node[1..node.len()])
+        .ok_or_else(|| format!("unknown node {}", String::from_utf8_lossy(node)))
 }
 
 fn get_walk_segment_ids(
@@ -1050,10 +1268,11 @@ fn get_walk_segment_ids(
     graph_storage: &GraphStorage,
     end: usize,
     chunk_size: usize,
-) -> (Vec<ItemId>, u32) {
-    let (segment_ids, bp_lens): (Vec<_>, Vec<_>) = (0..end)
+) -> Result<(Vec<ItemId>, u32), String> {
+    let chunks: Vec<(Vec<ItemId>, u32)> = (0..end)
+        .into_par_iter()
         .step_by(chunk_size)
-        .map(|chunk_start| {
+        .map(|chunk_start| -> Result<_, String> {
             let chunk_end = *[end, chunk_start + chunk_size].iter().min().unwrap();
             let mut bp_len: u32 = 0;
 
@@ -1075,19 +1294,20 @@ fn get_walk_segment_ids(
                 if curr_pos >= segment_end {
                     break;
                 }
-                let segment_id = get_walk_segment_id(&data[curr_pos..segment_end], graph_storage);
+                let segment_id = get_walk_segment_id(&data[curr_pos..segment_end], graph_storage)?;
                 bp_len += graph_storage.node_len(&segment_id);
                 segment_ids.push(segment_id);
                 // move curr_pos forward (after next comma)
                 curr_pos = segment_end;
             }
-            (segment_ids, bp_len)
+            Ok((segment_ids, bp_len))
         })
-        .unzip();
+        .collect::<Result<_, String>>()?;
+    let (segment_ids, bp_lens): (Vec<_>, Vec<_>) = chunks.into_iter().unzip();
 
     let segment_ids = segment_ids.into_iter().concat();
     let bp_len = bp_lens.into_iter().sum();
-    (segment_ids, bp_len)
+    Ok((segment_ids, bp_len))
 }
 
 fn get_path_segment_ids(
@@ -1095,11 +1315,11 @@ fn get_path_segment_ids(
     graph_storage: &GraphStorage,
     end: usize,
     chunk_size: usize,
-) -> (Vec<ItemId>, u32) {
-    let (segment_ids, bp_lens): (Vec<_>, Vec<_>) = (0..end)
+) -> Result<(Vec<ItemId>, u32), String> {
+    let chunks: Vec<(Vec<ItemId>, u32)> = (0..end)
         .into_par_iter()
         .step_by(chunk_size)
-        .map(|chunk_start| {
+        .map(|chunk_start| -> Result<_, String> {
             let chunk_end = *[end, chunk_start + chunk_size].iter().min().unwrap();
             let mut bp_len: u32 = 0;
 
@@ -1122,23 +1342,50 @@ fn get_path_segment_ids(
                     Some(idx) => curr_pos + idx,
                 };
                 let segment_end = if segment_end < end { segment_end } else { end };
-                if curr_pos >= segment_end {
+                if is_path_end(data, curr_pos, end) {
                     break;
                 }
-                let segment_id = get_segment_id(&data[curr_pos..segment_end], graph_storage);
+                let segment_id = get_segment_id(&data[curr_pos..segment_end], graph_storage)?;
                 bp_len += graph_storage.node_len(&segment_id);
                 segment_ids.push(segment_id);
                 // move curr_pos forward (after next comma)
                 curr_pos = segment_end + 1;
             }
-            (segment_ids, bp_len)
+            Ok((segment_ids, bp_len))
         })
-        .unzip();
+        .collect::<Result<_, String>>()?;
+    let (segment_ids, bp_lens): (Vec<_>, Vec<_>) = chunks.into_iter().unzip();
 
     let segment_ids = segment_ids.into_iter().concat();
     let bp_len = bp_lens.into_iter().sum();
 
-    (segment_ids, bp_len)
+    Ok((segment_ids, bp_len))
+}
+
+// counts the nodes of a path whose steps are already given as items, like
+// parse_path_seq_update_tables_multiple does for steps that are not yet parsed
+pub fn update_tables_from_items_multiple(
+    path: &[(ItemId, Orientation)],
+    graph_storage: &GraphStorage,
+    item_table: &mut ItemTable,
+    exclude_tables: Vec<&mut Option<ActiveTable>>,
+    num_path: usize,
+) -> (u32, u32) {
+    let mut bp_len = 0;
+    for (sid, _) in path {
+        item_table.items.push(sid.0);
+        bp_len += graph_storage.node_len(sid);
+    }
+    item_table.id_prefsum[num_path + 1] += path.len() as ItemIdSize;
+    item_table.id_prefsum[num_path + 1] += item_table.id_prefsum[num_path];
+
+    // is exclude table is given, we assume that all nodes of the path are excluded
+    for ex in exclude_tables.into_iter().flatten() {
+        for (sid, _) in path {
+            ex.items[sid.0 as usize] |= true;
+        }
+    }
+    (path.len() as u32, bp_len)
 }
 
 pub fn parse_path_seq_update_tables_multiple(
@@ -1147,15 +1394,12 @@ pub fn parse_path_seq_update_tables_multiple(
     item_table: &mut ItemTable,
     exclude_tables: Vec<&mut Option<ActiveTable>>,
     num_path: usize,
-) -> (u32, u32) {
-    let mut it = data.iter();
-    let end = it
-        .position(|x| x == &b'\t' || x == &b'\n' || x == &b'\r')
-        .unwrap();
+) -> Result<(u32, u32), String> {
+    let end = find_field_end(data).unwrap_or(data.len());
 
     log::debug!("parsing path sequences of size {} bytes..", end);
 
-    let (segment_ids, bp_len) = get_path_segment_ids(data, graph_storage, end, CHUNK_SIZE);
+    let (segment_ids, bp_len) = get_path_segment_ids(data, graph_storage, end, CHUNK_SIZE)?;
 
     segment_ids.into_iter().for_each(|segment_id| {
         item_table.items.push(segment_id.0);
@@ -1180,7 +1424,7 @@ pub fn parse_path_seq_update_tables_multiple(
     }
 
     log::debug!("..done");
-    (num_nodes_path as u32, bp_len)
+    Ok((num_nodes_path as u32, bp_len))
 }
 
 pub fn parse_path_seq_update_tables(
@@ -1189,44 +1433,17 @@ pub fn parse_path_seq_update_tables(
     item_table: &mut ItemTable,
     exclude_table: Option<&mut ActiveTable>,
     num_path: usize,
-) -> (u32, u32) {
-    let mut it = data.iter();
-    let end = it
-        .position(|x| x == &b'\t' || x == &b'\n' || x == &b'\r')
-        .unwrap();
+) -> Result<(u32, u32), String> {
+    let end = find_field_end(data).unwrap_or(data.len());
 
     log::debug!("parsing path sequences of size {} bytes..", end);
 
-    let items_ptr = Wrap(&mut item_table.items);
-    let id_prefsum_ptr = Wrap(&mut item_table.id_prefsum);
-
-    let mutex_item_table = Arc::new(Mutex::new(&mut item_table.items));
-
-    //let mut plus_strands: Vec<u32> = vec![0; rayon::current_num_threads()];
-    let bp_len = data[..end]
-        .par_split(|&x| x == b',')
-        .map(|node| {
-            let segment_id = graph_storage
-                .get_node_id(&node[0..node.len() - 1])
-                .unwrap_or_else(|| panic!("unknown node {}", str::from_utf8(node).unwrap()));
-            // TODO: Is orientation really necessary?
-            let orientation = node[node.len() - 1];
-            assert!(
-                orientation == b'-' || orientation == b'+',
-                "unknown orientation of segment {}",
-                str::from_utf8(node).unwrap()
-            );
-            //plus_strands[rayon::current_thread_index().unwrap()] += (orientation == b'+') as u32;
-
-            if let Ok(_) = mutex_item_table.lock() {
-                unsafe {
-                    (*items_ptr.0).push(segment_id.0);
-                    (&mut (*id_prefsum_ptr.0))[num_path + 1] += 1;
-                }
-            }
-            graph_storage.node_len(&segment_id)
-        })
-        .sum();
+    let (segment_ids, bp_len) = get_path_segment_ids(data, graph_storage, end, CHUNK_SIZE)?;
+
+    segment_ids.into_iter().for_each(|segment_id| {
+        item_table.items.push(segment_id.0);
+        item_table.id_prefsum[num_path + 1] += 1;
+    });
 
     // compute prefix sum
     let mut num_nodes_path = 0;
@@ -1244,7 +1461,86 @@ pub fn parse_path_seq_update_tables(
     }
 
     log::debug!("..done");
-    (num_nodes_path as u32, bp_len)
+    Ok((num_nodes_path as u32, bp_len))
+}
+
+/// Reads a segment (S) line into the buffer with its sequence replaced by `*`, such that the
+/// sequence is never held in memory, and returns the length of the sequence; lines with fewer than
+/// three columns and segments without sequence (`*`) are read as they are and yield none
+pub fn read_segment_line<R: BufRead>(
+    data: &mut R,
+    buf: &mut Vec<u8>,
+) -> Result<Option<usize>, Error> {
+    // record type and segment name
+    for _ in 0..2 {
+        if read_field(data, buf)? != Some(b'\t') {
+            return Ok(None);
+        }
+    }
+    let is_star = data.fill_buf()?.first() == Some(&b'*');
+    let (len, delimiter) = skip_field(data)?;
+    let res = if is_star && len == 1 { None } else { Some(len) };
+    buf.push(b'*');
+    if let Some(delimiter) = delimiter {
+        buf.push(delimiter);
+        if delimiter != b'\n' {
+            data.read_until(b'\n', buf)?;
+        }
+    }
+    Ok(res)
+}
+
+// appends the remainder of the current field, including its delimiter (tab or newline), to the
+// buffer and returns the delimiter, or none at the end of the data
+fn read_field<R: BufRead>(data: &mut R, buf: &mut Vec<u8>) -> Result<Option<u8>, Error> {
+    loop {
+        let (used, delimiter) = {
+            let available = data.fill_buf()?;
+            if available.is_empty() {
+                return Ok(None);
+            }
+            match memchr2(b'\t', b'\n', available) {
+                Some(i) => (i + 1, Some(available[i])),
+                None => (available.len(), None),
+            }
+        };
+        buf.extend_from_slice(&data.fill_buf()?[..used]);
+        data.consume(used);
+        if delimiter.is_some() {
+            return Ok(delimiter);
+        }
+    }
+}
+
+// skips the remainder of the current field without buffering it and returns its length and the
+// delimiter (tab, carriage return, or newline) that ends it, or none at the end of the data
+fn skip_field<R: BufRead>(data: &mut R) -> Result<(usize, Option<u8>), Error> {
+    let mut len = 0;
+    loop {
+        let (used, delimiter) = {
+            let available = data.fill_buf()?;
+            if available.is_empty() {
+                return Ok((len, None));
+            }
+            match memchr3(b'\t', b'\r', b'\n', available) {
+                Some(i) => (i + 1, Some(available[i])),
+                None => (available.len(), None),
+            }
+        };
+        data.consume(used);
+        match delimiter {
+            Some(_) => return Ok((len + used - 1, delimiter)),
+            None => len += used,
+        }
+    }
+}
+
+/// Value of the LN (segment length) tag among the tab-separated tags of a segment line
+pub fn parse_length_tag(tags: &[u8]) -> Option<usize> {
+    tags.split(|&c| c == b'\t' || c == b'\n' || c == b'\r')
+        .find_map(|tag| tag.strip_prefix(b"LN:i:"))
+        .and_then(|value| str::from_utf8(value).ok())
+        .and_then(|value| usize::from_str(value).ok())
 }
 
 #[cfg(test)]
@@ -1259,7 +1555,7 @@ mod tests {
             .position(|x| x == &b'\t' || x == &b'\n' || x == &b'\r')
             .unwrap();
         let graph_storage =
-            GraphStorage::from_gfa("tests/test_files/t_groups.gfa", true, CountType::Node);
+            GraphStorage::from_gfa("tests/test_files/t_groups.gfa", true, CountType::Node).unwrap();
         let exp = vec![
             ItemId(1),
             ItemId(3),
@@ -1273,11 +1569,24 @@ mod tests {
             ItemId(15),
         ];
         for i in 1..35 {
-            let (res, _) = get_path_segment_ids(data, &graph_storage, end, i);
+            let (res, _) = get_path_segment_ids(data, &graph_storage, end, i).unwrap();
             assert_eq!(res, exp);
         }
     }
 
+    #[test]
+    fn test_path_empty_step() {
+        let graph_storage =
+            GraphStorage::from_gfa("tests/test_files/t_groups.gfa", true, CountType::Node).unwrap();
+        for data in ["1+,,3+\t*", "1+,3+,\t*", ",1+\t*"] {
+            let end = data.find('\t').unwrap();
+            for i in 1..10 {
+                assert!(get_path_segment_ids(data.as_bytes(), &graph_storage, end, i).is_err());
+            }
+            assert!(parse_path_seq_to_item_vec(data.as_bytes(), &graph_storage).is_err());
+        }
+    }
+
     #[test]
     fn test_walk_chunk_sizes() {
         let data = ">1>3>5>6>8>9>11>12>14>15\n".as_bytes();
@@ -1286,7 +1595,7 @@ mod tests {
             .position(|x| x == &b'\t' || x == &b'\n' || x == &b'\r')
             .unwrap();
         let graph_storage =
-            GraphStorage::from_gfa("tests/test_files/t_groups.gfa", true, CountType::Node);
+            GraphStorage::from_gfa("tests/test_files/t_groups.gfa", true, CountType::Node).unwrap();
         let exp = vec![
             ItemId(1),
             ItemId(3),
@@ -1300,8 +1609,83 @@ mod tests {
             ItemId(15),
         ];
         for i in 1..35 {
-            let (res, _) = get_walk_segment_ids(data, &graph_storage, end, i);
+            let (res, _) = get_walk_segment_ids(data, &graph_storage, end, i).unwrap();
             assert_eq!(res, exp);
         }
     }
+
+    #[test]
+    fn test_update_tables_from_items_multiple() {
+        let data = "1+,3+,5+,6+,8+\t*".as_bytes();
+        let graph_storage =
+            GraphStorage::from_gfa("tests/test_files/t_groups.gfa", true, CountType::Node).unwrap();
+        let mut parsed = ItemTable::new(2);
+        let mut parsed_ex = Some(ActiveTable::new(graph_storage.node_lens.len(), false));
+        let parsed_res = parse_path_seq_update_tables_multiple(
+            data,
+            &graph_storage,
+            &mut parsed,
+            vec![&mut parsed_ex],
+            1,
+        )
+        .unwrap();
+
+        let items = PathSteps::Path(data).to_item_vec(&graph_storage).unwrap();
+        let mut shared = ItemTable::new(2);
+        let mut shared_ex = Some(ActiveTable::new(graph_storage.node_lens.len(), false));
+        let shared_res = update_tables_from_items_multiple(
+            &items,
+            &graph_storage,
+            &mut shared,
+            vec![&mut shared_ex],
+            1,
+        );
+        assert_eq!(shared_res, parsed_res);
+        assert_eq!(&shared.items[..], &parsed.items[..]);
+        assert_eq!(shared.id_prefsum, parsed.id_prefsum);
+        assert_eq!(shared_ex.unwrap().items, parsed_ex.unwrap().items);
+    }
+
+    #[test]
+    fn test_parse_walk_identifier() {
+        let (path_seg, steps) =
+            parse_walk_identifier(b"W\tHG00438\t1\tchr1\t1000\t1010\t>1>3\n").unwrap();
+        assert_eq!(path_seg.id(), "HG00438#1#chr1");
+        assert_eq!(path_seg.range(), (1000, 1010));
+        assert_eq!(steps, b">1>3\n");
+
+        // partial walks of chromosome-split graphs may leave their end open
+        let (path_seg, _) = parse_walk_identifier(b"W\tHG00438\t1\tchr1\t1000\t*\t>1>3\n").unwrap();
+        assert_eq!(path_seg.coords(), None);
+        assert_eq!(path_seg.range(), (1000, usize::MAX));
+
+        assert_eq!(
+            parse_walk_identifier(b"W\tHG00438\t1\tchr1\t1000\t10\t>1>3\n").unwrap_err(),
+            (
+                6,
+                "end 10 of walk is smaller than its start 1000".to_string()
+            )
+        );
+    }
+
+    #[test]
+    fn test_read_segment_line() {
+        let data = "S\t1\tACGTACGTAC\tLN:i:10\nS\t2\t*\tLN:i:7\r\nS\t3\tAC\nS\t4\n";
+        // a small buffer capacity forces fields to be read in several chunks
+        let mut data = BufReader::with_capacity(3, data.as_bytes());
+        let mut buf = Vec::new();
+        assert_eq!(read_segment_line(&mut data, &mut buf).unwrap(), Some(10));
+        assert_eq!(buf, b"S\t1\t*\tLN:i:10\n");
+        buf.clear();
+        assert_eq!(read_segment_line(&mut data, &mut buf).unwrap(), None);
+        assert_eq!(buf, b"S\t2\t*\tLN:i:7\r\n");
+        assert_eq!(parse_length_tag(&buf[6..]), Some(7));
+        buf.clear();
+        assert_eq!(read_segment_line(&mut data, &mut buf).unwrap(), Some(2));
+        assert_eq!(buf, b"S\t3\t*\n");
+        buf.clear();
+        assert_eq!(read_segment_line(&mut data, &mut buf).unwrap(), None);
+        assert_eq!(buf, b"S\t4\n");
+        assert_eq!(parse_length_tag(b"\tSN:Z:chr1\tLN:i:x"), None);
+    }
 }
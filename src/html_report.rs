@@ -1,20 +1,25 @@
 use base64::engine::general_purpose::STANDARD;
+use std::collections::{HashMap, HashSet};
 use std::ffi::OsStr;
 use std::fs::File;
-use std::io::{BufReader, Read};
+use std::io::{BufReader, Read, Write};
 use std::path::Path;
-use std::{collections::HashMap, str::from_utf8};
+use std::process::{Command, Stdio};
+use std::str::from_utf8;
 use std::{f64, fmt};
 
 use base64::{engine::general_purpose, Engine};
 use handlebars::{to_json, Handlebars, RenderError};
 
 use itertools::Itertools;
+use pulldown_cmark::{
+    html as md_html, Event as MdEvent, Options as MdOptions, Parser as MdParser, Tag as MdTag,
+};
 use serde::{Deserialize, Serialize};
 use time::{macros::format_description, OffsetDateTime};
 
 use crate::graph_broker::{GraphBroker, ItemId};
-use crate::util::{get_default_plot_downloads, to_id};
+use crate::util::{format_rss, get_default_plot_downloads, get_seed, to_id, StageUsage};
 use shadow_rs::shadow;
 
 shadow!(build);
@@ -35,6 +40,10 @@ pub const VEGA_EMBED: &[u8] = include_bytes!("../etc/vega-embed@6.29.0.min.js");
 pub const VEGA_LITE: &[u8] = include_bytes!("../etc/vega-lite@6.1.0.min.js");
 
 pub const REPORT_HBS: &[u8] = include_bytes!("../hbs/report.hbs");
+pub const ASSETS_HBS: &[u8] = include_bytes!("../hbs/assets.hbs");
+pub const DATA_SCRIPT_HBS: &[u8] = include_bytes!("../hbs/data_script.hbs");
+pub const PLOT_HBS: &[u8] = include_bytes!("../hbs/plot.hbs");
+pub const PRINT_HBS: &[u8] = include_bytes!("../hbs/print.hbs");
 pub const BAR_HBS: &[u8] = include_bytes!("../hbs/bar.hbs");
 pub const TREE_HBS: &[u8] = include_bytes!("../hbs/tree.hbs");
 pub const TABLE_HBS: &[u8] = include_bytes!("../hbs/table.hbs");
@@ -46,6 +55,81 @@ pub const LINE_HBS: &[u8] = include_bytes!("../hbs/line.hbs");
 pub const PNG_HBS: &[u8] = include_bytes!("../hbs/png.hbs");
 pub const SVG_HBS: &[u8] = include_bytes!("../hbs/svg.hbs");
 pub const PDF_HBS: &[u8] = include_bytes!("../hbs/pdf.hbs");
+pub const MARKDOWN_HBS: &[u8] = include_bytes!("../hbs/markdown.hbs");
+pub const HTML_HBS: &[u8] = include_bytes!("../hbs/html.hbs");
+pub const BOX_PLOT_HBS: &[u8] = include_bytes!("../hbs/box_plot.hbs");
+pub const SCATTER_HBS: &[u8] = include_bytes!("../hbs/scatter.hbs");
+pub const CHROMOSOMAL_HBS: &[u8] = include_bytes!("../hbs/chromosomal.hbs");
+
+// browsers that print pages to PDF without a display, tried in this order unless PANACUS_BROWSER
+// points to one
+const HEADLESS_BROWSERS: &[&str] = &[
+    "chromium",
+    "chromium-browser",
+    "google-chrome",
+    "google-chrome-stable",
+    "microsoft-edge",
+];
+
+fn get_timestamp() -> String {
+    OffsetDateTime::now_utc()
+        .format(&format_description!(
+            "[year]-[month]-[day]T[hour]:[minute]:[second]Z"
+        ))
+        .unwrap()
+}
+
+// prints the HTML report to the PDF file with a headless browser, which loads the report from a
+// temporary file; the file is removed also if printing fails
+fn write_pdf(report: &str, pdf_file: &str) -> anyhow::Result<()> {
+    let mut html_file = tempfile::Builder::new()
+        .prefix("panacus-")
+        .suffix(".html")
+        .tempfile()?;
+    html_file.write_all(report.as_bytes())?;
+    html_file.flush()?;
+    print_to_pdf(html_file.path(), pdf_file)?;
+    html_file.close()?;
+    Ok(())
+}
+
+fn print_to_pdf(html_file: &Path, pdf_file: &str) -> anyhow::Result<()> {
+    let browsers: Vec<String> = match std::env::var("PANACUS_BROWSER") {
+        Ok(browser) => vec![browser],
+        Err(_) => HEADLESS_BROWSERS.iter().map(|x| x.to_string()).collect(),
+    };
+    let url = format!("file://{}", std::fs::canonicalize(html_file)?.display());
+    for browser in &browsers {
+        log::info!("printing report to {} with {}..", pdf_file, browser);
+        // the virtual time budget lets the plots be rendered before the page is printed
+        let status = Command::new(browser)
+            .arg("--headless")
+            .arg("--disable-gpu")
+            .arg("--no-pdf-header-footer")
+            .arg("--virtual-time-budget=30000")
+            .arg(format!("--print-to-pdf={}", pdf_file))
+            .arg(&url)
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status();
+        match status {
+            Ok(status) if status.success() => return Ok(()),
+            Ok(status) => anyhow::bail!(
+                "{} failed to print the report to {} ({})",
+                browser,
+                pdf_file,
+                status
+            ),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+            Err(e) => anyhow::bail!("unable to run {}: {}", browser, e),
+        }
+    }
+    anyhow::bail!(
+        "no headless browser found to print the report to PDF (tried {}); install Chromium or set PANACUS_BROWSER to its path, or write the report with --print and print it from a browser",
+        browsers.join(", ")
+    )
+}
 
 fn combine_vars(mut a: JsVars, b: JsVars) -> JsVars {
     for (k, v) in b {
@@ -56,6 +140,79 @@ fn combine_vars(mut a: JsVars, b: JsVars) -> JsVars {
     a
 }
 
+/// Layout in which the HTML report is written
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HtmlOutput {
+    // single self-contained HTML file, written to the output stream
+    Report,
+    // directory holding index.html and separate asset and data files
+    Directory(String),
+    // directory holding one standalone HTML file per analysis section with plots
+    Plots(String),
+    // single self-contained HTML file showing all sections one after another, paginated for
+    // printing, written to the output stream
+    Print,
+    // PDF file printed from the paginated HTML file by a headless browser
+    Pdf(String),
+}
+
+impl HtmlOutput {
+    pub fn write<W: Write>(
+        &self,
+        sections: Vec<AnalysisSection>,
+        filename: &str,
+        config: &str,
+        theme: Option<&str>,
+        out: &mut W,
+    ) -> anyhow::Result<()> {
+        let mut registry = Handlebars::new();
+        match self {
+            Self::Report => {
+                let report = AnalysisSection::generate_report(
+                    sections,
+                    &mut registry,
+                    filename,
+                    config,
+                    theme,
+                )?;
+                writeln!(out, "{report}")?;
+            }
+            Self::Directory(dir) => AnalysisSection::generate_report_dir(
+                sections,
+                &mut registry,
+                filename,
+                config,
+                theme,
+                dir,
+            )?,
+            Self::Plots(dir) => {
+                AnalysisSection::generate_plot_files(sections, &mut registry, config, theme, dir)?
+            }
+            Self::Print => {
+                let report = AnalysisSection::generate_print_report(
+                    sections,
+                    &mut registry,
+                    filename,
+                    config,
+                    theme,
+                )?;
+                writeln!(out, "{report}")?;
+            }
+            Self::Pdf(file) => {
+                let report = AnalysisSection::generate_print_report(
+                    sections,
+                    &mut registry,
+                    filename,
+                    config,
+                    theme,
+                )?;
+                write_pdf(&report, file)?;
+            }
+        }
+        Ok(())
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug)]
 pub struct AnalysisSection {
     pub analysis: String,
@@ -66,6 +223,9 @@ pub struct AnalysisSection {
     pub id: String,
     pub table: Option<String>,
     pub plot_downloads: Vec<(String, String)>,
+    // free-text description of the section, shown above its plots
+    #[serde(default)]
+    pub description: Option<String>,
 }
 
 impl AnalysisSection {
@@ -116,6 +276,7 @@ impl AnalysisSection {
             ("run_name", to_json(&self.run_name)),
             ("run_id", to_json(&self.run_id)),
             ("countable", to_json(&self.countable)),
+            ("description", to_json(&self.description)),
             ("has_table", to_json(self.table.is_some())),
             ("has_graph", to_json(!self.plot_downloads.is_empty())),
             (
@@ -170,10 +331,9 @@ impl AnalysisSection {
                 let mut lines = buffer.lines();
                 let header = lines
                     .next()
-                    .expect(&format!(
-                        "{} file {} should contain at least one line",
-                        t, file
-                    ))
+                    .ok_or_else(|| {
+                        anyhow::anyhow!("{} file {} should contain at least one line", t, file)
+                    })?
                     .split(split_char)
                     .map(|x| x.trim().to_owned())
                     .collect();
@@ -194,7 +354,18 @@ impl AnalysisSection {
                 id: format!("pdf-{id}"),
                 file,
             },
-            _ => unimplemented!("Other formats have not been implemented yet"),
+            Some("md") | Some("markdown") => ReportItem::Markdown {
+                id: format!("md-{id}"),
+                file,
+            },
+            Some("html") | Some("htm") => ReportItem::Html {
+                id: format!("html-{id}"),
+                file,
+            },
+            _ => anyhow::bail!(
+                "format of custom file {} is not supported, expected one of: svg, png, json, csv, tsv, pdf, md, html",
+                file
+            ),
         };
         Ok(vec![AnalysisSection {
             id: id,
@@ -205,10 +376,153 @@ impl AnalysisSection {
             table,
             items: vec![report_item],
             plot_downloads,
+            description: None,
         }])
     }
 }
 
+#[derive(Debug, Default)]
+pub struct Provenance {
+    pub command_line: String,
+    pub config: String,
+    pub threads: usize,
+    pub seed: u64,
+    pub inputs: Vec<String>,
+    pub stages: Vec<StageUsage>,
+}
+
+impl Provenance {
+    pub fn new(config: &str) -> Self {
+        Self {
+            command_line: std::env::args().collect::<Vec<String>>().join(" "),
+            config: config.to_string(),
+            threads: rayon::current_num_threads(),
+            seed: get_seed(),
+            ..Default::default()
+        }
+    }
+
+    pub fn add_input(&mut self, file: &str) {
+        if !self.inputs.iter().any(|x| x == file) {
+            self.inputs.push(file.to_string());
+        }
+    }
+
+    pub fn add_stage(&mut self, usage: StageUsage) {
+        self.stages.push(usage);
+    }
+
+    // adds the inputs and stages of a part of the pipeline, e.g., of runs executed in parallel
+    pub fn merge(&mut self, other: Provenance) {
+        for file in other.inputs.iter() {
+            self.add_input(file);
+        }
+        self.stages.extend(other.stages);
+    }
+}
+
+impl AnalysisSection {
+    pub fn generate_provenance_section(provenance: &Provenance) -> anyhow::Result<Vec<Self>> {
+        let analysis = "Provenance".to_string();
+        let run_name = "Report".to_string();
+        let run_id = "provenance".to_string();
+
+        let environment = vec![
+            vec!["command line".to_string(), provenance.command_line.clone()],
+            vec![
+                "panacus version".to_string(),
+                build::PKG_VERSION.to_string(),
+            ],
+            vec!["panacus commit".to_string(), build::COMMIT_HASH.to_string()],
+            vec!["threads".to_string(), provenance.threads.to_string()],
+            vec!["seed".to_string(), provenance.seed.to_string()],
+            vec!["configuration".to_string(), provenance.config.clone()],
+        ];
+        let inputs = provenance
+            .inputs
+            .iter()
+            .map(|file| {
+                let (size, checksum) = crate::io::file_size_and_checksum(file)?;
+                Ok(vec![
+                    file.clone(),
+                    size.to_string(),
+                    format!("{:08x}", checksum),
+                ])
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        let runtimes: Vec<Vec<String>> = provenance
+            .stages
+            .iter()
+            .map(|usage| {
+                vec![
+                    usage.stage.clone(),
+                    format!("{:.3}", usage.seconds),
+                    format_rss(usage.peak_rss),
+                ]
+            })
+            .collect();
+
+        let to_tsv = |header: &[&str], values: &Vec<Vec<String>>| {
+            let mut res = header.join("\t");
+            for row in values {
+                res.push('\n');
+                res.push_str(&row.join("\t"));
+            }
+            format!("`{}`", res)
+        };
+        let input_header = ["file", "size (bytes)", "crc32"];
+        let runtime_header = ["task", "wall-clock time (s)", "peak RSS (MB)"];
+
+        Ok(vec![
+            AnalysisSection {
+                id: "provenance-environment".to_string(),
+                analysis: analysis.clone(),
+                run_name: run_name.clone(),
+                run_id: run_id.clone(),
+                countable: "Environment".to_string(),
+                table: None,
+                items: vec![ReportItem::Table {
+                    id: "provenance-environment-table".to_string(),
+                    header: vec!["property".to_string(), "value".to_string()],
+                    values: environment,
+                }],
+                plot_downloads: Vec::new(),
+                description: None,
+            },
+            AnalysisSection {
+                id: "provenance-inputs".to_string(),
+                analysis: analysis.clone(),
+                run_name: run_name.clone(),
+                run_id: run_id.clone(),
+                countable: "Input Files".to_string(),
+                table: Some(to_tsv(&input_header, &inputs)),
+                items: vec![ReportItem::Table {
+                    id: "provenance-inputs-table".to_string(),
+                    header: input_header.iter().map(|x| x.to_string()).collect(),
+                    values: inputs,
+                }],
+                plot_downloads: Vec::new(),
+                description: None,
+            },
+            AnalysisSection {
+                id: "provenance-runtimes".to_string(),
+                analysis,
+                run_name,
+                run_id,
+                countable: "Runtimes".to_string(),
+                table: Some(to_tsv(&runtime_header, &runtimes)),
+                items: vec![ReportItem::Table {
+                    id: "provenance-runtimes-table".to_string(),
+                    header: runtime_header.iter().map(|x| x.to_string()).collect(),
+                    values: runtimes,
+                }],
+                plot_downloads: Vec::new(),
+                description: None,
+            },
+        ])
+    }
+}
+
 fn get_extension_from_filename(filename: &str) -> Option<&str> {
     Path::new(filename).extension().and_then(OsStr::to_str)
 }
@@ -233,15 +547,165 @@ fn get_js_objects_string(objects: JsVars) -> String {
 }
 
 impl AnalysisSection {
+    // sections from different JSON files may share ids (e.g. when the same graph name was used
+    // in several runs); the ids of colliding files are therefore suffixed with the file index
+    pub fn merge_sections(reports: Vec<(String, Vec<Self>)>) -> Vec<Self> {
+        let mut seen_ids: HashSet<String> = HashSet::new();
+        let mut seen_run_ids: HashSet<String> = HashSet::new();
+        let mut merged = Vec::new();
+        for (i, (file_name, mut report)) in reports.into_iter().enumerate() {
+            let collides = report.iter().any(|section| {
+                seen_ids.contains(&section.id)
+                    || section.items.iter().any(|x| seen_ids.contains(&x.get_id()))
+            });
+            let run_collides = report.iter().any(|x| seen_run_ids.contains(&x.run_id));
+            if collides || run_collides {
+                log::info!(
+                    "sections of {} collide with previously loaded sections, renaming their ids",
+                    file_name
+                );
+                let suffix = format!("{}", i + 1);
+                for section in report.iter_mut() {
+                    section.add_id_suffix(&suffix);
+                    if run_collides {
+                        section.run_name = format!("{} ({})", section.run_name, file_name);
+                    }
+                }
+            }
+            for section in report.iter() {
+                seen_ids.insert(section.id.clone());
+                seen_ids.extend(section.items.iter().map(|x| x.get_id()));
+                seen_run_ids.insert(section.run_id.clone());
+            }
+            merged.extend(report);
+        }
+        merged
+    }
+
+    fn add_id_suffix(&mut self, suffix: &str) {
+        self.id = format!("{}-{}", self.id, suffix);
+        self.run_id = format!("{}-{}", self.run_id, suffix);
+        for item in self.items.iter_mut() {
+            item.add_id_suffix(suffix);
+        }
+    }
+
     pub fn generate_report(
         sections: Vec<Self>,
         registry: &mut Handlebars,
         filename: &str,
         config: &str,
+        theme: Option<&str>,
     ) -> Result<String, RenderError> {
-        if !registry.has_template("report") {
-            registry.register_template_string("report", from_utf8(REPORT_HBS).unwrap())?;
+        let vars = Self::get_report_variables(sections, registry, filename, config, theme)?;
+        registry.render("report", &vars)
+    }
+
+    /// Writes the report as directory holding index.html, the report data (data.js), and the
+    /// javascript and stylesheet assets as separate files, which browsers open more easily than
+    /// a single self-contained HTML file in the case of large reports
+    pub fn generate_report_dir(
+        sections: Vec<Self>,
+        registry: &mut Handlebars,
+        filename: &str,
+        config: &str,
+        theme: Option<&str>,
+        dir: &str,
+    ) -> anyhow::Result<()> {
+        let mut vars = Self::get_report_variables(sections, registry, filename, config, theme)?;
+        vars.insert("external", "true".to_string());
+        let dir = Path::new(dir);
+        let assets = dir.join("assets");
+        std::fs::create_dir_all(&assets)?;
+        for (name, content) in [
+            ("bootstrap.bundle.min.js", BOOTSTRAP_JS),
+            ("color-modes.min.js", BOOTSTRAP_COLOR_MODES_JS),
+            ("vega.min.js", VEGA),
+            ("vega-lite.min.js", VEGA_LITE),
+            ("vega-embed.min.js", VEGA_EMBED),
+            ("lib.js", CUSTOM_LIB_JS),
+            ("hook_after.js", HOOK_AFTER_JS),
+            ("bootstrap.min.css", BOOTSTRAP_CSS),
+            ("custom.css", CUSTOM_CSS),
+        ] {
+            std::fs::write(assets.join(name), content)?;
         }
+        std::fs::write(
+            dir.join("data.js"),
+            format!("const objects = {};\n", vars["data_hook"]),
+        )?;
+        std::fs::write(dir.join("index.html"), registry.render("report", &vars)?)?;
+        log::info!("wrote report to directory {}", dir.display());
+        Ok(())
+    }
+
+    /// Writes one standalone HTML file per analysis section, holding just its plots and their
+    /// data, e.g., for embedding them in lab notebooks
+    pub fn generate_plot_files(
+        sections: Vec<Self>,
+        registry: &mut Handlebars,
+        config: &str,
+        theme: Option<&str>,
+        dir: &str,
+    ) -> anyhow::Result<()> {
+        Self::register_page(registry, "plot", PLOT_HBS)?;
+        std::fs::create_dir_all(dir)?;
+        let mut file_names = HashSet::new();
+        for mut section in sections {
+            section.items.retain(|item| item.is_plot());
+            if section.items.is_empty() {
+                continue;
+            }
+            let mut file_name = section.id.clone();
+            let mut i = 1;
+            while !file_names.insert(file_name.clone()) {
+                i += 1;
+                file_name = format!("{}-{}", section.id, i);
+            }
+            let (content, js_objects) =
+                Self::generate_report_content(vec![section], registry, config)?;
+            let mut vars = Self::get_variables();
+            vars.insert("content", content);
+            vars.insert("data_hook", get_js_objects_string(js_objects));
+            vars.insert("fname", file_name.clone());
+            vars.insert("vega_theme", theme.unwrap_or("{}").to_string());
+            let path = Path::new(dir).join(format!("{}.html", file_name));
+            std::fs::write(&path, registry.render("plot", &vars)?)?;
+            log::info!("wrote plot {}", path.display());
+        }
+        Ok(())
+    }
+
+    /// Generates the report as single self-contained HTML file that shows all sections one after
+    /// another, each starting on a new page when printed, and renders plots as SVG, e.g., for
+    /// archiving the report or attaching it to publications
+    pub fn generate_print_report(
+        sections: Vec<Self>,
+        registry: &mut Handlebars,
+        filename: &str,
+        config: &str,
+        theme: Option<&str>,
+    ) -> Result<String, RenderError> {
+        Self::register_page(registry, "print", PRINT_HBS)?;
+        let (content, js_objects) = Self::generate_report_content(sections, registry, config)?;
+        let mut vars = Self::get_variables();
+        vars.insert("content", content);
+        vars.insert("data_hook", get_js_objects_string(js_objects));
+        vars.insert("fname", filename.to_string());
+        vars.insert("vega_theme", theme.unwrap_or("{}").to_string());
+        vars.insert("version", build::VERSION.to_string());
+        vars.insert("timestamp", get_timestamp());
+        registry.render("print", &vars)
+    }
+
+    fn get_report_variables(
+        sections: Vec<Self>,
+        registry: &mut Handlebars,
+        filename: &str,
+        config: &str,
+        theme: Option<&str>,
+    ) -> Result<HashMap<&'static str, String>, RenderError> {
+        Self::register_page(registry, "report", REPORT_HBS)?;
 
         let tree = Self::get_tree(&sections, registry)?;
 
@@ -251,7 +715,9 @@ impl AnalysisSection {
         vars.insert("data_hook", get_js_objects_string(js_objects));
         vars.insert("fname", filename.to_string());
         vars.insert("tree", tree);
-        registry.render("report", &vars)
+        // theme is a Vega config object that is passed to all vegaEmbed calls
+        vars.insert("vega_theme", theme.unwrap_or("{}").to_string());
+        Ok(vars)
     }
 
     fn get_tree(sections: &Vec<Self>, registry: &mut Handlebars) -> Result<String, RenderError> {
@@ -268,6 +734,7 @@ impl AnalysisSection {
                 .filter(|x| x.analysis == analysis_name)
                 .collect::<Vec<_>>();
             let mut runs = Vec::new();
+            let mut analysis_search = Vec::new();
             for (run_id, run_name) in run_ids {
                 let run_sections = analysis_sections
                     .iter()
@@ -277,31 +744,40 @@ impl AnalysisSection {
                     continue;
                 }
                 let mut countables = Vec::new();
+                let mut run_search = Vec::new();
                 for section in &run_sections {
+                    let search =
+                        Self::get_search_text(&[&analysis_name, &run_name, &section.countable]);
                     let content = HashMap::from([
                         ("title", to_json(&section.countable)),
                         ("id", to_json(to_id(&section.countable))),
                         ("href", to_json(&section.id)),
+                        ("search", to_json(&search)),
                     ]);
                     countables.push(to_json(content));
+                    run_search.push(search);
                 }
                 let run_id = run_sections
                     .first()
                     .expect("Run section has at least one run")
                     .run_id
                     .clone();
+                let run_search = run_search.join(" ");
                 let content = HashMap::from([
                     ("title", to_json(&run_name)),
                     ("id", to_json(to_id(&run_id))),
                     ("countables", to_json(countables)),
+                    ("search", to_json(&run_search)),
                 ]);
                 runs.push(to_json(content));
+                analysis_search.push(run_search);
             }
             let content = HashMap::from([
                 ("title", to_json(&analysis_name)),
                 ("id", to_json(to_id(&analysis_name))),
                 ("icon", to_json("icon-id")),
                 ("runs", to_json(runs)),
+                ("search", to_json(analysis_search.join(" "))),
             ]);
             analyses.push(to_json(content));
         }
@@ -313,16 +789,7 @@ impl AnalysisSection {
         let version_text = build::VERSION;
         // let version_text = format!("v{version}-{hash}");
         vars.insert("version", to_json(version_text));
-        let now = OffsetDateTime::now_utc();
-        vars.insert(
-            "timestamp",
-            to_json(
-                now.format(&format_description!(
-                    "[year]-[month]-[day]T[hour]:[minute]:[second]Z"
-                ))
-                .unwrap(),
-            ),
-        );
+        vars.insert("timestamp", to_json(get_timestamp()));
         if !registry.has_template("tree") {
             registry.register_template_string("tree", from_utf8(TREE_HBS).unwrap())?;
         }
@@ -330,6 +797,31 @@ impl AnalysisSection {
         Ok(tree)
     }
 
+    // the navigation tree is filtered client-side by matching the query against these
+    // lower-cased strings, which contain the names of the node itself and all its ancestors
+    fn get_search_text(names: &[&str]) -> String {
+        names
+            .iter()
+            .map(|x| x.to_lowercase())
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    // registers the template of a page along with the partials shared by all pages, which embed
+    // the assets and the data of the plots
+    fn register_page(
+        registry: &mut Handlebars,
+        name: &str,
+        template: &[u8],
+    ) -> Result<(), RenderError> {
+        if !registry.has_template(name) {
+            registry.register_partial("assets", from_utf8(ASSETS_HBS).unwrap())?;
+            registry.register_partial("data_script", from_utf8(DATA_SCRIPT_HBS).unwrap())?;
+            registry.register_template_string(name, from_utf8(template).unwrap())?;
+        }
+        Ok(())
+    }
+
     fn get_variables() -> HashMap<&'static str, String> {
         let mut vars = HashMap::new();
         vars.insert(
@@ -407,6 +899,33 @@ impl AnalysisSection {
     }
 }
 
+/// Phenotype of each label of a plot, by which the labels are colored in the report
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Phenotypes {
+    pub column: String,
+    pub values: Vec<String>,
+}
+
+impl Phenotypes {
+    fn to_js(phenotypes: &Option<Self>, labels: &[String]) -> String {
+        match phenotypes {
+            Some(p) => {
+                let values: Vec<HashMap<&str, &String>> = labels
+                    .iter()
+                    .zip(&p.values)
+                    .map(|(label, value)| HashMap::from([("label", label), ("value", value)]))
+                    .collect();
+                to_json(HashMap::from([
+                    ("column", to_json(&p.column)),
+                    ("values", to_json(values)),
+                ]))
+                .to_string()
+            }
+            None => "null".to_string(),
+        }
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug)]
 pub enum ReportItem {
     Bar {
@@ -426,6 +945,11 @@ pub enum ReportItem {
         labels: Vec<String>,
         values: Vec<Vec<f64>>,
         log_toggle: bool,
+        phenotypes: Option<Phenotypes>,
+        // additional value of each bar shown when hovering over it, given by its name and, like
+        // the values, one row per name
+        #[serde(default)]
+        hover: Option<(String, Vec<Vec<f64>>)>,
     },
     Table {
         id: String,
@@ -442,6 +966,7 @@ pub enum ReportItem {
         x_labels: Vec<String>,
         y_labels: Vec<String>,
         values: Vec<Vec<f32>>,
+        phenotypes: Option<Phenotypes>,
     },
     Line {
         id: String,
@@ -453,6 +978,49 @@ pub enum ReportItem {
         log_x: bool,
         log_y: bool,
     },
+    BoxPlot {
+        id: String,
+        name: String,
+        x_label: String,
+        y_label: String,
+        labels: Vec<String>,
+        values: Vec<Vec<f64>>,
+        violin: bool,
+    },
+    Scatter {
+        id: String,
+        name: String,
+        x_label: String,
+        y_label: String,
+        x_values: Vec<f64>,
+        y_values: Vec<f64>,
+        // points of a fitted curve that is drawn on top of the scatter plot
+        fit: Option<Vec<(f64, f64)>>,
+        // points (x, y, lower, upper) of a prediction beyond the data that is drawn as a dashed
+        // line within its interval
+        prediction: Option<Vec<(f64, f64, f64, f64)>>,
+        annotation: Option<String>,
+        log_x: bool,
+        log_y: bool,
+    },
+    // values along the coordinates of a sequence, each given for an interval [start, end)
+    Chromosomal {
+        id: String,
+        name: String,
+        sequence: String,
+        y_label: String,
+        starts: Vec<usize>,
+        ends: Vec<usize>,
+        values: Vec<f64>,
+        // further tracks (name, values) over the same intervals, e.g., one per group, stacked
+        // under the values
+        #[serde(default)]
+        tracks: Vec<(String, Vec<f64>)>,
+        // features (start, end, name), e.g., genes or centromeres, drawn as annotation track
+        // under the values
+        #[serde(default)]
+        annotations: Vec<(usize, usize, String)>,
+    },
     Png {
         id: String,
         file: String,
@@ -469,6 +1037,14 @@ pub enum ReportItem {
         id: String,
         file: String,
     },
+    Markdown {
+        id: String,
+        file: String,
+    },
+    Html {
+        id: String,
+        file: String,
+    },
 }
 
 impl ReportItem {
@@ -480,13 +1056,39 @@ impl ReportItem {
             Self::Heatmap { id, .. } => id.to_string(),
             Self::Hexbin { id, .. } => id.to_string(),
             Self::Line { id, .. } => id.to_string(),
+            Self::BoxPlot { id, .. } => id.to_string(),
+            Self::Scatter { id, .. } => id.to_string(),
+            Self::Chromosomal { id, .. } => id.to_string(),
             Self::Png { id, .. } => id.to_string(),
             Self::Svg { id, .. } => id.to_string(),
             Self::Json { id, .. } => id.to_string(),
             Self::Pdf { id, .. } => id.to_string(),
+            Self::Markdown { id, .. } => id.to_string(),
+            Self::Html { id, .. } => id.to_string(),
         }
     }
 
+    fn add_id_suffix(&mut self, suffix: &str) {
+        let id = match self {
+            Self::Bar { id, .. } => id,
+            Self::MultiBar { id, .. } => id,
+            Self::Table { id, .. } => id,
+            Self::Heatmap { id, .. } => id,
+            Self::Hexbin { id, .. } => id,
+            Self::Line { id, .. } => id,
+            Self::BoxPlot { id, .. } => id,
+            Self::Scatter { id, .. } => id,
+            Self::Chromosomal { id, .. } => id,
+            Self::Png { id, .. } => id,
+            Self::Svg { id, .. } => id,
+            Self::Json { id, .. } => id,
+            Self::Pdf { id, .. } => id,
+            Self::Markdown { id, .. } => id,
+            Self::Html { id, .. } => id,
+        };
+        *id = format!("{}-{}", id, suffix);
+    }
+
     fn get_name(&self) -> String {
         match self {
             Self::Bar { name, .. } => name.to_string(),
@@ -495,13 +1097,34 @@ impl ReportItem {
             Self::Heatmap { name, .. } => name.to_string(),
             Self::Hexbin { .. } => "Hexbin".to_string(),
             Self::Line { name, .. } => name.to_string(),
+            Self::BoxPlot { name, .. } => name.to_string(),
+            Self::Scatter { name, .. } => name.to_string(),
+            Self::Chromosomal { name, .. } => name.to_string(),
             Self::Png { .. } => "Png".to_string(),
             Self::Svg { .. } => "Svg".to_string(),
             Self::Json { .. } => "Json".to_string(),
             Self::Pdf { .. } => "Pdf".to_string(),
+            Self::Markdown { .. } => "Markdown".to_string(),
+            Self::Html { .. } => "Html".to_string(),
         }
     }
 
+    // whether the item is rendered as Vega plot
+    fn is_plot(&self) -> bool {
+        matches!(
+            self,
+            Self::Bar { .. }
+                | Self::MultiBar { .. }
+                | Self::Heatmap { .. }
+                | Self::Hexbin { .. }
+                | Self::Line { .. }
+                | Self::BoxPlot { .. }
+                | Self::Scatter { .. }
+                | Self::Chromosomal { .. }
+                | Self::Json { .. }
+        )
+    }
+
     fn into_html(self, registry: &mut Handlebars) -> RenderedHTML {
         match self {
             Self::Table { id, header, values } => {
@@ -524,6 +1147,7 @@ impl ReportItem {
                 x_labels,
                 y_labels,
                 values,
+                phenotypes,
             } => {
                 if !registry.has_template("heatmap") {
                     registry
@@ -539,7 +1163,13 @@ impl ReportItem {
                     }
                 }
                 data_set.push_str("]}");
-                let js_object = format!("new Heatmap('{}', '{}', {})", id, name, data_set,);
+                let js_object = format!(
+                    "new Heatmap('{}', '{}', {}, {})",
+                    id,
+                    name,
+                    data_set,
+                    Phenotypes::to_js(&phenotypes, &x_labels)
+                );
                 let max_scale = format!(
                     "{:.2}",
                     values
@@ -607,23 +1237,36 @@ impl ReportItem {
                 labels,
                 values,
                 log_toggle,
+                phenotypes,
+                hover,
             } => {
                 if !registry.has_template("bar") {
                     registry.register_template_string("bar", from_utf8(BAR_HBS).unwrap())?;
                 }
+                let phenotypes_text = Phenotypes::to_js(&phenotypes, &labels);
                 let data_text = (0..labels.len())
                     .cartesian_product(0..names.len())
                     .map(|(l, n)| {
+                        let hover_text = match &hover {
+                            Some((name, hover_values)) => {
+                                format!(", '{}': {}", name, hover_values[n][l])
+                            }
+                            None => String::new(),
+                        };
                         format!(
-                            "{{'label': '{}', 'name': '{}', 'value': {}}}",
-                            labels[l], names[n], values[n][l]
+                            "{{'label': '{}', 'name': '{}', 'value': {}{}}}",
+                            labels[l], names[n], values[n][l], hover_text
                         )
                     })
                     .join(",");
                 let data_text = format!("{{'values': [{}]}}", data_text);
+                let hover_text = match &hover {
+                    Some((name, _)) => format!("'{}'", name),
+                    None => "null".to_string(),
+                };
                 let js_object = format!(
-                    "new MultiBar('{}', '{}', '{}', {}, {})",
-                    id, x_label, y_label, log_toggle, data_text
+                    "new MultiBar('{}', '{}', '{}', {}, {}, {}, {})",
+                    id, x_label, y_label, log_toggle, data_text, phenotypes_text, hover_text
                 );
                 let data = HashMap::from([
                     ("id".to_string(), to_json(&id)),
@@ -705,6 +1348,187 @@ impl ReportItem {
                     )]),
                 ))
             }
+            Self::BoxPlot {
+                id,
+                name,
+                x_label,
+                y_label,
+                labels,
+                values,
+                violin,
+            } => {
+                if !registry.has_template("box_plot") {
+                    registry
+                        .register_template_string("box_plot", from_utf8(BOX_PLOT_HBS).unwrap())?;
+                }
+
+                let mut data_text = "{'values': [".to_string();
+                for (label, vals) in labels.iter().zip(values.iter()) {
+                    for v in vals {
+                        data_text
+                            .push_str(&format!("{{ 'label': '{}', 'value': {} }}, ", label, v));
+                    }
+                }
+                data_text.push_str("]}");
+                let js_object = format!(
+                    "new BoxPlot('{}', '{}', '{}', '{}', {}, {})",
+                    id, name, x_label, y_label, violin, data_text
+                );
+
+                let data = HashMap::from([("id".to_string(), to_json(&id))]);
+                Ok((
+                    registry.render("box_plot", &data)?,
+                    HashMap::from([(
+                        "datasets".to_string(),
+                        HashMap::from([(id.clone(), js_object)]),
+                    )]),
+                ))
+            }
+            Self::Scatter {
+                id,
+                name,
+                x_label,
+                y_label,
+                x_values,
+                y_values,
+                fit,
+                prediction,
+                annotation,
+                log_x,
+                log_y,
+            } => {
+                if !registry.has_template("scatter") {
+                    registry
+                        .register_template_string("scatter", from_utf8(SCATTER_HBS).unwrap())?;
+                }
+
+                let points_to_text = |points: &mut dyn Iterator<Item = (f64, f64)>| {
+                    let mut text = "{'values': [".to_string();
+                    for (x, y) in points {
+                        text.push_str(&format!("{{ 'x': {}, 'y': {} }}, ", x, y));
+                    }
+                    text.push_str("]}");
+                    text
+                };
+                let data_text = points_to_text(&mut x_values.into_iter().zip(y_values));
+                let fit_text = match fit {
+                    Some(fit) => points_to_text(&mut fit.into_iter()),
+                    None => "null".to_string(),
+                };
+                let prediction_text = match prediction {
+                    Some(prediction) => {
+                        let mut text = "{'values': [".to_string();
+                        for (x, y, lower, upper) in prediction {
+                            text.push_str(&format!(
+                                "{{ 'x': {}, 'y': {}, 'lower': {}, 'upper': {} }}, ",
+                                x, y, lower, upper
+                            ));
+                        }
+                        text.push_str("]}");
+                        text
+                    }
+                    None => "null".to_string(),
+                };
+                let js_object = format!(
+                    "new Scatter('{}', '{}', '{}', '{}', {}, {}, {}, {}, {}, {})",
+                    id,
+                    name,
+                    x_label,
+                    y_label,
+                    log_x,
+                    log_y,
+                    data_text,
+                    fit_text,
+                    prediction_text,
+                    to_json(&annotation),
+                );
+
+                let data = HashMap::from([("id".to_string(), to_json(&id))]);
+                Ok((
+                    registry.render("scatter", &data)?,
+                    HashMap::from([(
+                        "datasets".to_string(),
+                        HashMap::from([(id.clone(), js_object)]),
+                    )]),
+                ))
+            }
+            Self::Chromosomal {
+                id,
+                name,
+                sequence,
+                y_label,
+                starts,
+                ends,
+                values,
+                tracks,
+                annotations,
+            } => {
+                if !registry.has_template("chromosomal") {
+                    registry.register_template_string(
+                        "chromosomal",
+                        from_utf8(CHROMOSOMAL_HBS).unwrap(),
+                    )?;
+                }
+
+                let mut data_text = "{'values': [".to_string();
+                for ((start, end), value) in starts.iter().zip(ends.iter()).zip(values.iter()) {
+                    data_text.push_str(&format!(
+                        "{{ 'start': {}, 'end': {}, 'value': {} }}, ",
+                        start, end, value
+                    ));
+                }
+                data_text.push_str("]}");
+                let tracks: Vec<HashMap<&str, handlebars::JsonValue>> = tracks
+                    .iter()
+                    .map(|(track_name, track_values)| {
+                        let track_data: Vec<HashMap<&str, handlebars::JsonValue>> = starts
+                            .iter()
+                            .zip(&ends)
+                            .zip(track_values)
+                            .map(|((start, end), value)| {
+                                HashMap::from([
+                                    ("start", to_json(start)),
+                                    ("end", to_json(end)),
+                                    ("value", to_json(value)),
+                                ])
+                            })
+                            .collect();
+                        HashMap::from([
+                            ("name", to_json(track_name)),
+                            ("data", to_json(HashMap::from([("values", track_data)]))),
+                        ])
+                    })
+                    .collect();
+                let annotations: Vec<HashMap<&str, handlebars::JsonValue>> = annotations
+                    .iter()
+                    .map(|(start, end, name)| {
+                        HashMap::from([
+                            ("start", to_json(start)),
+                            ("end", to_json(end)),
+                            ("name", to_json(name)),
+                        ])
+                    })
+                    .collect();
+                let js_object = format!(
+                    "new Chromosomal('{}', '{}', '{}', '{}', {}, {}, {})",
+                    id,
+                    name,
+                    sequence,
+                    y_label,
+                    data_text,
+                    to_json(HashMap::from([("values", annotations)])),
+                    to_json(tracks)
+                );
+
+                let data = HashMap::from([("id".to_string(), to_json(&id))]);
+                Ok((
+                    registry.render("chromosomal", &data)?,
+                    HashMap::from([(
+                        "datasets".to_string(),
+                        HashMap::from([(id.clone(), js_object)]),
+                    )]),
+                ))
+            }
             Self::Png { id, file } => {
                 if !registry.has_template("png") {
                     registry.register_template_string("png", from_utf8(PNG_HBS).unwrap())?;
@@ -779,6 +1603,76 @@ impl ReportItem {
                     HashMap::from([("datasets".to_string(), HashMap::new())]),
                 ))
             }
+            Self::Markdown { id, file } => {
+                if !registry.has_template("markdown") {
+                    registry
+                        .register_template_string("markdown", from_utf8(MARKDOWN_HBS).unwrap())?;
+                }
+                let f = File::open(&file)?;
+                let mut reader = BufReader::new(f);
+                let mut buffer = String::new();
+                reader.read_to_string(&mut buffer)?;
+                let content = markdown_to_html(&buffer, &file);
+                let data = HashMap::from([("content", &content), ("id", &id)]);
+                Ok((
+                    registry.render("markdown", &data)?,
+                    HashMap::from([("datasets".to_string(), HashMap::new())]),
+                ))
+            }
+            Self::Html { id, file } => {
+                if !registry.has_template("html") {
+                    registry.register_template_string("html", from_utf8(HTML_HBS).unwrap())?;
+                }
+                let f = File::open(file)?;
+                let mut reader = BufReader::new(f);
+                let mut buffer = String::new();
+                reader.read_to_string(&mut buffer)?;
+                // content is placed (escaped) in the srcdoc of a sandboxed iframe, so that
+                // foreign scripts and styles cannot interfere with the report
+                let data = HashMap::from([("content", &buffer), ("id", &id)]);
+                Ok((
+                    registry.render("html", &data)?,
+                    HashMap::from([("datasets".to_string(), HashMap::new())]),
+                ))
+            }
+        }
+    }
+}
+
+fn markdown_to_html(text: &str, file: &str) -> String {
+    let options = MdOptions::ENABLE_TABLES | MdOptions::ENABLE_STRIKETHROUGH;
+    let base_dir = Path::new(file).parent().unwrap_or_else(|| Path::new(""));
+    // images referenced by relative paths are embedded, otherwise they would be missing from
+    // the self-contained report
+    let parser = MdParser::new_ext(text, options).map(|event| match event {
+        MdEvent::Start(MdTag::Image(link_type, dest, title)) => {
+            let dest = embed_local_image(&dest, base_dir).map_or(dest, |x| x.into());
+            MdEvent::Start(MdTag::Image(link_type, dest, title))
+        }
+        _ => event,
+    });
+    let mut res = String::new();
+    md_html::push_html(&mut res, parser);
+    res
+}
+
+fn embed_local_image(dest: &str, base_dir: &Path) -> Option<String> {
+    if dest.contains("://") || dest.starts_with("data:") {
+        return None;
+    }
+    let path = base_dir.join(dest);
+    let mime = match get_extension_from_filename(dest)?.to_lowercase().as_str() {
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "svg" => "image/svg+xml",
+        _ => return None,
+    };
+    match std::fs::read(&path) {
+        Ok(content) => Some(format!("data:{};base64,{}", mime, STANDARD.encode(content))),
+        Err(e) => {
+            log::warn!("unable to embed image {}: {}", path.display(), e);
+            None
         }
     }
 }
@@ -864,6 +1758,12 @@ impl Bin {
         for bin in &mut bins {
             bin.size = bin.content.len() as u64;
         }
+        // fixed order, such that bins can be referred to by their index
+        bins.sort_by(|a, b| {
+            (a.x, a.y)
+                .partial_cmp(&(b.x, b.y))
+                .unwrap_or(std::cmp::Ordering::Equal)
+        });
         bins
     }
 
@@ -871,3 +1771,211 @@ impl Bin {
         (((x1 - x2).powf(2.0) + (y1 - y2).powf(2.0)) as f64).sqrt()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn get_section(id: &str, run_id: &str) -> AnalysisSection {
+        AnalysisSection {
+            analysis: "Coverage Histogram".to_string(),
+            run_name: "graph".to_string(),
+            run_id: run_id.to_string(),
+            countable: "Node".to_string(),
+            items: vec![ReportItem::Table {
+                id: format!("table-{id}"),
+                header: Vec::new(),
+                values: Vec::new(),
+            }],
+            id: id.to_string(),
+            table: None,
+            plot_downloads: Vec::new(),
+            description: None,
+        }
+    }
+
+    #[test]
+    fn test_merge_sections_renames_colliding_ids() {
+        let reports = vec![
+            (
+                "a.json".to_string(),
+                vec![get_section("hist-graph", "graph")],
+            ),
+            (
+                "b.json".to_string(),
+                vec![get_section("hist-graph", "graph")],
+            ),
+        ];
+        let merged = AnalysisSection::merge_sections(reports);
+        assert_eq!(merged[0].id, "hist-graph");
+        assert_eq!(merged[1].id, "hist-graph-2");
+        assert_eq!(merged[1].run_id, "graph-2");
+        assert_eq!(merged[1].run_name, "graph (b.json)");
+        assert_eq!(merged[1].items[0].get_id(), "table-hist-graph-2");
+    }
+
+    #[test]
+    fn test_merge_sections_keeps_distinct_ids() {
+        let reports = vec![
+            ("a.json".to_string(), vec![get_section("hist-a", "a")]),
+            ("b.json".to_string(), vec![get_section("hist-b", "b")]),
+        ];
+        let merged = AnalysisSection::merge_sections(reports);
+        assert_eq!(merged[0].id, "hist-a");
+        assert_eq!(merged[1].id, "hist-b");
+        assert_eq!(merged[1].run_id, "b");
+    }
+
+    fn get_plot_section(id: &str) -> AnalysisSection {
+        let mut section = get_section(id, "graph");
+        section.items = vec![ReportItem::Bar {
+            id: format!("bar-{id}"),
+            name: "graph".to_string(),
+            x_label: "x".to_string(),
+            y_label: "y".to_string(),
+            labels: vec!["1".to_string()],
+            values: vec![1.0],
+            log_toggle: false,
+        }];
+        section
+    }
+
+    #[test]
+    fn test_generate_report_pages() {
+        let mut registry = Handlebars::new();
+        let report = AnalysisSection::generate_report(
+            vec![get_plot_section("hist-graph")],
+            &mut registry,
+            "graph.gfa",
+            "",
+            None,
+        )
+        .unwrap();
+        // single file embeds assets and data, and names the page after the file it is opened from
+        assert!(report.contains(from_utf8(CUSTOM_LIB_JS).unwrap()));
+        assert!(report.contains(from_utf8(HOOK_AFTER_JS).unwrap()));
+        assert!(report.contains("const objects = {"));
+        assert!(report.contains("document.title = page;"));
+        assert!(!report.contains("data.js"));
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = |name: &str| dir.path().join(name).to_str().unwrap().to_string();
+        AnalysisSection::generate_report_dir(
+            vec![get_plot_section("hist-graph")],
+            &mut registry,
+            "graph.gfa",
+            "",
+            None,
+            &path("report"),
+        )
+        .unwrap();
+        let index = std::fs::read_to_string(path("report/index.html")).unwrap();
+        assert!(index.contains(r#"<script src="data.js"></script>"#));
+        assert!(index.contains(r#"<script src="assets/hook_after.js"></script>"#));
+        assert!(!index.contains("const objects"));
+        assert!(!index.contains(from_utf8(CUSTOM_LIB_JS).unwrap()));
+        let data = std::fs::read_to_string(path("report/data.js")).unwrap();
+        assert!(data.starts_with("const objects = {"));
+
+        AnalysisSection::generate_plot_files(
+            vec![
+                get_plot_section("hist-graph"),
+                get_section("table", "graph"),
+            ],
+            &mut registry,
+            "",
+            None,
+            &path("plots"),
+        )
+        .unwrap();
+        // sections without plots are left out
+        assert!(!dir.path().join("plots/table.html").exists());
+        let plot = std::fs::read_to_string(path("plots/hist-graph.html")).unwrap();
+        assert!(plot.contains(from_utf8(HOOK_AFTER_JS).unwrap()));
+        assert!(plot.contains("const objects = {"));
+        assert!(plot.contains("classList.add('show', 'active')"));
+        assert!(!plot.contains("document.title = page;"));
+    }
+
+    #[test]
+    fn test_generate_print_report() {
+        let mut registry = Handlebars::new();
+        let report = AnalysisSection::generate_print_report(
+            vec![
+                get_plot_section("hist-graph"),
+                get_section("table", "graph"),
+            ],
+            &mut registry,
+            "graph.gfa",
+            "",
+            None,
+        )
+        .unwrap();
+        assert!(report.contains(r#"<body class="print-report">"#));
+        assert!(report.contains(&format!("using panacus {}", build::VERSION)));
+        // all sections are shown, and the page is not switched to the color mode of the browser
+        assert!(report.contains(r#"id="nav-hist-graph""#));
+        assert!(report.contains(r#"id="nav-table""#));
+        assert!(report.contains("classList.add('show', 'active')"));
+        assert!(report.contains(from_utf8(HOOK_AFTER_JS).unwrap()));
+        assert!(report.contains("const objects = {"));
+        assert!(!report.contains(from_utf8(BOOTSTRAP_COLOR_MODES_JS).unwrap()));
+    }
+
+    #[test]
+    fn test_markdown_item() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = |name: &str| dir.path().join(name).to_str().unwrap().to_string();
+        std::fs::write(path("logo.png"), b"png").unwrap();
+        std::fs::write(
+            path("notes.md"),
+            "# Notes\n\n| a | b |\n|---|---|\n| 1 | ~~2~~ |\n\n![logo](logo.png) ![remote](https://example.com/x.png)\n",
+        )
+        .unwrap();
+        let item = ReportItem::Markdown {
+            id: "md-notes".to_string(),
+            file: path("notes.md"),
+        };
+        let (html, _) = item.into_html(&mut Handlebars::new()).unwrap();
+        assert!(html.starts_with(r#"<div id="md-notes" class="markdown-content">"#));
+        assert!(html.contains("<h1>Notes</h1>"));
+        assert!(html.contains("<table>"));
+        assert!(html.contains("<del>2</del>"));
+        // local images are embedded, remote ones are kept as links
+        assert!(html.contains(&format!(
+            r#"src="data:image/png;base64,{}""#,
+            STANDARD.encode(b"png")
+        )));
+        assert!(html.contains(r#"src="https://example.com/x.png""#));
+    }
+
+    #[test]
+    fn test_html_item() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir
+            .path()
+            .join("snippet.html")
+            .to_str()
+            .unwrap()
+            .to_string();
+        std::fs::write(
+            &file,
+            "<style>body { color: red; }</style><p class=\"x\">a & b</p><script>alert('x')</script>",
+        )
+        .unwrap();
+        let item = ReportItem::Html {
+            id: "html-snippet".to_string(),
+            file,
+        };
+        let (html, _) = item.into_html(&mut Handlebars::new()).unwrap();
+        // the snippet is shown in a sandboxed frame, escaped such that it cannot leave the srcdoc
+        assert!(
+            html.starts_with(r#"<iframe id="html-snippet" sandbox="allow-scripts allow-popups""#)
+        );
+        assert!(html.contains(
+            "srcdoc=\"&lt;style&gt;body { color: red; }&lt;/style&gt;&lt;p class&#x3D;&quot;x&quot;&gt;a &amp; b&lt;/p&gt;&lt;script&gt;"
+        ));
+        assert!(!html.contains("<script>"));
+        assert!(!html.contains("<style>"));
+    }
+}
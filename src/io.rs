@@ -1,10 +1,14 @@
 /* standard use */
-use std::io::{BufRead, BufReader, Read};
+use std::collections::{HashMap, HashSet};
+use std::io::{BufRead, BufReader, Cursor, Read};
 use std::io::{Error, ErrorKind};
 use std::str::{self, FromStr};
+use std::sync::{Arc, Mutex};
 
 /* external use */
 use flate2::read::MultiGzDecoder;
+use memmap2::Mmap;
+use once_cell::sync::Lazy;
 use quick_csv::Csv;
 use rayon::prelude::*;
 
@@ -12,30 +16,420 @@ use rayon::prelude::*;
 use crate::graph_broker::{AbacusByGroup, PathSegment, ThresholdContainer};
 use crate::util::*;
 
-pub fn bufreader_from_compressed_gfa(gfa_file: &str) -> BufReader<Box<dyn Read>> {
+mod bigwig;
+mod convert;
+mod gfa2;
+mod newick;
+mod xlsx;
+pub use bigwig::write_bigwig;
+use convert::find_converter;
+use gfa2::{is_gfa2, Gfa2Reader};
+pub use newick::{is_newick_file, parse_newick_leaves, parse_newick_tree, NewickNode, NewickTree};
+pub use xlsx::{set_xlsx_file, write_xlsx_tables};
+
+const MMAP_READ_BUFFER_SIZE: usize = 1 << 20;
+
+// several GFA files (e.g., one per chromosome) can be given as a comma-separated list, they are
+// then read as one pangenome graph
+pub const GRAPH_FILE_SEPARATOR: char = ',';
+
+// files whose content is held in memory rather than on disk, for environments without file system
+// (e.g., the browser); they take precedence over files of the same name on disk
+static IN_MEMORY_FILES: Lazy<Mutex<HashMap<String, Arc<[u8]>>>> = Lazy::new(Default::default);
+
+// maximum number of problems that are listed when reporting malformed input
+const MAX_REPORTED_PROBLEMS: usize = 20;
+
+/// Problem in a user-provided input file; line and column numbers are 1-based, column 0 refers
+/// to the whole line
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq)]
+#[error("{file}:{line}:{column}: {msg}")]
+pub struct InputError {
+    pub file: String,
+    pub line: usize,
+    pub column: usize,
+    pub msg: String,
+}
+
+/// Collects all problems found while reading an input file, such that they can be reported
+/// together rather than one by one
+#[derive(Debug, Clone)]
+pub struct InputProblems {
+    file: String,
+    problems: Vec<InputError>,
+}
+
+impl InputProblems {
+    pub fn new(file: &str) -> Self {
+        Self {
+            file: file.to_string(),
+            problems: Vec::new(),
+        }
+    }
+
+    pub fn push<S: Into<String>>(&mut self, line: usize, column: usize, msg: S) {
+        self.problems.push(InputError {
+            file: self.file.clone(),
+            line,
+            column,
+            msg: msg.into(),
+        });
+    }
+
+    pub fn into_result(self) -> Result<(), Error> {
+        if self.problems.is_empty() {
+            return Ok(());
+        }
+        for p in self.problems.iter().take(MAX_REPORTED_PROBLEMS) {
+            log::error!("{}", p);
+        }
+        let mut msg = format!("found {} problem(s) in {}:", self.problems.len(), self.file);
+        for p in self.problems.iter().take(MAX_REPORTED_PROBLEMS) {
+            msg.push_str(&format!("\n  {}", p));
+        }
+        if self.problems.len() > MAX_REPORTED_PROBLEMS {
+            msg.push_str(&format!(
+                "\n  ... and {} more",
+                self.problems.len() - MAX_REPORTED_PROBLEMS
+            ));
+        }
+        Err(Error::new(ErrorKind::InvalidData, msg))
+    }
+}
+
+pub fn split_graph_files(gfa_file: &str) -> Vec<&str> {
+    gfa_file
+        .split(GRAPH_FILE_SEPARATOR)
+        .map(|x| x.trim())
+        .filter(|x| !x.is_empty())
+        .collect()
+}
+
+pub fn is_multi_file_graph(gfa_file: &str) -> bool {
+    split_graph_files(gfa_file).len() > 1
+}
+
+pub fn is_glob(file_name: &str) -> bool {
+    file_name.contains(['*', '?'])
+}
+
+// files matching a pattern such as `graphs/chr*.gfa`, in which `*` and `?` stand for any sequence
+// of characters and any single character within a path component; hidden files are only matched
+// explicitly
+pub fn expand_glob(pattern: &str) -> Result<Vec<String>, Error> {
+    let mut matches = vec![String::new()];
+    for (i, component) in pattern.split('/').enumerate() {
+        let mut next = Vec::new();
+        for prefix in matches {
+            let join = |name: &str| {
+                if i == 0 {
+                    name.to_string()
+                } else {
+                    format!("{}/{}", prefix, name)
+                }
+            };
+            if !is_glob(component) {
+                next.push(join(component));
+                continue;
+            }
+            let dir = match (i, prefix.as_str()) {
+                (0, _) => ".",
+                (_, "") => "/",
+                (_, dir) => dir,
+            };
+            let re = regex::Regex::new(&format!(
+                "^{}$",
+                regex::escape(component)
+                    .replace("\\*", ".*")
+                    .replace("\\?", ".")
+            ))
+            .expect("escaped pattern is a valid regex");
+            let mut names = match std::fs::read_dir(dir) {
+                Ok(entries) => entries
+                    .filter_map(|entry| entry.ok())
+                    .map(|entry| entry.file_name().to_string_lossy().into_owned())
+                    .filter(|name| !name.starts_with('.') && re.is_match(name))
+                    .map(|name| join(&name))
+                    .collect(),
+                Err(_) => Vec::new(),
+            };
+            names.sort();
+            next.extend(names);
+        }
+        matches = next;
+    }
+    matches.retain(|path| std::path::Path::new(path).exists());
+    if matches.is_empty() {
+        return Err(Error::new(
+            ErrorKind::NotFound,
+            format!("no file matches {}", pattern),
+        ));
+    }
+    Ok(matches)
+}
+
+/// Whether the graph is given by an index written by `panacus index` rather than a graph file
+pub fn is_graph_index(gfa_file: &str) -> bool {
+    gfa_file.ends_with(".pidx")
+}
+
+pub fn bufreader_from_compressed_gfa(gfa_file: &str) -> Result<BufReader<Box<dyn Read>>, Error> {
+    // everything but sequences is read from the index itself
+    if is_graph_index(gfa_file) {
+        let msg = format!(
+            "graph index {} does not contain sequences, use the graph file instead",
+            gfa_file
+        );
+        log::error!("{}", &msg);
+        return Err(Error::new(ErrorKind::InvalidInput, msg));
+    }
+    let files = split_graph_files(gfa_file);
+    if files.len() > 1 {
+        log::info!("loading graph from {} files: {}", files.len(), &gfa_file);
+        let readers = files
+            .into_iter()
+            .map(bufreader_from_single_compressed_gfa)
+            .collect::<Result<Vec<_>, _>>()?;
+        return Ok(BufReader::new(Box::new(MultiGfaReader::new(readers))));
+    }
+    bufreader_from_single_compressed_gfa(gfa_file)
+}
+
+fn bufreader_from_single_compressed_gfa(gfa_file: &str) -> Result<BufReader<Box<dyn Read>>, Error> {
+    let mut reader = open_single_compressed_gfa(gfa_file)?;
+    if is_gfa2(gfa_file, &mut reader)? {
+        log::info!("{} is in GFA2 format, translating it to GFA1..", &gfa_file);
+        return Ok(BufReader::new(Box::new(Gfa2Reader::new(reader))));
+    }
+    Ok(reader)
+}
+
+/// Registers content under the given file name, such that reading the file yields this content
+/// instead of that of the file on disk
+#[cfg(any(target_arch = "wasm32", test))]
+pub fn add_in_memory_file(file_name: &str, content: Vec<u8>) {
+    IN_MEMORY_FILES
+        .lock()
+        .unwrap()
+        .insert(file_name.to_string(), content.into());
+}
+
+/// Drops the content of all files registered in memory
+#[cfg(any(target_arch = "wasm32", test))]
+pub fn clear_in_memory_files() {
+    IN_MEMORY_FILES.lock().unwrap().clear();
+}
+
+fn get_in_memory_file(file_name: &str) -> Option<Cursor<Arc<[u8]>>> {
+    IN_MEMORY_FILES
+        .lock()
+        .unwrap()
+        .get(file_name)
+        .map(|content| Cursor::new(Arc::clone(content)))
+}
+
+/// Opens a (possibly in-memory) file for reading
+pub fn open_file(file_name: &str) -> Result<Box<dyn Read>, Error> {
+    match get_in_memory_file(file_name) {
+        Some(content) => Ok(Box::new(content)),
+        None => Ok(Box::new(std::fs::File::open(file_name)?)),
+    }
+}
+
+/// Opens a (possibly in-memory) file for reading, decompressing it if its name ends with .gz
+pub fn bufreader_from_compressed_file(file_name: &str) -> Result<BufReader<Box<dyn Read>>, Error> {
+    let f = open_file(file_name).map_err(|e| {
+        let msg = format!("unable to open file {}: {}", file_name, e);
+        log::error!("{}", &msg);
+        Error::new(e.kind(), msg)
+    })?;
+    if file_name.ends_with(".gz") {
+        return Ok(BufReader::new(Box::new(MultiGzDecoder::new(f))));
+    }
+    Ok(BufReader::new(f))
+}
+
+fn open_single_compressed_gfa(gfa_file: &str) -> Result<BufReader<Box<dyn Read>>, Error> {
     log::info!("loading graph from {}", &gfa_file);
-    let f = std::fs::File::open(gfa_file).expect("Error opening file");
-    let reader: Box<dyn Read> = if gfa_file.ends_with(".gz") {
+    if let Some(content) = get_in_memory_file(gfa_file) {
+        if gfa_file.ends_with(".gz") {
+            return Ok(BufReader::new(Box::new(MultiGzDecoder::new(content))));
+        }
+        return Ok(BufReader::new(Box::new(content)));
+    }
+    let f = std::fs::File::open(gfa_file).map_err(|e| {
+        let msg = format!("unable to open graph file {}: {}", gfa_file, e);
+        log::error!("{}", &msg);
+        Error::new(e.kind(), msg)
+    })?;
+    if let Some(converter) = find_converter(gfa_file) {
+        return Ok(BufReader::with_capacity(
+            MMAP_READ_BUFFER_SIZE,
+            Box::new(Cursor::new(converter.convert(gfa_file)?)),
+        ));
+    }
+    if gfa_file.ends_with(".gz") {
         log::info!("assuming that {} is gzip compressed..", &gfa_file);
-        Box::new(MultiGzDecoder::new(f))
+        return Ok(BufReader::new(Box::new(MultiGzDecoder::new(f))));
+    }
+    // memory-mapping lets repeated runs on the same graph reuse the OS page cache; the graph
+    // must not be modified while panacus is running
+    Ok(match unsafe { Mmap::map(&f) } {
+        Ok(mmap) => {
+            log::debug!("memory-mapped {} ({} bytes)", &gfa_file, mmap.len());
+            BufReader::with_capacity(MMAP_READ_BUFFER_SIZE, Box::new(Cursor::new(mmap)))
+        }
+        Err(e) => {
+            log::debug!(
+                "unable to memory-map {} ({}), reading it instead",
+                &gfa_file,
+                e
+            );
+            BufReader::new(Box::new(f))
+        }
+    })
+}
+
+/// Concatenates several GFA files into a single stream. Segment identifiers are prefixed with the
+/// index of their file, so that identical identifiers of different files do not collide; path
+/// names are kept as they are.
+struct MultiGfaReader {
+    readers: Vec<BufReader<Box<dyn Read>>>,
+    current: usize,
+    line: Vec<u8>,
+    buf: Vec<u8>,
+    pos: usize,
+}
+
+impl MultiGfaReader {
+    fn new(readers: Vec<BufReader<Box<dyn Read>>>) -> Self {
+        Self {
+            readers,
+            current: 0,
+            line: Vec::new(),
+            buf: Vec::new(),
+            pos: 0,
+        }
+    }
+
+    fn fill_line(&mut self) -> std::io::Result<bool> {
+        while self.current < self.readers.len() {
+            self.line.clear();
+            if self.readers[self.current].read_until(b'\n', &mut self.line)? > 0 {
+                if self.line.last() != Some(&b'\n') {
+                    self.line.push(b'\n');
+                }
+                self.buf.clear();
+                self.pos = 0;
+                prefix_segment_ids(&self.line, self.current + 1, &mut self.buf);
+                return Ok(true);
+            }
+            self.current += 1;
+        }
+        Ok(false)
+    }
+}
+
+impl Read for MultiGfaReader {
+    fn read(&mut self, out: &mut [u8]) -> std::io::Result<usize> {
+        if self.pos >= self.buf.len() && !self.fill_line()? {
+            return Ok(0);
+        }
+        let n = usize::min(out.len(), self.buf.len() - self.pos);
+        out[..n].copy_from_slice(&self.buf[self.pos..self.pos + n]);
+        self.pos += n;
+        Ok(n)
+    }
+}
+
+fn prefix_segment_ids(line: &[u8], file_idx: usize, out: &mut Vec<u8>) {
+    let prefix = format!("{}:", file_idx);
+    let prefix = prefix.as_bytes();
+    let mut cols = line.split(|&x| x == b'\t').enumerate().peekable();
+    while let Some((i, col)) = cols.next() {
+        match (line[0], i) {
+            // segment name, and both segments of links, containments, and jumps
+            (b'S', 1) | (b'L', 1) | (b'L', 3) | (b'C', 1) | (b'C', 3) | (b'J', 1) | (b'J', 3) => {
+                out.extend_from_slice(prefix);
+                out.extend_from_slice(col);
+            }
+            // path steps, e.g. 1+,2-,3+
+            (b'P', 2) => {
+                for (j, step) in col.split(|&x| x == b',').enumerate() {
+                    if j > 0 {
+                        out.push(b',');
+                    }
+                    out.extend_from_slice(prefix);
+                    out.extend_from_slice(step);
+                }
+            }
+            // walk steps, e.g. >1<2>3
+            (b'W', 6) => {
+                for &c in col {
+                    out.push(c);
+                    if c == b'>' || c == b'<' {
+                        out.extend_from_slice(prefix);
+                    }
+                }
+            }
+            _ => out.extend_from_slice(col),
+        }
+        if cols.peek().is_some() {
+            out.push(b'\t');
+        }
+    }
+}
+
+/// Names of the nodes of a graph of several files without the index of their file, as long as
+/// they are still unique; otherwise, the names are returned as they are
+pub fn strip_segment_id_prefixes(names: Vec<String>) -> Vec<String> {
+    let stripped: Vec<String> = names
+        .iter()
+        .map(|name| match name.split_once(':') {
+            Some((idx, id)) if idx.bytes().all(|c| c.is_ascii_digit()) => id.to_string(),
+            _ => name.clone(),
+        })
+        .collect();
+    let unique: HashSet<&String> = stripped.iter().collect();
+    if unique.len() == names.len() {
+        stripped
     } else {
-        Box::new(f)
-    };
-    BufReader::new(reader)
+        log::debug!("segment ids of the graph files overlap, keeping the index of their file");
+        names
+    }
+}
+
+pub fn file_size_and_checksum(file_name: &str) -> Result<(u64, u32), Error> {
+    // CRC32 of the raw (possibly compressed) file content
+    let mut reader = BufReader::new(std::fs::File::open(file_name)?);
+    let mut crc = flate2::Crc::new();
+    loop {
+        let buf = reader.fill_buf()?;
+        if buf.is_empty() {
+            break;
+        }
+        let n = buf.len();
+        crc.update(buf);
+        reader.consume(n);
+    }
+    Ok((std::fs::metadata(file_name)?.len(), crc.sum()))
 }
 
 pub fn parse_bed_to_path_segments<R: Read>(
     data: &mut BufReader<R>,
+    file_name: &str,
     use_block_info: bool,
-) -> Vec<PathSegment> {
+) -> Result<Vec<PathSegment>, Error> {
     // based on https://en.wikipedia.org/wiki/BED_(file_format)
     let mut segments = Vec::new();
+    let mut problems = InputProblems::new(file_name);
 
     for (i, line) in data.lines().enumerate() {
         let line = match line {
             Ok(l) => l,
             Err(e) => {
-                panic!("error reading line {}: {}", i + 1, e);
+                problems.push(i + 1, 0, format!("unable to read line: {}", e));
+                break;
             }
         };
 
@@ -58,16 +452,18 @@ pub fn parse_bed_to_path_segments<R: Read>(
         if fields.len() == 1 {
             segments.push(PathSegment::from_str(path_name));
         } else if fields.len() >= 3 {
-            let start = usize::from_str(fields[1]).expect(&format!(
-                "error line {}: `{}` is not an usize",
-                i + 1,
-                fields[1]
-            ));
-            let end = usize::from_str(fields[2]).expect(&format!(
-                "error line {}: `{}` is not an usize",
-                i + 1,
-                fields[2]
-            ));
+            let start = usize::from_str(fields[1]);
+            if start.is_err() {
+                problems.push(i + 1, 2, format!("`{}` is not an usize", fields[1]));
+            }
+            let end = usize::from_str(fields[2]);
+            if end.is_err() {
+                problems.push(i + 1, 3, format!("`{}` is not an usize", fields[2]));
+            }
+            let (start, end) = match (start, end) {
+                (Ok(start), Ok(end)) => (start, end),
+                _ => continue,
+            };
 
             if use_block_info && fields.len() == 12 {
                 let block_count = fields[9].parse::<usize>().unwrap_or(0);
@@ -91,57 +487,253 @@ pub fn parse_bed_to_path_segments<R: Read>(
                         ));
                     }
                 } else {
-                    panic!(
-                        "error in block sizes/starts in line {}: counts do not match",
-                        i + 1
-                    );
+                    problems.push(i + 1, 10, "counts of block sizes/starts do not match");
                 }
             } else {
                 segments.push(PathSegment::from_str_start_end(path_name, start, end));
             }
         } else {
-            panic!(
-                "error in line {}: row must have either 1, 3, or 12 columns, but has 2",
-                i + 1
+            problems.push(
+                i + 1,
+                0,
+                "row must have either 1, 3, or 12 columns, but has 2",
             );
         }
     }
 
-    segments
+    problems.into_result()?;
+    Ok(segments)
 }
 
-pub fn parse_groups<R: Read>(data: &mut BufReader<R>) -> Result<Vec<(PathSegment, String)>, Error> {
+/// Parses the features of a BED file (at least 3 columns) as sequence, start, end, and name,
+/// which is empty if the file has no fourth column; browser, track, and comment lines are skipped
+pub fn parse_bed_features<R: Read>(
+    data: &mut BufReader<R>,
+    file_name: &str,
+) -> Result<Vec<(String, usize, usize, String)>, Error> {
+    let mut res = Vec::new();
+    let mut problems = InputProblems::new(file_name);
+
+    for (i, line) in data.lines().enumerate() {
+        let line = line?;
+        let line = line.trim_end_matches('\r');
+        if line.is_empty()
+            || line.starts_with("browser ")
+            || line.starts_with("track ")
+            || line.starts_with('#')
+        {
+            continue;
+        }
+        let fields: Vec<&str> = line.split('\t').collect();
+        if fields.len() < 3 {
+            problems.push(
+                i + 1,
+                0,
+                format!("row must have at least 3 columns, but has {}", fields.len()),
+            );
+            continue;
+        }
+        match (usize::from_str(fields[1]), usize::from_str(fields[2])) {
+            (Ok(start), Ok(end)) => res.push((
+                fields[0].to_string(),
+                start,
+                end,
+                fields.get(3).unwrap_or(&"").to_string(),
+            )),
+            (start, _) => {
+                let col = if start.is_err() { 2 } else { 3 };
+                problems.push(i + 1, col, format!("`{}` is not an usize", fields[col - 1]));
+            }
+        }
+    }
+
+    problems.into_result()?;
+    Ok(res)
+}
+
+pub fn parse_groups<R: Read>(
+    data: &mut BufReader<R>,
+    file_name: &str,
+) -> Result<Vec<(PathSegment, String)>, Error> {
     let mut res: Vec<(PathSegment, String)> = Vec::new();
+    let mut problems = InputProblems::new(file_name);
 
     let mut i = 1;
     let mut buf = vec![];
-    while data.read_until(b'\n', &mut buf).unwrap_or(0) > 0 {
+    while data.read_until(b'\n', &mut buf)? > 0 {
         //Remove new line at the end
-        if let Some(&last_byte) = buf.last() {
+        while let Some(&last_byte) = buf.last() {
             if last_byte == b'\n' || last_byte == b'\r' {
                 buf.pop();
+            } else {
+                break;
             }
         }
-        let line = String::from_utf8(buf.clone())
-            .expect(&format!("error in line {}: some character is not UTF-8", i));
-        let columns: Vec<&str> = line.split('\t').collect();
-
-        if columns.len() != 2 {
-            let msg = format!("error in line {}: table must have exactly two columns", i);
-            log::error!("{}", &msg);
-            return Err(Error::new(ErrorKind::InvalidData, msg));
+        match str::from_utf8(&buf) {
+            Ok(line) => {
+                let columns: Vec<&str> = line.split('\t').collect();
+                if columns.len() != 2 {
+                    problems.push(
+                        i,
+                        0,
+                        format!(
+                            "table must have exactly two columns, but has {}",
+                            columns.len()
+                        ),
+                    );
+                } else {
+                    let path_seg = PathSegment::from_str(columns[0]);
+                    res.push((path_seg, columns[1].to_string()));
+                }
+            }
+            Err(e) => problems.push(i, e.valid_up_to() + 1, "some character is not UTF-8"),
         }
 
-        let path_seg = PathSegment::from_str(columns[0]);
-        res.push((path_seg, columns[1].to_string()));
+        i += 1;
+        buf.clear();
+    }
+
+    problems.into_result()?;
+    Ok(res)
+}
+
+/// Parses a tab-separated two-column file that assigns groups to phenotypes; empty lines and lines
+/// starting with '#' are skipped
+pub fn parse_phenotypes<R: Read>(
+    data: &mut BufReader<R>,
+    file_name: &str,
+) -> Result<Vec<(String, String)>, Error> {
+    Ok(parse_tab_table(data, file_name, Some(2))?
+        .into_iter()
+        .map(|mut row| {
+            let label = row.pop().unwrap();
+            (row.pop().unwrap(), label)
+        })
+        .collect())
+}
+
+// parses the rows of a tab-separated table, skipping empty lines and lines starting with '#'; if
+// the number of columns is not given, the first line is the header (which may start with '#'),
+// whose number of columns all rows must have
+fn parse_tab_table<R: Read>(
+    data: &mut BufReader<R>,
+    file_name: &str,
+    columns: Option<usize>,
+) -> Result<Vec<Vec<String>>, Error> {
+    let mut res: Vec<Vec<String>> = Vec::new();
+    let mut problems = InputProblems::new(file_name);
+
+    let mut i = 1;
+    let mut buf = vec![];
+    while data.read_until(b'\n', &mut buf)? > 0 {
+        while let Some(&last_byte) = buf.last() {
+            if last_byte == b'\n' || last_byte == b'\r' {
+                buf.pop();
+            } else {
+                break;
+            }
+        }
+        match str::from_utf8(&buf) {
+            Ok("") => (),
+            Ok(line) if line.starts_with('#') && (columns.is_some() || !res.is_empty()) => (),
+            Ok(line) => {
+                let row: Vec<String> = line.split('\t').map(|c| c.to_string()).collect();
+                match (columns, res.first()) {
+                    (Some(n), _) if row.len() != n => problems.push(
+                        i,
+                        0,
+                        format!(
+                            "table must have exactly {} columns, but has {}",
+                            n,
+                            row.len()
+                        ),
+                    ),
+                    (None, None) if row.len() < 2 => {
+                        problems.push(i, 0, "header must have at least two columns");
+                        res.push(row);
+                    }
+                    (None, Some(header)) if row.len() != header.len() => problems.push(
+                        i,
+                        0,
+                        format!(
+                            "row has {} columns, but header has {}",
+                            row.len(),
+                            header.len()
+                        ),
+                    ),
+                    _ => res.push(row),
+                }
+            }
+            Err(e) => problems.push(i, e.valid_up_to() + 1, "some character is not UTF-8"),
+        }
 
         i += 1;
         buf.clear();
     }
 
+    problems.into_result()?;
     Ok(res)
 }
 
+/// Assigns each of the given groups to a label (e.g., a phenotype or population) of the
+/// tab-separated two-column file, or to none if it is not listed; returns the labels in order of
+/// appearance and the assignment. The kind of the labels is used in messages
+pub fn get_group_labels(
+    file: &str,
+    kind: &str,
+    groups: &[String],
+) -> anyhow::Result<(Vec<String>, Vec<Option<usize>>)> {
+    log::info!("loading {}s from {}", kind, file);
+    let mut data = BufReader::new(
+        open_file(file)
+            .map_err(|e| anyhow::anyhow!("unable to open {} file {}: {}", kind, file, e))?,
+    );
+    let mut group2label: HashMap<String, usize> = HashMap::new();
+    let mut labels: Vec<String> = Vec::new();
+    for (group, label) in parse_phenotypes(&mut data, file)? {
+        let l = match labels.iter().position(|x| x == &label) {
+            Some(l) => l,
+            None => {
+                labels.push(label);
+                labels.len() - 1
+            }
+        };
+        if let Some(k) = group2label.insert(group.clone(), l) {
+            if k != l {
+                anyhow::bail!(
+                    "group {} is assigned to {}s {} and {} in {}",
+                    group,
+                    kind,
+                    labels[k],
+                    labels[l],
+                    file
+                );
+            }
+        }
+    }
+    for g in group2label.keys().filter(|g| !groups.contains(g)) {
+        log::warn!("group {} of {} file {} is not in the graph", g, kind, file);
+    }
+    let sets = groups.iter().map(|g| group2label.get(g).copied()).collect();
+    Ok((labels, sets))
+}
+
+/// Parses a tab-separated table with a header line, whose first column identifies samples/groups
+/// and whose other columns hold their metadata (e.g., phenotypes); empty lines and lines starting
+/// with '#' after the header are skipped
+pub fn parse_metadata<R: Read>(
+    data: &mut BufReader<R>,
+    file_name: &str,
+) -> Result<(Vec<String>, Vec<Vec<String>>), Error> {
+    let mut rows = parse_tab_table(data, file_name, None)?;
+    let header = if rows.is_empty() {
+        Vec::new()
+    } else {
+        rows.remove(0)
+    };
+    Ok((header, rows))
+}
+
 pub fn parse_tsv<R: Read>(
     data: &mut BufReader<R>,
 ) -> Result<(Vec<Vec<u8>>, Vec<Vec<Vec<u8>>>), Error> {
@@ -165,7 +757,7 @@ pub fn parse_tsv<R: Read>(
             continue;
         }
         // Push header
-        if is_header && (b'#' == row[0][0]) {
+        if is_header && (row[0].first() == Some(&b'#')) {
             let mut c = row[0].to_vec();
             for e in &row[1..] {
                 c.push(b'\t');
@@ -181,7 +773,7 @@ pub fn parse_tsv<R: Read>(
             log::debug!("Skipping empty line");
             continue;
         // Handle comments
-        } else if b'#' == row[0][0] {
+        } else if row[0].first() == Some(&b'#') {
             log::debug!("Handling comment");
             let mut c = row[0].to_vec();
             for e in &row[1..] {
@@ -199,82 +791,99 @@ pub fn parse_tsv<R: Read>(
 }
 
 fn transpose_table(table: &Vec<Vec<Vec<u8>>>) -> Vec<Vec<&[u8]>> {
-    let n = table.first().unwrap_or(&Vec::new()).len();
-
-    let mut res = vec![vec![&table[0][0][..]; table.len()]; n];
-
-    for j in 0..n {
-        for i in 0..table.len() {
-            res[j][i] = &table[i][j][..];
-        }
-    }
-
-    res
+    // rows may differ in length, missing cells are represented by empty fields
+    let n = table.iter().map(|row| row.len()).max().unwrap_or(0);
+
+    (0..n)
+        .map(|j| {
+            table
+                .iter()
+                .map(|row| row.get(j).map(|x| &x[..]).unwrap_or(&[]))
+                .collect()
+        })
+        .collect()
 }
 
-fn parse_column(col: &Vec<&[u8]>, offset: usize) -> Result<Vec<usize>, Error> {
+fn parse_column(
+    col: &Vec<&[u8]>,
+    offset: usize,
+    column: usize,
+    problems: &mut InputProblems,
+) -> Vec<usize> {
     let skip_lines = 2;
-    let mut res = vec![0; col.len() - skip_lines];
+    let mut res = vec![0; col.len().saturating_sub(skip_lines)];
 
-    for (i, e) in col[skip_lines..].iter().enumerate() {
-        if let Ok(val) = usize::from_str(str::from_utf8(e).unwrap()) {
-            res[i] = val;
-        } else {
-            let msg = format!(
-                "error in line {}: value must be integer, but is '{}'",
+    for (i, e) in col.iter().skip(skip_lines).enumerate() {
+        match str::from_utf8(e).ok().and_then(|x| usize::from_str(x).ok()) {
+            Some(val) => res[i] = val,
+            None => problems.push(
                 i + 3 + offset,
-                &str::from_utf8(e).unwrap()
-            );
-            log::error!("{}", &msg);
-            Err(Error::new(ErrorKind::InvalidData, msg))?
+                column,
+                format!(
+                    "value must be integer, but is '{}'",
+                    String::from_utf8_lossy(e)
+                ),
+            ),
         }
     }
 
-    Ok(res)
+    res
 }
 
 pub fn parse_hists<R: Read>(
     data: &mut BufReader<R>,
+    file_name: &str,
 ) -> Result<(Vec<(CountType, Vec<usize>)>, Vec<Vec<u8>>), Error> {
-    log::info!("loading coverage histogram from");
+    log::info!("loading coverage histogram from {}", file_name);
     let (comments, raw_table) = parse_tsv(data)?;
     let raw_table = transpose_table(&raw_table);
-    if raw_table.len() < 4 && b"panacus" != raw_table[0][0] {
-        let msg = format!(
-            "error in line {}: table appears not to be generated by panacus",
-            comments.len()
+    let mut problems = InputProblems::new(file_name);
+    if raw_table.len() < 2
+        || raw_table[0].len() < 2
+        || (raw_table.len() < 4 && b"panacus" != raw_table[0][0])
+    {
+        problems.push(
+            comments.len() + 1,
+            0,
+            "table appears not to be generated by panacus",
         );
-        log::error!("{}", &msg);
-        return Err(Error::new(ErrorKind::InvalidData, msg));
+        return problems.into_result().map(|_| (Vec::new(), Vec::new()));
     }
 
     let mut res = Vec::new();
 
-    let index = parse_column(&raw_table[0], comments.len())?;
-    let mx = index.iter().max().unwrap();
-    for col in &raw_table[1..] {
+    let index = parse_column(&raw_table[0], comments.len(), 1, &mut problems);
+    let mx = index.iter().max().copied().unwrap_or(0);
+    for (j, col) in raw_table.iter().enumerate().skip(1) {
         if b"hist" == &col[0] {
-            let count = CountType::from_str(str::from_utf8(col[1]).unwrap()).map_err(|_| {
-                let msg = format!(
-                    "error in line {}: expected count type declaration, but got '{}'",
+            match str::from_utf8(col[1])
+                .ok()
+                .and_then(|x| CountType::from_str(x).ok())
+            {
+                Some(count) => {
+                    let mut cov = vec![0; mx + 1];
+                    let values = parse_column(col, comments.len(), j + 1, &mut problems);
+                    for (i, c) in index.iter().zip(values) {
+                        cov[*i] = c;
+                    }
+                    res.push((count, cov));
+                }
+                None => problems.push(
                     2 + comments.len(),
-                    &str::from_utf8(col[1]).unwrap()
-                );
-                log::error!("{}", &msg);
-                Error::new(ErrorKind::InvalidData, msg)
-            })?;
-            let mut cov = vec![0; mx + 1];
-            for (i, c) in index.iter().zip(parse_column(col, comments.len())?) {
-                cov[*i] = c;
+                    j + 1,
+                    format!(
+                        "expected count type declaration, but got '{}'",
+                        String::from_utf8_lossy(col[1])
+                    ),
+                ),
             }
-
-            res.push((count, cov));
         }
     }
+    problems.into_result()?;
 
     if res.is_empty() {
-        let msg = "table does not contain hist columns";
-        log::error!("{}", msg);
+        let msg = format!("table {} does not contain hist columns", file_name);
+        log::error!("{}", &msg);
         Err(Error::new(ErrorKind::InvalidData, msg))
     } else {
         Ok((res, comments))
@@ -480,6 +1089,37 @@ pub fn write_table_with_start_index(
     Ok(res)
 }
 
+/// Writes a table in long format, with one row per column and index instead of one column per
+/// header; header fields that are empty for all columns are omitted, and so are NaN values
+pub fn write_long_table(
+    headers: &[Vec<String>],
+    columns: &[Vec<f64>],
+    start_index: usize,
+    index_name: &str,
+) -> Result<String, Error> {
+    let n = headers.first().unwrap_or(&Vec::new()).len();
+    let fields: Vec<usize> = (0..n)
+        .filter(|i| headers.iter().skip(1).any(|h| !h[*i].is_empty()))
+        .collect();
+    let mut res = String::new();
+    for i in fields.iter() {
+        res.push_str(&format!("{}\t", headers[0][*i]));
+    }
+    res.push_str(&format!("{}\tvalue\n", index_name));
+    for (header, column) in headers.iter().skip(1).zip(columns) {
+        for (i, value) in column.iter().enumerate() {
+            if value.is_nan() {
+                continue;
+            }
+            for j in fields.iter() {
+                res.push_str(&format!("{}\t", header[*j]));
+            }
+            res.push_str(&format!("{}\t{:0}\n", i + start_index, value.floor()));
+        }
+    }
+    Ok(res)
+}
+
 pub fn write_ordered_table(
     headers: &Vec<Vec<String>>,
     columns: &Vec<Vec<f64>>,
@@ -568,6 +1208,9 @@ pub fn write_ordered_histgrowth_table(
         })
         .collect();
 
+    // content contributed by the group added at each step
+    let new_content: Vec<Vec<f64>> = output_columns.iter().map(|c| get_new_content(c)).collect();
+    output_columns.extend(new_content);
     // insert empty row for 0 element
     for c in &mut output_columns {
         c.insert(0, f64::NAN);
@@ -590,6 +1233,20 @@ pub fn write_ordered_histgrowth_table(
             })
             .collect::<Vec<Vec<String>>>(),
     );
+    header_cols.extend(
+        hist_aux
+            .coverage
+            .iter()
+            .zip(&hist_aux.quorum)
+            .map(|(c, q)| {
+                vec![
+                    "ordered-growth-new".to_string(),
+                    abacus_group.count.to_string(),
+                    c.get_string(),
+                    q.get_string(),
+                ]
+            }),
+    );
     let table = write_ordered_table(&header_cols, &output_columns, &abacus_group.groups)?;
     res.push_str(&table);
     Ok(res)
@@ -1079,4 +1736,137 @@ mod tests {
     //         assert_eq!(group, test_groups[i]);
     //     }
     // }
+
+    #[test]
+    fn test_prefix_segment_ids() {
+        let mut out = Vec::new();
+        super::prefix_segment_ids(b"P\ta#1#chr1\t1+,2-\t*\n", 2, &mut out);
+        assert_eq!(out, b"P\ta#1#chr1\t2:1+,2:2-\t*\n".to_vec());
+        out.clear();
+        super::prefix_segment_ids(b"W\ta\t1\tchr1\t0\t10\t>1<2\n", 2, &mut out);
+        assert_eq!(out, b"W\ta\t1\tchr1\t0\t10\t>2:1<2:2\n".to_vec());
+        out.clear();
+        super::prefix_segment_ids(b"L\t1\t+\t2\t-\t0M\n", 2, &mut out);
+        assert_eq!(out, b"L\t2:1\t+\t2:2\t-\t0M\n".to_vec());
+        out.clear();
+        super::prefix_segment_ids(b"C\t1\t+\t2\t-\t5\t3M\n", 2, &mut out);
+        assert_eq!(out, b"C\t2:1\t+\t2:2\t-\t5\t3M\n".to_vec());
+        out.clear();
+        super::prefix_segment_ids(b"J\t1\t+\t2\t-\t*\n", 2, &mut out);
+        assert_eq!(out, b"J\t2:1\t+\t2:2\t-\t*\n".to_vec());
+    }
+
+    #[test]
+    fn test_get_group_labels() {
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        std::io::Write::write_all(&mut file, b"a\tcase\nb\tcontrol\n#c\tcase\nd\tcase\n").unwrap();
+        let file_name = file.path().to_str().unwrap().to_string();
+        let groups = ["b", "a", "e"].map(|g| g.to_string());
+        let (labels, sets) = super::get_group_labels(&file_name, "phenotype", &groups).unwrap();
+        assert_eq!(labels, vec!["case", "control"]);
+        assert_eq!(sets, vec![Some(1), Some(0), None]);
+
+        std::io::Write::write_all(&mut file, b"a\tcontrol\n").unwrap();
+        let msg = super::get_group_labels(&file_name, "population", &groups)
+            .unwrap_err()
+            .to_string();
+        assert!(msg.contains("group a is assigned to populations case and control"));
+    }
+
+    #[test]
+    fn test_parse_metadata() {
+        let table = "#sample\tpop\tsex\n\nHG1\tAFR\tF\n# comment\nHG2\tEUR\tM\n";
+        let mut data = std::io::BufReader::new(table.as_bytes());
+        let (header, rows) = super::parse_metadata(&mut data, "meta.tsv").unwrap();
+        assert_eq!(header, vec!["#sample", "pop", "sex"]);
+        assert_eq!(rows, vec![vec!["HG1", "AFR", "F"], vec!["HG2", "EUR", "M"]]);
+
+        let table = "sample\tpop\nHG1\tAFR\tF\n";
+        let mut data = std::io::BufReader::new(table.as_bytes());
+        let msg = super::parse_metadata(&mut data, "meta.tsv")
+            .unwrap_err()
+            .to_string();
+        assert!(msg.contains("meta.tsv:2:0: row has 3 columns, but header has 2"));
+    }
+
+    #[test]
+    fn test_strip_segment_id_prefixes() {
+        let names = |x: &[&str]| x.iter().map(|x| x.to_string()).collect::<Vec<String>>();
+        assert_eq!(
+            super::strip_segment_id_prefixes(names(&["", "1:a", "1:b", "2:c"])),
+            names(&["", "a", "b", "c"])
+        );
+        assert_eq!(
+            super::strip_segment_id_prefixes(names(&["", "1:a", "2:a"])),
+            names(&["", "1:a", "2:a"])
+        );
+    }
+
+    #[test]
+    fn test_parse_bed_reports_all_problems() {
+        let bed = "chr1\t1\t10\nchr2\tx\t10\nchr3\t1\nchr4\t1\ty\n";
+        let mut data = std::io::BufReader::new(bed.as_bytes());
+        let err = super::parse_bed_to_path_segments(&mut data, "test.bed", false).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+        let msg = err.to_string();
+        assert!(msg.starts_with("found 3 problem(s) in test.bed:"));
+        assert!(msg.contains("test.bed:2:2: `x` is not an usize"));
+        assert!(msg.contains("test.bed:3:0: "));
+        assert!(msg.contains("test.bed:4:3: `y` is not an usize"));
+    }
+
+    #[test]
+    fn test_parse_bed_features() {
+        let bed = "track name=genes\nchr1\t10\t20\tGENE1\t0\t+\nchr2\t5\t8\r\n";
+        let mut data = std::io::BufReader::new(bed.as_bytes());
+        assert_eq!(
+            super::parse_bed_features(&mut data, "test.bed").unwrap(),
+            vec![
+                ("chr1".to_string(), 10, 20, "GENE1".to_string()),
+                ("chr2".to_string(), 5, 8, String::new())
+            ]
+        );
+        let mut data = std::io::BufReader::new("chr1\t10\t20\nchr3\tx\t8\nchr4\t1\n".as_bytes());
+        let msg = super::parse_bed_features(&mut data, "test.bed")
+            .unwrap_err()
+            .to_string();
+        assert!(msg.contains("test.bed:2:2: `x` is not an usize"));
+        assert!(msg.contains("test.bed:3:0: row must have at least 3 columns, but has 2"));
+    }
+
+    #[test]
+    fn test_in_memory_file() {
+        use std::io::Read;
+        let name = "in-memory/test_in_memory_file.bed";
+        assert!(super::open_file(name).is_err());
+        super::add_in_memory_file(name, b"a#0\t0\t10\n".to_vec());
+        let mut content = String::new();
+        super::open_file(name)
+            .unwrap()
+            .read_to_string(&mut content)
+            .unwrap();
+        assert_eq!(content, "a#0\t0\t10\n");
+        let mut data = super::bufreader_from_compressed_gfa(name).unwrap();
+        content.clear();
+        data.read_to_string(&mut content).unwrap();
+        assert_eq!(content, "a#0\t0\t10\n");
+        super::clear_in_memory_files();
+        assert!(super::open_file(name).is_err());
+    }
+
+    #[test]
+    fn test_expand_glob() {
+        assert_eq!(
+            super::expand_glob("tests/test_files/t_*.gfa").unwrap(),
+            vec![
+                "tests/test_files/t_groups.gfa".to_string(),
+                "tests/test_files/t_walks.gfa".to_string(),
+            ]
+        );
+        assert_eq!(
+            super::expand_glob("tests/test_*/t_group?.gfa").unwrap(),
+            vec!["tests/test_files/t_groups.gfa".to_string()]
+        );
+        assert!(super::expand_glob("tests/test_files/*.missing").is_err());
+    }
 }
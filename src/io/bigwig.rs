@@ -0,0 +1,470 @@
+/* standard use */
+use std::io::{Error, ErrorKind, Write};
+
+/* crate use */
+use flate2::{write::ZlibEncoder, Compression};
+
+const BIGWIG_MAGIC: u32 = 0x888F_FC26;
+const CHROM_TREE_MAGIC: u32 = 0x78CA_8C91;
+const CIR_TREE_MAGIC: u32 = 0x2468_ACE0;
+const BIGWIG_VERSION: u16 = 4;
+
+const HEADER_SIZE: usize = 64;
+const ZOOM_HEADER_SIZE: usize = 24;
+const SUMMARY_SIZE: usize = 40;
+
+// number of records that are compressed together into one block
+const ITEMS_PER_SLOT: usize = 1024;
+// number of children of each node of the R-tree that indexes the blocks
+const BLOCK_SIZE: usize = 256;
+const MAX_ZOOM_LEVELS: usize = 10;
+const ZOOM_FACTOR: u64 = 4;
+
+/// Sequence given by its name, its length, and its values for sorted, non-overlapping intervals
+/// [start, end)
+pub type BigWigChrom<'a> = (&'a str, u32, &'a [(u32, u32, f32)]);
+
+// range (start chromosome, start, end chromosome, end) that is covered by a block or index node
+type Key = (u32, u32, u32, u32);
+
+struct Block {
+    key: Key,
+    offset: u64,
+    size: u64,
+}
+
+#[derive(Clone, Copy)]
+struct Summary {
+    covered: u64,
+    min: f64,
+    max: f64,
+    sum: f64,
+    sum_squares: f64,
+}
+
+impl Summary {
+    fn new() -> Self {
+        Self {
+            covered: 0,
+            min: f64::INFINITY,
+            max: f64::NEG_INFINITY,
+            sum: 0.0,
+            sum_squares: 0.0,
+        }
+    }
+
+    fn add(&mut self, value: f64, bp: u64) {
+        self.covered += bp;
+        self.min = self.min.min(value);
+        self.max = self.max.max(value);
+        self.sum += value * bp as f64;
+        self.sum_squares += value * value * bp as f64;
+    }
+}
+
+/// Writes a bigWig file of the given sequences
+pub fn write_bigwig<W: Write>(out: &mut W, chroms: &[BigWigChrom]) -> Result<(), Error> {
+    // sequences are identified by their rank in the (sorted) chromosome tree
+    let mut chroms = chroms.to_vec();
+    chroms.sort_by_key(|c| c.0);
+
+    let zoom_levels = get_zoom_levels(&chroms);
+    let total_summary_offset = HEADER_SIZE + ZOOM_HEADER_SIZE * zoom_levels.len();
+    let mut buf: Vec<u8> = vec![0; total_summary_offset + SUMMARY_SIZE];
+    let mut max_block_size = 0;
+
+    let chrom_tree_offset = buf.len();
+    write_chrom_tree(&mut buf, &chroms)?;
+
+    let full_data_offset = buf.len();
+    // number of blocks, set once all blocks are written
+    put(&mut buf, 0u64.to_le_bytes());
+    let mut blocks = Vec::new();
+    for (chrom_id, (_, _, intervals)) in chroms.iter().enumerate() {
+        let chrom_id = chrom_id as u32;
+        for chunk in intervals.chunks(ITEMS_PER_SLOT) {
+            let (start, end) = (chunk[0].0, chunk[chunk.len() - 1].1);
+            let mut data = Vec::with_capacity(24 + 12 * chunk.len());
+            put(&mut data, chrom_id.to_le_bytes());
+            put(&mut data, start.to_le_bytes());
+            put(&mut data, end.to_le_bytes());
+            // item step and span are only used by fixed and variable step sections
+            put(&mut data, 0u32.to_le_bytes());
+            put(&mut data, 0u32.to_le_bytes());
+            // section type bedGraph, reserved byte, and item count
+            put(&mut data, [1u8, 0u8]);
+            put(&mut data, (chunk.len() as u16).to_le_bytes());
+            for (s, e, v) in chunk {
+                put(&mut data, s.to_le_bytes());
+                put(&mut data, e.to_le_bytes());
+                put(&mut data, v.to_le_bytes());
+            }
+            max_block_size = max_block_size.max(data.len());
+            blocks.push(write_block(
+                &mut buf,
+                (chrom_id, start, chrom_id, end),
+                &data,
+            )?);
+        }
+    }
+    patch(
+        &mut buf,
+        full_data_offset,
+        (blocks.len() as u64).to_le_bytes(),
+    );
+    let full_index_offset = buf.len();
+    write_index(&mut buf, &blocks);
+
+    for (i, reduction) in zoom_levels.iter().enumerate() {
+        let data_offset = buf.len();
+        let summaries = summarize(&chroms, *reduction);
+        put(&mut buf, (summaries.len() as u32).to_le_bytes());
+        let mut blocks = Vec::new();
+        for chunk in summaries.chunks(ITEMS_PER_SLOT) {
+            let mut data = Vec::with_capacity(32 * chunk.len());
+            for (chrom_id, start, end, s) in chunk {
+                put(&mut data, chrom_id.to_le_bytes());
+                put(&mut data, start.to_le_bytes());
+                put(&mut data, end.to_le_bytes());
+                put(&mut data, (s.covered as u32).to_le_bytes());
+                put(&mut data, (s.min as f32).to_le_bytes());
+                put(&mut data, (s.max as f32).to_le_bytes());
+                put(&mut data, (s.sum as f32).to_le_bytes());
+                put(&mut data, (s.sum_squares as f32).to_le_bytes());
+            }
+            max_block_size = max_block_size.max(data.len());
+            let (first, last) = (&chunk[0], &chunk[chunk.len() - 1]);
+            blocks.push(write_block(
+                &mut buf,
+                (first.0, first.1, last.0, last.2),
+                &data,
+            )?);
+        }
+        let index_offset = buf.len();
+        write_index(&mut buf, &blocks);
+
+        let mut zoom_header = Vec::with_capacity(ZOOM_HEADER_SIZE);
+        put(&mut zoom_header, reduction.to_le_bytes());
+        put(&mut zoom_header, 0u32.to_le_bytes());
+        put(&mut zoom_header, (data_offset as u64).to_le_bytes());
+        put(&mut zoom_header, (index_offset as u64).to_le_bytes());
+        let offset = HEADER_SIZE + ZOOM_HEADER_SIZE * i;
+        buf[offset..offset + ZOOM_HEADER_SIZE].copy_from_slice(&zoom_header);
+    }
+
+    let mut total = Summary::new();
+    for (_, _, intervals) in &chroms {
+        for (s, e, v) in intervals.iter() {
+            total.add(*v as f64, (e - s) as u64);
+        }
+    }
+    if total.covered == 0 {
+        total.min = 0.0;
+        total.max = 0.0;
+    }
+    let mut summary = Vec::with_capacity(SUMMARY_SIZE);
+    put(&mut summary, total.covered.to_le_bytes());
+    put(&mut summary, total.min.to_le_bytes());
+    put(&mut summary, total.max.to_le_bytes());
+    put(&mut summary, total.sum.to_le_bytes());
+    put(&mut summary, total.sum_squares.to_le_bytes());
+    buf[total_summary_offset..total_summary_offset + SUMMARY_SIZE].copy_from_slice(&summary);
+
+    let mut header = Vec::with_capacity(HEADER_SIZE);
+    put(&mut header, BIGWIG_MAGIC.to_le_bytes());
+    put(&mut header, BIGWIG_VERSION.to_le_bytes());
+    put(&mut header, (zoom_levels.len() as u16).to_le_bytes());
+    put(&mut header, (chrom_tree_offset as u64).to_le_bytes());
+    put(&mut header, (full_data_offset as u64).to_le_bytes());
+    put(&mut header, (full_index_offset as u64).to_le_bytes());
+    // field count and defined field count are only used by bigBed
+    put(&mut header, 0u16.to_le_bytes());
+    put(&mut header, 0u16.to_le_bytes());
+    // no autoSql
+    put(&mut header, 0u64.to_le_bytes());
+    put(&mut header, (total_summary_offset as u64).to_le_bytes());
+    put(&mut header, (max_block_size as u32).to_le_bytes());
+    // no extension header
+    put(&mut header, 0u64.to_le_bytes());
+    buf[..HEADER_SIZE].copy_from_slice(&header);
+
+    // the file is terminated by the magic number, too
+    put(&mut buf, BIGWIG_MAGIC.to_le_bytes());
+    out.write_all(&buf)
+}
+
+fn put<const N: usize>(buf: &mut Vec<u8>, bytes: [u8; N]) {
+    buf.extend_from_slice(&bytes);
+}
+
+fn patch<const N: usize>(buf: &mut [u8], offset: usize, bytes: [u8; N]) {
+    buf[offset..offset + N].copy_from_slice(&bytes);
+}
+
+// zoom levels summarize the values over bins that are a multiple of the average interval length,
+// growing by a constant factor until a single bin covers the longest sequence
+fn get_zoom_levels(chroms: &[BigWigChrom]) -> Vec<u32> {
+    let (count, covered) = chroms
+        .iter()
+        .flat_map(|c| c.2.iter())
+        .fold((0u64, 0u64), |(n, bp), (s, e, _)| {
+            (n + 1, bp + (e - s) as u64)
+        });
+    let max_len = chroms.iter().map(|c| c.1 as u64).max().unwrap_or(0);
+    let mut res = Vec::new();
+    if count == 0 {
+        return res;
+    }
+    let mut reduction = (covered / count * ZOOM_FACTOR).max(1);
+    while res.len() < MAX_ZOOM_LEVELS && reduction < max_len {
+        res.push(reduction as u32);
+        reduction *= ZOOM_FACTOR;
+    }
+    res
+}
+
+// summarizes the values over bins of the given size; each summary spans only positions that are
+// covered by intervals
+fn summarize(chroms: &[BigWigChrom], reduction: u32) -> Vec<(u32, u32, u32, Summary)> {
+    let mut res = Vec::new();
+    for (chrom_id, (_, _, intervals)) in chroms.iter().enumerate() {
+        let chrom_id = chrom_id as u32;
+        let mut current: Option<(u32, u32, u32, Summary)> = None;
+        for &(start, end, value) in intervals.iter() {
+            let mut pos = start;
+            while pos < end {
+                let bin_start = pos / reduction * reduction;
+                let until = end.min(bin_start.saturating_add(reduction));
+                match current.as_mut() {
+                    Some(c) if c.1 / reduction * reduction == bin_start => {
+                        c.2 = until;
+                        c.3.add(value as f64, (until - pos) as u64);
+                    }
+                    _ => {
+                        res.extend(current.take());
+                        let mut summary = Summary::new();
+                        summary.add(value as f64, (until - pos) as u64);
+                        current = Some((chrom_id, pos, until, summary));
+                    }
+                }
+                pos = until;
+            }
+        }
+        res.extend(current);
+    }
+    res
+}
+
+fn write_block(buf: &mut Vec<u8>, key: Key, data: &[u8]) -> Result<Block, Error> {
+    let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(data)?;
+    let compressed = encoder.finish()?;
+    let block = Block {
+        key,
+        offset: buf.len() as u64,
+        size: compressed.len() as u64,
+    };
+    buf.extend_from_slice(&compressed);
+    Ok(block)
+}
+
+// all sequences are stored in a single leaf of the B+ tree
+fn write_chrom_tree(buf: &mut Vec<u8>, chroms: &[BigWigChrom]) -> Result<(), Error> {
+    // all sequences are kept in a single leaf, whose number of items is stored in 16 bits
+    if chroms.len() > u16::MAX as usize {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            format!(
+                "bigWig files can hold at most {} sequences, but {} are given",
+                u16::MAX,
+                chroms.len()
+            ),
+        ));
+    }
+    let key_size = chroms.iter().map(|c| c.0.len()).max().unwrap_or(0).max(1);
+    put(buf, CHROM_TREE_MAGIC.to_le_bytes());
+    put(buf, (chroms.len().max(1) as u32).to_le_bytes());
+    put(buf, (key_size as u32).to_le_bytes());
+    // value size: chromosome id and length
+    put(buf, 8u32.to_le_bytes());
+    put(buf, (chroms.len() as u64).to_le_bytes());
+    put(buf, 0u64.to_le_bytes());
+
+    put(buf, [1u8, 0u8]);
+    put(buf, (chroms.len() as u16).to_le_bytes());
+    for (chrom_id, (name, length, _)) in chroms.iter().enumerate() {
+        buf.extend_from_slice(name.as_bytes());
+        buf.resize(buf.len() + key_size - name.len(), 0);
+        put(buf, (chrom_id as u32).to_le_bytes());
+        put(buf, length.to_le_bytes());
+    }
+    Ok(())
+}
+
+fn span(keys: &[Key]) -> Key {
+    let (first, last) = (keys[0], keys[keys.len() - 1]);
+    (first.0, first.1, last.2, last.3)
+}
+
+fn put_key(buf: &mut Vec<u8>, key: Key) {
+    put(buf, key.0.to_le_bytes());
+    put(buf, key.1.to_le_bytes());
+    put(buf, key.2.to_le_bytes());
+    put(buf, key.3.to_le_bytes());
+}
+
+// writes the R-tree that indexes the given blocks, with the root node first
+fn write_index(buf: &mut Vec<u8>, blocks: &[Block]) {
+    let keys: Vec<Key> = blocks.iter().map(|b| b.key).collect();
+    // nodes of each level, from the leaves up to the root
+    let mut levels: Vec<Vec<Key>> = vec![keys.chunks(BLOCK_SIZE).map(span).collect()];
+    while levels[levels.len() - 1].len() > 1 {
+        let level = levels[levels.len() - 1]
+            .chunks(BLOCK_SIZE)
+            .map(span)
+            .collect();
+        levels.push(level);
+    }
+
+    let (start, end) = match (keys.first(), keys.last()) {
+        (Some(first), Some(last)) => (*first, *last),
+        _ => ((0, 0, 0, 0), (0, 0, 0, 0)),
+    };
+    put(buf, CIR_TREE_MAGIC.to_le_bytes());
+    put(buf, (BLOCK_SIZE as u32).to_le_bytes());
+    put(buf, (blocks.len() as u64).to_le_bytes());
+    put_key(buf, (start.0, start.1, end.2, end.3));
+    let end_file_offset = blocks.last().map(|b| b.offset + b.size).unwrap_or(0);
+    put(buf, end_file_offset.to_le_bytes());
+    put(buf, (ITEMS_PER_SLOT as u32).to_le_bytes());
+    put(buf, 0u32.to_le_bytes());
+
+    if blocks.is_empty() {
+        put(buf, [1u8, 0u8]);
+        put(buf, 0u16.to_le_bytes());
+        return;
+    }
+
+    // nodes are written level by level, so the offset of each node is known in advance
+    let mut offsets: Vec<Vec<u64>> = vec![Vec::new(); levels.len()];
+    let mut pos = buf.len() as u64;
+    for k in (0..levels.len()).rev() {
+        let (children, item_size) = if k == 0 {
+            (blocks.len(), 32)
+        } else {
+            (levels[k - 1].len(), 24)
+        };
+        for j in 0..levels[k].len() {
+            offsets[k].push(pos);
+            let count = BLOCK_SIZE.min(children - j * BLOCK_SIZE);
+            pos += (4 + count * item_size) as u64;
+        }
+    }
+    for k in (0..levels.len()).rev() {
+        if k == 0 {
+            for chunk in blocks.chunks(BLOCK_SIZE) {
+                put(buf, [1u8, 0u8]);
+                put(buf, (chunk.len() as u16).to_le_bytes());
+                for block in chunk {
+                    put_key(buf, block.key);
+                    put(buf, block.offset.to_le_bytes());
+                    put(buf, block.size.to_le_bytes());
+                }
+            }
+        } else {
+            for (j, chunk) in levels[k - 1].chunks(BLOCK_SIZE).enumerate() {
+                put(buf, [0u8, 0u8]);
+                put(buf, (chunk.len() as u16).to_le_bytes());
+                for (i, key) in chunk.iter().enumerate() {
+                    put_key(buf, *key);
+                    put(buf, offsets[k - 1][j * BLOCK_SIZE + i].to_le_bytes());
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::read::ZlibDecoder;
+    use std::convert::TryInto;
+    use std::io::Read;
+
+    fn read_u32(buf: &[u8], offset: usize) -> u32 {
+        u32::from_le_bytes(buf[offset..offset + 4].try_into().unwrap())
+    }
+
+    fn read_u64(buf: &[u8], offset: usize) -> u64 {
+        u64::from_le_bytes(buf[offset..offset + 8].try_into().unwrap())
+    }
+
+    #[test]
+    fn test_write_bigwig() {
+        let intervals: Vec<(u32, u32, f32)> = (0..3000)
+            .map(|i| (i * 10, i * 10 + 10, (i % 7) as f32))
+            .collect();
+        let mut buf = Vec::new();
+        write_bigwig(
+            &mut buf,
+            &[
+                ("chr2", 30000, &intervals[..10]),
+                ("chr1", 40000, &intervals),
+            ],
+        )
+        .unwrap();
+
+        assert_eq!(read_u32(&buf, 0), BIGWIG_MAGIC);
+        assert_eq!(read_u32(&buf, buf.len() - 4), BIGWIG_MAGIC);
+        let zoom_levels = u16::from_le_bytes(buf[6..8].try_into().unwrap()) as usize;
+        assert!(zoom_levels > 0);
+
+        // chromosomes are sorted by name
+        let chrom_tree = read_u64(&buf, 8) as usize;
+        assert_eq!(read_u32(&buf, chrom_tree), CHROM_TREE_MAGIC);
+        assert_eq!(&buf[chrom_tree + 36..chrom_tree + 40], b"chr1");
+        assert_eq!(read_u32(&buf, chrom_tree + 44), 40000);
+
+        // 3 blocks for chr1 and 1 block for chr2
+        let full_data = read_u64(&buf, 16) as usize;
+        assert_eq!(read_u64(&buf, full_data), 4);
+        let full_index = read_u64(&buf, 24) as usize;
+        assert_eq!(read_u32(&buf, full_index), CIR_TREE_MAGIC);
+        assert_eq!(read_u64(&buf, full_index + 8), 4);
+
+        // first leaf item points to the first block of chr1
+        let leaf = full_index + 48;
+        assert_eq!(buf[leaf], 1);
+        let (offset, size) = (
+            read_u64(&buf, leaf + 4 + 16) as usize,
+            read_u64(&buf, leaf + 4 + 24) as usize,
+        );
+        let mut data = Vec::new();
+        ZlibDecoder::new(&buf[offset..offset + size])
+            .read_to_end(&mut data)
+            .unwrap();
+        assert_eq!(data.len(), 24 + 12 * ITEMS_PER_SLOT);
+        assert_eq!(read_u32(&data, 0), 0);
+        assert_eq!(read_u32(&data, 8), 10240);
+        assert_eq!(
+            f32::from_le_bytes(data[24 + 12 * 3 + 8..24 + 12 * 4].try_into().unwrap()),
+            3.0
+        );
+
+        // total summary
+        let total_summary = HEADER_SIZE + ZOOM_HEADER_SIZE * zoom_levels;
+        assert_eq!(read_u64(&buf, total_summary), 30100);
+    }
+
+    #[test]
+    fn test_write_bigwig_too_many_chroms() {
+        let names: Vec<String> = (0..=u16::MAX as usize)
+            .map(|i| format!("chr{}", i))
+            .collect();
+        let chroms: Vec<BigWigChrom> = names.iter().map(|n| (&n[..], 10, &[][..])).collect();
+        let err = write_bigwig(&mut Vec::new(), &chroms).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidInput);
+        // the chromosome tree still fits all sequences of the largest possible bigWig file
+        assert!(write_bigwig(&mut Vec::new(), &chroms[1..]).is_ok());
+    }
+}
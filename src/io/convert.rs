@@ -0,0 +1,174 @@
+/* standard use */
+use std::collections::HashMap;
+use std::io::{BufWriter, Error, ErrorKind, Read, Write};
+use std::process::{Child, ChildStdout, Command, Stdio};
+use std::sync::{Arc, Mutex};
+
+/* external use */
+use memmap2::Mmap;
+use once_cell::sync::Lazy;
+
+// GFA of the graphs converted so far, such that the converter runs only once per graph although
+// the graph is read several times; the temporary files are removed by the OS once unmapped
+static CONVERTED_GRAPHS: Lazy<Mutex<HashMap<String, ConvertedGraph>>> = Lazy::new(Default::default);
+
+/// Binary graph format that is read by streaming the output of an external GFA converter
+pub struct Converter {
+    pub name: &'static str,
+    extensions: &'static [&'static str],
+    // environment variable that can point to the converter binary, if it is not in PATH
+    env_var: &'static str,
+    program: &'static str,
+    args: &'static [&'static str],
+}
+
+const CONVERTERS: &[Converter] = &[
+    Converter {
+        name: "odgi",
+        extensions: &[".og"],
+        env_var: "PANACUS_ODGI",
+        program: "odgi",
+        args: &["view", "--to-gfa", "--idx"],
+    },
+    // Protobuf (.vg), PackedGraph (.pg), and HashGraph (.hg) graphs of the vg toolkit
+    Converter {
+        name: "vg",
+        extensions: &[".vg", ".pg", ".hg"],
+        env_var: "PANACUS_VG",
+        program: "vg",
+        args: &["convert", "--gfa-out"],
+    },
+];
+
+pub fn find_converter(graph_file: &str) -> Option<&'static Converter> {
+    CONVERTERS
+        .iter()
+        .find(|c| c.extensions.iter().any(|ext| graph_file.ends_with(ext)))
+}
+
+impl Converter {
+    fn program(&self) -> String {
+        std::env::var(self.env_var).unwrap_or_else(|_| self.program.to_string())
+    }
+
+    /// GFA of the graph, converted on first use into a temporary file that is reused by all
+    /// further reads of the graph
+    pub fn convert(&'static self, graph_file: &str) -> Result<ConvertedGraph, Error> {
+        // the lock is held during the conversion, such that concurrent reads wait for it
+        let mut converted = CONVERTED_GRAPHS.lock().unwrap();
+        if let Some(gfa) = converted.get(graph_file) {
+            log::debug!("reusing GFA of {} converted before", graph_file);
+            return Ok(gfa.clone());
+        }
+        let file = tempfile::tempfile()?;
+        let mut out = BufWriter::new(&file);
+        std::io::copy(&mut self.spawn(graph_file)?, &mut out)?;
+        out.flush()?;
+        drop(out);
+        // empty files cannot be mapped
+        let gfa = if file.metadata()?.len() == 0 {
+            ConvertedGraph(None)
+        } else {
+            ConvertedGraph(Some(Arc::new(unsafe { Mmap::map(&file)? })))
+        };
+        converted.insert(graph_file.to_string(), gfa.clone());
+        Ok(gfa)
+    }
+
+    fn spawn(&'static self, graph_file: &str) -> Result<ConverterReader, Error> {
+        let program = self.program();
+        log::info!(
+            "converting {} graph {} to GFA with {}..",
+            self.name,
+            graph_file,
+            &program
+        );
+        let mut child = Command::new(&program)
+            .args(self.args)
+            .arg(graph_file)
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::inherit())
+            .spawn()
+            .map_err(|e| {
+                let msg = format!(
+                    "unable to run {} to read {}: {}; install {} or set {} to its path",
+                    &program, graph_file, e, self.name, self.env_var
+                );
+                log::error!("{}", &msg);
+                Error::new(e.kind(), msg)
+            })?;
+        let stdout = child.stdout.take().expect("stdout of converter is piped");
+        Ok(ConverterReader {
+            converter: self,
+            graph_file: graph_file.to_string(),
+            child,
+            stdout,
+            finished: false,
+        })
+    }
+}
+
+/// Memory-mapped GFA written by a converter
+#[derive(Clone)]
+pub struct ConvertedGraph(Option<Arc<Mmap>>);
+
+impl AsRef<[u8]> for ConvertedGraph {
+    fn as_ref(&self) -> &[u8] {
+        self.0.as_ref().map_or(&[], |mmap| &mmap[..])
+    }
+}
+
+/// Reads the GFA written by a converter process, and reports a failure of the process as an
+/// error once its output is exhausted
+struct ConverterReader {
+    converter: &'static Converter,
+    graph_file: String,
+    child: Child,
+    stdout: ChildStdout,
+    finished: bool,
+}
+
+impl Read for ConverterReader {
+    fn read(&mut self, out: &mut [u8]) -> std::io::Result<usize> {
+        let n = self.stdout.read(out)?;
+        if n == 0 && !out.is_empty() && !self.finished {
+            self.finished = true;
+            let status = self.child.wait()?;
+            if !status.success() {
+                let msg = format!(
+                    "{} failed to convert {} to GFA ({})",
+                    self.converter.name, &self.graph_file, status
+                );
+                log::error!("{}", &msg);
+                return Err(Error::new(ErrorKind::InvalidData, msg));
+            }
+        }
+        Ok(n)
+    }
+}
+
+impl Drop for ConverterReader {
+    fn drop(&mut self) {
+        // the graph may not have been read to its end
+        if !self.finished {
+            let _ = self.child.kill();
+            let _ = self.child.wait();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_converter() {
+        assert_eq!(find_converter("graph.og").map(|c| c.name), Some("odgi"));
+        assert_eq!(find_converter("graph.vg").map(|c| c.name), Some("vg"));
+        assert_eq!(find_converter("graph.pg").map(|c| c.name), Some("vg"));
+        assert_eq!(find_converter("graph.hg").map(|c| c.name), Some("vg"));
+        assert!(find_converter("graph.gfa").is_none());
+        assert!(find_converter("graph.gfa.gz").is_none());
+    }
+}
@@ -0,0 +1,219 @@
+/* standard use */
+use std::collections::HashSet;
+use std::io::{BufRead, BufReader, Read};
+
+/// Translates a GFA2 stream on the fly into the GFA1 records that panacus operates on:
+/// segments (S) become GFA1 segments, dovetail edges (E) become links (L), and ordered groups (O)
+/// become paths (P). Fragments (F), gaps (G), and unordered groups (U) do not contribute to the
+/// graph topology or its haplotypes and are skipped.
+pub struct Gfa2Reader {
+    reader: BufReader<Box<dyn Read>>,
+    edge_ids: HashSet<Vec<u8>>,
+    anonymous_groups: usize,
+    skipped: usize,
+    line: Vec<u8>,
+    buf: Vec<u8>,
+    pos: usize,
+}
+
+impl Gfa2Reader {
+    pub fn new(reader: BufReader<Box<dyn Read>>) -> Self {
+        Self {
+            reader,
+            edge_ids: HashSet::new(),
+            anonymous_groups: 0,
+            skipped: 0,
+            line: Vec::new(),
+            buf: Vec::new(),
+            pos: 0,
+        }
+    }
+
+    fn fill_line(&mut self) -> std::io::Result<bool> {
+        loop {
+            self.line.clear();
+            if self.reader.read_until(b'\n', &mut self.line)? == 0 {
+                if self.skipped > 0 {
+                    log::info!(
+                        "skipped {} GFA2 fragment, gap, unordered group, or containment records",
+                        self.skipped
+                    );
+                    self.skipped = 0;
+                }
+                return Ok(false);
+            }
+            while let Some(b'\n') | Some(b'\r') = self.line.last() {
+                self.line.pop();
+            }
+            self.buf.clear();
+            self.pos = 0;
+            if self.translate_line() {
+                self.buf.push(b'\n');
+                return Ok(true);
+            }
+        }
+    }
+
+    // writes the GFA1 counterpart of the current line into the buffer; returns false if the line
+    // has no counterpart
+    fn translate_line(&mut self) -> bool {
+        let cols: Vec<&[u8]> = self.line.split(|&x| x == b'\t').collect();
+        match cols[0] {
+            b"H" => self.buf.extend_from_slice(b"H\tVN:Z:1.0"),
+            // S <sid> <slen> <sequence> <tag>*
+            b"S" if cols.len() >= 4 => {
+                self.buf.extend_from_slice(b"S\t");
+                self.buf.extend_from_slice(cols[1]);
+                self.buf.push(b'\t');
+                if cols[3] == b"*" {
+                    // node lengths are derived from the sequence, so an absent sequence is
+                    // replaced by a placeholder of the declared length
+                    let len = std::str::from_utf8(cols[2])
+                        .ok()
+                        .and_then(|x| x.parse::<usize>().ok())
+                        .unwrap_or(0);
+                    self.buf.resize(self.buf.len() + len, b'N');
+                } else {
+                    self.buf.extend_from_slice(cols[3]);
+                }
+            }
+            // E <eid> <sid1><+|-> <sid2><+|-> <beg1> <end1> <beg2> <end2> <alignment> <tag>*
+            b"E" if cols.len() >= 8 => {
+                if cols[1] != b"*" {
+                    self.edge_ids.insert(cols[1].to_vec());
+                }
+                let (u, v) = match (cols[2].split_last(), cols[3].split_last()) {
+                    (Some(u), Some(v)) => (u, v),
+                    _ => return self.pass_through(),
+                };
+                // dovetail overlaps either reach the end of the first segment or start at the
+                // beginning of the first segment; all others are containments
+                let (first, second) = if cols[5].ends_with(b"$") {
+                    (u, v)
+                } else if cols[4] == b"0" {
+                    (v, u)
+                } else {
+                    self.skipped += 1;
+                    return false;
+                };
+                self.buf.extend_from_slice(b"L\t");
+                self.buf.extend_from_slice(first.1);
+                self.buf.push(b'\t');
+                self.buf.push(*first.0);
+                self.buf.push(b'\t');
+                self.buf.extend_from_slice(second.1);
+                self.buf.push(b'\t');
+                self.buf.push(*second.0);
+                self.buf.extend_from_slice(b"\t*");
+            }
+            // O <oid> <ref>([ ]<ref>)* <tag>*
+            b"O" if cols.len() >= 3 => {
+                self.buf.extend_from_slice(b"P\t");
+                if cols[1] == b"*" {
+                    self.anonymous_groups += 1;
+                    self.buf
+                        .extend_from_slice(format!("group{}", self.anonymous_groups).as_bytes());
+                } else {
+                    self.buf.extend_from_slice(cols[1]);
+                }
+                self.buf.push(b'\t');
+                let mut first = true;
+                for step in cols[2].split(|&x| x == b' ').filter(|x| !x.is_empty()) {
+                    // edges are implied by consecutive segments of the path
+                    if step.len() > 1 && self.edge_ids.contains(&step[..step.len() - 1]) {
+                        continue;
+                    }
+                    if !first {
+                        self.buf.push(b',');
+                    }
+                    self.buf.extend_from_slice(step);
+                    first = false;
+                }
+                self.buf.extend_from_slice(b"\t*");
+            }
+            b"F" | b"G" | b"U" => {
+                self.skipped += 1;
+                return false;
+            }
+            _ => return self.pass_through(),
+        }
+        true
+    }
+
+    // lines that are not understood are handed on unmodified, such that the GFA1 parser can
+    // report them
+    fn pass_through(&mut self) -> bool {
+        self.buf.clear();
+        self.buf.extend_from_slice(&self.line);
+        true
+    }
+}
+
+impl Read for Gfa2Reader {
+    fn read(&mut self, out: &mut [u8]) -> std::io::Result<usize> {
+        if self.pos >= self.buf.len() && !self.fill_line()? {
+            return Ok(0);
+        }
+        let n = usize::min(out.len(), self.buf.len() - self.pos);
+        out[..n].copy_from_slice(&self.buf[self.pos..self.pos + n]);
+        self.pos += n;
+        Ok(n)
+    }
+}
+
+// GFA2 files declare their version in the header, e.g., "H\tVN:Z:2.0"
+pub fn is_gfa2<R: BufRead>(gfa_file: &str, reader: &mut R) -> std::io::Result<bool> {
+    if gfa_file.ends_with(".gfa2") || gfa_file.ends_with(".gfa2.gz") {
+        return Ok(true);
+    }
+    let head = reader.fill_buf()?;
+    let first_line = &head[..memchr::memchr(b'\n', head).unwrap_or(head.len())];
+    Ok(first_line.starts_with(b"H")
+        && first_line
+            .split(|&x| x == b'\t')
+            .any(|x| x.starts_with(b"VN:Z:2")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn translate(gfa2: &str) -> String {
+        let reader: BufReader<Box<dyn Read>> =
+            BufReader::new(Box::new(std::io::Cursor::new(gfa2.as_bytes().to_vec())));
+        let mut out = String::new();
+        Gfa2Reader::new(reader).read_to_string(&mut out).unwrap();
+        out
+    }
+
+    #[test]
+    fn test_gfa2_translation() {
+        let gfa2 = "H\tVN:Z:2.0\n\
+            S\t1\t4\tACGT\n\
+            S\t2\t3\t*\n\
+            E\te1\t1+\t2-\t4$\t4$\t3$\t3$\t0M\n\
+            E\te2\t2+\t1+\t0\t0\t4$\t4$\t0M\n\
+            E\te3\t1+\t2+\t1\t2\t0\t1\t1M\n\
+            F\t1\tread1+\t0\t4$\t0\t4\t4M\n\
+            O\tsample#1#chr1\t1+ e1+ 2-\n\
+            O\t*\t2+ 1+\n";
+        assert_eq!(
+            translate(gfa2),
+            "H\tVN:Z:1.0\n\
+            S\t1\tACGT\n\
+            S\t2\tNNN\n\
+            L\t1\t+\t2\t-\t*\n\
+            L\t1\t+\t2\t+\t*\n\
+            P\tsample#1#chr1\t1+,2-\t*\n\
+            P\tgroup1\t2+,1+\t*\n"
+        );
+    }
+
+    #[test]
+    fn test_is_gfa2() {
+        let mut gfa2 = std::io::Cursor::new(b"H\tVN:Z:2.0\nS\t1\t1\tA\n".to_vec());
+        assert!(is_gfa2("graph.gfa", &mut gfa2).unwrap());
+        let mut gfa1 = std::io::Cursor::new(b"H\tVN:Z:1.0\nS\t1\tA\n".to_vec());
+        assert!(!is_gfa2("graph.gfa", &mut gfa1).unwrap());
+    }
+}
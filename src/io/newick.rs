@@ -0,0 +1,283 @@
+/* standard use */
+use std::fmt;
+use std::io::{BufReader, Read};
+use std::io::{Error, ErrorKind};
+use std::iter::Peekable;
+use std::path::Path;
+use std::str::Chars;
+
+// file extensions of phylogenies in Newick format
+const NEWICK_EXTENSIONS: [&str; 5] = ["nwk", "newick", "nw", "tree", "tre"];
+
+/// Tells whether the file holds a phylogeny in Newick format, judged by its extension or, failing
+/// that, by its first non-whitespace character
+pub fn is_newick_file(file_name: &str) -> Result<bool, Error> {
+    let extension = Path::new(file_name)
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.to_lowercase());
+    if let Some(e) = extension {
+        if NEWICK_EXTENSIONS.contains(&e.as_str()) {
+            return Ok(true);
+        }
+    }
+    let mut buf = [0; 256];
+    let n = super::open_file(file_name)?.read(&mut buf)?;
+    Ok(buf[..n]
+        .iter()
+        .find(|c| !c.is_ascii_whitespace())
+        .map(|c| *c == b'(')
+        .unwrap_or(false))
+}
+
+/// Node of a phylogeny; leaves have no children
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NewickNode {
+    pub label: Option<String>,
+    pub children: Vec<usize>,
+}
+
+/// Phylogeny in Newick format; nodes are stored in preorder, i.e., the root comes first and each
+/// node precedes its descendants, which in turn are ordered as in the file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NewickTree {
+    pub nodes: Vec<NewickNode>,
+}
+
+impl NewickTree {
+    pub fn is_leaf(&self, node: usize) -> bool {
+        self.nodes[node].children.is_empty()
+    }
+
+    /// Labels of the labeled leaves in the order in which they appear in the tree
+    pub fn get_leaves(&self) -> Vec<&str> {
+        self.nodes
+            .iter()
+            .filter(|n| n.children.is_empty())
+            .filter_map(|n| n.label.as_deref())
+            .collect()
+    }
+}
+
+impl fmt::Display for NewickTree {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        if !self.nodes.is_empty() {
+            self.fmt_node(0, formatter)?;
+        }
+        write!(formatter, ";")
+    }
+}
+
+impl NewickTree {
+    fn fmt_node(&self, node: usize, formatter: &mut fmt::Formatter) -> fmt::Result {
+        let children = &self.nodes[node].children;
+        if !children.is_empty() {
+            write!(formatter, "(")?;
+            for (i, child) in children.iter().enumerate() {
+                if i > 0 {
+                    write!(formatter, ",")?;
+                }
+                self.fmt_node(*child, formatter)?;
+            }
+            write!(formatter, ")")?;
+        }
+        match &self.nodes[node].label {
+            Some(label) if label.contains(|c: char| "()[]':;, \t".contains(c)) => {
+                write!(formatter, "'{}'", label.replace('\'', "''"))
+            }
+            Some(label) => write!(formatter, "{}", label),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Parses a phylogeny in Newick format; branch lengths and comments are ignored
+pub fn parse_newick_tree<R: Read>(
+    data: &mut BufReader<R>,
+    file_name: &str,
+) -> Result<NewickTree, Error> {
+    let mut text = String::new();
+    data.read_to_string(&mut text)?;
+    let invalid = |msg: &str| {
+        Error::new(
+            ErrorKind::InvalidData,
+            format!("invalid Newick tree in {}: {}", file_name, msg),
+        )
+    };
+
+    let mut nodes: Vec<NewickNode> = Vec::new();
+    // internal nodes whose closing parenthesis is yet to come, innermost last
+    let mut open: Vec<usize> = Vec::new();
+    // adds a node as child of the innermost open node
+    let add_node = |nodes: &mut Vec<NewickNode>, open: &[usize], label: Option<String>| {
+        let node = nodes.len();
+        match open.last() {
+            Some(&parent) => nodes[parent].children.push(node),
+            None if !nodes.is_empty() => return Err(invalid("tree has more than one root")),
+            None => (),
+        }
+        nodes.push(NewickNode {
+            label,
+            children: Vec::new(),
+        });
+        Ok(node)
+    };
+    // a label following an opening parenthesis or comma creates a leaf, whereas a label following
+    // a closing parenthesis names the internal node that has just been closed
+    let mut expects_leaf = true;
+    let mut closed: Option<usize> = None;
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        let label = match c {
+            '(' => {
+                if !expects_leaf {
+                    return Err(invalid("unexpected opening parenthesis"));
+                }
+                let node = add_node(&mut nodes, &open, None)?;
+                open.push(node);
+                continue;
+            }
+            ',' | ')' => {
+                if open.is_empty() {
+                    return Err(invalid(if c == ')' {
+                        "unbalanced parentheses"
+                    } else {
+                        "comma outside of parentheses"
+                    }));
+                }
+                if expects_leaf {
+                    // unlabeled leaf
+                    add_node(&mut nodes, &open, None)?;
+                }
+                if c == ')' {
+                    closed = open.pop();
+                    expects_leaf = false;
+                } else {
+                    closed = None;
+                    expects_leaf = true;
+                }
+                continue;
+            }
+            ';' => break,
+            '[' => {
+                if !chars.any(|c| c == ']') {
+                    return Err(invalid("unterminated comment"));
+                }
+                continue;
+            }
+            ':' => {
+                while chars.next_if(|c| !",();[".contains(*c)).is_some() {}
+                continue;
+            }
+            c if c.is_whitespace() => continue,
+            '\'' => parse_quoted_label(&mut chars).ok_or_else(|| invalid("unterminated quote"))?,
+            c => {
+                let mut label = c.to_string();
+                while let Some(c) = chars.next_if(|c| !",():;[".contains(*c) && !c.is_whitespace())
+                {
+                    label.push(c);
+                }
+                label
+            }
+        };
+        if expects_leaf {
+            add_node(&mut nodes, &open, Some(label))?;
+            expects_leaf = false;
+        } else if let Some(node) = closed.take() {
+            nodes[node].label = Some(label);
+        } else {
+            return Err(invalid(&format!("unexpected label {}", label)));
+        }
+    }
+    if !open.is_empty() {
+        return Err(invalid("unbalanced parentheses"));
+    }
+    let tree = NewickTree { nodes };
+    if tree.get_leaves().is_empty() {
+        return Err(invalid("tree has no labeled leaves"));
+    }
+    Ok(tree)
+}
+
+/// Parses a phylogeny in Newick format and returns the labels of its leaves in the order in which
+/// they appear in the tree
+pub fn parse_newick_leaves<R: Read>(
+    data: &mut BufReader<R>,
+    file_name: &str,
+) -> Result<Vec<String>, Error> {
+    Ok(parse_newick_tree(data, file_name)?
+        .get_leaves()
+        .into_iter()
+        .map(|l| l.to_string())
+        .collect())
+}
+
+// parses a label in single quotes, where a quote within the label is given by two quotes
+fn parse_quoted_label(chars: &mut Peekable<Chars>) -> Option<String> {
+    let mut label = String::new();
+    loop {
+        match chars.next()? {
+            '\'' if chars.peek() == Some(&'\'') => {
+                chars.next();
+                label.push('\'');
+            }
+            '\'' => return Some(label),
+            c => label.push(c),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(text: &str) -> Result<Vec<String>, Error> {
+        parse_newick_leaves(&mut BufReader::new(text.as_bytes()), "test.nwk")
+    }
+
+    #[test]
+    fn test_parse_newick_leaves() {
+        assert_eq!(
+            parse("((HG00438:0.1,'HG 00621':0.2)anc:0.05,[comment](HG00673,NA18906)90:1);\n")
+                .unwrap(),
+            vec!["HG00438", "HG 00621", "HG00673", "NA18906"]
+        );
+        assert_eq!(parse("('it''s',b);").unwrap(), vec!["it's", "b"]);
+        assert!(parse("((a,b);").is_err());
+        assert!(parse("(a,b));").is_err());
+        assert!(parse("();").is_err());
+        assert!(parse("(a,b)c,d;").is_err());
+    }
+
+    #[test]
+    fn test_newick_tree_to_string() {
+        let text = "((a,'b c')90,'it''s')100;";
+        let tree = parse_newick_tree(&mut BufReader::new(text.as_bytes()), "test.nwk").unwrap();
+        assert_eq!(tree.to_string(), text);
+    }
+
+    #[test]
+    fn test_parse_newick_tree() {
+        let tree = parse_newick_tree(
+            &mut BufReader::new(&b"((a,b)x:0.1,,c)root;"[..]),
+            "test.nwk",
+        )
+        .unwrap();
+        let labels: Vec<Option<&str>> = tree.nodes.iter().map(|n| n.label.as_deref()).collect();
+        assert_eq!(
+            labels,
+            vec![
+                Some("root"),
+                Some("x"),
+                Some("a"),
+                Some("b"),
+                None,
+                Some("c")
+            ]
+        );
+        assert_eq!(tree.nodes[0].children, vec![1, 4, 5]);
+        assert_eq!(tree.nodes[1].children, vec![2, 3]);
+        assert!(tree.is_leaf(4));
+        assert_eq!(tree.get_leaves(), vec!["a", "b", "c"]);
+    }
+}
@@ -0,0 +1,420 @@
+/* standard use */
+use std::collections::HashSet;
+use std::fs::File;
+use std::io::{BufWriter, Error, Write};
+use std::path::{Path, PathBuf};
+
+/* external crate */
+use flate2::{write::DeflateEncoder, Compression, Crc};
+use once_cell::sync::OnceCell;
+
+// file to which the tables of all sections are written as one spreadsheet; none is written if
+// unset
+static XLSX_FILE: OnceCell<PathBuf> = OnceCell::new();
+
+// limit of Excel on the length of sheet names
+const MAX_SHEET_NAME_LEN: usize = 31;
+// limit of Excel on the number of rows of a sheet
+const MAX_SHEET_ROWS: usize = 1 << 20;
+
+/// Writes the tables of all report sections to the given file as an XLSX spreadsheet with one
+/// sheet per section, once the pipeline is finished; can only be set once
+pub fn set_xlsx_file(file: &Path) -> Result<(), Error> {
+    XLSX_FILE.set(file.to_path_buf()).map_err(|_| {
+        Error::new(
+            std::io::ErrorKind::AlreadyExists,
+            "XLSX file has already been set",
+        )
+    })
+}
+
+/// Writes the given tables, each given by its name, its text, and the separator of its fields,
+/// to the XLSX file, if set; comment lines (starting with #) are skipped, and fields that are
+/// numbers are written as such. Tables exceeding the row limit of Excel are split into several
+/// sheets
+pub fn write_xlsx_tables(tables: &[(String, String, char)]) -> Result<(), Error> {
+    let file = match XLSX_FILE.get() {
+        Some(file) => file,
+        None => return Ok(()),
+    };
+    log::info!(
+        "writing {} tables to spreadsheet {}",
+        tables.len(),
+        file.display()
+    );
+    let sheets = get_sheets(tables, MAX_SHEET_ROWS);
+    let mut out = BufWriter::new(File::create(file)?);
+    write_xlsx(&mut out, &sheets)?;
+    out.flush()
+}
+
+// sheets of the tables, each with at most max_rows rows; the rows of larger tables are spread
+// over sheets named by the table and the number of their part
+fn get_sheets(tables: &[(String, String, char)], max_rows: usize) -> Vec<(String, Vec<Vec<&str>>)> {
+    let mut names = HashSet::new();
+    let mut res = Vec::new();
+    for (name, text, sep) in tables {
+        let rows: Vec<Vec<&str>> = text
+            .lines()
+            .filter(|l| !l.starts_with('#') && !l.trim().is_empty())
+            .map(|l| l.split(*sep).map(|x| x.trim()).collect())
+            .collect();
+        if rows.len() <= max_rows {
+            res.push((get_sheet_name(name, &mut names), rows));
+            continue;
+        }
+        let parts = (rows.len() + max_rows - 1) / max_rows;
+        log::warn!(
+            "table {} has {} rows, more than a sheet can hold, splitting it into {} sheets",
+            name,
+            rows.len(),
+            parts
+        );
+        for (i, part) in rows.chunks(max_rows).enumerate() {
+            // the part number is kept if the name is shortened to the limit of Excel
+            let suffix = format!(" part {}", i + 1);
+            let prefix: String = name
+                .chars()
+                .take(MAX_SHEET_NAME_LEN - suffix.len())
+                .collect();
+            let sheet_name = get_sheet_name(&format!("{}{}", prefix, suffix), &mut names);
+            res.push((sheet_name, part.to_vec()));
+        }
+    }
+    res
+}
+
+// name of a sheet without the characters that Excel does not allow, shortened to its limit and
+// made unique among the names given so far
+fn get_sheet_name(name: &str, names: &mut HashSet<String>) -> String {
+    let name: String = name
+        .chars()
+        .map(|c| match c {
+            '[' | ']' | ':' | '*' | '?' | '/' | '\\' => '-',
+            c => c,
+        })
+        .collect();
+    let name = name.trim_matches('\'').trim();
+    let name = if name.is_empty() { "Sheet" } else { name };
+    let mut i = 1;
+    loop {
+        let suffix = if i == 1 {
+            String::new()
+        } else {
+            format!(" ({})", i)
+        };
+        let mut res: String = name
+            .chars()
+            .take(MAX_SHEET_NAME_LEN - suffix.len())
+            .collect();
+        res.push_str(&suffix);
+        // sheet names are compared case-insensitively
+        if names.insert(res.to_lowercase()) {
+            return res;
+        }
+        i += 1;
+    }
+}
+
+/// Writes the sheets, each given by its name and its rows of cells, as XLSX (Office Open XML)
+/// spreadsheet, i.e., a ZIP archive of the XML parts of the workbook
+pub fn write_xlsx<W: Write>(out: &mut W, sheets: &[(String, Vec<Vec<&str>>)]) -> Result<(), Error> {
+    let mut zip = ZipWriter::new(out);
+    let mut content_types = String::from(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"yes\"?>\n\
+         <Types xmlns=\"http://schemas.openxmlformats.org/package/2006/content-types\">\
+         <Default Extension=\"rels\" ContentType=\"application/vnd.openxmlformats-package.relationships+xml\"/>\
+         <Default Extension=\"xml\" ContentType=\"application/xml\"/>\
+         <Override PartName=\"/xl/workbook.xml\" ContentType=\"application/vnd.openxmlformats-officedocument.spreadsheetml.sheet.main+xml\"/>",
+    );
+    let mut workbook = String::from(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"yes\"?>\n\
+         <workbook xmlns=\"http://schemas.openxmlformats.org/spreadsheetml/2006/main\" \
+         xmlns:r=\"http://schemas.openxmlformats.org/officeDocument/2006/relationships\"><sheets>",
+    );
+    let mut workbook_rels = String::from(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"yes\"?>\n\
+         <Relationships xmlns=\"http://schemas.openxmlformats.org/package/2006/relationships\">",
+    );
+    for (i, (name, _)) in sheets.iter().enumerate() {
+        let id = i + 1;
+        content_types.push_str(&format!(
+            "<Override PartName=\"/xl/worksheets/sheet{}.xml\" ContentType=\"application/vnd.openxmlformats-officedocument.spreadsheetml.worksheet+xml\"/>",
+            id
+        ));
+        workbook.push_str(&format!(
+            "<sheet name=\"{}\" sheetId=\"{}\" r:id=\"rId{}\"/>",
+            escape_xml(name),
+            id,
+            id
+        ));
+        workbook_rels.push_str(&format!(
+            "<Relationship Id=\"rId{}\" Type=\"http://schemas.openxmlformats.org/officeDocument/2006/relationships/worksheet\" Target=\"worksheets/sheet{}.xml\"/>",
+            id, id
+        ));
+    }
+    content_types.push_str("</Types>");
+    workbook.push_str("</sheets></workbook>");
+    workbook_rels.push_str("</Relationships>");
+
+    zip.add_file("[Content_Types].xml", content_types.as_bytes())?;
+    zip.add_file(
+        "_rels/.rels",
+        b"<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"yes\"?>\n\
+          <Relationships xmlns=\"http://schemas.openxmlformats.org/package/2006/relationships\">\
+          <Relationship Id=\"rId1\" Type=\"http://schemas.openxmlformats.org/officeDocument/2006/relationships/officeDocument\" Target=\"xl/workbook.xml\"/>\
+          </Relationships>",
+    )?;
+    zip.add_file("xl/workbook.xml", workbook.as_bytes())?;
+    zip.add_file("xl/_rels/workbook.xml.rels", workbook_rels.as_bytes())?;
+    for (i, (_, rows)) in sheets.iter().enumerate() {
+        zip.add_file(
+            &format!("xl/worksheets/sheet{}.xml", i + 1),
+            get_worksheet(rows).as_bytes(),
+        )?;
+    }
+    zip.finish()
+}
+
+fn get_worksheet(rows: &[Vec<&str>]) -> String {
+    let mut res = String::from(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"yes\"?>\n\
+         <worksheet xmlns=\"http://schemas.openxmlformats.org/spreadsheetml/2006/main\"><sheetData>",
+    );
+    for (i, row) in rows.iter().enumerate() {
+        res.push_str(&format!("<row r=\"{}\">", i + 1));
+        for (j, cell) in row.iter().enumerate() {
+            let reference = format!("{}{}", get_column_name(j), i + 1);
+            match cell.parse::<f64>() {
+                Ok(x) if x.is_finite() => {
+                    res.push_str(&format!("<c r=\"{}\"><v>{}</v></c>", reference, x))
+                }
+                _ if cell.is_empty() => (),
+                _ => res.push_str(&format!(
+                    "<c r=\"{}\" t=\"inlineStr\"><is><t xml:space=\"preserve\">{}</t></is></c>",
+                    reference,
+                    escape_xml(cell)
+                )),
+            }
+        }
+        res.push_str("</row>");
+    }
+    res.push_str("</sheetData></worksheet>");
+    res
+}
+
+// name of the column with the given (0-based) index, i.e., A, ..., Z, AA, AB, ...
+fn get_column_name(mut index: usize) -> String {
+    let mut res = Vec::new();
+    loop {
+        res.push(b'A' + (index % 26) as u8);
+        if index < 26 {
+            break;
+        }
+        index = index / 26 - 1;
+    }
+    res.reverse();
+    String::from_utf8(res).unwrap()
+}
+
+// escapes the special characters of XML and drops the control characters it does not allow
+fn escape_xml(text: &str) -> String {
+    let mut res = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => res.push_str("&amp;"),
+            '<' => res.push_str("&lt;"),
+            '>' => res.push_str("&gt;"),
+            '"' => res.push_str("&quot;"),
+            '\t' | '\n' | '\r' => res.push(c),
+            c if c.is_control() => (),
+            c => res.push(c),
+        }
+    }
+    res
+}
+
+// minimal writer of ZIP archives whose files are compressed with deflate; the archive is written
+// sequentially, followed by its central directory
+struct ZipWriter<'a, W: Write> {
+    out: &'a mut W,
+    offset: u64,
+    // name, CRC-32, compressed size, uncompressed size, and offset of the local header of each file
+    entries: Vec<(String, u32, u32, u32, u32)>,
+}
+
+// modification date of all files, i.e., 1980-01-01, such that archives are reproducible
+const ZIP_DATE: u16 = 0x21;
+
+impl<'a, W: Write> ZipWriter<'a, W> {
+    fn new(out: &'a mut W) -> Self {
+        Self {
+            out,
+            offset: 0,
+            entries: Vec::new(),
+        }
+    }
+
+    fn write(&mut self, data: &[u8]) -> Result<(), Error> {
+        self.out.write_all(data)?;
+        self.offset += data.len() as u64;
+        Ok(())
+    }
+
+    fn to_u32(x: u64) -> Result<u32, Error> {
+        if x > u32::MAX as u64 {
+            return Err(Error::new(
+                std::io::ErrorKind::InvalidData,
+                "spreadsheet exceeds the 4 GB limit of ZIP archives",
+            ));
+        }
+        Ok(x as u32)
+    }
+
+    fn add_file(&mut self, name: &str, data: &[u8]) -> Result<(), Error> {
+        let mut crc = Crc::new();
+        crc.update(data);
+        let mut encoder = DeflateEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(data)?;
+        let compressed = encoder.finish()?;
+        let entry = (
+            name.to_string(),
+            crc.sum(),
+            Self::to_u32(compressed.len() as u64)?,
+            Self::to_u32(data.len() as u64)?,
+            Self::to_u32(self.offset)?,
+        );
+
+        let mut header = Vec::new();
+        header.extend_from_slice(&0x0403_4b50u32.to_le_bytes());
+        header.extend_from_slice(&20u16.to_le_bytes()); // version needed to extract
+        header.extend_from_slice(&0u16.to_le_bytes()); // flags
+        header.extend_from_slice(&8u16.to_le_bytes()); // deflate
+        header.extend_from_slice(&0u16.to_le_bytes()); // time
+        header.extend_from_slice(&ZIP_DATE.to_le_bytes());
+        header.extend_from_slice(&entry.1.to_le_bytes());
+        header.extend_from_slice(&entry.2.to_le_bytes());
+        header.extend_from_slice(&entry.3.to_le_bytes());
+        header.extend_from_slice(&(name.len() as u16).to_le_bytes());
+        header.extend_from_slice(&0u16.to_le_bytes()); // extra field length
+        header.extend_from_slice(name.as_bytes());
+        self.write(&header)?;
+        self.write(&compressed)?;
+        self.entries.push(entry);
+        Ok(())
+    }
+
+    fn finish(mut self) -> Result<(), Error> {
+        let start = self.offset;
+        let entries = std::mem::take(&mut self.entries);
+        for (name, crc, compressed_size, size, offset) in &entries {
+            let mut header = Vec::new();
+            header.extend_from_slice(&0x0201_4b50u32.to_le_bytes());
+            header.extend_from_slice(&20u16.to_le_bytes()); // version made by
+            header.extend_from_slice(&20u16.to_le_bytes()); // version needed to extract
+            header.extend_from_slice(&0u16.to_le_bytes()); // flags
+            header.extend_from_slice(&8u16.to_le_bytes()); // deflate
+            header.extend_from_slice(&0u16.to_le_bytes()); // time
+            header.extend_from_slice(&ZIP_DATE.to_le_bytes());
+            header.extend_from_slice(&crc.to_le_bytes());
+            header.extend_from_slice(&compressed_size.to_le_bytes());
+            header.extend_from_slice(&size.to_le_bytes());
+            header.extend_from_slice(&(name.len() as u16).to_le_bytes());
+            header.extend_from_slice(&[0; 8]); // extra, comment, disk, and internal attributes
+            header.extend_from_slice(&0u32.to_le_bytes()); // external attributes
+            header.extend_from_slice(&offset.to_le_bytes());
+            header.extend_from_slice(name.as_bytes());
+            self.write(&header)?;
+        }
+        let size = Self::to_u32(self.offset - start)?;
+        let mut end = Vec::new();
+        end.extend_from_slice(&0x0605_4b50u32.to_le_bytes());
+        end.extend_from_slice(&[0; 4]); // number of this disk and of the disk with the directory
+        end.extend_from_slice(&(entries.len() as u16).to_le_bytes());
+        end.extend_from_slice(&(entries.len() as u16).to_le_bytes());
+        end.extend_from_slice(&size.to_le_bytes());
+        end.extend_from_slice(&Self::to_u32(start)?.to_le_bytes());
+        end.extend_from_slice(&0u16.to_le_bytes()); // comment length
+        self.write(&end)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_get_sheet_name() {
+        let mut names = HashSet::new();
+        assert_eq!(get_sheet_name("Hist", &mut names), "Hist");
+        assert_eq!(get_sheet_name("hist", &mut names), "hist (2)");
+        assert_eq!(get_sheet_name("a/b: c?", &mut names), "a-b- c-");
+        let long = "Growth of a rather long run name";
+        assert_eq!(
+            get_sheet_name(long, &mut names),
+            "Growth of a rather long run nam"
+        );
+        assert_eq!(
+            get_sheet_name(long, &mut names),
+            "Growth of a rather long run (2)"
+        );
+        assert_eq!(get_column_name(0), "A");
+        assert_eq!(get_column_name(25), "Z");
+        assert_eq!(get_column_name(26), "AA");
+        assert_eq!(get_column_name(701), "ZZ");
+        assert_eq!(get_column_name(702), "AAA");
+    }
+
+    #[test]
+    fn test_get_sheets() {
+        let tables = vec![
+            (
+                "Hist".to_string(),
+                "# comment\na\tb\n1\t2\n".to_string(),
+                '\t',
+            ),
+            (
+                "Coverage".to_string(),
+                "node,count\n1,2\n2,3\n\n3,4\n4,5\n".to_string(),
+                ',',
+            ),
+        ];
+        let sheets = get_sheets(&tables, 2);
+        assert_eq!(
+            sheets,
+            vec![
+                ("Hist".to_string(), vec![vec!["a", "b"], vec!["1", "2"]]),
+                (
+                    "Coverage part 1".to_string(),
+                    vec![vec!["node", "count"], vec!["1", "2"]]
+                ),
+                (
+                    "Coverage part 2".to_string(),
+                    vec![vec!["2", "3"], vec!["3", "4"]]
+                ),
+                ("Coverage part 3".to_string(), vec![vec!["4", "5"]]),
+            ]
+        );
+        assert_eq!(get_sheets(&tables, MAX_SHEET_ROWS).len(), 2);
+    }
+
+    #[test]
+    fn test_write_xlsx() {
+        let sheets = vec![(
+            "Info".to_string(),
+            vec![vec!["feature", "value"], vec!["a<b", "12"]],
+        )];
+        let mut out = Vec::new();
+        write_xlsx(&mut out, &sheets).unwrap();
+        assert_eq!(&out[..4], b"PK\x03\x04");
+        assert_eq!(&out[out.len() - 22..out.len() - 18], b"PK\x05\x06");
+        // the archive holds the 4 parts of the workbook and the sheet
+        assert_eq!(
+            u16::from_le_bytes([out[out.len() - 12], out[out.len() - 11]]),
+            5
+        );
+        let worksheet = get_worksheet(&sheets[0].1);
+        assert!(worksheet.contains("<c r=\"B2\"><v>12</v></c>"));
+        assert!(worksheet.contains("<t xml:space=\"preserve\">a&lt;b</t>"));
+    }
+}
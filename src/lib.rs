@@ -1,27 +1,44 @@
 /* private use */
 pub mod analyses;
 mod analysis_parameter;
+mod bench;
+mod cache;
 mod commands;
+mod daemon;
+mod estimate;
 pub mod graph_broker;
 mod html_report;
 mod io;
+mod lint;
+mod progress;
+mod run_summary;
+mod serve;
 mod util;
 
 use env_logger::Builder;
 use log::LevelFilter;
 use std::io::Read;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
 use std::{fmt::Debug, io::Write};
 use thiserror::Error;
 
 use analyses::Analysis;
 use analyses::ConstructibleAnalysis;
-use analysis_parameter::{AnalysisParameter, AnalysisRun, Task};
+use analysis_parameter::{AlphaMethod, AnalysisParameter, AnalysisRun, Task};
 use clap::{Arg, ArgAction, ArgMatches, Command};
-use graph_broker::{GraphBroker, GraphState};
-use html_report::AnalysisSection;
+use graph_broker::GraphBroker;
+use html_report::{AnalysisSection, HtmlOutput, Provenance};
+use util::StageMeter;
+
+pub use daemon::{load_graph, query_count_type, run_query};
+// file system of the WebAssembly build, see panacus-wasm
+#[cfg(target_arch = "wasm32")]
+pub use io::{add_in_memory_file, clear_in_memory_files};
 
 use std::fs::File;
 use std::io::BufReader;
+use std::time::Instant;
 
 use shadow_rs::shadow;
 
@@ -72,29 +89,81 @@ fn set_number_of_threads(params: &ArgMatches) {
 }
 
 fn set_verbosity(args: &ArgMatches) {
+    let mut builder = Builder::new();
     if args.get_flag("verbose") {
-        Builder::new().filter_level(LevelFilter::Debug).init();
+        builder.filter_level(LevelFilter::Debug);
     } else {
-        Builder::new().filter_level(LevelFilter::Info).init();
+        builder.filter_level(LevelFilter::Info);
+    }
+    if args.get_one::<String>("log_format").map(|x| x.as_str()) == Some("json") {
+        // one JSON object per line, so that workflow managers can parse the log stream
+        let start = std::time::Instant::now();
+        builder.format(move |buf, record| {
+            let event = serde_json::json!({
+                "timestamp": buf.timestamp_millis().to_string(),
+                "elapsed_secs": start.elapsed().as_secs_f64(),
+                "level": record.level().to_string(),
+                "stage": record.target(),
+                "message": record.args().to_string(),
+            });
+            writeln!(buf, "{}", event)
+        });
     }
+    // warnings are also kept for the run summary
+    let logger = builder.build();
+    log::set_max_level(logger.filter());
+    log::set_boxed_logger(Box::new(run_summary::WarningRecorder::new(logger)))
+        .expect("logger is only set once");
 }
 
-pub fn run_cli() -> Result<(), anyhow::Error> {
-    let mut out = std::io::BufWriter::new(std::io::stdout());
+fn get_output_writer(args: &ArgMatches) -> anyhow::Result<std::io::BufWriter<Box<dyn Write>>> {
+    let stdout = std::io::stdout();
+    let writer: Box<dyn Write> = match args.get_one::<String>("compress").map(|x| x.as_str()) {
+        Some("gzip") => Box::new(flate2::write::GzEncoder::new(
+            stdout,
+            flate2::Compression::default(),
+        )),
+        // zstd is a C library that is not built for wasm32
+        #[cfg(not(target_arch = "wasm32"))]
+        Some("zstd") => Box::new(zstd::Encoder::new(stdout, 0)?.auto_finish()),
+        _ => Box::new(stdout),
+    };
+    Ok(std::io::BufWriter::new(writer))
+}
 
+pub fn run_cli() -> Result<(), anyhow::Error> {
     // read parameters and store them in memory
     // let params = cli::read_params();
     let args = Command::new("panacus")
         .subcommand(commands::render::get_subcommand())
         .subcommand(commands::report::get_subcommand())
+        .subcommand(commands::serve::get_subcommand())
+        .subcommand(commands::daemon::get_subcommand())
+        .subcommand(commands::bench::get_subcommand())
         .subcommand(commands::hist::get_subcommand())
         .subcommand(commands::growth::get_subcommand())
-        // .subcommand(commands::histgrowth::get_subcommand())
+        .subcommand(commands::histgrowth::get_subcommand())
+        .subcommand(commands::index::get_subcommand())
+        .subcommand(commands::groups::get_subcommand())
         .subcommand(commands::info::get_subcommand())
+        .subcommand(commands::duplicates::get_subcommand())
+        .subcommand(commands::kmer_comparison::get_subcommand())
         .subcommand(commands::ordered_histgrowth::get_subcommand())
         .subcommand(commands::table::get_subcommand())
         .subcommand(commands::node_distribution::get_subcommand())
         .subcommand(commands::similarity::get_subcommand())
+        .subcommand(commands::window_similarity::get_subcommand())
+        .subcommand(commands::chromosomal::get_subcommand())
+        .subcommand(commands::differential::get_subcommand())
+        .subcommand(commands::phylo_growth::get_subcommand())
+        .subcommand(commands::base_composition::get_subcommand())
+        .subcommand(commands::node_lengths::get_subcommand())
+        .subcommand(commands::node_coverage::get_subcommand())
+        .subcommand(commands::depth::get_subcommand())
+        .subcommand(commands::paths::get_subcommand())
+        .subcommand(commands::size_classes::get_subcommand())
+        .subcommand(commands::node_multiplicity::get_subcommand())
+        .subcommand(commands::coverage_colors::get_subcommand())
         .subcommand_required(true)
         .arg(
             Arg::new("threads")
@@ -106,6 +175,101 @@ pub fn run_cli() -> Result<(), anyhow::Error> {
                 .global(true)
                 .help("Set the number of threads used (default: use all threads)"),
         )
+        .arg(
+            Arg::new("seed")
+                .long("seed")
+                .action(ArgAction::Set)
+                .value_name("SEED")
+                .default_value("0")
+                .value_parser(clap::value_parser!(u64))
+                .global(true)
+                .help("Seed of all randomized computations (e.g., permutations and bootstrap replicates), such that their results are reproducible"),
+        )
+        .arg(
+            Arg::new("parallel_runs")
+                .long("parallel-runs")
+                .action(ArgAction::Set)
+                .value_name("COUNT")
+                .default_value("1")
+                .value_parser(clap::builder::RangedU64ValueParser::<usize>::new().range(1..))
+                .global(true)
+                .help("Execute the runs of a YAML config over up to COUNT different graphs in parallel, sharing the threads; each holds its graph in memory, and peak memory is then that of the whole process"),
+        )
+        .arg(
+            Arg::new("memory_budget")
+                .long("memory-budget")
+                .action(ArgAction::Set)
+                .value_name("MB")
+                .value_parser(clap::builder::RangedU64ValueParser::<u64>::new().range(1..))
+                .global(true)
+                .help("Execute fewer runs in parallel (see --parallel-runs) if they are estimated to take more than MB megabytes of memory together; runs whose memory cannot be estimated take the whole budget"),
+        )
+        .arg(
+            Arg::new("cache")
+                .long("cache")
+                .action(ArgAction::SetTrue)
+                .global(true)
+                .help("Cache histograms and growth curves on disk (in $XDG_CACHE_HOME/panacus or ~/.cache/panacus, up to 1 GiB) and reuse those cached by previous runs on the same graph, subset, grouping, etc.; runs that only report histograms and growth curves then skip parsing the graph"),
+        )
+        .arg(
+            Arg::new("compress")
+                .long("compress")
+                .action(ArgAction::Set)
+                .value_name("FORMAT")
+                .value_parser(["gzip", "zstd"])
+                .global(true)
+                .help("Compress the output (tables, JSON or HTML report) with the given format"),
+        )
+        .arg(
+            Arg::new("spill_dir")
+                .long("spill-dir")
+                .action(ArgAction::Set)
+                .value_name("DIR")
+                .global(true)
+                .help("Keep item tables and group abaci in memory-mapped temporary files in the given directory instead of in memory, such that huge graphs can be processed (slower)"),
+        )
+        .arg(
+            Arg::new("write_path_sets")
+                .long("write-path-sets")
+                .action(ArgAction::Set)
+                .value_name("FILE")
+                .global(true)
+                .help("Write the paths/path coordinates of subset and exclude lists that combine several files (e.g., --subset a.txt+b.txt) to the given file in BED format, such that they can be verified"),
+        )
+        .arg(
+            Arg::new("run_summary")
+                .long("run-summary")
+                .action(ArgAction::Set)
+                .value_name("FILE")
+                .global(true)
+                .help("Write a machine-readable summary of the run (inputs, parameters, output files, warnings, and runtimes of its tasks) in JSON format to the given file, also if the run fails"),
+        )
+        .arg(
+            Arg::new("progress")
+                .long("progress")
+                .action(ArgAction::Set)
+                .value_name("FILE")
+                .global(true)
+                .help("Write progress events (tasks started and finished, percent complete) as newline-delimited JSON to the given file, e.g., /dev/fd/3 for a dedicated file descriptor, such that workflow managers can show progress bars"),
+        )
+        .arg(
+            Arg::new("xlsx")
+                .long("xlsx")
+                .action(ArgAction::Set)
+                .value_name("FILE")
+                .global(true)
+                .help("Also write the tables of all analyses (and of custom CSV/TSV sections) to the given file as an XLSX spreadsheet, with one sheet per section; tables exceeding the row limit of Excel are split into several sheets"),
+        )
+        .arg(
+            Arg::new("log_format")
+                .long("log-format")
+                .action(ArgAction::Set)
+                .value_name("FORMAT")
+                .value_parser(["text", "json"])
+                .default_value("text")
+                .global(true)
+                .help("Format of log messages written to stderr"),
+        )
         .arg(
             Arg::new("verbose")
                 .short('v')
@@ -119,36 +283,131 @@ pub fn run_cli() -> Result<(), anyhow::Error> {
 
     set_verbosity(&args);
     set_number_of_threads(&args);
+    util::set_seed(*args.get_one::<u64>("seed").unwrap());
+    util::set_parallel_runs(*args.get_one::<usize>("parallel_runs").unwrap());
+    util::set_memory_budget(
+        args.get_one::<u64>("memory_budget")
+            .map(|mb| mb * 1024 * 1024),
+    );
+    cache::set_enabled(args.get_flag("cache"));
+    if let Some(dir) = args.get_one::<String>("spill_dir") {
+        graph_broker::set_spill_dir(std::path::Path::new(dir))?;
+    }
+    if let Some(file) = args.get_one::<String>("write_path_sets") {
+        graph_broker::set_path_sets_file(std::path::Path::new(file))?;
+        run_summary::add_output_file(file);
+    }
+    if let Some(file) = args.get_one::<String>("progress") {
+        progress::set_progress_file(file)?;
+    }
+    if let Some(file) = args.get_one::<String>("xlsx") {
+        io::set_xlsx_file(std::path::Path::new(file))?;
+        run_summary::add_output_file(file);
+    }
+    let mut out = get_output_writer(&args)?;
 
     let mut instructions: Vec<AnalysisRun> = Vec::new();
     let mut shall_write_html = false;
     let mut dry_run = false;
     let mut json = false;
     let mut config_content = "EMPTY".to_string();
+    let mut theme = None;
+    let mut html_output = HtmlOutput::Report;
 
     if let Some(args) = args.subcommand_matches("render") {
+        let theme = read_theme(args)?;
         let json_files: Vec<String> = args
             .get_many::<String>("json_files")
             .unwrap()
             .cloned()
             .collect();
-        let mut full_report = Vec::new();
+        let mut reports = Vec::new();
         for file_path in &json_files {
             let file = File::open(file_path)?;
             let reader = BufReader::new(file);
 
             // Read the JSON contents of the file as an instance of `User`.
             let report: Vec<AnalysisSection> = serde_json::from_reader(reader)?;
-            full_report.extend(report);
+            reports.push((file_path.clone(), report));
         }
-        let mut registry = handlebars::Handlebars::new();
-        let report_text = AnalysisSection::generate_report(
+        let full_report = AnalysisSection::merge_sections(reports);
+        get_html_output(args).write(
             full_report,
-            &mut registry,
             &json_files[0],
             "-- GENERATED VIA RENDER --",
+            theme.as_deref(),
+            &mut out,
+        )?;
+        return Ok(());
+    }
+
+    if let Some(args) = args.subcommand_matches("serve") {
+        let yaml_file = args
+            .get_one::<String>("yaml_file")
+            .expect("serve subcommand has yaml config");
+        let port = *args
+            .get_one::<u16>("port")
+            .expect("serve subcommand has port");
+        return serve::serve(yaml_file, port, read_theme(args)?);
+    }
+
+    if let Some(args) = args.subcommand_matches("daemon") {
+        let gfa_file = args
+            .get_one::<String>("gfa_file")
+            .expect("daemon subcommand has gfa file");
+        let port = *args
+            .get_one::<u16>("port")
+            .expect("daemon subcommand has port");
+        return daemon::daemon(gfa_file, port);
+    }
+
+    if let Some(args) = args.subcommand_matches("bench") {
+        let gfa_file = args
+            .get_one::<String>("gfa_file")
+            .expect("bench subcommand has gfa file");
+        let count_type = *args
+            .get_one::<util::CountType>("count")
+            .expect("bench subcommand has count type");
+        let thread_counts = commands::bench::get_thread_counts(args)?;
+        bench::bench(gfa_file, count_type, &thread_counts, &mut out)?;
+        return Ok(());
+    }
+
+    if let Some(args) = args.subcommand_matches("index") {
+        let gfa_file = args
+            .get_one::<String>("gfa_file")
+            .expect("index subcommand has gfa file");
+        let index_file = match args.get_one::<String>("output") {
+            Some(file) => file.to_owned(),
+            None => commands::index::get_default_index_file(gfa_file),
+        };
+        graph_broker::write_graph_index(gfa_file, &index_file)?;
+        return Ok(());
+    }
+
+    if let Some(args) = args.subcommand_matches("groups") {
+        let gfa_file = args
+            .get_one::<String>("gfa_file")
+            .expect("groups subcommand has gfa file");
+        let file = args
+            .get_one::<String>("file")
+            .expect("groups subcommand has file");
+        let grouping = if args.get_flag("groupby-sample") {
+            Some(analysis_parameter::Grouping::Sample)
+        } else if args.get_flag("groupby-haplotype") {
+            Some(analysis_parameter::Grouping::Haplotype)
+        } else {
+            args.get_one::<String>("groupby")
+                .map(|g| analysis_parameter::Grouping::Custom(g.clone()))
+        };
+        lint::lint_list(
+            gfa_file,
+            file,
+            args.get_one::<lint::ListKind>("kind").copied(),
+            grouping.as_ref(),
+            args.get_one::<String>("output").map(|o| o.as_str()),
+            &mut out,
         )?;
-        writeln!(&mut out, "{report_text}")?;
         return Ok(());
     }
 
@@ -160,19 +419,50 @@ pub fn run_cli() -> Result<(), anyhow::Error> {
         {
             if args.get_one::<String>("subset").is_some()
                 || args.get_one::<String>("exclude").is_some()
-                || args.get_one::<String>("grouping").is_some()
+                || args.get_one::<String>("exclude-reference").is_some()
+                || args.get_one::<String>("groupby").is_some()
                 || args.get_flag("groupby-sample")
                 || args.get_flag("groupby-haplotype")
+                || args.get_one::<String>("groupby-regex").is_some()
             {
-                panic!("subset, exclude and groupby can only be used in graph mode (with a .gfa or .gfa.gz file)");
+                anyhow::bail!("subset, exclude and groupby can only be used in graph mode (with a .gfa or .gfa.gz file)");
+            }
+            if args.get_many::<f64>("percentiles").is_some() {
+                anyhow::bail!("percentiles of the growth can only be calculated in graph mode (with a .gfa or .gfa.gz file), since they need the coverage of each countable by each genome");
+            }
+            if args.get_many::<usize>("rarefaction").is_some() {
+                anyhow::bail!("rarefaction can only be calculated in graph mode (with a .gfa or .gfa.gz file), since it needs the coverage of each countable by each genome");
+            }
+            if args.get_one::<String>("stratify").is_some() {
+                anyhow::bail!("stratified growth can only be calculated in graph mode (with a .gfa or .gfa.gz file), since it needs the coverage of each countable by each genome");
             }
             let coverage = args.get_one::<String>("coverage").cloned();
             let quorum = args.get_one::<String>("quorum").cloned();
             let add_hist = args.get_flag("hist");
+            let extrapolate = args.get_one::<usize>("extrapolate").copied();
+            let alpha_method = args
+                .get_one::<AlphaMethod>("alpha_method")
+                .expect("growth subcommand has alpha method")
+                .to_owned();
+            let alpha_from = args.get_one::<usize>("alpha-from").copied();
+            let alpha_to = args.get_one::<usize>("alpha-to").copied();
             let parameter = AnalysisParameter::Growth {
                 coverage,
                 quorum,
                 add_hist,
+                extrapolate,
+                alpha_method,
+                alpha_from,
+                alpha_to,
+                long: args.get_flag("long"),
+                percentiles: Vec::new(),
+                permutations: *args
+                    .get_one::<usize>("permutations")
+                    .expect("growth subcommand has number of permutations"),
+                rarefaction: Vec::new(),
+                stratify: None,
+                normalize: args.get_flag("normalize"),
+                count_type: util::CountType::Node,
             };
             let mut growth = analyses::growth::Growth::from_parameter(parameter);
             let table = growth.generate_table_from_hist(
@@ -190,6 +480,8 @@ pub fn run_cli() -> Result<(), anyhow::Error> {
         if let Some(report_matches) = args.subcommand_matches("report") {
             dry_run = report_matches.get_flag("dry_run");
             json = report_matches.get_flag("json");
+            theme = read_theme(report_matches)?;
+            html_output = get_html_output(report_matches);
             let config = report_matches
                 .get_one::<String>("yaml_file")
                 .expect("Contains required yaml config")
@@ -206,12 +498,18 @@ pub fn run_cli() -> Result<(), anyhow::Error> {
     if let Some(growth) = commands::growth::get_instructions(&args) {
         instructions.extend(growth?);
     }
-    // if let Some(histgrowth) = commands::histgrowth::get_instructions(&args) {
-    //     instructions.extend(histgrowth?);
-    // }
+    if let Some(histgrowth) = commands::histgrowth::get_instructions(&args) {
+        instructions.extend(histgrowth?);
+    }
     if let Some(info) = commands::info::get_instructions(&args) {
         instructions.extend(info?);
     }
+    if let Some(duplicates) = commands::duplicates::get_instructions(&args) {
+        instructions.extend(duplicates?);
+    }
+    if let Some(kmer_comparison) = commands::kmer_comparison::get_instructions(&args) {
+        instructions.extend(kmer_comparison?);
+    }
     if let Some(ordered_histgrowth) = commands::ordered_histgrowth::get_instructions(&args) {
         instructions.extend(ordered_histgrowth?);
     }
@@ -224,21 +522,81 @@ pub fn run_cli() -> Result<(), anyhow::Error> {
     if let Some(similarity) = commands::similarity::get_instructions(&args) {
         instructions.extend(similarity?);
     }
+    if let Some(window_similarity) = commands::window_similarity::get_instructions(&args) {
+        instructions.extend(window_similarity?);
+    }
+    if let Some(chromosomal) = commands::chromosomal::get_instructions(&args) {
+        instructions.extend(chromosomal?);
+    }
+    if let Some(differential) = commands::differential::get_instructions(&args) {
+        instructions.extend(differential?);
+    }
+    if let Some(phylo_growth) = commands::phylo_growth::get_instructions(&args) {
+        instructions.extend(phylo_growth?);
+    }
+    if let Some(base_composition) = commands::base_composition::get_instructions(&args) {
+        instructions.extend(base_composition?);
+    }
+    if let Some(node_lengths) = commands::node_lengths::get_instructions(&args) {
+        instructions.extend(node_lengths?);
+    }
+    if let Some(node_coverage) = commands::node_coverage::get_instructions(&args) {
+        instructions.extend(node_coverage?);
+    }
+    if let Some(depth) = commands::depth::get_instructions(&args) {
+        instructions.extend(depth?);
+    }
+    if let Some(paths) = commands::paths::get_instructions(&args) {
+        instructions.extend(paths?);
+    }
+    if let Some(size_classes) = commands::size_classes::get_instructions(&args) {
+        instructions.extend(size_classes?);
+    }
+    if let Some(node_multiplicity) = commands::node_multiplicity::get_instructions(&args) {
+        instructions.extend(node_multiplicity?);
+    }
+    if let Some(coverage_colors) = commands::coverage_colors::get_instructions(&args) {
+        instructions.extend(coverage_colors?);
+    }
 
+    let runs = instructions.clone();
     let instructions: Vec<Task> = get_tasks(instructions)?;
     log::info!("{:?}", instructions);
 
     // ride on!
     if !dry_run {
-        execute_pipeline(
+        let start = Instant::now();
+        if let HtmlOutput::Directory(path) | HtmlOutput::Plots(path) | HtmlOutput::Pdf(path) =
+            &html_output
+        {
+            run_summary::add_output_file(path);
+        }
+        let mut provenance = Provenance::new(&config_content);
+        let res = execute_pipeline_with_provenance(
             instructions,
             &mut out,
             shall_write_html,
             json,
-            &config_content,
-        )?;
+            theme.as_deref(),
+            &html_output,
+            &mut provenance,
+        );
+        progress::pipeline_finished(res.is_ok(), start.elapsed().as_secs_f64());
+        let summary = match args.get_one::<String>("run_summary") {
+            Some(file) => run_summary::write_run_summary(
+                file,
+                &provenance,
+                &runs,
+                &res,
+                start.elapsed().as_secs_f64(),
+            ),
+            None => Ok(()),
+        };
+        run_summary::first_error(res, summary)?;
     } else {
         println!("{:#?}", instructions);
+        let estimates = estimate::estimate_tasks(&instructions, rayon::current_num_threads());
+        write!(out, "{}", estimate::get_estimates_table(&estimates))?;
     }
 
     // clean up & close down
@@ -252,58 +610,283 @@ pub enum ConfigParseError {
     NameNotFound { name: String },
 }
 
+fn read_theme(args: &ArgMatches) -> anyhow::Result<Option<String>> {
+    match args.get_one::<String>("theme") {
+        Some(file) => {
+            let mut theme = String::new();
+            BufReader::new(File::open(file)?).read_to_string(&mut theme)?;
+            // fail early instead of producing a report with broken javascript
+            serde_json::from_str::<serde_json::Value>(&theme).map_err(|e| {
+                anyhow::anyhow!("theme {} is not a valid Vega config (JSON): {}", file, e)
+            })?;
+            Ok(Some(theme))
+        }
+        None => Ok(None),
+    }
+}
+
+fn get_html_output(args: &ArgMatches) -> HtmlOutput {
+    if let Some(dir) = args.get_one::<String>("plot") {
+        HtmlOutput::Plots(dir.to_owned())
+    } else if let Some(dir) = args.get_one::<String>("report_dir") {
+        HtmlOutput::Directory(dir.to_owned())
+    } else if let Some(file) = args.get_one::<String>("pdf") {
+        HtmlOutput::Pdf(file.to_owned())
+    } else if args.get_flag("print") {
+        HtmlOutput::Print
+    } else {
+        HtmlOutput::Report
+    }
+}
+
 fn get_tasks(instructions: Vec<AnalysisRun>) -> anyhow::Result<Vec<Task>> {
     let tasks = AnalysisRun::convert_to_tasks(instructions);
     Ok(tasks)
 }
 
 pub fn execute_pipeline<W: Write>(
-    mut instructions: Vec<Task>,
+    instructions: Vec<Task>,
     out: &mut std::io::BufWriter<W>,
     shall_write_html: bool,
     json: bool,
     config_content: &str,
+    theme: Option<&str>,
+    html_output: &HtmlOutput,
+) -> anyhow::Result<()> {
+    execute_pipeline_with_provenance(
+        instructions,
+        out,
+        shall_write_html,
+        json,
+        theme,
+        html_output,
+        &mut Provenance::new(config_content),
+    )
+}
+
+// runs the tasks, recording inputs and runtimes in the provenance, which is thus also available
+// if the pipeline fails
+fn execute_pipeline_with_provenance<W: Write>(
+    instructions: Vec<Task>,
+    out: &mut std::io::BufWriter<W>,
+    shall_write_html: bool,
+    json: bool,
+    theme: Option<&str>,
+    html_output: &HtmlOutput,
+    provenance: &mut Provenance,
 ) -> anyhow::Result<()> {
     if instructions.is_empty() {
         log::warn!("No instructions supplied");
         return Ok(());
     }
+    progress::pipeline_started(instructions.len());
+    // without report, only the table of the last analysis is written
+    let generate_table = !json && !shall_write_html;
+    let chains = split_by_graph(instructions);
+    let mut parallel_runs = util::get_parallel_runs().min(chains.len());
+    if let (true, Some(budget)) = (parallel_runs > 1, util::get_memory_budget()) {
+        let threads = rayon::current_num_threads();
+        let memory: Vec<Option<u64>> = chains
+            .iter()
+            .map(|chain| estimate::estimate_peak_memory(chain, threads))
+            .collect();
+        parallel_runs = estimate::get_runs_within_budget(&memory, parallel_runs, budget);
+    }
+    let results = if parallel_runs > 1 {
+        log::info!(
+            "executing the runs over {} graphs, {} at a time",
+            chains.len(),
+            parallel_runs
+        );
+        // the peak memory of concurrent runs cannot be told apart, hence it is reported once for
+        // all of them
+        let meter = StageMeter::start("parallel runs");
+        util::set_stage_peak_rss(false);
+        let results = execute_chains_in_parallel(chains, generate_table, parallel_runs);
+        util::set_stage_peak_rss(true);
+        provenance.add_stage(meter.finish());
+        results
+    } else {
+        let n = chains.len();
+        let mut results = Vec::new();
+        for (i, chain) in chains.into_iter().enumerate() {
+            let mut chain_provenance = Provenance::default();
+            let res = execute_tasks(chain, generate_table && i + 1 == n, &mut chain_provenance);
+            let failed = res.is_err();
+            results.push((chain_provenance, res));
+            if failed {
+                break;
+            }
+        }
+        results
+    };
+    let mut report = Vec::new();
+    let mut table = None;
+    for (chain_provenance, res) in results {
+        provenance.merge(chain_provenance);
+        let (sections, chain_table) = res?;
+        report.extend(sections);
+        table = chain_table;
+    }
+    io::write_xlsx_tables(&get_section_tables(&report))?;
+    if json || shall_write_html {
+        report.extend(AnalysisSection::generate_provenance_section(provenance)?);
+    }
+    if json {
+        let json_text = serde_json::to_string_pretty(&report)?;
+        writeln!(out, "{json_text}")?;
+    } else if shall_write_html {
+        html_output.write(
+            report,
+            "<Placeholder Filename>",
+            &provenance.config,
+            theme,
+            out,
+        )?;
+    } else if let Some(table) = table {
+        writeln!(out, "{table}")?;
+    }
+    Ok(())
+}
+
+// tables of the sections, named after their analysis (or custom section) and run, with the
+// separator of their fields
+fn get_section_tables(report: &[AnalysisSection]) -> Vec<(String, String, char)> {
+    report
+        .iter()
+        .filter_map(|section| {
+            let table = section.table.as_ref()?.trim_matches('`');
+            let (name, sep) = if section.analysis == "Custom" {
+                let tsv = table.lines().next().map_or(false, |l| l.contains('\t'));
+                (section.countable.clone(), if tsv { '\t' } else { ',' })
+            } else {
+                (format!("{} {}", section.analysis, section.run_name), '\t')
+            };
+            Some((name.trim().to_string(), table.to_string(), sep))
+        })
+        .collect()
+}
+
+// tasks of consecutive runs over the same graph, which share the loaded graph; runs over
+// different graphs share no state and can be executed in parallel
+fn split_by_graph(tasks: Vec<Task>) -> Vec<Vec<Task>> {
+    let mut chains: Vec<Vec<Task>> = Vec::new();
+    let mut current_graph: Option<String> = None;
+    for task in tasks {
+        if let Task::GraphStateChange { graph, .. } = &task {
+            if current_graph.as_ref() != Some(graph) {
+                current_graph = Some(graph.clone());
+                chains.push(Vec::new());
+            }
+        }
+        match chains.last_mut() {
+            Some(chain) => chain.push(task),
+            None => chains.push(vec![task]),
+        }
+    }
+    chains
+}
+
+type ChainResult = (
+    Provenance,
+    anyhow::Result<(Vec<AnalysisSection>, Option<String>)>,
+);
+
+// at most parallel_runs chains are executed at a time, each by a job that takes the next chain
+// once it is done with its previous one; the results are in the order of the chains
+fn execute_chains_in_parallel(
+    chains: Vec<Vec<Task>>,
+    generate_table: bool,
+    parallel_runs: usize,
+) -> Vec<ChainResult> {
+    let n = chains.len();
+    let chains: Vec<Mutex<Option<Vec<Task>>>> =
+        chains.into_iter().map(|x| Mutex::new(Some(x))).collect();
+    let results: Vec<Mutex<Option<ChainResult>>> = (0..n).map(|_| Mutex::new(None)).collect();
+    let next = AtomicUsize::new(0);
+    rayon::scope(|s| {
+        for _ in 0..parallel_runs {
+            s.spawn(|_| loop {
+                let i = next.fetch_add(1, Ordering::Relaxed);
+                if i >= n {
+                    break;
+                }
+                let chain = chains[i]
+                    .lock()
+                    .unwrap()
+                    .take()
+                    .expect("chain is taken once");
+                let mut provenance = Provenance::default();
+                let res = execute_tasks(chain, generate_table && i + 1 == n, &mut provenance);
+                *results[i].lock().unwrap() = Some((provenance, res));
+            });
+        }
+    });
+    results
+        .into_iter()
+        .map(|x| x.into_inner().unwrap().expect("all chains are executed"))
+        .collect()
+}
+
+// whether the analyses up to the next change of the graph state use nothing but histograms
+fn uses_hists_only(tasks: &[Task]) -> bool {
+    for task in tasks {
+        match task {
+            Task::Analysis(analysis) if !analysis.uses_hists_only() => return false,
+            Task::Analysis(_) | Task::CustomSection { .. } => (),
+            Task::GraphStateChange { .. } => break,
+            _ => return false,
+        }
+    }
+    true
+}
+
+// executes the tasks on a graph broker of their own, returning the report sections and, if
+// requested, the table of the last analysis
+fn execute_tasks(
+    mut instructions: Vec<Task>,
+    generate_table: bool,
+    provenance: &mut Provenance,
+) -> anyhow::Result<(Vec<AnalysisSection>, Option<String>)> {
     let mut report = Vec::new();
     let mut gb = GraphBroker::new();
     for index in 0..instructions.len() {
+        let task_name = match &instructions[index] {
+            Task::Analysis(analysis) => format!("{} ({})", analysis.get_type(), gb.get_run_name()),
+            Task::GraphStateChange { graph, .. } => format!("load graph {}", graph),
+            Task::CustomSection { name, .. } => format!("custom section {}", name),
+            t => format!("{:?}", t),
+        };
+        let progress_index = progress::task_started(&task_name);
+        let meter = StageMeter::start(&task_name);
+        let mut graph_stages = Vec::new();
         match &mut instructions[index] {
             Task::Analysis(analysis) => {
                 log::info!("Executing Analysis: {}", analysis.get_type());
                 report.extend(analysis.generate_report_section(Some(&gb))?);
             }
             Task::CustomSection { name, file } => {
+                provenance.add_input(file);
                 report.extend(AnalysisSection::generate_custom_section(
                     &gb,
                     name.clone(),
                     file.clone(),
                 )?);
             }
-            Task::GraphStateChange {
-                graph,
-                name,
-                subset,
-                exclude,
-                grouping,
-                nice,
-                reqs,
-            } => {
+            Task::GraphStateChange { reqs, .. } => {
                 log::info!("Executing graph change: {:?}", reqs);
-                gb.change_graph_state(
-                    GraphState {
-                        graph: graph.to_string(),
-                        name: name.clone(),
-                        subset: subset.to_string(),
-                        exclude: exclude.to_string(),
-                        grouping: grouping.clone(),
-                    },
-                    &reqs,
-                    *nice,
-                )?;
+                let (state, reqs, nice) = instructions[index].get_graph_state().unwrap();
+                for file in state.get_input_files() {
+                    provenance.add_input(&file);
+                }
+                // runs that only report histograms (and growth curves computed from them) are
+                // served from the cache without parsing the graph, if possible
+                if !(uses_hists_only(&instructions[index + 1..])
+                    && gb.load_cached_hists(state.clone(), reqs))
+                {
+                    gb.change_graph_state(state, reqs, nice)?;
+                }
+                graph_stages = gb.take_stage_usage();
             }
             Task::OrderChange(order) => {
                 log::info!("Executing order change: {:?}", order);
@@ -314,24 +897,27 @@ pub fn execute_pipeline<W: Write>(
                 unimplemented!("CSC Change is not yet implemented");
             }
         }
-    }
-    if json {
-        let json_text = serde_json::to_string_pretty(&report)?;
-        writeln!(out, "{json_text}")?;
-    } else if shall_write_html {
-        let mut registry = handlebars::Handlebars::new();
-        let report = AnalysisSection::generate_report(
-            report,
-            &mut registry,
-            "<Placeholder Filename>",
-            config_content,
-        )?;
-        writeln!(out, "{report}")?;
-    } else {
-        if let Task::Analysis(analysis) = instructions.last_mut().unwrap() {
-            let table = analysis.generate_table(Some(&gb))?;
-            writeln!(out, "{table}")?;
+        // the stages of loading the graph reset the peak memory, hence the peak of the task is
+        // the largest of them
+        let mut usage = meter.finish();
+        usage.peak_rss = graph_stages
+            .iter()
+            .map(|s| s.peak_rss)
+            .chain(std::iter::once(usage.peak_rss))
+            .max()
+            .flatten();
+        progress::task_finished(&task_name, progress_index, usage.seconds);
+        provenance.add_stage(usage);
+        for mut stage in graph_stages {
+            stage.stage = format!("{}: {}", task_name, stage.stage);
+            provenance.add_stage(stage);
         }
     }
-    Ok(())
+    let table = match instructions.last_mut() {
+        Some(Task::Analysis(analysis)) if generate_table => {
+            Some(analysis.generate_table(Some(&gb))?)
+        }
+        _ => None,
+    };
+    Ok((report, table))
 }
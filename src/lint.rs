@@ -0,0 +1,496 @@
+/* standard use */
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+
+/* external use */
+use strum_macros::{EnumString, EnumVariantNames};
+
+/* private use */
+use crate::analysis_parameter::Grouping;
+use crate::graph_broker::{get_path_groups, read_path_segments, PathSegment};
+use crate::io::{is_newick_file, open_file};
+
+/// Kind of a file listing paths/walks, which determines how its entries are checked
+#[derive(Debug, Clone, Copy, PartialEq, EnumString, EnumVariantNames)]
+#[strum(serialize_all = "lowercase")]
+pub enum ListKind {
+    // two-column assignment of paths/walks to groups (--groupby)
+    Grouping,
+    // paths/walks in the order of the growth curve (--order), which must comprise all of them
+    Order,
+    // paths/walks or their coordinates (--subset, --exclude)
+    Subset,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Status {
+    Ok,
+    // matches a path/walk only when ignoring surrounding whitespace or case
+    Renamed,
+    // matches no path/walk of the graph
+    Unknown,
+    // repeats an earlier entry
+    Duplicate,
+    // assigns a path/walk to another group than an earlier entry
+    Conflict,
+    // has the wrong number of columns
+    Malformed,
+    // path/walk of the graph that is not listed
+    Missing,
+}
+
+const STATUSES: [Status; 7] = [
+    Status::Ok,
+    Status::Renamed,
+    Status::Unknown,
+    Status::Duplicate,
+    Status::Conflict,
+    Status::Malformed,
+    Status::Missing,
+];
+
+impl fmt::Display for Status {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Status::Ok => "ok",
+            Status::Renamed => "renamed",
+            Status::Unknown => "unknown",
+            Status::Duplicate => "duplicate",
+            Status::Conflict => "conflict",
+            Status::Malformed => "malformed",
+            Status::Missing => "missing",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Finding {
+    // line of the entry, missing paths/walks have none
+    line: Option<usize>,
+    entry: String,
+    status: Status,
+    // canonical name of the path/walk or group the entry refers to
+    path: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Linted {
+    findings: Vec<Finding>,
+    covered: usize,
+    total: usize,
+    // lines of the file with canonical path and group names, leaving out entries that cannot be
+    // resolved
+    normalized: Vec<String>,
+}
+
+// names of the paths/walks of the graph or of their groups, looked up exactly or ignoring case
+struct Names {
+    exact: HashMap<String, usize>,
+    lowercase: HashMap<String, Vec<usize>>,
+}
+
+impl Names {
+    fn new(names: &[String]) -> Self {
+        let mut res = Self {
+            exact: HashMap::new(),
+            lowercase: HashMap::new(),
+        };
+        for (i, name) in names.iter().enumerate() {
+            res.exact.insert(name.clone(), i);
+            res.lowercase
+                .entry(name.to_lowercase())
+                .or_default()
+                .push(i);
+        }
+        res
+    }
+
+    // index of the name, and whether it is only found when ignoring case
+    fn resolve(&self, name: &str) -> Option<(usize, bool)> {
+        if let Some(i) = self.exact.get(name) {
+            return Some((*i, false));
+        }
+        match self.lowercase.get(&name.to_lowercase()).map(|v| &v[..]) {
+            Some([i]) => Some((*i, true)),
+            _ => None,
+        }
+    }
+}
+
+// paths/walks of the graph and their groups, each path/walk forming a group of its own if the
+// paths/walks are not grouped
+struct PathNames {
+    paths: Vec<PathSegment>,
+    path_names: Names,
+    groups: Vec<String>,
+    // paths/walks of each group
+    members: Vec<Vec<usize>>,
+    group_names: Names,
+}
+
+impl PathNames {
+    fn new(path_segments: &[PathSegment], groups: &HashMap<PathSegment, String>) -> Self {
+        let mut paths: Vec<PathSegment> = Vec::new();
+        let mut path_ids: Vec<String> = Vec::new();
+        let mut seen: HashSet<String> = HashSet::new();
+        for path in path_segments.iter().map(|p| p.clear_coords()) {
+            if seen.insert(path.id()) {
+                path_ids.push(path.id());
+                paths.push(path);
+            }
+        }
+        let mut group_ids: Vec<String> = Vec::new();
+        let mut members: Vec<Vec<usize>> = Vec::new();
+        let mut index: HashMap<String, usize> = HashMap::new();
+        for (i, path) in paths.iter().enumerate() {
+            let group = groups.get(path).cloned().unwrap_or_else(|| path.id());
+            let g = *index.entry(group.clone()).or_insert_with(|| {
+                group_ids.push(group);
+                members.push(Vec::new());
+                members.len() - 1
+            });
+            members[g].push(i);
+        }
+        Self {
+            paths,
+            path_names: Names::new(&path_ids),
+            group_names: Names::new(&group_ids),
+            groups: group_ids,
+            members,
+        }
+    }
+}
+
+/// Cross-checks a grouping, order, or subset file against the paths/walks of the graph, writes
+/// the entries that do not match (and the paths/walks that are not listed) as tab-separated
+/// table, and, if an output file is given, the file with canonical path names; if the paths/walks
+/// are grouped, entries of order and subset files may also name groups
+pub fn lint_list<W: Write>(
+    gfa_file: &str,
+    file: &str,
+    kind: Option<ListKind>,
+    grouping: Option<&Grouping>,
+    output: Option<&str>,
+    out: &mut W,
+) -> anyhow::Result<()> {
+    if is_newick_file(file)? {
+        anyhow::bail!(
+            "{} is a Newick tree, only lists of paths can be checked",
+            file
+        );
+    }
+    let paths = read_path_segments(gfa_file)?;
+    let groups = match grouping {
+        Some(Grouping::Custom(groupby)) => get_path_groups(&paths, groupby, false, false)?,
+        Some(Grouping::Haplotype) => get_path_groups(&paths, "", true, false)?,
+        Some(Grouping::Sample) => get_path_groups(&paths, "", false, true)?,
+        Some(Grouping::Regex { .. }) => {
+            anyhow::bail!("only groupings by file, sample, or haplotype can be checked against")
+        }
+        None => HashMap::new(),
+    };
+    log::info!("checking {} against {} paths/walks", file, paths.len());
+    let lines = BufReader::new(open_file(file)?)
+        .lines()
+        .map(|l| l.map(|l| l.trim_end_matches('\r').to_string()))
+        .collect::<Result<Vec<String>, _>>()?;
+    let kind = kind.unwrap_or_else(|| detect_kind(&lines));
+    let linted = lint_lines(&paths, &groups, &lines, kind);
+
+    let count = |status: Status| {
+        linted
+            .findings
+            .iter()
+            .filter(|f| f.status == status)
+            .count()
+    };
+    writeln!(
+        out,
+        "# {} ({:?}): {}",
+        file,
+        kind,
+        STATUSES
+            .iter()
+            .map(|s| format!("{} {}", count(*s), s))
+            .collect::<Vec<_>>()
+            .join(", ")
+    )?;
+    writeln!(
+        out,
+        "# {} of {} paths/walks of the graph are listed",
+        linted.covered, linted.total
+    )?;
+    if linted.covered < linted.total {
+        let note = match kind {
+            ListKind::Grouping => "paths/walks missing from a grouping form groups of their own",
+            ListKind::Order => {
+                "paths/walks missing from an order fail the run, the normalized file appends them (or their groups)"
+            }
+            ListKind::Subset => "paths/walks missing from a subset are left out of all analyses",
+        };
+        writeln!(out, "# {}", note)?;
+    }
+    writeln!(out, "line\tentry\tstatus\tpath")?;
+    for finding in linted.findings.iter().filter(|f| f.status != Status::Ok) {
+        writeln!(
+            out,
+            "{}\t{}\t{}\t{}",
+            finding.line.map_or("*".to_string(), |l| l.to_string()),
+            finding.entry,
+            finding.status,
+            finding.path.as_deref().unwrap_or("*")
+        )?;
+    }
+
+    if let Some(output) = output {
+        log::info!("writing normalized {:?} file to {}", kind, output);
+        let mut writer =
+            BufWriter::new(std::fs::File::create(output).map_err(|e| {
+                anyhow::anyhow!("unable to create normalized file {}: {}", output, e)
+            })?);
+        crate::run_summary::add_output_file(output);
+        for line in &linted.normalized {
+            writeln!(writer, "{}", line)?;
+        }
+        writer.flush()?;
+    }
+    Ok(())
+}
+
+// a file whose first entry has two columns is a grouping, anything else a subset
+fn detect_kind(lines: &[String]) -> ListKind {
+    match lines
+        .iter()
+        .find(|l| !l.trim().is_empty() && !is_comment(l))
+    {
+        Some(line) if line.split('\t').count() == 2 => ListKind::Grouping,
+        _ => ListKind::Subset,
+    }
+}
+
+fn is_comment(line: &str) -> bool {
+    line.starts_with('#') || line.starts_with("browser ") || line.starts_with("track ")
+}
+
+fn lint_lines(
+    path_segments: &[PathSegment],
+    groups: &HashMap<PathSegment, String>,
+    lines: &[String],
+    kind: ListKind,
+) -> Linted {
+    let names = PathNames::new(path_segments, groups);
+    let mut findings = Vec::new();
+    let mut normalized = Vec::new();
+    let mut covered: HashSet<usize> = HashSet::new();
+    let mut assigned: HashMap<usize, String> = HashMap::new();
+    let mut seen: HashSet<String> = HashSet::new();
+
+    for (i, line) in lines.iter().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        if kind != ListKind::Grouping && is_comment(line) {
+            normalized.push(line.clone());
+            continue;
+        }
+        let mut finding = Finding {
+            line: Some(i + 1),
+            entry: line.clone(),
+            status: Status::Ok,
+            path: None,
+        };
+        let mut fields: Vec<String> = line.split('\t').map(|f| f.to_string()).collect();
+        let malformed = match kind {
+            ListKind::Grouping => fields.len() != 2 || fields[1].trim().is_empty(),
+            _ => fields.len() == 2,
+        };
+        if malformed {
+            finding.status = Status::Malformed;
+            findings.push(finding);
+            continue;
+        }
+
+        let raw = &fields[0];
+        let segment = PathSegment::from_str(raw.trim());
+        let name = segment.clear_coords().id();
+        // entries of orders and subsets that name no path/walk may name a group, which then
+        // stands for all its paths/walks
+        let (entry_paths, canonical, ignoring_case) = match names.path_names.resolve(&name) {
+            Some((path, ignoring_case)) => {
+                let mut canonical = names.paths[path].clone();
+                canonical.start = segment.start;
+                canonical.end = segment.end;
+                (vec![path], canonical.to_string(), ignoring_case)
+            }
+            None => match names.group_names.resolve(raw.trim()) {
+                Some((group, ignoring_case)) if kind != ListKind::Grouping => (
+                    names.members[group].clone(),
+                    names.groups[group].clone(),
+                    ignoring_case,
+                ),
+                _ => {
+                    finding.status = Status::Unknown;
+                    findings.push(finding);
+                    continue;
+                }
+            },
+        };
+        finding.path = Some(canonical.clone());
+        if ignoring_case || raw.trim() != raw.as_str() {
+            finding.status = Status::Renamed;
+        }
+        fields[0] = canonical;
+
+        if kind == ListKind::Grouping {
+            let group = fields[1].trim().to_string();
+            match assigned.get(&entry_paths[0]) {
+                Some(g) if g == &group => finding.status = Status::Duplicate,
+                Some(_) => finding.status = Status::Conflict,
+                None => {
+                    assigned.insert(entry_paths[0], group.clone());
+                    fields[1] = group;
+                }
+            }
+        }
+        let normalized_line = fields.join("\t");
+        if !seen.insert(normalized_line.clone()) {
+            finding.status = Status::Duplicate;
+        }
+        if matches!(finding.status, Status::Ok | Status::Renamed) {
+            covered.extend(entry_paths);
+            normalized.push(normalized_line);
+        }
+        findings.push(finding);
+    }
+
+    for (i, path) in names.paths.iter().enumerate() {
+        if !covered.contains(&i) {
+            findings.push(Finding {
+                line: None,
+                entry: String::new(),
+                status: Status::Missing,
+                path: Some(path.id()),
+            });
+        }
+    }
+    if kind == ListKind::Order {
+        // groups none of whose paths/walks are listed are appended as a whole, the other
+        // paths/walks that are missing one by one
+        for (g, members) in names.members.iter().enumerate() {
+            if members.iter().all(|i| !covered.contains(i)) {
+                normalized.push(names.groups[g].clone());
+            } else {
+                normalized.extend(
+                    members
+                        .iter()
+                        .filter(|i| !covered.contains(i))
+                        .map(|i| names.paths[*i].id()),
+                );
+            }
+        }
+    }
+    Linted {
+        findings,
+        covered: covered.len(),
+        total: names.paths.len(),
+        normalized,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn get_paths() -> Vec<PathSegment> {
+        ["HG00438#1#chr1", "HG00438#2#chr1", "CHM13#0#chr1"]
+            .iter()
+            .map(|p| PathSegment::from_str(p))
+            .collect()
+    }
+
+    fn get_lines(text: &str) -> Vec<String> {
+        text.lines().map(|l| l.to_string()).collect()
+    }
+
+    #[test]
+    fn test_lint_grouping() {
+        let lines = get_lines(
+            "HG00438#1#chr1\tHG00438\nhg00438#2#chr1 \tHG00438\nHG00438#1#chr1\tother\nHG00438#1#chr1\tHG00438\nHG01234#1#chr1\tHG01234\nCHM13#0#chr1",
+        );
+        assert_eq!(detect_kind(&lines), ListKind::Grouping);
+        let linted = lint_lines(&get_paths(), &HashMap::new(), &lines, ListKind::Grouping);
+        assert_eq!(
+            linted
+                .findings
+                .iter()
+                .map(|f| (f.line, f.status))
+                .collect::<Vec<_>>(),
+            vec![
+                (Some(1), Status::Ok),
+                (Some(2), Status::Renamed),
+                (Some(3), Status::Conflict),
+                (Some(4), Status::Duplicate),
+                (Some(5), Status::Unknown),
+                (Some(6), Status::Malformed),
+                (None, Status::Missing),
+            ]
+        );
+        assert_eq!((linted.covered, linted.total), (2, 3));
+        assert_eq!(
+            linted.normalized,
+            vec!["HG00438#1#chr1\tHG00438", "HG00438#2#chr1\tHG00438"]
+        );
+    }
+
+    #[test]
+    fn test_lint_order() {
+        let lines = get_lines("# order\nHG00438#2#chr1\n\nhg00438#1#CHR1:10-20");
+        assert_eq!(detect_kind(&lines), ListKind::Subset);
+        let linted = lint_lines(&get_paths(), &HashMap::new(), &lines, ListKind::Order);
+        assert_eq!(linted.covered, 2);
+        assert_eq!(
+            linted.normalized,
+            vec![
+                "# order",
+                "HG00438#2#chr1",
+                "HG00438#1#chr1:10-20",
+                "CHM13#0#chr1"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_lint_group_entries() {
+        let paths = get_paths();
+        let groups: HashMap<PathSegment, String> = paths
+            .iter()
+            .map(|p| (p.clone(), p.sample.clone()))
+            .collect();
+        // hg00438 names the group of both HG00438 haplotypes, which is unknown without grouping
+        let lines = get_lines("hg00438\nCHM13#0#chr1:0-5\nHG01234");
+        let linted = lint_lines(&paths, &groups, &lines, ListKind::Subset);
+        assert_eq!(
+            linted
+                .findings
+                .iter()
+                .map(|f| (f.line, f.status, f.path.as_deref()))
+                .collect::<Vec<_>>(),
+            vec![
+                (Some(1), Status::Renamed, Some("HG00438")),
+                (Some(2), Status::Ok, Some("CHM13#0#chr1:0-5")),
+                (Some(3), Status::Unknown, None),
+            ]
+        );
+        assert_eq!((linted.covered, linted.total), (3, 3));
+        assert_eq!(linted.normalized, vec!["HG00438", "CHM13#0#chr1:0-5"]);
+        let linted = lint_lines(&paths, &HashMap::new(), &lines, ListKind::Subset);
+        assert_eq!(linted.covered, 1);
+
+        // groups missing from an order are appended as a whole
+        let lines = get_lines("CHM13#0#chr1");
+        let linted = lint_lines(&paths, &groups, &lines, ListKind::Order);
+        assert_eq!(linted.normalized, vec!["CHM13#0#chr1", "HG00438"]);
+    }
+}
@@ -0,0 +1,139 @@
+/* standard use */
+use std::fs::File;
+use std::io::{BufWriter, Error, Write};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+/* external use */
+use once_cell::sync::Lazy;
+use serde::Serialize;
+
+// file to which progress events are written, one JSON object per line, e.g., for workflow
+// managers showing progress bars; events are dropped if unset
+static PROGRESS: Lazy<Mutex<Option<BufWriter<File>>>> = Lazy::new(Default::default);
+static TASKS: AtomicUsize = AtomicUsize::new(0);
+static STARTED_TASKS: AtomicUsize = AtomicUsize::new(0);
+static FINISHED_TASKS: AtomicUsize = AtomicUsize::new(0);
+
+#[derive(Serialize, Debug, PartialEq)]
+#[serde(tag = "event", rename_all = "snake_case")]
+enum Event<'a> {
+    PipelineStarted {
+        tasks: usize,
+    },
+    TaskStarted {
+        task: &'a str,
+        index: usize,
+        tasks: usize,
+    },
+    TaskFinished {
+        task: &'a str,
+        index: usize,
+        seconds: f64,
+        percent: f64,
+    },
+    PipelineFinished {
+        success: bool,
+        seconds: f64,
+    },
+}
+
+/// Writes progress events to the given file (e.g., /dev/fd/3 for a dedicated file descriptor)
+/// from now on
+pub fn set_progress_file(file: &str) -> Result<(), Error> {
+    let f = File::create(file).map_err(|e| {
+        Error::new(
+            e.kind(),
+            format!("unable to create progress file {}: {}", file, e),
+        )
+    })?;
+    *PROGRESS.lock().unwrap() = Some(BufWriter::new(f));
+    Ok(())
+}
+
+fn emit(event: &Event) {
+    let mut progress = PROGRESS.lock().unwrap();
+    if let Some(out) = progress.as_mut() {
+        let res = serde_json::to_writer(&mut *out, event)
+            .map_err(Error::from)
+            .and_then(|_| writeln!(out))
+            .and_then(|_| out.flush());
+        if let Err(e) = res {
+            log::warn!(
+                "unable to write progress event, stopping progress events: {}",
+                e
+            );
+            *progress = None;
+        }
+    }
+}
+
+pub fn pipeline_started(tasks: usize) {
+    TASKS.store(tasks, Ordering::Relaxed);
+    STARTED_TASKS.store(0, Ordering::Relaxed);
+    FINISHED_TASKS.store(0, Ordering::Relaxed);
+    emit(&Event::PipelineStarted { tasks });
+}
+
+/// Reports the start of a task, returning its index, by which its end is reported
+pub fn task_started(task: &str) -> usize {
+    let index = STARTED_TASKS.fetch_add(1, Ordering::Relaxed);
+    emit(&Event::TaskStarted {
+        task,
+        index,
+        tasks: TASKS.load(Ordering::Relaxed),
+    });
+    index
+}
+
+pub fn task_finished(task: &str, index: usize, seconds: f64) {
+    let finished = FINISHED_TASKS.fetch_add(1, Ordering::Relaxed) + 1;
+    emit(&Event::TaskFinished {
+        task,
+        index,
+        seconds,
+        percent: get_percent(finished, TASKS.load(Ordering::Relaxed)),
+    });
+}
+
+pub fn pipeline_finished(success: bool, seconds: f64) {
+    emit(&Event::PipelineFinished { success, seconds });
+}
+
+fn get_percent(finished: usize, tasks: usize) -> f64 {
+    if tasks == 0 {
+        100.0
+    } else {
+        (100.0 * finished as f64 / tasks as f64).min(100.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_event_json() {
+        assert_eq!(
+            serde_json::to_string(&Event::TaskFinished {
+                task: "Hist (run)",
+                index: 1,
+                seconds: 0.5,
+                percent: 50.0,
+            })
+            .unwrap(),
+            r#"{"event":"task_finished","task":"Hist (run)","index":1,"seconds":0.5,"percent":50.0}"#
+        );
+        assert_eq!(
+            serde_json::to_string(&Event::PipelineStarted { tasks: 3 }).unwrap(),
+            r#"{"event":"pipeline_started","tasks":3}"#
+        );
+    }
+
+    #[test]
+    fn test_get_percent() {
+        assert_eq!(get_percent(1, 4), 25.0);
+        assert_eq!(get_percent(0, 0), 100.0);
+        assert_eq!(get_percent(5, 4), 100.0);
+    }
+}
@@ -0,0 +1,186 @@
+/* standard use */
+use std::fs::File;
+use std::io::BufWriter;
+use std::sync::Mutex;
+
+/* external use */
+use once_cell::sync::Lazy;
+use serde::Serialize;
+
+/* private use */
+use crate::analysis_parameter::AnalysisRun;
+use crate::build;
+use crate::html_report::Provenance;
+
+/// Version of the layout of the run summary, increased whenever fields are renamed or removed
+pub const RUN_SUMMARY_SCHEMA_VERSION: u32 = 1;
+
+// warnings logged and files written by the analyses during the run
+static WARNINGS: Lazy<Mutex<Vec<String>>> = Lazy::new(Default::default);
+static OUTPUT_FILES: Lazy<Mutex<Vec<String>>> = Lazy::new(Default::default);
+
+/// Records a file written by an analysis (apart from the standard output) for the run summary
+pub fn add_output_file(file: &str) {
+    let mut files = OUTPUT_FILES.lock().unwrap();
+    if !files.iter().any(|x| x == file) {
+        files.push(file.to_string());
+    }
+}
+
+/// Logger that passes all records on to the given logger, keeping warnings and errors for the
+/// run summary
+pub struct WarningRecorder<L: log::Log> {
+    inner: L,
+}
+
+impl<L: log::Log> WarningRecorder<L> {
+    pub fn new(inner: L) -> Self {
+        Self { inner }
+    }
+}
+
+impl<L: log::Log> log::Log for WarningRecorder<L> {
+    fn enabled(&self, metadata: &log::Metadata) -> bool {
+        self.inner.enabled(metadata)
+    }
+
+    fn log(&self, record: &log::Record) {
+        if record.level() <= log::Level::Warn && self.inner.enabled(record.metadata()) {
+            WARNINGS.lock().unwrap().push(record.args().to_string());
+        }
+        self.inner.log(record);
+    }
+
+    fn flush(&self) {
+        self.inner.flush();
+    }
+}
+
+#[derive(Serialize)]
+struct RunSummary<'a> {
+    schema_version: u32,
+    panacus_version: &'a str,
+    panacus_commit: &'a str,
+    command_line: &'a str,
+    threads: usize,
+    seed: u64,
+    // success or failure; error holds the cause of a failure
+    status: &'a str,
+    error: Option<String>,
+    config: &'a str,
+    runs: &'a [AnalysisRun],
+    inputs: Vec<InputFile<'a>>,
+    outputs: Vec<String>,
+    warnings: Vec<String>,
+    tasks: Vec<TaskRuntime<'a>>,
+    total_seconds: f64,
+}
+
+#[derive(Serialize)]
+struct InputFile<'a> {
+    file: &'a str,
+    // missing if the file cannot be read (anymore)
+    size: Option<u64>,
+    crc32: Option<String>,
+}
+
+#[derive(Serialize)]
+struct TaskRuntime<'a> {
+    task: &'a str,
+    seconds: f64,
+    // missing if the platform does not report it
+    peak_rss_bytes: Option<u64>,
+}
+
+/// Writes the summary of a (possibly failed) run in JSON format to the given file, for
+/// provenance tracking and automated QC gating by workflow managers
+pub fn write_run_summary(
+    file: &str,
+    provenance: &Provenance,
+    runs: &[AnalysisRun],
+    result: &anyhow::Result<()>,
+    total_seconds: f64,
+) -> anyhow::Result<()> {
+    let inputs = provenance
+        .inputs
+        .iter()
+        .map(|input| {
+            let (size, crc32) = match crate::io::file_size_and_checksum(input) {
+                Ok((size, checksum)) => (Some(size), Some(format!("{:08x}", checksum))),
+                Err(_) => (None, None),
+            };
+            InputFile {
+                file: input,
+                size,
+                crc32,
+            }
+        })
+        .collect();
+    let summary = RunSummary {
+        schema_version: RUN_SUMMARY_SCHEMA_VERSION,
+        panacus_version: build::PKG_VERSION,
+        panacus_commit: build::COMMIT_HASH,
+        command_line: &provenance.command_line,
+        threads: provenance.threads,
+        seed: provenance.seed,
+        status: if result.is_ok() { "success" } else { "failure" },
+        error: result.as_ref().err().map(|e| format!("{:#}", e)),
+        config: &provenance.config,
+        runs,
+        inputs,
+        outputs: OUTPUT_FILES.lock().unwrap().clone(),
+        warnings: WARNINGS.lock().unwrap().clone(),
+        tasks: provenance
+            .stages
+            .iter()
+            .map(|usage| TaskRuntime {
+                task: &usage.stage,
+                seconds: usage.seconds,
+                peak_rss_bytes: usage.peak_rss,
+            })
+            .collect(),
+        total_seconds,
+    };
+    let out = BufWriter::new(
+        File::create(file)
+            .map_err(|e| anyhow::anyhow!("unable to create run summary {}: {}", file, e))?,
+    );
+    serde_json::to_writer_pretty(out, &summary)?;
+    log::info!("wrote run summary to {}", file);
+    Ok(())
+}
+
+/// Result of a run whose summary has been written: the error of the run takes precedence over that
+/// of writing the summary, which is then only logged
+pub fn first_error(result: anyhow::Result<()>, summary: anyhow::Result<()>) -> anyhow::Result<()> {
+    match (result, summary) {
+        (Err(e), Err(summary_error)) => {
+            log::error!("{:#}", summary_error);
+            Err(e)
+        }
+        (result, summary) => result.and(summary),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_first_error() {
+        assert!(first_error(Ok(()), Ok(())).is_ok());
+        let msg = |res: anyhow::Result<()>| res.unwrap_err().to_string();
+        assert_eq!(msg(first_error(Err(anyhow::anyhow!("run")), Ok(()))), "run");
+        assert_eq!(
+            msg(first_error(Ok(()), Err(anyhow::anyhow!("summary")))),
+            "summary"
+        );
+        assert_eq!(
+            msg(first_error(
+                Err(anyhow::anyhow!("run")),
+                Err(anyhow::anyhow!("summary"))
+            )),
+            "run"
+        );
+    }
+}
@@ -0,0 +1,222 @@
+/* standard use */
+use std::fs;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+/* private use */
+use crate::analysis_parameter::AnalysisRun;
+use crate::html_report::HtmlOutput;
+
+// path polled by the served report to find out whether it has been rebuilt
+const VERSION_PATH: &str = "/__panacus/version";
+// interval in which the YAML config is checked for changes
+const WATCH_INTERVAL: Duration = Duration::from_millis(500);
+// time after which a connection that has not sent its request is dropped
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+
+// reloads the page once the version reported by the server differs from the one the page was
+// built with
+const LIVE_RELOAD_JS: &str = r#"<script>
+(function () {
+  var version = "{version}";
+  setInterval(function () {
+    fetch("{path}").then(function (r) { return r.text(); }).then(function (v) {
+      if (v !== version) { location.reload(); }
+    }).catch(function () {});
+  }, 1000);
+})();
+</script>"#;
+
+/// Latest build of the report, numbered by version
+struct ServedReport {
+    version: u64,
+    html: String,
+}
+
+/// Runs the pipeline of the YAML config and serves the resulting HTML report on the given local
+/// port; the report is rebuilt whenever the config changes, and open pages reload themselves
+pub fn serve(yaml_file: &str, port: u16, theme: Option<String>) -> anyhow::Result<()> {
+    let listener = TcpListener::bind(("127.0.0.1", port))
+        .map_err(|e| anyhow::anyhow!("unable to listen on port {}: {}", port, e))?;
+    let report = Arc::new(Mutex::new(ServedReport {
+        version: 1,
+        html: build_report(yaml_file, theme.as_deref(), 1),
+    }));
+    log::info!(
+        "serving report of {} on http://127.0.0.1:{}/",
+        yaml_file,
+        port
+    );
+
+    let watched = Arc::clone(&report);
+    let yaml = yaml_file.to_string();
+    thread::spawn(move || watch_config(&yaml, theme.as_deref(), &watched));
+
+    // each connection is answered by a thread of its own, such that a client that is slow to send
+    // its request does not block the others
+    for stream in listener.incoming() {
+        match stream {
+            Ok(stream) => {
+                let report = Arc::clone(&report);
+                thread::spawn(move || {
+                    if let Err(e) = respond(stream, &report) {
+                        log::warn!("unable to answer request: {}", e);
+                    }
+                });
+            }
+            Err(e) => log::warn!("unable to accept connection: {}", e),
+        }
+    }
+    Ok(())
+}
+
+// rebuilds the report whenever the modification time of the config changes
+fn watch_config(yaml_file: &str, theme: Option<&str>, report: &Mutex<ServedReport>) {
+    let modified = |file: &str| fs::metadata(file).and_then(|m| m.modified()).ok();
+    let mut last: Option<SystemTime> = modified(yaml_file);
+    loop {
+        thread::sleep(WATCH_INTERVAL);
+        let current = modified(yaml_file);
+        if current == last {
+            continue;
+        }
+        last = current;
+        log::info!("{} changed, rebuilding report..", yaml_file);
+        let version = report.lock().unwrap().version + 1;
+        let html = build_report(yaml_file, theme, version);
+        let mut report = report.lock().unwrap();
+        report.version = version;
+        report.html = html;
+        log::info!("report rebuilt (version {})", version);
+    }
+}
+
+// runs the pipeline of the config; errors are shown in the page rather than ending the server,
+// such that the config can be fixed while it is served
+fn build_report(yaml_file: &str, theme: Option<&str>, version: u64) -> String {
+    let html = run_report(yaml_file, theme).unwrap_or_else(|e| {
+        log::error!("unable to build report: {:#}", e);
+        format!(
+            "<!DOCTYPE html>\n<html><head><title>panacus report</title></head><body><h1>Unable to build report</h1><pre>{}</pre></body></html>",
+            escape_html(&format!("{:#}", e))
+        )
+    });
+    inject_live_reload(&html, version)
+}
+
+fn run_report(yaml_file: &str, theme: Option<&str>) -> anyhow::Result<String> {
+    let config = fs::read_to_string(yaml_file)
+        .map_err(|e| anyhow::anyhow!("unable to read {}: {}", yaml_file, e))?;
+    let runs = AnalysisRun::from_yaml(&config)?;
+    let tasks = AnalysisRun::convert_to_tasks(runs);
+    let mut out = BufWriter::new(Vec::new());
+    crate::execute_pipeline(
+        tasks,
+        &mut out,
+        true,
+        false,
+        &config,
+        theme,
+        &HtmlOutput::Report,
+    )?;
+    let html = out.into_inner().map_err(|e| anyhow::anyhow!("{}", e))?;
+    Ok(String::from_utf8_lossy(&html).into_owned())
+}
+
+fn respond(mut stream: TcpStream, report: &Mutex<ServedReport>) -> std::io::Result<()> {
+    stream.set_read_timeout(Some(REQUEST_TIMEOUT))?;
+    let mut request_line = String::new();
+    BufReader::new(&stream).read_line(&mut request_line)?;
+    let (status, content_type, body) = match get_request_path(&request_line) {
+        Some("/") | Some("/index.html") => (
+            "200 OK",
+            "text/html; charset=utf-8",
+            report.lock().unwrap().html.clone(),
+        ),
+        Some(VERSION_PATH) => (
+            "200 OK",
+            "text/plain",
+            report.lock().unwrap().version.to_string(),
+        ),
+        _ => ("404 Not Found", "text/plain", "not found".to_string()),
+    };
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nCache-Control: no-store\r\nConnection: close\r\n\r\n",
+        status,
+        content_type,
+        body.len()
+    )?;
+    stream.write_all(body.as_bytes())?;
+    stream.flush()
+}
+
+// path of a GET request, without query string
+fn get_request_path(request_line: &str) -> Option<&str> {
+    let mut fields = request_line.split_whitespace();
+    match (fields.next(), fields.next()) {
+        (Some("GET"), Some(target)) => target.split('?').next(),
+        _ => None,
+    }
+}
+
+fn inject_live_reload(html: &str, version: u64) -> String {
+    let script = LIVE_RELOAD_JS
+        .replace("{version}", &version.to_string())
+        .replace("{path}", VERSION_PATH);
+    match html.rfind("</body>") {
+        Some(i) => format!("{}{}\n{}", &html[..i], script, &html[i..]),
+        None => format!("{}\n{}", html, script),
+    }
+}
+
+fn escape_html(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_get_request_path() {
+        assert_eq!(get_request_path("GET / HTTP/1.1\r\n"), Some("/"));
+        assert_eq!(
+            get_request_path("GET /__panacus/version?t=1 HTTP/1.1\r\n"),
+            Some(VERSION_PATH)
+        );
+        assert_eq!(get_request_path("POST / HTTP/1.1\r\n"), None);
+        assert_eq!(get_request_path(""), None);
+    }
+
+    #[test]
+    fn test_respond() {
+        let listener = TcpListener::bind(("127.0.0.1", 0)).unwrap();
+        let report = Mutex::new(ServedReport {
+            version: 7,
+            html: String::new(),
+        });
+        let mut client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        write!(client, "GET {} HTTP/1.1\r\n\r\n", VERSION_PATH).unwrap();
+        let (stream, _) = listener.accept().unwrap();
+        respond(stream, &report).unwrap();
+        let mut response = String::new();
+        std::io::Read::read_to_string(&mut client, &mut response).unwrap();
+        assert!(response.starts_with("HTTP/1.1 200 OK"));
+        assert!(response.ends_with("\r\n\r\n7"));
+    }
+
+    #[test]
+    fn test_inject_live_reload() {
+        let html = inject_live_reload("<html><body><p>x</p></body></html>", 3);
+        assert!(html.starts_with("<html><body><p>x</p><script>"));
+        assert!(html.ends_with("</script>\n</body></html>"));
+        assert!(html.contains("var version = \"3\";"));
+        assert!(html.contains(VERSION_PATH));
+    }
+}
@@ -2,12 +2,14 @@
 use std::cmp::Ordering;
 use std::collections::HashMap;
 use std::fmt;
+use std::sync::atomic::{self, AtomicBool, AtomicU64, AtomicUsize};
 
+use rand::{rngs::StdRng, SeedableRng};
 use serde::{Deserialize, Serialize};
 /* external use */
 use strum_macros::{EnumIter, EnumString, EnumVariantNames};
 
-use crate::graph_broker::ItemId;
+use crate::graph_broker::{ItemId, SpillVec};
 
 /* internal use */
 
@@ -16,6 +18,174 @@ pub type ItemIdSize = u64;
 pub type CountSize = u32;
 pub type GroupSize = u64;
 
+// seed of all randomized computations (permutations, bootstrap replicates, subsampling)
+static SEED: AtomicU64 = AtomicU64::new(0);
+static PARALLEL_RUNS: AtomicUsize = AtomicUsize::new(1);
+// memory budget (bytes) of runs executed in parallel, 0 if unbounded
+static MEMORY_BUDGET: AtomicU64 = AtomicU64::new(0);
+// whether the peak memory is measured per stage, which is not possible while several runs are
+// executed in parallel, as resetting it affects the whole process
+static STAGE_PEAK_RSS: AtomicBool = AtomicBool::new(true);
+
+/// Sets the seed from which the random number generators of all randomized computations are
+/// derived, such that their results are reproducible
+pub fn set_seed(seed: u64) {
+    SEED.store(seed, atomic::Ordering::Relaxed);
+}
+
+pub fn get_seed() -> u64 {
+    SEED.load(atomic::Ordering::Relaxed)
+}
+
+/// Sets the number of runs over different graphs that are executed in parallel
+pub fn set_parallel_runs(parallel_runs: usize) {
+    PARALLEL_RUNS.store(parallel_runs, atomic::Ordering::Relaxed);
+}
+
+pub fn get_parallel_runs() -> usize {
+    PARALLEL_RUNS.load(atomic::Ordering::Relaxed)
+}
+
+/// Sets the memory (bytes) that runs executed in parallel are expected to take together at most
+pub fn set_memory_budget(budget: Option<u64>) {
+    MEMORY_BUDGET.store(budget.unwrap_or(0), atomic::Ordering::Relaxed);
+}
+
+pub fn get_memory_budget() -> Option<u64> {
+    Some(MEMORY_BUDGET.load(atomic::Ordering::Relaxed)).filter(|x| *x > 0)
+}
+
+/// Measures the peak memory of each stage, or, if disabled while runs are executed in parallel,
+/// reports no peak memory for stages
+pub fn set_stage_peak_rss(enabled: bool) {
+    STAGE_PEAK_RSS.store(enabled, atomic::Ordering::Relaxed);
+}
+
+fn is_stage_peak_rss() -> bool {
+    STAGE_PEAK_RSS.load(atomic::Ordering::Relaxed)
+}
+
+/// Random number generator of the given stream (e.g., the index of a permutation) derived from
+/// the global seed; as each stream has its own generator, results do not depend on the number of
+/// threads
+pub fn get_rng(stream: u64) -> StdRng {
+    // multiplying by the golden ratio spreads the streams of different seeds apart; seed 0 yields
+    // the streams 0, 1, 2, ...
+    StdRng::seed_from_u64(
+        get_seed()
+            .wrapping_mul(0x9e37_79b9_7f4a_7c15)
+            .wrapping_add(stream),
+    )
+}
+
+/// Measures the duration of a stage for logging; there is no clock on wasm32, where the elapsed
+/// time is always zero
+pub struct Stopwatch {
+    #[cfg(not(target_arch = "wasm32"))]
+    start: std::time::Instant,
+}
+
+impl Stopwatch {
+    pub fn start() -> Self {
+        Stopwatch {
+            #[cfg(not(target_arch = "wasm32"))]
+            start: std::time::Instant::now(),
+        }
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn elapsed(&self) -> std::time::Duration {
+        self.start.elapsed()
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    pub fn elapsed(&self) -> std::time::Duration {
+        std::time::Duration::ZERO
+    }
+}
+
+/// Wall-clock time and approximate peak resident set size (bytes, if available on the platform)
+/// of a stage of the pipeline
+#[derive(Debug, Clone, PartialEq)]
+pub struct StageUsage {
+    pub stage: String,
+    pub seconds: f64,
+    pub peak_rss: Option<u64>,
+}
+
+/// Measures a stage of the pipeline; as the peak memory is reset at the start of each stage,
+/// stages are not nested
+pub struct StageMeter {
+    stage: String,
+    timer: Stopwatch,
+}
+
+impl StageMeter {
+    pub fn start(stage: &str) -> Self {
+        if is_stage_peak_rss() {
+            reset_peak_rss();
+        }
+        StageMeter {
+            stage: stage.to_string(),
+            timer: Stopwatch::start(),
+        }
+    }
+
+    pub fn finish(self) -> StageUsage {
+        let usage = StageUsage {
+            stage: self.stage,
+            seconds: self.timer.elapsed().as_secs_f64(),
+            peak_rss: get_peak_rss().filter(|_| is_stage_peak_rss()),
+        };
+        log::info!(
+            "{} took {:.3}s, peak RSS (MB): {}",
+            usage.stage,
+            usage.seconds,
+            format_rss(usage.peak_rss)
+        );
+        usage
+    }
+}
+
+/// Peak resident set size in MB, as reported in logs and tables
+pub fn format_rss(peak_rss: Option<u64>) -> String {
+    peak_rss.map_or("NA".to_string(), |x| {
+        format!("{:.1}", x as f64 / (1024.0 * 1024.0))
+    })
+}
+
+// peak resident set size (bytes) of the process since the last reset, only available on Linux
+#[cfg(target_os = "linux")]
+pub fn get_peak_rss() -> Option<u64> {
+    parse_peak_rss(&std::fs::read_to_string("/proc/self/status").ok()?)
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn get_peak_rss() -> Option<u64> {
+    None
+}
+
+// writing 5 to clear_refs resets the peak resident set size to the current one (Linux >= 4.0);
+// if that is not permitted, the peak of the process so far is reported
+#[cfg(target_os = "linux")]
+fn reset_peak_rss() {
+    let _ = std::fs::write("/proc/self/clear_refs", "5");
+}
+
+#[cfg(not(target_os = "linux"))]
+fn reset_peak_rss() {}
+
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+fn parse_peak_rss(status: &str) -> Option<u64> {
+    let kb = status
+        .lines()
+        .find_map(|l| l.strip_prefix("VmHWM:"))?
+        .trim()
+        .trim_end_matches("kB")
+        .trim();
+    kb.parse::<u64>().ok().map(|x| x * 1024)
+}
+
 pub struct Wrap<T>(pub *mut T);
 unsafe impl Sync for Wrap<Vec<usize>> {}
 unsafe impl Sync for Wrap<Vec<u64>> {}
@@ -45,6 +215,7 @@ pub enum CountType {
     Node,
     Bp,
     Edge,
+    Bubble,
     All,
 }
 
@@ -63,6 +234,7 @@ impl fmt::Display for CountType {
                 CountType::Node => "node",
                 CountType::Edge => "edge",
                 CountType::Bp => "bp",
+                CountType::Bubble => "bubble",
                 CountType::All => "all",
             }
         )
@@ -79,14 +251,15 @@ pub fn get_default_plot_downloads() -> Vec<(String, String)> {
 
 #[derive(Debug, Clone)]
 pub struct ItemTable {
-    pub items: Vec<ItemIdSize>,
+    // written to a temporary file while the table is built if a spill directory is set
+    pub items: SpillVec<ItemIdSize>,
     pub id_prefsum: Vec<ItemIdSize>,
 }
 
 impl ItemTable {
     pub fn new(num_walks_paths: usize) -> Self {
         Self {
-            items: vec![],
+            items: SpillVec::growable(),
             id_prefsum: vec![0; num_walks_paths + 1],
         }
     }
@@ -328,6 +501,9 @@ impl fmt::Display for ActiveTableError {
 pub enum Threshold {
     Relative(f64),
     Absolute(usize),
+    // ranges with inclusive lower and upper bound
+    AbsoluteRange(usize, usize),
+    RelativeRange(f64, f64),
 }
 
 impl fmt::Display for Threshold {
@@ -335,6 +511,8 @@ impl fmt::Display for Threshold {
         match self {
             Threshold::Relative(c) => write!(formatter, "{}R", c)?,
             Threshold::Absolute(c) => write!(formatter, "{}A", c)?,
+            Threshold::AbsoluteRange(a, b) => write!(formatter, "{}..{}A", a, b)?,
+            Threshold::RelativeRange(a, b) => write!(formatter, "{}..{}R", a, b)?,
         }
         Ok(())
     }
@@ -345,20 +523,44 @@ impl Threshold {
         match self {
             Threshold::Relative(c) => format!("{}", c),
             Threshold::Absolute(c) => format!("{}", c),
+            Threshold::AbsoluteRange(a, b) => format!("{}..{}", a, b),
+            Threshold::RelativeRange(a, b) => format!("{}..{}", a, b),
         }
     }
 
+    // lower bound
     pub fn to_absolute(self, n: usize) -> usize {
         match self {
-            Threshold::Absolute(c) => c,
-            Threshold::Relative(c) => (n as f64 * c).ceil() as usize,
+            Threshold::Absolute(c) | Threshold::AbsoluteRange(c, _) => c,
+            Threshold::Relative(c) | Threshold::RelativeRange(c, _) => {
+                (n as f64 * c).ceil() as usize
+            }
         }
     }
 
+    // lower bound
     pub fn to_relative(self, n: usize) -> f64 {
         match self {
-            Threshold::Relative(c) => c,
-            Threshold::Absolute(c) => c as f64 / n as f64,
+            Threshold::Relative(c) | Threshold::RelativeRange(c, _) => c,
+            Threshold::Absolute(c) | Threshold::AbsoluteRange(c, _) => c as f64 / n as f64,
+        }
+    }
+
+    // upper bound; thresholds without range are open-ended
+    pub fn to_absolute_upper(self, n: usize) -> usize {
+        match self {
+            Threshold::Absolute(_) | Threshold::Relative(_) => usize::MAX,
+            Threshold::AbsoluteRange(_, c) => c,
+            Threshold::RelativeRange(_, c) => (n as f64 * c).floor() as usize,
+        }
+    }
+
+    // upper bound; thresholds without range are open-ended
+    pub fn to_relative_upper(self, n: usize) -> f64 {
+        match self {
+            Threshold::Absolute(_) | Threshold::Relative(_) => f64::INFINITY,
+            Threshold::AbsoluteRange(_, c) => c as f64 / n as f64,
+            Threshold::RelativeRange(_, c) => c,
         }
     }
 }
@@ -416,6 +618,31 @@ pub fn median_already_sorted(v: &[u32]) -> f64 {
     }
 }
 
+/// Content contributed by the group added at each step of the ordered growth, i.e., the difference
+/// to the growth of the previous step
+pub fn get_new_content(growth: &[f64]) -> Vec<f64> {
+    let mut previous = 0.0;
+    growth
+        .iter()
+        .map(|x| {
+            let d = x - previous;
+            previous = *x;
+            d
+        })
+        .collect()
+}
+
+// percentile p (in %) of the values, interpolating linearly between the closest ranks
+pub fn percentile_already_sorted(v: &[f64], p: f64) -> f64 {
+    //v.sort(); this has been done before
+    if v.is_empty() {
+        return f64::NAN;
+    }
+    let pos = p / 100.0 * (v.len() - 1) as f64;
+    let (lower, upper) = (pos.floor() as usize, pos.ceil() as usize);
+    v[lower] + (v[upper] - v[lower]) * (pos - lower as f64)
+}
+
 pub fn n50_already_sorted(v: &[u32]) -> Option<u32> {
     //v.sort(); this has been done before
     let total_length: u32 = v.iter().sum();
@@ -431,7 +658,6 @@ pub fn n50_already_sorted(v: &[u32]) -> Option<u32> {
     None
 }
 
-#[allow(dead_code)]
 pub fn reverse_complement(dna: &[u8]) -> Vec<u8> {
     dna.iter()
         .rev() // Reverse the sequence
@@ -444,6 +670,8 @@ pub fn reverse_complement(dna: &[u8]) -> Vec<u8> {
             b't' => b'a',
             b'c' => b'g',
             b'g' => b'c',
+            b'N' => b'N',
+            b'n' => b'n',
             _ => panic!("Invalid nucleotide: {}", b as char),
         })
         .collect()
@@ -576,4 +804,32 @@ mod tests {
         ic.add(ItemId(0), 14, 17);
         assert_eq!(ic.map.get(&ItemId(0)), Some(&vec![(0, 12), (13, 20)]));
     }
+
+    #[test]
+    fn test_parse_peak_rss() {
+        let status =
+            "Name:\tpanacus\nVmPeak:\t  20000 kB\nVmHWM:\t    1024 kB\nVmRSS:\t     512 kB\n";
+        assert_eq!(parse_peak_rss(status), Some(1024 * 1024));
+        assert_eq!(parse_peak_rss("Name:\tpanacus\n"), None);
+        assert_eq!(format_rss(Some(3 * 1024 * 1024 / 2)), "1.5");
+        assert_eq!(format_rss(None), "NA");
+    }
+
+    #[test]
+    fn test_get_rng() {
+        use rand::Rng;
+        let draw = |stream| get_rng(stream).gen::<u64>();
+        assert_eq!(draw(1), draw(1));
+        assert_ne!(draw(0), draw(1));
+        assert_eq!(draw(3), StdRng::seed_from_u64(3).gen::<u64>());
+    }
+
+    #[test]
+    fn test_get_new_content() {
+        assert_eq!(
+            get_new_content(&[3.0, 5.0, 5.0, 6.0]),
+            vec![3.0, 2.0, 0.0, 1.0]
+        );
+        assert!(get_new_content(&[]).is_empty());
+    }
 }
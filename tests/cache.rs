@@ -0,0 +1,23 @@
+use assert_cmd::prelude::*; // Add methods on commands
+use predicates::prelude::*; // Used for writing assertions
+use std::process::Command; // Run programs
+
+#[test]
+fn cached_hist_skips_parsing() -> Result<(), Box<dyn std::error::Error>> {
+    let cache_dir = tempfile::tempdir()?;
+    let run = || -> Result<_, Box<dyn std::error::Error>> {
+        let mut cmd = Command::cargo_bin("panacus")?;
+        cmd.env("XDG_CACHE_HOME", cache_dir.path())
+            .arg("hist")
+            .arg("--cache")
+            .arg("tests/test_files/t_groups.gfa");
+        Ok(cmd.assert().success())
+    };
+
+    let first = run()?.stderr(predicate::str::contains("skipping its parsing").not());
+    let table = first.get_output().stdout.clone();
+    let second = run()?.stderr(predicate::str::contains("skipping its parsing"));
+    assert_eq!(second.get_output().stdout, table);
+    assert!(cache_dir.path().join("panacus").read_dir()?.count() > 0);
+    Ok(())
+}
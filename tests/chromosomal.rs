@@ -0,0 +1,48 @@
+use assert_cmd::prelude::*; // Add methods on commands
+use predicates::prelude::*; // Used for writing assertions
+use std::process::Command; // Run programs
+
+#[test]
+fn chromosomal_rejects_empty_window() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("panacus")?;
+
+    cmd.arg("chromosomal")
+        .arg("tests/test_files/t_groups.gfa")
+        .arg("-r")
+        .arg("x")
+        .arg("-w")
+        .arg("0");
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("--window"));
+    Ok(())
+}
+
+#[test]
+fn chromosomal_stacks_track_per_group() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempfile::tempdir()?;
+    let groups = dir.path().join("groups.tsv");
+    std::fs::write(&groups, "x\ta\ny#1\ta\ny#2\ta\ny#3\tb\ny#4\tb\ny#5\tb\n")?;
+    let mut cmd = Command::cargo_bin("panacus")?;
+
+    cmd.arg("chromosomal")
+        .arg("tests/test_files/t_groups.gfa")
+        .arg("-r")
+        .arg("x")
+        .arg("-G")
+        .arg("-g")
+        .arg(&groups);
+    // group b lacks the first 14bp of x, hence the tracks of both groups are split there
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "\nx\t0\t14\t1\nx\t14\t50\t2\ntrack type=bedGraph",
+        ))
+        .stdout(predicate::str::contains(
+            "chromosomal a\" description=\"fraction of bp along x covered by a\"\nx\t0\t14\t1\nx\t14\t50\t1\n",
+        ))
+        .stdout(predicate::str::contains(
+            "chromosomal b\" description=\"fraction of bp along x covered by b\"\nx\t0\t14\t0\nx\t14\t50\t1\n",
+        ));
+    Ok(())
+}
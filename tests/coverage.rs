@@ -0,0 +1,36 @@
+use assert_cmd::prelude::*; // Add methods on commands
+use predicates::prelude::*; // Used for writing assertions
+use std::process::Command; // Run programs
+
+#[test]
+fn multi_file_graph_reports_node_names_of_files() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempfile::tempdir()?;
+    let chr1 = dir.path().join("chr1.gfa");
+    let chr2 = dir.path().join("chr2.gfa");
+    std::fs::write(
+        &chr1,
+        "S\ta\tACGT\nS\tb\tAC\nL\ta\t+\tb\t+\t0M\nJ\ta\t+\tb\t+\t*\nP\tx#1#chr1\ta+,b+\t*\nP\ty#1#chr1\ta+\t*\n",
+    )?;
+    std::fs::write(
+        &chr2,
+        "S\tc\tACG\nS\td\tA\nC\tc\t+\td\t+\t1\t1M\nP\tx#1#chr2\tc+,d+\t*\nP\ty#1#chr2\tc+\t*\n",
+    )?;
+    let graph = format!("{},{}", chr1.display(), chr2.display());
+    let mut cmd = Command::cargo_bin("panacus")?;
+    cmd.arg("coverage").arg(&graph);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("\na\t4\t2\t2\t2\n"))
+        .stdout(predicate::str::contains("\nd\t1\t1\t1\t1\n"))
+        .stdout(predicate::str::contains("1:").not());
+
+    // segment ids that occur in several files keep the index of their file
+    let graph = format!("{},{}", chr1.display(), chr1.display());
+    let mut cmd = Command::cargo_bin("panacus")?;
+    cmd.arg("coverage").arg(&graph);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("\n1:a\t4\t"))
+        .stdout(predicate::str::contains("\n2:a\t4\t"));
+    Ok(())
+}
@@ -33,3 +33,61 @@ fn growth_html_gets_written_successfully() -> Result<(), Box<dyn std::error::Err
         .stdout(predicate::str::contains("panacus\thist\tgrowth"));
     Ok(())
 }
+
+#[test]
+fn growth_long_table_gets_written_successfully() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("panacus")?;
+
+    cmd.arg("growth")
+        .arg("tests/test_files/t_groups.hist.tsv")
+        .arg("--long");
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "panacus\tcount\tcoverage\tquorum\tm\tvalue",
+        ))
+        .stdout(predicate::str::contains("growth\tnode\t1\t0\t1\t"));
+    Ok(())
+}
+
+#[test]
+fn growth_of_hist_rejects_grouping() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("panacus")?;
+
+    cmd.arg("growth")
+        .arg("tests/test_files/t_groups.hist.tsv")
+        .arg("--groupby")
+        .arg("groups.tsv");
+    cmd.assert().failure().stderr(predicate::str::contains(
+        "subset, exclude and groupby can only be used in graph mode",
+    ));
+    Ok(())
+}
+
+#[test]
+fn growth_of_hist_rejects_exclude_reference() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("panacus")?;
+
+    cmd.arg("growth")
+        .arg("tests/test_files/t_groups.hist.tsv")
+        .arg("--exclude-reference")
+        .arg("GRCh38");
+    cmd.assert().failure().stderr(predicate::str::contains(
+        "subset, exclude and groupby can only be used in graph mode",
+    ));
+    Ok(())
+}
+
+#[test]
+fn growth_of_hist_rejects_groupby_regex() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("panacus")?;
+
+    cmd.arg("growth")
+        .arg("tests/test_files/t_groups.hist.tsv")
+        .arg("--groupby-regex")
+        .arg("^(?P<sample>[^#]+)");
+    cmd.assert().failure().stderr(predicate::str::contains(
+        "subset, exclude and groupby can only be used in graph mode",
+    ));
+    Ok(())
+}
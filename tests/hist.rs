@@ -31,3 +31,216 @@ fn hist_html_gets_written_successfully() -> Result<(), Box<dyn std::error::Error
         .stdout(predicate::str::contains("panacus\thist"));
     Ok(())
 }
+
+#[test]
+fn strand_dedup_places_bp_on_representative() -> Result<(), Box<dyn std::error::Error>> {
+    // node 2 is the reverse complement of node 1, such that the first 3 bp of path b cover the
+    // last 3 bp of node 1 on its reverse strand
+    let dir = tempfile::tempdir()?;
+    let gfa = dir.path().join("graph.gfa");
+    std::fs::write(
+        &gfa,
+        "S\t1\tAAAAACCCCC\nS\t2\tGGGGGTTTTT\nP\ta\t1+\t*\nP\tb\t2+\t*\n",
+    )?;
+    let subset = dir.path().join("subset.txt");
+    std::fs::write(&subset, "a:0-3\nb:0-3\n")?;
+    let hist = |args: &[&str]| -> Result<String, Box<dyn std::error::Error>> {
+        let mut cmd = Command::cargo_bin("panacus")?;
+        cmd.arg("hist").arg("-c").arg("bp").args(args).arg(&gfa);
+        let output = cmd.assert().success().get_output().stdout.clone();
+        Ok(String::from_utf8(output)?)
+    };
+
+    let subset = subset.to_str().unwrap();
+    // without strand awareness, the covered bp of b are placed on the first bp of node 1
+    let dedup = hist(&["-s", subset, "--dedup"])?;
+    assert!(dedup.contains("\n2\t3\n"));
+    let strand_dedup = hist(&["-s", subset, "--strand-dedup"])?;
+    assert!(strand_dedup.contains("# dedup: strand-aware"));
+    assert!(strand_dedup.contains("\n2\t6\n"));
+
+    // nodes are covered as a whole without subset coordinates
+    let table = |s: String| {
+        s.lines()
+            .filter(|l| !l.starts_with('#'))
+            .collect::<Vec<_>>()
+            .join("\n")
+    };
+    assert_eq!(
+        table(hist(&["--dedup"])?),
+        table(hist(&["--strand-dedup"])?)
+    );
+    Ok(())
+}
+
+#[test]
+fn uncovered_nodes_are_left_out_of_coverage_zero() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempfile::tempdir()?;
+    let exclude = dir.path().join("exclude.txt");
+    std::fs::write(&exclude, "y#4\n")?;
+    let list = dir.path().join("uncovered.tsv");
+    let mut cmd = Command::cargo_bin("panacus")?;
+    cmd.arg("hist")
+        .arg("--uncovered")
+        .arg("report")
+        .arg("--list-uncovered")
+        .arg(&list)
+        .arg("-e")
+        .arg(&exclude)
+        .arg("tests/test_files/t_groups.gfa");
+    // nodes 2, 4, 7, 10, and 13 are not traversed by any path, while node 14 of y#4 is excluded
+    // and thus stays with coverage 0
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "# not covered by any path (left out of coverage 0): 5 node",
+        ))
+        .stdout(predicate::str::contains("\n0\t1\n"));
+    assert_eq!(
+        std::fs::read_to_string(&list)?,
+        "name\tlength\n2\t1\n4\t1\n7\t1\n10\t1\n13\t1\n"
+    );
+    Ok(())
+}
+
+#[test]
+fn failed_run_is_reported_before_run_summary() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempfile::tempdir()?;
+    let summary = dir.path().join("summary.json");
+    let mut cmd = Command::cargo_bin("panacus")?;
+    cmd.arg("hist")
+        .arg(dir.path().join("missing.gfa"))
+        .arg("--run-summary")
+        .arg(&summary);
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("missing.gfa"));
+    let content = std::fs::read_to_string(&summary)?;
+    assert!(content.contains("\"status\": \"failure\""));
+
+    // the summary cannot be written, but the error of the run is the one reported
+    let mut cmd = Command::cargo_bin("panacus")?;
+    cmd.arg("hist")
+        .arg(dir.path().join("missing.gfa"))
+        .arg("--run-summary")
+        .arg(dir.path().join("missing").join("summary.json"));
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("Error: unable to open graph file"));
+    Ok(())
+}
+
+#[cfg(unix)]
+#[test]
+fn converted_graph_is_converted_once() -> Result<(), Box<dyn std::error::Error>> {
+    use std::os::unix::fs::PermissionsExt;
+
+    // fake odgi that logs its calls and writes the GFA stored in the .og file
+    let dir = tempfile::tempdir()?;
+    let calls = dir.path().join("calls.txt");
+    let odgi = dir.path().join("odgi");
+    std::fs::write(
+        &odgi,
+        format!(
+            "#!/bin/sh\necho \"$@\" >> {}\ncat \"$4\"\n",
+            calls.display()
+        ),
+    )?;
+    std::fs::set_permissions(&odgi, std::fs::Permissions::from_mode(0o755))?;
+    let graph = dir.path().join("graph.og");
+    std::fs::copy("tests/test_files/t_groups.gfa", &graph)?;
+
+    let mut cmd = Command::cargo_bin("panacus")?;
+    cmd.env("PANACUS_ODGI", &odgi)
+        .arg("hist")
+        .arg("-c")
+        .arg("bp")
+        .arg(&graph);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("panacus\thist"));
+    assert_eq!(std::fs::read_to_string(&calls)?.lines().count(), 1);
+    Ok(())
+}
+
+#[test]
+fn dry_run_writes_estimates_to_output() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempfile::tempdir()?;
+    let config = dir.path().join("config.yaml");
+    std::fs::write(
+        &config,
+        "- graph: tests/test_files/t_groups.gfa\n  analyses:\n    - !Hist\n      count_type: Node\n",
+    )?;
+    let mut cmd = Command::cargo_bin("panacus")?;
+    cmd.arg("report")
+        .arg("--dry-run")
+        .arg("--compress")
+        .arg("gzip")
+        .arg(&config);
+    let output = cmd.assert().success().get_output().stdout.clone();
+    // the estimates are compressed like all other output, following the debug output of the tasks
+    let start = output
+        .windows(2)
+        .position(|w| w == [0x1f, 0x8b])
+        .expect("output contains gzip stream");
+    let mut table = String::new();
+    std::io::Read::read_to_string(
+        &mut flate2::read::MultiGzDecoder::new(&output[start..]),
+        &mut table,
+    )?;
+    assert!(table.starts_with("task\testimated memory (MB)\testimated runtime (s)\n"));
+    assert!(table.contains("load graph tests/test_files/t_groups.gfa\t"));
+    Ok(())
+}
+
+#[test]
+fn report_writes_output_files_of_analyses() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempfile::tempdir()?;
+    let config = dir.path().join("config.yaml");
+    let table = dir.path().join("hist.tsv");
+    let sections = dir.path().join("hist.json");
+    std::fs::write(
+        &config,
+        format!(
+            "- graph: tests/test_files/t_groups.gfa\n  analyses:\n    - !Hist\n      count_type: Node\n      output: {}\n    - !Hist\n      count_type: Bp\n      output: {}\n",
+            table.display(),
+            sections.display()
+        ),
+    )?;
+    let mut cmd = Command::cargo_bin("panacus")?;
+    cmd.arg("report").arg(&config);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("<html"));
+    // the table of the analysis, or its report sections for .json files
+    let table = std::fs::read_to_string(&table)?;
+    assert!(table.contains("\npanacus\thist"));
+    assert!(table.contains("\n0\t"));
+    let sections: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(&sections)?)?;
+    let sections = sections.as_array().expect("report sections are a list");
+    assert!(sections
+        .iter()
+        .all(|s| s["analysis"] == "Coverage Histogram"));
+    assert!(sections.iter().any(|s| s["countable"] == "bp"));
+    Ok(())
+}
+
+#[test]
+fn run_summary_lists_files_of_graph_state() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempfile::tempdir()?;
+    let subset = dir.path().join("subset.txt");
+    std::fs::write(&subset, "x\ny#1\n")?;
+    let summary = dir.path().join("summary.json");
+    let mut cmd = Command::cargo_bin("panacus")?;
+    cmd.arg("hist")
+        .arg("tests/test_files/t_groups.gfa")
+        .arg("--subset")
+        .arg(&subset)
+        .arg("--run-summary")
+        .arg(&summary);
+    cmd.assert().success();
+    let summary = std::fs::read_to_string(&summary)?;
+    assert!(summary.contains("\"file\": \"tests/test_files/t_groups.gfa\""));
+    assert!(summary.contains(&format!("\"file\": \"{}\"", subset.display())));
+    Ok(())
+}
@@ -30,3 +30,20 @@ fn histgrowth_html_gets_written_successfully() -> Result<(), Box<dyn std::error:
     ));
     Ok(())
 }
+
+#[test]
+fn histgrowth_reports_hist_and_growth_from_one_pass() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("panacus")?;
+
+    cmd.arg("histgrowth")
+        .arg("tests/test_files/t_groups.gfa")
+        .arg("--hist");
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "panacus\thist\tgrowth\ncount\tnode\tnode\ncoverage\t\t1\nquorum\t\t0\n0\t5\tNaN\n1\t0\t3\n2\t10\t6\n",
+        ))
+        .stdout(predicate::str::contains("\n6\t0\t10\n"))
+        .stderr(predicate::str::contains("parse graph took").count(1));
+    Ok(())
+}
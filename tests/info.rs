@@ -48,3 +48,39 @@ fn info_table_groups_get_written() -> Result<(), Box<dyn std::error::Error>> {
         .stdout(predicate::str::contains("group\ty\tnode\t10"));
     Ok(())
 }
+
+#[ignore]
+#[test]
+fn info_table_groups_walks_by_sample() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("panacus")?;
+
+    cmd.arg("info").arg("tests/test_files/t_walks.gfa");
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("group\ts1\tpath\t2"))
+        .stdout(predicate::str::contains("group\ts1\tbp\t16"))
+        .stdout(predicate::str::contains("group\ts1\texclusive bp\t1"))
+        .stdout(predicate::str::contains("group\ts2\texclusive node\t1"));
+    Ok(())
+}
+
+#[test]
+fn info_writes_node_ids() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempfile::tempdir()?;
+    let graph = dir.path().join("graph.gfa");
+    let node_ids = dir.path().join("node_ids.tsv");
+    std::fs::write(
+        &graph,
+        "S\tsegB\tACGT\nS\tsegA\tAC\nL\tsegB\t+\tsegA\t+\t0M\nP\tx\tsegB+,segA+\t*\n",
+    )?;
+    let mut cmd = Command::cargo_bin("panacus")?;
+
+    cmd.arg("info").arg(&graph).arg("--node-ids").arg(&node_ids);
+    cmd.assert().success();
+    // node IDs are assigned in order of the segments in the graph file
+    assert_eq!(
+        std::fs::read_to_string(&node_ids)?,
+        "id\tname\tlength\n1\tsegB\t4\n2\tsegA\t2\n"
+    );
+    Ok(())
+}
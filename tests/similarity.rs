@@ -0,0 +1,26 @@
+use assert_cmd::prelude::*; // Add methods on commands
+use predicates::prelude::*; // Used for writing assertions
+use std::process::Command; // Run programs
+
+#[test]
+fn similarity_of_edges_differs_from_nodes() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempfile::tempdir()?;
+    let graph = dir.path().join("graph.gfa");
+    // both paths visit nodes 1 and 3, but only b goes straight from 1 to 3
+    std::fs::write(
+        &graph,
+        "S\t1\tA\nS\t2\tC\nS\t3\tG\nL\t1\t+\t2\t+\t0M\nL\t2\t+\t3\t+\t0M\nL\t1\t+\t3\t+\t0M\nP\ta\t1+,2+,3+\t*\nP\tb\t1+,3+\t*\n",
+    )?;
+    let mut cmd = Command::cargo_bin("panacus")?;
+    cmd.arg("similarity").arg(&graph);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("\na\t1\t0.6666667\n"));
+
+    let mut cmd = Command::cargo_bin("panacus")?;
+    cmd.arg("similarity").arg(&graph).arg("-c").arg("edge");
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("\na\t1\t0\nb\t0\t1\n"));
+    Ok(())
+}